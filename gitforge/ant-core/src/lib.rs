@@ -1,6 +1,6 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, MutexGuard};
 use thiserror::Error;
 use tokio::sync::broadcast;
 
@@ -19,9 +19,60 @@ pub struct VersionedSystemEvent {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum SystemEvent {
-    GoalCreated { goal_id: String, task: String },
-    GoalCancelled { goal_id: String },
-    GoalStatusChanged { goal_id: String, status: GoalStatus },
+    GoalCreated {
+        goal_id: String,
+        task: String,
+    },
+    GoalCancelled {
+        goal_id: String,
+    },
+    GoalStatusChanged {
+        goal_id: String,
+        status: GoalStatus,
+    },
+    SettingChanged {
+        key: String,
+        value: serde_json::Value,
+    },
+    PrStateChanged {
+        pr_id: i64,
+        state: String,
+    },
+    MergeConflicted {
+        branch: String,
+        paths: Vec<String>,
+    },
+    /// A protected-branch rule was bypassed with an explicit force flag.
+    ProtectionOverridden {
+        branch: String,
+        /// `commit`, `push`, `merge`, or `delete`.
+        action: String,
+        rule: String,
+        reason: Option<String>,
+    },
+    /// A line an agent or executor logged for a goal; `id` orders a goal's
+    /// lines and resumes a read of the stored log.
+    GoalLogAppended {
+        goal_id: String,
+        id: i64,
+        line: String,
+    },
+    /// Work in progress in two checkouts changes the same files, so
+    /// whichever branch merges second will likely conflict.
+    PotentialConflict {
+        worktrees: Vec<String>,
+        branches: Vec<String>,
+        paths: Vec<String>,
+    },
+    /// A rebase replayed commit `current` of `total`; `rebased` is `None`
+    /// when the change was already upstream.
+    RebaseProgress {
+        branch: Option<String>,
+        commit: String,
+        rebased: Option<String>,
+        current: usize,
+        total: usize,
+    },
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
@@ -65,7 +116,7 @@ impl AntEngine {
         let goal_id = goal_id.into();
         let task = task.into();
 
-        let mut goals = self.goals.lock().expect("goals lock poisoned");
+        let mut goals = self.goals();
         if goals.contains_key(&goal_id) {
             return Err(AntError::GoalAlreadyExists(goal_id));
         }
@@ -85,12 +136,21 @@ impl AntEngine {
         Ok(())
     }
 
+    /// Every update is a single map operation, so the goals are whole
+    /// even if a holder panicked; recover them instead of panicking too.
+    fn goals(&self) -> MutexGuard<'_, HashMap<String, GoalStatus>> {
+        self.goals.lock().unwrap_or_else(|poisoned| {
+            self.goals.clear_poison();
+            poisoned.into_inner()
+        })
+    }
+
     pub fn subscribe_events(&self) -> broadcast::Receiver<VersionedSystemEvent> {
         self.bus.subscribe()
     }
 
     pub fn get_goal_status(&self, goal_id: &str) -> Result<GoalStatus, AntError> {
-        let goals = self.goals.lock().expect("goals lock poisoned");
+        let goals = self.goals();
         goals
             .get(goal_id)
             .cloned()
@@ -98,7 +158,7 @@ impl AntEngine {
     }
 
     pub fn cancel_goal(&self, goal_id: &str) -> Result<(), AntError> {
-        let mut goals = self.goals.lock().expect("goals lock poisoned");
+        let mut goals = self.goals();
         let status = goals
             .get_mut(goal_id)
             .ok_or_else(|| AntError::GoalNotFound(goal_id.to_string()))?;
@@ -117,6 +177,109 @@ impl AntEngine {
         Ok(())
     }
 
+    /// Moves a goal to `status`, e.g. when the agent finishes or gives up.
+    pub fn update_goal_status(&self, goal_id: &str, status: GoalStatus) -> Result<(), AntError> {
+        let mut goals = self.goals();
+        let current = goals
+            .get_mut(goal_id)
+            .ok_or_else(|| AntError::GoalNotFound(goal_id.to_string()))?;
+        if *current == status {
+            return Ok(());
+        }
+
+        *current = status.clone();
+        drop(goals);
+
+        self.emit(SystemEvent::GoalStatusChanged {
+            goal_id: goal_id.to_string(),
+            status,
+        });
+
+        Ok(())
+    }
+
+    pub fn pr_state_changed(&self, pr_id: i64, state: impl Into<String>) {
+        self.emit(SystemEvent::PrStateChanged {
+            pr_id,
+            state: state.into(),
+        });
+    }
+
+    /// Announces that a merge into `branch` stopped with conflicts in `paths`.
+    pub fn merge_conflicted(&self, branch: impl Into<String>, paths: Vec<String>) {
+        self.emit(SystemEvent::MergeConflicted {
+            branch: branch.into(),
+            paths,
+        });
+    }
+
+    /// Announces that `action` went ahead on `branch` despite `rule`.
+    pub fn protection_overridden(
+        &self,
+        branch: impl Into<String>,
+        action: impl Into<String>,
+        rule: impl Into<String>,
+        reason: Option<String>,
+    ) {
+        self.emit(SystemEvent::ProtectionOverridden {
+            branch: branch.into(),
+            action: action.into(),
+            rule: rule.into(),
+            reason,
+        });
+    }
+
+    /// Streams a stored goal log line to live consoles.
+    pub fn goal_log_appended(&self, goal_id: impl Into<String>, id: i64, line: impl Into<String>) {
+        self.emit(SystemEvent::GoalLogAppended {
+            goal_id: goal_id.into(),
+            id,
+            line: line.into(),
+        });
+    }
+
+    /// Announces that the checkouts `worktrees`, on `branches`, both
+    /// change `paths`.
+    pub fn potential_conflict(
+        &self,
+        worktrees: Vec<String>,
+        branches: Vec<String>,
+        paths: Vec<String>,
+    ) {
+        self.emit(SystemEvent::PotentialConflict {
+            worktrees,
+            branches,
+            paths,
+        });
+    }
+
+    /// Reports a rebase step, for live progress in the UI and agents.
+    pub fn rebase_progress(
+        &self,
+        branch: Option<String>,
+        commit: impl Into<String>,
+        rebased: Option<String>,
+        current: usize,
+        total: usize,
+    ) {
+        self.emit(SystemEvent::RebaseProgress {
+            branch,
+            commit: commit.into(),
+            rebased,
+            current,
+            total,
+        });
+    }
+
+    /// Announces a settings write so subscribers (UI, agent) can react
+    /// without polling the store.
+    pub fn setting_changed(&self, key: impl Into<String>, value: serde_json::Value) {
+        self.emit(SystemEvent::SettingChanged {
+            key: key.into(),
+            value,
+        });
+    }
+
     fn emit(&self, event: SystemEvent) {
         let _ = self.bus.send(VersionedSystemEvent {
             schema_version: SYSTEM_EVENT_SCHEMA_VERSION,
@@ -171,4 +334,63 @@ mod tests {
         let event = rx.try_recv().expect("event received");
         assert_eq!(event.schema_version, SYSTEM_EVENT_SCHEMA_VERSION);
     }
+
+    #[test]
+    fn a_panic_holding_the_goals_lock_does_not_brick_the_engine() {
+        let engine = AntEngine::new();
+        engine.create_goal("G-9", "Survive").expect("goal created");
+        let goals = Arc::clone(&engine.goals);
+        let panicked = std::thread::spawn(move || {
+            let _held = goals.lock().expect("lock");
+            panic!("agent task failed");
+        })
+        .join();
+        assert!(panicked.is_err());
+
+        let status = engine.get_goal_status("G-9").expect("status after a panic");
+        assert_eq!(status, GoalStatus::Pending);
+        engine.cancel_goal("G-9").expect("goal cancelled");
+    }
+
+    #[test]
+    fn update_goal_status_broadcasts_only_changes() {
+        let engine = AntEngine::new();
+        engine.create_goal("G-4", "Ship it").expect("goal created");
+        let mut rx = engine.subscribe_events();
+
+        engine
+            .update_goal_status("G-4", GoalStatus::Completed)
+            .expect("status updated");
+        engine
+            .update_goal_status("G-4", GoalStatus::Completed)
+            .expect("same status");
+
+        let event = rx.try_recv().expect("event received");
+        assert!(matches!(
+            event.event,
+            SystemEvent::GoalStatusChanged {
+                status: GoalStatus::Completed,
+                ..
+            }
+        ));
+        assert!(rx.try_recv().is_err());
+        assert!(engine
+            .update_goal_status("missing", GoalStatus::Failed)
+            .is_err());
+    }
+
+    #[test]
+    fn setting_changed_is_broadcast() {
+        let engine = AntEngine::new();
+        let mut rx = engine.subscribe_events();
+
+        engine.setting_changed("ui.theme", serde_json::json!("dark"));
+
+        let event = rx.try_recv().expect("event received");
+        let json = serde_json::to_value(&event.event).expect("serialize");
+        assert_eq!(
+            json,
+            serde_json::json!({"type": "setting_changed", "key": "ui.theme", "value": "dark"})
+        );
+    }
 }
@@ -0,0 +1,253 @@
+//! Hot paths the UI and agents hit on every refresh, measured on a
+//! synthetic repository. Run with `cargo bench --features bench`;
+//! `GITFORGE_BENCH_FILES` (default 5000) and `GITFORGE_BENCH_COMMITS`
+//! (default 200) scale it.
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use gitforge::content;
+use gitforge::graph;
+use gitforge::mcp::server::{GitForgeMcp, McpRequest};
+use gitforge::revcache::RevCache;
+use gitforge::status::{StatusCache, Untracked};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// Modified in the working tree, and large enough for real hunks.
+const BIG_FILE: &str = "src/big.rs";
+
+struct Fixture {
+    path: String,
+    files: usize,
+}
+
+fn env_count(name: &str, default: usize) -> usize {
+    std::env::var(name)
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(default)
+}
+
+fn file_name(i: usize) -> String {
+    format!("src/mod{:03}/file{i:05}.rs", i / 100)
+}
+
+/// Built once per process and reused by every group: `files` tracked files
+/// in nested directories, `commits` commits each touching a few of them,
+/// then 1% modified and 1% untracked on top.
+fn fixture() -> &'static Fixture {
+    static FIXTURE: OnceLock<Fixture> = OnceLock::new();
+    FIXTURE.get_or_init(|| {
+        let files = env_count("GITFORGE_BENCH_FILES", 5000);
+        let commits = env_count("GITFORGE_BENCH_COMMITS", 200);
+        let dir: PathBuf = std::env::temp_dir().join(format!(
+            "gitforge-bench-{files}-{commits}-{}",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        let repo = git2::Repository::init(&dir).expect("init bench repo");
+        let sig = git2::Signature::now("Bench", "bench@gitforge.dev").expect("signature");
+
+        let write = |name: &str, contents: String| {
+            let full = dir.join(name);
+            std::fs::create_dir_all(full.parent().expect("parent")).expect("mkdir");
+            std::fs::write(full, contents).expect("write file");
+        };
+        let body = |i: usize, rev: usize| {
+            (0..40)
+                .map(|line| format!("pub fn f{i}_{line}() -> usize {{ {rev} + {line} }}\n"))
+                .collect::<String>()
+        };
+
+        let mut index = repo.index().expect("index");
+        for i in 0..files {
+            write(&file_name(i), body(i, 0));
+            index.add_path(Path::new(&file_name(i))).expect("stage");
+        }
+        write(BIG_FILE, body(0, 0).repeat(200));
+        index.add_path(Path::new(BIG_FILE)).expect("stage");
+
+        let mut parent: Option<git2::Oid> = None;
+        for rev in 0..commits.max(1) {
+            if rev > 0 {
+                for i in (0..5).map(|k| (rev * 7 + k * 131) % files.max(1)) {
+                    write(&file_name(i), body(i, rev));
+                    index.add_path(Path::new(&file_name(i))).expect("stage");
+                }
+            }
+            index.write().expect("write index");
+            let tree = repo
+                .find_tree(index.write_tree().expect("write tree"))
+                .expect("tree");
+            let parents: Vec<git2::Commit> = parent
+                .map(|oid| repo.find_commit(oid).expect("parent"))
+                .into_iter()
+                .collect();
+            let parents: Vec<&git2::Commit> = parents.iter().collect();
+            let message = format!("commit {rev}");
+            parent = Some(
+                repo.commit(Some("HEAD"), &sig, &sig, &message, &tree, &parents)
+                    .expect("commit"),
+            );
+        }
+
+        for i in (0..files).step_by(100) {
+            write(&file_name(i), body(i, usize::MAX));
+            write(&format!("scratch/new{i:05}.txt"), "untracked\n".to_string());
+        }
+        write(BIG_FILE, body(1, 1).repeat(200));
+
+        Fixture {
+            path: dir.to_string_lossy().to_string(),
+            files,
+        }
+    })
+}
+
+fn open() -> git2::Repository {
+    git2::Repository::open(&fixture().path).expect("open bench repo")
+}
+
+fn status(c: &mut Criterion) {
+    let fixture = fixture();
+    let repo = open();
+    let mut group = c.benchmark_group(format!("status/{}_files", fixture.files));
+    group.sample_size(20);
+
+    for (name, untracked) in [
+        ("full_all", Untracked::All),
+        ("full_normal", Untracked::Normal),
+        ("full_no", Untracked::No),
+    ] {
+        let cache = StatusCache::new(&fixture.path);
+        group.bench_function(name, |b| {
+            b.iter(|| cache.status(&repo, untracked, true).expect("status"))
+        });
+    }
+
+    let cache = StatusCache::new(&fixture.path);
+    cache
+        .status(&repo, Untracked::All, false)
+        .expect("warm status");
+    group.bench_function("cached", |b| {
+        b.iter(|| cache.status(&repo, Untracked::All, false).expect("status"))
+    });
+    group.finish();
+}
+
+fn diff(c: &mut Criterion) {
+    let fixture = fixture();
+    let repo = open();
+    let mut group = c.benchmark_group("diff");
+    group.sample_size(20);
+
+    group.bench_function("index_to_workdir_patch", |b| {
+        b.iter(|| {
+            let diff = repo
+                .diff_index_to_workdir(None, None)
+                .expect("workdir diff");
+            let mut bytes = 0;
+            diff.print(git2::DiffFormat::Patch, |_, _, line| {
+                bytes += line.content().len();
+                true
+            })
+            .expect("print patch");
+            bytes
+        })
+    });
+
+    let head = repo.head().expect("head").peel_to_commit().expect("commit");
+    let parent = head.parent(0).ok();
+    group.bench_function("head_vs_parent_stats", |b| {
+        b.iter(|| {
+            let old = parent.as_ref().map(|p| p.tree().expect("parent tree"));
+            let diff = repo
+                .diff_tree_to_tree(old.as_ref(), Some(&head.tree().expect("tree")), None)
+                .expect("tree diff");
+            diff.stats().expect("stats").insertions()
+        })
+    });
+
+    group.bench_function("file_hunks", |b| {
+        b.iter(|| content::diff_file(&fixture.path, BIG_FILE, None, None).expect("diff file"))
+    });
+    group.finish();
+}
+
+fn log(c: &mut Criterion) {
+    let repo = open();
+    let head = repo.head().expect("head").target().expect("head oid");
+    let mut group = c.benchmark_group("log");
+    group.sample_size(20);
+
+    group.bench_function("history_uncached", |b| {
+        b.iter(|| {
+            RevCache::default()
+                .history(&repo, head, usize::MAX)
+                .expect("history")
+        })
+    });
+
+    let revs = RevCache::default();
+    revs.history(&repo, head, 100).expect("warm history");
+    group.bench_function("history_cached_page", |b| {
+        b.iter(|| revs.history(&repo, head, 100).expect("history"))
+    });
+
+    let root = RevCache::default()
+        .history(&repo, head, usize::MAX)
+        .expect("history")
+        .last()
+        .copied()
+        .expect("root commit");
+    group.bench_function("ahead_behind_uncached", |b| {
+        b.iter(|| {
+            RevCache::default()
+                .ahead_behind(&repo, head, root)
+                .expect("ahead/behind")
+        })
+    });
+
+    group.bench_function("commit_graph_lanes", |b| {
+        b.iter(|| graph::commit_graph(&repo, graph::DEFAULT_LIMIT).expect("graph"))
+    });
+    group.finish();
+}
+
+fn mcp_dispatch(c: &mut Criterion) {
+    let fixture = fixture();
+    let runtime = tokio::runtime::Runtime::new().expect("runtime");
+    let server = GitForgeMcp::new(fixture.path.clone()).expect("mcp server");
+    let request = |method: &str, params: serde_json::Value| McpRequest {
+        jsonrpc: "2.0".into(),
+        id: serde_json::json!(1),
+        method: method.into(),
+        params,
+    };
+    let tools = request("tools/list", serde_json::json!({}));
+    let status = request("git_status", serde_json::json!({ "summary": true }));
+    let prs = request("prs_list", serde_json::json!({ "limit": 50 }));
+
+    let mut group = c.benchmark_group("mcp");
+    group.sample_size(30);
+    group.bench_function("tools_list", |b| {
+        b.iter(|| runtime.block_on(server.execute_mcp_for_tauri(&tools)))
+    });
+    runtime.block_on(server.execute_mcp_for_tauri(&status));
+    group.bench_function("git_status_summary_cached", |b| {
+        b.iter(|| runtime.block_on(server.execute_mcp_for_tauri(&status)))
+    });
+    group.bench_function("prs_list", |b| {
+        b.iter(|| runtime.block_on(server.execute_mcp_for_tauri(&prs)))
+    });
+    group.bench_function("git_status_8_concurrent", |b| {
+        b.iter(|| {
+            runtime.block_on(futures_util::future::join_all(
+                (0..8).map(|_| server.execute_mcp_for_tauri(&status)),
+            ))
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, status, diff, log, mcp_dispatch);
+criterion_main!(benches);
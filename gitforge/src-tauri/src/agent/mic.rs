@@ -0,0 +1,108 @@
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use std::sync::mpsc;
+use std::time::Duration;
+
+use super::voice::AudioClip;
+
+/// Default input device capture, downmixed to mono `f32` samples.
+///
+/// `cpal::Stream` is not `Send` on every platform, so a `Microphone` must
+/// stay on the thread that opened it.
+pub struct Microphone {
+    stream: cpal::Stream,
+    rx: mpsc::Receiver<Vec<f32>>,
+    sample_rate: u32,
+}
+
+impl Microphone {
+    pub fn open_default() -> Result<Self, String> {
+        let device = cpal::default_host()
+            .default_input_device()
+            .ok_or_else(|| "no input device available".to_string())?;
+        let supported = device
+            .default_input_config()
+            .map_err(|e| format!("failed to query input config: {e}"))?;
+
+        let sample_rate = supported.sample_rate().0;
+        let format = supported.sample_format();
+        let config: cpal::StreamConfig = supported.into();
+        let (tx, rx) = mpsc::channel();
+
+        let stream = match format {
+            cpal::SampleFormat::F32 => build_stream::<f32>(&device, &config, tx),
+            cpal::SampleFormat::I16 => build_stream::<i16>(&device, &config, tx),
+            cpal::SampleFormat::U16 => build_stream::<u16>(&device, &config, tx),
+            other => return Err(format!("unsupported sample format {other:?}")),
+        }?;
+
+        stream
+            .pause()
+            .map_err(|e| format!("failed to pause input stream: {e}"))?;
+
+        Ok(Self {
+            stream,
+            rx,
+            sample_rate,
+        })
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    pub fn start(&self) -> Result<(), String> {
+        self.drain();
+        self.stream
+            .play()
+            .map_err(|e| format!("failed to start recording: {e}"))
+    }
+
+    /// Stops capture and returns everything recorded since `start`.
+    pub fn stop(&self) -> Result<AudioClip, String> {
+        self.stream
+            .pause()
+            .map_err(|e| format!("failed to stop recording: {e}"))?;
+        Ok(AudioClip::new(self.drain(), self.sample_rate))
+    }
+
+    /// Waits up to `timeout` for the next captured chunk.
+    pub fn next_chunk(&self, timeout: Duration) -> Option<Vec<f32>> {
+        self.rx.recv_timeout(timeout).ok()
+    }
+
+    fn drain(&self) -> Vec<f32> {
+        self.rx.try_iter().flatten().collect()
+    }
+}
+
+fn build_stream<T>(
+    device: &cpal::Device,
+    config: &cpal::StreamConfig,
+    tx: mpsc::Sender<Vec<f32>>,
+) -> Result<cpal::Stream, String>
+where
+    T: cpal::SizedSample,
+    f32: cpal::FromSample<T>,
+{
+    let channels = usize::from(config.channels.max(1));
+    device
+        .build_input_stream(
+            config,
+            move |data: &[T], _: &cpal::InputCallbackInfo| {
+                let mono = data
+                    .chunks(channels)
+                    .map(|frame| {
+                        frame
+                            .iter()
+                            .map(|s| <f32 as cpal::FromSample<T>>::from_sample_(*s))
+                            .sum::<f32>()
+                            / frame.len() as f32
+                    })
+                    .collect();
+                let _ = tx.send(mono);
+            },
+            |err| eprintln!("microphone stream error: {err}"),
+            None,
+        )
+        .map_err(|e| format!("failed to open input stream: {e}"))
+}
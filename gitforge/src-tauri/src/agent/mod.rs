@@ -1,3 +1,9 @@
+#[cfg(feature = "voice")]
+pub mod mic;
+#[cfg(feature = "voice")]
+pub mod recorder;
+pub mod voice;
+
 #[derive(Default)]
 pub struct BpgtAgent {
     db_path: String,
@@ -0,0 +1,55 @@
+use std::sync::mpsc;
+use std::thread::JoinHandle;
+
+use super::mic::Microphone;
+use super::voice::AudioClip;
+
+/// A push-to-talk recording that can be held across async calls.
+///
+/// [`Microphone`] must stay on the thread that opened it, so the capture
+/// runs on its own thread and `Recorder` only holds channels to it.
+pub struct Recorder {
+    stop: mpsc::Sender<()>,
+    clip: mpsc::Receiver<Result<AudioClip, String>>,
+    thread: JoinHandle<()>,
+}
+
+impl Recorder {
+    /// Opens the default input device and starts recording; returns once
+    /// capture is running, or with the error that kept it from starting.
+    pub fn start() -> Result<Self, String> {
+        let (stop, stop_rx) = mpsc::channel();
+        let (ready_tx, ready) = mpsc::channel();
+        let (clip_tx, clip) = mpsc::channel();
+
+        let thread = std::thread::spawn(move || {
+            let mic = match Microphone::open_default().and_then(|mic| mic.start().map(|_| mic)) {
+                Ok(mic) => mic,
+                Err(e) => {
+                    let _ = ready_tx.send(Err(e));
+                    return;
+                }
+            };
+            let _ = ready_tx.send(Ok(()));
+            // A dropped `Recorder` also ends the recording.
+            let _ = stop_rx.recv();
+            let _ = clip_tx.send(mic.stop());
+        });
+
+        ready
+            .recv()
+            .map_err(|_| "recording thread exited before starting".to_string())??;
+        Ok(Self { stop, clip, thread })
+    }
+
+    /// Stops capture and returns everything recorded since [`Recorder::start`].
+    pub fn stop(self) -> Result<AudioClip, String> {
+        let _ = self.stop.send(());
+        let clip = self
+            .clip
+            .recv()
+            .map_err(|_| "recording thread exited without a clip".to_string())?;
+        let _ = self.thread.join();
+        clip
+    }
+}
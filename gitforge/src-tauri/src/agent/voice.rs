@@ -0,0 +1,312 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Mono PCM audio with samples in `[-1.0, 1.0]`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AudioClip {
+    pub samples: Vec<f32>,
+    pub sample_rate: u32,
+}
+
+impl AudioClip {
+    pub fn new(samples: Vec<f32>, sample_rate: u32) -> Self {
+        Self {
+            samples,
+            sample_rate,
+        }
+    }
+
+    pub fn duration_ms(&self) -> u64 {
+        if self.sample_rate == 0 {
+            return 0;
+        }
+        self.samples.len() as u64 * 1000 / u64::from(self.sample_rate)
+    }
+
+    /// Linear resampling; good enough for speech models that expect 16 kHz.
+    pub fn resample(&self, rate: u32) -> AudioClip {
+        if rate == self.sample_rate || self.samples.is_empty() || self.sample_rate == 0 {
+            return AudioClip::new(self.samples.clone(), rate);
+        }
+
+        let ratio = f64::from(self.sample_rate) / f64::from(rate);
+        let len = (self.samples.len() as f64 / ratio).floor() as usize;
+        let last = self.samples.len() - 1;
+        let samples = (0..len)
+            .map(|i| {
+                let pos = i as f64 * ratio;
+                let idx = pos.floor() as usize;
+                let frac = (pos - idx as f64) as f32;
+                let a = self.samples[idx.min(last)];
+                let b = self.samples[(idx + 1).min(last)];
+                a + (b - a) * frac
+            })
+            .collect();
+
+        AudioClip::new(samples, rate)
+    }
+
+    /// Encodes the clip as a 16-bit PCM mono WAV file.
+    pub fn to_wav_bytes(&self) -> Vec<u8> {
+        let data_len = (self.samples.len() * 2) as u32;
+        let mut out = Vec::with_capacity(44 + data_len as usize);
+
+        out.extend_from_slice(b"RIFF");
+        out.extend_from_slice(&(36 + data_len).to_le_bytes());
+        out.extend_from_slice(b"WAVEfmt ");
+        out.extend_from_slice(&16u32.to_le_bytes());
+        out.extend_from_slice(&1u16.to_le_bytes()); // PCM
+        out.extend_from_slice(&1u16.to_le_bytes()); // mono
+        out.extend_from_slice(&self.sample_rate.to_le_bytes());
+        out.extend_from_slice(&(self.sample_rate * 2).to_le_bytes());
+        out.extend_from_slice(&2u16.to_le_bytes());
+        out.extend_from_slice(&16u16.to_le_bytes());
+        out.extend_from_slice(b"data");
+        out.extend_from_slice(&data_len.to_le_bytes());
+        for sample in &self.samples {
+            let value = (sample.clamp(-1.0, 1.0) * f32::from(i16::MAX)) as i16;
+            out.extend_from_slice(&value.to_le_bytes());
+        }
+
+        out
+    }
+
+    pub fn write_wav(&self, path: &Path) -> Result<(), String> {
+        std::fs::write(path, self.to_wav_bytes())
+            .map_err(|e| format!("failed to write {}: {e}", path.display()))
+    }
+}
+
+/// Converts recorded speech to text for the agent loop.
+pub trait SpeechToText {
+    fn transcribe(&self, clip: &AudioClip) -> Result<String, String>;
+}
+
+/// Runs a local STT program (e.g. whisper.cpp) on a temporary WAV file and
+/// reads the transcript from stdout. `{wav}` in the command line is replaced
+/// with the file path; without it the path is appended.
+#[derive(Debug, Clone)]
+pub struct CommandStt {
+    program: String,
+    args: Vec<String>,
+    sample_rate: u32,
+}
+
+pub const STT_SAMPLE_RATE: u32 = 16_000;
+
+impl CommandStt {
+    pub fn parse(command_line: &str) -> Result<Self, String> {
+        let mut parts = command_line.split_whitespace().map(str::to_string);
+        let program = parts
+            .next()
+            .ok_or_else(|| "empty STT command".to_string())?;
+        let mut args: Vec<String> = parts.collect();
+        if !args.iter().any(|a| a.contains("{wav}")) {
+            args.push("{wav}".to_string());
+        }
+
+        Ok(Self {
+            program,
+            args,
+            sample_rate: STT_SAMPLE_RATE,
+        })
+    }
+
+    fn temp_wav_path() -> PathBuf {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or_default();
+        std::env::temp_dir().join(format!("gitforge-voice-{}-{nanos}.wav", std::process::id()))
+    }
+}
+
+impl SpeechToText for CommandStt {
+    fn transcribe(&self, clip: &AudioClip) -> Result<String, String> {
+        let wav = Self::temp_wav_path();
+        clip.resample(self.sample_rate).write_wav(&wav)?;
+
+        let wav_arg = wav.to_string_lossy();
+        let args: Vec<String> = self
+            .args
+            .iter()
+            .map(|a| a.replace("{wav}", &wav_arg))
+            .collect();
+        let output = Command::new(&self.program).args(&args).output();
+        let _ = std::fs::remove_file(&wav);
+
+        let output = output.map_err(|e| format!("failed to run '{}': {e}", self.program))?;
+        if !output.status.success() {
+            return Err(format!(
+                "'{}' exited with {}: {}",
+                self.program,
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+}
+
+/// Energy-based voice activity detector that splits a continuous stream into
+/// utterances separated by silence.
+pub struct Segmenter {
+    sample_rate: u32,
+    frame_len: usize,
+    threshold: f32,
+    min_speech_frames: usize,
+    trailing_silence_frames: usize,
+    pending: Vec<f32>,
+    current: Vec<f32>,
+    speech_frames: usize,
+    silence_frames: usize,
+}
+
+const FRAME_MS: u32 = 20;
+
+impl Segmenter {
+    pub fn new(sample_rate: u32) -> Self {
+        let frames_for = |ms: u32| (ms / FRAME_MS) as usize;
+        Self {
+            sample_rate,
+            frame_len: (sample_rate * FRAME_MS / 1000).max(1) as usize,
+            threshold: 0.02,
+            min_speech_frames: frames_for(250),
+            trailing_silence_frames: frames_for(800),
+            pending: Vec::new(),
+            current: Vec::new(),
+            speech_frames: 0,
+            silence_frames: 0,
+        }
+    }
+
+    pub fn with_threshold(mut self, threshold: f32) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    /// Feeds captured samples and returns every utterance completed by them.
+    pub fn push(&mut self, samples: &[f32]) -> Vec<AudioClip> {
+        self.pending.extend_from_slice(samples);
+
+        let mut utterances = Vec::new();
+        let mut offset = 0;
+        while self.pending.len() - offset >= self.frame_len {
+            let frame = &self.pending[offset..offset + self.frame_len];
+            offset += self.frame_len;
+
+            let rms = (frame.iter().map(|s| s * s).sum::<f32>() / frame.len() as f32).sqrt();
+            if rms >= self.threshold {
+                self.current.extend_from_slice(frame);
+                self.speech_frames += 1;
+                self.silence_frames = 0;
+            } else if !self.current.is_empty() {
+                self.current.extend_from_slice(frame);
+                self.silence_frames += 1;
+                if self.silence_frames >= self.trailing_silence_frames {
+                    if let Some(clip) = self.take_utterance() {
+                        utterances.push(clip);
+                    }
+                }
+            }
+        }
+        self.pending.drain(..offset);
+
+        utterances
+    }
+
+    /// Returns whatever speech is buffered, e.g. when capture stops.
+    pub fn flush(&mut self) -> Option<AudioClip> {
+        self.pending.clear();
+        self.take_utterance()
+    }
+
+    fn take_utterance(&mut self) -> Option<AudioClip> {
+        let samples = std::mem::take(&mut self.current);
+        let long_enough = self.speech_frames >= self.min_speech_frames;
+        self.speech_frames = 0;
+        self.silence_frames = 0;
+        long_enough.then(|| AudioClip::new(samples, self.sample_rate))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tone(ms: u32, rate: u32) -> Vec<f32> {
+        let len = (rate * ms / 1000) as usize;
+        (0..len)
+            .map(|i| (i as f32 * 440.0 * std::f32::consts::TAU / rate as f32).sin() * 0.5)
+            .collect()
+    }
+
+    fn silence(ms: u32, rate: u32) -> Vec<f32> {
+        vec![0.0; (rate * ms / 1000) as usize]
+    }
+
+    #[test]
+    fn wav_header_describes_mono_pcm16() {
+        let clip = AudioClip::new(vec![0.0, 0.5, -0.5], 16_000);
+        let bytes = clip.to_wav_bytes();
+
+        assert_eq!(&bytes[0..4], b"RIFF");
+        assert_eq!(&bytes[8..12], b"WAVE");
+        assert_eq!(u16::from_le_bytes([bytes[22], bytes[23]]), 1);
+        assert_eq!(
+            u32::from_le_bytes([bytes[24], bytes[25], bytes[26], bytes[27]]),
+            16_000
+        );
+        assert_eq!(bytes.len(), 44 + 6);
+    }
+
+    #[test]
+    fn resample_scales_length() {
+        let clip = AudioClip::new(silence(1000, 48_000), 48_000);
+        let resampled = clip.resample(16_000);
+
+        assert_eq!(resampled.sample_rate, 16_000);
+        assert_eq!(resampled.samples.len(), 16_000);
+    }
+
+    #[test]
+    fn segmenter_splits_utterances_on_silence() {
+        let rate = 16_000;
+        let mut segmenter = Segmenter::new(rate);
+
+        let mut stream = tone(600, rate);
+        stream.extend(silence(1000, rate));
+        stream.extend(tone(400, rate));
+        stream.extend(silence(1000, rate));
+
+        let utterances = segmenter.push(&stream);
+        assert_eq!(utterances.len(), 2);
+        assert!(utterances[0].duration_ms() >= 600);
+        assert!(segmenter.flush().is_none());
+    }
+
+    #[test]
+    fn segmenter_ignores_short_clicks() {
+        let rate = 16_000;
+        let mut segmenter = Segmenter::new(rate);
+
+        let mut stream = tone(40, rate);
+        stream.extend(silence(1000, rate));
+
+        assert!(segmenter.push(&stream).is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn command_stt_reads_transcript_from_stdout() {
+        let stt = CommandStt::parse("echo commit {wav}").expect("parse command");
+        let transcript = stt
+            .transcribe(&AudioClip::new(tone(100, 16_000), 16_000))
+            .expect("transcribe");
+
+        assert!(transcript.starts_with("commit "));
+        assert!(transcript.ends_with(".wav"));
+    }
+}
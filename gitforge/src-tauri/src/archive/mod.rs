@@ -0,0 +1,241 @@
+use serde::Serialize;
+
+const BLOCK: usize = 512;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ArchiveSummary {
+    pub commit: String,
+    pub files: usize,
+    pub bytes: u64,
+}
+
+/// Writes the tree of `revision` as a ustar archive, handing it to `emit`
+/// in pieces of `chunk_size` bytes (the last one shorter) so the whole
+/// archive is never in memory. Entries are placed under `prefix`, e.g.
+/// `project-1.0/`. Submodules are skipped.
+pub fn tar(
+    repo: &git2::Repository,
+    revision: &str,
+    prefix: &str,
+    chunk_size: usize,
+    emit: &mut dyn FnMut(&[u8]) -> Result<(), String>,
+) -> Result<ArchiveSummary, String> {
+    let commit = repo
+        .revparse_single(revision)
+        .and_then(|object| object.peel_to_commit())
+        .map_err(|e| format!("failed to resolve {revision}: {e}"))?;
+    let tree = commit
+        .tree()
+        .map_err(|e| format!("failed to read tree: {e}"))?;
+    let mtime = commit.time().seconds().max(0) as u64;
+
+    let mut blobs = Vec::new();
+    tree.walk(git2::TreeWalkMode::PreOrder, |dir, entry| {
+        if entry.kind() == Some(git2::ObjectType::Blob) {
+            if let Some(name) = entry.name() {
+                blobs.push((format!("{dir}{name}"), entry.id(), entry.filemode()));
+            }
+        }
+        git2::TreeWalkResult::Ok
+    })
+    .map_err(|e| format!("failed to walk tree: {e}"))?;
+
+    let mut out = Chunked {
+        buf: Vec::with_capacity(chunk_size.max(BLOCK) * 2),
+        chunk_size: chunk_size.max(BLOCK),
+        emit,
+        bytes: 0,
+    };
+    for (path, id, filemode) in &blobs {
+        let blob = repo
+            .find_blob(*id)
+            .map_err(|e| format!("failed to read {path}: {e}"))?;
+        let path = format!("{prefix}{path}");
+        let content = blob.content();
+        if *filemode == i32::from(git2::FileMode::Link) {
+            let target = String::from_utf8_lossy(content);
+            out.write(&header(&path, 0, 0o777, mtime, b'2', &target)?)?;
+            continue;
+        }
+        let mode = if *filemode == i32::from(git2::FileMode::BlobExecutable) {
+            0o755
+        } else {
+            0o644
+        };
+        out.write(&header(&path, content.len() as u64, mode, mtime, b'0', "")?)?;
+        out.write(content)?;
+        out.pad()?;
+    }
+    // End of archive: two zero blocks.
+    out.write(&[0; BLOCK * 2])?;
+    out.flush()?;
+
+    Ok(ArchiveSummary {
+        commit: commit.id().to_string(),
+        files: blobs.len(),
+        bytes: out.bytes,
+    })
+}
+
+struct Chunked<'a> {
+    buf: Vec<u8>,
+    chunk_size: usize,
+    emit: &'a mut dyn FnMut(&[u8]) -> Result<(), String>,
+    bytes: u64,
+}
+
+impl Chunked<'_> {
+    fn write(&mut self, data: &[u8]) -> Result<(), String> {
+        self.bytes += data.len() as u64;
+        for piece in data.chunks(self.chunk_size) {
+            self.buf.extend_from_slice(piece);
+            if self.buf.len() >= self.chunk_size {
+                (self.emit)(&self.buf[..self.chunk_size])?;
+                self.buf.drain(..self.chunk_size);
+            }
+        }
+        Ok(())
+    }
+
+    /// Zero-fills up to the next block boundary.
+    fn pad(&mut self) -> Result<(), String> {
+        let partial = (self.bytes % BLOCK as u64) as usize;
+        if partial == 0 {
+            return Ok(());
+        }
+        self.write(&[0; BLOCK][partial..])
+    }
+
+    fn flush(&mut self) -> Result<(), String> {
+        if !self.buf.is_empty() {
+            (self.emit)(&self.buf)?;
+            self.buf.clear();
+        }
+        Ok(())
+    }
+}
+
+fn header(
+    path: &str,
+    size: u64,
+    mode: u32,
+    mtime: u64,
+    kind: u8,
+    link: &str,
+) -> Result<[u8; BLOCK], String> {
+    let (prefix, name) = split_path(path)?;
+    if link.len() > 100 {
+        return Err(format!("symlink target of {path} is too long for tar"));
+    }
+
+    let mut h = [0u8; BLOCK];
+    h[..name.len()].copy_from_slice(name.as_bytes());
+    octal(&mut h[100..108], u64::from(mode), path)?;
+    octal(&mut h[108..116], 0, path)?;
+    octal(&mut h[116..124], 0, path)?;
+    octal(&mut h[124..136], size, path)?;
+    octal(&mut h[136..148], mtime, path)?;
+    h[148..156].fill(b' ');
+    h[156] = kind;
+    h[157..157 + link.len()].copy_from_slice(link.as_bytes());
+    h[257..263].copy_from_slice(b"ustar\0");
+    h[263..265].copy_from_slice(b"00");
+    h[345..345 + prefix.len()].copy_from_slice(prefix.as_bytes());
+
+    let checksum: u64 = h.iter().map(|&b| u64::from(b)).sum();
+    octal(&mut h[148..155], checksum, path)?;
+    Ok(h)
+}
+
+/// Zero-padded octal filling all but the last byte of `field`, which stays NUL.
+fn octal(field: &mut [u8], value: u64, path: &str) -> Result<(), String> {
+    let digits = format!("{value:0width$o}", width = field.len() - 1);
+    if digits.len() >= field.len() {
+        return Err(format!("{path} is too large for tar"));
+    }
+    field[..digits.len()].copy_from_slice(digits.as_bytes());
+    field[digits.len()] = 0;
+    Ok(())
+}
+
+/// ustar keeps names in a 100-byte field plus a 155-byte prefix, split at a `/`.
+fn split_path(path: &str) -> Result<(&str, &str), String> {
+    if path.len() <= 100 {
+        return Ok(("", path));
+    }
+    path.match_indices('/')
+        .map(|(i, _)| (&path[..i], &path[i + 1..]))
+        .find(|(prefix, name)| prefix.len() <= 155 && !name.is_empty() && name.len() <= 100)
+        .ok_or_else(|| format!("{path} is too long for tar"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    fn repo_with_files(files: &[(&str, &str)]) -> (crate::test_support::TempDir, git2::Repository) {
+        let (dir, repo) = crate::test_support::init_repo("archive");
+        {
+            let mut index = repo.index().expect("index");
+            for (file, contents) in files {
+                let full = dir.join(file);
+                std::fs::create_dir_all(full.parent().unwrap()).expect("mkdir");
+                std::fs::write(full, contents).expect("write");
+                index.add_path(Path::new(file)).expect("stage");
+            }
+            let tree = repo
+                .find_tree(index.write_tree().expect("write tree"))
+                .expect("tree");
+            let sig = git2::Signature::now("Test", "test@example.com").expect("signature");
+            repo.commit(Some("HEAD"), &sig, &sig, "init", &tree, &[])
+                .expect("commit");
+        }
+        (dir, repo)
+    }
+
+    #[test]
+    fn tree_is_written_as_ustar_in_chunks() {
+        let long = format!("{}/file.txt", "nested".repeat(20));
+        let (_dir, repo) = repo_with_files(&[("README.md", "hello\n"), (&long, "deep")]);
+
+        let mut chunks: Vec<Vec<u8>> = Vec::new();
+        let summary = tar(&repo, "HEAD", "demo/", BLOCK, &mut |chunk| {
+            chunks.push(chunk.to_vec());
+            Ok(())
+        })
+        .expect("archive");
+        let archive: Vec<u8> = chunks.concat();
+
+        assert_eq!(summary.files, 2);
+        assert_eq!(summary.bytes, archive.len() as u64);
+        assert!(chunks.iter().all(|c| c.len() == BLOCK));
+        assert!(archive[archive.len() - 2 * BLOCK..].iter().all(|&b| b == 0));
+
+        // Entries come in tree order: README.md (header, one data block),
+        // then the long path.
+        let readme = &archive[..BLOCK];
+        assert!(readme.starts_with(b"demo/README.md\0"));
+        assert_eq!(&readme[257..263], b"ustar\0");
+        assert_eq!(&readme[124..136], b"00000000006\0");
+        assert!(archive[BLOCK..].starts_with(b"hello\n\0"));
+        let summed: u64 = readme
+            .iter()
+            .enumerate()
+            .map(|(i, &b)| {
+                if (148..156).contains(&i) {
+                    32
+                } else {
+                    u64::from(b)
+                }
+            })
+            .sum();
+        let recorded = u64::from_str_radix(std::str::from_utf8(&readme[148..154]).unwrap(), 8)
+            .expect("octal checksum");
+        assert_eq!(summed, recorded);
+
+        let nested = &archive[2 * BLOCK..3 * BLOCK];
+        assert!(nested.starts_with(b"file.txt\0"));
+        assert!(String::from_utf8_lossy(&nested[345..500]).starts_with("demo/nested"));
+    }
+}
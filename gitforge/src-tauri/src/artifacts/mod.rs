@@ -0,0 +1,246 @@
+//! Build outputs, test reports and screenshots attached to goals and PRs.
+//! Bytes are kept once per content under `.git/gitforge/artifacts`, named
+//! by their git blob id; the metadata store records what each is attached
+//! to.
+
+use std::path::PathBuf;
+
+use crate::budget::MAX_RESPONSE_BYTES;
+use crate::store::{ArtifactRecord, MetaStore, NewArtifact};
+
+/// Largest artifact, so one still fits in a response once base64-encoded.
+pub const MAX_BYTES: usize = MAX_RESPONSE_BYTES / 4 * 3;
+/// Artifacts are MCP resources at `gitforge://artifacts/<id>`.
+pub const URI_PREFIX: &str = "gitforge://artifacts/";
+
+#[derive(Debug, Clone, Default)]
+pub struct Attachment<'a> {
+    pub name: &'a str,
+    /// Guessed from `name` when `None`.
+    pub media_type: Option<&'a str>,
+    pub bytes: &'a [u8],
+    pub goal_id: Option<&'a str>,
+    pub pr_id: Option<i64>,
+}
+
+/// Stores `attachment`'s bytes, unless the same content is already there,
+/// and records it against its goal and/or PR, at least one of which is
+/// required.
+pub fn attach(
+    repo: &git2::Repository,
+    store: &dyn MetaStore,
+    attachment: &Attachment,
+) -> Result<ArtifactRecord, String> {
+    if attachment.goal_id.is_none() && attachment.pr_id.is_none() {
+        return Err("an artifact needs a goal_id or a pr_id to attach to".to_string());
+    }
+    if let Some(id) = attachment.pr_id {
+        if store.get_pr(id)?.is_none() {
+            return Err(format!("PR {id} not found"));
+        }
+    }
+    if attachment.name.is_empty() || attachment.name.contains(['/', '\\']) {
+        return Err(format!(
+            "invalid artifact name '{}': expected a file name",
+            attachment.name
+        ));
+    }
+    if attachment.bytes.len() > MAX_BYTES {
+        return Err(format!(
+            "{} is {} bytes; artifacts are limited to {MAX_BYTES}",
+            attachment.name,
+            attachment.bytes.len()
+        ));
+    }
+    let hash = write(repo, attachment.bytes)?;
+    let id = store.add_artifact(NewArtifact {
+        hash: &hash,
+        name: attachment.name,
+        media_type: attachment
+            .media_type
+            .unwrap_or_else(|| media_type(attachment.name)),
+        size: attachment.bytes.len() as u64,
+        goal_id: attachment.goal_id,
+        pr_id: attachment.pr_id,
+    })?;
+    store
+        .get_artifact(id)?
+        .ok_or_else(|| format!("artifact {id} vanished after it was saved"))
+}
+
+/// `artifact`'s bytes, checked against its hash.
+pub fn read(repo: &git2::Repository, artifact: &ArtifactRecord) -> Result<Vec<u8>, String> {
+    let path = path(repo, &artifact.hash)?;
+    let bytes = std::fs::read(&path)
+        .map_err(|e| format!("failed to read artifact {}: {e}", artifact.id))?;
+    if hash(&bytes)? != artifact.hash {
+        return Err(format!(
+            "artifact {} is corrupt: {} no longer matches its hash",
+            artifact.id,
+            path.display()
+        ));
+    }
+    Ok(bytes)
+}
+
+pub fn uri(id: i64) -> String {
+    format!("{URI_PREFIX}{id}")
+}
+
+/// The artifact id in a [`uri`].
+pub fn parse_uri(uri: &str) -> Option<i64> {
+    uri.strip_prefix(URI_PREFIX)?.parse().ok()
+}
+
+/// Whether content of `media_type` reads as text rather than base64.
+pub fn is_text(media_type: &str) -> bool {
+    media_type.starts_with("text/")
+        || matches!(
+            media_type,
+            "application/json" | "application/xml" | "image/svg+xml"
+        )
+}
+
+/// The media type `name`'s extension suggests.
+pub fn media_type(name: &str) -> &'static str {
+    let extension = name
+        .rsplit_once('.')
+        .map(|(_, extension)| extension.to_ascii_lowercase())
+        .unwrap_or_default();
+    match extension.as_str() {
+        "txt" | "log" | "out" => "text/plain",
+        "md" => "text/markdown",
+        "csv" => "text/csv",
+        "html" | "htm" => "text/html",
+        "json" => "application/json",
+        "xml" => "application/xml",
+        "svg" => "image/svg+xml",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "pdf" => "application/pdf",
+        "zip" => "application/zip",
+        "gz" | "tgz" => "application/gzip",
+        "tar" => "application/x-tar",
+        _ => "application/octet-stream",
+    }
+}
+
+fn dir(repo: &git2::Repository) -> PathBuf {
+    crate::db::location::common_dir(repo)
+        .join("gitforge")
+        .join("artifacts")
+}
+
+/// `<dir>/ab/cdef...`, fanned out like loose git objects.
+fn path(repo: &git2::Repository, hash: &str) -> Result<PathBuf, String> {
+    if hash.len() != 40 || !hash.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(format!("invalid artifact hash '{hash}'"));
+    }
+    Ok(dir(repo).join(&hash[..2]).join(&hash[2..]))
+}
+
+fn hash(bytes: &[u8]) -> Result<String, String> {
+    git2::Oid::hash_object(git2::ObjectType::Blob, bytes)
+        .map(|oid| oid.to_string())
+        .map_err(|e| format!("failed to hash artifact: {e}"))
+}
+
+/// Writes `bytes` under their hash through a temporary file, so a reader
+/// never sees part of one.
+fn write(repo: &git2::Repository, bytes: &[u8]) -> Result<String, String> {
+    let hash = hash(bytes)?;
+    let dest = path(repo, &hash)?;
+    if dest.exists() {
+        return Ok(hash);
+    }
+    let parent = dest.parent().expect("fanned-out path has a parent");
+    std::fs::create_dir_all(parent)
+        .map_err(|e| format!("failed to create {}: {e}", parent.display()))?;
+    let temp = dest.with_extension(format!("tmp-{}", std::process::id()));
+    std::fs::write(&temp, bytes)
+        .and_then(|()| std::fs::rename(&temp, &dest))
+        .map_err(|e| {
+            let _ = std::fs::remove_file(&temp);
+            format!("failed to store artifact {hash}: {e}")
+        })?;
+    Ok(hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::{self, NewPr};
+
+    #[test]
+    fn attaches_content_once_and_reads_it_back() {
+        let (dir, repo) = crate::test_support::init_repo("artifacts");
+        let store = store::open(&dir.to_string_lossy()).expect("store");
+        let pr = store
+            .create_pr(NewPr {
+                title: "Build",
+                from: "feature",
+                to: "main",
+                description: None,
+            })
+            .expect("pr");
+
+        let report = Attachment {
+            name: "report.xml",
+            bytes: b"<testsuite tests=\"3\"/>",
+            goal_id: Some("G-1"),
+            ..Attachment::default()
+        };
+        let first = attach(&repo, store.as_ref(), &report).expect("attach");
+        assert_eq!(first.media_type, "application/xml");
+        assert_eq!(
+            first.hash,
+            git2::Oid::hash_object(git2::ObjectType::Blob, report.bytes)
+                .unwrap()
+                .to_string()
+        );
+        let again = attach(
+            &repo,
+            store.as_ref(),
+            &Attachment {
+                pr_id: Some(pr),
+                goal_id: None,
+                ..report.clone()
+            },
+        )
+        .expect("attach to the PR");
+        assert_eq!(again.hash, first.hash);
+        let stored = std::fs::read_dir(dir.join(".git/gitforge/artifacts").join(&first.hash[..2]))
+            .expect("fan-out dir")
+            .count();
+        assert_eq!(stored, 1);
+        assert_eq!(read(&repo, &again).expect("read"), report.bytes);
+        assert_eq!(parse_uri(&uri(again.id)), Some(again.id));
+
+        for attachment in [
+            Attachment {
+                goal_id: None,
+                ..report.clone()
+            },
+            Attachment {
+                pr_id: Some(pr + 1),
+                ..report.clone()
+            },
+            Attachment {
+                name: "../escape.txt",
+                ..report.clone()
+            },
+        ] {
+            assert!(attach(&repo, store.as_ref(), &attachment).is_err());
+        }
+        std::fs::write(
+            dir.join(".git/gitforge/artifacts")
+                .join(&first.hash[..2])
+                .join(&first.hash[2..]),
+            "tampered",
+        )
+        .expect("tamper");
+        assert!(read(&repo, &first).is_err_and(|e| e.contains("corrupt")));
+    }
+}
@@ -0,0 +1,335 @@
+use git2::{Cred, CredentialType};
+use std::path::PathBuf;
+
+use super::Forge;
+
+/// Private keys tried, in order, after the agent and any configured key.
+const DEFAULT_KEYS: [&str; 3] = ["id_ed25519", "id_ecdsa", "id_rsa"];
+
+/// Per-remote overrides from `gitforge.remote.<name>.*` git config. None of
+/// them hold a secret: tokens are named by environment variable.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RemoteAuth {
+    /// `username`: user for SSH and HTTPS when the URL has none.
+    pub username: Option<String>,
+    /// `sshKey`: private key file tried right after the SSH agent.
+    pub ssh_key: Option<PathBuf>,
+    /// `tokenEnv`: environment variable holding an HTTPS token.
+    pub token_env: Option<String>,
+}
+
+impl RemoteAuth {
+    pub fn from_config(config: &git2::Config, remote: &str) -> Result<Self, String> {
+        let read = |name: &str| {
+            let key = format!("gitforge.remote.{remote}.{name}");
+            match config.get_string(&key) {
+                Ok(value) if value.is_empty() => Ok(None),
+                Ok(value) => Ok(Some(value)),
+                Err(e) if e.code() == git2::ErrorCode::NotFound => Ok(None),
+                Err(e) => Err(format!("invalid {key}: {e}")),
+            }
+        };
+        Ok(Self {
+            username: read("username")?,
+            ssh_key: read("sshKey")?.map(|path| expand_home(&path)),
+            token_env: read("tokenEnv")?,
+        })
+    }
+}
+
+fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), home_dir()) {
+        (Some(rest), Some(home)) => home.join(rest),
+        _ => PathBuf::from(path),
+    }
+}
+
+fn home_dir() -> Option<PathBuf> {
+    std::env::var_os("HOME")
+        .or_else(|| std::env::var_os("USERPROFILE"))
+        .map(PathBuf::from)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Source {
+    Username,
+    Agent,
+    Key(PathBuf),
+    RemoteToken,
+    ForgeToken,
+    Helper,
+    Default,
+}
+
+impl Source {
+    fn describe(&self) -> String {
+        match self {
+            Source::Username => "username".to_string(),
+            Source::Agent => "ssh-agent".to_string(),
+            Source::Key(path) => format!("key {}", path.display()),
+            Source::RemoteToken => "remote tokenEnv".to_string(),
+            Source::ForgeToken => "gitforge auth token".to_string(),
+            Source::Helper => "credential helper".to_string(),
+            Source::Default => "default credentials".to_string(),
+        }
+    }
+}
+
+/// Answers libgit2's credential requests for one network operation. Every
+/// source is offered at most once, because libgit2 asks again after each
+/// rejection:
+///
+/// - SSH: the agent, the remote's `sshKey`, then `~/.ssh/id_ed25519`,
+///   `id_ecdsa`, and `id_rsa`.
+/// - HTTPS: the remote's `tokenEnv`, the `gitforge auth login` token for the
+///   host, then git's `credential.helper`.
+pub struct Credentials {
+    config: git2::Config,
+    remote: RemoteAuth,
+    ssh_dir: Option<PathBuf>,
+    tried: Vec<Source>,
+}
+
+impl Credentials {
+    pub fn new(config: git2::Config, remote: RemoteAuth) -> Self {
+        Self {
+            config,
+            remote,
+            ssh_dir: home_dir().map(|home| home.join(".ssh")),
+            tried: Vec::new(),
+        }
+    }
+
+    /// For fetches and pushes: the repository's config and `remote`'s
+    /// overrides.
+    pub fn for_remote(repo: &git2::Repository, remote: &str) -> Result<Self, String> {
+        let config = repo
+            .config()
+            .map_err(|e| format!("failed to read git config: {e}"))?;
+        let overrides = RemoteAuth::from_config(&config, remote)?;
+        Ok(Self::new(config, overrides))
+    }
+
+    /// For clones, which have no repository config yet.
+    pub fn for_clone() -> Result<Self, String> {
+        let config =
+            git2::Config::open_default().map_err(|e| format!("failed to read git config: {e}"))?;
+        Ok(Self::new(config, RemoteAuth::default()))
+    }
+
+    /// The next credential to offer for `url`, or an error naming what was
+    /// already tried once nothing is left.
+    pub fn next(
+        &mut self,
+        url: &str,
+        username_from_url: Option<&str>,
+        allowed: CredentialType,
+    ) -> Result<Cred, git2::Error> {
+        let username = self
+            .remote
+            .username
+            .clone()
+            .or(username_from_url.map(str::to_string));
+
+        if allowed.contains(CredentialType::USERNAME) && self.first(Source::Username) {
+            return Cred::username(username.as_deref().unwrap_or("git"));
+        }
+        if allowed.contains(CredentialType::SSH_KEY) {
+            let user = username.as_deref().unwrap_or("git");
+            if self.first(Source::Agent) {
+                if let Ok(cred) = Cred::ssh_key_from_agent(user) {
+                    return Ok(cred);
+                }
+            }
+            for key in self.key_files() {
+                if key.is_file() && self.first(Source::Key(key.clone())) {
+                    return Cred::ssh_key(user, None, &key, None);
+                }
+            }
+        }
+        if allowed.contains(CredentialType::USER_PASS_PLAINTEXT) {
+            if let Some(secret) = self.remote_token() {
+                if self.first(Source::RemoteToken) {
+                    let user = username.as_deref().unwrap_or("x-access-token");
+                    return Cred::userpass_plaintext(user, &secret);
+                }
+            }
+            if let Some(forge) = Forge::for_url(url) {
+                if self.first(Source::ForgeToken) {
+                    if let Some(token) = forge_token(forge) {
+                        // GitHub accepts any username with a token.
+                        return Cred::userpass_plaintext("x-access-token", &token);
+                    }
+                }
+            }
+            if self.first(Source::Helper) {
+                if let Ok(cred) = Cred::credential_helper(&self.config, url, username.as_deref()) {
+                    return Ok(cred);
+                }
+            }
+        }
+        if allowed.contains(CredentialType::DEFAULT) && self.first(Source::Default) {
+            return Cred::default();
+        }
+        Err(git2::Error::from_str(&self.exhausted(url)))
+    }
+
+    /// Records `source` as tried; `false` if it already was.
+    fn first(&mut self, source: Source) -> bool {
+        if self.tried.contains(&source) {
+            return false;
+        }
+        self.tried.push(source);
+        true
+    }
+
+    fn key_files(&self) -> Vec<PathBuf> {
+        let defaults = self
+            .ssh_dir
+            .iter()
+            .flat_map(|dir| DEFAULT_KEYS.map(|name| dir.join(name)));
+        self.remote
+            .ssh_key
+            .clone()
+            .into_iter()
+            .chain(defaults)
+            .collect()
+    }
+
+    fn remote_token(&self) -> Option<String> {
+        let var = self.remote.token_env.as_deref()?;
+        std::env::var(var)
+            .ok()
+            .map(|value| value.trim().to_string())
+            .filter(|value| !value.is_empty())
+    }
+
+    fn exhausted(&self, url: &str) -> String {
+        let hint = match Forge::for_url(url) {
+            Some(forge) => format!("; run `gitforge auth login {}`", forge.as_str()),
+            None if url.starts_with("http") => "; configure git's credential.helper".to_string(),
+            None => "; add a key to ssh-agent or set gitforge.remote.<name>.sshKey".to_string(),
+        };
+        if self.tried.is_empty() {
+            return format!("authentication required for {url}{hint}");
+        }
+        let tried: Vec<String> = self.tried.iter().map(Source::describe).collect();
+        format!(
+            "authentication failed for {url} (tried {}){hint}",
+            tried.join(", ")
+        )
+    }
+}
+
+fn forge_token(forge: Forge) -> Option<String> {
+    match super::token(forge) {
+        Ok(token) => token.map(|token| token.secret),
+        Err(e) => {
+            eprintln!("{e}");
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    /// Looks for default keys in `dir` instead of `~/.ssh`.
+    fn with_ssh_dir(mut credentials: Credentials, dir: &Path) -> Credentials {
+        credentials.ssh_dir = Some(dir.to_path_buf());
+        credentials
+    }
+
+    fn temp_repo(label: &str) -> (crate::test_support::TempDir, git2::Repository) {
+        let (dir, repo) = crate::test_support::init_repo(&format!("credentials-{label}"));
+        (dir, repo)
+    }
+
+    #[test]
+    fn remote_overrides_come_from_git_config() {
+        let (_dir, repo) = temp_repo("config");
+        let mut config = repo.config().expect("config");
+        config
+            .set_str("gitforge.remote.origin.username", "deploy")
+            .expect("username");
+        config
+            .set_str("gitforge.remote.origin.sshKey", "/keys/deploy")
+            .expect("key");
+        config
+            .set_str("gitforge.remote.origin.tokenEnv", "DEPLOY_TOKEN")
+            .expect("token env");
+
+        let origin = RemoteAuth::from_config(&config, "origin").expect("origin");
+        assert_eq!(origin.username.as_deref(), Some("deploy"));
+        assert_eq!(origin.ssh_key, Some(PathBuf::from("/keys/deploy")));
+        assert_eq!(origin.token_env.as_deref(), Some("DEPLOY_TOKEN"));
+        assert_eq!(
+            RemoteAuth::from_config(&config, "upstream").expect("upstream"),
+            RemoteAuth::default()
+        );
+    }
+
+    #[test]
+    fn ssh_sources_are_offered_once_each() {
+        let (dir, _repo) = temp_repo("ssh");
+        let ssh_dir = dir.join("ssh");
+        std::fs::create_dir_all(&ssh_dir).expect("ssh dir");
+        std::fs::write(ssh_dir.join("id_ed25519"), "key").expect("default key");
+        std::fs::write(dir.join("deploy"), "key").expect("configured key");
+        let remote = RemoteAuth {
+            ssh_key: Some(dir.join("deploy")),
+            ..RemoteAuth::default()
+        };
+        let mut credentials = with_ssh_dir(
+            Credentials::new(git2::Config::new().expect("config"), remote),
+            &ssh_dir,
+        );
+
+        let url = "ssh://git@git.example.com/team/repo.git";
+        let ssh = CredentialType::SSH_KEY;
+        let kinds: Vec<u32> = (0..3)
+            .map(|_| credentials.next(url, Some("git"), ssh).expect("cred"))
+            .map(|cred| cred.credtype())
+            .collect();
+        assert!(kinds.iter().all(|kind| *kind == ssh.bits()), "{kinds:?}");
+
+        let Err(err) = credentials.next(url, Some("git"), ssh) else {
+            panic!("every ssh source was already tried");
+        };
+        let message = err.message();
+        assert!(
+            message.contains("ssh-agent") && message.contains("deploy"),
+            "{message}"
+        );
+        assert!(message.contains("id_ed25519"), "{message}");
+    }
+
+    #[test]
+    fn https_tries_the_remote_token_then_gives_up() {
+        std::env::set_var("GITFORGE_TEST_REMOTE_TOKEN", "s3cret");
+        let remote = RemoteAuth {
+            token_env: Some("GITFORGE_TEST_REMOTE_TOKEN".to_string()),
+            ..RemoteAuth::default()
+        };
+        // Standalone config, so the user's own helpers stay out of it.
+        let config = git2::Config::new().expect("config");
+        let mut credentials = Credentials::new(config, remote);
+
+        let url = "https://git.example.com/team/repo.git";
+        let plaintext = CredentialType::USER_PASS_PLAINTEXT;
+        let cred = credentials.next(url, None, plaintext).expect("token");
+        assert_eq!(cred.credtype(), plaintext.bits());
+
+        let Err(err) = credentials.next(url, None, plaintext) else {
+            panic!("the token was already offered");
+        };
+        assert!(
+            err.message().contains("remote tokenEnv")
+                && err.message().contains("credential.helper"),
+            "{}",
+            err.message()
+        );
+    }
+}
@@ -0,0 +1,437 @@
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use super::Forge;
+
+pub const DEVICE_CODE_URL: &str = "https://github.com/login/device/code";
+pub const ACCESS_TOKEN_URL: &str = "https://github.com/login/oauth/access_token";
+pub const USER_URL: &str = "https://api.github.com/user";
+pub const API_URL: &str = "https://api.github.com";
+/// Git config and environment variable naming the OAuth app to sign in
+/// through; device flow must be enabled in the app's settings.
+pub const CLIENT_ID_KEY: &str = "gitforge.github.clientId";
+pub const CLIENT_ID_ENV: &str = "GITFORGE_GITHUB_CLIENT_ID";
+/// Pushing to private repositories and reading organization PRs.
+pub const DEFAULT_SCOPE: &str = "repo read:org";
+
+const GRANT_TYPE: &str = "urn:ietf:params:oauth:grant-type:device_code";
+const TIMEOUT: Duration = Duration::from_secs(30);
+/// Added to the polling interval on each `slow_down`, per RFC 8628.
+const SLOW_DOWN: Duration = Duration::from_secs(5);
+
+/// The HTTP calls the flow makes, so it can be driven without a network.
+pub trait Transport {
+    /// Form POST expecting a JSON reply. GitHub reports flow errors in the
+    /// body, so error statuses with a JSON body are replies too.
+    fn post_form(&self, url: &str, form: &[(&str, &str)]) -> Result<serde_json::Value, String>;
+    fn get_json(&self, url: &str, token: &str) -> Result<serde_json::Value, String>;
+    fn post_json(
+        &self,
+        url: &str,
+        token: &str,
+        body: &serde_json::Value,
+    ) -> Result<serde_json::Value, String>;
+}
+
+pub struct Http;
+
+impl Transport for Http {
+    fn post_form(&self, url: &str, form: &[(&str, &str)]) -> Result<serde_json::Value, String> {
+        let reply = ureq::post(url)
+            .set("Accept", "application/json")
+            .timeout(TIMEOUT)
+            .send_form(form);
+        json_reply(url, reply)
+    }
+
+    fn get_json(&self, url: &str, token: &str) -> Result<serde_json::Value, String> {
+        let reply = ureq::get(url)
+            .set("Accept", "application/vnd.github+json")
+            .set("Authorization", &format!("Bearer {token}"))
+            .set("User-Agent", "gitforge")
+            .timeout(TIMEOUT)
+            .call();
+        json_reply(url, reply)
+    }
+
+    fn post_json(
+        &self,
+        url: &str,
+        token: &str,
+        body: &serde_json::Value,
+    ) -> Result<serde_json::Value, String> {
+        let reply = ureq::post(url)
+            .set("Accept", "application/vnd.github+json")
+            .set("Authorization", &format!("Bearer {token}"))
+            .set("User-Agent", "gitforge")
+            .timeout(TIMEOUT)
+            .send_json(body);
+        json_reply(url, reply)
+    }
+}
+
+fn json_reply(
+    url: &str,
+    reply: Result<ureq::Response, ureq::Error>,
+) -> Result<serde_json::Value, String> {
+    let response = match reply {
+        Ok(response) => response,
+        Err(ureq::Error::Status(code, response)) => {
+            return response
+                .into_json()
+                .map_err(|_| format!("{url} answered HTTP {code}"))
+        }
+        Err(e) => return Err(format!("failed to reach {url}: {e}")),
+    };
+    response
+        .into_json()
+        .map_err(|e| format!("invalid response from {url}: {e}"))
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeviceCode {
+    pub device_code: String,
+    /// What the user types at `verification_uri`.
+    pub user_code: String,
+    pub verification_uri: String,
+    /// Seconds the code stays valid.
+    pub expires_in: u64,
+    /// Minimum seconds between polls.
+    #[serde(default = "default_interval")]
+    pub interval: u64,
+}
+
+fn default_interval() -> u64 {
+    5
+}
+
+/// `--client-id`, then [`CLIENT_ID_ENV`], then [`CLIENT_ID_KEY`] in the
+/// repository's (or the global) git config.
+pub fn client_id(explicit: Option<&str>, repo_path: &str) -> Option<String> {
+    if let Some(id) = explicit.filter(|id| !id.is_empty()) {
+        return Some(id.to_string());
+    }
+    if let Some(id) = std::env::var(CLIENT_ID_ENV)
+        .ok()
+        .filter(|id| !id.is_empty())
+    {
+        return Some(id);
+    }
+    let config = git2::Repository::open(repo_path)
+        .and_then(|repo| repo.config())
+        .or_else(|_| git2::Config::open_default())
+        .ok()?;
+    config
+        .get_string(CLIENT_ID_KEY)
+        .ok()
+        .filter(|id| !id.is_empty())
+}
+
+/// Starts a device-flow login: show the user `user_code` and
+/// `verification_uri`, then [`poll_token`].
+pub fn request_code(
+    http: &dyn Transport,
+    client_id: &str,
+    scope: &str,
+) -> Result<DeviceCode, String> {
+    let reply = http.post_form(
+        DEVICE_CODE_URL,
+        &[("client_id", client_id), ("scope", scope)],
+    )?;
+    if let Some(error) = reply.get("error").and_then(|v| v.as_str()) {
+        return Err(rejected(error, &reply));
+    }
+    serde_json::from_value(reply).map_err(|e| format!("invalid device code response: {e}"))
+}
+
+/// Waits for the user to authorize `code`, polling no faster than GitHub
+/// allows. `sleep` is called before every poll.
+pub fn poll_token(
+    http: &dyn Transport,
+    client_id: &str,
+    code: &DeviceCode,
+    sleep: &mut dyn FnMut(Duration),
+) -> Result<String, String> {
+    let mut interval = Duration::from_secs(code.interval.max(1));
+    let expires = Duration::from_secs(code.expires_in);
+    let mut waited = Duration::ZERO;
+    while waited < expires {
+        sleep(interval);
+        waited += interval;
+        let reply = http.post_form(
+            ACCESS_TOKEN_URL,
+            &[
+                ("client_id", client_id),
+                ("device_code", &code.device_code),
+                ("grant_type", GRANT_TYPE),
+            ],
+        )?;
+        if let Some(token) = reply.get("access_token").and_then(|v| v.as_str()) {
+            return Ok(token.to_string());
+        }
+        match reply.get("error").and_then(|v| v.as_str()) {
+            Some("authorization_pending") => {}
+            Some("slow_down") => {
+                interval = reply
+                    .get("interval")
+                    .and_then(|v| v.as_u64())
+                    .map_or(interval + SLOW_DOWN, Duration::from_secs);
+            }
+            Some("expired_token") => break,
+            Some("access_denied") => return Err("the login was denied on GitHub".to_string()),
+            Some(error) => return Err(rejected(error, &reply)),
+            None => return Err("GitHub answered without a token or an error".to_string()),
+        }
+    }
+    Err("the device code expired before it was authorized; run the login again".to_string())
+}
+
+/// Login name of the account `token` belongs to.
+pub fn user_login(http: &dyn Transport, token: &str) -> Result<String, String> {
+    let user = http.get_json(USER_URL, token)?;
+    user.get("login")
+        .and_then(|v| v.as_str())
+        .map(str::to_string)
+        .ok_or_else(|| {
+            let message = user.get("message").and_then(|v| v.as_str());
+            format!(
+                "GitHub did not accept the token: {}",
+                message.unwrap_or("no login in response")
+            )
+        })
+}
+
+/// `owner/name` of a github.com remote, from its HTTPS or SSH URL.
+pub fn repo_slug(url: &str) -> Option<String> {
+    let path = if Forge::for_url(url) == Some(Forge::GitHub) {
+        let rest = url.split_once("://")?.1;
+        rest.split_once('/')?.1
+    } else if let Some(rest) = url.strip_prefix("ssh://") {
+        let (authority, path) = rest.split_once('/')?;
+        let host = authority.rsplit('@').next()?.split(':').next()?;
+        if !host.eq_ignore_ascii_case("github.com") {
+            return None;
+        }
+        path
+    } else {
+        let (authority, path) = url.split_once(':')?;
+        let host = authority.rsplit('@').next()?;
+        if !host.eq_ignore_ascii_case("github.com") {
+            return None;
+        }
+        path
+    };
+    let path = path.trim_end_matches('/');
+    let path = path.strip_suffix(".git").unwrap_or(path);
+    let (owner, name) = path.split_once('/')?;
+    let valid = |part: &str| !part.is_empty() && !part.contains('/');
+    (valid(owner) && valid(name)).then(|| format!("{owner}/{name}"))
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct NewRelease<'a> {
+    pub tag_name: &'a str,
+    /// Commit GitHub creates the tag at if it was not pushed.
+    pub target_commitish: &'a str,
+    pub name: &'a str,
+    pub body: &'a str,
+    pub draft: bool,
+}
+
+/// Publishes a release in `slug` (`owner/name`) and returns its page.
+pub fn create_release(
+    http: &dyn Transport,
+    token: &str,
+    slug: &str,
+    release: &NewRelease,
+) -> Result<String, String> {
+    let url = format!("{API_URL}/repos/{slug}/releases");
+    let body = serde_json::to_value(release).map_err(|e| e.to_string())?;
+    let reply = http.post_json(&url, token, &body)?;
+    if let Some(page) = reply.get("html_url").and_then(|v| v.as_str()) {
+        return Ok(page.to_string());
+    }
+    let message = reply
+        .get("message")
+        .and_then(|v| v.as_str())
+        .unwrap_or("no release in response");
+    let details: Vec<&str> = reply
+        .get("errors")
+        .and_then(|v| v.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(|error| error.get("code").and_then(|v| v.as_str()))
+        .collect();
+    if details.is_empty() {
+        Err(format!("GitHub did not create the release: {message}"))
+    } else {
+        Err(format!(
+            "GitHub did not create the release: {message} ({})",
+            details.join(", ")
+        ))
+    }
+}
+
+fn rejected(error: &str, reply: &serde_json::Value) -> String {
+    match reply.get("error_description").and_then(|v| v.as_str()) {
+        Some(description) => format!("GitHub rejected the login ({error}): {description}"),
+        None => format!("GitHub rejected the login ({error})"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+
+    /// A POST's URL and form fields.
+    type Sent = (String, Vec<(String, String)>);
+
+    /// Answers POSTs from a script, recording the forms it was sent.
+    struct Scripted {
+        replies: RefCell<Vec<serde_json::Value>>,
+        forms: RefCell<Vec<Sent>>,
+    }
+
+    impl Scripted {
+        fn new(replies: Vec<serde_json::Value>) -> Self {
+            Self {
+                replies: RefCell::new(replies),
+                forms: RefCell::default(),
+            }
+        }
+    }
+
+    impl Transport for Scripted {
+        fn post_form(&self, url: &str, form: &[(&str, &str)]) -> Result<serde_json::Value, String> {
+            let form = form
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect();
+            self.forms.borrow_mut().push((url.to_string(), form));
+            Ok(self.replies.borrow_mut().remove(0))
+        }
+
+        fn get_json(&self, _url: &str, token: &str) -> Result<serde_json::Value, String> {
+            Ok(serde_json::json!({ "login": format!("user-of-{token}") }))
+        }
+
+        fn post_json(
+            &self,
+            url: &str,
+            _token: &str,
+            body: &serde_json::Value,
+        ) -> Result<serde_json::Value, String> {
+            let form = vec![("body".to_string(), body.to_string())];
+            self.forms.borrow_mut().push((url.to_string(), form));
+            Ok(self.replies.borrow_mut().remove(0))
+        }
+    }
+
+    fn code() -> DeviceCode {
+        DeviceCode {
+            device_code: "dev-123".to_string(),
+            user_code: "ABCD-1234".to_string(),
+            verification_uri: "https://github.com/login/device".to_string(),
+            expires_in: 60,
+            interval: 5,
+        }
+    }
+
+    #[test]
+    fn device_code_is_requested_with_client_and_scope() {
+        let http = Scripted::new(vec![serde_json::json!({
+            "device_code": "dev-123",
+            "user_code": "ABCD-1234",
+            "verification_uri": "https://github.com/login/device",
+            "expires_in": 60
+        })]);
+        assert_eq!(request_code(&http, "client", "repo"), Ok(code()));
+        let forms = http.forms.borrow();
+        assert_eq!(forms[0].0, DEVICE_CODE_URL);
+        assert!(forms[0]
+            .1
+            .contains(&("scope".to_string(), "repo".to_string())));
+
+        let http = Scripted::new(vec![serde_json::json!({
+            "error": "unauthorized_client",
+            "error_description": "device flow is disabled"
+        })]);
+        let err = request_code(&http, "client", "repo").unwrap_err();
+        assert!(err.contains("device flow is disabled"), "{err}");
+    }
+
+    #[test]
+    fn polling_waits_out_pending_and_slow_down_replies() {
+        let http = Scripted::new(vec![
+            serde_json::json!({ "error": "authorization_pending" }),
+            serde_json::json!({ "error": "slow_down", "interval": 10 }),
+            serde_json::json!({ "access_token": "gho_token", "token_type": "bearer" }),
+        ]);
+        let mut sleeps = Vec::new();
+        let token = poll_token(&http, "client", &code(), &mut |d| sleeps.push(d.as_secs()));
+
+        assert_eq!(token, Ok("gho_token".to_string()));
+        assert_eq!(sleeps, [5, 5, 10]);
+        let forms = http.forms.borrow();
+        assert!(forms.iter().all(|(url, form)| url == ACCESS_TOKEN_URL
+            && form.contains(&("device_code".to_string(), "dev-123".to_string()))));
+        assert_eq!(
+            user_login(&http, "gho_token"),
+            Ok("user-of-gho_token".into())
+        );
+    }
+
+    #[test]
+    fn polling_stops_on_denial_and_expiry() {
+        let http = Scripted::new(vec![serde_json::json!({ "error": "access_denied" })]);
+        let err = poll_token(&http, "client", &code(), &mut |_| {}).unwrap_err();
+        assert!(err.contains("denied"), "{err}");
+
+        let pending = serde_json::json!({ "error": "authorization_pending" });
+        let http = Scripted::new(vec![pending; 12]);
+        let err = poll_token(&http, "client", &code(), &mut |_| {}).unwrap_err();
+        assert!(err.contains("expired"), "{err}");
+        assert_eq!(http.forms.borrow().len(), 12, "60s of codes at 5s apart");
+    }
+
+    #[test]
+    fn releases_go_to_the_remotes_repository() {
+        for url in [
+            "https://github.com/nanocubit/gitforge.git",
+            "git@github.com:nanocubit/gitforge.git",
+            "ssh://git@github.com/nanocubit/gitforge",
+        ] {
+            assert_eq!(
+                repo_slug(url).as_deref(),
+                Some("nanocubit/gitforge"),
+                "{url}"
+            );
+        }
+        assert_eq!(repo_slug("git@gitlab.com:a/b.git"), None);
+        assert_eq!(repo_slug("https://github.com/only-owner"), None);
+
+        let release = NewRelease {
+            tag_name: "v1.0.0",
+            target_commitish: "abc123",
+            name: "v1.0.0",
+            body: "notes",
+            draft: false,
+        };
+        let http = Scripted::new(vec![
+            serde_json::json!({ "html_url": "https://github.com/o/r/releases/tag/v1.0.0" }),
+            serde_json::json!({
+                "message": "Validation Failed",
+                "errors": [{ "resource": "Release", "code": "already_exists" }]
+            }),
+        ]);
+        let page = create_release(&http, "token", "o/r", &release).expect("release");
+        assert!(page.ends_with("/tag/v1.0.0"), "{page}");
+        assert_eq!(
+            http.forms.borrow()[0].0,
+            format!("{API_URL}/repos/o/r/releases")
+        );
+        let err = create_release(&http, "token", "o/r", &release).unwrap_err();
+        assert!(err.contains("already_exists"), "{err}");
+    }
+}
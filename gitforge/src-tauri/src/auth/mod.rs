@@ -0,0 +1,185 @@
+use serde::Serialize;
+use std::fmt;
+
+pub mod credentials;
+pub mod github;
+
+/// Keyring service every forge token is stored under, one entry per forge.
+pub const KEYRING_SERVICE: &str = "gitforge";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Forge {
+    GitHub,
+}
+
+impl Forge {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "github" | "github.com" => Some(Forge::GitHub),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Forge::GitHub => "github",
+        }
+    }
+
+    /// Host whose HTTPS remotes the forge's token authenticates.
+    pub fn host(self) -> &'static str {
+        match self {
+            Forge::GitHub => "github.com",
+        }
+    }
+
+    /// Environment variables that take precedence over the keyring, for CI
+    /// and other headless setups.
+    pub fn env_vars(self) -> &'static [&'static str] {
+        match self {
+            Forge::GitHub => &["GITFORGE_GITHUB_TOKEN", "GITHUB_TOKEN"],
+        }
+    }
+
+    /// The forge an `http(s)://` remote URL points at.
+    pub fn for_url(url: &str) -> Option<Self> {
+        let rest = url
+            .strip_prefix("https://")
+            .or_else(|| url.strip_prefix("http://"))?;
+        let authority = rest.split('/').next().unwrap_or_default();
+        let host = authority.rsplit('@').next().unwrap_or_default();
+        let host = host.split(':').next().unwrap_or_default();
+        [Forge::GitHub]
+            .into_iter()
+            .find(|forge| host.eq_ignore_ascii_case(forge.host()))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TokenSource {
+    Env,
+    Keyring,
+}
+
+#[derive(Clone, PartialEq, Eq)]
+pub struct Token {
+    pub forge: Forge,
+    pub secret: String,
+    pub source: TokenSource,
+    /// The environment variable it came from.
+    pub var: Option<&'static str>,
+}
+
+/// Keeps the secret out of logs and error messages.
+impl fmt::Debug for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Token")
+            .field("forge", &self.forge)
+            .field("source", &self.source)
+            .field("var", &self.var)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The token to use for `forge`: an environment override if one is set,
+/// otherwise what `gitforge auth login` stored in the OS keyring.
+pub fn token(forge: Forge) -> Result<Option<Token>, String> {
+    if let Some(token) = env_token(forge, |var| std::env::var(var).ok()) {
+        return Ok(Some(token));
+    }
+    match entry(forge)?.get_password() {
+        Ok(secret) => Ok(Some(Token {
+            forge,
+            secret,
+            source: TokenSource::Keyring,
+            var: None,
+        })),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => Err(format!("failed to read the {} token: {e}", forge.as_str())),
+    }
+}
+
+fn env_token(forge: Forge, lookup: impl Fn(&str) -> Option<String>) -> Option<Token> {
+    forge.env_vars().iter().find_map(|var| {
+        let secret = lookup(var).filter(|value| !value.trim().is_empty())?;
+        Some(Token {
+            forge,
+            secret: secret.trim().to_string(),
+            source: TokenSource::Env,
+            var: Some(*var),
+        })
+    })
+}
+
+pub fn store_token(forge: Forge, secret: &str) -> Result<(), String> {
+    entry(forge)?
+        .set_password(secret)
+        .map_err(|e| format!("failed to store the {} token: {e}", forge.as_str()))
+}
+
+/// `false` when no token was stored.
+pub fn delete_token(forge: Forge) -> Result<bool, String> {
+    match entry(forge)?.delete_password() {
+        Ok(()) => Ok(true),
+        Err(keyring::Error::NoEntry) => Ok(false),
+        Err(e) => Err(format!(
+            "failed to delete the {} token: {e}",
+            forge.as_str()
+        )),
+    }
+}
+
+fn entry(forge: Forge) -> Result<keyring::Entry, String> {
+    keyring::Entry::new(KEYRING_SERVICE, forge.as_str())
+        .map_err(|e| format!("failed to open the OS keyring: {e}"))
+}
+
+/// Compares without an early exit, so response times don't reveal how much
+/// of a guessed token was right.
+pub(crate) fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn https_remotes_map_to_their_forge() {
+        for url in [
+            "https://github.com/nanocubit/gitforge.git",
+            "https://me@GitHub.com/nanocubit/gitforge",
+            "http://github.com:443/nanocubit/gitforge",
+        ] {
+            assert_eq!(Forge::for_url(url), Some(Forge::GitHub), "{url}");
+        }
+        for url in [
+            "git@github.com:nanocubit/gitforge.git",
+            "https://github.com.evil.example/x",
+            "https://gitlab.com/a/b",
+        ] {
+            assert_eq!(Forge::for_url(url), None, "{url}");
+        }
+        assert_eq!(Forge::parse("GitHub"), Some(Forge::GitHub));
+    }
+
+    #[test]
+    fn environment_overrides_in_order_and_ignores_blanks() {
+        let token = env_token(Forge::GitHub, |var| match var {
+            "GITFORGE_GITHUB_TOKEN" => Some("  ".to_string()),
+            "GITHUB_TOKEN" => Some("ghp_secret\n".to_string()),
+            _ => None,
+        })
+        .expect("token");
+        assert_eq!(token.secret, "ghp_secret");
+        assert_eq!(token.var, Some("GITHUB_TOKEN"));
+        assert!(!format!("{token:?}").contains("ghp_secret"));
+
+        assert!(env_token(Forge::GitHub, |_| None).is_none());
+    }
+}
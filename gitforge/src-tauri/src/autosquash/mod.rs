@@ -0,0 +1,209 @@
+//! `fixup!` commits and folding them into the commits they fix, like
+//! `git commit --fixup` and `git rebase -i --autosquash`, so an agent's
+//! iterative commits can be tidied before a PR. The folding is a
+//! [`rebase_plan`](crate::rebase_plan) with the fixups moved into place.
+
+use crate::rebase_plan::{self, Action, Applied, Failure, Plan, Step};
+
+const FIXUP: &str = "fixup! ";
+const SQUASH: &str = "squash! ";
+
+/// The message of a fixup commit for revision `target`, which HEAD must
+/// contain.
+pub fn fixup_message(repo: &git2::Repository, target: &str) -> Result<String, String> {
+    let commit = repo
+        .revparse_single(target)
+        .and_then(|object| object.peel_to_commit())
+        .map_err(|e| format!("failed to resolve {target}: {e}"))?;
+    let head = rebase_plan::head_commit(repo)?;
+    if head.id() != commit.id()
+        && !repo
+            .graph_descendant_of(head.id(), commit.id())
+            .map_err(|e| format!("failed to walk history: {e}"))?
+    {
+        return Err(format!("{target} is not on the current branch"));
+    }
+    if commit.parent_count() > 1 {
+        return Err(format!("{target} is a merge commit"));
+    }
+    Ok(format!(
+        "{FIXUP}{}",
+        strip_prefixes(commit.summary().unwrap_or(""))
+    ))
+}
+
+/// How the first-parent history after `onto` would be reordered. Without
+/// `onto`, it starts at the parent of the oldest commit a fixup targets,
+/// looking back no further than the first merge.
+pub fn plan(repo: &git2::Repository, onto: Option<&str>) -> Result<Plan, String> {
+    let onto = onto
+        .map(|rev| {
+            repo.revparse_single(rev)
+                .and_then(|object| object.peel_to_commit())
+                .map(|commit| commit.id())
+                .map_err(|e| format!("failed to resolve {rev}: {e}"))
+        })
+        .transpose()?;
+    let commits = rebase_plan::range(repo, onto)?;
+
+    // Each fixup goes after the oldest earlier commit it names, by summary
+    // or id prefix.
+    let mut targets: Vec<Option<usize>> = vec![None; commits.len()];
+    for (i, commit) in commits.iter().enumerate() {
+        let summary = commit.summary().unwrap_or("");
+        if !is_fold(summary) {
+            continue;
+        }
+        let wanted = strip_prefixes(summary);
+        targets[i] = commits[..i].iter().position(|candidate| {
+            let candidate_summary = candidate.summary().unwrap_or("");
+            !is_fold(candidate_summary)
+                && (candidate_summary == wanted
+                    || (wanted.len() >= 7 && candidate.id().to_string().starts_with(wanted)))
+        });
+    }
+    let Some(first) = targets.iter().flatten().min().copied() else {
+        return Err("no fixup! or squash! commits to fold".to_string());
+    };
+    let start = if onto.is_some() { 0 } else { first };
+    let base = match start {
+        0 => match onto {
+            Some(onto) => onto,
+            None => commits[0]
+                .parent_id(0)
+                .map_err(|_| "can't fold fixups into the root commit".to_string())?,
+        },
+        start => commits[start - 1].id(),
+    };
+
+    let step = |i: usize| {
+        let commit = &commits[i];
+        let summary = commit.summary().unwrap_or("").to_string();
+        let action = match targets[i] {
+            Some(_) if summary.starts_with(SQUASH) => Action::Squash,
+            Some(_) => Action::Fixup,
+            None => Action::Pick,
+        };
+        Step {
+            action,
+            commit: commit.id().to_string(),
+            summary,
+            message: None,
+        }
+    };
+    let mut steps = Vec::new();
+    for i in start..commits.len() {
+        if targets[i].is_some() {
+            continue;
+        }
+        steps.push(step(i));
+        steps.extend(
+            (i + 1..commits.len())
+                .filter(|&j| targets[j] == Some(i))
+                .map(step),
+        );
+    }
+    Ok(Plan {
+        base: base.to_string(),
+        steps,
+    })
+}
+
+/// Applies [`plan`]; see [`rebase_plan::apply`].
+pub fn autosquash(repo: &git2::Repository, onto: Option<&str>) -> Result<Applied, Failure> {
+    rebase_plan::apply(repo, plan(repo, onto)?)
+}
+
+fn is_fold(summary: &str) -> bool {
+    summary.starts_with(FIXUP) || summary.starts_with(SQUASH)
+}
+
+/// `fixup! fixup! subject` targets `subject`.
+fn strip_prefixes(mut summary: &str) -> &str {
+    while let Some(rest) = summary
+        .strip_prefix(FIXUP)
+        .or_else(|| summary.strip_prefix(SQUASH))
+    {
+        summary = rest;
+    }
+    summary
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn fixups_fold_into_their_targets_and_conflicts_change_nothing() {
+        let (dir, repo) = crate::test_support::init_repo("autosquash");
+        let sig = git2::Signature::now("GitForge Test", "test@gitforge.dev").expect("sig");
+        let commit = |path: &str, text: &str, message: &str| {
+            fs::write(dir.join(path), text).expect("write");
+            let mut index = repo.index().expect("index");
+            index.add_path(std::path::Path::new(path)).expect("add");
+            index.write().expect("write index");
+            let tree = repo
+                .find_tree(index.write_tree().expect("tree"))
+                .expect("tree");
+            let parent = repo.head().ok().and_then(|h| h.peel_to_commit().ok());
+            let parents: Vec<&git2::Commit> = parent.iter().collect();
+            repo.commit(Some("HEAD"), &sig, &sig, message, &tree, &parents)
+                .expect("commit")
+        };
+        let base = commit("base.txt", "base\n", "Base");
+        commit("a.txt", "a\n", "Add a");
+        commit("b.txt", "b\n", "Add b");
+        let message = fixup_message(&repo, "HEAD~1").expect("message");
+        assert_eq!(message, "fixup! Add a");
+        commit("a.txt", "a fixed\n", &message);
+        commit("b.txt", "b more\n", "squash! Add b\n\nAlso b more.");
+
+        let plan = plan(&repo, None).expect("plan");
+        assert_eq!(plan.base, base.to_string());
+        let actions: Vec<(Action, &str)> = plan
+            .steps
+            .iter()
+            .map(|step| (step.action, step.summary.as_str()))
+            .collect();
+        assert_eq!(
+            actions,
+            [
+                (Action::Pick, "Add a"),
+                (Action::Fixup, "fixup! Add a"),
+                (Action::Pick, "Add b"),
+                (Action::Squash, "squash! Add b"),
+            ]
+        );
+
+        let squashed = autosquash(&repo, None).expect("autosquash");
+        assert_eq!(squashed.plan.edits(), 2);
+        let head = repo.head().unwrap().peel_to_commit().unwrap();
+        assert_eq!(head.id().to_string(), squashed.head);
+        assert_eq!(head.message(), Some("Add b\n\nAlso b more.\n"));
+        let first = head.parent(0).unwrap();
+        assert_eq!(first.summary(), Some("Add a"));
+        assert_eq!(first.parent_id(0).unwrap(), base);
+        assert_eq!(fs::read_to_string(dir.join("a.txt")).unwrap(), "a fixed\n");
+        assert!(plan_is_empty(&repo));
+
+        // A fixup whose change can't apply to its target.
+        commit("a.txt", "a again\n", "Rewrite a");
+        commit("a.txt", "a final\n", "fixup! Add a");
+        let before = repo.head().unwrap().target().unwrap();
+        match autosquash(&repo, None).expect_err("conflict") {
+            Failure::Conflict { step, paths } => {
+                assert_eq!(
+                    (step.action, paths),
+                    (Action::Fixup, vec!["a.txt".to_string()])
+                );
+            }
+            other => panic!("{other}"),
+        }
+        assert_eq!(repo.head().unwrap().target().unwrap(), before);
+    }
+
+    fn plan_is_empty(repo: &git2::Repository) -> bool {
+        plan(repo, None).is_err_and(|e| e.starts_with("no fixup!"))
+    }
+}
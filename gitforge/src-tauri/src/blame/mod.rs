@@ -0,0 +1,323 @@
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use crate::content;
+
+/// Blobs kept in the cache; the least recently used one is dropped first.
+pub const MAX_CACHED_BLOBS: usize = 64;
+/// Widest range blamed per call, a few screens of gutter.
+pub const MAX_RANGE_LINES: u32 = 2000;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BlameLine {
+    /// 1-based line number in the blamed revision.
+    pub line: u32,
+    pub commit: String,
+    pub short_id: String,
+    pub author: String,
+    pub email: String,
+    /// Author time, seconds since the unix epoch.
+    pub time: i64,
+    pub summary: String,
+    /// Line number in `commit`, for jumping to the change.
+    pub original_line: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BlameRange {
+    pub path: String,
+    pub revision: String,
+    pub blob: String,
+    pub total_lines: u32,
+    pub start: u32,
+    pub end: u32,
+    pub lines: Vec<BlameLine>,
+}
+
+/// How many of the blamed lines one author last touched.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AuthorShare {
+    pub author: String,
+    pub email: String,
+    pub lines: usize,
+}
+
+#[derive(Default)]
+struct Entry {
+    lines: BTreeMap<u32, BlameLine>,
+    last_used: u64,
+}
+
+#[derive(Default)]
+struct Blobs {
+    entries: HashMap<(String, git2::Oid), Entry>,
+    clock: u64,
+}
+
+/// Blame lines keyed by path and blob OID, filled in as the editor scrolls.
+/// A blob that hasn't changed keeps its blame across commits, so moving
+/// HEAD doesn't throw the gutter away. Cloning shares the cache.
+#[derive(Clone, Default)]
+pub struct BlameCache {
+    blobs: Arc<Mutex<Blobs>>,
+}
+
+impl BlameCache {
+    /// Blame for lines `start..=end` (1-based, clamped to the file) of `path`
+    /// at `revision` (default `HEAD`). Only lines not already cached for the
+    /// blob are blamed.
+    pub fn blame_range(
+        &self,
+        repo: &git2::Repository,
+        path: &str,
+        revision: Option<&str>,
+        start: u32,
+        end: u32,
+    ) -> Result<BlameRange, String> {
+        content::check_path(path)?;
+        let revision = revision.unwrap_or("HEAD");
+        let commit = repo
+            .revparse_single(revision)
+            .and_then(|object| object.peel_to_commit())
+            .map_err(|e| format!("unknown revision '{revision}': {e}"))?;
+        let blob = commit
+            .tree()
+            .and_then(|tree| tree.get_path(Path::new(path)))
+            .and_then(|entry| entry.to_object(repo))
+            .and_then(|object| object.peel_to_blob())
+            .map_err(|_| format!("{path} is not a file at {revision}"))?;
+        let key = (path.to_string(), blob.id());
+        let total_lines = line_count(blob.content());
+
+        let start = start.max(1);
+        let end = end
+            .min(total_lines)
+            .min(start.saturating_add(MAX_RANGE_LINES - 1));
+        let mut range = BlameRange {
+            path: path.to_string(),
+            revision: revision.to_string(),
+            blob: blob.id().to_string(),
+            total_lines,
+            start,
+            end,
+            lines: Vec::new(),
+        };
+        if start > end {
+            return Ok(range);
+        }
+
+        let missing = {
+            let blobs = self.lock();
+            let cached = blobs.entries.get(&key);
+            (start..=end)
+                .filter(|line| !cached.is_some_and(|entry| entry.lines.contains_key(line)))
+                .collect::<Vec<u32>>()
+        };
+        // Blame outside the lock; another window may be scrolling too.
+        let fresh = match (missing.first(), missing.last()) {
+            (Some(&first), Some(&last)) => blame_lines(repo, path, commit.id(), first, last)?,
+            _ => Vec::new(),
+        };
+
+        let mut blobs = self.lock();
+        blobs.clock += 1;
+        let now = blobs.clock;
+        let entry = blobs.entries.entry(key).or_default();
+        entry.last_used = now;
+        for line in fresh {
+            entry.lines.insert(line.line, line);
+        }
+        range.lines = entry
+            .lines
+            .range(start..=end)
+            .map(|(_, l)| l.clone())
+            .collect();
+        evict(&mut blobs);
+        Ok(range)
+    }
+
+    /// A panic mid-update may leave an entry half-filled, so the cache
+    /// starts over.
+    fn lock(&self) -> std::sync::MutexGuard<'_, Blobs> {
+        crate::sync::lock_or_repair(&self.blobs, |blobs| *blobs = Blobs::default())
+    }
+}
+
+fn blame_lines(
+    repo: &git2::Repository,
+    path: &str,
+    newest: git2::Oid,
+    first: u32,
+    last: u32,
+) -> Result<Vec<BlameLine>, String> {
+    let mut opts = git2::BlameOptions::new();
+    opts.newest_commit(newest)
+        .min_line(first as usize)
+        .max_line(last as usize);
+    let blame = repo
+        .blame_file(Path::new(path), Some(&mut opts))
+        .map_err(|e| format!("failed to blame {path}: {e}"))?;
+
+    let mut summaries: HashMap<git2::Oid, String> = HashMap::new();
+    let mut lines = Vec::new();
+    for hunk in blame.iter() {
+        let id = hunk.final_commit_id();
+        let summary = summaries
+            .entry(id)
+            .or_insert_with(|| {
+                repo.find_commit(id)
+                    .map(|c| c.summary().unwrap_or_default().to_string())
+                    .unwrap_or_default()
+            })
+            .clone();
+        let signature = hunk.final_signature();
+        let from = hunk.final_start_line() as u32;
+        for offset in 0..hunk.lines_in_hunk() as u32 {
+            let line = from + offset;
+            if line < first || line > last {
+                continue;
+            }
+            lines.push(BlameLine {
+                line,
+                commit: id.to_string(),
+                short_id: id.to_string()[..7].to_string(),
+                author: signature.name().unwrap_or_default().to_string(),
+                email: signature.email().unwrap_or_default().to_string(),
+                time: signature.when().seconds(),
+                summary: summary.clone(),
+                original_line: hunk.orig_start_line() as u32 + offset,
+            });
+        }
+    }
+    Ok(lines)
+}
+
+/// Authors of `lines` by email, most lines first, for suggesting who
+/// should review a change to them.
+pub fn authors(lines: &[BlameLine]) -> Vec<AuthorShare> {
+    let mut shares: Vec<AuthorShare> = Vec::new();
+    for line in lines {
+        match shares.iter_mut().find(|share| share.email == line.email) {
+            Some(share) => share.lines += 1,
+            None => shares.push(AuthorShare {
+                author: line.author.clone(),
+                email: line.email.clone(),
+                lines: 1,
+            }),
+        }
+    }
+    // Stable, so ties keep the order they first appear in.
+    shares.sort_by_key(|share| std::cmp::Reverse(share.lines));
+    shares
+}
+
+/// Lines as the editor numbers them: a trailing newline doesn't start one.
+fn line_count(bytes: &[u8]) -> u32 {
+    let newlines = bytes.iter().filter(|b| **b == b'\n').count() as u32;
+    match bytes.last() {
+        None => 0,
+        Some(b'\n') => newlines,
+        Some(_) => newlines + 1,
+    }
+}
+
+fn evict(blobs: &mut Blobs) {
+    while blobs.entries.len() > MAX_CACHED_BLOBS {
+        let Some(oldest) = blobs
+            .entries
+            .iter()
+            .min_by_key(|(_, entry)| entry.last_used)
+            .map(|(key, _)| key.clone())
+        else {
+            return;
+        };
+        blobs.entries.remove(&oldest);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn temp_repo(label: &str) -> (crate::test_support::TempDir, git2::Repository) {
+        let (dir, repo) = crate::test_support::init_repo(&format!("blame-{label}"));
+        (dir, repo)
+    }
+
+    fn commit_file(
+        dir: &Path,
+        repo: &git2::Repository,
+        text: &str,
+        author: &str,
+        message: &str,
+    ) -> git2::Oid {
+        fs::write(dir.join("story.txt"), text).expect("write file");
+        let mut index = repo.index().expect("index");
+        index.add_path(Path::new("story.txt")).expect("stage");
+        index.write().expect("write index");
+        let tree = repo
+            .find_tree(index.write_tree().expect("write tree"))
+            .expect("find tree");
+        let sig = git2::Signature::now(author, "test@gitforge.dev").expect("signature");
+        let parent = repo.head().ok().and_then(|h| h.peel_to_commit().ok());
+        let parents: Vec<&git2::Commit> = parent.iter().collect();
+        repo.commit(Some("HEAD"), &sig, &sig, message, &tree, &parents)
+            .expect("commit")
+    }
+
+    #[test]
+    fn range_is_attributed_per_line_and_cached_incrementally() {
+        let (dir, repo) = temp_repo("range");
+        let first = commit_file(&dir, &repo, "a\nb\nc\nd\n", "Ann", "first");
+        let second = commit_file(&dir, &repo, "a\nB\nc\nd\n", "Bob", "second");
+
+        let cache = BlameCache::default();
+        let top = cache
+            .blame_range(&repo, "story.txt", None, 1, 2)
+            .expect("blame top");
+        assert_eq!(top.total_lines, 4);
+        assert_eq!(top.lines.len(), 2);
+        assert_eq!(top.lines[0].commit, first.to_string());
+        assert_eq!(top.lines[1].commit, second.to_string());
+        assert_eq!(top.lines[1].author, "Bob");
+        assert_eq!(top.lines[1].summary, "second");
+        assert_eq!(top.lines[1].email, "test@gitforge.dev");
+
+        let key = (
+            "story.txt".to_string(),
+            git2::Oid::from_str(&top.blob).unwrap(),
+        );
+        assert_eq!(cache.lock().entries[&key].lines.len(), 2);
+
+        let all = cache
+            .blame_range(&repo, "story.txt", None, 0, 100)
+            .expect("blame clamped");
+        assert_eq!((all.start, all.end), (1, 4));
+        assert_eq!(all.lines.len(), 4);
+        assert_eq!(all.lines[3].commit, first.to_string());
+        assert_eq!(cache.lock().entries[&key].lines.len(), 4);
+        // Ann and Bob share an email, so they're one reviewer.
+        let shares = authors(&all.lines);
+        assert_eq!((shares.len(), shares[0].lines), (1, 4));
+    }
+
+    #[test]
+    fn older_revisions_and_missing_files() {
+        let (dir, repo) = temp_repo("revision");
+        let first = commit_file(&dir, &repo, "one\ntwo", "Ann", "first");
+        commit_file(&dir, &repo, "one\n2\nthree\n", "Bob", "second");
+
+        let cache = BlameCache::default();
+        let old = cache
+            .blame_range(&repo, "story.txt", Some(&first.to_string()), 1, 10)
+            .expect("blame old");
+        assert_eq!(old.total_lines, 2);
+        assert!(old.lines.iter().all(|l| l.author == "Ann"));
+
+        assert!(cache.blame_range(&repo, "nope.txt", None, 1, 1).is_err());
+        assert!(cache.blame_range(&repo, "../x", None, 1, 1).is_err());
+    }
+}
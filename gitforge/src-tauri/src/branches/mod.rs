@@ -0,0 +1,661 @@
+pub mod naming;
+
+use serde::{Deserialize, Serialize};
+
+use crate::revcache::RevCache;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LastCommit {
+    pub id: String,
+    pub summary: String,
+    pub author: String,
+    /// Seconds since the unix epoch.
+    pub time: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BranchInfo {
+    /// Shorthand, e.g. `main` or `origin/main`.
+    pub name: String,
+    pub remote: bool,
+    pub is_head: bool,
+    pub upstream: Option<String>,
+    /// Commits on this branch missing from its upstream, and vice versa.
+    pub ahead: usize,
+    pub behind: usize,
+    /// Whether HEAD already contains the branch's tip.
+    pub merged: bool,
+    pub last_commit: Option<LastCommit>,
+}
+
+/// A branch `check_delete` found safe to delete, or that `force` lets go.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Deletion {
+    pub name: String,
+    /// The tip, to recreate the branch from if needed.
+    pub commit: String,
+    pub upstream: Option<String>,
+    /// Whether HEAD contains the tip.
+    pub merged: bool,
+    /// Whether the upstream contains the tip.
+    pub pushed: bool,
+}
+
+impl Deletion {
+    /// Deleting loses no commits, like `git branch -d` allows.
+    pub fn is_safe(&self) -> bool {
+        self.merged || self.pushed
+    }
+}
+
+/// What to do with uncommitted changes when switching.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DirtyMode {
+    /// Fail if tracked files have uncommitted changes.
+    #[default]
+    Refuse,
+    /// Keep changes that don't conflict with the target, like `git switch`.
+    Carry,
+    /// Stash everything (including untracked files) before switching.
+    Stash,
+    /// Throw away local changes to tracked files.
+    Discard,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SwitchResult {
+    pub branch: String,
+    pub previous: Option<String>,
+    /// Stash commit created by [`DirtyMode::Stash`].
+    pub stashed: Option<String>,
+    /// A local branch was created to track a remote one.
+    pub created: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Checkout {
+    /// The branch now checked out; `None` for a detached HEAD.
+    pub branch: Option<String>,
+    pub commit: String,
+    /// The branch checked out before, or its commit if HEAD was detached.
+    pub previous: Option<String>,
+    /// The branch was created, by `create` or to track a remote branch.
+    pub created: bool,
+}
+
+/// Local branches, then remote ones when `include_remote`, each sorted by
+/// name. Ahead/behind counts are memoized in `revs`.
+pub fn branches_list(
+    repo: &git2::Repository,
+    include_remote: bool,
+    revs: &RevCache,
+) -> Result<Vec<BranchInfo>, String> {
+    let filter = if include_remote {
+        None
+    } else {
+        Some(git2::BranchType::Local)
+    };
+    let branches = repo
+        .branches(filter)
+        .map_err(|e| format!("failed to list branches: {e}"))?;
+
+    let mut items = Vec::new();
+    for branch in branches {
+        let (branch, kind) = branch.map_err(|e| format!("failed to read branch: {e}"))?;
+        if let Some(info) = branch_info(repo, &branch, kind, revs)? {
+            items.push(info);
+        }
+    }
+    items.sort_by(|a, b| (a.remote, &a.name).cmp(&(b.remote, &b.name)));
+    Ok(items)
+}
+
+/// Creates local branch `name` at `start`, a revision. Starting from a
+/// remote-tracking branch makes it the upstream, as `git branch` does.
+pub fn branch_create(
+    repo: &git2::Repository,
+    name: &str,
+    start: &str,
+    revs: &RevCache,
+) -> Result<BranchInfo, String> {
+    if repo.find_branch(name, git2::BranchType::Local).is_ok() {
+        return Err(format!("branch '{name}' already exists"));
+    }
+    let commit = repo
+        .revparse_single(start)
+        .and_then(|object| object.peel_to_commit())
+        .map_err(|e| format!("failed to resolve {start}: {e}"))?;
+    let mut branch = repo
+        .branch(name, &commit, false)
+        .map_err(|e| format!("failed to create branch {name}: {e}"))?;
+    if repo.find_branch(start, git2::BranchType::Remote).is_ok() {
+        branch
+            .set_upstream(Some(start))
+            .map_err(|e| format!("failed to track {start}: {e}"))?;
+    }
+    branch_info(repo, &branch, git2::BranchType::Local, revs)?
+        .ok_or_else(|| format!("branch {name} has a non-UTF-8 name"))
+}
+
+/// What deleting local branch `name` would lose. Refuses the current
+/// branch and branches checked out in a linked worktree.
+pub fn check_delete(
+    repo: &git2::Repository,
+    name: &str,
+    revs: &RevCache,
+) -> Result<Deletion, String> {
+    let branch = repo
+        .find_branch(name, git2::BranchType::Local)
+        .map_err(|_| format!("branch '{name}' not found"))?;
+    if branch.is_head() {
+        return Err(format!("branch '{name}' is checked out; switch away first"));
+    }
+    if let Some(worktree) = checked_out_in(repo, name)? {
+        return Err(format!(
+            "branch '{name}' is checked out in worktree {worktree}; remove it first"
+        ));
+    }
+    let tip = branch
+        .get()
+        .target()
+        .ok_or_else(|| format!("branch '{name}' has no target"))?;
+    let upstream = branch.upstream().ok();
+    let upstream_tip = upstream.as_ref().and_then(|u| u.get().target());
+    let head = repo.head().ok().and_then(|h| h.target());
+    Ok(Deletion {
+        name: name.to_string(),
+        commit: tip.to_string(),
+        upstream: upstream
+            .as_ref()
+            .and_then(|u| u.name().ok().flatten())
+            .map(str::to_string),
+        merged: contains(repo, head, tip, revs)?,
+        pushed: contains(repo, upstream_tip, tip, revs)?,
+    })
+}
+
+/// Deletes local branch `name`; see [`check_delete`] first.
+pub fn branch_delete(repo: &git2::Repository, name: &str) -> Result<(), String> {
+    repo.find_branch(name, git2::BranchType::Local)
+        .and_then(|mut branch| branch.delete())
+        .map_err(|e| format!("failed to delete branch {name}: {e}"))
+}
+
+fn branch_info(
+    repo: &git2::Repository,
+    branch: &git2::Branch<'_>,
+    kind: git2::BranchType,
+    revs: &RevCache,
+) -> Result<Option<BranchInfo>, String> {
+    let Some(name) = branch.name().ok().flatten().map(str::to_string) else {
+        return Ok(None);
+    };
+    let remote = kind == git2::BranchType::Remote;
+    if remote && name.ends_with("/HEAD") {
+        return Ok(None);
+    }
+    let upstream = branch.upstream().ok();
+    let upstream_name = upstream
+        .as_ref()
+        .and_then(|u| u.name().ok().flatten())
+        .map(str::to_string);
+    let (ahead, behind) = match (
+        branch.get().target(),
+        upstream.and_then(|u| u.get().target()),
+    ) {
+        (Some(local), Some(upstream)) => revs
+            .ahead_behind(repo, local, upstream)
+            .map_err(|e| format!("failed to compare {name} with upstream: {e}"))?,
+        _ => (0, 0),
+    };
+    let last_commit = branch.get().peel_to_commit().ok().map(|commit| LastCommit {
+        id: commit.id().to_string(),
+        summary: commit.summary().unwrap_or_default().to_string(),
+        author: commit.author().name().unwrap_or_default().to_string(),
+        time: commit.author().when().seconds(),
+    });
+    let merged = match branch.get().target() {
+        Some(tip) => contains(repo, repo.head().ok().and_then(|h| h.target()), tip, revs)?,
+        None => false,
+    };
+
+    Ok(Some(BranchInfo {
+        name,
+        remote,
+        is_head: branch.is_head(),
+        upstream: upstream_name,
+        ahead,
+        behind,
+        merged,
+        last_commit,
+    }))
+}
+
+/// Whether `tip` is `base` or one of its ancestors.
+fn contains(
+    repo: &git2::Repository,
+    base: Option<git2::Oid>,
+    tip: git2::Oid,
+    revs: &RevCache,
+) -> Result<bool, String> {
+    match base {
+        Some(base) if base == tip => Ok(true),
+        Some(base) => Ok(revs.merge_base(repo, base, tip)? == Some(tip)),
+        None => Ok(false),
+    }
+}
+
+/// The linked worktree that has `name` checked out, if any.
+fn checked_out_in(repo: &git2::Repository, name: &str) -> Result<Option<String>, String> {
+    let worktrees = repo
+        .worktrees()
+        .map_err(|e| format!("failed to list worktrees: {e}"))?;
+    for worktree in worktrees.iter().flatten() {
+        let Ok(linked) = repo
+            .find_worktree(worktree)
+            .and_then(|wt| git2::Repository::open_from_worktree(&wt))
+        else {
+            continue;
+        };
+        let branch = linked
+            .head()
+            .ok()
+            .and_then(|h| h.shorthand().map(str::to_string));
+        if branch.as_deref() == Some(name) {
+            return Ok(Some(worktree.to_string()));
+        }
+    }
+    Ok(None)
+}
+
+/// Checks out local branch `name`. A remote-only name such as `feature`
+/// (found as `origin/feature`) gets a tracking branch first.
+pub fn branch_switch(
+    repo: &mut git2::Repository,
+    name: &str,
+    mode: DirtyMode,
+) -> Result<SwitchResult, String> {
+    let previous = repo
+        .head()
+        .ok()
+        .and_then(|h| h.shorthand().map(str::to_string));
+    let (refname, created) = local_branch(repo, name)?;
+
+    let dirty = dirty_files(repo)?;
+    let mut stashed = None;
+    if dirty > 0 {
+        match mode {
+            DirtyMode::Refuse => {
+                return Err(format!(
+                    "working tree has uncommitted changes in {dirty} files; commit, stash, or pick another mode"
+                ))
+            }
+            DirtyMode::Stash => {
+                let signature = repo
+                    .signature()
+                    .or_else(|_| git2::Signature::now("GitForge", "gitforge@localhost"))
+                    .map_err(|e| format!("failed to create signature: {e}"))?;
+                let message = format!("gitforge: switching to {name}");
+                let oid = repo
+                    .stash_save(&signature, &message, Some(git2::StashFlags::INCLUDE_UNTRACKED))
+                    .map_err(|e| format!("failed to stash changes: {e}"))?;
+                stashed = Some(oid.to_string());
+            }
+            DirtyMode::Carry | DirtyMode::Discard => {}
+        }
+    }
+
+    check_out_branch(repo, name, &refname, mode == DirtyMode::Discard)?;
+
+    Ok(SwitchResult {
+        branch: name.to_string(),
+        previous,
+        stashed,
+        created,
+    })
+}
+
+/// Checks out `target`: a branch as [`branch_switch`] does, otherwise any
+/// revision with a detached HEAD. With `create`, `target` is a new branch
+/// started at `start` (default HEAD). Changes to tracked files refuse the
+/// checkout unless `force`, which discards them.
+pub fn checkout(
+    repo: &git2::Repository,
+    target: &str,
+    create: bool,
+    start: Option<&str>,
+    force: bool,
+    revs: &RevCache,
+) -> Result<Checkout, String> {
+    let previous = match repo.head() {
+        Ok(head) if repo.head_detached().unwrap_or(false) => head.target().map(|id| id.to_string()),
+        Ok(head) => head.shorthand().map(str::to_string),
+        Err(_) => None,
+    };
+    let dirty = dirty_paths(repo)?;
+    if !force && !dirty.is_empty() {
+        return Err(format!(
+            "working tree has uncommitted changes in {} files; commit or stash them, or pass force",
+            dirty.len()
+        ));
+    }
+    if create || is_branch(repo, target)? {
+        if let Some(worktree) = checked_out_in(repo, target)? {
+            return Err(format!(
+                "branch '{target}' is checked out in worktree {worktree}"
+            ));
+        }
+        if create {
+            branch_create(repo, target, start.unwrap_or("HEAD"), revs)?;
+        }
+        let (refname, tracked) = local_branch(repo, target)?;
+        check_out_branch(repo, target, &refname, force)?;
+        return Ok(Checkout {
+            branch: Some(target.to_string()),
+            commit: head_commit(repo)?,
+            previous,
+            created: create || tracked,
+        });
+    }
+
+    let commit = repo
+        .revparse_single(target)
+        .and_then(|object| object.peel_to_commit())
+        .map_err(|_| format!("'{target}' is neither a branch nor a commit"))?;
+    check_out_tree(repo, target, commit.as_object(), force)?;
+    repo.set_head_detached(commit.id())
+        .map_err(|e| format!("failed to detach HEAD at {target}: {e}"))?;
+    Ok(Checkout {
+        branch: None,
+        commit: commit.id().to_string(),
+        previous,
+        created: false,
+    })
+}
+
+/// Checks out `refname`, branch `name`, and points HEAD at it.
+fn check_out_branch(
+    repo: &git2::Repository,
+    name: &str,
+    refname: &str,
+    force: bool,
+) -> Result<(), String> {
+    let target = repo
+        .revparse_single(refname)
+        .map_err(|e| format!("failed to resolve {name}: {e}"))?;
+    check_out_tree(repo, name, &target, force)?;
+    repo.set_head(refname)
+        .map_err(|e| format!("failed to switch HEAD to {name}: {e}"))
+}
+
+/// Safe checkouts keep local changes that don't conflict with `target`;
+/// forced ones overwrite them.
+fn check_out_tree(
+    repo: &git2::Repository,
+    name: &str,
+    target: &git2::Object,
+    force: bool,
+) -> Result<(), String> {
+    let mut checkout = git2::build::CheckoutBuilder::new();
+    if force {
+        checkout.force();
+    } else {
+        checkout.safe();
+    }
+    repo.checkout_tree(target, Some(&mut checkout))
+        .map_err(|e| format!("failed to check out {name}: {e}"))
+}
+
+/// Whether `name` is a local branch, or a branch on some remote that
+/// [`branch_switch`] would track.
+fn is_branch(repo: &git2::Repository, name: &str) -> Result<bool, String> {
+    if repo.find_branch(name, git2::BranchType::Local).is_ok() {
+        return Ok(true);
+    }
+    let remotes = repo
+        .remotes()
+        .map_err(|e| format!("failed to list remotes: {e}"))?;
+    Ok(remotes.iter().flatten().any(|remote| {
+        repo.find_branch(&format!("{remote}/{name}"), git2::BranchType::Remote)
+            .is_ok()
+    }))
+}
+
+fn head_commit(repo: &git2::Repository) -> Result<String, String> {
+    repo.head()
+        .and_then(|head| head.peel_to_commit())
+        .map(|commit| commit.id().to_string())
+        .map_err(|e| format!("failed to read HEAD: {e}"))
+}
+
+/// Full ref name of local branch `name`, creating it from a remote branch
+/// of the same name if needed.
+fn local_branch(repo: &git2::Repository, name: &str) -> Result<(String, bool), String> {
+    if let Ok(branch) = repo.find_branch(name, git2::BranchType::Local) {
+        let refname = branch
+            .get()
+            .name()
+            .ok_or_else(|| format!("branch {name} has a non-UTF-8 name"))?;
+        return Ok((refname.to_string(), false));
+    }
+
+    let remotes = repo
+        .remotes()
+        .map_err(|e| format!("failed to list remotes: {e}"))?;
+    for remote in remotes.iter().flatten() {
+        let remote_name = format!("{remote}/{name}");
+        let Ok(remote_branch) = repo.find_branch(&remote_name, git2::BranchType::Remote) else {
+            continue;
+        };
+        let commit = remote_branch
+            .get()
+            .peel_to_commit()
+            .map_err(|e| format!("failed to resolve {remote_name}: {e}"))?;
+        let mut branch = repo
+            .branch(name, &commit, false)
+            .map_err(|e| format!("failed to create branch {name}: {e}"))?;
+        branch
+            .set_upstream(Some(&remote_name))
+            .map_err(|e| format!("failed to track {remote_name}: {e}"))?;
+        return Ok((format!("refs/heads/{name}"), true));
+    }
+
+    Err(format!("branch '{name}' not found"))
+}
+
+/// Tracked files with staged or unstaged changes; untracked files never
+/// block a switch.
+fn dirty_files(repo: &git2::Repository) -> Result<usize, String> {
+    dirty_paths(repo).map(|paths| paths.len())
+}
+
+/// The paths [`dirty_files`] counts.
+pub fn dirty_paths(repo: &git2::Repository) -> Result<Vec<String>, String> {
+    let mut opts = git2::StatusOptions::new();
+    opts.include_untracked(false).include_ignored(false);
+    let statuses = repo
+        .statuses(Some(&mut opts))
+        .map_err(|e| format!("failed to read status: {e}"))?;
+    Ok(statuses
+        .iter()
+        .filter(|entry| entry.status() != git2::Status::CURRENT)
+        .filter_map(|entry| entry.path().map(str::to_string))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::path::Path;
+
+    fn committed_repo(label: &str) -> (crate::test_support::TempDir, git2::Repository) {
+        let (dir, repo) = crate::test_support::init_repo(&format!("branches-{label}"));
+        fs::write(dir.join("README.md"), "base\n").expect("write file");
+        commit_all(&repo, "init");
+        (dir, repo)
+    }
+
+    fn commit_all(repo: &git2::Repository, message: &str) -> git2::Oid {
+        let mut index = repo.index().expect("index");
+        index
+            .add_all(["*"], git2::IndexAddOption::DEFAULT, None)
+            .expect("stage");
+        index.write().expect("write index");
+        let tree = repo
+            .find_tree(index.write_tree().expect("write tree"))
+            .expect("find tree");
+        let sig = git2::Signature::now("GitForge Test", "test@gitforge.dev").expect("signature");
+        let parent = repo.head().ok().and_then(|h| h.peel_to_commit().ok());
+        let parents: Vec<&git2::Commit> = parent.iter().collect();
+        repo.commit(Some("HEAD"), &sig, &sig, message, &tree, &parents)
+            .expect("commit")
+    }
+
+    fn head_branch(repo: &git2::Repository) -> String {
+        repo.head().unwrap().shorthand().unwrap().to_string()
+    }
+
+    #[test]
+    fn list_reports_ahead_behind_against_upstream() {
+        let (dir, repo) = committed_repo("list");
+        let base = repo.head().unwrap().target().unwrap();
+        let main = head_branch(&repo);
+        // Fake a remote-tracking branch one commit behind.
+        repo.remote("origin", "https://example.invalid/repo.git")
+            .expect("remote");
+        repo.reference("refs/remotes/origin/feature", base, true, "fake remote")
+            .expect("remote ref");
+        repo.branch("feature", &repo.find_commit(base).unwrap(), false)
+            .expect("branch")
+            .set_upstream(Some("origin/feature"))
+            .expect("track");
+        repo.set_head("refs/heads/feature").expect("switch");
+        fs::write(dir.join("README.md"), "ahead\n").expect("edit");
+        commit_all(&repo, "ahead");
+
+        let branches = branches_list(&repo, true, &RevCache::default()).expect("list");
+        let feature = branches
+            .iter()
+            .find(|b| b.name == "feature")
+            .expect("feature");
+        assert!(feature.is_head);
+        assert_eq!((feature.ahead, feature.behind), (1, 0));
+        assert_eq!(feature.upstream.as_deref(), Some("origin/feature"));
+        assert_eq!(
+            feature.last_commit.as_ref().map(|c| c.summary.as_str()),
+            Some("ahead")
+        );
+        assert!(branches.iter().any(|b| b.name == main && !b.remote));
+        assert!(branches
+            .iter()
+            .any(|b| b.name == "origin/feature" && b.remote));
+        assert!(branches_list(&repo, false, &RevCache::default())
+            .expect("local only")
+            .iter()
+            .all(|b| !b.remote));
+    }
+
+    #[test]
+    fn switch_refuses_dirty_tree_unless_told_otherwise() {
+        let (dir, mut repo) = committed_repo("switch");
+        let head = repo.head().unwrap().peel_to_commit().unwrap();
+        repo.branch("other", &head, false).expect("branch");
+        drop(head);
+        fs::write(dir.join("README.md"), "local edit\n").expect("edit");
+
+        let err = branch_switch(&mut repo, "other", DirtyMode::Refuse).expect_err("dirty");
+        assert!(err.contains("uncommitted changes in 1 files"), "{err}");
+
+        let switched = branch_switch(&mut repo, "other", DirtyMode::Stash).expect("stash switch");
+        assert!(switched.stashed.is_some());
+        assert_eq!(head_branch(&repo), "other");
+        assert_eq!(
+            fs::read_to_string(Path::new(&dir).join("README.md")).unwrap(),
+            "base\n"
+        );
+    }
+
+    #[test]
+    fn branches_from_remotes_track_them_and_deletes_check_for_lost_commits() {
+        let (dir, repo) = committed_repo("create");
+        let base = repo.head().unwrap().target().unwrap();
+        repo.remote("origin", "https://example.invalid/repo.git")
+            .expect("remote");
+        repo.reference("refs/remotes/origin/main", base, true, "fake remote")
+            .expect("remote ref");
+        let revs = RevCache::default();
+
+        let created = branch_create(&repo, "topic", "origin/main", &revs).expect("create");
+        assert_eq!(created.upstream.as_deref(), Some("origin/main"));
+        assert!(created.merged && !created.is_head);
+        assert!(branch_create(&repo, "topic", "HEAD", &revs).is_err());
+
+        repo.set_head("refs/heads/topic").expect("switch");
+        fs::write(dir.join("README.md"), "topic\n").expect("edit");
+        commit_all(&repo, "topic only");
+        let main = repo.find_commit(base).unwrap();
+        repo.branch("main-copy", &main, false).expect("branch");
+        repo.set_head("refs/heads/main-copy").expect("switch back");
+
+        let deletion = check_delete(&repo, "topic", &revs).expect("check");
+        assert!(!deletion.merged && !deletion.pushed && !deletion.is_safe());
+        assert!(check_delete(&repo, "main-copy", &revs).is_err());
+        branch_delete(&repo, "topic").expect("delete");
+        assert!(repo.find_branch("topic", git2::BranchType::Local).is_err());
+    }
+
+    #[test]
+    fn checkout_switches_creates_and_detaches_but_refuses_dirty_trees() {
+        let (dir, repo) = committed_repo("checkout");
+        let main = head_branch(&repo);
+        let base = repo.head().unwrap().target().unwrap().to_string();
+        let revs = RevCache::default();
+
+        let created = checkout(&repo, "topic", true, None, false, &revs).expect("create");
+        assert_eq!(created.branch.as_deref(), Some("topic"));
+        assert_eq!(created.previous.as_deref(), Some(main.as_str()));
+        assert!(created.created);
+        fs::write(dir.join("README.md"), "topic\n").expect("edit");
+        commit_all(&repo, "topic only");
+
+        let detached = checkout(&repo, &base, false, None, false, &revs).expect("detach");
+        assert_eq!(detached.branch, None);
+        assert_eq!(detached.commit, base);
+        assert!(repo.head_detached().unwrap());
+        assert_eq!(fs::read_to_string(dir.join("README.md")).unwrap(), "base\n");
+
+        fs::write(dir.join("README.md"), "wip\n").expect("dirty");
+        let err = checkout(&repo, "topic", false, None, false, &revs).expect_err("dirty");
+        assert!(err.contains("pass force"), "{err}");
+        assert!(checkout(&repo, "nope", false, None, true, &revs).is_err());
+        let forced = checkout(&repo, "topic", false, None, true, &revs).expect("force");
+        assert_eq!(forced.previous.as_deref(), Some(base.as_str()));
+        assert!(!forced.created);
+        assert_eq!(
+            fs::read_to_string(dir.join("README.md")).unwrap(),
+            "topic\n"
+        );
+    }
+
+    #[test]
+    fn remote_only_branch_gets_a_tracking_branch() {
+        let (_dir, mut repo) = committed_repo("track");
+        let base = repo.head().unwrap().target().unwrap();
+        repo.remote("origin", "https://example.invalid/repo.git")
+            .expect("remote");
+        repo.reference("refs/remotes/origin/topic", base, true, "fake remote")
+            .expect("remote ref");
+
+        let switched = branch_switch(&mut repo, "topic", DirtyMode::Refuse).expect("switch");
+        assert!(switched.created);
+        let branch = repo
+            .find_branch("topic", git2::BranchType::Local)
+            .expect("local topic");
+        assert_eq!(
+            branch.upstream().unwrap().name().unwrap(),
+            Some("origin/topic")
+        );
+    }
+}
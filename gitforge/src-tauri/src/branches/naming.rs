@@ -0,0 +1,190 @@
+/// Allowed branch names, one glob per value, e.g. `feature/*` or
+/// `fix/{ticket}-*`. Without any, every valid name is allowed.
+pub const PATTERN_KEY: &str = "gitforge.branchNames.pattern";
+
+/// Longest slug `suggest` makes from a description.
+const MAX_SLUG: usize = 48;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Policy {
+    pub patterns: Vec<String>,
+}
+
+impl Policy {
+    pub fn load(repo: &git2::Repository) -> Result<Self, String> {
+        let config = repo
+            .config()
+            .map_err(|e| format!("failed to read git config: {e}"))?;
+        let mut patterns = Vec::new();
+        if let Ok(mut entries) = config.multivar(PATTERN_KEY, None) {
+            while let Some(entry) = entries.next() {
+                let entry = entry.map_err(|e| format!("failed to read {PATTERN_KEY}: {e}"))?;
+                if let Some(value) = entry.value().map(str::trim).filter(|v| !v.is_empty()) {
+                    patterns.push(value.to_string());
+                }
+            }
+        }
+        Ok(Self { patterns })
+    }
+
+    pub fn allows(&self, name: &str) -> bool {
+        self.patterns.is_empty()
+            || self
+                .patterns
+                .iter()
+                .any(|pattern| matches(pattern.as_bytes(), name.as_bytes()))
+    }
+
+    /// What `check` does, after refusing names git won't take; for
+    /// branches about to be created.
+    pub fn check_new(&self, name: &str) -> Result<(), String> {
+        if !git2::Branch::name_is_valid(name).unwrap_or(false) {
+            return Err(format!("'{name}' isn't a valid branch name"));
+        }
+        self.check(name)
+    }
+
+    /// Refuses names the patterns don't match, suggesting a compliant one
+    /// made from the rejected name.
+    pub fn check(&self, name: &str) -> Result<(), String> {
+        if self.allows(name) {
+            return Ok(());
+        }
+        let hint = self
+            .suggest_for(name)
+            .map(|suggestion| format!("; try '{suggestion}'"))
+            .unwrap_or_default();
+        Err(format!(
+            "branch '{name}' doesn't match {PATTERN_KEY} ({}){hint}",
+            self.patterns.join(", ")
+        ))
+    }
+
+    /// A compliant name made from a rejected one's words.
+    pub fn suggest_for(&self, name: &str) -> Option<String> {
+        self.suggest(&name.replace(['/', '-', '_'], " "))
+    }
+
+    /// A compliant name for a task description: its slug put in the first
+    /// pattern it fits. `{ticket}` takes a `KEY-123` id from the
+    /// description, so patterns needing one are skipped without it.
+    pub fn suggest(&self, description: &str) -> Option<String> {
+        let ticket = description
+            .split(|c: char| !(c.is_ascii_alphanumeric() || c == '-'))
+            .find(|word| is_ticket(word.as_bytes()));
+        let slug = slug(&match ticket {
+            Some(ticket) => description.replacen(ticket, "", 1),
+            None => description.to_string(),
+        });
+        if self.patterns.is_empty() {
+            return (!slug.is_empty()).then_some(slug);
+        }
+        self.patterns.iter().find_map(|pattern| {
+            if pattern.contains("{ticket}") && ticket.is_none() {
+                return None;
+            }
+            let name = pattern.replace("{ticket}", ticket.unwrap_or_default());
+            let name = match name.split_once('*') {
+                Some((head, tail)) => format!("{head}{slug}{}", tail.replace('*', "")),
+                None => name,
+            };
+            let name = name.trim_end_matches(['-', '/']).to_string();
+            let valid = git2::Branch::name_is_valid(&name).unwrap_or(false);
+            (valid && self.allows(&name)).then_some(name)
+        })
+    }
+}
+
+/// Lowercase words joined by `-`, cut at a word boundary.
+fn slug(text: &str) -> String {
+    let mut slug = String::new();
+    for word in text
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|w| !w.is_empty())
+    {
+        if slug.len() + word.len() + 1 > MAX_SLUG {
+            break;
+        }
+        if !slug.is_empty() {
+            slug.push('-');
+        }
+        slug.push_str(&word.to_ascii_lowercase());
+    }
+    slug
+}
+
+/// `KEY-123`: an uppercase letter, more uppercase letters or digits, a
+/// dash and digits.
+fn is_ticket(word: &[u8]) -> bool {
+    ticket_len(word) == Some(word.len())
+}
+
+fn ticket_len(name: &[u8]) -> Option<usize> {
+    let dash = name.iter().position(|&c| c == b'-')?;
+    let (key, rest) = (&name[..dash], &name[dash + 1..]);
+    let valid_key = key.len() >= 2
+        && key[0].is_ascii_uppercase()
+        && key
+            .iter()
+            .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit());
+    let digits = rest.iter().take_while(|c| c.is_ascii_digit()).count();
+    (valid_key && digits > 0).then_some(dash + 1 + digits)
+}
+
+/// `*` matches any run of characters, `{ticket}` a `KEY-123` id.
+fn matches(pattern: &[u8], name: &[u8]) -> bool {
+    match pattern {
+        [] => name.is_empty(),
+        [b'*', rest @ ..] => (0..=name.len()).any(|i| matches(rest, &name[i..])),
+        _ if pattern.starts_with(b"{ticket}") => {
+            let rest = &pattern[b"{ticket}".len()..];
+            // The id's digits may run on, so try every prefix that's an id.
+            let Some(longest) = ticket_len(name) else {
+                return false;
+            };
+            let dash = name.iter().position(|&c| c == b'-').unwrap_or_default();
+            (dash + 2..=longest).any(|end| matches(rest, &name[end..]))
+        }
+        [c, rest @ ..] => name.first() == Some(c) && matches(rest, &name[1..]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_are_checked_against_patterns_and_suggested_from_tasks() {
+        let policy = Policy {
+            patterns: vec![
+                "feature/{ticket}-*".into(),
+                "goal/*".into(),
+                "release/*".into(),
+            ],
+        };
+        assert!(policy.allows("feature/GF-12-device-login"));
+        assert!(policy.allows("goal/tidy-docs"));
+        assert!(!policy.allows("feature/device-login"));
+        assert!(!policy.allows("feature/gf-12-login"));
+
+        assert_eq!(
+            policy.suggest("GF-12: Add device login (OAuth)").as_deref(),
+            Some("feature/GF-12-add-device-login-oauth")
+        );
+        assert_eq!(
+            policy.suggest("Tidy the docs!").as_deref(),
+            Some("goal/tidy-the-docs")
+        );
+        assert_eq!(
+            policy.check("patch-login").unwrap_err(),
+            "branch 'patch-login' doesn't match gitforge.branchNames.pattern \
+             (feature/{ticket}-*, goal/*, release/*); try 'goal/patch-login'"
+        );
+        assert!(policy.check_new("bad..name").unwrap_err().contains("valid"));
+        assert_eq!(
+            Policy::default().suggest("Fix the thing").as_deref(),
+            Some("fix-the-thing")
+        );
+        assert!(Policy::default().check_new("anything/goes").is_ok());
+    }
+}
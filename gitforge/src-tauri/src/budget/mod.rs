@@ -0,0 +1,119 @@
+use serde::Serialize;
+use std::io;
+
+/// Serialized bytes one list in a response (status entries, diff lines,
+/// graph rows) may take before it is cut short and marked `truncated`, so
+/// a pathological repository can't balloon the daemon while it builds a
+/// reply.
+pub const MAX_RESPONSE_BYTES: usize = 8 * 1024 * 1024;
+
+/// Length of `value` as JSON, measured by serializing into a counter
+/// rather than a buffer or a `serde_json::Value`.
+pub fn serialized_len<T: Serialize + ?Sized>(value: &T) -> usize {
+    let mut counter = Counter(0);
+    // The counter never fails, and every type measured here serializes.
+    let _ = serde_json::to_writer(&mut counter, value);
+    counter.0
+}
+
+struct Counter(usize);
+
+impl io::Write for Counter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0 += buf.len();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Items collected until their serialized size would pass `limit`. The
+/// first item that doesn't fit closes the collection, so what is kept is
+/// always a prefix and callers can resume from `len()`.
+#[derive(Debug)]
+pub struct Bounded<T> {
+    items: Vec<T>,
+    bytes: usize,
+    limit: usize,
+    truncated: bool,
+}
+
+impl<T: Serialize> Bounded<T> {
+    pub fn new(limit: usize) -> Self {
+        Self {
+            items: Vec::new(),
+            bytes: 0,
+            limit,
+            truncated: false,
+        }
+    }
+
+    /// Keeps `item` if it fits; `false` once the budget is spent, so
+    /// producers can stop early.
+    pub fn push(&mut self, item: T) -> bool {
+        if self.truncated {
+            return false;
+        }
+        // One more for the separating comma.
+        let size = serialized_len(&item) + 1;
+        if self.bytes + size > self.limit {
+            self.truncated = true;
+            return false;
+        }
+        self.bytes += size;
+        self.items.push(item);
+        true
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Serialized size of the kept items.
+    pub fn bytes(&self) -> usize {
+        self.bytes
+    }
+
+    /// An item was turned away.
+    pub fn truncated(&self) -> bool {
+        self.truncated
+    }
+
+    pub fn into_vec(self) -> Vec<T> {
+        self.items
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lengths_match_serde_json_output() {
+        let value = serde_json::json!({ "path": "src/ü.rs", "lines": [1, 2, 3] });
+        assert_eq!(
+            serialized_len(&value),
+            serde_json::to_string(&value).unwrap().len()
+        );
+        assert_eq!(serialized_len("a\"b"), 6);
+    }
+
+    #[test]
+    fn collection_closes_at_the_first_item_over_budget() {
+        let mut items = Bounded::new(12);
+        assert!(items.push("abc"));
+        assert!(items.push("def"));
+        assert!(!items.push("longer than the rest"));
+        assert!(!items.push("x"), "nothing is accepted after truncation");
+
+        assert!(items.truncated());
+        assert_eq!(items.bytes(), 12);
+        assert_eq!(items.into_vec(), ["abc", "def"]);
+    }
+}
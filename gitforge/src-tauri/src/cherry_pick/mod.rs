@@ -0,0 +1,255 @@
+//! `git cherry-pick`: copying commits onto HEAD, typically to backport a
+//! fix from one worktree's branch to another's.
+
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Status {
+    Applied,
+    /// Its change is already on HEAD; no commit was made.
+    Empty,
+    /// It doesn't apply; it and the commits after it were left out.
+    Conflicts,
+    NotApplied,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Picked {
+    pub commit: String,
+    pub summary: String,
+    pub status: Status,
+    /// The copy made on HEAD.
+    pub new_commit: Option<String>,
+    /// Paths it conflicts on.
+    pub files: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CherryPick {
+    pub previous_head: String,
+    pub head: String,
+    /// Every requested commit, in the order given.
+    pub commits: Vec<Picked>,
+    pub applied: usize,
+}
+
+impl CherryPick {
+    pub fn conflicted(&self) -> Option<&Picked> {
+        self.commits.iter().find(|c| c.status == Status::Conflicts)
+    }
+}
+
+/// Applies `commits` onto HEAD in order, keeping their authors and
+/// messages. Each is applied in memory first: the first that conflicts is
+/// reported with its paths and, like every commit after it, left out,
+/// while the ones before it stay applied. `mainline` picks the parent a
+/// merge commit is diffed against; `record_origin` appends `(cherry
+/// picked from commit ...)` as `git cherry-pick -x` does. Uncommitted
+/// changes to tracked files refuse it.
+pub fn cherry_pick(
+    repo: &git2::Repository,
+    commits: &[String],
+    mainline: Option<u32>,
+    record_origin: bool,
+) -> Result<CherryPick, String> {
+    if commits.is_empty() {
+        return Err("no commits to cherry-pick".to_string());
+    }
+    let picks = commits
+        .iter()
+        .map(|rev| {
+            repo.revparse_single(rev)
+                .and_then(|object| object.peel_to_commit())
+                .map_err(|e| format!("unknown commit '{rev}': {e}"))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    if let Some(merge) = picks
+        .iter()
+        .find(|c| c.parent_count() > 1 && mainline.is_none())
+    {
+        return Err(format!(
+            "{} is a merge; pass mainline to pick the parent to diff against",
+            merge.id()
+        ));
+    }
+    let dirty = crate::branches::dirty_paths(repo)?;
+    if !dirty.is_empty() {
+        return Err(format!(
+            "working tree has uncommitted changes in {} files; commit or stash them first",
+            dirty.len()
+        ));
+    }
+    let previous = repo
+        .head()
+        .and_then(|head| head.peel_to_commit())
+        .map_err(|e| format!("HEAD has no commit to cherry-pick onto: {e}"))?;
+    let signature = repo
+        .signature()
+        .or_else(|_| git2::Signature::now("GitForge", "gitforge@localhost"))
+        .map_err(|e| format!("failed to create signature: {e}"))?;
+
+    let mut tip = previous.clone();
+    let mut picked = Vec::with_capacity(picks.len());
+    let mut stopped = false;
+    for commit in &picks {
+        let mut entry = Picked {
+            commit: commit.id().to_string(),
+            summary: commit.summary().unwrap_or("").to_string(),
+            status: Status::NotApplied,
+            new_commit: None,
+            files: Vec::new(),
+        };
+        if stopped {
+            picked.push(entry);
+            continue;
+        }
+        let mainline = if commit.parent_count() > 1 {
+            mainline.unwrap_or(1)
+        } else {
+            0
+        };
+        let mut index = repo
+            .cherrypick_commit(commit, &tip, mainline, None)
+            .map_err(|e| format!("failed to apply {}: {e}", commit.id()))?;
+        if index.has_conflicts() {
+            entry.status = Status::Conflicts;
+            entry.files = crate::rebase_plan::conflicts(&index);
+            stopped = true;
+            picked.push(entry);
+            continue;
+        }
+        let tree = index
+            .write_tree_to(repo)
+            .and_then(|id| repo.find_tree(id))
+            .map_err(|e| format!("failed to write tree: {e}"))?;
+        if tree.id() == tip.tree_id() {
+            entry.status = Status::Empty;
+            picked.push(entry);
+            continue;
+        }
+        let mut message = commit.message().unwrap_or("").to_string();
+        if record_origin {
+            if !message.ends_with('\n') {
+                message.push('\n');
+            }
+            message.push_str(&format!("\n(cherry picked from commit {})\n", commit.id()));
+        }
+        let id = repo
+            .commit(None, &commit.author(), &signature, &message, &tree, &[&tip])
+            .map_err(|e| format!("failed to write commit: {e}"))?;
+        tip = repo
+            .find_commit(id)
+            .map_err(|e| format!("failed to read commit {id}: {e}"))?;
+        entry.status = Status::Applied;
+        entry.new_commit = Some(id.to_string());
+        picked.push(entry);
+    }
+
+    if tip.id() != previous.id() {
+        let reflog = format!("gitforge: cherry-pick {} commits", picked.len());
+        advance_head(repo, &tip, &reflog)?;
+    }
+    Ok(CherryPick {
+        previous_head: previous.id().to_string(),
+        head: tip.id().to_string(),
+        applied: picked
+            .iter()
+            .filter(|c| c.status == Status::Applied)
+            .count(),
+        commits: picked,
+    })
+}
+
+/// Checks out `commit`, refusing to overwrite local edits, then moves the
+/// current branch, or a detached HEAD, to it.
+pub(crate) fn advance_head(
+    repo: &git2::Repository,
+    commit: &git2::Commit,
+    reflog: &str,
+) -> Result<(), String> {
+    let mut checkout = git2::build::CheckoutBuilder::new();
+    checkout.safe();
+    repo.checkout_tree(commit.as_object(), Some(&mut checkout))
+        .map_err(|e| format!("failed to check out the result: {e}"))?;
+    match repo.head().ok().filter(|head| head.is_branch()) {
+        Some(mut head) => head.set_target(commit.id(), reflog).map(|_| ()),
+        None => repo.set_head_detached(commit.id()),
+    }
+    .map_err(|e| format!("failed to move HEAD: {e}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::path::Path;
+
+    #[test]
+    fn picks_in_order_skipping_empty_and_stopping_at_conflicts() {
+        let (dir, repo) = crate::test_support::init_repo("cherry-pick");
+        let sig = git2::Signature::now("GitForge Test", "test@gitforge.dev").expect("sig");
+        let commit = |path: &str, text: &str, message: &str| {
+            fs::write(dir.join(path), text).expect("write");
+            let mut index = repo.index().expect("index");
+            index.add_path(Path::new(path)).expect("add");
+            index.write().expect("write index");
+            let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+            let parent = repo.head().ok().and_then(|h| h.peel_to_commit().ok());
+            let parents: Vec<&git2::Commit> = parent.iter().collect();
+            repo.commit(Some("HEAD"), &sig, &sig, message, &tree, &parents)
+                .expect("commit")
+                .to_string()
+        };
+        commit("a.txt", "base\n", "base");
+        let base = repo.head().unwrap().peel_to_commit().unwrap();
+        let main = repo.head().unwrap().name().unwrap().to_string();
+        repo.branch("fixes", &base, false).expect("branch");
+        repo.set_head("refs/heads/fixes").expect("switch");
+        let fix = commit("fix.txt", "fix\n", "Fix the thing");
+        let doc = commit("doc.txt", "doc\n", "Document it");
+        let clash = commit("a.txt", "fixes\n", "Edit a");
+        let later = commit("later.txt", "later\n", "Later");
+
+        repo.set_head(&main).expect("back to main");
+        repo.checkout_head(Some(git2::build::CheckoutBuilder::new().force()))
+            .expect("checkout");
+        commit("doc.txt", "doc\n", "Same doc on main");
+        commit("a.txt", "main\n", "Edit a on main");
+
+        let picked = cherry_pick(
+            &repo,
+            &[fix.clone(), doc.clone(), clash.clone(), later.clone()],
+            None,
+            true,
+        )
+        .expect("cherry-pick");
+        let statuses: Vec<Status> = picked.commits.iter().map(|c| c.status).collect();
+        assert_eq!(
+            statuses,
+            [
+                Status::Applied,
+                Status::Empty,
+                Status::Conflicts,
+                Status::NotApplied
+            ]
+        );
+        assert_eq!(picked.applied, 1);
+        assert_eq!(
+            picked.conflicted().map(|c| c.files.clone()),
+            Some(vec!["a.txt".to_string()])
+        );
+        let head = repo.head().unwrap().peel_to_commit().unwrap();
+        assert_eq!(Some(head.id().to_string()), picked.commits[0].new_commit);
+        assert!(head
+            .message()
+            .unwrap()
+            .ends_with(&format!("(cherry picked from commit {fix})\n")));
+        assert!(dir.join("fix.txt").exists() && !dir.join("later.txt").exists());
+        assert_eq!(fs::read_to_string(dir.join("a.txt")).unwrap(), "main\n");
+
+        fs::write(dir.join("a.txt"), "dirty\n").expect("dirty");
+        assert!(cherry_pick(&repo, &[later], None, false).is_err_and(|e| e.contains("uncommitted")));
+        assert!(cherry_pick(&repo, &["nope".to_string()], None, false).is_err());
+    }
+}
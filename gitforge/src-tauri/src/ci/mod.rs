@@ -0,0 +1,669 @@
+use serde_json::{json, Map, Value};
+use std::time::Duration;
+
+use crate::auth::{self, github, Forge};
+use crate::store::CheckRecord;
+
+/// Git config naming the CI to trigger: `github`, `gitlab`, or `webhook`.
+pub const KIND_KEY: &str = "gitforge.ci.kind";
+/// GitLab instance (default `https://gitlab.com`) or the webhook to POST to.
+pub const URL_KEY: &str = "gitforge.ci.url";
+/// GitHub Actions workflow file or id, e.g. `ci.yml`.
+pub const WORKFLOW_KEY: &str = "gitforge.ci.workflow";
+/// GitHub `owner/name` (default: the `origin` remote's) or GitLab project
+/// path or id.
+pub const REPO_KEY: &str = "gitforge.ci.repo";
+/// Environment variable holding the API token. GitHub falls back to the
+/// `gitforge auth login` token.
+pub const TOKEN_ENV_KEY: &str = "gitforge.ci.tokenEnv";
+
+pub const GITLAB_URL: &str = "https://gitlab.com";
+
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    GitHub,
+    GitLab,
+    Webhook,
+}
+
+impl Kind {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "github" => Some(Kind::GitHub),
+            "gitlab" => Some(Kind::GitLab),
+            "webhook" => Some(Kind::Webhook),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Kind::GitHub => "github",
+            Kind::GitLab => "gitlab",
+            Kind::Webhook => "webhook",
+        }
+    }
+
+    fn default_token_env(self) -> &'static str {
+        match self {
+            Kind::GitHub => "GITFORGE_CI_GITHUB_TOKEN",
+            Kind::GitLab => "GITFORGE_GITLAB_TOKEN",
+            Kind::Webhook => "GITFORGE_CI_WEBHOOK_TOKEN",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CiConfig {
+    pub kind: Kind,
+    /// Without a trailing slash.
+    pub url: Option<String>,
+    pub workflow: Option<String>,
+    pub repo: Option<String>,
+    pub token_env: String,
+}
+
+impl CiConfig {
+    /// The configured CI, or `None` when [`KIND_KEY`] is unset.
+    pub fn from_config(config: &git2::Config) -> Result<Option<Self>, String> {
+        let read = |key: &str| match config.get_string(key) {
+            Ok(value) if value.trim().is_empty() => Ok(None),
+            Ok(value) => Ok(Some(value.trim().to_string())),
+            Err(e) if e.code() == git2::ErrorCode::NotFound => Ok(None),
+            Err(e) => Err(format!("invalid {key}: {e}")),
+        };
+        let Some(kind) = read(KIND_KEY)? else {
+            return Ok(None);
+        };
+        let kind = Kind::parse(&kind).ok_or_else(|| {
+            format!("invalid {KIND_KEY} {kind:?} (expected github, gitlab, or webhook)")
+        })?;
+        let url = read(URL_KEY)?.map(|url| url.trim_end_matches('/').to_string());
+        let workflow = read(WORKFLOW_KEY)?;
+        let repo = read(REPO_KEY)?;
+        match kind {
+            Kind::GitHub if workflow.is_none() => {
+                return Err(format!("{WORKFLOW_KEY} must name the workflow to dispatch"))
+            }
+            Kind::GitLab if repo.is_none() => {
+                return Err(format!("{REPO_KEY} must name the GitLab project"))
+            }
+            Kind::Webhook if url.is_none() => {
+                return Err(format!("{URL_KEY} must name the webhook"))
+            }
+            _ => {}
+        }
+        Ok(Some(Self {
+            kind,
+            url,
+            workflow,
+            repo,
+            token_env: read(TOKEN_ENV_KEY)?.unwrap_or_else(|| kind.default_token_env().to_string()),
+        }))
+    }
+}
+
+/// The HTTP calls the providers need, so they can be driven without a
+/// network. Error statuses with a JSON body are replies too; empty replies
+/// (GitHub answers dispatches with 204) come back as `null`.
+pub trait Transport {
+    fn get_json(&self, url: &str, auth: Option<&str>) -> Result<Value, String>;
+    fn post_json(&self, url: &str, auth: Option<&str>, body: &Value) -> Result<Value, String>;
+}
+
+pub struct Http;
+
+impl Transport for Http {
+    fn get_json(&self, url: &str, auth: Option<&str>) -> Result<Value, String> {
+        let mut request = ureq::get(url)
+            .set("Accept", "application/json")
+            .set("User-Agent", "gitforge")
+            .timeout(TIMEOUT);
+        if let Some(auth) = auth {
+            request = request.set("Authorization", auth);
+        }
+        json_reply(url, request.call())
+    }
+
+    fn post_json(&self, url: &str, auth: Option<&str>, body: &Value) -> Result<Value, String> {
+        let mut request = ureq::post(url)
+            .set("Accept", "application/json")
+            .set("User-Agent", "gitforge")
+            .timeout(TIMEOUT);
+        if let Some(auth) = auth {
+            request = request.set("Authorization", auth);
+        }
+        json_reply(url, request.send_json(body))
+    }
+}
+
+fn json_reply(url: &str, reply: Result<ureq::Response, ureq::Error>) -> Result<Value, String> {
+    let (code, response) = match reply {
+        Ok(response) => (None, response),
+        Err(ureq::Error::Status(code, response)) => (Some(code), response),
+        Err(e) => return Err(format!("failed to reach {url}: {e}")),
+    };
+    let text = response
+        .into_string()
+        .map_err(|e| format!("failed to read the response from {url}: {e}"))?;
+    if text.trim().is_empty() {
+        return match code {
+            Some(code) => Err(format!("{url} answered HTTP {code}")),
+            None => Ok(Value::Null),
+        };
+    }
+    serde_json::from_str(&text).map_err(|e| match code {
+        Some(code) => format!("{url} answered HTTP {code}"),
+        None => format!("invalid response from {url}: {e}"),
+    })
+}
+
+/// Where a CI run stands, in GitHub's terms: `status` is `queued`,
+/// `in_progress`, or `completed`, and `conclusion` is set once completed.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Run {
+    pub status: String,
+    pub conclusion: Option<String>,
+    /// Id to poll the run by; for webhooks, the `status_url` they answered
+    /// with.
+    pub run_id: Option<String>,
+    pub url: Option<String>,
+}
+
+/// A configured CI with its repository and credentials resolved.
+pub struct Ci {
+    config: CiConfig,
+    /// GitHub `owner/name` or GitLab project.
+    repo: Option<String>,
+    http: Box<dyn Transport + Send + Sync>,
+    auth: Option<String>,
+}
+
+impl Ci {
+    /// The CI configured for `repo`, or `None` when there is none. Fails
+    /// when a required token is missing, naming where it was looked for.
+    pub fn open(
+        repo: &git2::Repository,
+        http: Box<dyn Transport + Send + Sync>,
+    ) -> Result<Option<Self>, String> {
+        let config = repo
+            .config()
+            .map_err(|e| format!("failed to read git config: {e}"))?;
+        let Some(config) = CiConfig::from_config(&config)? else {
+            return Ok(None);
+        };
+        let mut token = std::env::var(&config.token_env)
+            .ok()
+            .map(|token| token.trim().to_string())
+            .filter(|token| !token.is_empty());
+        let mut target = config.repo.clone();
+        if config.kind == Kind::GitHub {
+            if token.is_none() {
+                token = auth::token(Forge::GitHub)?.map(|token| token.secret);
+            }
+            if target.is_none() {
+                let origin = repo
+                    .find_remote("origin")
+                    .map_err(|e| format!("{REPO_KEY} is unset and there is no origin: {e}"))?;
+                target = origin.url().and_then(github::repo_slug);
+                if target.is_none() {
+                    return Err(format!(
+                        "{REPO_KEY} is unset and origin is not a GitHub repository"
+                    ));
+                }
+            }
+        }
+        if token.is_none() && config.kind != Kind::Webhook {
+            return Err(format!(
+                "no {} CI token; set {}",
+                config.kind.as_str(),
+                config.token_env
+            ));
+        }
+        Ok(Some(Self::new(config, target, http, token)))
+    }
+
+    pub fn new(
+        config: CiConfig,
+        repo: Option<String>,
+        http: Box<dyn Transport + Send + Sync>,
+        token: Option<String>,
+    ) -> Self {
+        Self {
+            config,
+            repo,
+            http,
+            auth: token.map(|token| format!("Bearer {token}")),
+        }
+    }
+
+    pub fn kind(&self) -> Kind {
+        self.config.kind
+    }
+
+    /// Starts a run for `branch`. `inputs` become workflow inputs, pipeline
+    /// variables, or the webhook's `inputs`.
+    pub fn trigger(
+        &self,
+        branch: &str,
+        commit: Option<&str>,
+        pr_id: Option<i64>,
+        inputs: &Map<String, Value>,
+    ) -> Result<Run, String> {
+        let auth = self.auth.as_deref();
+        match self.config.kind {
+            Kind::GitHub => {
+                let (slug, workflow) = self.github_target();
+                let url = format!(
+                    "{}/repos/{slug}/actions/workflows/{}/dispatches",
+                    github::API_URL,
+                    encode(workflow)
+                );
+                let reply =
+                    self.http
+                        .post_json(&url, auth, &json!({ "ref": branch, "inputs": inputs }))?;
+                if let Some(message) = reply.get("message").and_then(Value::as_str) {
+                    return Err(format!("GitHub did not dispatch {workflow}: {message}"));
+                }
+                // Dispatches don't return the run; `poll` finds it later.
+                Ok(Run {
+                    status: "queued".to_string(),
+                    url: Some(format!(
+                        "https://github.com/{slug}/actions/workflows/{workflow}"
+                    )),
+                    ..Run::default()
+                })
+            }
+            Kind::GitLab => {
+                let variables: Vec<Value> = inputs
+                    .iter()
+                    .map(|(key, value)| json!({ "key": key, "value": plain(value) }))
+                    .collect();
+                let url = format!("{}/pipeline", self.gitlab_project());
+                let reply = self.http.post_json(
+                    &url,
+                    auth,
+                    &json!({ "ref": branch, "variables": variables }),
+                )?;
+                gitlab_run(&reply)
+                    .ok_or_else(|| format!("GitLab did not create a pipeline: {}", message(&reply)))
+            }
+            Kind::Webhook => {
+                let url = self.config.url.as_deref().unwrap_or_default();
+                let body = json!({
+                    "branch": branch,
+                    "commit": commit,
+                    "pr_id": pr_id,
+                    "inputs": inputs,
+                });
+                let reply = self.http.post_json(url, auth, &body)?;
+                Ok(webhook_run(&reply))
+            }
+        }
+    }
+
+    /// The run behind `check` as it stands now; `None` when there is
+    /// nothing newer to report (a GitHub dispatch whose run hasn't started,
+    /// or a webhook without a `status_url`).
+    pub fn poll(&self, check: &CheckRecord) -> Result<Option<Run>, String> {
+        let auth = self.auth.as_deref();
+        match (self.config.kind, check.run_id.as_deref()) {
+            (Kind::GitHub, Some(id)) => {
+                let (slug, _) = self.github_target();
+                let url = format!("{}/repos/{slug}/actions/runs/{id}", github::API_URL);
+                let reply = self.http.get_json(&url, auth)?;
+                github_run(&reply)
+                    .map(Some)
+                    .ok_or_else(|| format!("GitHub did not return run {id}: {}", message(&reply)))
+            }
+            (Kind::GitHub, None) => {
+                let (slug, workflow) = self.github_target();
+                let url = format!(
+                    "{}/repos/{slug}/actions/workflows/{}/runs?event=workflow_dispatch&branch={}&per_page=10",
+                    github::API_URL,
+                    encode(workflow),
+                    encode(&check.branch)
+                );
+                let reply = self.http.get_json(&url, auth)?;
+                let runs = reply
+                    .get("workflow_runs")
+                    .and_then(Value::as_array)
+                    .ok_or_else(|| format!("GitHub did not list runs: {}", message(&reply)))?;
+                // Runs are newest first; the oldest one started since the
+                // dispatch is ours.
+                let since = iso_timestamp(&check.created_at);
+                Ok(runs
+                    .iter()
+                    .rev()
+                    .find(|run| {
+                        run.get("created_at")
+                            .and_then(Value::as_str)
+                            .is_some_and(|created| created >= since.as_str())
+                    })
+                    .and_then(github_run))
+            }
+            (Kind::GitLab, Some(id)) => {
+                let url = format!("{}/pipelines/{}", self.gitlab_project(), encode(id));
+                let reply = self.http.get_json(&url, auth)?;
+                gitlab_run(&reply).map(Some).ok_or_else(|| {
+                    format!("GitLab did not return pipeline {id}: {}", message(&reply))
+                })
+            }
+            (Kind::Webhook, Some(status_url)) => {
+                let reply = self.http.get_json(status_url, auth)?;
+                let mut run = webhook_run(&reply);
+                run.run_id = Some(status_url.to_string());
+                Ok(Some(run))
+            }
+            (Kind::GitLab | Kind::Webhook, None) => Ok(None),
+        }
+    }
+
+    fn github_target(&self) -> (&str, &str) {
+        (
+            self.repo.as_deref().unwrap_or_default(),
+            self.config.workflow.as_deref().unwrap_or_default(),
+        )
+    }
+
+    fn gitlab_project(&self) -> String {
+        let base = self.config.url.as_deref().unwrap_or(GITLAB_URL);
+        let project = self.repo.as_deref().unwrap_or_default();
+        format!("{base}/api/v4/projects/{}", encode(project))
+    }
+}
+
+fn github_run(run: &Value) -> Option<Run> {
+    let id = run.get("id").and_then(Value::as_u64)?;
+    let status = match run.get("status").and_then(Value::as_str) {
+        Some("completed") => "completed",
+        Some("in_progress") => "in_progress",
+        _ => "queued",
+    };
+    Some(Run {
+        status: status.to_string(),
+        conclusion: run
+            .get("conclusion")
+            .and_then(Value::as_str)
+            .map(str::to_string),
+        run_id: Some(id.to_string()),
+        url: run
+            .get("html_url")
+            .and_then(Value::as_str)
+            .map(str::to_string),
+    })
+}
+
+fn gitlab_run(pipeline: &Value) -> Option<Run> {
+    let id = pipeline.get("id").and_then(Value::as_u64)?;
+    let (status, conclusion) = match pipeline.get("status").and_then(Value::as_str) {
+        Some("running") => ("in_progress", None),
+        Some("success") => ("completed", Some("success")),
+        Some("failed") => ("completed", Some("failure")),
+        Some("canceled") => ("completed", Some("cancelled")),
+        Some("skipped") => ("completed", Some("skipped")),
+        // created, pending, manual, scheduled, ...
+        _ => ("queued", None),
+    };
+    Some(Run {
+        status: status.to_string(),
+        conclusion: conclusion.map(str::to_string),
+        run_id: Some(id.to_string()),
+        url: pipeline
+            .get("web_url")
+            .and_then(Value::as_str)
+            .map(str::to_string),
+    })
+}
+
+/// A webhook may answer `{status, conclusion, url, status_url}`. Without a
+/// `status_url` there is nothing to follow, so an accepted trigger counts
+/// as completed unless it says otherwise.
+fn webhook_run(reply: &Value) -> Run {
+    let text = |key: &str| reply.get(key).and_then(Value::as_str).map(str::to_string);
+    let status_url = text("status_url");
+    let status = text("status").unwrap_or_else(|| {
+        if status_url.is_some() {
+            "queued"
+        } else {
+            "completed"
+        }
+        .to_string()
+    });
+    let conclusion = text("conclusion")
+        .or_else(|| (status == "completed" && status_url.is_none()).then(|| "accepted".into()));
+    Run {
+        status,
+        conclusion,
+        run_id: status_url,
+        url: text("url"),
+    }
+}
+
+fn message(reply: &Value) -> String {
+    reply
+        .get("message")
+        .or_else(|| reply.get("error"))
+        .map(|message| match message {
+            Value::String(text) => text.clone(),
+            other => other.to_string(),
+        })
+        .unwrap_or_else(|| "unexpected response".to_string())
+}
+
+/// Pipeline variables are strings; other JSON values are sent as JSON.
+fn plain(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// Percent-encodes everything but RFC 3986 unreserved characters, for
+/// path segments and query values.
+fn encode(text: &str) -> String {
+    let mut encoded = String::with_capacity(text.len());
+    for byte in text.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    encoded
+}
+
+/// `YYYY-MM-DD HH:MM:SS` (UTC, as the store writes it) in GitHub's ISO form.
+fn iso_timestamp(stored: &str) -> String {
+    format!("{}Z", stored.replacen(' ', "T", 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    /// A request's method, URL, and body.
+    type Sent = (&'static str, String, Value);
+
+    /// Answers from a script, recording what was sent.
+    #[derive(Default)]
+    struct Scripted {
+        replies: Mutex<Vec<Value>>,
+        sent: Mutex<Vec<Sent>>,
+    }
+
+    impl Transport for Arc<Scripted> {
+        fn get_json(&self, url: &str, _auth: Option<&str>) -> Result<Value, String> {
+            self.sent
+                .lock()
+                .unwrap()
+                .push(("GET", url.to_string(), Value::Null));
+            Ok(self.replies.lock().unwrap().remove(0))
+        }
+
+        fn post_json(&self, url: &str, _auth: Option<&str>, body: &Value) -> Result<Value, String> {
+            self.sent
+                .lock()
+                .unwrap()
+                .push(("POST", url.to_string(), body.clone()));
+            Ok(self.replies.lock().unwrap().remove(0))
+        }
+    }
+
+    fn scripted(kind: Kind, repo: Option<&str>, replies: Vec<Value>) -> (Ci, Arc<Scripted>) {
+        let http = Arc::new(Scripted {
+            replies: Mutex::new(replies),
+            ..Scripted::default()
+        });
+        let config = CiConfig {
+            kind,
+            url: (kind == Kind::Webhook).then(|| "https://ci.example.com/hook".to_string()),
+            workflow: Some("ci.yml".to_string()),
+            repo: None,
+            token_env: kind.default_token_env().to_string(),
+        };
+        let ci = Ci::new(
+            config,
+            repo.map(str::to_string),
+            Box::new(Arc::clone(&http)),
+            Some("t0ken".to_string()),
+        );
+        (ci, http)
+    }
+
+    fn check(run_id: Option<&str>) -> CheckRecord {
+        CheckRecord {
+            id: 1,
+            pr_id: 1,
+            provider: "github".to_string(),
+            branch: "feature/x".to_string(),
+            status: "queued".to_string(),
+            conclusion: None,
+            run_id: run_id.map(str::to_string),
+            url: None,
+            created_at: "2026-10-14 12:00:00".to_string(),
+            updated_at: "2026-10-14 12:00:00".to_string(),
+        }
+    }
+
+    #[test]
+    fn config_requires_what_each_provider_needs() {
+        let (_dir, repo) = crate::test_support::init_repo("ci-config");
+        let mut config = repo.config().expect("config");
+        assert_eq!(CiConfig::from_config(&config), Ok(None));
+
+        config.set_str(KIND_KEY, "github").expect("kind");
+        let err = CiConfig::from_config(&config).unwrap_err();
+        assert!(err.contains(WORKFLOW_KEY), "{err}");
+        config.set_str(WORKFLOW_KEY, "ci.yml").expect("workflow");
+        let github = CiConfig::from_config(&config)
+            .expect("github")
+            .expect("set");
+        assert_eq!(github.token_env, "GITFORGE_CI_GITHUB_TOKEN");
+
+        config.set_str(KIND_KEY, "gitlab").expect("kind");
+        let err = CiConfig::from_config(&config).unwrap_err();
+        assert!(err.contains(REPO_KEY), "{err}");
+        config.set_str(KIND_KEY, "jenkins").expect("kind");
+        assert!(CiConfig::from_config(&config).is_err());
+    }
+
+    #[test]
+    fn github_dispatches_then_finds_and_follows_the_run() {
+        let (ci, http) = scripted(
+            Kind::GitHub,
+            Some("o/r"),
+            vec![
+                Value::Null,
+                json!({ "workflow_runs": [
+                    { "id": 9, "status": "in_progress", "created_at": "2026-10-14T12:00:05Z" },
+                    { "id": 8, "status": "completed", "created_at": "2026-10-14T11:59:00Z" }
+                ]}),
+                json!({ "id": 9, "status": "completed", "conclusion": "failure",
+                        "html_url": "https://github.com/o/r/actions/runs/9" }),
+            ],
+        );
+        let inputs = json!({ "suite": "full" }).as_object().cloned().unwrap();
+        let run = ci
+            .trigger("feature/x", Some("abc"), Some(1), &inputs)
+            .expect("dispatch");
+        assert_eq!(
+            (run.status.as_str(), run.run_id.as_deref()),
+            ("queued", None)
+        );
+
+        let found = ci.poll(&check(None)).expect("poll").expect("run found");
+        assert_eq!(found.run_id.as_deref(), Some("9"));
+        assert_eq!(found.status, "in_progress");
+        let done = ci.poll(&check(Some("9"))).expect("poll").expect("run");
+        assert_eq!(done.conclusion.as_deref(), Some("failure"));
+
+        let sent = http.sent.lock().unwrap();
+        assert!(sent[0]
+            .1
+            .ends_with("/repos/o/r/actions/workflows/ci.yml/dispatches"));
+        assert_eq!(sent[0].2, json!({ "ref": "feature/x", "inputs": inputs }));
+        assert!(sent[1].1.contains("branch=feature%2Fx"), "{}", sent[1].1);
+        assert!(sent[2].1.ends_with("/actions/runs/9"));
+    }
+
+    #[test]
+    fn gitlab_pipelines_and_webhooks_map_their_statuses() {
+        let (ci, http) = scripted(
+            Kind::GitLab,
+            Some("team/app"),
+            vec![
+                json!({ "id": 77, "status": "created", "web_url": "https://gitlab.com/p/77" }),
+                json!({ "id": 77, "status": "canceled" }),
+                json!({ "message": { "base": ["Reference not found"] } }),
+            ],
+        );
+        let inputs = json!({ "DEPLOY": true }).as_object().cloned().unwrap();
+        let run = ci.trigger("main", None, None, &inputs).expect("pipeline");
+        assert_eq!(run.run_id.as_deref(), Some("77"));
+        assert_eq!(run.status, "queued");
+        let done = ci.poll(&check(Some("77"))).expect("poll").expect("run");
+        assert_eq!(
+            (done.status.as_str(), done.conclusion.as_deref()),
+            ("completed", Some("cancelled"))
+        );
+        let err = ci.trigger("nope", None, None, &Map::new()).unwrap_err();
+        assert!(err.contains("Reference not found"), "{err}");
+        {
+            let sent = http.sent.lock().unwrap();
+            assert!(sent[0].1.ends_with("/api/v4/projects/team%2Fapp/pipeline"));
+            assert_eq!(
+                sent[0].2["variables"][0],
+                json!({ "key": "DEPLOY", "value": "true" })
+            );
+        }
+
+        let (hook, _) = scripted(
+            Kind::Webhook,
+            None,
+            vec![
+                json!({}),
+                json!({ "status_url": "https://ci.example.com/runs/5" }),
+                json!({ "status": "completed", "conclusion": "success" }),
+            ],
+        );
+        let fire_and_forget = hook
+            .trigger("main", Some("abc"), Some(1), &Map::new())
+            .expect("hook");
+        assert_eq!(fire_and_forget.conclusion.as_deref(), Some("accepted"));
+        assert_eq!(hook.poll(&check(None)).expect("poll"), None);
+        let followed = hook
+            .trigger("main", Some("abc"), Some(1), &Map::new())
+            .expect("hook");
+        assert_eq!(followed.status, "queued");
+        let done = hook
+            .poll(&check(followed.run_id.as_deref()))
+            .expect("poll")
+            .expect("run");
+        assert_eq!(done.conclusion.as_deref(), Some("success"));
+    }
+}
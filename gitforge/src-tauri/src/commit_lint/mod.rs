@@ -0,0 +1,288 @@
+use crate::release::Change;
+use crate::trailers;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// The rule set, relative to the working tree root.
+pub const CONFIG_PATH: &str = ".gitforge/commit-lint.toml";
+
+/// How a rule's failure is treated: `error` blocks the commit, `warn` is
+/// only reported.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Level {
+    #[default]
+    Off,
+    Warn,
+    Error,
+}
+
+/// `.gitforge/commit-lint.toml`. Every rule is off unless configured:
+///
+/// ```toml
+/// max_subject_length = 72
+/// conventional = "error"
+/// imperative = "warn"
+/// required_trailers = ["Signed-off-by"]
+/// issue_reference = "error"
+/// issue_prefixes = ["GF", "OPS"]
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Rules {
+    /// Longest subject line in characters; an error past it.
+    pub max_subject_length: Option<usize>,
+    /// `type(scope): summary`, as `gitforge release` reads it.
+    pub conventional: Level,
+    /// The summary starts with "Add", not "Added", "Adding" or "Adds".
+    pub imperative: Level,
+    /// Trailer keys the message must carry, compared case-insensitively.
+    pub required_trailers: Vec<String>,
+    /// A `#123` or `KEY-123` reference anywhere in the message.
+    pub issue_reference: Level,
+    /// Limits `issue_reference` to `KEY-123` with one of these keys.
+    pub issue_prefixes: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Problem {
+    pub rule: &'static str,
+    pub level: Level,
+    pub message: String,
+}
+
+/// Subjects git writes itself, which no rule applies to.
+const GENERATED: [&str; 5] = ["Merge ", "Revert \"", "fixup! ", "squash! ", "amend! "];
+
+/// Verbs commonly put in the wrong form; "-ed" and "-ing" words are caught
+/// without it.
+const VERBS: [&str; 24] = [
+    "add",
+    "allow",
+    "bump",
+    "change",
+    "clean",
+    "create",
+    "delete",
+    "drop",
+    "enable",
+    "disable",
+    "ensure",
+    "fix",
+    "handle",
+    "implement",
+    "improve",
+    "make",
+    "move",
+    "prevent",
+    "refactor",
+    "remove",
+    "rename",
+    "replace",
+    "support",
+    "update",
+];
+
+/// Words ending in "-ed" that are already imperative.
+const ED_VERBS: [&str; 10] = [
+    "embed", "exceed", "feed", "need", "proceed", "seed", "shed", "speed", "succeed", "shred",
+];
+
+impl Rules {
+    /// The rules in `workdir`'s `.gitforge/commit-lint.toml`; all off
+    /// without one.
+    pub fn load(workdir: &Path) -> Result<Self, String> {
+        let text = match std::fs::read_to_string(workdir.join(CONFIG_PATH)) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(format!("failed to read {CONFIG_PATH}: {e}")),
+        };
+        toml::from_str(&text).map_err(|e| format!("invalid {CONFIG_PATH}: {e}"))
+    }
+
+    pub fn lint(&self, message: &str) -> Vec<Problem> {
+        let subject = message.lines().next().unwrap_or_default().trim_end();
+        if GENERATED.iter().any(|prefix| subject.starts_with(prefix)) {
+            return Vec::new();
+        }
+        let mut problems = Vec::new();
+        let mut report = |rule, level: Level, message: String| {
+            if level != Level::Off {
+                problems.push(Problem {
+                    rule,
+                    level,
+                    message,
+                });
+            }
+        };
+
+        if subject.is_empty() {
+            report("subject", Level::Error, "the subject line is empty".into());
+            return problems;
+        }
+        if let Some(max) = self.max_subject_length {
+            let length = subject.chars().count();
+            if length > max {
+                report(
+                    "max_subject_length",
+                    Level::Error,
+                    format!("the subject is {length} characters, over the {max} allowed"),
+                );
+            }
+        }
+        let change = Change::parse(message, "");
+        if change.is_none() {
+            report(
+                "conventional",
+                self.conventional,
+                "the subject isn't `type(scope): summary`".into(),
+            );
+        }
+        let summary = change.as_ref().map_or(subject, |c| c.summary.as_str());
+        if let Some(word) = non_imperative(summary) {
+            report(
+                "imperative",
+                self.imperative,
+                format!("start with an imperative verb, not \"{word}\""),
+            );
+        }
+        let present = trailers::read(message);
+        for key in &self.required_trailers {
+            if !present.iter().any(|t| t.key.eq_ignore_ascii_case(key)) {
+                report(
+                    "required_trailers",
+                    Level::Error,
+                    format!("the {key} trailer is missing"),
+                );
+            }
+        }
+        if !has_issue_reference(message, &self.issue_prefixes) {
+            let expected = if self.issue_prefixes.is_empty() {
+                "#123 or KEY-123".to_string()
+            } else {
+                self.issue_prefixes
+                    .iter()
+                    .map(|key| format!("{key}-123"))
+                    .collect::<Vec<_>>()
+                    .join(" or ")
+            };
+            report(
+                "issue_reference",
+                self.issue_reference,
+                format!("reference an issue ({expected})"),
+            );
+        }
+        problems
+    }
+}
+
+/// Problems that block the commit, as one message.
+pub fn refusal(problems: &[Problem]) -> Option<String> {
+    let errors: Vec<&str> = problems
+        .iter()
+        .filter(|p| p.level == Level::Error)
+        .map(|p| p.message.as_str())
+        .collect();
+    (!errors.is_empty()).then(|| format!("commit message rejected: {}", errors.join("; ")))
+}
+
+/// The summary's first word when it looks past tense, a gerund, or third
+/// person. A heuristic, which is why it's usually a warning.
+fn non_imperative(summary: &str) -> Option<&str> {
+    let word = summary
+        .split_whitespace()
+        .next()?
+        .trim_matches(|c: char| !c.is_alphanumeric());
+    let lower = word.to_ascii_lowercase();
+    let inflected = |verb: &str| {
+        let stem = verb.strip_suffix('e').unwrap_or(verb);
+        [
+            format!("{verb}s"),
+            format!("{verb}es"),
+            format!("{stem}ed"),
+            format!("{stem}ing"),
+            format!("{verb}{}ed", verb.chars().last().unwrap_or_default()),
+            format!("{verb}{}ing", verb.chars().last().unwrap_or_default()),
+        ]
+        .contains(&lower)
+    };
+    let suffixed = (lower.len() > 4 && lower.ends_with("ing"))
+        || (lower.len() > 3 && lower.ends_with("ed") && !ED_VERBS.contains(&lower.as_str()));
+    (suffixed || VERBS.iter().any(|verb| inflected(verb))).then_some(word)
+}
+
+fn has_issue_reference(message: &str, prefixes: &[String]) -> bool {
+    let digits = |rest: &str| rest.chars().next().is_some_and(|c| c.is_ascii_digit());
+    message
+        .split(|c: char| !(c.is_ascii_alphanumeric() || c == '#' || c == '-'))
+        .any(|word| {
+            if let Some(number) = word.strip_prefix('#') {
+                return prefixes.is_empty() && digits(number);
+            }
+            let Some((key, number)) = word.split_once('-') else {
+                return false;
+            };
+            digits(number)
+                && if prefixes.is_empty() {
+                    key.len() >= 2
+                        && key.starts_with(|c: char| c.is_ascii_uppercase())
+                        && key
+                            .chars()
+                            .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit())
+                } else {
+                    prefixes.iter().any(|prefix| prefix == key)
+                }
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn configured_rules_report_errors_and_warnings() {
+        let rules: Rules = toml::from_str(
+            "max_subject_length = 40\n\
+             conventional = \"error\"\n\
+             imperative = \"warn\"\n\
+             required_trailers = [\"Signed-off-by\"]\n\
+             issue_reference = \"error\"\n",
+        )
+        .expect("rules");
+
+        let clean =
+            "feat(auth): add device login\n\nCloses #12\n\nSigned-off-by: A <a@example.com>\n";
+        assert!(rules.lint(clean).is_empty(), "{:?}", rules.lint(clean));
+
+        let sloppy = "Added a much longer subject than forty characters\n";
+        let problems = rules.lint(sloppy);
+        let found: Vec<(&str, Level)> = problems.iter().map(|p| (p.rule, p.level)).collect();
+        assert_eq!(
+            found,
+            [
+                ("max_subject_length", Level::Error),
+                ("conventional", Level::Error),
+                ("imperative", Level::Warn),
+                ("required_trailers", Level::Error),
+                ("issue_reference", Level::Error),
+            ]
+        );
+        assert!(refusal(&problems)
+            .expect("errors")
+            .starts_with("commit message rejected: the subject is 49 characters"));
+        assert!(rules.lint("Merge branch 'main'\n").is_empty());
+
+        for (summary, flagged) in [
+            ("fixes login", true),
+            ("updating docs", true),
+            ("embed fonts", false),
+            ("use the cache", false),
+        ] {
+            assert_eq!(non_imperative(summary).is_some(), flagged, "{summary}");
+        }
+        let gf = ["GF".to_string()];
+        assert!(has_issue_reference("fix: typo (GF-7)", &gf));
+        assert!(!has_issue_reference("fix: typo (#7, OPS-7)", &gf));
+        assert!(Rules::default().lint("anything\n").is_empty());
+    }
+}
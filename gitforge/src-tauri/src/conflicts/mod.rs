@@ -0,0 +1,381 @@
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use crate::content::{self, FileContent};
+
+/// `revision` labels on the sides returned by [`conflict_sides`].
+pub const BASE: &str = "BASE";
+pub const OURS: &str = "OURS";
+pub const THEIRS: &str = "THEIRS";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ConflictFile {
+    pub path: String,
+    /// Which index stages exist; a missing side means it deleted the file.
+    pub base: bool,
+    pub ours: bool,
+    pub theirs: bool,
+}
+
+/// Everything the three-way merge editor shows for one path.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ConflictSides {
+    pub path: String,
+    pub base: FileContent,
+    pub ours: FileContent,
+    pub theirs: FileContent,
+    /// The working-tree file, usually with conflict markers.
+    pub working: FileContent,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Resolution {
+    Ours,
+    Theirs,
+    /// Contents edited in the merge editor.
+    Buffer(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct MergeCommit {
+    pub id: String,
+    pub parents: Vec<String>,
+    pub message: String,
+}
+
+/// Conflicted paths in index order.
+pub fn conflicts_list(repo: &git2::Repository) -> Result<Vec<ConflictFile>, String> {
+    let index = index(repo)?;
+    let conflicts = index
+        .conflicts()
+        .map_err(|e| format!("failed to read conflicts: {e}"))?;
+    let mut files = Vec::new();
+    for conflict in conflicts {
+        let conflict = conflict.map_err(|e| format!("failed to read conflict: {e}"))?;
+        let Some(path) = [&conflict.our, &conflict.their, &conflict.ancestor]
+            .into_iter()
+            .flatten()
+            .next()
+            .map(|entry| String::from_utf8_lossy(&entry.path).into_owned())
+        else {
+            continue;
+        };
+        files.push(ConflictFile {
+            path,
+            base: conflict.ancestor.is_some(),
+            ours: conflict.our.is_some(),
+            theirs: conflict.their.is_some(),
+        });
+    }
+    Ok(files)
+}
+
+pub fn conflict_sides(repo: &git2::Repository, path: &str) -> Result<ConflictSides, String> {
+    let conflict = conflict(repo, path)?;
+    let side = |entry: &Option<git2::IndexEntry>, label: &str| -> Result<FileContent, String> {
+        let bytes = match entry {
+            Some(entry) => Some(
+                repo.find_blob(entry.id)
+                    .map_err(|e| format!("failed to read {label} side of {path}: {e}"))?
+                    .content()
+                    .to_vec(),
+            ),
+            None => None,
+        };
+        Ok(content::describe(path, label, bytes))
+    };
+    Ok(ConflictSides {
+        path: path.to_string(),
+        base: side(&conflict.ancestor, BASE)?,
+        ours: side(&conflict.our, OURS)?,
+        theirs: side(&conflict.their, THEIRS)?,
+        working: content::describe(
+            path,
+            content::WORKDIR,
+            content::load(repo, path, content::WORKDIR)?,
+        ),
+    })
+}
+
+/// Writes the chosen contents to the working tree and marks `path`
+/// resolved. Taking a side that deleted the file deletes it.
+pub fn resolve_conflict(
+    repo: &git2::Repository,
+    path: &str,
+    resolution: Resolution,
+) -> Result<(), String> {
+    let conflict = conflict(repo, path)?;
+    let contents = match resolution {
+        Resolution::Buffer(text) => Some(text.into_bytes()),
+        Resolution::Ours | Resolution::Theirs => {
+            let entry = if resolution == Resolution::Ours {
+                &conflict.our
+            } else {
+                &conflict.their
+            };
+            match entry {
+                Some(entry) => Some(
+                    repo.find_blob(entry.id)
+                        .map_err(|e| format!("failed to read {path}: {e}"))?
+                        .content()
+                        .to_vec(),
+                ),
+                None => None,
+            }
+        }
+    };
+
+    let full = workdir(repo)?.join(path);
+    match contents {
+        Some(bytes) => {
+            if let Some(parent) = full.parent() {
+                std::fs::create_dir_all(parent)
+                    .map_err(|e| format!("failed to create {}: {e}", parent.display()))?;
+            }
+            std::fs::write(&full, bytes).map_err(|e| format!("failed to write {path}: {e}"))?;
+        }
+        None => match std::fs::remove_file(&full) {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(format!("failed to delete {path}: {e}")),
+        },
+    }
+    stage_resolution(repo, path)
+}
+
+/// Marks `path` resolved with whatever is in the working tree, like
+/// `git add` after fixing markers by hand.
+pub fn mark_resolved(repo: &git2::Repository, path: &str) -> Result<(), String> {
+    conflict(repo, path)?;
+    stage_resolution(repo, path)
+}
+
+/// Commits the merge in progress with `HEAD` and every `MERGE_HEAD` as
+/// parents, then clears the merge state. `message` defaults to `MERGE_MSG`.
+pub fn merge_finalize(
+    repo: &git2::Repository,
+    message: Option<&str>,
+) -> Result<MergeCommit, String> {
+    if repo.state() != git2::RepositoryState::Merge {
+        return Err("no merge in progress".to_string());
+    }
+    let mut index = index(repo)?;
+    if index.has_conflicts() {
+        let remaining = conflicts_list(repo)?.len();
+        return Err(format!("{remaining} files still have conflicts"));
+    }
+
+    let head = repo
+        .head()
+        .and_then(|h| h.peel_to_commit())
+        .map_err(|e| format!("failed to resolve HEAD: {e}"))?;
+    let merge_head = std::fs::read_to_string(repo.path().join("MERGE_HEAD"))
+        .map_err(|e| format!("failed to read MERGE_HEAD: {e}"))?;
+    let mut parents = vec![head];
+    for line in merge_head.lines().filter(|l| !l.trim().is_empty()) {
+        let oid = git2::Oid::from_str(line.trim())
+            .map_err(|e| format!("invalid MERGE_HEAD entry '{line}': {e}"))?;
+        parents.push(
+            repo.find_commit(oid)
+                .map_err(|e| format!("failed to read merged commit {oid}: {e}"))?,
+        );
+    }
+
+    let message = match message {
+        Some(message) if !message.trim().is_empty() => message.to_string(),
+        _ => repo
+            .message()
+            .map_err(|e| format!("failed to read MERGE_MSG: {e}"))?,
+    };
+    let tree = index
+        .write_tree()
+        .and_then(|oid| repo.find_tree(oid))
+        .map_err(|e| format!("failed to write merge tree: {e}"))?;
+    let signature = repo
+        .signature()
+        .or_else(|_| git2::Signature::now("GitForge", "gitforge@localhost"))
+        .map_err(|e| format!("failed to create signature: {e}"))?;
+    let parent_refs: Vec<&git2::Commit> = parents.iter().collect();
+    let id = repo
+        .commit(
+            Some("HEAD"),
+            &signature,
+            &signature,
+            &message,
+            &tree,
+            &parent_refs,
+        )
+        .map_err(|e| format!("failed to create merge commit: {e}"))?;
+    repo.cleanup_state()
+        .map_err(|e| format!("failed to clear merge state: {e}"))?;
+
+    Ok(MergeCommit {
+        id: id.to_string(),
+        parents: parents.iter().map(|c| c.id().to_string()).collect(),
+        message,
+    })
+}
+
+fn index(repo: &git2::Repository) -> Result<git2::Index, String> {
+    repo.index()
+        .map_err(|e| format!("failed to open index: {e}"))
+}
+
+fn workdir(repo: &git2::Repository) -> Result<&Path, String> {
+    repo.workdir()
+        .ok_or_else(|| "bare repositories have no working tree".to_string())
+}
+
+fn conflict(repo: &git2::Repository, path: &str) -> Result<git2::IndexConflict, String> {
+    content::check_path(path)?;
+    let index = index(repo)?;
+    let conflicts = index
+        .conflicts()
+        .map_err(|e| format!("failed to read conflicts: {e}"))?;
+    for conflict in conflicts.flatten() {
+        let matches = [&conflict.our, &conflict.their, &conflict.ancestor]
+            .into_iter()
+            .flatten()
+            .any(|entry| entry.path == path.as_bytes());
+        if matches {
+            return Ok(conflict);
+        }
+    }
+    Err(format!("{path} is not conflicted"))
+}
+
+/// Replaces the conflict stages with the working-tree state of `path`;
+/// both calls drop every stage of the path, not just stage 0.
+fn stage_resolution(repo: &git2::Repository, path: &str) -> Result<(), String> {
+    let mut index = index(repo)?;
+    if workdir(repo)?.join(path).exists() {
+        index.add_path(Path::new(path))
+    } else {
+        index.remove_path(Path::new(path))
+    }
+    .map_err(|e| format!("failed to stage {path}: {e}"))?;
+    index
+        .write()
+        .map_err(|e| format!("failed to write index: {e}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn commit_all(repo: &git2::Repository, message: &str) -> git2::Oid {
+        let mut index = repo.index().expect("index");
+        index
+            .add_all(["*"], git2::IndexAddOption::DEFAULT, None)
+            .expect("stage");
+        index.write().expect("write index");
+        let tree = repo
+            .find_tree(index.write_tree().expect("write tree"))
+            .expect("find tree");
+        let sig = git2::Signature::now("GitForge Test", "test@gitforge.dev").expect("signature");
+        let parent = repo.head().ok().and_then(|h| h.peel_to_commit().ok());
+        let parents: Vec<&git2::Commit> = parent.iter().collect();
+        repo.commit(Some("HEAD"), &sig, &sig, message, &tree, &parents)
+            .expect("commit")
+    }
+
+    /// Repo mid-merge with `story.txt` edited on both sides.
+    fn conflicted_repo(label: &str) -> (crate::test_support::TempDir, git2::Repository) {
+        let (dir, repo) = crate::test_support::init_repo(&format!("conflicts-{label}"));
+        let mut config = repo.config().expect("config");
+        config.set_str("user.name", "GitForge Test").expect("name");
+        config
+            .set_str("user.email", "test@gitforge.dev")
+            .expect("email");
+
+        fs::write(dir.join("story.txt"), "base\n").expect("write");
+        fs::write(dir.join("clean.txt"), "clean\n").expect("write");
+        let base = commit_all(&repo, "base");
+        let main_ref = repo.head().unwrap().name().unwrap().to_string();
+
+        repo.branch("theirs", &repo.find_commit(base).unwrap(), false)
+            .expect("branch");
+        repo.set_head("refs/heads/theirs").expect("switch");
+        fs::write(dir.join("story.txt"), "theirs\n").expect("write");
+        let theirs = commit_all(&repo, "their change");
+
+        repo.set_head(&main_ref).expect("switch back");
+        repo.checkout_head(Some(git2::build::CheckoutBuilder::new().force()))
+            .expect("checkout");
+        fs::write(dir.join("story.txt"), "ours\n").expect("write");
+        commit_all(&repo, "our change");
+
+        let annotated = repo.find_annotated_commit(theirs).expect("annotated");
+        repo.merge(&[&annotated], None, None).expect("merge");
+        drop(annotated);
+        (dir, repo)
+    }
+
+    #[test]
+    fn lists_conflicts_and_returns_all_sides() {
+        let (_dir, repo) = conflicted_repo("sides");
+
+        let files = conflicts_list(&repo).expect("list");
+        assert_eq!(
+            files,
+            [ConflictFile {
+                path: "story.txt".to_string(),
+                base: true,
+                ours: true,
+                theirs: true,
+            }]
+        );
+
+        let sides = conflict_sides(&repo, "story.txt").expect("sides");
+        assert_eq!(sides.base.content.as_deref(), Some("base\n"));
+        assert_eq!(sides.ours.content.as_deref(), Some("ours\n"));
+        assert_eq!(sides.theirs.content.as_deref(), Some("theirs\n"));
+        assert!(sides.working.content.unwrap().contains("<<<<<<<"));
+        assert!(conflict_sides(&repo, "clean.txt").is_err());
+    }
+
+    #[test]
+    fn resolving_then_finalizing_creates_a_merge_commit() {
+        let (dir, repo) = conflicted_repo("finalize");
+        let err = merge_finalize(&repo, None).expect_err("still conflicted");
+        assert!(err.contains("1 files still have conflicts"), "{err}");
+
+        resolve_conflict(&repo, "story.txt", Resolution::Theirs).expect("take theirs");
+        assert_eq!(
+            fs::read_to_string(dir.join("story.txt")).unwrap(),
+            "theirs\n"
+        );
+        assert!(conflicts_list(&repo).expect("list").is_empty());
+
+        let merge = merge_finalize(&repo, Some("Merge theirs")).expect("finalize");
+        assert_eq!(merge.parents.len(), 2);
+        assert_eq!(repo.state(), git2::RepositoryState::Clean);
+        let head = repo.head().unwrap().peel_to_commit().unwrap();
+        assert_eq!(head.id().to_string(), merge.id);
+        assert_eq!(head.message(), Some("Merge theirs"));
+        assert!(merge_finalize(&repo, None).is_err());
+    }
+
+    #[test]
+    fn buffer_and_manual_resolutions_are_staged() {
+        let (_dir, repo) = conflicted_repo("buffer");
+        resolve_conflict(
+            &repo,
+            "story.txt",
+            Resolution::Buffer("ours and theirs\n".to_string()),
+        )
+        .expect("resolve with buffer");
+        assert_eq!(
+            content::load(&repo, "story.txt", content::INDEX).unwrap(),
+            Some(b"ours and theirs\n".to_vec())
+        );
+        assert!(mark_resolved(&repo, "story.txt").is_err());
+
+        let (dir, repo) = conflicted_repo("manual");
+        fs::write(dir.join("story.txt"), "hand merged\n").expect("edit");
+        mark_resolved(&repo, "story.txt").expect("mark resolved");
+        assert!(conflicts_list(&repo).expect("list").is_empty());
+    }
+}
@@ -0,0 +1,403 @@
+use serde::Serialize;
+use std::path::{Component, Path};
+
+use crate::budget::{serialized_len, MAX_RESPONSE_BYTES};
+
+/// Files larger than this are reported without content; Monaco slows
+/// down badly well before it runs out of memory.
+pub const MAX_CONTENT_BYTES: usize = 2 * 1024 * 1024;
+/// Like git, only the first 8000 bytes are checked for NUL when sniffing binaries.
+const BINARY_SNIFF_BYTES: usize = 8000;
+
+/// `revision` values that don't name a commit.
+pub const WORKDIR: &str = "WORKDIR";
+pub const INDEX: &str = "INDEX";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FileContent {
+    pub path: String,
+    pub revision: String,
+    /// `false` when the file doesn't exist at `revision` (added/deleted).
+    pub exists: bool,
+    pub size: usize,
+    pub binary: bool,
+    pub too_large: bool,
+    /// `None` for missing, binary, and oversized files.
+    pub content: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DiffLine {
+    /// `+`, `-`, or ` `.
+    pub origin: char,
+    pub old_lineno: Option<u32>,
+    pub new_lineno: Option<u32>,
+    pub content: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DiffHunk {
+    pub header: String,
+    pub old_start: u32,
+    pub old_lines: u32,
+    pub new_start: u32,
+    pub new_lines: u32,
+    pub lines: Vec<DiffLine>,
+}
+
+/// Both sides for Monaco's diff editor plus hunks for change navigation.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FileDiff {
+    pub path: String,
+    pub original: FileContent,
+    pub modified: FileContent,
+    /// Empty when either side is binary or too large.
+    pub hunks: Vec<DiffHunk>,
+    /// The hunks stop early because their lines passed
+    /// [`MAX_RESPONSE_BYTES`]; the last one may hold fewer lines than its
+    /// header counts.
+    pub truncated: bool,
+}
+
+/// Reads `path` at `revision`: a commit-ish, [`INDEX`], or [`WORKDIR`] (the
+/// default when `None`).
+pub fn read_file_at(
+    repo_path: &str,
+    path: &str,
+    revision: Option<&str>,
+) -> Result<FileContent, String> {
+    let repo = open(repo_path)?;
+    let revision = revision.unwrap_or(WORKDIR);
+    let bytes = load(&repo, path, revision)?;
+    Ok(describe(path, revision, bytes))
+}
+
+/// Diffs `path` between two revisions (see [`read_file_at`]); `target`
+/// defaults to the working tree and `base` to `HEAD`.
+pub fn diff_file(
+    repo_path: &str,
+    path: &str,
+    base: Option<&str>,
+    target: Option<&str>,
+) -> Result<FileDiff, String> {
+    let repo = open(repo_path)?;
+    let base = base.unwrap_or("HEAD");
+    let target = target.unwrap_or(WORKDIR);
+    let old = load(&repo, path, base)?;
+    let new = load(&repo, path, target)?;
+
+    let original = describe(path, base, old.clone());
+    let modified = describe(path, target, new.clone());
+    let comparable = [&original, &modified]
+        .iter()
+        .all(|side| !side.binary && !side.too_large);
+    let (hunks, truncated) = if comparable {
+        hunks_within(
+            path,
+            old.as_deref().unwrap_or_default(),
+            new.as_deref().unwrap_or_default(),
+            MAX_RESPONSE_BYTES,
+        )?
+    } else {
+        (Vec::new(), false)
+    };
+
+    Ok(FileDiff {
+        path: path.to_string(),
+        original,
+        modified,
+        hunks,
+        truncated,
+    })
+}
+
+fn open(repo_path: &str) -> Result<git2::Repository, String> {
+    git2::Repository::open(repo_path).map_err(|e| format!("failed to open repository: {e}"))
+}
+
+/// Repository-relative paths only; `..` and absolute paths could escape
+/// the working tree.
+pub(crate) fn check_path(path: &str) -> Result<(), String> {
+    let relative = Path::new(path)
+        .components()
+        .all(|c| matches!(c, Component::Normal(_) | Component::CurDir));
+    if path.is_empty() || !relative {
+        return Err(format!(
+            "invalid path '{path}': must be relative to the repository"
+        ));
+    }
+    Ok(())
+}
+
+/// Raw bytes of `path` at `revision`, or `None` if it doesn't exist there.
+pub(crate) fn load(
+    repo: &git2::Repository,
+    path: &str,
+    revision: &str,
+) -> Result<Option<Vec<u8>>, String> {
+    check_path(path)?;
+    match revision {
+        WORKDIR => {
+            let workdir = repo
+                .workdir()
+                .ok_or("bare repositories have no working tree")?;
+            match std::fs::read(workdir.join(path)) {
+                Ok(bytes) => Ok(Some(bytes)),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+                Err(e) => Err(format!("failed to read {path}: {e}")),
+            }
+        }
+        INDEX => {
+            let index = repo
+                .index()
+                .map_err(|e| format!("failed to read index: {e}"))?;
+            let Some(entry) = index.get_path(Path::new(path), 0) else {
+                return Ok(None);
+            };
+            let blob = repo
+                .find_blob(entry.id)
+                .map_err(|e| format!("failed to read {path} from index: {e}"))?;
+            Ok(Some(blob.content().to_vec()))
+        }
+        revision => {
+            let tree = repo
+                .revparse_single(revision)
+                .and_then(|object| object.peel_to_tree())
+                .map_err(|e| format!("unknown revision '{revision}': {e}"))?;
+            let entry = match tree.get_path(Path::new(path)) {
+                Ok(entry) => entry,
+                Err(e) if e.code() == git2::ErrorCode::NotFound => return Ok(None),
+                Err(e) => return Err(format!("failed to look up {path}: {e}")),
+            };
+            let blob = entry
+                .to_object(repo)
+                .and_then(|object| object.peel_to_blob())
+                .map_err(|_| format!("{path} is not a file at {revision}"))?;
+            Ok(Some(blob.content().to_vec()))
+        }
+    }
+}
+
+pub(crate) fn describe(path: &str, revision: &str, bytes: Option<Vec<u8>>) -> FileContent {
+    let exists = bytes.is_some();
+    let bytes = bytes.unwrap_or_default();
+    let size = bytes.len();
+    let too_large = size > MAX_CONTENT_BYTES;
+    let sniff = &bytes[..size.min(BINARY_SNIFF_BYTES)];
+    let mut binary = sniff.contains(&0);
+
+    let content = if too_large || binary || !exists {
+        None
+    } else {
+        match String::from_utf8(bytes) {
+            Ok(text) => Some(text),
+            Err(_) => {
+                binary = true;
+                None
+            }
+        }
+    };
+
+    FileContent {
+        path: path.to_string(),
+        revision: revision.to_string(),
+        exists,
+        size,
+        binary,
+        too_large,
+        content,
+    }
+}
+
+pub(crate) fn hunks(path: &str, old: &[u8], new: &[u8]) -> Result<Vec<DiffHunk>, String> {
+    hunks_within(path, old, new, usize::MAX).map(|(hunks, _)| hunks)
+}
+
+/// Hunks whose lines fit in `budget` serialized bytes, and whether any
+/// were left out. Lines are converted one at a time, so a huge rewrite
+/// stops costing memory once the budget is spent.
+fn hunks_within(
+    path: &str,
+    old: &[u8],
+    new: &[u8],
+    budget: usize,
+) -> Result<(Vec<DiffHunk>, bool), String> {
+    let mut opts = git2::DiffOptions::new();
+    opts.context_lines(3);
+    let patch = git2::Patch::from_buffers(
+        old,
+        Some(Path::new(path)),
+        new,
+        Some(Path::new(path)),
+        Some(&mut opts),
+    )
+    .map_err(|e| format!("failed to diff {path}: {e}"))?;
+
+    let mut spent = 0;
+    let mut out = Vec::new();
+    for h in 0..patch.num_hunks() {
+        let (hunk, line_count) = patch
+            .hunk(h)
+            .map_err(|e| format!("failed to read hunk: {e}"))?;
+        let header = String::from_utf8_lossy(hunk.header())
+            .trim_end()
+            .to_string();
+        spent += header.len();
+        let mut lines = Vec::new();
+        let mut fits = spent <= budget;
+        for l in 0..line_count {
+            if !fits {
+                break;
+            }
+            let line = patch
+                .line_in_hunk(h, l)
+                .map_err(|e| format!("failed to read diff line: {e}"))?;
+            let line = DiffLine {
+                origin: line.origin(),
+                old_lineno: line.old_lineno(),
+                new_lineno: line.new_lineno(),
+                content: String::from_utf8_lossy(line.content()).into_owned(),
+            };
+            spent += serialized_len(&line) + 1;
+            fits = spent <= budget;
+            if fits {
+                lines.push(line);
+            }
+        }
+        let kept = DiffHunk {
+            header,
+            old_start: hunk.old_start(),
+            old_lines: hunk.old_lines(),
+            new_start: hunk.new_start(),
+            new_lines: hunk.new_lines(),
+            lines,
+        };
+        if fits {
+            out.push(kept);
+            continue;
+        }
+        if !kept.lines.is_empty() {
+            out.push(kept);
+        }
+        return Ok((out, true));
+    }
+    Ok((out, false))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    /// Repo with `notes.txt` committed as three lines.
+    fn committed_repo(label: &str) -> crate::test_support::TempDir {
+        let (dir, repo) = crate::test_support::init_repo(&format!("content-{label}"));
+        fs::write(dir.join("notes.txt"), "one\ntwo\nthree\n").expect("write file");
+
+        let mut index = repo.index().expect("index");
+        index.add_path(Path::new("notes.txt")).expect("stage");
+        index.write().expect("write index");
+        let tree = repo
+            .find_tree(index.write_tree().expect("write tree"))
+            .expect("find tree");
+        let sig = git2::Signature::now("GitForge Test", "test@gitforge.dev").expect("signature");
+        repo.commit(Some("HEAD"), &sig, &sig, "init", &tree, &[])
+            .expect("commit");
+        dir
+    }
+
+    #[test]
+    fn reads_committed_and_working_copies() {
+        let dir = committed_repo("read");
+        let repo = dir.as_str();
+        fs::write(Path::new(&repo).join("notes.txt"), "changed\n").expect("edit");
+
+        let head = read_file_at(repo, "notes.txt", Some("HEAD")).expect("head");
+        let staged = read_file_at(repo, "notes.txt", Some(INDEX)).expect("index");
+        let work = read_file_at(repo, "notes.txt", None).expect("workdir");
+
+        assert_eq!(head.content.as_deref(), Some("one\ntwo\nthree\n"));
+        assert_eq!(staged.content, head.content);
+        assert_eq!(work.content.as_deref(), Some("changed\n"));
+        assert!(
+            !read_file_at(repo, "missing.txt", Some("HEAD"))
+                .expect("missing")
+                .exists
+        );
+        assert!(read_file_at(repo, "../outside", None).is_err());
+    }
+
+    #[test]
+    fn binary_and_large_files_have_no_content() {
+        let dir = committed_repo("guards");
+        let repo = dir.as_str();
+        let dir = Path::new(&repo);
+        fs::write(dir.join("image.bin"), [0x89, b'P', b'N', b'G', 0, 1]).expect("binary");
+        fs::write(dir.join("huge.txt"), "x".repeat(MAX_CONTENT_BYTES + 1)).expect("large");
+
+        let binary = read_file_at(repo, "image.bin", None).expect("binary");
+        assert!(binary.binary && binary.content.is_none());
+
+        let large = diff_file(repo, "huge.txt", None, None).expect("diff large");
+        assert!(large.modified.too_large && !large.original.exists);
+        assert!(large.hunks.is_empty());
+    }
+
+    #[test]
+    fn diff_reports_hunks_with_line_numbers() {
+        let dir = committed_repo("diff");
+        let repo = dir.as_str();
+        fs::write(Path::new(&repo).join("notes.txt"), "one\n2\nthree\n").expect("edit");
+
+        let diff = diff_file(repo, "notes.txt", None, None).expect("diff");
+        assert_eq!(diff.hunks.len(), 1);
+        let hunk = &diff.hunks[0];
+        assert_eq!((hunk.old_start, hunk.new_start), (1, 1));
+
+        let changed: Vec<(char, &str)> = hunk
+            .lines
+            .iter()
+            .filter(|l| l.origin != ' ')
+            .map(|l| (l.origin, l.content.as_str()))
+            .collect();
+        assert_eq!(changed, [('-', "two\n"), ('+', "2\n")]);
+        assert_eq!(
+            hunk.lines
+                .iter()
+                .find(|l| l.origin == '+')
+                .and_then(|l| l.new_lineno),
+            Some(2)
+        );
+    }
+
+    #[test]
+    fn hunks_stop_at_the_byte_budget() {
+        let old: String = (0..100).map(|i| format!("line {i}\n")).collect();
+        let new = old
+            .replace("line 10\n", "ten\n")
+            .replace("line 80\n", "eighty\n");
+
+        let (all, truncated) =
+            hunks_within("f.txt", old.as_bytes(), new.as_bytes(), usize::MAX).expect("diff");
+        assert_eq!(all.len(), 2);
+        assert!(!truncated);
+
+        let first: usize = all[0].header.len()
+            + all[0]
+                .lines
+                .iter()
+                .map(|l| serialized_len(l) + 1)
+                .sum::<usize>();
+        let (cut, truncated) =
+            hunks_within("f.txt", old.as_bytes(), new.as_bytes(), first + 200).expect("diff");
+        assert!(truncated);
+        assert_eq!(cut.len(), 2, "the second hunk is kept partially");
+        assert_eq!(cut[0], all[0]);
+        assert!(!cut[1].lines.is_empty() && cut[1].lines.len() < all[1].lines.len());
+        assert_eq!(cut[1].new_lines, all[1].new_lines);
+
+        let (none, truncated) =
+            hunks_within("f.txt", old.as_bytes(), new.as_bytes(), 10).expect("diff");
+        assert!(none.is_empty() && truncated);
+    }
+}
@@ -0,0 +1,275 @@
+use ant_core::{SystemEvent, VersionedSystemEvent};
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
+
+use super::Pool;
+
+/// Largest page [`events_since`] returns, however many are requested.
+pub const MAX_EVENTS_PAGE: usize = 1000;
+
+/// A persisted event. `event` stays raw JSON so rows written by a newer
+/// schema version can still be inspected by an older binary.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoredEvent {
+    pub seq: i64,
+    pub schema_version: u16,
+    pub kind: String,
+    pub event: serde_json::Value,
+    pub recorded_at: String,
+}
+
+/// Appends `event` and returns its sequence number. `AUTOINCREMENT` keeps
+/// sequences strictly increasing even after old rows are deleted.
+pub fn append(db: &rusqlite::Connection, event: &VersionedSystemEvent) -> Result<i64, String> {
+    let payload = serde_json::to_value(&event.event)
+        .map_err(|e| format!("failed to serialize event: {e}"))?;
+    let kind = payload
+        .get("type")
+        .and_then(|v| v.as_str())
+        .unwrap_or("unknown")
+        .to_string();
+
+    db.execute(
+        "INSERT INTO events (schema_version, kind, payload) VALUES (?1, ?2, ?3)",
+        rusqlite::params![event.schema_version, kind, payload.to_string()],
+    )
+    .map_err(|e| format!("failed to store event: {e}"))?;
+    let seq = db.last_insert_rowid();
+
+    project_goal(db, &event.event)?;
+    Ok(seq)
+}
+
+/// Keeps the `goals` table in step with goal events so goals can be listed
+/// and searched without replaying the log.
+fn project_goal(db: &rusqlite::Connection, event: &SystemEvent) -> Result<(), String> {
+    let result = match event {
+        SystemEvent::GoalCreated { goal_id, task } => db.execute(
+            "INSERT INTO goals (goal_id, task) VALUES (?1, ?2)
+             ON CONFLICT(goal_id) DO UPDATE SET task = excluded.task,
+                 updated_at = CURRENT_TIMESTAMP",
+            rusqlite::params![goal_id, task],
+        ),
+        SystemEvent::GoalStatusChanged { goal_id, status } => {
+            let status = serde_json::to_value(status)
+                .ok()
+                .and_then(|v| v.as_str().map(str::to_string));
+            db.execute(
+                "INSERT INTO goals (goal_id, status) VALUES (?1, ?2)
+                 ON CONFLICT(goal_id) DO UPDATE SET status = excluded.status,
+                     updated_at = CURRENT_TIMESTAMP",
+                rusqlite::params![goal_id, status],
+            )
+        }
+        SystemEvent::GoalCancelled { .. }
+        | SystemEvent::SettingChanged { .. }
+        | SystemEvent::PrStateChanged { .. }
+        | SystemEvent::MergeConflicted { .. }
+        | SystemEvent::ProtectionOverridden { .. }
+        | SystemEvent::GoalLogAppended { .. }
+        | SystemEvent::PotentialConflict { .. }
+        | SystemEvent::RebaseProgress { .. } => return Ok(()),
+    };
+
+    result
+        .map(|_| ())
+        .map_err(|e| format!("failed to update goal: {e}"))
+}
+
+/// Events with a sequence greater than `after`, oldest first.
+pub fn events_since(
+    db: &rusqlite::Connection,
+    after: i64,
+    limit: usize,
+) -> Result<Vec<StoredEvent>, String> {
+    let limit = limit.clamp(1, MAX_EVENTS_PAGE) as i64;
+    let mut stmt = db
+        .prepare(
+            "SELECT seq, schema_version, kind, payload, recorded_at FROM events
+             WHERE seq > ?1 ORDER BY seq LIMIT ?2",
+        )
+        .map_err(|e| format!("failed to prepare event query: {e}"))?;
+
+    let rows = stmt
+        .query_map(rusqlite::params![after, limit], |row| {
+            let payload: String = row.get(3)?;
+            Ok(StoredEvent {
+                seq: row.get(0)?,
+                schema_version: row.get(1)?,
+                kind: row.get(2)?,
+                event: serde_json::from_str(&payload).unwrap_or(serde_json::Value::String(payload)),
+                recorded_at: row.get(4)?,
+            })
+        })
+        .map_err(|e| format!("failed to read events: {e}"))?;
+
+    rows.collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("failed to read events: {e}"))
+}
+
+/// Events with a sequence below `before`, or the newest ones when it's
+/// `None`, newest first.
+pub fn events_before(
+    db: &rusqlite::Connection,
+    before: Option<i64>,
+    limit: usize,
+) -> Result<Vec<StoredEvent>, String> {
+    let limit = limit.clamp(1, MAX_EVENTS_PAGE) as i64;
+    let mut stmt = db
+        .prepare(
+            "SELECT seq, schema_version, kind, payload, recorded_at FROM events
+             WHERE seq < ?1 ORDER BY seq DESC LIMIT ?2",
+        )
+        .map_err(|e| format!("failed to prepare event query: {e}"))?;
+
+    let rows = stmt
+        .query_map(
+            rusqlite::params![before.unwrap_or(i64::MAX), limit],
+            |row| {
+                let payload: String = row.get(3)?;
+                Ok(StoredEvent {
+                    seq: row.get(0)?,
+                    schema_version: row.get(1)?,
+                    kind: row.get(2)?,
+                    event: serde_json::from_str(&payload)
+                        .unwrap_or(serde_json::Value::String(payload)),
+                    recorded_at: row.get(4)?,
+                })
+            },
+        )
+        .map_err(|e| format!("failed to read events: {e}"))?;
+
+    rows.collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("failed to read events: {e}"))
+}
+
+/// Persists everything published on `rx` until the bus closes. A lagging
+/// recorder loses events, so the gap is logged rather than hidden.
+pub fn spawn_recorder(
+    pool: Pool,
+    mut rx: broadcast::Receiver<VersionedSystemEvent>,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        loop {
+            let event = match rx.recv().await {
+                Ok(event) => event,
+                Err(broadcast::error::RecvError::Lagged(missed)) => {
+                    eprintln!("event recorder lagged, {missed} events not persisted");
+                    continue;
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            };
+
+            let pool = pool.clone();
+            let stored = tokio::task::spawn_blocking(move || {
+                let db = pool
+                    .get()
+                    .map_err(|e| format!("db connection unavailable: {e}"))?;
+                append(&db, &event)
+            })
+            .await;
+
+            match stored {
+                Ok(Ok(_)) => {}
+                Ok(Err(e)) => eprintln!("{e}"),
+                Err(e) => eprintln!("event recorder task failed: {e}"),
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ant_core::{AntEngine, GoalStatus, SystemEvent, SYSTEM_EVENT_SCHEMA_VERSION};
+
+    fn memory_db() -> rusqlite::Connection {
+        let db = rusqlite::Connection::open_in_memory().expect("open memory db");
+        db.execute_batch(super::super::SCHEMA).expect("init schema");
+        db
+    }
+
+    fn goal_event(goal_id: &str) -> VersionedSystemEvent {
+        VersionedSystemEvent {
+            schema_version: SYSTEM_EVENT_SCHEMA_VERSION,
+            event: SystemEvent::GoalStatusChanged {
+                goal_id: goal_id.to_string(),
+                status: GoalStatus::Running,
+            },
+        }
+    }
+
+    #[test]
+    fn events_since_pages_in_sequence_order() {
+        let db = memory_db();
+        let seqs: Vec<i64> = ["G-1", "G-2", "G-3"]
+            .iter()
+            .map(|id| append(&db, &goal_event(id)).expect("append"))
+            .collect();
+        assert!(seqs.windows(2).all(|w| w[0] < w[1]));
+
+        let page = events_since(&db, seqs[0], 1).expect("query");
+        assert_eq!(page.len(), 1);
+        assert_eq!(page[0].seq, seqs[1]);
+        assert_eq!(page[0].kind, "goal_status_changed");
+        assert_eq!(page[0].event["goal_id"], "G-2");
+
+        assert!(events_since(&db, seqs[2], 10).expect("query").is_empty());
+    }
+
+    #[test]
+    fn sequence_is_not_reused_after_deletes() {
+        let db = memory_db();
+        let first = append(&db, &goal_event("G-1")).expect("append");
+        db.execute("DELETE FROM events", []).expect("delete");
+
+        assert!(append(&db, &goal_event("G-2")).expect("append") > first);
+    }
+
+    #[test]
+    fn goal_events_are_projected_into_goals() {
+        let db = memory_db();
+        append(
+            &db,
+            &VersionedSystemEvent {
+                schema_version: SYSTEM_EVENT_SCHEMA_VERSION,
+                event: SystemEvent::GoalCreated {
+                    goal_id: "G-1".to_string(),
+                    task: "Triage flaky tests".to_string(),
+                },
+            },
+        )
+        .expect("append created");
+        append(&db, &goal_event("G-1")).expect("append status");
+
+        let (task, status): (String, String) = db
+            .query_row(
+                "SELECT task, status FROM goals WHERE goal_id = 'G-1'",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .expect("goal row");
+        assert_eq!(task, "Triage flaky tests");
+        assert_eq!(status, "running");
+    }
+
+    #[tokio::test]
+    async fn recorder_persists_engine_events() {
+        let dir = crate::test_support::temp_dir("events");
+        let pool = super::super::open_pool(dir.as_str()).expect("pool");
+
+        let engine = AntEngine::new();
+        let recorder = spawn_recorder(pool.clone(), engine.subscribe_events());
+        engine.create_goal("G-1", "Nightly run").expect("goal");
+        drop(engine);
+        recorder.await.expect("recorder finished");
+
+        let db = pool.get().expect("connection");
+        let kinds: Vec<String> = events_since(&db, 0, 10)
+            .expect("query")
+            .into_iter()
+            .map(|e| e.kind)
+            .collect();
+        assert_eq!(kinds, ["goal_created", "goal_status_changed"]);
+    }
+}
@@ -0,0 +1,215 @@
+use std::path::{Path, PathBuf};
+
+use super::DB_FILE_NAME;
+
+/// Explicit database file, overriding everything else.
+pub const DB_ENV: &str = "GITFORGE_DB";
+/// Per-repo git config key: `git` (default), `xdg`, or a file path.
+pub const DB_CONFIG_KEY: &str = "gitforge.db";
+
+/// Where `gitforge.db` lives for `repo_path`.
+///
+/// Resolution order: `$GITFORGE_DB`, then the repo's `gitforge.db` git
+/// config, then `<git common dir>/gitforge/gitforge.db` so the file never
+/// shows up in `git status` and linked worktrees share one registry.
+/// Directories that are not git repositories keep the file in their root.
+pub fn db_path(repo_path: &str) -> PathBuf {
+    if let Some(path) = std::env::var_os(DB_ENV).filter(|v| !v.is_empty()) {
+        return PathBuf::from(path);
+    }
+
+    let Ok(repo) = git2::Repository::open(repo_path) else {
+        return legacy_db_path(repo_path);
+    };
+    let configured = repo
+        .config()
+        .ok()
+        .and_then(|config| config.get_string(DB_CONFIG_KEY).ok());
+
+    resolve(&repo, configured.as_deref())
+}
+
+/// The pre-migration location in the working tree root.
+pub fn legacy_db_path(repo_path: &str) -> PathBuf {
+    Path::new(repo_path).join(DB_FILE_NAME)
+}
+
+fn resolve(repo: &git2::Repository, configured: Option<&str>) -> PathBuf {
+    let root = repo.workdir().unwrap_or_else(|| repo.path());
+    match configured.map(str::trim) {
+        None | Some("") | Some("git") => common_dir(repo).join("gitforge").join(DB_FILE_NAME),
+        Some("xdg") => xdg_db_path(&xdg_data_home(), root),
+        Some(path) => root.join(path),
+    }
+}
+
+/// The main repository's git dir, shared by all linked worktrees. git2 0.18
+/// has no accessor, so read the `commondir` file a linked worktree carries.
+pub(crate) fn common_dir(repo: &git2::Repository) -> PathBuf {
+    let git_dir = repo.path();
+    if !repo.is_worktree() {
+        return git_dir.to_path_buf();
+    }
+    match std::fs::read_to_string(git_dir.join("commondir")) {
+        Ok(contents) => {
+            let common = git_dir.join(contents.trim());
+            common.canonicalize().unwrap_or(common)
+        }
+        Err(_) => git_dir.to_path_buf(),
+    }
+}
+
+pub(crate) fn xdg_data_home() -> PathBuf {
+    std::env::var_os("XDG_DATA_HOME")
+        .filter(|v| !v.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".local/share")))
+        .unwrap_or_else(std::env::temp_dir)
+}
+
+/// `<data home>/gitforge/repos/<name>-<hash>/gitforge.db`, keyed by the
+/// canonical repository path so two clones never share a database.
+fn xdg_db_path(data_home: &Path, repo_root: &Path) -> PathBuf {
+    let canonical = repo_root
+        .canonicalize()
+        .unwrap_or_else(|_| repo_root.to_path_buf());
+    let name = canonical
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| "repo".to_string());
+    let key = format!(
+        "{name}-{:016x}",
+        fnv1a(canonical.to_string_lossy().as_bytes())
+    );
+
+    data_home
+        .join("gitforge")
+        .join("repos")
+        .join(key)
+        .join(DB_FILE_NAME)
+}
+
+/// Stable across Rust releases, unlike `DefaultHasher`.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// Moves a database left in the working tree root (plus its WAL/SHM files)
+/// to `target`, unless `target` already exists.
+pub fn migrate_legacy(repo_path: &str, target: &Path) -> Result<bool, String> {
+    let legacy = legacy_db_path(repo_path);
+    if !legacy.exists() || target.exists() || same_file(&legacy, target) {
+        return Ok(false);
+    }
+
+    if let Some(parent) = target.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| format!("failed to create {}: {e}", parent.display()))?;
+    }
+
+    for suffix in ["", "-wal", "-shm"] {
+        let from = with_suffix(&legacy, suffix);
+        if from.exists() {
+            move_file(&from, &with_suffix(target, suffix))?;
+        }
+    }
+
+    Ok(true)
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+fn same_file(a: &Path, b: &Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+/// `rename` fails across filesystems (e.g. repo on a USB disk, XDG dir in
+/// home), so fall back to copy + remove.
+fn move_file(from: &Path, to: &Path) -> Result<(), String> {
+    if std::fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+    std::fs::copy(from, to)
+        .and_then(|_| std::fs::remove_file(from))
+        .map_err(|e| format!("failed to move {} to {}: {e}", from.display(), to.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::init_repo;
+
+    #[test]
+    fn default_location_is_inside_git_dir() {
+        let (dir, repo) = init_repo("dbloc-default");
+        let path = db_path(dir.to_str().expect("utf8 path"));
+
+        assert!(path.starts_with(repo.path()));
+        assert!(path.ends_with("gitforge/gitforge.db"));
+    }
+
+    #[test]
+    fn linked_worktrees_share_the_main_database() {
+        let (dir, repo) = init_repo("dbloc-linked");
+        let sig = git2::Signature::now("GitForge Test", "test@gitforge.dev").expect("signature");
+        let tree_id = repo.index().expect("index").write_tree().expect("tree");
+        let tree = repo.find_tree(tree_id).expect("find tree");
+        repo.commit(Some("HEAD"), &sig, &sig, "init", &tree, &[])
+            .expect("commit");
+        let wt_path = dir.join("linked-wt");
+        repo.worktree("linked-wt", &wt_path, None)
+            .expect("add worktree");
+
+        assert_eq!(
+            db_path(wt_path.to_str().expect("utf8 path")),
+            db_path(dir.to_str().expect("utf8 path"))
+        );
+    }
+
+    #[test]
+    fn git_config_selects_custom_path() {
+        let (dir, repo) = init_repo("dbloc-config");
+        repo.config()
+            .expect("config")
+            .set_str(DB_CONFIG_KEY, "meta/forge.db")
+            .expect("set config");
+
+        assert_eq!(
+            db_path(dir.to_str().expect("utf8 path")),
+            repo.workdir().expect("workdir").join("meta/forge.db")
+        );
+    }
+
+    #[test]
+    fn xdg_location_is_keyed_by_repo_path() {
+        let base = Path::new("/data");
+        let a = xdg_db_path(base, Path::new("/work/a/service"));
+        let b = xdg_db_path(base, Path::new("/work/b/service"));
+
+        assert!(a.starts_with("/data/gitforge/repos"));
+        assert_ne!(a, b);
+    }
+
+    #[test]
+    fn legacy_database_is_migrated_once() {
+        let (dir, _repo) = init_repo("dbloc-migrate");
+        let repo_path = dir.to_str().expect("utf8 path");
+        std::fs::write(legacy_db_path(repo_path), b"legacy").expect("write legacy db");
+
+        let target = db_path(repo_path);
+        assert!(migrate_legacy(repo_path, &target).expect("migrate"));
+        assert!(!legacy_db_path(repo_path).exists());
+        assert_eq!(std::fs::read(&target).expect("read target"), b"legacy");
+
+        assert!(!migrate_legacy(repo_path, &target).expect("second migrate"));
+    }
+}
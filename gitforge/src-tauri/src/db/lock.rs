@@ -0,0 +1,244 @@
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions, TryLockError};
+use std::io::{Read, Seek, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Condvar, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+/// How often a contended file lock is retried.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LockError {
+    /// Another process or thread held the lock for the whole timeout.
+    Busy {
+        pid: Option<u32>,
+        operation: Option<String>,
+    },
+    Io(String),
+}
+
+impl std::fmt::Display for LockError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LockError::Busy { pid, operation } => {
+                write!(f, "gitforge.db is locked")?;
+                match pid {
+                    Some(pid) => write!(f, " by PID {pid}")?,
+                    None => write!(f, " by another process")?,
+                }
+                if let Some(operation) = operation {
+                    write!(f, " ({operation})")?;
+                }
+                Ok(())
+            }
+            LockError::Io(message) => f.write_str(message),
+        }
+    }
+}
+
+impl From<LockError> for String {
+    fn from(err: LockError) -> Self {
+        err.to_string()
+    }
+}
+
+/// Serializes multi-statement writes to one repository's metadata across
+/// threads (in-process table) and processes (OS file lock on
+/// `gitforge.db.lock`). Released on drop, or by the OS if the holder dies.
+pub struct WriteLock {
+    path: PathBuf,
+    _file: File,
+}
+
+impl Drop for WriteLock {
+    fn drop(&mut self) {
+        let (held, released) = registry();
+        // Even after a panic elsewhere, or the path would stay taken.
+        crate::sync::lock(held).remove(&self.path);
+        released.notify_all();
+    }
+}
+
+/// Paths locked by this process and the operation holding each.
+fn registry() -> &'static (Mutex<HashMap<PathBuf, String>>, Condvar) {
+    static REGISTRY: OnceLock<(Mutex<HashMap<PathBuf, String>>, Condvar)> = OnceLock::new();
+    REGISTRY.get_or_init(|| (Mutex::new(HashMap::new()), Condvar::new()))
+}
+
+pub fn lock_path(repo_path: &str) -> PathBuf {
+    let mut name = super::db_path(repo_path).into_os_string();
+    name.push(".lock");
+    PathBuf::from(name)
+}
+
+/// Takes the write lock for `repo_path`, waiting up to `timeout`.
+/// `operation` is shown to anyone who has to wait (e.g. `"worktree prune"`).
+pub fn acquire(
+    repo_path: &str,
+    operation: &str,
+    timeout: Duration,
+) -> Result<WriteLock, LockError> {
+    acquire_path(lock_path(repo_path), operation, timeout)
+}
+
+fn acquire_path(path: PathBuf, operation: &str, timeout: Duration) -> Result<WriteLock, LockError> {
+    let deadline = Instant::now() + timeout;
+
+    {
+        let (held, released) = registry();
+        let mut held = crate::sync::lock(held);
+        while let Some(holder) = held.get(&path) {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Err(LockError::Busy {
+                    pid: Some(std::process::id()),
+                    operation: Some(holder.clone()),
+                });
+            }
+            held = match released.wait_timeout(held, remaining) {
+                Ok((held, _)) => held,
+                Err(poisoned) => poisoned.into_inner().0,
+            };
+        }
+        held.insert(path.clone(), operation.to_string());
+    }
+
+    match lock_file(&path, operation, deadline) {
+        Ok(file) => Ok(WriteLock { path, _file: file }),
+        Err(e) => {
+            let (held, released) = registry();
+            crate::sync::lock(held).remove(&path);
+            released.notify_all();
+            Err(e)
+        }
+    }
+}
+
+fn lock_file(path: &Path, operation: &str, deadline: Instant) -> Result<File, LockError> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| LockError::Io(format!("failed to create {}: {e}", parent.display())))?;
+    }
+    let mut file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(path)
+        .map_err(|e| LockError::Io(format!("failed to open {}: {e}", path.display())))?;
+
+    loop {
+        match file.try_lock() {
+            Ok(()) => break,
+            Err(TryLockError::WouldBlock) if Instant::now() < deadline => {
+                std::thread::sleep(POLL_INTERVAL);
+            }
+            Err(TryLockError::WouldBlock) => {
+                let (pid, operation) = read_owner(path);
+                return Err(LockError::Busy { pid, operation });
+            }
+            Err(TryLockError::Error(e)) => {
+                return Err(LockError::Io(format!(
+                    "failed to lock {}: {e}",
+                    path.display()
+                )))
+            }
+        }
+    }
+
+    // Owner details are advisory; a failed write only degrades the message
+    // other processes see.
+    let _ = file
+        .set_len(0)
+        .and_then(|_| file.rewind())
+        .and_then(|_| writeln!(file, "{}\n{operation}", std::process::id()));
+    Ok(file)
+}
+
+/// Best effort: platforms with mandatory locks refuse the read.
+fn read_owner(path: &Path) -> (Option<u32>, Option<String>) {
+    let mut contents = String::new();
+    if File::open(path)
+        .and_then(|mut f| f.read_to_string(&mut contents))
+        .is_err()
+    {
+        return (None, None);
+    }
+    let mut lines = contents.lines();
+    let pid = lines.next().and_then(|l| l.trim().parse().ok());
+    let operation = lines.next().map(str::to_string).filter(|op| !op.is_empty());
+    (pid, operation)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{temp_dir, TempDir};
+
+    fn temp_lock(label: &str) -> (TempDir, PathBuf) {
+        let dir = temp_dir(&format!("lock-{label}"));
+        let path = dir.join("gitforge.db.lock");
+        (dir, path)
+    }
+
+    #[test]
+    fn second_holder_times_out_with_owner_details() {
+        let (_dir, path) = temp_lock("busy");
+        let _held = acquire_path(path.clone(), "pr merge", Duration::ZERO).expect("first lock");
+
+        let err = acquire_path(path, "worktree prune", Duration::from_millis(100))
+            .err()
+            .expect("second lock must fail");
+        assert_eq!(
+            err,
+            LockError::Busy {
+                pid: Some(std::process::id()),
+                operation: Some("pr merge".to_string()),
+            }
+        );
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "gitforge.db is locked by PID {} (pr merge)",
+                std::process::id()
+            )
+        );
+    }
+
+    #[test]
+    fn waiter_proceeds_once_the_holder_releases() {
+        let (_dir, path) = temp_lock("handoff");
+        let held = acquire_path(path.clone(), "import", Duration::ZERO).expect("first lock");
+
+        let waiter = {
+            let path = path.clone();
+            std::thread::spawn(move || {
+                acquire_path(path, "maintain", Duration::from_secs(5)).map(|_| ())
+            })
+        };
+        std::thread::sleep(Duration::from_millis(50));
+        drop(held);
+
+        assert_eq!(waiter.join().expect("waiter thread"), Ok(()));
+    }
+
+    #[test]
+    fn file_lock_reports_holder_from_lock_file() {
+        let (_dir, path) = temp_lock("file");
+        let holder = File::create(&path).expect("create lock file");
+        holder.try_lock().expect("lock as another holder");
+        std::fs::write(&path, "4242\nui: stage hunk\n").expect("write owner");
+
+        // Same process, but the in-process table doesn't know this holder,
+        // so the OS lock is what blocks.
+        let err = lock_file(&path, "cli", Instant::now()).expect_err("locked file must fail");
+        assert_eq!(
+            err,
+            LockError::Busy {
+                pid: Some(4242),
+                operation: Some("ui: stage hunk".to_string()),
+            }
+        );
+    }
+}
@@ -0,0 +1,318 @@
+pub mod events;
+pub mod location;
+pub mod lock;
+pub mod portable;
+pub mod search;
+
+pub use location::db_path;
+
+use std::path::PathBuf;
+use std::time::Duration;
+
+pub const DB_FILE_NAME: &str = "gitforge.db";
+
+/// Upper bound on simultaneously open connections per repository; readers
+/// share WAL snapshots, so a handful covers UI + agent bursts.
+pub const POOL_SIZE: u32 = 4;
+
+pub type Pool = r2d2::Pool<r2d2_sqlite::SqliteConnectionManager>;
+pub type PooledConnection = r2d2::PooledConnection<r2d2_sqlite::SqliteConnectionManager>;
+
+/// How long a connection waits on a lock held by another reader/writer
+/// (UI, CLI, MCP server) before failing with `database is locked`.
+pub const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+pub(crate) const SCHEMA: &str = "CREATE TABLE IF NOT EXISTS prs (
+        id INTEGER PRIMARY KEY,
+        title TEXT NOT NULL,
+        from_branch TEXT,
+        to_branch TEXT,
+        state TEXT DEFAULT 'open',
+        created_at TEXT DEFAULT CURRENT_TIMESTAMP,
+        description TEXT,
+        links TEXT,
+        approvals TEXT,
+        reviewers TEXT,
+        merged_at TEXT
+    );
+    CREATE TABLE IF NOT EXISTS pr_comments (
+        id INTEGER PRIMARY KEY,
+        pr_id INTEGER NOT NULL REFERENCES prs(id),
+        author TEXT,
+        body TEXT NOT NULL,
+        path TEXT,
+        line INTEGER,
+        created_at TEXT DEFAULT CURRENT_TIMESTAMP,
+        suggestion TEXT,
+        applied_commit TEXT
+    );
+    CREATE TABLE IF NOT EXISTS issues (
+        id INTEGER PRIMARY KEY,
+        title TEXT NOT NULL,
+        body TEXT,
+        state TEXT NOT NULL DEFAULT 'open',
+        goal_id TEXT,
+        pr_id INTEGER,
+        created_at TEXT DEFAULT CURRENT_TIMESTAMP,
+        updated_at TEXT DEFAULT CURRENT_TIMESTAMP
+    );
+    CREATE TABLE IF NOT EXISTS pr_checks (
+        id INTEGER PRIMARY KEY,
+        pr_id INTEGER NOT NULL REFERENCES prs(id),
+        provider TEXT NOT NULL,
+        branch TEXT NOT NULL,
+        status TEXT NOT NULL,
+        conclusion TEXT,
+        run_id TEXT,
+        url TEXT,
+        created_at TEXT DEFAULT CURRENT_TIMESTAMP,
+        updated_at TEXT DEFAULT CURRENT_TIMESTAMP
+    );
+    CREATE TABLE IF NOT EXISTS goals (
+        id INTEGER PRIMARY KEY,
+        goal_id TEXT NOT NULL UNIQUE,
+        task TEXT NOT NULL DEFAULT '',
+        status TEXT,
+        updated_at TEXT DEFAULT CURRENT_TIMESTAMP
+    );
+    CREATE TABLE IF NOT EXISTS goal_logs (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        goal_id TEXT NOT NULL,
+        line TEXT NOT NULL,
+        recorded_at TEXT DEFAULT CURRENT_TIMESTAMP
+    );
+    CREATE TABLE IF NOT EXISTS artifacts (
+        id INTEGER PRIMARY KEY,
+        hash TEXT NOT NULL,
+        name TEXT NOT NULL,
+        media_type TEXT NOT NULL,
+        size INTEGER NOT NULL,
+        goal_id TEXT,
+        pr_id INTEGER,
+        created_at TEXT DEFAULT CURRENT_TIMESTAMP
+    );
+    CREATE TABLE IF NOT EXISTS worktrees (
+        id INTEGER PRIMARY KEY,
+        name TEXT UNIQUE,
+        path TEXT,
+        branch TEXT,
+        created_at TEXT DEFAULT CURRENT_TIMESTAMP
+    );
+    CREATE TABLE IF NOT EXISTS events (
+        seq INTEGER PRIMARY KEY AUTOINCREMENT,
+        schema_version INTEGER NOT NULL,
+        kind TEXT NOT NULL,
+        payload TEXT NOT NULL,
+        recorded_at TEXT DEFAULT CURRENT_TIMESTAMP
+    );
+    CREATE TABLE IF NOT EXISTS settings (
+        key TEXT PRIMARY KEY,
+        value TEXT NOT NULL,
+        updated_at TEXT DEFAULT CURRENT_TIMESTAMP
+    );
+    CREATE TABLE IF NOT EXISTS commit_stats (
+        oid TEXT PRIMARY KEY,
+        author TEXT NOT NULL,
+        email TEXT NOT NULL,
+        time INTEGER NOT NULL,
+        files TEXT NOT NULL
+    );";
+
+/// Created after column migrations so older tables have every indexed column.
+pub(crate) const INDEXES: &str = "
+    CREATE INDEX IF NOT EXISTS prs_state_created ON prs (state, created_at);
+    CREATE INDEX IF NOT EXISTS pr_comments_pr ON pr_comments (pr_id, created_at);
+    CREATE INDEX IF NOT EXISTS issues_state ON issues (state);
+    CREATE INDEX IF NOT EXISTS pr_checks_pr ON pr_checks (pr_id);
+    CREATE INDEX IF NOT EXISTS worktrees_branch ON worktrees (branch);
+    CREATE INDEX IF NOT EXISTS goals_status ON goals (status, updated_at);
+    CREATE INDEX IF NOT EXISTS goal_logs_goal ON goal_logs (goal_id, id);
+    CREATE INDEX IF NOT EXISTS artifacts_goal ON artifacts (goal_id);
+    CREATE INDEX IF NOT EXISTS artifacts_pr ON artifacts (pr_id);
+    CREATE INDEX IF NOT EXISTS events_kind ON events (kind, seq);
+    CREATE INDEX IF NOT EXISTS events_recorded ON events (recorded_at);";
+
+/// Resolves the database file for `repo_path`, moving a legacy working-tree
+/// copy into place and creating parent directories as needed.
+fn prepare_path(repo_path: &str) -> Result<PathBuf, String> {
+    let path = db_path(repo_path);
+    if location::migrate_legacy(repo_path, &path)? {
+        eprintln!("📦 Moved gitforge.db to {}", path.display());
+    }
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| format!("failed to create {}: {e}", parent.display()))?;
+    }
+    Ok(path)
+}
+
+/// Opens the metadata database for `repo_path` and ensures the schema exists.
+pub fn open(repo_path: &str) -> Result<rusqlite::Connection, String> {
+    let mut db = rusqlite::Connection::open(prepare_path(repo_path)?)
+        .map_err(|e| format!("failed to open sqlite db: {e}"))?;
+
+    configure(&mut db).map_err(|e| format!("failed to configure sqlite db: {e}"))?;
+    init_schema(&db)?;
+
+    Ok(db)
+}
+
+/// Opens a connection pool for `repo_path`; every pooled connection gets the
+/// same pragmas as [`open`], and the schema is created once up front.
+pub fn open_pool(repo_path: &str) -> Result<Pool, String> {
+    let manager =
+        r2d2_sqlite::SqliteConnectionManager::file(prepare_path(repo_path)?).with_init(configure);
+    let pool = r2d2::Pool::builder()
+        .max_size(POOL_SIZE)
+        .connection_timeout(BUSY_TIMEOUT)
+        .build(manager)
+        .map_err(|e| format!("failed to open sqlite pool: {e}"))?;
+
+    let db = pool
+        .get()
+        .map_err(|e| format!("failed to open sqlite db: {e}"))?;
+    init_schema(&db)?;
+
+    Ok(pool)
+}
+
+fn init_schema(db: &rusqlite::Connection) -> Result<(), String> {
+    db.execute_batch(SCHEMA)
+        .map_err(|e| format!("failed to initialize db: {e}"))?;
+    add_column_if_missing(db, "prs", "description", "TEXT")?;
+    add_column_if_missing(db, "prs", "links", "TEXT")?;
+    add_column_if_missing(db, "prs", "approvals", "TEXT")?;
+    add_column_if_missing(db, "prs", "reviewers", "TEXT")?;
+    add_column_if_missing(db, "prs", "merged_at", "TEXT")?;
+    add_column_if_missing(db, "pr_comments", "suggestion", "TEXT")?;
+    add_column_if_missing(db, "pr_comments", "applied_commit", "TEXT")?;
+    db.execute_batch(INDEXES)
+        .map_err(|e| format!("failed to create indices: {e}"))?;
+    search::ensure_index(db)
+}
+
+/// `CREATE TABLE IF NOT EXISTS` leaves tables from older releases alone, so
+/// columns added later are patched in here.
+fn add_column_if_missing(
+    db: &rusqlite::Connection,
+    table: &str,
+    column: &str,
+    decl: &str,
+) -> Result<(), String> {
+    let exists: bool = db
+        .query_row(
+            &format!("SELECT EXISTS(SELECT 1 FROM pragma_table_info('{table}') WHERE name = ?1)"),
+            [column],
+            |row| row.get(0),
+        )
+        .map_err(|e| format!("failed to inspect table '{table}': {e}"))?;
+    if !exists {
+        db.execute_batch(&format!("ALTER TABLE {table} ADD COLUMN {column} {decl}"))
+            .map_err(|e| format!("failed to add column '{table}.{column}': {e}"))?;
+    }
+    Ok(())
+}
+
+/// WAL lets readers proceed while a writer is active; the busy timeout
+/// covers the remaining writer/writer contention.
+fn configure(db: &mut rusqlite::Connection) -> rusqlite::Result<()> {
+    db.busy_timeout(BUSY_TIMEOUT)?;
+    db.pragma_update_and_check(None, "journal_mode", "WAL", |_| Ok(()))?;
+    db.pragma_update(None, "synchronous", "NORMAL")?;
+    db.pragma_update(None, "foreign_keys", true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::temp_dir;
+
+    #[test]
+    fn open_enables_wal_and_foreign_keys() {
+        let dir = temp_dir("db-pragmas");
+        let db = open(dir.as_str()).expect("open db");
+
+        let mode: String = db
+            .query_row("PRAGMA journal_mode", [], |row| row.get(0))
+            .expect("journal mode");
+        let foreign_keys: i64 = db
+            .query_row("PRAGMA foreign_keys", [], |row| row.get(0))
+            .expect("foreign keys");
+
+        assert_eq!(mode, "wal");
+        assert_eq!(foreign_keys, 1);
+    }
+
+    #[test]
+    fn readers_are_not_blocked_by_an_open_writer() {
+        let dir = temp_dir("db-concurrent");
+        let writer = open(dir.as_str()).expect("open writer");
+        let reader = open(dir.as_str()).expect("open reader");
+
+        writer
+            .execute_batch(
+                "BEGIN IMMEDIATE;
+                 INSERT INTO prs (title, from_branch, to_branch) VALUES ('wip', 'a', 'b');",
+            )
+            .expect("start write transaction");
+
+        let count: i64 = reader
+            .query_row("SELECT COUNT(*) FROM prs", [], |row| row.get(0))
+            .expect("read during write");
+        assert_eq!(count, 0);
+
+        writer.execute_batch("COMMIT").expect("commit");
+    }
+
+    #[test]
+    fn pool_hands_out_connections_that_read_during_a_write() {
+        let dir = temp_dir("db-pool");
+        let pool = open_pool(dir.as_str()).expect("open pool");
+
+        let writer = pool.get().expect("writer connection");
+        let reader = pool.get().expect("reader connection");
+        writer
+            .execute_batch(
+                "BEGIN IMMEDIATE;
+                 INSERT INTO worktrees (name, path, branch) VALUES ('wt', '/tmp/wt', 'b');",
+            )
+            .expect("start write transaction");
+
+        let count: i64 = reader
+            .query_row("SELECT COUNT(*) FROM worktrees", [], |row| row.get(0))
+            .expect("read during write");
+        assert_eq!(count, 0);
+
+        let foreign_keys: i64 = reader
+            .query_row("PRAGMA foreign_keys", [], |row| row.get(0))
+            .expect("foreign keys");
+        assert_eq!(foreign_keys, 1);
+
+        writer.execute_batch("COMMIT").expect("commit");
+    }
+
+    #[test]
+    fn tables_from_older_releases_gain_new_columns() {
+        let dir = temp_dir("db-upgrade");
+        let repo = dir.as_str();
+        rusqlite::Connection::open(db_path(repo))
+            .expect("open legacy db")
+            .execute_batch(
+                "CREATE TABLE prs (
+                     id INTEGER PRIMARY KEY,
+                     title TEXT NOT NULL,
+                     from_branch TEXT,
+                     to_branch TEXT,
+                     state TEXT DEFAULT 'open',
+                     created_at TEXT DEFAULT CURRENT_TIMESTAMP
+                 );
+                 INSERT INTO prs (title) VALUES ('from v0');",
+            )
+            .expect("create legacy schema");
+
+        let db = open(repo).expect("open upgraded db");
+        db.execute("UPDATE prs SET description = 'now searchable'", [])
+            .expect("description column exists");
+        assert_eq!(search::search(&db, "v0", 10).expect("search").len(), 1);
+    }
+}
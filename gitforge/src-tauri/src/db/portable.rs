@@ -0,0 +1,309 @@
+use rusqlite::types::{Value, ValueRef};
+use serde::{Deserialize, Serialize};
+
+pub const EXPORT_FORMAT: &str = "gitforge-export";
+pub const EXPORT_VERSION: u16 = 1;
+
+/// Portable snapshot of every metadata table in `gitforge.db`.
+///
+/// Tables are exported generically (schema SQL plus rows keyed by column), so
+/// tables added by newer versions travel without changes to this module.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Export {
+    pub format: String,
+    pub version: u16,
+    pub tables: Vec<TableDump>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TableDump {
+    pub name: String,
+    pub sql: String,
+    pub rows: Vec<serde_json::Map<String, serde_json::Value>>,
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct ImportSummary {
+    pub tables: usize,
+    pub rows: usize,
+}
+
+pub fn export(db: &rusqlite::Connection) -> Result<Export, String> {
+    let mut stmt = db
+        .prepare(
+            // Search indexes are derived data rebuilt by triggers on import,
+            // so FTS virtual tables and their shadow tables are skipped.
+            "SELECT name, sql FROM sqlite_master m
+             WHERE type = 'table' AND name NOT LIKE 'sqlite_%'
+               AND sql NOT LIKE 'CREATE VIRTUAL TABLE%'
+               AND NOT EXISTS (
+                   SELECT 1 FROM sqlite_master v
+                   WHERE v.type = 'table' AND v.sql LIKE 'CREATE VIRTUAL TABLE%'
+                     AND m.name LIKE v.name || '\\_%' ESCAPE '\\'
+               )
+             ORDER BY name",
+        )
+        .map_err(|e| format!("failed to list tables: {e}"))?;
+
+    let tables = stmt
+        .query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })
+        .map_err(|e| format!("failed to list tables: {e}"))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("failed to read table list: {e}"))?;
+
+    let mut dumps = Vec::with_capacity(tables.len());
+    for (name, sql) in tables {
+        let rows = dump_rows(db, &name)?;
+        dumps.push(TableDump { name, sql, rows });
+    }
+
+    Ok(Export {
+        format: EXPORT_FORMAT.to_string(),
+        version: EXPORT_VERSION,
+        tables: dumps,
+    })
+}
+
+/// Imports a snapshot into `db`, creating missing tables and replacing rows
+/// that share a primary key. Runs in a single transaction.
+pub fn import(db: &mut rusqlite::Connection, export: &Export) -> Result<ImportSummary, String> {
+    if export.format != EXPORT_FORMAT {
+        return Err(format!("unsupported export format '{}'", export.format));
+    }
+    if export.version != EXPORT_VERSION {
+        return Err(format!(
+            "unsupported export version {} (expected {EXPORT_VERSION})",
+            export.version
+        ));
+    }
+
+    let tx = db
+        .transaction()
+        .map_err(|e| format!("failed to begin import: {e}"))?;
+    // Tables are imported alphabetically, not in dependency order.
+    tx.pragma_update(None, "defer_foreign_keys", true)
+        .map_err(|e| format!("failed to begin import: {e}"))?;
+
+    let mut summary = ImportSummary::default();
+    for table in &export.tables {
+        if !is_identifier(&table.name) {
+            return Err(format!("invalid table name '{}'", table.name));
+        }
+
+        let exists: bool = tx
+            .query_row(
+                "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?1)",
+                [&table.name],
+                |row| row.get(0),
+            )
+            .map_err(|e| format!("failed to inspect table '{}': {e}", table.name))?;
+        if !exists {
+            tx.execute_batch(&table.sql)
+                .map_err(|e| format!("failed to create table '{}': {e}", table.name))?;
+        }
+
+        for row in &table.rows {
+            insert_row(&tx, &table.name, row)?;
+            summary.rows += 1;
+        }
+        summary.tables += 1;
+    }
+
+    tx.commit()
+        .map_err(|e| format!("failed to commit import: {e}"))?;
+
+    Ok(summary)
+}
+
+fn dump_rows(
+    db: &rusqlite::Connection,
+    table: &str,
+) -> Result<Vec<serde_json::Map<String, serde_json::Value>>, String> {
+    let mut stmt = db
+        .prepare(&format!("SELECT * FROM \"{table}\""))
+        .map_err(|e| format!("failed to read table '{table}': {e}"))?;
+    let columns: Vec<String> = stmt.column_names().iter().map(|c| c.to_string()).collect();
+
+    let mut rows = stmt
+        .query([])
+        .map_err(|e| format!("failed to read table '{table}': {e}"))?;
+
+    let mut items = Vec::new();
+    while let Some(row) = rows
+        .next()
+        .map_err(|e| format!("failed to read row from '{table}': {e}"))?
+    {
+        let mut item = serde_json::Map::new();
+        for (idx, column) in columns.iter().enumerate() {
+            let value = row
+                .get_ref(idx)
+                .map_err(|e| format!("failed to read column '{column}': {e}"))?;
+            item.insert(column.clone(), value_to_json(value));
+        }
+        items.push(item);
+    }
+
+    Ok(items)
+}
+
+fn insert_row(
+    tx: &rusqlite::Transaction<'_>,
+    table: &str,
+    row: &serde_json::Map<String, serde_json::Value>,
+) -> Result<(), String> {
+    if let Some(column) = row.keys().find(|c| !is_identifier(c)) {
+        return Err(format!("invalid column name '{column}' in '{table}'"));
+    }
+
+    let columns: Vec<String> = row.keys().map(|c| format!("\"{c}\"")).collect();
+    let placeholders: Vec<String> = (1..=row.len()).map(|i| format!("?{i}")).collect();
+    let sql = format!(
+        "INSERT OR REPLACE INTO \"{table}\" ({}) VALUES ({})",
+        columns.join(", "),
+        placeholders.join(", ")
+    );
+
+    let values: Vec<Value> = row.values().map(json_to_value).collect();
+    tx.execute(&sql, rusqlite::params_from_iter(values))
+        .map_err(|e| format!("failed to import row into '{table}': {e}"))?;
+
+    Ok(())
+}
+
+fn value_to_json(value: ValueRef<'_>) -> serde_json::Value {
+    match value {
+        ValueRef::Null => serde_json::Value::Null,
+        ValueRef::Integer(i) => serde_json::json!(i),
+        ValueRef::Real(f) => serde_json::json!(f),
+        ValueRef::Text(t) => serde_json::json!(String::from_utf8_lossy(t)),
+        ValueRef::Blob(b) => {
+            let hex: String = b.iter().map(|byte| format!("{byte:02x}")).collect();
+            serde_json::json!({ "blob": hex })
+        }
+    }
+}
+
+fn json_to_value(value: &serde_json::Value) -> Value {
+    match value {
+        serde_json::Value::Null => Value::Null,
+        serde_json::Value::Bool(b) => Value::Integer(i64::from(*b)),
+        serde_json::Value::Number(n) => match n.as_i64() {
+            Some(i) => Value::Integer(i),
+            None => Value::Real(n.as_f64().unwrap_or_default()),
+        },
+        serde_json::Value::String(s) => Value::Text(s.clone()),
+        serde_json::Value::Object(obj) => match obj.get("blob").and_then(|v| v.as_str()) {
+            Some(hex) if obj.len() == 1 => Value::Blob(decode_hex(hex)),
+            _ => Value::Text(value.to_string()),
+        },
+        serde_json::Value::Array(_) => Value::Text(value.to_string()),
+    }
+}
+
+fn decode_hex(hex: &str) -> Vec<u8> {
+    (0..hex.len())
+        .step_by(2)
+        .filter_map(|i| hex.get(i..i + 2))
+        .filter_map(|pair| u8::from_str_radix(pair, 16).ok())
+        .collect()
+}
+
+fn is_identifier(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn memory_db() -> rusqlite::Connection {
+        let db = rusqlite::Connection::open_in_memory().expect("open memory db");
+        db.execute_batch(super::super::SCHEMA).expect("init schema");
+        db
+    }
+
+    #[test]
+    fn export_import_roundtrip_preserves_rows() {
+        let source = memory_db();
+        source
+            .execute(
+                "INSERT INTO prs (title, from_branch, to_branch) VALUES ('Export PR', 'feature/a', 'main')",
+                [],
+            )
+            .expect("insert pr");
+        source
+            .execute(
+                "INSERT INTO worktrees (name, path, branch) VALUES ('wt', '/tmp/wt', 'feature/a')",
+                [],
+            )
+            .expect("insert worktree");
+
+        let snapshot = export(&source).expect("export");
+        let json = serde_json::to_string(&snapshot).expect("serialize export");
+        let snapshot: Export = serde_json::from_str(&json).expect("parse export");
+
+        let mut target = memory_db();
+        let summary = import(&mut target, &snapshot).expect("import");
+        assert_eq!(summary.rows, 2);
+
+        let title: String = target
+            .query_row("SELECT title FROM prs", [], |row| row.get(0))
+            .expect("pr imported");
+        assert_eq!(title, "Export PR");
+    }
+
+    #[test]
+    fn import_creates_tables_missing_from_target() {
+        let source = memory_db();
+        source
+            .execute_batch(
+                "CREATE TABLE notes (id INTEGER PRIMARY KEY, body TEXT);
+                 INSERT INTO notes (body) VALUES ('carry me');",
+            )
+            .expect("create extra table");
+
+        let snapshot = export(&source).expect("export");
+        let mut target = memory_db();
+        import(&mut target, &snapshot).expect("import");
+
+        let body: String = target
+            .query_row("SELECT body FROM notes", [], |row| row.get(0))
+            .expect("note imported");
+        assert_eq!(body, "carry me");
+    }
+
+    #[test]
+    fn search_index_is_rebuilt_instead_of_exported() {
+        let source = memory_db();
+        super::super::search::ensure_index(&source).expect("create index");
+        source
+            .execute_batch(
+                "INSERT INTO prs (id, title) VALUES (1, 'Indexed PR');
+                 INSERT INTO pr_comments (pr_id, body) VALUES (1, 'indexed comment');",
+            )
+            .expect("insert rows");
+
+        let snapshot = export(&source).expect("export");
+        assert!(snapshot.tables.iter().all(|t| !t.name.contains("_fts")));
+
+        let mut target = memory_db();
+        super::super::search::ensure_index(&target).expect("create index");
+        import(&mut target, &snapshot).expect("import");
+        let hits = super::super::search::search(&target, "indexed", 10).expect("search");
+        assert_eq!(hits.len(), 2);
+    }
+
+    #[test]
+    fn import_rejects_unknown_format() {
+        let snapshot = Export {
+            format: "something-else".into(),
+            version: EXPORT_VERSION,
+            tables: Vec::new(),
+        };
+
+        let mut target = memory_db();
+        assert!(import(&mut target, &snapshot).is_err());
+    }
+}
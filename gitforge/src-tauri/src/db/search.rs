@@ -0,0 +1,260 @@
+use serde::Serialize;
+
+/// Default and maximum number of hits [`search`] returns.
+pub const DEFAULT_LIMIT: usize = 20;
+pub const MAX_LIMIT: usize = 200;
+
+/// One FTS5 table per entity, keyed by the entity's rowid so triggers can
+/// update the index without scanning it. Each insert trigger deletes first:
+/// `INSERT OR REPLACE` (used by import) does not fire delete triggers.
+const INDEX_SCHEMA: &str = "
+    CREATE VIRTUAL TABLE prs_fts USING fts5(title, body, tokenize = 'porter unicode61');
+    CREATE VIRTUAL TABLE pr_comments_fts USING fts5(body, tokenize = 'porter unicode61');
+    CREATE VIRTUAL TABLE goals_fts USING fts5(goal_id, task, tokenize = 'porter unicode61');
+
+    CREATE TRIGGER prs_fts_insert AFTER INSERT ON prs BEGIN
+        DELETE FROM prs_fts WHERE rowid = new.id;
+        INSERT INTO prs_fts (rowid, title, body) VALUES (new.id, new.title, coalesce(new.description, ''));
+    END;
+    CREATE TRIGGER prs_fts_update AFTER UPDATE OF title, description ON prs BEGIN
+        DELETE FROM prs_fts WHERE rowid = old.id;
+        INSERT INTO prs_fts (rowid, title, body) VALUES (new.id, new.title, coalesce(new.description, ''));
+    END;
+    CREATE TRIGGER prs_fts_delete AFTER DELETE ON prs BEGIN
+        DELETE FROM prs_fts WHERE rowid = old.id;
+    END;
+
+    CREATE TRIGGER pr_comments_fts_insert AFTER INSERT ON pr_comments BEGIN
+        DELETE FROM pr_comments_fts WHERE rowid = new.id;
+        INSERT INTO pr_comments_fts (rowid, body) VALUES (new.id, new.body);
+    END;
+    CREATE TRIGGER pr_comments_fts_update AFTER UPDATE OF body ON pr_comments BEGIN
+        DELETE FROM pr_comments_fts WHERE rowid = old.id;
+        INSERT INTO pr_comments_fts (rowid, body) VALUES (new.id, new.body);
+    END;
+    CREATE TRIGGER pr_comments_fts_delete AFTER DELETE ON pr_comments BEGIN
+        DELETE FROM pr_comments_fts WHERE rowid = old.id;
+    END;
+
+    CREATE TRIGGER goals_fts_insert AFTER INSERT ON goals BEGIN
+        DELETE FROM goals_fts WHERE rowid = new.id;
+        INSERT INTO goals_fts (rowid, goal_id, task) VALUES (new.id, new.goal_id, new.task);
+    END;
+    CREATE TRIGGER goals_fts_update AFTER UPDATE OF task ON goals BEGIN
+        DELETE FROM goals_fts WHERE rowid = old.id;
+        INSERT INTO goals_fts (rowid, goal_id, task) VALUES (new.id, new.goal_id, new.task);
+    END;
+    CREATE TRIGGER goals_fts_delete AFTER DELETE ON goals BEGIN
+        DELETE FROM goals_fts WHERE rowid = old.id;
+    END;
+
+    INSERT INTO prs_fts (rowid, title, body) SELECT id, title, coalesce(description, '') FROM prs;
+    INSERT INTO pr_comments_fts (rowid, body) SELECT id, body FROM pr_comments;
+    INSERT INTO goals_fts (rowid, goal_id, task) SELECT id, goal_id, task FROM goals;";
+
+/// Creates the search index and its triggers on first use, backfilling rows
+/// that were written before the index existed.
+pub fn ensure_index(db: &rusqlite::Connection) -> Result<(), String> {
+    let exists: bool = db
+        .query_row(
+            "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'prs_fts')",
+            [],
+            |row| row.get(0),
+        )
+        .map_err(|e| format!("failed to inspect search index: {e}"))?;
+    if exists {
+        return Ok(());
+    }
+
+    db.execute_batch(&format!("BEGIN; {INDEX_SCHEMA} COMMIT;"))
+        .map_err(|e| {
+            let _ = db.execute_batch("ROLLBACK");
+            format!("failed to create search index: {e}")
+        })
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HitKind {
+    Pr,
+    Comment,
+    Goal,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchHit {
+    pub kind: HitKind,
+    /// PR or comment id, or the goal id.
+    pub id: String,
+    /// Owning PR for comments.
+    pub pr_id: Option<i64>,
+    pub title: String,
+    /// Matching text with hits wrapped in `[` `]`.
+    pub snippet: String,
+    /// bm25 score; lower is more relevant.
+    pub rank: f64,
+}
+
+/// Ranked hits across PRs, review comments, and goals. Every word in `query`
+/// must match, and the last one is treated as a prefix so results update
+/// while the user types.
+pub fn search(
+    db: &rusqlite::Connection,
+    query: &str,
+    limit: usize,
+) -> Result<Vec<SearchHit>, String> {
+    let Some(expr) = match_expression(query) else {
+        return Ok(Vec::new());
+    };
+    let limit = limit.clamp(1, MAX_LIMIT) as i64;
+
+    let mut stmt = db
+        .prepare(
+            "SELECT 'pr', CAST(p.id AS TEXT), NULL, p.title,
+                    snippet(prs_fts, -1, '[', ']', '…', 12), bm25(prs_fts, 10.0, 1.0)
+               FROM prs_fts JOIN prs p ON p.id = prs_fts.rowid
+              WHERE prs_fts MATCH ?1
+             UNION ALL
+             SELECT 'comment', CAST(c.id AS TEXT), c.pr_id, coalesce(p.title, ''),
+                    snippet(pr_comments_fts, 0, '[', ']', '…', 12), bm25(pr_comments_fts)
+               FROM pr_comments_fts JOIN pr_comments c ON c.id = pr_comments_fts.rowid
+               LEFT JOIN prs p ON p.id = c.pr_id
+              WHERE pr_comments_fts MATCH ?1
+             UNION ALL
+             SELECT 'goal', g.goal_id, NULL, g.goal_id,
+                    snippet(goals_fts, 1, '[', ']', '…', 12), bm25(goals_fts, 2.0, 1.0)
+               FROM goals_fts JOIN goals g ON g.id = goals_fts.rowid
+              WHERE goals_fts MATCH ?1
+             ORDER BY 6 LIMIT ?2",
+        )
+        .map_err(|e| format!("failed to prepare search: {e}"))?;
+
+    let rows = stmt
+        .query_map(rusqlite::params![expr, limit], |row| {
+            let kind = match row.get::<_, String>(0)?.as_str() {
+                "pr" => HitKind::Pr,
+                "comment" => HitKind::Comment,
+                _ => HitKind::Goal,
+            };
+            Ok(SearchHit {
+                kind,
+                id: row.get(1)?,
+                pr_id: row.get(2)?,
+                title: row.get(3)?,
+                snippet: row.get(4)?,
+                rank: row.get(5)?,
+            })
+        })
+        .map_err(|e| format!("failed to search: {e}"))?;
+
+    rows.collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("failed to read search hits: {e}"))
+}
+
+/// Turns free text into an FTS5 expression, quoting every term so user
+/// input can never be parsed as query syntax.
+fn match_expression(query: &str) -> Option<String> {
+    let terms: Vec<String> = query
+        .split_whitespace()
+        .map(|term| format!("\"{}\"", term.replace('"', "\"\"")))
+        .collect();
+    let (last, rest) = terms.split_last()?;
+
+    let mut expr = rest.join(" ");
+    if !expr.is_empty() {
+        expr.push(' ');
+    }
+    expr.push_str(last);
+    expr.push('*');
+    Some(expr)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn memory_db() -> rusqlite::Connection {
+        let db = rusqlite::Connection::open_in_memory().expect("open memory db");
+        db.execute_batch(super::super::SCHEMA).expect("init schema");
+        ensure_index(&db).expect("create index");
+        db
+    }
+
+    #[test]
+    fn search_ranks_hits_across_entity_types() {
+        let db = memory_db();
+        db.execute_batch(
+            "INSERT INTO prs (id, title, description) VALUES
+                 (1, 'Cache repository handles', 'Avoid reopening on every call'),
+                 (2, 'Fix typo', 'README cache section');
+             INSERT INTO pr_comments (pr_id, body) VALUES (2, 'Does this invalidate the cache?');
+             INSERT INTO goals (goal_id, task) VALUES ('G-1', 'Benchmark cache hit rate');",
+        )
+        .expect("seed rows");
+
+        let hits = search(&db, "cache", 10).expect("search");
+        assert_eq!(hits.len(), 4);
+        assert_eq!(hits[0].kind, HitKind::Pr);
+        assert_eq!(hits[0].id, "1", "title matches outrank body matches");
+        assert!(hits
+            .iter()
+            .any(|h| h.kind == HitKind::Goal && h.id == "G-1"));
+
+        let comment = hits
+            .iter()
+            .find(|h| h.kind == HitKind::Comment)
+            .expect("comment hit");
+        assert_eq!(comment.pr_id, Some(2));
+        assert!(comment.snippet.contains("[cache]"));
+    }
+
+    #[test]
+    fn index_follows_updates_deletes_and_replaces() {
+        let db = memory_db();
+        db.execute("INSERT INTO prs (id, title) VALUES (1, 'Old title')", [])
+            .expect("insert");
+        db.execute("UPDATE prs SET title = 'Shiny title' WHERE id = 1", [])
+            .expect("update");
+        assert!(search(&db, "old", 10).expect("search").is_empty());
+        assert_eq!(search(&db, "shiny", 10).expect("search").len(), 1);
+
+        db.execute(
+            "INSERT OR REPLACE INTO prs (id, title) VALUES (1, 'Shiny again')",
+            [],
+        )
+        .expect("replace");
+        assert_eq!(search(&db, "shiny", 10).expect("search").len(), 1);
+
+        db.execute("DELETE FROM prs", []).expect("delete");
+        assert!(search(&db, "shiny", 10).expect("search").is_empty());
+    }
+
+    #[test]
+    fn query_syntax_is_treated_as_text() {
+        let db = memory_db();
+        db.execute(
+            "INSERT INTO prs (title) VALUES ('Handle \"quoted\" AND NOT')",
+            [],
+        )
+        .expect("insert");
+
+        assert_eq!(search(&db, "\"quoted", 10).expect("search").len(), 1);
+        assert_eq!(search(&db, "NOT", 10).expect("search").len(), 1);
+        assert_eq!(search(&db, "han", 10).expect("prefix search").len(), 1);
+        assert!(search(&db, "   ", 10).expect("blank search").is_empty());
+    }
+
+    #[test]
+    fn existing_rows_are_backfilled() {
+        let db = rusqlite::Connection::open_in_memory().expect("open memory db");
+        db.execute_batch(super::super::SCHEMA).expect("init schema");
+        db.execute(
+            "INSERT INTO prs (title) VALUES ('Written before indexing')",
+            [],
+        )
+        .expect("insert");
+
+        ensure_index(&db).expect("create index");
+        assert_eq!(search(&db, "indexing", 10).expect("search").len(), 1);
+    }
+}
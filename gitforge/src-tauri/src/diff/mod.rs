@@ -0,0 +1,242 @@
+//! Unified diffs between two sides of the repository, as `git diff`
+//! prints them, with per-file line counts.
+
+use serde::Serialize;
+
+use crate::content::{INDEX, WORKDIR};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Query<'a> {
+    /// A commit-ish or [`INDEX`].
+    pub base: &'a str,
+    /// A commit-ish, [`INDEX`], or [`WORKDIR`].
+    pub target: &'a str,
+    /// Pathspecs; empty for everything.
+    pub paths: &'a [String],
+    pub context: u32,
+    /// Shows untracked files as added when `target` is the working tree.
+    pub untracked: bool,
+}
+
+impl Default for Query<'_> {
+    /// What `git diff` shows: unstaged changes.
+    fn default() -> Self {
+        Self {
+            base: INDEX,
+            target: WORKDIR,
+            paths: &[],
+            context: 3,
+            untracked: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Diff {
+    pub base: String,
+    pub target: String,
+    pub files: Vec<FileStat>,
+    pub additions: usize,
+    pub deletions: usize,
+    /// Every file's patch, in `files` order.
+    pub patch: String,
+    /// `patch` stops at a line boundary because it passed `budget`;
+    /// `files` and the totals still cover everything.
+    pub truncated: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FileStat {
+    pub path: String,
+    /// Set for renames and copies.
+    pub old_path: Option<String>,
+    /// `added`, `deleted`, `modified`, `renamed`, `copied`, or `typechange`.
+    pub status: &'static str,
+    pub additions: usize,
+    pub deletions: usize,
+    pub binary: bool,
+}
+
+/// Diffs `query.base` against `query.target`, keeping at most `budget`
+/// bytes of patch text.
+pub fn diff(repo: &git2::Repository, query: &Query, budget: usize) -> Result<Diff, String> {
+    let mut opts = git2::DiffOptions::new();
+    opts.context_lines(query.context);
+    for path in query.paths {
+        opts.pathspec(path);
+    }
+    if query.untracked && query.target == WORKDIR {
+        opts.include_untracked(true)
+            .recurse_untracked_dirs(true)
+            .show_untracked_content(true);
+    }
+
+    let tree = |revision: &str| {
+        repo.revparse_single(revision)
+            .and_then(|object| object.peel_to_tree())
+            .map_err(|e| format!("unknown revision '{revision}': {e}"))
+    };
+    let failed = |e: git2::Error| {
+        format!(
+            "failed to diff {} against {}: {e}",
+            query.base, query.target
+        )
+    };
+    let mut diff = match (query.base, query.target) {
+        (WORKDIR, _) => return Err(format!("base can't be {WORKDIR}; diff it as the target")),
+        (INDEX, WORKDIR) => repo.diff_index_to_workdir(None, Some(&mut opts)),
+        (INDEX, target) => {
+            return Err(format!(
+                "target {target} must be {WORKDIR} when base is {INDEX}"
+            ))
+        }
+        (base, INDEX) => repo.diff_tree_to_index(Some(&tree(base)?), None, Some(&mut opts)),
+        (base, WORKDIR) => {
+            repo.diff_tree_to_workdir_with_index(Some(&tree(base)?), Some(&mut opts))
+        }
+        (base, target) => {
+            repo.diff_tree_to_tree(Some(&tree(base)?), Some(&tree(target)?), Some(&mut opts))
+        }
+    }
+    .map_err(failed)?;
+    diff.find_similar(None).map_err(failed)?;
+
+    let mut files = Vec::new();
+    for (idx, delta) in diff.deltas().enumerate() {
+        let path = |file: git2::DiffFile| {
+            file.path()
+                .map(|path| path.to_string_lossy().into_owned())
+                .unwrap_or_default()
+        };
+        let (additions, deletions) = match git2::Patch::from_diff(&diff, idx).map_err(failed)? {
+            Some(patch) => {
+                let (_, additions, deletions) = patch.line_stats().map_err(failed)?;
+                (additions, deletions)
+            }
+            None => (0, 0),
+        };
+        let renamed = matches!(delta.status(), git2::Delta::Renamed | git2::Delta::Copied);
+        files.push(FileStat {
+            path: path(delta.new_file()),
+            old_path: renamed.then(|| path(delta.old_file())),
+            status: status_name(delta.status()),
+            additions,
+            deletions,
+            binary: delta.flags().is_binary(),
+        });
+    }
+
+    let mut patch = String::new();
+    let mut truncated = false;
+    diff.print(git2::DiffFormat::Patch, |_, _, line| {
+        let content = String::from_utf8_lossy(line.content());
+        let origin = matches!(line.origin(), '+' | '-' | ' ').then_some(line.origin());
+        if patch.len() + content.len() + 1 > budget {
+            truncated = true;
+            return false;
+        }
+        patch.extend(origin);
+        patch.push_str(&content);
+        true
+    })
+    .or_else(|e| if truncated { Ok(()) } else { Err(failed(e)) })?;
+
+    Ok(Diff {
+        base: query.base.to_string(),
+        target: query.target.to_string(),
+        additions: files.iter().map(|file| file.additions).sum(),
+        deletions: files.iter().map(|file| file.deletions).sum(),
+        files,
+        patch,
+        truncated,
+    })
+}
+
+fn status_name(status: git2::Delta) -> &'static str {
+    match status {
+        git2::Delta::Added | git2::Delta::Untracked => "added",
+        git2::Delta::Deleted => "deleted",
+        git2::Delta::Renamed => "renamed",
+        git2::Delta::Copied => "copied",
+        git2::Delta::Typechange => "typechange",
+        _ => "modified",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::path::Path;
+
+    #[test]
+    fn diffs_each_side_with_stats_and_a_bounded_patch() {
+        let (dir, repo) = crate::test_support::init_repo("diff");
+        let sig = git2::Signature::now("GitForge Test", "test@gitforge.dev").expect("sig");
+        let stage = |path: &str, text: &str| {
+            fs::write(dir.join(path), text).expect("write");
+            let mut index = repo.index().expect("index");
+            index.add_path(Path::new(path)).expect("add");
+            index.write().expect("write index");
+        };
+        stage("a.txt", "one\ntwo\n");
+        let tree = repo
+            .find_tree(repo.index().unwrap().write_tree().unwrap())
+            .unwrap();
+        repo.commit(Some("HEAD"), &sig, &sig, "init", &tree, &[])
+            .expect("commit");
+
+        stage("a.txt", "one\ntwo\nthree\n");
+        fs::write(dir.join("a.txt"), "one\n2\nthree\n").expect("edit");
+        fs::write(dir.join("new.txt"), "new\n").expect("untracked");
+
+        let unstaged = diff(&repo, &Query::default(), usize::MAX).expect("unstaged");
+        assert_eq!(unstaged.files.len(), 1);
+        assert_eq!((unstaged.additions, unstaged.deletions), (1, 1));
+        assert!(unstaged.patch.contains("-two\n+2\n"), "{}", unstaged.patch);
+
+        let staged = diff(
+            &repo,
+            &Query {
+                base: "HEAD",
+                target: INDEX,
+                ..Query::default()
+            },
+            usize::MAX,
+        )
+        .expect("staged");
+        assert_eq!(staged.files[0].status, "modified");
+        assert_eq!((staged.additions, staged.deletions), (1, 0));
+        assert!(staged.patch.starts_with("diff --git a/a.txt b/a.txt\n"));
+
+        let everything = diff(
+            &repo,
+            &Query {
+                base: "HEAD",
+                untracked: true,
+                ..Query::default()
+            },
+            usize::MAX,
+        )
+        .expect("workdir");
+        let files: Vec<(&str, &str)> = everything
+            .files
+            .iter()
+            .map(|file| (file.path.as_str(), file.status))
+            .collect();
+        assert_eq!(files, [("a.txt", "modified"), ("new.txt", "added")]);
+
+        let cut = diff(&repo, &Query::default(), 40).expect("bounded");
+        assert!(cut.truncated && cut.patch.len() <= 40);
+        assert_eq!(cut.additions, 1);
+        assert!(diff(
+            &repo,
+            &Query {
+                target: "HEAD",
+                ..Query::default()
+            },
+            usize::MAX
+        )
+        .is_err());
+    }
+}
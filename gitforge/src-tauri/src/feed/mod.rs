@@ -0,0 +1,315 @@
+//! The repository's timeline: commits on the local branches, PRs opened
+//! and approved, worktrees created, and the recorded events (goal
+//! transitions, PR state changes, protection overrides) merged into one
+//! newest-first feed.
+
+use serde::Serialize;
+
+use crate::store::{self, MetaStore, PrQuery, DEFAULT_PAGE_SIZE, MAX_PAGE_SIZE};
+
+/// Recorded events are read in pages of this many.
+const EVENTS_PAGE: usize = 200;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Item {
+    /// `YYYY-MM-DD HH:MM:SS` in UTC, like the store's timestamps; commits
+    /// use their committer time.
+    pub at: String,
+    /// `commit`, `pr_opened`, `pr_approved`, `worktree_created`, or a
+    /// recorded event's kind, e.g. `goal_status_changed`.
+    pub kind: String,
+    /// Unique within the feed, e.g. `commit:<sha>` or `event:<seq>`.
+    pub key: String,
+    pub detail: serde_json::Value,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Feed {
+    pub items: Vec<Item>,
+    /// Cursor for the next, older page; `None` once the feed is exhausted.
+    pub next_before: Option<String>,
+}
+
+/// A page of the feed. `before` is the `next_before` of the previous page,
+/// so pages stay stable while new activity arrives.
+#[derive(Debug, Clone, Default)]
+pub struct Query {
+    pub before: Option<Cursor>,
+    pub limit: usize,
+}
+
+/// Where a page ends: items sort by `(at, key)`, newest first.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Cursor {
+    at: String,
+    key: String,
+}
+
+impl Cursor {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let (at, key) = text
+            .split_once('|')
+            .filter(|(at, key)| at.len() == "2024-01-01 00:00:00".len() && !key.is_empty())
+            .ok_or_else(|| format!("invalid feed cursor {text:?}"))?;
+        Ok(Self {
+            at: at.to_string(),
+            key: key.to_string(),
+        })
+    }
+
+    fn of(item: &Item) -> Self {
+        Self {
+            at: item.at.clone(),
+            key: item.key.clone(),
+        }
+    }
+}
+
+pub fn load(repo: &git2::Repository, store: &dyn MetaStore, query: &Query) -> Result<Feed, String> {
+    let limit = match query.limit {
+        0 => DEFAULT_PAGE_SIZE,
+        n => n.min(MAX_PAGE_SIZE),
+    };
+    let older = |item: &Item| {
+        query
+            .before
+            .as_ref()
+            .is_none_or(|cursor| Cursor::of(item) < *cursor)
+    };
+
+    let mut items = commits(repo, limit, &older)?;
+    items.extend(events(store, limit, &older)?);
+    items.extend(prs(store)?.into_iter().filter(|item| older(item)));
+    items.extend(worktrees(store)?.into_iter().filter(|item| older(item)));
+    items.sort_by(|a, b| (&b.at, &b.key).cmp(&(&a.at, &a.key)));
+    items.truncate(limit);
+
+    let next_before = (items.len() == limit)
+        .then(|| items.last().map(|item| format!("{}|{}", item.at, item.key)))
+        .flatten();
+    Ok(Feed { items, next_before })
+}
+
+/// The newest `limit` commits on HEAD and the local branches that `older`
+/// keeps.
+fn commits(
+    repo: &git2::Repository,
+    limit: usize,
+    older: &dyn Fn(&Item) -> bool,
+) -> Result<Vec<Item>, String> {
+    let mut walk = repo
+        .revwalk()
+        .map_err(|e| format!("failed to walk history: {e}"))?;
+    walk.set_sorting(git2::Sort::TIME)
+        .map_err(|e| format!("failed to walk history: {e}"))?;
+    // An unborn HEAD has no history yet.
+    let _ = walk.push_head();
+    walk.push_glob("refs/heads/*")
+        .map_err(|e| format!("failed to walk branches: {e}"))?;
+
+    let mut items = Vec::new();
+    for oid in walk {
+        let oid = oid.map_err(|e| format!("failed to walk history: {e}"))?;
+        let commit = repo
+            .find_commit(oid)
+            .map_err(|e| format!("failed to read commit {oid}: {e}"))?;
+        let item = Item {
+            at: store::timestamp(commit.time().seconds()),
+            kind: "commit".to_string(),
+            key: format!("commit:{oid}"),
+            detail: serde_json::json!({
+                "commit": oid.to_string(),
+                "summary": commit.summary().unwrap_or_default(),
+                "author": commit.author().name().unwrap_or_default(),
+            }),
+        };
+        if older(&item) {
+            items.push(item);
+            if items.len() == limit {
+                break;
+            }
+        }
+    }
+    Ok(items)
+}
+
+/// The newest `limit` recorded events that `older` keeps.
+fn events(
+    store: &dyn MetaStore,
+    limit: usize,
+    older: &dyn Fn(&Item) -> bool,
+) -> Result<Vec<Item>, String> {
+    let mut items = Vec::new();
+    let mut before = None;
+    loop {
+        let page = store.events_before(before, EVENTS_PAGE)?;
+        let Some(last) = page.last() else {
+            return Ok(items);
+        };
+        before = Some(last.seq);
+        for event in page {
+            let item = Item {
+                at: event.recorded_at,
+                kind: event.kind,
+                key: format!("event:{:020}", event.seq),
+                detail: event.event,
+            };
+            if older(&item) {
+                items.push(item);
+                if items.len() == limit {
+                    return Ok(items);
+                }
+            }
+        }
+    }
+}
+
+/// Every PR's opening and approvals.
+fn prs(store: &dyn MetaStore) -> Result<Vec<Item>, String> {
+    let mut items = Vec::new();
+    let mut before = None;
+    loop {
+        let page = store.list_prs(PrQuery {
+            state: None,
+            before,
+            limit: MAX_PAGE_SIZE,
+        })?;
+        for pr in page.items {
+            for approval in &pr.approvals {
+                items.push(Item {
+                    at: approval.approved_at.clone(),
+                    kind: "pr_approved".to_string(),
+                    key: format!("pr:{}:approved:{}", pr.id, approval.reviewer),
+                    detail: serde_json::json!({
+                        "pr_id": pr.id,
+                        "title": pr.title,
+                        "reviewer": approval.reviewer,
+                    }),
+                });
+            }
+            items.push(Item {
+                at: pr.created_at.clone(),
+                kind: "pr_opened".to_string(),
+                key: format!("pr:{}:opened", pr.id),
+                detail: serde_json::json!({
+                    "pr_id": pr.id,
+                    "title": pr.title,
+                    "from": pr.from,
+                    "to": pr.to,
+                }),
+            });
+        }
+        match page.next_before {
+            Some(next) => before = Some(next),
+            None => return Ok(items),
+        }
+    }
+}
+
+fn worktrees(store: &dyn MetaStore) -> Result<Vec<Item>, String> {
+    Ok(store
+        .list_worktrees()?
+        .into_iter()
+        .map(|worktree| Item {
+            at: worktree.created_at,
+            kind: "worktree_created".to_string(),
+            key: format!("worktree:{}", worktree.name),
+            detail: serde_json::json!({
+                "name": worktree.name,
+                "path": worktree.path,
+                "branch": worktree.branch,
+            }),
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ant_core::{GoalStatus, SystemEvent, VersionedSystemEvent, SYSTEM_EVENT_SCHEMA_VERSION};
+
+    #[test]
+    fn activity_merges_newest_first_and_pages() {
+        let (dir, repo) = crate::test_support::init_repo("feed");
+        // Commits from 2001, so they sort below everything the store
+        // stamps with the current time.
+        let signature = git2::Signature::new(
+            "GitForge Test",
+            "test@gitforge.dev",
+            &git2::Time::new(1_000_000_000, 0),
+        )
+        .expect("sig");
+        let tree = repo
+            .index()
+            .and_then(|mut index| index.write_tree())
+            .and_then(|id| repo.find_tree(id))
+            .expect("tree");
+        let first = repo
+            .commit(Some("HEAD"), &signature, &signature, "First", &tree, &[])
+            .expect("commit");
+        let first = repo.find_commit(first).expect("first");
+        let later = git2::Signature::new(
+            "GitForge Test",
+            "test@gitforge.dev",
+            &git2::Time::new(1_000_000_060, 0),
+        )
+        .expect("sig");
+        repo.commit(Some("HEAD"), &later, &later, "Second", &tree, &[&first])
+            .expect("commit");
+
+        let store = crate::store::open(&dir.to_string_lossy()).expect("store");
+        let pr = store
+            .create_pr(store::NewPr {
+                title: "Feed",
+                from: "feature",
+                to: "main",
+                description: None,
+            })
+            .expect("pr");
+        store.approve_pr(pr, "ana").expect("approve");
+        store
+            .append_event(&VersionedSystemEvent {
+                schema_version: SYSTEM_EVENT_SCHEMA_VERSION,
+                event: SystemEvent::GoalStatusChanged {
+                    goal_id: "G-1".to_string(),
+                    status: GoalStatus::Running,
+                },
+            })
+            .expect("event");
+        store
+            .upsert_worktree("wt", "/tmp/wt", "feature")
+            .expect("worktree");
+
+        let query = |before: Option<&str>, limit| Query {
+            before: before.map(|text| Cursor::parse(text).expect("cursor")),
+            limit,
+        };
+        let all = load(&repo, store.as_ref(), &query(None, 0)).expect("feed");
+        let kinds: Vec<&str> = all.items.iter().map(|item| item.kind.as_str()).collect();
+        assert_eq!(kinds.len(), 6, "{kinds:?}");
+        assert_eq!(kinds[4..], ["commit", "commit"]);
+        for kind in [
+            "pr_opened",
+            "pr_approved",
+            "goal_status_changed",
+            "worktree_created",
+        ] {
+            assert!(kinds[..4].contains(&kind), "{kinds:?}");
+        }
+        assert_eq!(all.items[4].detail["summary"], "Second");
+        assert_eq!(all.next_before, None);
+
+        let mut paged = Vec::new();
+        let mut before = None;
+        loop {
+            let page = load(&repo, store.as_ref(), &query(before.as_deref(), 4)).expect("page");
+            paged.extend(page.items);
+            match page.next_before {
+                Some(next) => before = Some(next),
+                None => break,
+            }
+        }
+        assert_eq!(paged, all.items);
+        assert!(Cursor::parse("yesterday").is_err());
+    }
+}
@@ -0,0 +1,473 @@
+use serde::Serialize;
+use std::collections::HashMap;
+
+use crate::budget::{Bounded, MAX_RESPONSE_BYTES};
+use crate::projects::Project;
+
+pub const DEFAULT_LIMIT: usize = 500;
+pub const MAX_LIMIT: usize = 5000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RefKind {
+    Head,
+    Branch,
+    Remote,
+    Tag,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RefLabel {
+    pub name: String,
+    pub kind: RefKind,
+}
+
+/// A line drawn from this commit's row down to one of its parents.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Edge {
+    pub parent: String,
+    pub from_lane: usize,
+    pub to_lane: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct GraphCommit {
+    pub id: String,
+    pub short_id: String,
+    pub summary: String,
+    pub author: String,
+    /// Author time, seconds since the unix epoch.
+    pub time: i64,
+    pub parents: Vec<String>,
+    pub lane: usize,
+    pub edges: Vec<Edge>,
+    /// Lanes still open below this row, including ones passing through it.
+    pub width: usize,
+    pub refs: Vec<RefLabel>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CommitGraph {
+    pub commits: Vec<GraphCommit>,
+    /// Widest row, so the UI can size the graph column once.
+    pub lanes: usize,
+    /// More history exists beyond `limit`, or the rows reached
+    /// [`MAX_RESPONSE_BYTES`] first.
+    pub truncated: bool,
+}
+
+/// Commits reachable from HEAD, branches, remotes, and tags, newest first in
+/// topological order, with lanes assigned so the UI only has to draw.
+pub fn commit_graph(repo: &git2::Repository, limit: usize) -> Result<CommitGraph, String> {
+    graph(repo, limit, None)
+}
+
+/// [`commit_graph`] limited to the commits that change `project`, each
+/// joined to the nearest such ancestors the way `git log --graph -- <paths>`
+/// draws it.
+pub fn project_graph(
+    repo: &git2::Repository,
+    limit: usize,
+    project: &Project,
+) -> Result<CommitGraph, String> {
+    graph(repo, limit, Some(project))
+}
+
+fn graph(
+    repo: &git2::Repository,
+    limit: usize,
+    project: Option<&Project>,
+) -> Result<CommitGraph, String> {
+    let limit = match limit {
+        0 => DEFAULT_LIMIT,
+        n => n.min(MAX_LIMIT),
+    };
+    let decorations = decorations(repo)?;
+
+    let mut walk = repo
+        .revwalk()
+        .map_err(|e| format!("failed to walk history: {e}"))?;
+    walk.set_sorting(git2::Sort::TOPOLOGICAL | git2::Sort::TIME)
+        .map_err(|e| format!("failed to walk history: {e}"))?;
+    for glob in ["refs/heads", "refs/remotes", "refs/tags"] {
+        walk.push_glob(glob)
+            .map_err(|e| format!("failed to walk {glob}: {e}"))?;
+    }
+    // Unborn or detached HEAD: neither is fatal for a graph.
+    let _ = walk.push_head();
+
+    let mut simplify = project.map(|project| Simplify::new(repo, project));
+    let mut layout = Layout::default();
+    let mut commits = Bounded::new(MAX_RESPONSE_BYTES);
+    let mut truncated = false;
+    for oid in walk {
+        let oid = oid.map_err(|e| format!("failed to walk history: {e}"))?;
+        if commits.len() == limit {
+            truncated = true;
+            break;
+        }
+        let commit = repo
+            .find_commit(oid)
+            .map_err(|e| format!("failed to read commit {oid}: {e}"))?;
+        let mut parents: Vec<git2::Oid> = commit.parent_ids().collect();
+        if let Some(simplify) = simplify.as_mut() {
+            if !simplify.kept(oid)? {
+                continue;
+            }
+            parents = simplify.parents(&parents)?;
+        }
+        let (lane, edges) = layout.place(oid, &parents);
+
+        let row = GraphCommit {
+            id: oid.to_string(),
+            short_id: oid.to_string()[..7].to_string(),
+            summary: commit.summary().unwrap_or_default().to_string(),
+            author: commit.author().name().unwrap_or_default().to_string(),
+            time: commit.author().when().seconds(),
+            parents: parents.iter().map(|p| p.to_string()).collect(),
+            lane,
+            edges,
+            width: layout.width(),
+            refs: decorations.get(&oid).cloned().unwrap_or_default(),
+        };
+        if !commits.push(row) {
+            truncated = true;
+            break;
+        }
+    }
+    let commits = commits.into_vec();
+
+    let lanes = commits
+        .iter()
+        .map(|c| c.width.max(c.lane + 1))
+        .max()
+        .unwrap_or(0);
+    Ok(CommitGraph {
+        commits,
+        lanes,
+        truncated,
+    })
+}
+
+/// Parent rewriting for [`project_graph`]: a skipped commit stands for the
+/// nearest kept commits below it.
+struct Simplify<'r> {
+    repo: &'r git2::Repository,
+    project: &'r Project,
+    kept: HashMap<git2::Oid, bool>,
+    rewritten: HashMap<git2::Oid, Vec<git2::Oid>>,
+}
+
+impl<'r> Simplify<'r> {
+    fn new(repo: &'r git2::Repository, project: &'r Project) -> Self {
+        Self {
+            repo,
+            project,
+            kept: HashMap::new(),
+            rewritten: HashMap::new(),
+        }
+    }
+
+    fn commit(&self, oid: git2::Oid) -> Result<git2::Commit<'r>, String> {
+        self.repo
+            .find_commit(oid)
+            .map_err(|e| format!("failed to read commit {oid}: {e}"))
+    }
+
+    fn kept(&mut self, oid: git2::Oid) -> Result<bool, String> {
+        if let Some(kept) = self.kept.get(&oid) {
+            return Ok(*kept);
+        }
+        let kept = self.project.touches(&self.commit(oid)?)?;
+        self.kept.insert(oid, kept);
+        Ok(kept)
+    }
+
+    fn parents(&mut self, parents: &[git2::Oid]) -> Result<Vec<git2::Oid>, String> {
+        let mut out = Vec::new();
+        for parent in parents {
+            for oid in self.resolve(*parent)? {
+                if !out.contains(&oid) {
+                    out.push(oid);
+                }
+            }
+        }
+        Ok(out)
+    }
+
+    /// The kept commits `start` stands for, without recursing so long
+    /// stretches of skipped history can't overflow the stack.
+    fn resolve(&mut self, start: git2::Oid) -> Result<Vec<git2::Oid>, String> {
+        let mut stack = vec![start];
+        while let Some(&oid) = stack.last() {
+            if self.rewritten.contains_key(&oid) {
+                stack.pop();
+                continue;
+            }
+            if self.kept(oid)? {
+                self.rewritten.insert(oid, vec![oid]);
+                stack.pop();
+                continue;
+            }
+            let parents: Vec<git2::Oid> = self.commit(oid)?.parent_ids().collect();
+            let pending: Vec<git2::Oid> = parents
+                .iter()
+                .filter(|parent| !self.rewritten.contains_key(parent))
+                .copied()
+                .collect();
+            if !pending.is_empty() {
+                stack.extend(pending);
+                continue;
+            }
+            let mut ids: Vec<git2::Oid> = Vec::new();
+            for parent in &parents {
+                for id in &self.rewritten[parent] {
+                    if !ids.contains(id) {
+                        ids.push(*id);
+                    }
+                }
+            }
+            self.rewritten.insert(oid, ids);
+            stack.pop();
+        }
+        Ok(self.rewritten[&start].clone())
+    }
+}
+
+/// Each slot holds the commit the lane is waiting for, or `None` once freed.
+#[derive(Default)]
+struct Layout {
+    lanes: Vec<Option<git2::Oid>>,
+}
+
+impl Layout {
+    fn place(&mut self, oid: git2::Oid, parents: &[git2::Oid]) -> (usize, Vec<Edge>) {
+        let lane = match self.lanes.iter().position(|l| *l == Some(oid)) {
+            Some(lane) => lane,
+            None => self.claim(),
+        };
+        // Other branches that ended at this commit merge into its lane.
+        for slot in self.lanes.iter_mut().skip(lane + 1) {
+            if *slot == Some(oid) {
+                *slot = None;
+            }
+        }
+        self.lanes[lane] = None;
+
+        let mut edges = Vec::with_capacity(parents.len());
+        for (i, parent) in parents.iter().enumerate() {
+            let to_lane = match self.lanes.iter().position(|l| *l == Some(*parent)) {
+                Some(existing) => existing,
+                None if i == 0 => lane,
+                None => self.claim(),
+            };
+            self.lanes[to_lane] = Some(*parent);
+            edges.push(Edge {
+                parent: parent.to_string(),
+                from_lane: lane,
+                to_lane,
+            });
+        }
+
+        while self.lanes.last() == Some(&None) {
+            self.lanes.pop();
+        }
+        (lane, edges)
+    }
+
+    fn claim(&mut self) -> usize {
+        match self.lanes.iter().position(Option::is_none) {
+            Some(free) => free,
+            None => {
+                self.lanes.push(None);
+                self.lanes.len() - 1
+            }
+        }
+    }
+
+    fn width(&self) -> usize {
+        self.lanes.len()
+    }
+}
+
+fn decorations(repo: &git2::Repository) -> Result<HashMap<git2::Oid, Vec<RefLabel>>, String> {
+    let mut labels: HashMap<git2::Oid, Vec<RefLabel>> = HashMap::new();
+    if let Ok(head) = repo.head() {
+        if let Some(oid) = head.target() {
+            labels.entry(oid).or_default().push(RefLabel {
+                name: "HEAD".to_string(),
+                kind: RefKind::Head,
+            });
+        }
+    }
+
+    let references = repo
+        .references()
+        .map_err(|e| format!("failed to list references: {e}"))?;
+    for reference in references.flatten() {
+        let kind = if reference.is_branch() {
+            RefKind::Branch
+        } else if reference.is_remote() {
+            RefKind::Remote
+        } else if reference.is_tag() {
+            RefKind::Tag
+        } else {
+            continue;
+        };
+        let (Some(name), Ok(commit)) = (reference.shorthand(), reference.peel_to_commit()) else {
+            continue;
+        };
+        labels.entry(commit.id()).or_default().push(RefLabel {
+            name: name.to_string(),
+            kind,
+        });
+    }
+    Ok(labels)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_repo(label: &str) -> (crate::test_support::TempDir, git2::Repository) {
+        crate::test_support::init_repo(&format!("graph-{label}"))
+    }
+
+    /// Empty-tree commit at a fixed time so ordering is deterministic.
+    fn commit(
+        repo: &git2::Repository,
+        update_ref: Option<&str>,
+        message: &str,
+        time: i64,
+        parents: &[git2::Oid],
+    ) -> git2::Oid {
+        let sig = git2::Signature::new(
+            "GitForge Test",
+            "test@gitforge.dev",
+            &git2::Time::new(time, 0),
+        )
+        .expect("signature");
+        let tree_id = repo
+            .treebuilder(None)
+            .and_then(|b| b.write())
+            .expect("empty tree");
+        let tree = repo.find_tree(tree_id).expect("find tree");
+        let parents: Vec<git2::Commit> = parents
+            .iter()
+            .map(|p| repo.find_commit(*p).expect("parent"))
+            .collect();
+        let parents: Vec<&git2::Commit> = parents.iter().collect();
+        repo.commit(update_ref, &sig, &sig, message, &tree, &parents)
+            .expect("commit")
+    }
+
+    #[test]
+    fn merge_topology_gets_two_lanes() {
+        let (_dir, repo) = temp_repo("merge");
+        let base = commit(&repo, Some("HEAD"), "base", 100, &[]);
+        let side = commit(&repo, None, "side", 200, &[base]);
+        let main = commit(&repo, None, "main work", 300, &[base]);
+        let merge = commit(&repo, None, "merge", 400, &[main, side]);
+        let branch = repo.head().expect("head");
+        let branch_name = branch.shorthand().expect("branch name").to_string();
+        repo.reference(branch.name().expect("ref name"), merge, true, "advance")
+            .expect("move branch");
+        repo.reference("refs/tags/v1", side, true, "tag")
+            .expect("tag");
+
+        let graph = commit_graph(&repo, 0).expect("graph");
+        let by_summary: HashMap<&str, &GraphCommit> = graph
+            .commits
+            .iter()
+            .map(|c| (c.summary.as_str(), c))
+            .collect();
+
+        assert_eq!(graph.commits.len(), 4);
+        assert_eq!(graph.commits[0].summary, "merge");
+        assert_eq!(graph.lanes, 2);
+        assert_eq!(by_summary["merge"].edges.len(), 2);
+        assert_eq!(by_summary["merge"].edges[1].to_lane, 1);
+        assert_eq!(by_summary["side"].lane, 1);
+        assert_eq!(by_summary["main work"].lane, 0);
+        assert_eq!(by_summary["base"].lane, 0);
+        assert_eq!(by_summary["base"].width, 0);
+
+        let merge_refs: Vec<&str> = by_summary["merge"]
+            .refs
+            .iter()
+            .map(|r| r.name.as_str())
+            .collect();
+        assert!(merge_refs.contains(&"HEAD") && merge_refs.contains(&branch_name.as_str()));
+        assert_eq!(by_summary["side"].refs[0].kind, RefKind::Tag);
+    }
+
+    #[test]
+    fn limit_truncates_history() {
+        let (_dir, repo) = temp_repo("limit");
+        let mut parent = commit(&repo, Some("HEAD"), "c0", 100, &[]);
+        for i in 1..5 {
+            parent = commit(&repo, Some("HEAD"), &format!("c{i}"), 100 + i, &[parent]);
+        }
+
+        let graph = commit_graph(&repo, 3).expect("graph");
+        assert_eq!(graph.commits.len(), 3);
+        assert!(graph.truncated);
+        assert!(!commit_graph(&repo, 10).expect("graph").truncated);
+    }
+
+    #[test]
+    fn project_graph_skips_commits_outside_the_project() {
+        let (_dir, repo) = temp_repo("project");
+        let sig =
+            git2::Signature::new("GitForge Test", "test@gitforge.dev", &git2::Time::new(0, 0))
+                .expect("signature");
+        let mut files: Vec<(&str, &str)> = Vec::new();
+        let mut parent: Option<git2::Oid> = None;
+        let mut ids = Vec::new();
+        for (path, text) in [
+            ("app.txt", "1"),
+            ("lib.txt", "1"),
+            ("lib.txt", "2"),
+            ("app.txt", "2"),
+        ] {
+            files.retain(|(p, _)| *p != path);
+            files.push((path, text));
+            let mut builder = repo.treebuilder(None).expect("builder");
+            for (path, text) in &files {
+                let blob = repo.blob(text.as_bytes()).expect("blob");
+                builder.insert(path, blob, 0o100644).expect("insert");
+            }
+            let tree = repo
+                .find_tree(builder.write().expect("tree"))
+                .expect("find tree");
+            let parents: Vec<git2::Commit> = parent
+                .iter()
+                .map(|p| repo.find_commit(*p).unwrap())
+                .collect();
+            let parents: Vec<&git2::Commit> = parents.iter().collect();
+            let id = repo
+                .commit(
+                    Some("HEAD"),
+                    &sig,
+                    &sig,
+                    &format!("{path} {text}"),
+                    &tree,
+                    &parents,
+                )
+                .expect("commit");
+            parent = Some(id);
+            ids.push(id);
+        }
+
+        let app = Project {
+            name: "app".to_string(),
+            paths: vec!["app.txt".to_string()],
+        };
+        let graph = project_graph(&repo, 0, &app).expect("graph");
+        let summaries: Vec<&str> = graph.commits.iter().map(|c| c.summary.as_str()).collect();
+        assert_eq!(summaries, ["app.txt 2", "app.txt 1"]);
+        assert_eq!(graph.commits[0].parents, [ids[0].to_string()]);
+        assert_eq!(graph.lanes, 1);
+        assert_eq!(commit_graph(&repo, 0).expect("graph").commits.len(), 4);
+    }
+}
@@ -0,0 +1,223 @@
+//! Commit history as `git log` lists it, paged and filtered for MCP
+//! clients that can't run git themselves.
+
+use serde::Serialize;
+
+use crate::store;
+
+pub mod search;
+
+pub const DEFAULT_LIMIT: usize = 50;
+pub const MAX_LIMIT: usize = 1000;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Query<'a> {
+    /// Where to start; `HEAD` when `None`.
+    pub rev: Option<&'a str>,
+    pub limit: usize,
+    /// Matching commits to pass over first.
+    pub skip: usize,
+    /// Case-insensitive substring of the author's name or email.
+    pub author: Option<&'a str>,
+    /// Only commits that change this file or directory.
+    pub path: Option<&'a str>,
+    /// Unix seconds; older commits are left out.
+    pub since: Option<i64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LogEntry {
+    pub oid: String,
+    pub author: String,
+    pub email: String,
+    /// Author date, `YYYY-MM-DD HH:MM:SS` UTC.
+    pub date: String,
+    pub summary: String,
+    pub parents: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LogPage {
+    /// Newest first.
+    pub items: Vec<LogEntry>,
+    /// `skip` for the next page; `None` on the last one.
+    pub next_skip: Option<usize>,
+}
+
+pub fn log(repo: &git2::Repository, query: &Query) -> Result<LogPage, String> {
+    let rev = query.rev.unwrap_or("HEAD");
+    let start = repo
+        .revparse_single(rev)
+        .and_then(|object| object.peel_to_commit())
+        .map_err(|e| format!("failed to resolve {rev}: {e}"))?;
+    let mut walk = repo
+        .revwalk()
+        .map_err(|e| format!("failed to walk history: {e}"))?;
+    walk.set_sorting(git2::Sort::TOPOLOGICAL | git2::Sort::TIME)
+        .and_then(|()| walk.push(start.id()))
+        .map_err(|e| format!("failed to walk history: {e}"))?;
+    let author = query.author.map(str::to_lowercase);
+    let limit = query.limit.clamp(1, MAX_LIMIT);
+
+    let mut items = Vec::new();
+    let mut matched = 0;
+    for oid in walk {
+        let commit = oid
+            .and_then(|oid| repo.find_commit(oid))
+            .map_err(|e| format!("failed to read commit: {e}"))?;
+        let signature = commit.author();
+        if query
+            .since
+            .is_some_and(|since| signature.when().seconds() < since)
+        {
+            // Time-sorted, so everything after is older too, give or take
+            // clock skew.
+            break;
+        }
+        if let Some(author) = &author {
+            let name = signature.name().unwrap_or("").to_lowercase();
+            let email = signature.email().unwrap_or("").to_lowercase();
+            if !name.contains(author.as_str()) && !email.contains(author.as_str()) {
+                continue;
+            }
+        }
+        if let Some(path) = query.path {
+            if !changes(&commit, path)? {
+                continue;
+            }
+        }
+        matched += 1;
+        if matched <= query.skip {
+            continue;
+        }
+        if items.len() == limit {
+            return Ok(LogPage {
+                items,
+                next_skip: Some(query.skip + limit),
+            });
+        }
+        items.push(LogEntry {
+            oid: commit.id().to_string(),
+            author: signature.name().unwrap_or("").to_string(),
+            email: signature.email().unwrap_or("").to_string(),
+            date: store::timestamp(signature.when().seconds()),
+            summary: commit.summary().unwrap_or("").to_string(),
+            parents: commit.parent_ids().map(|id| id.to_string()).collect(),
+        });
+    }
+    Ok(LogPage {
+        items,
+        next_skip: None,
+    })
+}
+
+/// Unix seconds for `YYYY-MM-DD` (midnight) or `YYYY-MM-DD HH:MM:SS`, UTC.
+pub fn parse_since(text: &str) -> Result<i64, String> {
+    let padded = if text.len() == "2024-01-01".len() {
+        format!("{text} 00:00:00")
+    } else {
+        text.to_string()
+    };
+    store::parse_timestamp(&padded)
+        .ok_or_else(|| format!("invalid date '{text}': expected YYYY-MM-DD or YYYY-MM-DD HH:MM:SS"))
+}
+
+/// Whether `path` differs between `commit` and its first parent. Tree
+/// entry ids are compared, so a directory counts when anything under it
+/// changed.
+fn changes(commit: &git2::Commit, path: &str) -> Result<bool, String> {
+    let entry =
+        |tree: git2::Tree| match tree.get_path(std::path::Path::new(path.trim_end_matches('/'))) {
+            Ok(entry) => Ok(Some(entry.id())),
+            Err(e) if e.code() == git2::ErrorCode::NotFound => Ok(None),
+            Err(e) => Err(format!("failed to look up {path}: {e}")),
+        };
+    let tree = commit
+        .tree()
+        .map_err(|e| format!("failed to read tree of {}: {e}", commit.id()))?;
+    let parent = match commit.parent(0) {
+        Ok(parent) => entry(
+            parent
+                .tree()
+                .map_err(|e| format!("failed to read tree of {}: {e}", parent.id()))?,
+        )?,
+        Err(_) => None,
+    };
+    Ok(entry(tree)? != parent)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn pages_and_filters_by_author_path_and_date() {
+        let (dir, repo) = crate::test_support::init_repo("history");
+        let commit = |path: &str, who: &str, secs: i64, message: &str| {
+            fs::create_dir_all(dir.join(path).parent().unwrap()).expect("dir");
+            fs::write(dir.join(path), message).expect("write");
+            let mut index = repo.index().expect("index");
+            index.add_path(std::path::Path::new(path)).expect("add");
+            index.write().expect("write index");
+            let tree = repo
+                .find_tree(index.write_tree().expect("tree"))
+                .expect("tree");
+            let sig = git2::Signature::new(
+                who,
+                &format!("{who}@example.com"),
+                &git2::Time::new(secs, 0),
+            )
+            .expect("sig");
+            let parent = repo.head().ok().and_then(|h| h.peel_to_commit().ok());
+            let parents: Vec<&git2::Commit> = parent.iter().collect();
+            repo.commit(Some("HEAD"), &sig, &sig, message, &tree, &parents)
+                .expect("commit")
+        };
+        let day = 86_400;
+        commit("README.md", "Ada", day, "Init");
+        commit("src/a.rs", "Bob", 2 * day, "Add a");
+        commit("src/b.rs", "Ada", 3 * day, "Add b");
+        commit("README.md", "Bob", 4 * day, "Docs");
+
+        let query = |f: &dyn Fn(&mut Query)| {
+            let mut query = Query {
+                limit: DEFAULT_LIMIT,
+                ..Query::default()
+            };
+            f(&mut query);
+            let page = log(&repo, &query).expect("log");
+            let summaries: Vec<String> = page.items.iter().map(|c| c.summary.clone()).collect();
+            (summaries, page.next_skip)
+        };
+        assert_eq!(query(&|_| {}).0, ["Docs", "Add b", "Add a", "Init"]);
+        assert_eq!(
+            query(&|q| q.limit = 2),
+            (vec!["Docs".into(), "Add b".into()], Some(2))
+        );
+        assert_eq!(
+            query(&|q| {
+                q.limit = 2;
+                q.skip = 2
+            }),
+            (vec!["Add a".into(), "Init".into()], None)
+        );
+        assert_eq!(query(&|q| q.author = Some("ADA")).0, ["Add b", "Init"]);
+        assert_eq!(query(&|q| q.path = Some("src/")).0, ["Add b", "Add a"]);
+        let since = parse_since("1970-01-04").expect("since");
+        assert_eq!(query(&|q| q.since = Some(since)).0, ["Docs", "Add b"]);
+        assert!(parse_since("last week").is_err());
+
+        let page = log(
+            &repo,
+            &Query {
+                limit: 1,
+                ..Query::default()
+            },
+        )
+        .expect("log");
+        assert_eq!(page.items[0].date, "1970-01-05 00:00:00");
+        assert_eq!(page.items[0].email, "Bob@example.com");
+        assert_eq!(page.items[0].parents.len(), 1);
+    }
+}
@@ -0,0 +1,399 @@
+//! Commit search by message, author, date and content, like `git log
+//! --grep`, `--author`, `--since`/`--until`, `-S` and `-G`.
+
+use regex::Regex;
+use serde::Serialize;
+
+use super::{LogEntry, MAX_LIMIT};
+use crate::store;
+
+/// Snippets kept per commit.
+const MAX_SNIPPETS: usize = 5;
+/// Characters of context kept before a match in a snippet.
+const CONTEXT: usize = 40;
+/// Characters a snippet is cut to.
+const SNIPPET_LEN: usize = 160;
+
+#[derive(Debug, Clone)]
+pub enum Content {
+    /// `-S`: the number of occurrences of the string changes.
+    Occurrences(Regex),
+    /// `-G`: an added or removed line matches.
+    Lines(Regex),
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Query<'a> {
+    /// Where to start; `HEAD` when `None`.
+    pub rev: Option<&'a str>,
+    pub limit: usize,
+    /// Matching commits to pass over first.
+    pub skip: usize,
+    /// Matched against the whole commit message.
+    pub message: Option<Regex>,
+    /// Case-insensitive substring of the author's name or email.
+    pub author: Option<&'a str>,
+    /// Unix seconds; older commits are left out.
+    pub since: Option<i64>,
+    /// Unix seconds; newer commits are left out.
+    pub until: Option<i64>,
+    pub content: Option<Content>,
+}
+
+impl Query<'_> {
+    pub fn is_empty(&self) -> bool {
+        self.message.is_none()
+            && self.author.is_none()
+            && self.since.is_none()
+            && self.until.is_none()
+            && self.content.is_none()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Snippet {
+    /// `message`, or the path the diff line is in.
+    pub source: String,
+    /// `+` or `-` for a diff line.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub origin: Option<char>,
+    /// The line, matches in `[` `]`, cut to the part around them.
+    pub text: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Hit {
+    #[serde(flatten)]
+    pub commit: LogEntry,
+    pub snippets: Vec<Snippet>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SearchPage {
+    /// Newest first.
+    pub items: Vec<Hit>,
+    /// `skip` for the next page; `None` on the last one.
+    pub next_skip: Option<usize>,
+}
+
+/// The commits reachable from `query.rev` that match every filter set,
+/// with the message lines and diff lines that matched. Content is
+/// compared against the first parent.
+pub fn search(repo: &git2::Repository, query: &Query) -> Result<SearchPage, String> {
+    let rev = query.rev.unwrap_or("HEAD");
+    let start = repo
+        .revparse_single(rev)
+        .and_then(|object| object.peel_to_commit())
+        .map_err(|e| format!("failed to resolve {rev}: {e}"))?;
+    let mut walk = repo
+        .revwalk()
+        .map_err(|e| format!("failed to walk history: {e}"))?;
+    walk.set_sorting(git2::Sort::TOPOLOGICAL | git2::Sort::TIME)
+        .and_then(|()| walk.push(start.id()))
+        .map_err(|e| format!("failed to walk history: {e}"))?;
+    let author = query.author.map(str::to_lowercase);
+    let limit = query.limit.clamp(1, MAX_LIMIT);
+
+    let mut items = Vec::new();
+    let mut matched = 0;
+    for oid in walk {
+        let commit = oid
+            .and_then(|oid| repo.find_commit(oid))
+            .map_err(|e| format!("failed to read commit: {e}"))?;
+        let signature = commit.author();
+        let when = signature.when().seconds();
+        if query.since.is_some_and(|since| when < since) {
+            // Time-sorted, as in `log`.
+            break;
+        }
+        if query.until.is_some_and(|until| when > until) {
+            continue;
+        }
+        if let Some(author) = &author {
+            let name = signature.name().unwrap_or("").to_lowercase();
+            let email = signature.email().unwrap_or("").to_lowercase();
+            if !name.contains(author.as_str()) && !email.contains(author.as_str()) {
+                continue;
+            }
+        }
+        let mut snippets = Vec::new();
+        if let Some(pattern) = &query.message {
+            let message = commit.message().unwrap_or("");
+            if !pattern.is_match(message) {
+                continue;
+            }
+            snippets.extend(
+                message
+                    .lines()
+                    .filter(|line| pattern.is_match(line))
+                    .map(|line| Snippet {
+                        source: "message".to_string(),
+                        origin: None,
+                        text: highlight(line, pattern),
+                    }),
+            );
+        }
+        if let Some(content) = &query.content {
+            match content_matches(repo, &commit, content)? {
+                Some(lines) => snippets.extend(lines),
+                None => continue,
+            }
+        }
+        matched += 1;
+        if matched <= query.skip {
+            continue;
+        }
+        if items.len() == limit {
+            return Ok(SearchPage {
+                items,
+                next_skip: Some(query.skip + limit),
+            });
+        }
+        snippets.truncate(MAX_SNIPPETS);
+        items.push(Hit {
+            commit: LogEntry {
+                oid: commit.id().to_string(),
+                author: signature.name().unwrap_or("").to_string(),
+                email: signature.email().unwrap_or("").to_string(),
+                date: store::timestamp(when),
+                summary: commit.summary().unwrap_or("").to_string(),
+                parents: commit.parent_ids().map(|id| id.to_string()).collect(),
+            },
+            snippets,
+        });
+    }
+    Ok(SearchPage {
+        items,
+        next_skip: None,
+    })
+}
+
+/// The changed lines of `commit` that `content` matches, or `None` when
+/// the commit doesn't match. For `-S` a file counts when its occurrences
+/// differ between the removed and added lines, which is the same as
+/// between the old and new file. Binary files are skipped.
+fn content_matches(
+    repo: &git2::Repository,
+    commit: &git2::Commit,
+    content: &Content,
+) -> Result<Option<Vec<Snippet>>, String> {
+    let tree = commit
+        .tree()
+        .map_err(|e| format!("failed to read tree of {}: {e}", commit.id()))?;
+    let parent = match commit.parent(0) {
+        Ok(parent) => Some(
+            parent
+                .tree()
+                .map_err(|e| format!("failed to read tree of {}: {e}", parent.id()))?,
+        ),
+        Err(_) => None,
+    };
+    let diff = repo
+        .diff_tree_to_tree(parent.as_ref(), Some(&tree), None)
+        .map_err(|e| format!("failed to diff {}: {e}", commit.id()))?;
+
+    // Per file: (path, removed occurrences, added occurrences, lines).
+    let mut files: Vec<(String, usize, usize, Vec<Snippet>)> = Vec::new();
+    diff.print(git2::DiffFormat::Patch, |delta, _, line| {
+        let origin = line.origin();
+        if origin != '+' && origin != '-' {
+            return true;
+        }
+        let path = delta
+            .new_file()
+            .path()
+            .or_else(|| delta.old_file().path())
+            .map(|p| p.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        if files.last().map(|f| &f.0) != Some(&path) {
+            files.push((path.clone(), 0, 0, Vec::new()));
+        }
+        let file = files.last_mut().expect("pushed above");
+        let text = String::from_utf8_lossy(line.content());
+        let text = text.trim_end_matches(['\n', '\r']);
+        let pattern = match content {
+            Content::Occurrences(pattern) | Content::Lines(pattern) => pattern,
+        };
+        let count = pattern.find_iter(text).count();
+        if count == 0 {
+            return true;
+        }
+        if origin == '-' {
+            file.1 += count;
+        } else {
+            file.2 += count;
+        }
+        file.3.push(Snippet {
+            source: path,
+            origin: Some(origin),
+            text: highlight(text, pattern),
+        });
+        true
+    })
+    .map_err(|e| format!("failed to diff {}: {e}", commit.id()))?;
+
+    let found: Vec<Snippet> = files
+        .into_iter()
+        .filter(|(_, removed, added, lines)| match content {
+            Content::Occurrences(_) => removed != added,
+            Content::Lines(_) => !lines.is_empty(),
+        })
+        .flat_map(|(_, _, _, lines)| lines)
+        .collect();
+    Ok((!found.is_empty()).then_some(found))
+}
+
+/// `line` with each match of `pattern` in `[` `]`, starting a little
+/// before the first match and cut to [`SNIPPET_LEN`] characters.
+fn highlight(line: &str, pattern: &Regex) -> String {
+    let line = line.trim();
+    let start = pattern.find(line).map_or(0, |m| m.start());
+    let from = line[..start]
+        .char_indices()
+        .rev()
+        .nth(CONTEXT - 1)
+        .map_or(0, |(i, _)| i);
+    let mut text = if from > 0 {
+        "…".to_string()
+    } else {
+        String::new()
+    };
+    let mut last = from;
+    for m in pattern.find_iter(&line[from..]) {
+        text.push_str(&line[last..from + m.start()]);
+        text.push('[');
+        text.push_str(m.as_str());
+        text.push(']');
+        last = from + m.end();
+    }
+    text.push_str(&line[last..]);
+    if text.chars().count() > SNIPPET_LEN {
+        text = text.chars().take(SNIPPET_LEN).collect();
+        text.push('…');
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn finds_commits_by_message_author_date_and_content() {
+        let (dir, repo) = crate::test_support::init_repo("history-search");
+        let commit = |path: &str, text: &str, who: &str, secs: i64, message: &str| {
+            fs::write(dir.join(path), text).expect("write");
+            let mut index = repo.index().expect("index");
+            index.add_path(std::path::Path::new(path)).expect("add");
+            index.write().expect("write index");
+            let tree = repo
+                .find_tree(index.write_tree().expect("tree"))
+                .expect("tree");
+            let sig = git2::Signature::new(
+                who,
+                &format!("{who}@example.com"),
+                &git2::Time::new(secs, 0),
+            )
+            .expect("sig");
+            let parent = repo.head().ok().and_then(|h| h.peel_to_commit().ok());
+            let parents: Vec<&git2::Commit> = parent.iter().collect();
+            repo.commit(Some("HEAD"), &sig, &sig, message, &tree, &parents)
+                .expect("commit");
+        };
+        let day = 86_400;
+        commit(
+            "a.rs",
+            "fn cache() {}\n",
+            "Ada",
+            day,
+            "Add cache\n\nFixes #12",
+        );
+        commit(
+            "a.rs",
+            "fn cache() {}\n// cache it\n",
+            "Bob",
+            2 * day,
+            "Comment",
+        );
+        commit("b.rs", "fn cache_size() {}\n", "Ada", 3 * day, "Size");
+        commit(
+            "a.rs",
+            "fn cached() {}\n// cache it\n",
+            "Bob",
+            4 * day,
+            "Rename",
+        );
+
+        let find = |query: Query| -> Vec<String> {
+            let page = search(&repo, &Query { limit: 50, ..query }).expect("search");
+            page.items
+                .iter()
+                .map(|h| h.commit.summary.clone())
+                .collect()
+        };
+        let message = Regex::new(r"(?i)fixes #\d+").unwrap();
+        assert_eq!(
+            find(Query {
+                message: Some(message.clone()),
+                ..Query::default()
+            }),
+            ["Add cache"]
+        );
+        // -S: the rename keeps the count of "cache" the same.
+        let literal = Regex::new(&regex::escape("cache")).unwrap();
+        assert_eq!(
+            find(Query {
+                content: Some(Content::Occurrences(literal.clone())),
+                ..Query::default()
+            }),
+            ["Size", "Comment", "Add cache"]
+        );
+        // -G: any changed line mentioning it.
+        assert_eq!(
+            find(Query {
+                content: Some(Content::Lines(literal)),
+                author: Some("bob"),
+                until: Some(3 * day),
+                ..Query::default()
+            }),
+            ["Comment"]
+        );
+        assert_eq!(
+            find(Query {
+                since: Some(2 * day),
+                until: Some(3 * day),
+                ..Query::default()
+            }),
+            ["Size", "Comment"]
+        );
+
+        let page = search(
+            &repo,
+            &Query {
+                limit: 1,
+                message: Some(message),
+                ..Query::default()
+            },
+        )
+        .expect("search");
+        assert_eq!(page.items[0].snippets[0].text, "[Fixes #12]");
+        let rename = search(
+            &repo,
+            &Query {
+                limit: 1,
+                content: Some(Content::Lines(Regex::new("cached").unwrap())),
+                ..Query::default()
+            },
+        )
+        .expect("search");
+        assert_eq!(
+            rename.items[0].snippets,
+            [Snippet {
+                source: "a.rs".to_string(),
+                origin: Some('+'),
+                text: "fn [cached]() {}".to_string(),
+            }]
+        );
+    }
+}
@@ -0,0 +1,138 @@
+pub mod tracker;
+
+/// Words that close the issue they precede, as on GitHub and GitLab.
+const CLOSING_KEYWORDS: [&str; 9] = [
+    "close", "closes", "closed", "fix", "fixes", "fixed", "resolve", "resolves", "resolved",
+];
+
+/// Issue numbers `message` closes with `Fixes #N` and friends, in order of
+/// appearance and without repeats. Keywords are case-insensitive and may end
+/// with a colon; each one closes the single `#N` that follows it.
+pub fn closing_refs(message: &str) -> Vec<i64> {
+    let words: Vec<&str> = message.split_whitespace().collect();
+    let mut refs = Vec::new();
+    for pair in words.windows(2) {
+        let keyword = pair[0]
+            .trim_matches(|c: char| !c.is_ascii_alphabetic())
+            .to_ascii_lowercase();
+        if !CLOSING_KEYWORDS.contains(&keyword.as_str()) {
+            continue;
+        }
+        if let Some(id) = issue_number(pair[1]) {
+            if !refs.contains(&id) {
+                refs.push(id);
+            }
+        }
+    }
+    refs
+}
+
+/// `#12`, `#12.` or `#12,` but not `#12a` or `#abc`.
+fn issue_number(word: &str) -> Option<i64> {
+    let rest = word.strip_prefix('#')?;
+    let end = rest
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(rest.len());
+    if rest[end..].starts_with(|c: char| c.is_alphanumeric()) {
+        return None;
+    }
+    rest[..end].parse().ok().filter(|id| *id > 0)
+}
+
+/// Issues closed by the commits on `from` that `to` doesn't have yet,
+/// oldest commit first.
+pub fn closed_by_branch(repo: &git2::Repository, from: &str, to: &str) -> Result<Vec<i64>, String> {
+    let mut refs = Vec::new();
+    for message in branch_messages(repo, from, to)? {
+        for id in closing_refs(&message) {
+            if !refs.contains(&id) {
+                refs.push(id);
+            }
+        }
+    }
+    Ok(refs)
+}
+
+/// Messages of the commits on `from` that `to` doesn't have yet, oldest
+/// first.
+pub fn branch_messages(
+    repo: &git2::Repository,
+    from: &str,
+    to: &str,
+) -> Result<Vec<String>, String> {
+    let resolve = |spec: &str| {
+        repo.revparse_single(spec)
+            .and_then(|object| object.peel_to_commit())
+            .map(|commit| commit.id())
+            .map_err(|e| format!("failed to resolve {spec}: {e}"))
+    };
+    let mut walk = repo
+        .revwalk()
+        .map_err(|e| format!("failed to walk history: {e}"))?;
+    walk.set_sorting(git2::Sort::TOPOLOGICAL | git2::Sort::REVERSE)
+        .map_err(|e| format!("failed to walk history: {e}"))?;
+    walk.push(resolve(from)?)
+        .map_err(|e| format!("failed to walk {from}: {e}"))?;
+    walk.hide(resolve(to)?)
+        .map_err(|e| format!("failed to walk {to}: {e}"))?;
+
+    walk.map(|oid| {
+        let oid = oid.map_err(|e| format!("failed to walk history: {e}"))?;
+        let commit = repo
+            .find_commit(oid)
+            .map_err(|e| format!("failed to read commit {oid}: {e}"))?;
+        Ok(String::from_utf8_lossy(commit.message_bytes()).into_owned())
+    })
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn closing_keywords_take_the_following_number() {
+        let message = "Handle empty repos\n\nFixes #12. Also closes: #3, resolved #12\n\
+                       See #4; fixes#5; fix #6a; (Resolves #7)";
+        assert_eq!(closing_refs(message), [12, 3, 7]);
+        assert!(closing_refs("refs #9, prefixes #10").is_empty());
+    }
+
+    fn commit(repo: &git2::Repository, message: &str) -> git2::Oid {
+        let signature = git2::Signature::now("Test", "test@example.com").expect("signature");
+        let tree = {
+            let mut index = repo.index().expect("index");
+            let id = index.write_tree().expect("tree");
+            repo.find_tree(id).expect("find tree")
+        };
+        let parent = repo.head().ok().and_then(|h| h.peel_to_commit().ok());
+        let parents: Vec<&git2::Commit> = parent.iter().collect();
+        repo.commit(
+            Some("HEAD"),
+            &signature,
+            &signature,
+            message,
+            &tree,
+            &parents,
+        )
+        .expect("commit")
+    }
+
+    #[test]
+    fn branch_commits_not_on_the_target_are_scanned() {
+        let (_dir, repo) = crate::test_support::init_repo("issues");
+
+        let base = commit(&repo, "Initial commit (fixes #1)");
+        repo.branch("main-line", &repo.find_commit(base).expect("base"), true)
+            .expect("branch");
+        commit(&repo, "Parse config\n\nFixes #2");
+        commit(&repo, "Cache lookups, closes #3 and fixes #2");
+
+        let refs = closed_by_branch(&repo, "HEAD", "main-line").expect("scan");
+        assert_eq!(refs, [2, 3]);
+        assert!(closed_by_branch(&repo, "HEAD", "HEAD")
+            .expect("empty")
+            .is_empty());
+        assert!(closed_by_branch(&repo, "missing", "HEAD").is_err());
+    }
+}
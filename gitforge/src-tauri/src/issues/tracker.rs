@@ -0,0 +1,566 @@
+use serde_json::{json, Value};
+use std::time::Duration;
+
+use crate::store::LinkedIssue;
+
+/// Git config naming the tracker: `jira` or `linear`. Nothing is linked
+/// while it is unset.
+pub const KIND_KEY: &str = "gitforge.tracker.kind";
+/// Jira site, e.g. `https://example.atlassian.net`.
+pub const URL_KEY: &str = "gitforge.tracker.url";
+/// Jira account email; with it the token is sent as Jira Cloud basic auth,
+/// without it as a Data Center bearer token.
+pub const USER_KEY: &str = "gitforge.tracker.user";
+/// Environment variable holding the API token, so no secret lives in git
+/// config.
+pub const TOKEN_ENV_KEY: &str = "gitforge.tracker.tokenEnv";
+/// Comma-separated project or team keys to recognize, e.g. `PROJ,OPS`.
+pub const PROJECTS_KEY: &str = "gitforge.tracker.projects";
+/// Status (Jira transition or Linear workflow state) to move linked issues
+/// to when their PR merges.
+pub const MERGE_STATE_KEY: &str = "gitforge.tracker.mergeState";
+
+pub const LINEAR_API_URL: &str = "https://api.linear.app/graphql";
+/// Keys looked up per PR, so a noisy history can't stall PR creation.
+pub const MAX_LINKS: usize = 20;
+
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Jira,
+    Linear,
+}
+
+impl Kind {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "jira" => Some(Kind::Jira),
+            "linear" => Some(Kind::Linear),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Kind::Jira => "jira",
+            Kind::Linear => "linear",
+        }
+    }
+
+    fn default_token_env(self) -> &'static str {
+        match self {
+            Kind::Jira => "GITFORGE_JIRA_TOKEN",
+            Kind::Linear => "GITFORGE_LINEAR_TOKEN",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TrackerConfig {
+    pub kind: Kind,
+    /// Jira site without a trailing slash.
+    pub url: Option<String>,
+    pub user: Option<String>,
+    pub token_env: String,
+    /// Upper-cased; empty accepts any `ABC-123` key.
+    pub projects: Vec<String>,
+    pub merge_state: Option<String>,
+}
+
+impl TrackerConfig {
+    /// The configured tracker, or `None` when [`KIND_KEY`] is unset.
+    pub fn from_config(config: &git2::Config) -> Result<Option<Self>, String> {
+        let read = |key: &str| match config.get_string(key) {
+            Ok(value) if value.trim().is_empty() => Ok(None),
+            Ok(value) => Ok(Some(value.trim().to_string())),
+            Err(e) if e.code() == git2::ErrorCode::NotFound => Ok(None),
+            Err(e) => Err(format!("invalid {key}: {e}")),
+        };
+        let Some(kind) = read(KIND_KEY)? else {
+            return Ok(None);
+        };
+        let kind = Kind::parse(&kind)
+            .ok_or_else(|| format!("invalid {KIND_KEY} {kind:?} (expected jira or linear)"))?;
+        let url = read(URL_KEY)?.map(|url| url.trim_end_matches('/').to_string());
+        if kind == Kind::Jira && url.is_none() {
+            return Err(format!("{URL_KEY} must name the Jira site"));
+        }
+
+        Ok(Some(Self {
+            kind,
+            url,
+            user: read(USER_KEY)?,
+            token_env: read(TOKEN_ENV_KEY)?.unwrap_or_else(|| kind.default_token_env().to_string()),
+            projects: read(PROJECTS_KEY)?
+                .map(|projects| {
+                    projects
+                        .split(',')
+                        .map(|project| project.trim().to_ascii_uppercase())
+                        .filter(|project| !project.is_empty())
+                        .collect()
+                })
+                .unwrap_or_default(),
+            merge_state: read(MERGE_STATE_KEY)?,
+        }))
+    }
+
+    pub fn open(repo_path: &str) -> Result<Option<Self>, String> {
+        let config = git2::Repository::open(repo_path)
+            .and_then(|repo| repo.config())
+            .map_err(|e| format!("failed to read git config: {e}"))?;
+        Self::from_config(&config)
+    }
+
+    /// Issue keys in `text`, in order and without repeats.
+    pub fn keys(&self, text: &str) -> Vec<String> {
+        issue_keys(text, &self.projects)
+    }
+}
+
+/// `ABC-123` keys in `text`. With `projects`, only those prefixes match,
+/// case-insensitively, so lower-case branch names like `proj-12-login`
+/// count; without, the prefix must be upper case to keep out words like
+/// `utf-8`.
+pub fn issue_keys(text: &str, projects: &[String]) -> Vec<String> {
+    let bytes = text.as_bytes();
+    let mut keys = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let starts_word = i == 0 || !bytes[i - 1].is_ascii_alphanumeric();
+        if !(starts_word && bytes[i].is_ascii_alphabetic()) {
+            i += 1;
+            continue;
+        }
+        let prefix_end = i + bytes[i..]
+            .iter()
+            .take_while(|b| b.is_ascii_alphanumeric())
+            .count();
+        let digits = bytes
+            .get(prefix_end + 1..)
+            .unwrap_or_default()
+            .iter()
+            .take_while(|b| b.is_ascii_digit())
+            .count();
+        let end = prefix_end + 1 + digits;
+        let is_key = bytes.get(prefix_end) == Some(&b'-')
+            && digits > 0
+            && bytes.get(end).is_none_or(|b| !b.is_ascii_alphanumeric());
+        if is_key {
+            let prefix = &text[i..prefix_end];
+            let known = if projects.is_empty() {
+                prefix.len() > 1
+                    && prefix
+                        .bytes()
+                        .all(|b| b.is_ascii_uppercase() || b.is_ascii_digit())
+            } else {
+                projects
+                    .iter()
+                    .any(|project| project.eq_ignore_ascii_case(prefix))
+            };
+            let key = text[i..end].to_ascii_uppercase();
+            if known && !keys.contains(&key) {
+                keys.push(key);
+            }
+            i = end;
+        } else {
+            i = prefix_end;
+        }
+    }
+    keys
+}
+
+/// The HTTP calls the trackers need, so they can be driven without a
+/// network. Error statuses with a JSON body are replies too, since both
+/// APIs explain failures in the body.
+pub trait Transport {
+    fn get_json(&self, url: &str, auth: &str) -> Result<Value, String>;
+    /// Empty replies (Jira answers transitions with 204) come back as
+    /// `null`.
+    fn post_json(&self, url: &str, auth: &str, body: &Value) -> Result<Value, String>;
+}
+
+pub struct Http;
+
+impl Transport for Http {
+    fn get_json(&self, url: &str, auth: &str) -> Result<Value, String> {
+        let reply = ureq::get(url)
+            .set("Accept", "application/json")
+            .set("Authorization", auth)
+            .timeout(TIMEOUT)
+            .call();
+        json_reply(url, reply)
+    }
+
+    fn post_json(&self, url: &str, auth: &str, body: &Value) -> Result<Value, String> {
+        let reply = ureq::post(url)
+            .set("Accept", "application/json")
+            .set("Authorization", auth)
+            .timeout(TIMEOUT)
+            .send_json(body);
+        json_reply(url, reply)
+    }
+}
+
+fn json_reply(url: &str, reply: Result<ureq::Response, ureq::Error>) -> Result<Value, String> {
+    let (code, response) = match reply {
+        Ok(response) => (None, response),
+        Err(ureq::Error::Status(code, response)) => (Some(code), response),
+        Err(e) => return Err(format!("failed to reach {url}: {e}")),
+    };
+    let text = response
+        .into_string()
+        .map_err(|e| format!("failed to read the response from {url}: {e}"))?;
+    if text.trim().is_empty() {
+        return match code {
+            Some(code) => Err(format!("{url} answered HTTP {code}")),
+            None => Ok(Value::Null),
+        };
+    }
+    serde_json::from_str(&text).map_err(|e| match code {
+        Some(code) => format!("{url} answered HTTP {code}"),
+        None => format!("invalid response from {url}: {e}"),
+    })
+}
+
+/// A configured tracker with its credentials resolved.
+pub struct Tracker {
+    config: TrackerConfig,
+    http: Box<dyn Transport + Send + Sync>,
+    auth: String,
+}
+
+impl Tracker {
+    /// Fails when the token variable is unset, naming it.
+    pub fn new(
+        config: TrackerConfig,
+        http: Box<dyn Transport + Send + Sync>,
+    ) -> Result<Self, String> {
+        let token = std::env::var(&config.token_env)
+            .ok()
+            .map(|token| token.trim().to_string())
+            .filter(|token| !token.is_empty())
+            .ok_or_else(|| {
+                format!(
+                    "set ${} to a {} API token",
+                    config.token_env,
+                    config.kind.as_str()
+                )
+            })?;
+        let auth = match (config.kind, &config.user) {
+            (Kind::Jira, Some(user)) => format!(
+                "Basic {}",
+                crate::mcp::chunks::base64(format!("{user}:{token}").as_bytes())
+            ),
+            (Kind::Jira, None) => format!("Bearer {token}"),
+            // Linear personal API keys go in the header as they are.
+            (Kind::Linear, _) => token,
+        };
+        Ok(Self { config, http, auth })
+    }
+
+    pub fn config(&self) -> &TrackerConfig {
+        &self.config
+    }
+
+    /// Title, status, and link of `key`.
+    pub fn fetch(&self, key: &str) -> Result<LinkedIssue, String> {
+        match self.config.kind {
+            Kind::Jira => self.jira_fetch(key),
+            Kind::Linear => self.linear_fetch(key),
+        }
+    }
+
+    /// `key` as a link, with whatever went wrong fetching it.
+    pub fn link(&self, key: &str) -> (LinkedIssue, Option<String>) {
+        match self.fetch(key) {
+            Ok(issue) => (issue, None),
+            Err(e) => (
+                LinkedIssue {
+                    key: key.to_string(),
+                    tracker: self.config.kind.as_str().to_string(),
+                    title: None,
+                    status: None,
+                    url: None,
+                },
+                Some(format!("{key}: {e}")),
+            ),
+        }
+    }
+
+    /// Moves `key` to the status named `state`.
+    pub fn transition(&self, key: &str, state: &str) -> Result<(), String> {
+        match self.config.kind {
+            Kind::Jira => self.jira_transition(key, state),
+            Kind::Linear => self.linear_transition(key, state),
+        }
+    }
+
+    fn jira_url(&self, path: &str) -> String {
+        format!("{}{path}", self.config.url.as_deref().unwrap_or_default())
+    }
+
+    fn jira_fetch(&self, key: &str) -> Result<LinkedIssue, String> {
+        let url = self.jira_url(&format!("/rest/api/2/issue/{key}?fields=summary,status"));
+        let reply = self.http.get_json(&url, &self.auth)?;
+        let fields = reply.get("fields").ok_or_else(|| jira_error(&reply))?;
+        Ok(LinkedIssue {
+            key: key.to_string(),
+            tracker: Kind::Jira.as_str().to_string(),
+            title: fields["summary"].as_str().map(str::to_string),
+            status: fields["status"]["name"].as_str().map(str::to_string),
+            url: Some(self.jira_url(&format!("/browse/{key}"))),
+        })
+    }
+
+    fn jira_transition(&self, key: &str, state: &str) -> Result<(), String> {
+        let url = self.jira_url(&format!("/rest/api/2/issue/{key}/transitions"));
+        let reply = self.http.get_json(&url, &self.auth)?;
+        let transitions = reply["transitions"]
+            .as_array()
+            .ok_or_else(|| jira_error(&reply))?;
+        let named = |transition: &&Value| {
+            [&transition["name"], &transition["to"]["name"]]
+                .iter()
+                .any(|name| name.as_str().is_some_and(|n| n.eq_ignore_ascii_case(state)))
+        };
+        let Some(transition) = transitions.iter().find(named) else {
+            let available: Vec<&str> = transitions
+                .iter()
+                .filter_map(|t| t["name"].as_str())
+                .collect();
+            return Err(format!(
+                "{key} has no transition to {state:?} (available: {})",
+                available.join(", ")
+            ));
+        };
+
+        let body = json!({ "transition": { "id": transition["id"] } });
+        let reply = self.http.post_json(&url, &self.auth, &body)?;
+        if reply.get("errorMessages").is_some() || reply.get("errors").is_some() {
+            return Err(jira_error(&reply));
+        }
+        Ok(())
+    }
+
+    fn linear(&self, query: &str, variables: Value) -> Result<Value, String> {
+        let body = json!({ "query": query, "variables": variables });
+        let reply = self.http.post_json(LINEAR_API_URL, &self.auth, &body)?;
+        if let Some(message) = reply["errors"][0]["message"].as_str() {
+            return Err(format!("Linear rejected the request: {message}"));
+        }
+        Ok(reply["data"].clone())
+    }
+
+    fn linear_fetch(&self, key: &str) -> Result<LinkedIssue, String> {
+        let data = self.linear(
+            "query($id: String!) { issue(id: $id) { title url state { name } } }",
+            json!({ "id": key }),
+        )?;
+        let issue = &data["issue"];
+        if issue.is_null() {
+            return Err("not found".to_string());
+        }
+        Ok(LinkedIssue {
+            key: key.to_string(),
+            tracker: Kind::Linear.as_str().to_string(),
+            title: issue["title"].as_str().map(str::to_string),
+            status: issue["state"]["name"].as_str().map(str::to_string),
+            url: issue["url"].as_str().map(str::to_string),
+        })
+    }
+
+    fn linear_transition(&self, key: &str, state: &str) -> Result<(), String> {
+        let data = self.linear(
+            "query($id: String!) { issue(id: $id) { team { states { nodes { id name } } } } }",
+            json!({ "id": key }),
+        )?;
+        let states = data["issue"]["team"]["states"]["nodes"]
+            .as_array()
+            .ok_or_else(|| format!("{key} not found"))?;
+        let target = states
+            .iter()
+            .find(|s| {
+                s["name"]
+                    .as_str()
+                    .is_some_and(|n| n.eq_ignore_ascii_case(state))
+            })
+            .ok_or_else(|| format!("{key}'s team has no {state:?} state"))?;
+
+        let data = self.linear(
+            "mutation($id: String!, $state: String!) { issueUpdate(id: $id, input: { stateId: $state }) { success } }",
+            json!({ "id": key, "state": target["id"] }),
+        )?;
+        if data["issueUpdate"]["success"].as_bool() != Some(true) {
+            return Err(format!("Linear did not move {key} to {state:?}"));
+        }
+        Ok(())
+    }
+}
+
+fn jira_error(reply: &Value) -> String {
+    let mut messages: Vec<String> = reply["errorMessages"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|m| m.as_str().map(str::to_string))
+        .collect();
+    if let Some(errors) = reply["errors"].as_object() {
+        messages.extend(errors.iter().map(|(field, e)| format!("{field}: {e}")));
+    }
+    if messages.is_empty() {
+        return "unexpected response from Jira".to_string();
+    }
+    format!("Jira rejected the request: {}", messages.join("; "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    /// Answers from a script, recording each request's URL, auth, and body.
+    #[derive(Default)]
+    struct Scripted {
+        replies: Mutex<Vec<Value>>,
+        sent: Mutex<Vec<(String, String, Value)>>,
+    }
+
+    impl Scripted {
+        fn new(replies: Vec<Value>) -> Arc<Self> {
+            Arc::new(Self {
+                replies: Mutex::new(replies),
+                ..Self::default()
+            })
+        }
+
+        fn answer(&self, url: &str, auth: &str, body: Value) -> Result<Value, String> {
+            let mut sent = self.sent.lock().unwrap();
+            sent.push((url.to_string(), auth.to_string(), body));
+            Ok(self.replies.lock().unwrap().remove(0))
+        }
+    }
+
+    impl Transport for Arc<Scripted> {
+        fn get_json(&self, url: &str, auth: &str) -> Result<Value, String> {
+            self.answer(url, auth, Value::Null)
+        }
+
+        fn post_json(&self, url: &str, auth: &str, body: &Value) -> Result<Value, String> {
+            self.answer(url, auth, body.clone())
+        }
+    }
+
+    fn config(kind: Kind, env: &str) -> TrackerConfig {
+        TrackerConfig {
+            kind,
+            url: Some("https://example.atlassian.net".to_string()),
+            user: Some("dev@example.com".to_string()),
+            token_env: env.to_string(),
+            projects: Vec::new(),
+            merge_state: Some("Done".to_string()),
+        }
+    }
+
+    #[test]
+    fn keys_are_found_in_branches_and_messages() {
+        let any: Vec<String> = Vec::new();
+        assert_eq!(
+            issue_keys("feature/PROJ-12-login (see OPS-3, PROJ-12)", &any),
+            ["PROJ-12", "OPS-3"]
+        );
+        assert!(issue_keys("utf-8, X-1, PROJ-12a, aPROJ-1", &any).is_empty());
+
+        let projects = vec!["PROJ".to_string()];
+        assert_eq!(issue_keys("proj-7-fix and OPS-3", &projects), ["PROJ-7"]);
+    }
+
+    #[test]
+    fn tracker_config_comes_from_git_config() {
+        let (_dir, repo) = crate::test_support::init_repo("tracker");
+        let mut config = repo.config().expect("config");
+        assert_eq!(TrackerConfig::from_config(&config), Ok(None));
+
+        config.set_str(KIND_KEY, "jira").expect("kind");
+        let err = TrackerConfig::from_config(&config).unwrap_err();
+        assert!(err.contains(URL_KEY), "{err}");
+
+        config
+            .set_str(URL_KEY, "https://example.atlassian.net/")
+            .expect("url");
+        config.set_str(PROJECTS_KEY, "proj, ops").expect("projects");
+        let tracker = TrackerConfig::from_config(&config)
+            .expect("config")
+            .expect("configured");
+        assert_eq!(
+            tracker.url.as_deref(),
+            Some("https://example.atlassian.net")
+        );
+        assert_eq!(tracker.token_env, "GITFORGE_JIRA_TOKEN");
+        assert_eq!(tracker.projects, ["PROJ", "OPS"]);
+    }
+
+    #[test]
+    fn jira_issues_are_fetched_and_transitioned() {
+        std::env::set_var("GITFORGE_TEST_JIRA_TOKEN", "t0ken");
+        let http = Scripted::new(vec![
+            json!({ "fields": { "summary": "Login page", "status": { "name": "In Progress" } } }),
+            json!({ "transitions": [
+                { "id": "11", "name": "Start", "to": { "name": "In Progress" } },
+                { "id": "31", "name": "Close", "to": { "name": "Done" } }
+            ] }),
+            Value::Null,
+        ]);
+        let tracker = Tracker::new(
+            config(Kind::Jira, "GITFORGE_TEST_JIRA_TOKEN"),
+            Box::new(Arc::clone(&http)),
+        )
+        .expect("tracker");
+
+        let issue = tracker.fetch("PROJ-12").expect("fetch");
+        assert_eq!(issue.title.as_deref(), Some("Login page"));
+        assert_eq!(issue.status.as_deref(), Some("In Progress"));
+        assert_eq!(
+            issue.url.as_deref(),
+            Some("https://example.atlassian.net/browse/PROJ-12")
+        );
+        tracker.transition("PROJ-12", "done").expect("transition");
+
+        let sent = http.sent.lock().unwrap();
+        let expected_auth = format!(
+            "Basic {}",
+            crate::mcp::chunks::base64(b"dev@example.com:t0ken")
+        );
+        assert!(sent.iter().all(|(_, auth, _)| *auth == expected_auth));
+        assert_eq!(sent[2].2, json!({ "transition": { "id": "31" } }));
+    }
+
+    #[test]
+    fn linear_errors_leave_an_unresolved_link() {
+        std::env::set_var("GITFORGE_TEST_LINEAR_TOKEN", "lin_api");
+        let http = Scripted::new(vec![
+            json!({ "errors": [{ "message": "Entity not found" }] }),
+        ]);
+        let tracker = Tracker::new(
+            config(Kind::Linear, "GITFORGE_TEST_LINEAR_TOKEN"),
+            Box::new(Arc::clone(&http)),
+        )
+        .expect("tracker");
+
+        let (link, error) = tracker.link("ENG-4");
+        assert_eq!(link.key, "ENG-4");
+        assert_eq!(link.tracker, "linear");
+        assert!(link.title.is_none());
+        assert!(error.expect("error").contains("Entity not found"));
+        assert_eq!(http.sent.lock().unwrap()[0].1, "lin_api");
+
+        let missing = Tracker::new(
+            config(Kind::Linear, "GITFORGE_TEST_UNSET_TOKEN"),
+            Box::new(http),
+        );
+        assert!(missing
+            .err()
+            .expect("no token")
+            .contains("GITFORGE_TEST_UNSET_TOKEN"));
+    }
+}
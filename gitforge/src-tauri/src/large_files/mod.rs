@@ -0,0 +1,221 @@
+use serde::Serialize;
+use std::path::Path;
+use std::process::Command;
+
+/// Largest blob `git_commit` accepts, in bytes; git's `k`, `m` and `g`
+/// suffixes work, and 0 turns the guard off.
+pub const MAX_SIZE_KEY: &str = "gitforge.commit.maxFileSize";
+/// GitHub warns about files over 50 MiB and refuses them over 100 MiB.
+pub const DEFAULT_MAX_SIZE: u64 = 50 * 1024 * 1024;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LargeFile {
+    pub path: String,
+    pub size: u64,
+}
+
+/// The configured limit, or `None` when the guard is off.
+pub fn max_size(repo: &git2::Repository) -> Result<Option<u64>, String> {
+    let config = repo
+        .config()
+        .map_err(|e| format!("failed to read git config: {e}"))?;
+    match config.get_i64(MAX_SIZE_KEY) {
+        Ok(size) => Ok((size > 0).then_some(size as u64)),
+        Err(e) if e.code() == git2::ErrorCode::NotFound => Ok(Some(DEFAULT_MAX_SIZE)),
+        Err(e) => Err(format!("invalid {MAX_SIZE_KEY}: {e}")),
+    }
+}
+
+/// Staged blobs over `limit`. Sizes come from object headers, so nothing
+/// large is read.
+pub fn staged(repo: &git2::Repository, limit: u64) -> Result<Vec<LargeFile>, String> {
+    let head = repo.head().ok().and_then(|head| head.peel_to_tree().ok());
+    let diff = repo
+        .diff_tree_to_index(head.as_ref(), None, None)
+        .map_err(|e| format!("failed to diff the index: {e}"))?;
+    over_limit(repo, &diff, limit)
+}
+
+/// Blobs over `limit` that `new` adds or changes relative to `old`.
+pub fn between(
+    repo: &git2::Repository,
+    old: &git2::Tree,
+    new: &git2::Tree,
+    limit: u64,
+) -> Result<Vec<LargeFile>, String> {
+    let diff = repo
+        .diff_tree_to_tree(Some(old), Some(new), None)
+        .map_err(|e| format!("failed to diff the trees: {e}"))?;
+    over_limit(repo, &diff, limit)
+}
+
+fn over_limit(
+    repo: &git2::Repository,
+    diff: &git2::Diff,
+    limit: u64,
+) -> Result<Vec<LargeFile>, String> {
+    let odb = repo
+        .odb()
+        .map_err(|e| format!("failed to open the object database: {e}"))?;
+    let mut large = Vec::new();
+    for delta in diff.deltas() {
+        let file = delta.new_file();
+        let Some(path) = file.path() else { continue };
+        if file.id().is_zero() || file.mode() == git2::FileMode::Commit {
+            continue;
+        }
+        let (size, _) = odb
+            .read_header(file.id())
+            .map_err(|e| format!("failed to read {}: {e}", path.display()))?;
+        if size as u64 > limit {
+            large.push(LargeFile {
+                path: path.to_string_lossy().into_owned(),
+                size: size as u64,
+            });
+        }
+    }
+    Ok(large)
+}
+
+/// Why a commit of `files` was refused, with the LFS command that fixes it.
+pub fn refusal(files: &[LargeFile], limit: u64) -> String {
+    let listed: Vec<String> = files
+        .iter()
+        .map(|file| format!("{} ({})", file.path, human(file.size)))
+        .collect();
+    let paths: Vec<String> = files.iter().map(|f| format!("\"{}\"", f.path)).collect();
+    format!(
+        "{} over the {} limit ({MAX_SIZE_KEY}): {}. Track them with Git LFS \
+         (git lfs track --filename {} && git add .gitattributes) or pass lfs_track",
+        if files.len() == 1 {
+            "a file is"
+        } else {
+            "files are"
+        },
+        human(limit),
+        listed.join(", "),
+        paths.join(" ")
+    )
+}
+
+/// Moves `paths` to Git LFS: adds them to `.gitattributes` and restages
+/// them through the LFS filter, so the index holds pointers instead.
+/// libgit2 can't run external filters, so this needs `git` and `git-lfs`.
+pub fn track(repo: &git2::Repository, paths: &[String]) -> Result<(), String> {
+    let workdir = repo
+        .workdir()
+        .ok_or_else(|| "LFS tracking needs a working tree".to_string())?;
+    if git(workdir, &["lfs", "version"]).is_err() {
+        return Err("git-lfs isn't installed; install it or raise the limit".to_string());
+    }
+    let attributes = workdir.join(".gitattributes");
+    let mut text = match std::fs::read_to_string(&attributes) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(format!("failed to read .gitattributes: {e}")),
+    };
+    for path in paths {
+        let line = attributes_line(path);
+        if text.lines().any(|existing| existing == line) {
+            continue;
+        }
+        if !text.is_empty() && !text.ends_with('\n') {
+            text.push('\n');
+        }
+        text.push_str(&line);
+        text.push('\n');
+    }
+    std::fs::write(&attributes, text)
+        .map_err(|e| format!("failed to write .gitattributes: {e}"))?;
+
+    let mut args = vec!["add", "--", ".gitattributes"];
+    args.extend(paths.iter().map(String::as_str));
+    git(workdir, &args)
+}
+
+/// The `.gitattributes` line `git lfs track --filename` writes for `path`.
+fn attributes_line(path: &str) -> String {
+    let mut pattern = String::new();
+    for c in path.chars() {
+        match c {
+            ' ' => pattern.push_str("[[:space:]]"),
+            '*' | '?' | '[' | ']' | '\\' | '!' | '#' => {
+                pattern.push('\\');
+                pattern.push(c);
+            }
+            c => pattern.push(c),
+        }
+    }
+    format!("{pattern} filter=lfs diff=lfs merge=lfs -text")
+}
+
+fn git(workdir: &Path, args: &[&str]) -> Result<(), String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(workdir)
+        .args(args)
+        .output()
+        .map_err(|e| format!("failed to run git: {e}"))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("git {} failed: {}", args.join(" "), stderr.trim()));
+    }
+    Ok(())
+}
+
+fn human(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{size:.1} {}", UNITS[unit])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn staged_blobs_over_the_limit_are_reported() {
+        let (dir, repo) = crate::test_support::init_repo("large-files");
+        assert_eq!(max_size(&repo).expect("default"), Some(DEFAULT_MAX_SIZE));
+        repo.config()
+            .and_then(|mut c| c.set_str(MAX_SIZE_KEY, "2k"))
+            .expect("limit");
+        assert_eq!(max_size(&repo).expect("configured"), Some(2048));
+
+        std::fs::write(dir.join("small.txt"), "ok\n").expect("write");
+        std::fs::write(dir.join("dump file.sql"), vec![b'x'; 3000]).expect("write");
+        let mut index = repo.index().expect("index");
+        index.add_path(Path::new("small.txt")).expect("stage");
+        index.add_path(Path::new("dump file.sql")).expect("stage");
+        index.write().expect("write index");
+
+        let large = staged(&repo, 2048).expect("scan");
+        assert_eq!(
+            large,
+            [LargeFile {
+                path: "dump file.sql".into(),
+                size: 3000
+            }]
+        );
+        assert!(refusal(&large, 2048)
+            .starts_with("a file is over the 2.0 KiB limit (gitforge.commit.maxFileSize): dump file.sql (2.9 KiB)"));
+        assert_eq!(
+            attributes_line("dump file.sql"),
+            "dump[[:space:]]file.sql filter=lfs diff=lfs merge=lfs -text"
+        );
+
+        repo.config()
+            .and_then(|mut c| c.set_i64(MAX_SIZE_KEY, 0))
+            .expect("off");
+        assert_eq!(max_size(&repo).expect("off"), None);
+    }
+}
@@ -0,0 +1,74 @@
+// The MCP tool list is one `json!` literal, deeper than the default limit.
+#![recursion_limit = "256"]
+
+pub mod agent;
+pub mod archive;
+pub mod artifacts;
+pub mod auth;
+pub mod autosquash;
+pub mod blame;
+pub mod branches;
+pub mod budget;
+pub mod cherry_pick;
+pub mod ci;
+pub mod commit_lint;
+pub mod conflicts;
+pub mod content;
+pub mod db;
+pub mod diff;
+pub mod email;
+pub mod feed;
+pub mod graph;
+pub mod history;
+pub mod issues;
+pub mod large_files;
+pub mod maintenance;
+pub mod mirror;
+pub mod mcp {
+    pub mod access;
+    pub mod chunks;
+    pub mod index_lock;
+    pub mod journal;
+    pub mod paths;
+    pub mod pool;
+    pub mod ref_watch;
+    pub mod registry;
+    pub mod server;
+    pub mod single_flight;
+}
+pub mod notifications;
+pub mod operations;
+pub mod owners;
+pub mod precommit;
+pub mod projects;
+pub mod protection;
+#[cfg(feature = "history-purge")]
+pub mod purge;
+pub mod quick_open;
+pub mod rebase_plan;
+pub mod recent;
+pub mod release;
+pub mod reset;
+pub mod revcache;
+pub mod revert;
+pub mod sandbox;
+pub mod scaffold;
+pub mod secrets;
+pub mod smart_http;
+pub mod staging;
+pub mod stale;
+pub mod stash;
+pub mod stats;
+pub mod status;
+pub mod store;
+pub mod stream;
+pub mod suggestion;
+pub mod sync;
+pub mod tasks;
+pub mod test_runner;
+#[cfg(test)]
+mod test_support;
+pub mod trailers;
+pub mod watcher;
+pub mod workspace;
+pub mod worktree;
@@ -1,27 +1,60 @@
+#[cfg(feature = "voice")]
+use gitforge::agent::recorder::Recorder;
+#[cfg(feature = "voice")]
+use gitforge::agent::voice::{CommandStt, SpeechToText};
+use gitforge::agent::BpgtAgent;
+use gitforge::blame::{BlameCache, BlameRange};
+use gitforge::branches::{self, BranchInfo, DirtyMode, SwitchResult};
+use gitforge::conflicts::{self, ConflictFile, ConflictSides, MergeCommit, Resolution};
+use gitforge::content::{self, FileContent, FileDiff};
+use gitforge::graph::{self, CommitGraph};
+use gitforge::mcp::registry::ServerRegistry;
+use gitforge::mcp::server::GitForgeMcp;
+use gitforge::mcp::server::McpRequest;
+use gitforge::notifications::{Notification, Notifications};
+use gitforge::operations::{
+    self, FetchSummary, OperationKind, Operations, Progress, ProgressSink, PushSummary,
+    RebaseSummary, PROGRESS_EVENT,
+};
+use gitforge::protection;
+use gitforge::quick_open::{QuickOpen, QuickOpenMatch};
+use gitforge::rebase_plan::{self, Applied, Plan};
+use gitforge::recent::{self, RecentRepo, RecentRepos};
+use gitforge::revcache::RevCache;
+use gitforge::secrets;
+use gitforge::staging::{self, Discarded};
+use gitforge::stream::EventStreams;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
+use tauri::{Emitter, Manager};
+use tauri_plugin_dialog::DialogExt;
+use tauri_plugin_notification::NotificationExt;
 
-mod agent;
-mod mcp {
-    pub mod server;
-}
-
-use agent::BpgtAgent;
-use mcp::server::GitForgeMcp;
+/// Label of the next repository window; `main` is the first one.
+static NEXT_WINDOW: AtomicUsize = AtomicUsize::new(1);
 
+/// Runs `method` against `repo_path`, or against the calling window's
+/// repository when no path is given.
 #[tauri::command]
-async fn mcp_call(method: String, params: serde_json::Value, repo_path: String) -> Result<serde_json::Value, String> {
-    let server = GitForgeMcp::new(repo_path)?;
-    let request = mcp::server::McpRequest {
+async fn mcp_call(
+    window: tauri::Window,
+    registry: tauri::State<'_, ServerRegistry>,
+    method: String,
+    params: serde_json::Value,
+    repo_path: Option<String>,
+) -> Result<serde_json::Value, String> {
+    let server = match repo_path {
+        Some(repo_path) => registry.get_or_open(&repo_path)?,
+        None => registry.for_window(window.label())?,
+    };
+    let request = McpRequest {
         jsonrpc: "2.0".to_string(),
         id: serde_json::json!(1),
         method,
         params,
     };
 
-    let response = {
-        let server = Arc::new(server);
-        server.execute_mcp_for_tauri(&request).await
-    };
+    let response = server.execute_mcp_for_tauri(&request).await;
 
     match response.error {
         Some(err) => Err(err.message),
@@ -29,14 +62,694 @@ async fn mcp_call(method: String, params: serde_json::Value, repo_path: String)
     }
 }
 
+/// Shows the repository containing `repo_path` in the calling window and
+/// returns its root.
+#[tauri::command]
+async fn open_repo(
+    window: tauri::Window,
+    registry: tauri::State<'_, ServerRegistry>,
+    repo_path: String,
+) -> Result<String, String> {
+    let root = recent::repo_root(&repo_path)?;
+    let server = registry.bind_window(window.label(), &root)?;
+    watch_notifications(window.app_handle(), &root, &server)?;
+    RecentRepos::open_default().touch(&root)?;
+    let _ = window.set_title(&format!("GitForge — {root}"));
+    Ok(root)
+}
+
+/// OS notifications for goal, PR, and merge events in `root`, filtered by
+/// the `notifications.*` settings.
+fn watch_notifications(
+    app: &tauri::AppHandle,
+    root: &str,
+    server: &GitForgeMcp,
+) -> Result<(), String> {
+    let handle = app.clone();
+    let notify = Arc::new(move |notification: &Notification| {
+        let _ = handle
+            .notification()
+            .builder()
+            .title(&notification.title)
+            .body(&notification.body)
+            .show();
+    });
+    app.state::<Notifications>()
+        .watch(root, server.engine(), Arc::clone(server.store()), notify)
+        .map(|_| ())
+}
+
+/// Opens the repository in its own window, or focuses the window already
+/// showing it. Returns the window label.
+#[tauri::command]
+async fn open_repo_window(
+    app: tauri::AppHandle,
+    registry: tauri::State<'_, ServerRegistry>,
+    repo_path: String,
+) -> Result<String, String> {
+    let root = recent::repo_root(&repo_path)?;
+    if let Some(label) = registry.windows_for(&root)?.into_iter().next() {
+        if let Some(window) = app.get_webview_window(&label) {
+            let _ = window.set_focus();
+            return Ok(label);
+        }
+    }
+
+    let label = format!("repo-{}", NEXT_WINDOW.fetch_add(1, Ordering::Relaxed));
+    let server = registry.bind_window(&label, &root)?;
+    tauri::WebviewWindowBuilder::new(&app, &label, tauri::WebviewUrl::App("index.html".into()))
+        .title(format!("GitForge — {root}"))
+        .build()
+        .map_err(|e| {
+            let _ = registry.unbind_window(&label);
+            format!("failed to open window: {e}")
+        })?;
+    watch_notifications(&app, &root, &server)?;
+    RecentRepos::open_default().touch(&root)?;
+    Ok(label)
+}
+
+/// Native folder picker; opens the chosen repository in a new window.
+/// `None` when the user cancels.
+#[tauri::command]
+async fn pick_repo(
+    app: tauri::AppHandle,
+    registry: tauri::State<'_, ServerRegistry>,
+) -> Result<Option<String>, String> {
+    let dialog = app.clone();
+    let picked = tokio::task::spawn_blocking(move || {
+        dialog
+            .dialog()
+            .file()
+            .set_title("Open repository")
+            .blocking_pick_folder()
+    })
+    .await
+    .map_err(|e| format!("folder picker failed: {e}"))?;
+
+    match picked {
+        Some(path) => {
+            let path = path
+                .into_path()
+                .map_err(|e| format!("folder picker failed: {e}"))?;
+            open_repo_window(app, registry, path.to_string_lossy().to_string())
+                .await
+                .map(Some)
+        }
+        None => Ok(None),
+    }
+}
+
+#[tauri::command]
+async fn recent_repos() -> Result<Vec<RecentRepo>, String> {
+    Ok(RecentRepos::open_default().list())
+}
+
+/// Repository shown by the calling window, so a reloaded page can restore it.
+#[tauri::command]
+async fn window_repo(
+    window: tauri::Window,
+    registry: tauri::State<'_, ServerRegistry>,
+) -> Result<Option<String>, String> {
+    Ok(registry
+        .window_repo(window.label())?
+        .map(|path| path.to_string_lossy().to_string()))
+}
+
+/// Releases the repo's server and event stream; returns whether it was open.
+#[tauri::command]
+async fn close_repo(
+    registry: tauri::State<'_, ServerRegistry>,
+    streams: tauri::State<'_, EventStreams>,
+    notifications: tauri::State<'_, Notifications>,
+    file_index: tauri::State<'_, QuickOpen>,
+    repo_path: String,
+) -> Result<bool, String> {
+    streams.unsubscribe(&repo_path)?;
+    notifications.unwatch(&repo_path)?;
+    file_index.forget(&repo_path)?;
+    registry.close(&repo_path)
+}
+
+/// Pushes `system_event`, `repo_changed`, and `repo_event` to the windows
+/// showing `repo_path` (every window if none is bound to it), so panels
+/// update without polling `mcp_call`. Subscribing twice is a no-op.
+#[tauri::command]
+async fn events_subscribe(
+    app: tauri::AppHandle,
+    registry: tauri::State<'_, ServerRegistry>,
+    streams: tauri::State<'_, EventStreams>,
+    repo_path: String,
+) -> Result<bool, String> {
+    let server = registry.get_or_open(&repo_path)?;
+    let repo = repo_path.clone();
+    let sink = Arc::new(move |name: &str, payload: serde_json::Value| {
+        let labels = app
+            .state::<ServerRegistry>()
+            .windows_for(&repo)
+            .unwrap_or_default();
+        if labels.is_empty() {
+            let _ = app.emit(name, payload);
+            return;
+        }
+        for window in labels
+            .iter()
+            .filter_map(|label| app.get_webview_window(label))
+        {
+            let _ = window.emit(name, payload.clone());
+        }
+    });
+    streams.subscribe(&repo_path, server.engine(), sink)
+}
+
+#[tauri::command]
+async fn events_unsubscribe(
+    streams: tauri::State<'_, EventStreams>,
+    repo_path: String,
+) -> Result<bool, String> {
+    streams.unsubscribe(&repo_path)
+}
+
+/// File content for the editor; `revision` is a commit-ish, `INDEX`, or
+/// `WORKDIR` (default).
+#[tauri::command]
+async fn read_file_at(
+    repo_path: String,
+    path: String,
+    revision: Option<String>,
+) -> Result<FileContent, String> {
+    tokio::task::spawn_blocking(move || {
+        content::read_file_at(&repo_path, &path, revision.as_deref())
+    })
+    .await
+    .map_err(|e| format!("read_file_at failed: {e}"))?
+}
+
+/// Both sides and hunks for Monaco's diff editor; `base` defaults to `HEAD`
+/// and `target` to the working tree.
+#[tauri::command]
+async fn diff_file(
+    repo_path: String,
+    path: String,
+    base: Option<String>,
+    target: Option<String>,
+) -> Result<FileDiff, String> {
+    tokio::task::spawn_blocking(move || {
+        content::diff_file(&repo_path, &path, base.as_deref(), target.as_deref())
+    })
+    .await
+    .map_err(|e| format!("diff_file failed: {e}"))?
+}
+
+/// Graph rows for the history panel; `limit` 0 means the default page.
+#[tauri::command]
+async fn commit_graph(repo_path: String, limit: Option<usize>) -> Result<CommitGraph, String> {
+    tokio::task::spawn_blocking(move || {
+        let repo = git2::Repository::open(&repo_path)
+            .map_err(|e| format!("failed to open repository: {e}"))?;
+        graph::commit_graph(&repo, limit.unwrap_or_default())
+    })
+    .await
+    .map_err(|e| format!("commit_graph failed: {e}"))?
+}
+
+/// Gutter blame for the visible lines of `path`; scrolling only blames lines
+/// not seen before for the same blob.
+#[tauri::command]
+async fn blame_range(
+    cache: tauri::State<'_, BlameCache>,
+    repo_path: String,
+    path: String,
+    start: u32,
+    end: u32,
+    revision: Option<String>,
+) -> Result<BlameRange, String> {
+    let cache = cache.inner().clone();
+    with_repo(repo_path, move |repo| {
+        cache.blame_range(repo, &path, revision.as_deref(), start, end)
+    })
+    .await
+}
+
+/// Ctrl-P file picker: tracked paths fuzzy-matching `query`, best first.
+#[tauri::command]
+async fn quick_open(
+    index: tauri::State<'_, QuickOpen>,
+    repo_path: String,
+    query: String,
+    limit: Option<usize>,
+) -> Result<Vec<QuickOpenMatch>, String> {
+    let index = index.inner().clone();
+    tokio::task::spawn_blocking(move || index.query(&repo_path, &query, limit.unwrap_or_default()))
+        .await
+        .map_err(|e| format!("quick_open failed: {e}"))?
+}
+
+/// Runs a blocking git2 operation off the async runtime.
+async fn with_repo<T: Send + 'static>(
+    repo_path: String,
+    op: impl FnOnce(&git2::Repository) -> Result<T, String> + Send + 'static,
+) -> Result<T, String> {
+    tokio::task::spawn_blocking(move || {
+        let repo = git2::Repository::open(&repo_path)
+            .map_err(|e| format!("failed to open repository: {e}"))?;
+        op(&repo)
+    })
+    .await
+    .map_err(|e| format!("git task failed: {e}"))?
+}
+
+#[tauri::command]
+async fn stage_file(repo_path: String, path: String) -> Result<(), String> {
+    with_repo(repo_path, move |repo| staging::stage_file(repo, &path)).await
+}
+
+#[tauri::command]
+async fn unstage_file(repo_path: String, path: String) -> Result<(), String> {
+    with_repo(repo_path, move |repo| staging::unstage_file(repo, &path)).await
+}
+
+#[tauri::command]
+async fn discard_file(repo_path: String, path: String) -> Result<Discarded, String> {
+    with_repo(repo_path, move |repo| staging::discard_file(repo, &path)).await
+}
+
+/// `hunk` indexes the hunks of `diff_file(path, "INDEX")`.
+#[tauri::command]
+async fn stage_hunk(repo_path: String, path: String, hunk: usize) -> Result<(), String> {
+    with_repo(repo_path, move |repo| {
+        staging::stage_hunk(repo, &path, hunk)
+    })
+    .await
+}
+
+/// `hunk` indexes the hunks of `diff_file(path, "HEAD", "INDEX")`.
+#[tauri::command]
+async fn unstage_hunk(repo_path: String, path: String, hunk: usize) -> Result<(), String> {
+    with_repo(repo_path, move |repo| {
+        staging::unstage_hunk(repo, &path, hunk)
+    })
+    .await
+}
+
+#[tauri::command]
+async fn branches_list(
+    revs: tauri::State<'_, RevCache>,
+    repo_path: String,
+    include_remote: Option<bool>,
+) -> Result<Vec<BranchInfo>, String> {
+    let revs = revs.inner().clone();
+    with_repo(repo_path, move |repo| {
+        branches::branches_list(repo, include_remote.unwrap_or(false), &revs)
+    })
+    .await
+}
+
+/// `dirty` is `refuse` (default), `carry`, `stash`, or `discard`.
+#[tauri::command]
+async fn branch_switch(
+    repo_path: String,
+    name: String,
+    dirty: Option<DirtyMode>,
+) -> Result<SwitchResult, String> {
+    tokio::task::spawn_blocking(move || {
+        let mut repo = git2::Repository::open(&repo_path)
+            .map_err(|e| format!("failed to open repository: {e}"))?;
+        branches::branch_switch(&mut repo, &name, dirty.unwrap_or_default())
+    })
+    .await
+    .map_err(|e| format!("branch_switch failed: {e}"))?
+}
+
+#[tauri::command]
+async fn conflicts_list(repo_path: String) -> Result<Vec<ConflictFile>, String> {
+    with_repo(repo_path, conflicts::conflicts_list).await
+}
+
+/// Base, ours, theirs, and working contents for the three-way editor.
+#[tauri::command]
+async fn conflict_sides(repo_path: String, path: String) -> Result<ConflictSides, String> {
+    with_repo(repo_path, move |repo| {
+        conflicts::conflict_sides(repo, &path)
+    })
+    .await
+}
+
+/// `resolution` is `"ours"`, `"theirs"`, or `{"buffer": "..."}`.
+#[tauri::command]
+async fn conflict_resolve(
+    repo_path: String,
+    path: String,
+    resolution: Resolution,
+) -> Result<(), String> {
+    with_repo(repo_path, move |repo| {
+        conflicts::resolve_conflict(repo, &path, resolution)
+    })
+    .await
+}
+
+#[tauri::command]
+async fn conflict_mark_resolved(repo_path: String, path: String) -> Result<(), String> {
+    with_repo(repo_path, move |repo| conflicts::mark_resolved(repo, &path)).await
+}
+
+#[tauri::command]
+async fn merge_finalize(repo_path: String, message: Option<String>) -> Result<MergeCommit, String> {
+    with_repo(repo_path, move |repo| {
+        conflicts::merge_finalize(repo, message.as_deref())
+    })
+    .await
+}
+
 #[tauri::command]
 async fn voice_process(text: String, db_path: String) -> Result<String, String> {
     let agent = BpgtAgent::new(&db_path);
     agent.process_voice(&text).await
 }
 
+/// Registers `operation_id` and runs `op` on the blocking pool, emitting
+/// `operation_progress` events until it finishes.
+async fn run_operation<T: Send + 'static>(
+    app: tauri::AppHandle,
+    operations: &Operations,
+    operation_id: String,
+    kind: OperationKind,
+    op: impl FnOnce(&operations::Operation) -> Result<T, String> + Send + 'static,
+) -> Result<T, String> {
+    let sink: ProgressSink = Arc::new(move |progress: &Progress| {
+        let _ = app.emit(PROGRESS_EVENT, progress);
+    });
+    let operation = operations.begin(&operation_id, kind, sink)?;
+    tokio::task::spawn_blocking(move || op(&operation))
+        .await
+        .map_err(|e| format!("operation {operation_id} failed: {e}"))?
+}
+
+fn open_repository(repo_path: &str) -> Result<git2::Repository, String> {
+    git2::Repository::open(repo_path).map_err(|e| format!("failed to open repository: {e}"))
+}
+
+/// The UI picks `operation_id` so it can listen and cancel before the
+/// clone returns.
+#[tauri::command]
+async fn git_clone(
+    app: tauri::AppHandle,
+    operations: tauri::State<'_, Operations>,
+    operation_id: String,
+    url: String,
+    path: String,
+) -> Result<(), String> {
+    run_operation(
+        app,
+        &operations,
+        operation_id,
+        OperationKind::Clone,
+        move |op| operations::clone(op, &url, std::path::Path::new(&path)),
+    )
+    .await
+}
+
+#[tauri::command]
+async fn git_fetch(
+    app: tauri::AppHandle,
+    operations: tauri::State<'_, Operations>,
+    operation_id: String,
+    repo_path: String,
+    remote: Option<String>,
+) -> Result<FetchSummary, String> {
+    run_operation(
+        app,
+        &operations,
+        operation_id,
+        OperationKind::Fetch,
+        move |op| {
+            let repo = open_repository(&repo_path)?;
+            operations::fetch(op, &repo, remote.as_deref().unwrap_or("origin"), &[], false)
+        },
+    )
+    .await
+}
+
+/// Pushes to protected branches are refused unless `force` is set, and
+/// outgoing commits adding likely secrets unless `allow_secrets` is. Either
+/// override records a `protection_overridden` event with `reason`.
+/// `force_with_lease` forces each ref unless it moved on the remote since
+/// the last fetch.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+async fn git_push(
+    app: tauri::AppHandle,
+    operations: tauri::State<'_, Operations>,
+    registry: tauri::State<'_, ServerRegistry>,
+    operation_id: String,
+    repo_path: String,
+    remote: Option<String>,
+    refspecs: Vec<String>,
+    force: Option<bool>,
+    force_with_lease: Option<bool>,
+    allow_secrets: Option<bool>,
+    reason: Option<String>,
+) -> Result<PushSummary, String> {
+    let server = registry.get_or_open(&repo_path)?;
+    run_operation(
+        app,
+        &operations,
+        operation_id,
+        OperationKind::Push,
+        move |op| {
+            let repo = open_repository(&repo_path)?;
+            let rules = protection::Rules::load(&repo)?;
+            protection::enforce(
+                server.store().as_ref(),
+                server.engine(),
+                rules.check_push(&repo, &refspecs),
+                "push",
+                force.unwrap_or(false),
+                reason.as_deref(),
+            )?;
+            let remote = remote.as_deref().unwrap_or("origin");
+            let findings = secrets::scan_outgoing(&repo, remote, &refspecs)?;
+            let branch = protection::current_branch(&repo).unwrap_or_else(|| "HEAD".to_string());
+            protection::enforce(
+                server.store().as_ref(),
+                server.engine(),
+                secrets::violation(&branch, &findings),
+                "push",
+                allow_secrets.unwrap_or(false),
+                reason.as_deref(),
+            )?;
+            operations::push(
+                op,
+                &repo,
+                remote,
+                &refspecs,
+                force_with_lease.unwrap_or(false),
+            )
+        },
+    )
+    .await
+}
+
+#[tauri::command]
+async fn git_rebase(
+    app: tauri::AppHandle,
+    operations: tauri::State<'_, Operations>,
+    operation_id: String,
+    repo_path: String,
+    upstream: String,
+) -> Result<RebaseSummary, String> {
+    run_operation(
+        app,
+        &operations,
+        operation_id,
+        OperationKind::Rebase,
+        move |op| {
+            let repo = open_repository(&repo_path)?;
+            operations::rebase(op, &repo, &upstream)
+        },
+    )
+    .await
+}
+
+/// The todo list for an interactive rebase of the commits after `onto`.
+#[tauri::command]
+async fn rebase_plan_get(repo_path: String, onto: String) -> Result<Plan, String> {
+    with_repo(repo_path, move |repo| rebase_plan::get(repo, &onto)).await
+}
+
+/// Runs a todo list from [`rebase_plan_get`] after the user edited it.
+#[tauri::command]
+async fn rebase_plan_apply(repo_path: String, plan: Plan) -> Result<Applied, String> {
+    with_repo(repo_path, move |repo| {
+        rebase_plan::apply(repo, plan).map_err(String::from)
+    })
+    .await
+}
+
+/// `false` when no operation `id` is running.
+#[tauri::command]
+async fn cancel_operation(
+    operations: tauri::State<'_, Operations>,
+    id: String,
+) -> Result<bool, String> {
+    operations.cancel(&id)
+}
+
+/// Recording in progress between `voice_record_start` and `voice_record_stop`.
+#[derive(Default)]
+struct VoiceRecording {
+    #[cfg(feature = "voice")]
+    recorder: std::sync::Mutex<Option<Recorder>>,
+}
+
+#[derive(serde::Serialize)]
+#[cfg_attr(not(feature = "voice"), allow(dead_code))]
+struct VoiceResult {
+    transcript: String,
+    /// Empty when nothing was said.
+    reply: String,
+    duration_ms: u64,
+}
+
+/// Starts recording from the default microphone in Rust, so the webview
+/// never asks for microphone permission.
+#[cfg(feature = "voice")]
+#[tauri::command]
+async fn voice_record_start(recording: tauri::State<'_, VoiceRecording>) -> Result<(), String> {
+    let mut slot = gitforge::sync::lock(&recording.recorder);
+    if slot.is_some() {
+        return Err("already recording".to_string());
+    }
+    *slot = Some(Recorder::start()?);
+    Ok(())
+}
+
+/// Stops recording, transcribes the clip with `stt_command` (default
+/// `$GITFORGE_STT_COMMAND`), and passes the transcript to the agent.
+#[cfg(feature = "voice")]
+#[tauri::command]
+async fn voice_record_stop(
+    recording: tauri::State<'_, VoiceRecording>,
+    db_path: String,
+    stt_command: Option<String>,
+) -> Result<VoiceResult, String> {
+    let recorder = gitforge::sync::lock(&recording.recorder)
+        .take()
+        .ok_or_else(|| "not recording".to_string())?;
+    let stt = stt(stt_command);
+
+    let (duration_ms, transcript) = tokio::task::spawn_blocking(move || {
+        let clip = recorder.stop()?;
+        let transcript = stt?.transcribe(&clip)?;
+        Ok::<_, String>((clip.duration_ms(), transcript))
+    })
+    .await
+    .map_err(|e| format!("voice recording failed: {e}"))??;
+
+    let reply = if transcript.is_empty() {
+        String::new()
+    } else {
+        BpgtAgent::new(&db_path).process_voice(&transcript).await?
+    };
+    Ok(VoiceResult {
+        transcript,
+        reply,
+        duration_ms,
+    })
+}
+
+#[cfg(not(feature = "voice"))]
+#[tauri::command]
+async fn voice_record_start(_recording: tauri::State<'_, VoiceRecording>) -> Result<(), String> {
+    Err(NO_VOICE.to_string())
+}
+
+#[cfg(not(feature = "voice"))]
+#[tauri::command]
+async fn voice_record_stop(
+    _recording: tauri::State<'_, VoiceRecording>,
+    _db_path: String,
+    _stt_command: Option<String>,
+) -> Result<VoiceResult, String> {
+    Err(NO_VOICE.to_string())
+}
+
+#[cfg(not(feature = "voice"))]
+const NO_VOICE: &str =
+    "gitforge was built without microphone support; rebuild with `--features voice`";
+
+#[cfg(feature = "voice")]
+fn stt(command: Option<String>) -> Result<CommandStt, String> {
+    let command = command
+        .or_else(|| std::env::var("GITFORGE_STT_COMMAND").ok())
+        .ok_or_else(|| {
+            "no STT command configured (pass sttCommand or set GITFORGE_STT_COMMAND)".to_string()
+        })?;
+    CommandStt::parse(&command)
+}
+
 fn main() {
     tauri::Builder::default()
+        .manage(ServerRegistry::default())
+        .manage(EventStreams::default())
+        .manage(BlameCache::default())
+        .manage(RevCache::default())
+        .manage(VoiceRecording::default())
+        .manage(Notifications::default())
+        .manage(Operations::default())
+        .manage(QuickOpen::default())
+        .plugin(tauri_plugin_dialog::init())
+        .plugin(tauri_plugin_notification::init())
+        .on_window_event(|window, event| {
+            if let tauri::WindowEvent::Destroyed = event {
+                let registry = window.state::<ServerRegistry>();
+                if let Ok(Some(repo)) = registry.unbind_window(window.label()) {
+                    let repo = repo.to_string_lossy();
+                    if registry.windows_for(&repo).is_ok_and(|w| w.is_empty()) {
+                        let _ = window.state::<EventStreams>().unsubscribe(&repo);
+                        let _ = window.state::<Notifications>().unwatch(&repo);
+                    }
+                }
+            }
+        })
+        .invoke_handler(tauri::generate_handler![
+            mcp_call,
+            open_repo,
+            close_repo,
+            open_repo_window,
+            pick_repo,
+            recent_repos,
+            window_repo,
+            events_subscribe,
+            events_unsubscribe,
+            read_file_at,
+            diff_file,
+            commit_graph,
+            blame_range,
+            quick_open,
+            stage_file,
+            unstage_file,
+            discard_file,
+            stage_hunk,
+            unstage_hunk,
+            branches_list,
+            branch_switch,
+            conflicts_list,
+            conflict_sides,
+            conflict_resolve,
+            conflict_mark_resolved,
+            merge_finalize,
+            git_clone,
+            git_fetch,
+            git_push,
+            git_rebase,
+            rebase_plan_get,
+            rebase_plan_apply,
+            cancel_operation,
+            voice_process,
+            voice_record_start,
+            voice_record_stop
+        ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
@@ -0,0 +1,717 @@
+use serde::Serialize;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use ant_core::{AntEngine, VersionedSystemEvent, SYSTEM_EVENT_SCHEMA_VERSION};
+
+use crate::mirror::TargetReport;
+use crate::revcache::RevCache;
+use crate::status::{StatusCache, Untracked};
+use crate::store::maintenance::RetentionPolicy;
+use crate::store::MetaStore;
+use crate::sync::lock;
+use crate::worktree::overlap::Overlap;
+
+/// Git config: `false` keeps the scheduler from starting.
+pub const ENABLED_KEY: &str = "gitforge.maintenance.enabled";
+/// Git config: seconds without interactive requests before a task starts.
+pub const QUIET_KEY: &str = "gitforge.maintenance.quietSeconds";
+pub const DEFAULT_QUIET: Duration = Duration::from_secs(30);
+/// How often the scheduler looks for due tasks, and how often a running
+/// `git gc` is checked for interactive requests.
+const TICK: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Task {
+    /// `git gc --auto`: repacks and prunes only when git thinks it's due.
+    Gc,
+    CommitGraph,
+    /// Event retention policy plus store compaction.
+    DbPrune,
+    /// Status snapshot and HEAD history, so the first request after a
+    /// quiet period is fast.
+    WarmCaches,
+    /// Every ref to the [`crate::mirror`] targets.
+    Backup,
+    /// Deletes [`crate::worktree::ephemeral`] worktrees past their TTL.
+    ExpireWorktrees,
+    /// Raises `potential_conflict` for checkouts whose work in progress
+    /// newly touches the same files; see [`crate::worktree::overlap`].
+    ConflictScan,
+}
+
+impl Task {
+    pub const ALL: [Task; 7] = [
+        Task::Gc,
+        Task::CommitGraph,
+        Task::DbPrune,
+        Task::WarmCaches,
+        Task::Backup,
+        Task::ExpireWorktrees,
+        Task::ConflictScan,
+    ];
+
+    pub fn parse(value: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|task| task.as_str() == value)
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Task::Gc => "gc",
+            Task::CommitGraph => "commit_graph",
+            Task::DbPrune => "db_prune",
+            Task::WarmCaches => "warm_caches",
+            Task::Backup => "backup",
+            Task::ExpireWorktrees => "expire_worktrees",
+            Task::ConflictScan => "conflict_scan",
+        }
+    }
+
+    /// Git config key for the interval in minutes; `0` disables the task.
+    pub fn interval_key(self) -> &'static str {
+        match self {
+            Task::Gc => "gitforge.maintenance.gcMinutes",
+            Task::CommitGraph => "gitforge.maintenance.commitGraphMinutes",
+            Task::DbPrune => "gitforge.maintenance.dbPruneMinutes",
+            Task::WarmCaches => "gitforge.maintenance.warmCachesMinutes",
+            Task::Backup => "gitforge.maintenance.backupMinutes",
+            Task::ExpireWorktrees => "gitforge.maintenance.expireWorktreesMinutes",
+            Task::ConflictScan => "gitforge.maintenance.conflictScanMinutes",
+        }
+    }
+
+    fn default_interval(self) -> Duration {
+        let minutes = match self {
+            Task::Gc => 24 * 60,
+            Task::CommitGraph => 60,
+            Task::DbPrune => 24 * 60,
+            Task::WarmCaches => 15,
+            Task::Backup => 60,
+            Task::ExpireWorktrees => 5,
+            Task::ConflictScan => 10,
+        };
+        Duration::from_secs(minutes * 60)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Schedule {
+    pub enabled: bool,
+    pub quiet: Duration,
+    /// Per task, in [`Task::ALL`] order; `None` never runs it on its own.
+    pub intervals: [Option<Duration>; 7],
+}
+
+impl Default for Schedule {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            quiet: DEFAULT_QUIET,
+            intervals: Task::ALL.map(|task| Some(task.default_interval())),
+        }
+    }
+}
+
+impl Schedule {
+    /// Defaults overridden by the repo's `gitforge.maintenance.*` git config.
+    pub fn from_config(repo_path: &str) -> Result<Self, String> {
+        let mut schedule = Self::default();
+        let Ok(config) = git2::Repository::open(repo_path).and_then(|repo| repo.config()) else {
+            return Ok(schedule);
+        };
+
+        match config.get_bool(ENABLED_KEY) {
+            Ok(enabled) => schedule.enabled = enabled,
+            Err(e) if e.code() == git2::ErrorCode::NotFound => {}
+            Err(e) => return Err(format!("invalid {ENABLED_KEY}: {e}")),
+        }
+        if let Some(seconds) = read_number(&config, QUIET_KEY)? {
+            schedule.quiet = Duration::from_secs(seconds);
+        }
+        for (slot, task) in schedule.intervals.iter_mut().zip(Task::ALL) {
+            if let Some(minutes) = read_number(&config, task.interval_key())? {
+                *slot = (minutes > 0).then(|| Duration::from_secs(minutes * 60));
+            }
+        }
+        Ok(schedule)
+    }
+
+    pub fn interval(&self, task: Task) -> Option<Duration> {
+        self.intervals[task as usize]
+    }
+}
+
+fn read_number(config: &git2::Config, key: &str) -> Result<Option<u64>, String> {
+    match config.get_i64(key) {
+        Ok(value) if value >= 0 => Ok(Some(value as u64)),
+        Ok(value) => Err(format!("{key} must not be negative (got {value})")),
+        Err(e) if e.code() == git2::ErrorCode::NotFound => Ok(None),
+        Err(e) => Err(format!("invalid {key}: {e}")),
+    }
+}
+
+/// Interactive requests in flight and when the last one ended. Cloning
+/// shares the tracker.
+#[derive(Clone, Default)]
+pub struct Activity {
+    inner: Arc<Mutex<Busy>>,
+}
+
+#[derive(Default)]
+struct Busy {
+    active: usize,
+    last: Option<Instant>,
+}
+
+impl Activity {
+    /// Marks a request as running until the guard is dropped.
+    pub fn begin(&self) -> ActivityGuard {
+        lock(&self.inner).active += 1;
+        ActivityGuard {
+            inner: Arc::clone(&self.inner),
+        }
+    }
+
+    /// Nothing running, and nothing ran within `quiet`.
+    pub fn is_idle(&self, quiet: Duration) -> bool {
+        let busy = lock(&self.inner);
+        busy.active == 0 && busy.last.is_none_or(|last| last.elapsed() >= quiet)
+    }
+}
+
+pub struct ActivityGuard {
+    inner: Arc<Mutex<Busy>>,
+}
+
+impl Drop for ActivityGuard {
+    fn drop(&mut self) {
+        let mut busy = lock(&self.inner);
+        busy.active -= 1;
+        busy.last = Some(Instant::now());
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "outcome", rename_all = "snake_case")]
+pub enum Outcome {
+    Done {
+        detail: String,
+    },
+    /// Stopped for an interactive request; retried at the next quiet spell.
+    Interrupted,
+    Failed {
+        error: String,
+    },
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TaskStatus {
+    pub task: Task,
+    /// `None` when the task only runs on demand.
+    pub interval_secs: Option<u64>,
+    pub runs: usize,
+    /// Seconds since the unix epoch.
+    pub last_run: Option<u64>,
+    pub last: Option<Outcome>,
+    /// Seconds until the scheduler next considers it.
+    pub due_in_secs: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MaintenanceStatus {
+    pub enabled: bool,
+    pub running: bool,
+    pub quiet_secs: u64,
+    /// Interactive requests are holding tasks back.
+    pub paused: bool,
+    pub tasks: Vec<TaskStatus>,
+}
+
+struct TaskState {
+    due: Option<Instant>,
+    runs: usize,
+    last_run: Option<u64>,
+    last: Option<Outcome>,
+}
+
+struct Shared {
+    repo_path: String,
+    store: Arc<dyn MetaStore>,
+    engine: AntEngine,
+    status: StatusCache,
+    revs: RevCache,
+    activity: Activity,
+    schedule: Schedule,
+    tasks: Mutex<Vec<TaskState>>,
+    /// The latest backup report per target.
+    backups: Mutex<Vec<TargetReport>>,
+    /// What the latest conflict scan found.
+    overlaps: Mutex<Vec<Overlap>>,
+    running: AtomicBool,
+    /// One task at a time, whether scheduled or requested.
+    worker: Mutex<()>,
+}
+
+/// Low-priority housekeeping for one repository: tasks start only after
+/// [`Schedule::quiet`] without interactive requests, and a running `git gc`
+/// is stopped as soon as one arrives. Cloning shares the scheduler.
+#[derive(Clone)]
+pub struct Maintenance {
+    shared: Arc<Shared>,
+}
+
+impl Maintenance {
+    pub fn new(
+        repo_path: &str,
+        store: Arc<dyn MetaStore>,
+        engine: AntEngine,
+        status: StatusCache,
+        revs: RevCache,
+        activity: Activity,
+        schedule: Schedule,
+    ) -> Self {
+        let now = Instant::now();
+        let tasks = Task::ALL
+            .map(|task| TaskState {
+                // Caches are worth warming right away; the rest wait one
+                // interval so starting the daemon doesn't kick off a gc.
+                due: schedule.interval(task).map(|interval| match task {
+                    Task::WarmCaches => now,
+                    _ => now + interval,
+                }),
+                runs: 0,
+                last_run: None,
+                last: None,
+            })
+            .into_iter()
+            .collect();
+        Self {
+            shared: Arc::new(Shared {
+                repo_path: repo_path.to_string(),
+                store,
+                engine,
+                status,
+                revs,
+                activity,
+                schedule,
+                tasks: Mutex::new(tasks),
+                backups: Mutex::new(Vec::new()),
+                overlaps: Mutex::new(Vec::new()),
+                running: AtomicBool::new(false),
+                worker: Mutex::new(()),
+            }),
+        }
+    }
+
+    /// Starts the background thread unless the schedule is disabled or it
+    /// already runs. It stops once the returned handle is dropped.
+    pub fn start(&self) -> Option<Running> {
+        if !self.shared.schedule.enabled || self.shared.running.swap(true, Ordering::SeqCst) {
+            return None;
+        }
+        let shared = Arc::clone(&self.shared);
+        let spawned = std::thread::Builder::new()
+            .name("gitforge-maintenance".to_string())
+            .spawn(move || shared.run_loop());
+        match spawned {
+            Ok(_) => Some(Running {
+                shared: Arc::clone(&self.shared),
+            }),
+            Err(e) => {
+                eprintln!("maintenance for {} disabled: {e}", self.shared.repo_path);
+                self.shared.running.store(false, Ordering::SeqCst);
+                None
+            }
+        }
+    }
+
+    /// Runs `task` now, waiting for any task already running. Requested
+    /// runs aren't interrupted by activity.
+    pub fn run_now(&self, task: Task) -> Outcome {
+        self.shared.run(task, false)
+    }
+
+    /// How the last backup to each target went, in target order.
+    pub fn backups(&self) -> Vec<TargetReport> {
+        lock(&self.shared.backups).clone()
+    }
+
+    /// Checkouts whose work overlapped at the last conflict scan.
+    pub fn overlaps(&self) -> Vec<Overlap> {
+        lock(&self.shared.overlaps).clone()
+    }
+
+    pub fn status(&self) -> MaintenanceStatus {
+        let shared = &self.shared;
+        let now = Instant::now();
+        let tasks = lock(&shared.tasks)
+            .iter()
+            .zip(Task::ALL)
+            .map(|(state, task)| TaskStatus {
+                task,
+                interval_secs: shared.schedule.interval(task).map(|i| i.as_secs()),
+                runs: state.runs,
+                last_run: state.last_run,
+                last: state.last.clone(),
+                due_in_secs: state
+                    .due
+                    .map(|due| due.saturating_duration_since(now).as_secs()),
+            })
+            .collect();
+        MaintenanceStatus {
+            enabled: shared.schedule.enabled,
+            running: shared.running.load(Ordering::SeqCst),
+            quiet_secs: shared.schedule.quiet.as_secs(),
+            paused: !shared.activity.is_idle(shared.schedule.quiet),
+            tasks,
+        }
+    }
+}
+
+/// Stops the scheduler thread when dropped; a task in progress finishes
+/// first.
+pub struct Running {
+    shared: Arc<Shared>,
+}
+
+impl Drop for Running {
+    fn drop(&mut self) {
+        self.shared.running.store(false, Ordering::SeqCst);
+    }
+}
+
+impl Shared {
+    fn run_loop(&self) {
+        while self.running.load(Ordering::SeqCst) {
+            std::thread::sleep(TICK);
+            if !self.idle() {
+                continue;
+            }
+            if let Some(task) = self.next_due() {
+                self.run(task, true);
+            }
+        }
+    }
+
+    fn idle(&self) -> bool {
+        self.activity.is_idle(self.schedule.quiet)
+    }
+
+    fn next_due(&self) -> Option<Task> {
+        let now = Instant::now();
+        lock(&self.tasks)
+            .iter()
+            .zip(Task::ALL)
+            .filter_map(|(state, task)| state.due.filter(|due| *due <= now).map(|due| (due, task)))
+            .min_by_key(|(due, _)| *due)
+            .map(|(_, task)| task)
+    }
+
+    fn run(&self, task: Task, interruptible: bool) -> Outcome {
+        let _worker = lock(&self.worker);
+        let outcome = match self.perform(task, interruptible) {
+            Ok(Some(detail)) => Outcome::Done { detail },
+            Ok(None) => Outcome::Interrupted,
+            Err(error) => Outcome::Failed { error },
+        };
+
+        let now = Instant::now();
+        let mut tasks = lock(&self.tasks);
+        let state = &mut tasks[task as usize];
+        state.runs += 1;
+        state.last_run = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .ok()
+            .map(|age| age.as_secs());
+        state.last = Some(outcome.clone());
+        state.due = match outcome {
+            Outcome::Interrupted => Some(now),
+            _ => self.schedule.interval(task).map(|interval| now + interval),
+        };
+        outcome
+    }
+
+    /// `Ok(None)` when interrupted.
+    fn perform(&self, task: Task, interruptible: bool) -> Result<Option<String>, String> {
+        let repo = git2::Repository::open(&self.repo_path)
+            .map_err(|e| format!("failed to open repository: {e}"))?;
+        match task {
+            Task::Gc => {
+                let interrupt = || interruptible && !self.activity.is_idle(Duration::ZERO);
+                // Without autoDetach=false git daemonizes and can't be stopped.
+                let args = ["-c", "gc.autoDetach=false", "gc", "--auto", "--quiet"];
+                Ok(run_git(repo.path(), &args, &interrupt)?.then(|| "gc --auto".to_string()))
+            }
+            Task::CommitGraph => {
+                let file = crate::revcache::write_commit_graph(&repo)?;
+                Ok(Some(format!("commit-graph is {} bytes", file.bytes)))
+            }
+            Task::DbPrune => {
+                let policy = RetentionPolicy::from_config(&self.repo_path)?;
+                let _lock = crate::db::lock::acquire(
+                    &self.repo_path,
+                    "gitforge maintenance",
+                    crate::db::BUSY_TIMEOUT,
+                )
+                .map_err(|e| e.to_string())?;
+                let report =
+                    crate::store::maintenance::maintain(self.store.as_ref(), &policy, false)?;
+                Ok(Some(format!(
+                    "pruned {} events, reclaimed {} bytes",
+                    report.events_pruned, report.reclaimed_bytes
+                )))
+            }
+            Task::WarmCaches => {
+                let view = self.status.status(&repo, Untracked::default(), false)?;
+                let commits = match repo.head().ok().and_then(|head| head.target()) {
+                    Some(tip) => self
+                        .revs
+                        .history(&repo, tip, crate::graph::DEFAULT_LIMIT)?
+                        .len(),
+                    None => 0,
+                };
+                Ok(Some(format!(
+                    "{} status entries, {commits} commits",
+                    view.entries.len()
+                )))
+            }
+            Task::Backup => {
+                let targets = crate::mirror::targets(&repo)?;
+                if targets.is_empty() {
+                    *lock(&self.backups) = Vec::new();
+                    return Ok(Some("no backup targets configured".to_string()));
+                }
+                let previous = lock(&self.backups).clone();
+                let reports = crate::mirror::backup(&repo, &targets, &previous);
+                let failures: Vec<String> = reports
+                    .iter()
+                    .filter_map(|report| report.error.clone())
+                    .collect();
+                let total = reports.len();
+                *lock(&self.backups) = reports;
+                if failures.is_empty() {
+                    Ok(Some(match total {
+                        1 => "backed up to 1 target".to_string(),
+                        n => format!("backed up to {n} targets"),
+                    }))
+                } else {
+                    Err(failures.join("; "))
+                }
+            }
+            Task::ExpireWorktrees => {
+                let _lock = crate::db::lock::acquire(
+                    &self.repo_path,
+                    "gitforge maintenance",
+                    crate::db::BUSY_TIMEOUT,
+                )
+                .map_err(|e| e.to_string())?;
+                let now = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map(|age| age.as_secs() as i64)
+                    .unwrap_or_default();
+                let expired = crate::worktree::ephemeral::expire(&repo, self.store.as_ref(), now)?;
+                Ok(Some(format!(
+                    "removed {} worktrees, promoted {}",
+                    expired.removed.len(),
+                    expired.promoted.len()
+                )))
+            }
+            Task::ConflictScan => {
+                let overlaps = crate::worktree::overlap::scan(&self.repo_path)?;
+                let previous = lock(&self.overlaps).clone();
+                // Only new or changed overlaps; one that persists was
+                // already announced.
+                let fresh: Vec<&Overlap> = overlaps
+                    .iter()
+                    .filter(|overlap| !previous.contains(overlap))
+                    .collect();
+                for overlap in &fresh {
+                    self.store.append_event(&VersionedSystemEvent {
+                        schema_version: SYSTEM_EVENT_SCHEMA_VERSION,
+                        event: overlap.event(),
+                    })?;
+                    self.engine.potential_conflict(
+                        overlap.worktrees.to_vec(),
+                        overlap.branches.to_vec(),
+                        overlap.paths.clone(),
+                    );
+                }
+                let detail = format!("{} overlapping pairs, {} new", overlaps.len(), fresh.len());
+                *lock(&self.overlaps) = overlaps;
+                Ok(Some(detail))
+            }
+        }
+    }
+}
+
+/// Runs git with output discarded, killing it when `interrupt` says so.
+/// `Ok(false)` when it was killed.
+fn run_git(git_dir: &Path, args: &[&str], interrupt: &dyn Fn() -> bool) -> Result<bool, String> {
+    let mut child = Command::new("git")
+        .arg("--git-dir")
+        .arg(git_dir)
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("failed to run git: {e}"))?;
+    loop {
+        if let Some(status) = child
+            .try_wait()
+            .map_err(|e| format!("failed to wait for git: {e}"))?
+        {
+            if status.success() {
+                return Ok(true);
+            }
+            let mut stderr = String::new();
+            if let Some(mut pipe) = child.stderr.take() {
+                let _ = std::io::Read::read_to_string(&mut pipe, &mut stderr);
+            }
+            return Err(format!("git {} failed: {}", args.join(" "), stderr.trim()));
+        }
+        if interrupt() {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Ok(false);
+        }
+        std::thread::sleep(TICK / 5);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_repo(label: &str) -> crate::test_support::TempDir {
+        let (dir, repo) = crate::test_support::init_repo(&format!("maintenance-{label}"));
+        let sig = git2::Signature::now("Test", "test@example.com").expect("signature");
+        let tree = repo
+            .find_tree(repo.index().unwrap().write_tree().expect("write tree"))
+            .expect("tree");
+        repo.commit(Some("HEAD"), &sig, &sig, "init", &tree, &[])
+            .expect("commit");
+        dir
+    }
+
+    fn maintenance(path: &str, activity: &Activity, schedule: Schedule) -> Maintenance {
+        let store = crate::store::open(path).expect("open store");
+        Maintenance::new(
+            path,
+            store,
+            AntEngine::new(),
+            StatusCache::new(path),
+            RevCache::default(),
+            activity.clone(),
+            schedule,
+        )
+    }
+
+    #[test]
+    fn schedule_reads_git_config_and_zero_disables() {
+        let dir = temp_repo("config");
+        let path = dir.as_str();
+        assert_eq!(
+            Schedule::from_config(path).expect("defaults"),
+            Schedule::default()
+        );
+
+        let mut config = git2::Repository::open(path)
+            .and_then(|repo| repo.config())
+            .expect("config");
+        config.set_i64(QUIET_KEY, 5).expect("quiet");
+        config
+            .set_i64(Task::Gc.interval_key(), 0)
+            .expect("gc interval");
+        config
+            .set_i64(Task::WarmCaches.interval_key(), 2)
+            .expect("warm interval");
+        let schedule = Schedule::from_config(path).expect("schedule");
+
+        assert_eq!(schedule.quiet, Duration::from_secs(5));
+        assert_eq!(schedule.interval(Task::Gc), None);
+        assert_eq!(
+            schedule.interval(Task::WarmCaches),
+            Some(Duration::from_secs(120))
+        );
+        assert_eq!(Task::parse("db_prune"), Some(Task::DbPrune));
+    }
+
+    #[test]
+    fn activity_is_idle_only_after_the_quiet_period() {
+        let activity = Activity::default();
+        assert!(activity.is_idle(Duration::from_secs(60)));
+
+        let guard = activity.begin();
+        assert!(!activity.is_idle(Duration::ZERO));
+        drop(guard);
+        assert!(activity.is_idle(Duration::ZERO));
+        assert!(!activity.is_idle(Duration::from_secs(60)));
+    }
+
+    #[test]
+    fn scheduler_waits_for_a_quiet_spell_then_runs_due_tasks() {
+        let dir = temp_repo("loop");
+        let path = dir.as_str();
+        let activity = Activity::default();
+        let schedule = Schedule {
+            enabled: true,
+            quiet: Duration::ZERO,
+            intervals: [
+                None,
+                None,
+                None,
+                Some(Duration::from_secs(3600)),
+                None,
+                None,
+                None,
+            ],
+        };
+        let maintenance = maintenance(path, &activity, schedule);
+
+        let busy = activity.begin();
+        let running = maintenance.start().expect("started");
+        assert!(maintenance.start().is_none(), "only one scheduler thread");
+        std::thread::sleep(TICK * 3);
+        assert!(maintenance.status().paused);
+        assert_eq!(
+            maintenance.status().tasks[Task::WarmCaches as usize].runs,
+            0
+        );
+
+        drop(busy);
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while maintenance.status().tasks[Task::WarmCaches as usize].runs == 0 {
+            assert!(Instant::now() < deadline, "warm-up never ran");
+            std::thread::sleep(TICK / 5);
+        }
+        let status = maintenance.status();
+        let warm = &status.tasks[Task::WarmCaches as usize];
+        assert!(matches!(warm.last, Some(Outcome::Done { .. })), "{warm:?}");
+        assert!(warm.due_in_secs.is_some_and(|secs| secs > 3000));
+        assert_eq!(status.tasks[Task::DbPrune as usize].runs, 0);
+
+        drop(running);
+        assert!(!maintenance.status().running);
+    }
+
+    #[test]
+    fn requested_runs_report_their_outcome() {
+        let dir = temp_repo("now");
+        let path = dir.as_str();
+        let maintenance = maintenance(path, &Activity::default(), Schedule::default());
+
+        let outcome = maintenance.run_now(Task::DbPrune);
+        assert!(
+            matches!(&outcome, Outcome::Done { detail } if detail.starts_with("pruned 0 events")),
+            "{outcome:?}"
+        );
+        let status = maintenance.status();
+        assert_eq!(status.tasks[Task::DbPrune as usize].runs, 1);
+        assert!(status.tasks[Task::DbPrune as usize].last_run.is_some());
+    }
+}
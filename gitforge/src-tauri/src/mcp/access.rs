@@ -0,0 +1,432 @@
+use crate::auth::constant_time_eq;
+use std::fmt;
+
+/// Environment variable `gitforge mcp-serve` reads client tokens from:
+/// whitespace-separated `token` or `token=scope,scope` entries.
+pub const TOKENS_ENV: &str = "GITFORGE_MCP_TOKENS";
+
+/// What a scope lets a client do in its area; each level includes the
+/// ones below it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Read,
+    Write,
+    Admin,
+}
+
+/// The part of GitForge a tool works on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Area {
+    /// Working tree, history and search.
+    Status,
+    /// The index and new commits.
+    Commit,
+    /// Branches and other refs.
+    Branch,
+    Worktree,
+    Pr,
+    Issue,
+    /// Agent goals and their logs.
+    Goal,
+    /// Files attached to goals and PRs, and the MCP resources serving them.
+    Artifact,
+    /// Commands declared in `.gitforge/tasks.toml`.
+    Task,
+    /// Settings and projects.
+    Settings,
+    /// Housekeeping, backups and bundles.
+    Maintenance,
+    Release,
+}
+
+const AREAS: [(&str, Area); 12] = [
+    ("status", Area::Status),
+    ("commit", Area::Commit),
+    ("branch", Area::Branch),
+    ("worktree", Area::Worktree),
+    ("pr", Area::Pr),
+    ("issue", Area::Issue),
+    ("goal", Area::Goal),
+    ("artifact", Area::Artifact),
+    ("task", Area::Task),
+    ("settings", Area::Settings),
+    ("maintenance", Area::Maintenance),
+    ("release", Area::Release),
+];
+
+/// `area:level`, e.g. `commit:write`, or `*:level` for every area.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Scope {
+    /// `None` is `*`.
+    pub area: Option<Area>,
+    pub level: Level,
+}
+
+impl Scope {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let invalid =
+            || format!("invalid scope '{text}' (expected area:read, area:write or area:admin)");
+        let (area, level) = text.split_once(':').ok_or_else(invalid)?;
+        let area = match area {
+            "*" => None,
+            name => Some(
+                AREAS
+                    .iter()
+                    .find(|(known, _)| *known == name)
+                    .map(|(_, area)| *area)
+                    .ok_or_else(|| format!("unknown scope area '{name}' in '{text}'"))?,
+            ),
+        };
+        let level = match level {
+            "read" => Level::Read,
+            "write" => Level::Write,
+            "admin" => Level::Admin,
+            _ => return Err(invalid()),
+        };
+        Ok(Self { area, level })
+    }
+
+    /// Whether holding this scope is enough for `needed`.
+    fn covers(self, needed: Scope) -> bool {
+        self.level >= needed.level && (self.area.is_none() || self.area == needed.area)
+    }
+}
+
+impl fmt::Display for Scope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let area = match self.area {
+            Some(area) => AREAS
+                .iter()
+                .find(|(_, known)| *known == area)
+                .map_or("?", |(name, _)| name),
+            None => "*",
+        };
+        let level = match self.level {
+            Level::Read => "read",
+            Level::Write => "write",
+            Level::Admin => "admin",
+        };
+        write!(f, "{area}:{level}")
+    }
+}
+
+/// The scope calling `method` takes. Tools without an entry, including
+/// any added without updating this list, need `*:admin`.
+pub fn required(method: &str) -> Scope {
+    use Area::*;
+    use Level::*;
+    let (area, level) = match method {
+        "git_status" | "git_archive" | "git_compare" | "git_diff" | "git_log" | "git_blame"
+        | "git_search_commits" | "git_stash_list" | "commit_graph" | "rebase_plan_get"
+        | "trailer_query" | "search" | "events_since" | "activity_feed" | "repo_stats"
+        | "workspace_status" | "refs_watch" | "refs_unwatch" => (Status, Read),
+        "git_commit"
+        | "stage_file"
+        | "git_add"
+        | "git_unstage"
+        | "unstage_file"
+        | "discard_file"
+        | "stage_hunk"
+        | "unstage_hunk"
+        | "apply_mbox"
+        | "checks_run"
+        | "git_autosquash"
+        | "rebase_autosquash"
+        | "rebase_plan_apply"
+        | "git_rebase"
+        | "git_rebase_continue"
+        | "git_rebase_abort"
+        | "git_cherry_pick"
+        | "git_revert"
+        | "git_reset"
+        | "git_stash_save"
+        | "git_stash_apply"
+        | "git_stash_pop"
+        | "git_stash_drop" => (Commit, Write),
+        "branch_name_suggest" | "git_branch_list" => (Branch, Read),
+        "git_bundle_import" | "git_branch_create" | "git_branch_delete" | "git_checkout"
+        | "git_fetch" | "git_push" | "git_pull" => (Branch, Write),
+        "git_worktree_list" | "git_worktree_status" | "worktree_diff" | "conflict_warnings" => {
+            (Worktree, Read)
+        }
+        "git_worktree_create"
+        | "git_worktree_sync"
+        | "worktree_create_ephemeral"
+        | "repo_scaffold"
+        | "workspace_sync" => (Worktree, Write),
+        "prs_list" | "workspace_prs" => (Pr, Read),
+        "git_create_pr"
+        | "pr_comment_add"
+        | "pr_apply_suggestion"
+        | "pr_send_email"
+        | "pr_checks"
+        | "ci_trigger"
+        | "run_tests" => (Pr, Write),
+        "pr_set_state" => (Pr, Admin),
+        "history_purge_path" => (Commit, Admin),
+        "issue_list" => (Issue, Read),
+        "issue_create" | "issue_update" | "issue_close" => (Issue, Write),
+        "goal_logs_get" => (Goal, Read),
+        "goal_log_append" => (Goal, Write),
+        "artifact_list" | "resources/list" | "resources/read" => (Artifact, Read),
+        "artifact_attach" => (Artifact, Write),
+        "task_list" => (Task, Read),
+        "task_run" => (Task, Write),
+        "settings_get" | "project_list" | "project_context" | "workspace_list" => (Settings, Read),
+        "settings_set" | "project_save" | "project_remove" | "workspace_save"
+        | "workspace_remove" => (Settings, Write),
+        "maintenance_status" | "backup_status" => (Maintenance, Read),
+        "maintenance_run" | "backup_now" | "commit_graph_write" | "git_bundle_create"
+        | "stale_report" => (Maintenance, Write),
+        "release_create" => (Release, Write),
+        _ => {
+            return Scope {
+                area: None,
+                level: Admin,
+            }
+        }
+    };
+    Scope {
+        area: Some(area),
+        level,
+    }
+}
+
+/// Whether `method` may change the repository or GitForge's metadata.
+pub fn mutates(method: &str) -> bool {
+    required(method).level > Level::Read
+}
+
+/// The scopes a client holds.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Access {
+    scopes: Vec<Scope>,
+}
+
+impl Default for Access {
+    fn default() -> Self {
+        Self::read_write()
+    }
+}
+
+impl Access {
+    /// Every tool: `*:admin`.
+    pub fn read_write() -> Self {
+        Self {
+            scopes: vec![Scope {
+                area: None,
+                level: Level::Admin,
+            }],
+        }
+    }
+
+    /// Tools that only read: `*:read`.
+    pub fn read_only() -> Self {
+        Self {
+            scopes: vec![Scope {
+                area: None,
+                level: Level::Read,
+            }],
+        }
+    }
+
+    /// `read-write`, `read-only`, or comma-separated scopes.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        match text {
+            "read-write" => Ok(Self::read_write()),
+            "read-only" => Ok(Self::read_only()),
+            scopes => Ok(Self {
+                scopes: scopes
+                    .split(',')
+                    .map(Scope::parse)
+                    .collect::<Result<_, _>>()?,
+            }),
+        }
+    }
+
+    /// These scopes with every level lowered to read.
+    pub fn capped(&self) -> Self {
+        Self {
+            scopes: self
+                .scopes
+                .iter()
+                .map(|scope| Scope {
+                    level: Level::Read,
+                    ..*scope
+                })
+                .collect(),
+        }
+    }
+
+    pub fn scopes(&self) -> &[Scope] {
+        &self.scopes
+    }
+
+    pub fn allows(&self, method: &str) -> bool {
+        self.refusal(method).is_none()
+    }
+
+    /// Why `method` is refused, or `None` when a scope covers it.
+    /// `tools/list` is always allowed; it only lists what else is.
+    pub fn refusal(&self, method: &str) -> Option<String> {
+        let needed = required(method);
+        if method == "tools/list" || self.scopes.iter().any(|scope| scope.covers(needed)) {
+            return None;
+        }
+        let held: Vec<String> = self.scopes.iter().map(Scope::to_string).collect();
+        let read_only = self.scopes.iter().all(|scope| scope.level == Level::Read);
+        Some(if read_only && needed.level > Level::Read {
+            format!("'{method}' can change the repository, and this client is read-only")
+        } else {
+            format!(
+                "'{method}' needs the {needed} scope; this client has {}",
+                held.join(", ")
+            )
+        })
+    }
+}
+
+/// The tokens a server accepts and what each allows. Empty means clients
+/// connect without one.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Tokens(Vec<(String, Access)>);
+
+impl Tokens {
+    /// `token` or `token=scopes` entries separated by whitespace, where
+    /// scopes are those `Access::parse` takes; a bare token is read-write.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut tokens = Vec::new();
+        for entry in text.split_whitespace() {
+            let (token, access) = match entry.split_once('=') {
+                Some((token, scopes)) => (token, Access::parse(scopes)?),
+                None => (entry, Access::read_write()),
+            };
+            if token.is_empty() {
+                return Err(format!("'{entry}' has no token"));
+            }
+            tokens.push((token.to_string(), access));
+        }
+        Ok(Self(tokens))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// What `presented` allows, or `None` when it isn't one of the tokens.
+    pub fn access(&self, presented: &str) -> Option<Access> {
+        self.0
+            .iter()
+            .find(|(token, _)| constant_time_eq(token.as_bytes(), presented.as_bytes()))
+            .map(|(_, access)| access.clone())
+    }
+}
+
+/// How a server is exposed: read-only for every client, or per token.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Options {
+    pub read_only: bool,
+    pub tokens: Tokens,
+}
+
+impl Options {
+    /// What a client presenting `token` may do, or `None` when tokens are
+    /// required and it has none or a wrong one. `--read-only` caps every
+    /// token.
+    pub fn access(&self, token: Option<&str>) -> Option<Access> {
+        let access = if self.tokens.is_empty() {
+            Access::read_write()
+        } else {
+            self.tokens.access(token?)?
+        };
+        Some(if self.read_only {
+            access.capped()
+        } else {
+            access
+        })
+    }
+
+    /// The desktop app's access: it needs no token, so only `read_only`
+    /// limits it.
+    pub fn local_access(&self) -> Access {
+        if self.read_only {
+            Access::read_only()
+        } else {
+            Access::read_write()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokens_carry_permissions_and_read_only_caps_them() {
+        let tokens = Tokens::parse("w1 r1=read-only\n w2=read-write").expect("tokens");
+        let options = Options {
+            read_only: false,
+            tokens: tokens.clone(),
+        };
+        assert_eq!(options.access(Some("w1")), Some(Access::read_write()));
+        assert_eq!(options.access(Some("w2")), Some(Access::read_write()));
+        assert_eq!(options.access(Some("r1")), Some(Access::read_only()));
+        assert_eq!(options.access(Some("nope")), None);
+        assert_eq!(options.access(None), None);
+
+        let capped = Options {
+            read_only: true,
+            tokens,
+        };
+        assert_eq!(capped.access(Some("w1")), Some(Access::read_only()));
+        assert_eq!(Options::default().access(None), Some(Access::read_write()));
+        assert!(Tokens::parse("t=admin").is_err());
+        assert!(Tokens::parse("=read-only").is_err());
+
+        let read_only = Access::read_only();
+        assert!(read_only.allows("git_status"));
+        assert!(!read_only.allows("git_commit"));
+        assert!(!read_only.allows("some_future_tool"));
+        assert!(Access::read_write().allows("git_commit"));
+    }
+
+    #[test]
+    fn scopes_cover_their_area_up_to_their_level() {
+        let access = Access::parse("status:read,commit:write,pr:admin").expect("scopes");
+        for method in [
+            "git_status",
+            "git_commit",
+            "stage_hunk",
+            "prs_list",
+            "pr_set_state",
+        ] {
+            assert!(access.allows(method), "{method}");
+        }
+        for method in ["issue_create", "git_worktree_create", "settings_set"] {
+            assert!(!access.allows(method), "{method}");
+        }
+        assert_eq!(
+            access.refusal("issue_list").as_deref(),
+            Some("'issue_list' needs the issue:read scope; this client has status:read, commit:write, pr:admin")
+        );
+        assert!(access.allows("tools/list"));
+        assert!(!access.allows("some_future_tool"));
+
+        let capped = access.capped();
+        assert!(capped.allows("prs_list"));
+        assert!(!capped.allows("git_commit"));
+        assert_eq!(
+            capped.refusal("pr_set_state").as_deref(),
+            Some("'pr_set_state' can change the repository, and this client is read-only")
+        );
+
+        assert_eq!(
+            Scope::parse("*:write").map(|s| s.to_string()).as_deref(),
+            Ok("*:write")
+        );
+        assert!(Scope::parse("commit").is_err());
+        assert!(Scope::parse("deploy:write").is_err());
+        assert!(Scope::parse("commit:owner").is_err());
+        assert!(mutates("git_commit") && !mutates("git_status"));
+    }
+}
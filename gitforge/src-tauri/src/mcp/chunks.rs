@@ -0,0 +1,145 @@
+use tokio::sync::mpsc;
+
+use crate::mcp::server::McpError;
+
+/// Notification method carrying one piece of a streamed result.
+pub const CHUNK_METHOD: &str = "result/chunk";
+/// Raw bytes per chunk for binary results, before base64.
+pub const CHUNK_BYTES: usize = 64 * 1024;
+/// Chunks queued before a slow client makes the tool wait.
+const QUEUE_DEPTH: usize = 16;
+
+/// Where a tool's result pieces go. Over WebSocket with `"stream": true`
+/// each piece is sent as a [`CHUNK_METHOD`] notification as soon as it is
+/// produced; otherwise pieces are collected and returned in the final
+/// result's `chunks` array.
+pub struct Chunks {
+    sink: Sink,
+    sent: usize,
+}
+
+enum Sink {
+    Buffered(Vec<serde_json::Value>),
+    Streaming(mpsc::Sender<serde_json::Value>),
+}
+
+impl Chunks {
+    pub fn buffered() -> Self {
+        Self {
+            sink: Sink::Buffered(Vec::new()),
+            sent: 0,
+        }
+    }
+
+    pub fn streaming() -> (Self, mpsc::Receiver<serde_json::Value>) {
+        let (tx, rx) = mpsc::channel(QUEUE_DEPTH);
+        let chunks = Self {
+            sink: Sink::Streaming(tx),
+            sent: 0,
+        };
+        (chunks, rx)
+    }
+
+    /// Blocks while the queue is full; call from the blocking pool only.
+    pub fn push(&mut self, chunk: serde_json::Value) -> Result<(), McpError> {
+        match &mut self.sink {
+            Sink::Buffered(items) => items.push(chunk),
+            Sink::Streaming(tx) => tx.blocking_send(chunk).map_err(|_| McpError {
+                code: -32603,
+                message: "client stopped reading the stream".to_string(),
+                data: None,
+            })?,
+        }
+        self.sent += 1;
+        Ok(())
+    }
+
+    /// Base64-encodes `bytes` as `{"data": ...}`.
+    pub fn push_bytes(&mut self, bytes: &[u8]) -> Result<(), McpError> {
+        self.push(serde_json::json!({ "data": base64(bytes) }))
+    }
+
+    /// Completes `result` (an object) with the buffered chunks, or with how
+    /// many were streamed.
+    pub fn finish(self, mut result: serde_json::Value) -> serde_json::Value {
+        let chunks = match self.sink {
+            Sink::Buffered(items) => serde_json::Value::Array(items),
+            Sink::Streaming(_) => serde_json::json!(self.sent),
+        };
+        if let Some(object) = result.as_object_mut() {
+            object.insert("chunks".to_string(), chunks);
+        }
+        result
+    }
+}
+
+/// The JSON-RPC notification for chunk `seq` of request `id`.
+pub fn frame(id: &serde_json::Value, seq: usize, data: serde_json::Value) -> serde_json::Value {
+    serde_json::json!({
+        "jsonrpc": "2.0",
+        "method": CHUNK_METHOD,
+        "params": { "id": id, "seq": seq, "data": data }
+    })
+}
+
+/// Standard base64 with padding.
+pub fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for group in bytes.chunks(3) {
+        let n = group
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &b)| n | u32::from(b) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= group.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn base64_matches_rfc_vectors() {
+        let cases = [
+            ("", ""),
+            ("f", "Zg=="),
+            ("fo", "Zm8="),
+            ("foo", "Zm9v"),
+            ("foob", "Zm9vYg=="),
+            ("fooba", "Zm9vYmE="),
+            ("foobar", "Zm9vYmFy"),
+        ];
+        for (input, encoded) in cases {
+            assert_eq!(base64(input.as_bytes()), encoded);
+        }
+    }
+
+    #[test]
+    fn buffered_chunks_land_in_the_result_and_streamed_ones_are_counted() {
+        let mut buffered = Chunks::buffered();
+        buffered.push_bytes(b"foo").expect("push");
+        let result = buffered.finish(serde_json::json!({ "files": 1 }));
+        assert_eq!(result["chunks"], serde_json::json!([{ "data": "Zm9v" }]));
+
+        let (mut streaming, mut rx) = Chunks::streaming();
+        std::thread::spawn(move || {
+            streaming.push(serde_json::json!(1)).expect("push");
+            streaming.push(serde_json::json!(2)).expect("push");
+            streaming.finish(serde_json::json!({}))
+        })
+        .join()
+        .map(|result| assert_eq!(result["chunks"], 2))
+        .expect("producer");
+        assert_eq!(rx.blocking_recv(), Some(serde_json::json!(1)));
+        assert_eq!(rx.blocking_recv(), Some(serde_json::json!(2)));
+        assert_eq!(rx.blocking_recv(), None);
+    }
+}
@@ -0,0 +1,147 @@
+//! Queues the tools that write the index, so two calls can't interleave
+//! their read-modify-write of `.git/index`. Waiters are served in arrival
+//! order and give up after a timeout instead of racing.
+
+use std::fmt;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::OwnedMutexGuard;
+
+/// How long a call waits for the index before failing.
+pub const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Tools that write the index.
+pub fn writes_index(method: &str) -> bool {
+    matches!(
+        method,
+        "git_commit"
+            | "stage_file"
+            | "git_add"
+            | "git_unstage"
+            | "unstage_file"
+            | "discard_file"
+            | "stage_hunk"
+            | "unstage_hunk"
+            | "apply_mbox"
+            | "git_checkout"
+            | "git_autosquash"
+            | "rebase_autosquash"
+            | "rebase_plan_apply"
+            | "history_purge_path"
+            | "git_stash_save"
+            | "git_stash_apply"
+            | "git_stash_pop"
+            | "git_pull"
+            | "git_rebase"
+            | "git_rebase_continue"
+            | "git_rebase_abort"
+            | "git_cherry_pick"
+            | "git_revert"
+            | "git_reset"
+            | "pr_apply_suggestion"
+    )
+}
+
+#[derive(Clone)]
+pub struct IndexLock {
+    lock: Arc<tokio::sync::Mutex<()>>,
+    /// The tool holding the lock, for the timeout error.
+    holder: Arc<Mutex<String>>,
+    waiting: Arc<AtomicUsize>,
+    timeout: Duration,
+}
+
+impl Default for IndexLock {
+    fn default() -> Self {
+        Self::with_timeout(TIMEOUT)
+    }
+}
+
+/// Why `acquire` gave up.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Busy {
+    /// The tool holding the index.
+    pub holder: String,
+    /// Calls that were queued ahead of this one.
+    pub queued: usize,
+    pub method: String,
+    pub timeout: Duration,
+}
+
+impl fmt::Display for Busy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "the index is busy with {} and {} more queued; {} gave up after {}s",
+            self.holder,
+            self.queued,
+            self.method,
+            self.timeout.as_secs_f32()
+        )
+    }
+}
+
+/// Held while a tool writes the index.
+pub struct IndexGuard {
+    _guard: OwnedMutexGuard<()>,
+}
+
+impl IndexLock {
+    pub fn with_timeout(timeout: Duration) -> Self {
+        Self {
+            lock: Arc::new(tokio::sync::Mutex::new(())),
+            holder: Arc::new(Mutex::new(String::new())),
+            waiting: Arc::new(AtomicUsize::new(0)),
+            timeout,
+        }
+    }
+
+    /// Waits in line for the index on behalf of `method`.
+    pub async fn acquire(&self, method: &str) -> Result<IndexGuard, Busy> {
+        let ahead = self.waiting.fetch_add(1, Ordering::SeqCst);
+        let acquired =
+            tokio::time::timeout(self.timeout, Arc::clone(&self.lock).lock_owned()).await;
+        self.waiting.fetch_sub(1, Ordering::SeqCst);
+        let mut holder = crate::sync::lock(&self.holder);
+        match acquired {
+            Ok(guard) => {
+                *holder = method.to_string();
+                Ok(IndexGuard { _guard: guard })
+            }
+            Err(_) => Err(Busy {
+                holder: holder.clone(),
+                queued: ahead,
+                method: method.to_string(),
+                timeout: self.timeout,
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn waiters_queue_and_time_out_behind_a_slow_writer() {
+        let lock = IndexLock::with_timeout(Duration::from_millis(50));
+        let held = lock.acquire("git_commit").await.expect("first");
+
+        let err = lock.acquire("stage_file").await.err().expect("times out");
+        assert_eq!(err.holder, "git_commit");
+        assert_eq!(
+            err.to_string(),
+            "the index is busy with git_commit and 0 more queued; stage_file gave up after 0.05s"
+        );
+
+        let next = {
+            let lock = lock.clone();
+            tokio::spawn(async move { lock.acquire("stage_hunk").await.is_ok() })
+        };
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        drop(held);
+        assert!(next.await.expect("join"));
+        assert!(writes_index("git_commit") && !writes_index("git_status"));
+    }
+}
@@ -0,0 +1,294 @@
+//! Journals tools that take several steps, so one that fails halfway, or
+//! dies with the process, doesn't leave a branch without its worktree or a
+//! record without either. Each step is written down before it runs, and
+//! `Operation::finish` deletes the entry once the last one succeeded. An
+//! operation dropped unfinished undoes its steps newest first, and
+//! `repair_pending_operations` does the same at startup for calls that
+//! died before they could.
+//!
+//! Journaled calls hold the metadata write lock from start to finish and
+//! repair takes it too, so repair only ever sees entries of dead calls.
+
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::store::MetaStore;
+
+/// One step of a journaled call, with what undoing it needs. Undoing is
+/// safe to repeat and to run for a step that never got to happen.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "step", rename_all = "snake_case")]
+pub enum Step {
+    /// A new branch at `target`; left alone once it points elsewhere.
+    CreateBranch { name: String, target: String },
+    /// A linked worktree checked out at `path`.
+    AddWorktree { name: String, path: String },
+    /// The worktree's row in the metadata store.
+    RegisterWorktree { name: String },
+}
+
+/// A journaled call: its tool and the steps it started.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Entry {
+    pub id: String,
+    pub tool: String,
+    pub started_at: String,
+    pub steps: Vec<Step>,
+}
+
+/// What startup repair did with an unfinished call.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Repaired {
+    pub entry: Entry,
+    /// Why the entry could not be rolled back; it stays for the next try.
+    pub error: Option<String>,
+}
+
+/// A journaled call in progress.
+pub struct Operation<'a> {
+    repo: &'a git2::Repository,
+    store: &'a dyn MetaStore,
+    path: PathBuf,
+    entry: Entry,
+    finished: bool,
+}
+
+/// Starts journaling a call to `tool`.
+pub fn begin<'a>(
+    repo: &'a git2::Repository,
+    store: &'a dyn MetaStore,
+    tool: &str,
+) -> Result<Operation<'a>, String> {
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or_default();
+    let id = format!("{nanos}-{}", std::process::id());
+    let operation = Operation {
+        repo,
+        store,
+        path: dir(repo).join(format!("{id}.json")),
+        entry: Entry {
+            id,
+            tool: tool.to_string(),
+            started_at: crate::store::now_timestamp(),
+            steps: Vec::new(),
+        },
+        finished: false,
+    };
+    operation.save()?;
+    Ok(operation)
+}
+
+impl Operation<'_> {
+    /// Records `step` before the caller takes it.
+    pub fn step(&mut self, step: Step) -> Result<(), String> {
+        self.entry.steps.push(step);
+        self.save()
+    }
+
+    /// Commits the call: every step stays.
+    pub fn finish(mut self) -> Result<(), String> {
+        self.finished = true;
+        remove(&self.path)
+    }
+
+    fn save(&self) -> Result<(), String> {
+        write(&self.path, &self.entry)
+    }
+}
+
+impl Drop for Operation<'_> {
+    fn drop(&mut self) {
+        if self.finished {
+            return;
+        }
+        let undone =
+            roll_back(self.repo, self.store, &self.entry.steps).and_then(|()| remove(&self.path));
+        if let Err(e) = undone {
+            eprintln!(
+                "failed to roll back {} ({}): {e}; it will be repaired at the next start",
+                self.entry.tool, self.entry.id
+            );
+        }
+    }
+}
+
+/// Whether `repo`'s journal holds any entries.
+pub fn has_pending(repo: &git2::Repository) -> bool {
+    std::fs::read_dir(dir(repo)).is_ok_and(|listing| {
+        listing
+            .filter_map(|entry| entry.ok())
+            .any(|entry| entry.path().extension().is_some_and(|ext| ext == "json"))
+    })
+}
+
+/// Rolls back the calls in `repo`'s journal that never finished, oldest
+/// first. The caller holds the metadata write lock.
+pub fn repair_pending_operations(
+    repo: &git2::Repository,
+    store: &dyn MetaStore,
+) -> Result<Vec<Repaired>, String> {
+    let dir = dir(repo);
+    let listing = match std::fs::read_dir(&dir) {
+        Ok(listing) => listing,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(format!("failed to read {}: {e}", dir.display())),
+    };
+    let mut paths: Vec<PathBuf> = listing
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .collect();
+    // Ids start with the time the call began.
+    paths.sort();
+
+    let mut repaired = Vec::new();
+    for path in paths {
+        let text = std::fs::read_to_string(&path)
+            .map_err(|e| format!("failed to read {}: {e}", path.display()))?;
+        let entry: Entry = serde_json::from_str(&text)
+            .map_err(|e| format!("failed to parse {}: {e}", path.display()))?;
+        let error = roll_back(repo, store, &entry.steps)
+            .and_then(|()| remove(&path))
+            .err();
+        repaired.push(Repaired { entry, error });
+    }
+    Ok(repaired)
+}
+
+/// Undoes `steps` newest first, going on past a failure so one stuck step
+/// doesn't keep the others in place.
+fn roll_back(repo: &git2::Repository, store: &dyn MetaStore, steps: &[Step]) -> Result<(), String> {
+    let errors: Vec<String> = steps
+        .iter()
+        .rev()
+        .filter_map(|step| undo(repo, store, step).err())
+        .collect();
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors.join("; "))
+    }
+}
+
+fn undo(repo: &git2::Repository, store: &dyn MetaStore, step: &Step) -> Result<(), String> {
+    match step {
+        Step::CreateBranch { name, target } => {
+            let Ok(mut branch) = repo.find_branch(name, git2::BranchType::Local) else {
+                return Ok(());
+            };
+            if branch.get().target().map(|oid| oid.to_string()).as_ref() != Some(target) {
+                return Ok(());
+            }
+            branch
+                .delete()
+                .map_err(|e| format!("failed to delete branch {name}: {e}"))
+        }
+        Step::AddWorktree { name, path } => {
+            if let Ok(worktree) = repo.find_worktree(name) {
+                let mut options = git2::WorktreePruneOptions::new();
+                options.valid(true).working_tree(true);
+                worktree
+                    .prune(Some(&mut options))
+                    .map_err(|e| format!("failed to remove worktree {name}: {e}"))?;
+            }
+            // libgit2 refuses a directory that exists, so an empty one was
+            // left by the failed add.
+            let _ = std::fs::remove_dir(path);
+            Ok(())
+        }
+        Step::RegisterWorktree { name } => store.remove_worktree(name).map(|_| ()),
+    }
+}
+
+fn dir(repo: &git2::Repository) -> PathBuf {
+    crate::db::location::common_dir(repo)
+        .join("gitforge")
+        .join("journal")
+}
+
+/// Writes `entry` through a temporary file, so a crash mid-write leaves
+/// the previous version.
+fn write(path: &Path, entry: &Entry) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| format!("failed to create {}: {e}", parent.display()))?;
+    }
+    let text = serde_json::to_string_pretty(entry)
+        .map_err(|e| format!("failed to encode journal entry: {e}"))?;
+    let temp = path.with_extension("tmp");
+    std::fs::write(&temp, text)
+        .and_then(|()| std::fs::rename(&temp, path))
+        .map_err(|e| format!("failed to write {}: {e}", path.display()))
+}
+
+fn remove(path: &Path) -> Result<(), String> {
+    match std::fs::remove_file(path) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(format!("failed to remove {}: {e}", path.display())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unfinished_operations_roll_back_now_or_at_repair() {
+        let (dir, repo) = crate::test_support::init_repo("journal");
+        let signature = git2::Signature::now("GitForge Test", "test@gitforge.dev").expect("sig");
+        let tree = repo
+            .index()
+            .and_then(|mut index| index.write_tree())
+            .and_then(|id| repo.find_tree(id))
+            .expect("tree");
+        let head = repo
+            .commit(Some("HEAD"), &signature, &signature, "initial", &tree, &[])
+            .expect("commit");
+        let store = crate::store::open(&dir.to_string_lossy()).expect("store");
+        let branch_step = |name: &str| Step::CreateBranch {
+            name: name.to_string(),
+            target: head.to_string(),
+        };
+        let commit = repo.find_commit(head).expect("head");
+
+        {
+            let mut op = begin(&repo, store.as_ref(), "git_worktree_create").expect("begin");
+            op.step(branch_step("failed")).expect("step");
+            repo.branch("failed", &commit, false).expect("branch");
+            op.step(Step::RegisterWorktree {
+                name: "failed".to_string(),
+            })
+            .expect("step");
+            store
+                .upsert_worktree("failed", "/nowhere", "failed")
+                .expect("row");
+            // Dropped here, as a `?` between steps would.
+        }
+        assert!(repo.find_branch("failed", git2::BranchType::Local).is_err());
+        assert!(store.list_worktrees().expect("rows").is_empty());
+
+        let mut op = begin(&repo, store.as_ref(), "git_worktree_create").expect("begin");
+        op.step(branch_step("done")).expect("step");
+        repo.branch("done", &commit, false).expect("branch");
+        op.finish().expect("finish");
+        assert!(repo.find_branch("done", git2::BranchType::Local).is_ok());
+
+        // A call that died: its entry is on disk with nobody to drop it.
+        let mut op = begin(&repo, store.as_ref(), "git_worktree_create").expect("begin");
+        op.step(branch_step("crashed")).expect("step");
+        repo.branch("crashed", &commit, false).expect("branch");
+        std::mem::forget(op);
+        let repaired = repair_pending_operations(&repo, store.as_ref()).expect("repair");
+        assert_eq!(repaired.len(), 1);
+        assert_eq!(repaired[0].error, None);
+        assert_eq!(repaired[0].entry.steps, [branch_step("crashed")]);
+        assert!(repo
+            .find_branch("crashed", git2::BranchType::Local)
+            .is_err());
+        assert!(repair_pending_operations(&repo, store.as_ref())
+            .expect("repair")
+            .is_empty());
+    }
+}
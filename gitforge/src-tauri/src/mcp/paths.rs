@@ -0,0 +1,162 @@
+use std::path::{Component, Path, PathBuf};
+
+/// Directories tools may read or write files under, one per value;
+/// relative ones are taken from the working tree. Without any, only the
+/// working tree itself is allowed.
+pub const ALLOWED_ROOT_KEY: &str = "gitforge.mcp.allowedRoot";
+
+/// Where a server's tools may touch the filesystem.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Roots {
+    /// Relative tool paths are resolved from here: the working tree.
+    base: PathBuf,
+    roots: Vec<PathBuf>,
+}
+
+impl Roots {
+    pub fn load(repo: &git2::Repository) -> Result<Self, String> {
+        let base = repo.workdir().unwrap_or_else(|| repo.path());
+        let base = base
+            .canonicalize()
+            .map_err(|e| format!("failed to resolve {}: {e}", base.display()))?;
+        let config = repo
+            .config()
+            .map_err(|e| format!("failed to read git config: {e}"))?;
+        let mut roots = Vec::new();
+        if let Ok(mut entries) = config.multivar(ALLOWED_ROOT_KEY, None) {
+            while let Some(entry) = entries.next() {
+                let entry = entry.map_err(|e| format!("failed to read {ALLOWED_ROOT_KEY}: {e}"))?;
+                if let Some(value) = entry.value().map(str::trim).filter(|v| !v.is_empty()) {
+                    roots.push(resolve(&base.join(value)));
+                }
+            }
+        }
+        if roots.is_empty() {
+            roots.push(base.clone());
+        }
+        Ok(Self { base, roots })
+    }
+
+    /// `input` made absolute, with `..` and symlinks resolved, as long as
+    /// it stays under an allowed root. The path needn't exist yet.
+    pub fn confine(&self, input: &str) -> Result<PathBuf, String> {
+        if input.trim().is_empty() {
+            return Err("the path is empty".to_string());
+        }
+        if input.contains('\0') {
+            return Err("the path contains a NUL byte".to_string());
+        }
+        let path = resolve(&self.base.join(input));
+        if self.roots.iter().any(|root| path.starts_with(root)) {
+            return Ok(path);
+        }
+        Err(format!(
+            "{input} is outside the allowed roots ({}); add one with {ALLOWED_ROOT_KEY}",
+            self.roots
+                .iter()
+                .map(|root| root.display().to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ))
+    }
+}
+
+/// `path` with `.` and `..` applied and its longest existing ancestor
+/// canonicalized, so neither `..` nor a symlink can leave a root unseen.
+fn resolve(path: &Path) -> PathBuf {
+    let mut lexical = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::ParentDir => {
+                lexical.pop();
+            }
+            Component::CurDir => {}
+            other => lexical.push(other),
+        }
+    }
+    let mut existing = lexical.as_path();
+    let mut rest = Vec::new();
+    loop {
+        if let Ok(real) = existing.canonicalize() {
+            return rest.iter().rev().fold(real, |path, part| path.join(part));
+        }
+        match (existing.parent(), existing.file_name()) {
+            (Some(parent), Some(name)) => {
+                rest.push(name.to_os_string());
+                existing = parent;
+            }
+            _ => return lexical,
+        }
+    }
+}
+
+/// A name used as one path or ref component, like a worktree's name: no
+/// separators, no leading dot, and nothing git refuses in a ref.
+pub fn check_component(kind: &str, name: &str) -> Result<(), String> {
+    let valid = !name.is_empty()
+        && !name.starts_with('.')
+        && !name.contains(['/', '\\'])
+        && git2::Reference::is_valid_name(&format!("refs/heads/{name}"));
+    if valid {
+        Ok(())
+    } else {
+        Err(format!("{name:?} isn't a valid {kind} name"))
+    }
+}
+
+/// A full ref name under `prefix`, e.g. a remote name below
+/// `refs/remotes/`, checked with git's reference name rules. A leading `-`
+/// is refused too, as git does for branches, so a name can't pass for an
+/// option.
+pub fn check_ref(kind: &str, prefix: &str, name: &str) -> Result<(), String> {
+    if !name.is_empty()
+        && !name.starts_with('-')
+        && git2::Reference::is_valid_name(&format!("{prefix}{name}"))
+    {
+        Ok(())
+    } else {
+        Err(format!("{name:?} isn't a valid {kind}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn paths_are_confined_to_the_allowed_roots() {
+        let (dir, repo) = crate::test_support::init_repo("paths");
+        let real = dir.canonicalize().expect("canonical");
+        let roots = Roots::load(&repo).expect("roots");
+
+        assert_eq!(roots.confine("out/a.bundle"), Ok(real.join("out/a.bundle")));
+        assert_eq!(
+            roots.confine(&real.join("x/../y").to_string_lossy()),
+            Ok(real.join("y"))
+        );
+        assert!(roots.confine("../elsewhere").is_err());
+        assert!(roots.confine("/etc/passwd").is_err());
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(std::env::temp_dir(), dir.join("tmp")).expect("symlink");
+            assert!(roots.confine("tmp/escape").is_err());
+        }
+
+        repo.config()
+            .and_then(|mut c| c.set_str(ALLOWED_ROOT_KEY, "../shared"))
+            .expect("root");
+        let roots = Roots::load(&repo).expect("roots");
+        let shared = real.parent().expect("parent").join("shared/wt");
+        assert_eq!(roots.confine("../shared/wt"), Ok(shared));
+        assert!(roots.confine("inside").is_err());
+
+        assert!(check_component("worktree", "feature-x").is_ok());
+        for bad in ["", "../x", "a/b", ".hidden", "a..b", "x.lock"] {
+            assert!(check_component("worktree", bad).is_err(), "{bad}");
+        }
+        assert!(check_ref("remote", "refs/remotes/", "laptop").is_ok());
+        for bad in ["bad name", "../x", "--upload-pack=touch"] {
+            assert!(check_ref("remote", "refs/remotes/", bad).is_err(), "{bad}");
+        }
+    }
+}
@@ -0,0 +1,149 @@
+use std::ops::{Deref, DerefMut};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+/// Idle handles kept per repository. `git2::Repository` isn't `Sync`, so
+/// concurrent tools each check out their own.
+pub const MAX_IDLE: usize = 4;
+
+/// `(size, mtime)` of `HEAD` and `index`; either changing means another
+/// process switched branches or staged, and cached handles may be stale.
+type Stamp = [Option<(u64, SystemTime)>; 2];
+
+/// Reuses opened repositories across MCP calls instead of reopening (and
+/// re-reading config and the index) every time.
+pub struct RepoPool {
+    path: String,
+    git_dir: Mutex<Option<PathBuf>>,
+    idle: Mutex<Vec<(Stamp, git2::Repository)>>,
+}
+
+impl RepoPool {
+    pub fn new(path: &str) -> Self {
+        Self {
+            path: path.to_string(),
+            git_dir: Mutex::new(None),
+            idle: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// An idle handle whose `HEAD` and index are unchanged since it was
+    /// returned, or a freshly opened one.
+    pub fn get(&self) -> Result<PooledRepo<'_>, git2::Error> {
+        let stamp = self.stamp();
+        if stamp.iter().all(Option::is_some) {
+            let mut idle = self.idle();
+            idle.retain(|(s, _)| *s == stamp);
+            if let Some((_, repo)) = idle.pop() {
+                return Ok(PooledRepo {
+                    repo: Some(repo),
+                    pool: self,
+                });
+            }
+        }
+
+        let repo = git2::Repository::open(&self.path)?;
+        crate::sync::lock(&self.git_dir).get_or_insert_with(|| repo.path().to_path_buf());
+        Ok(PooledRepo {
+            repo: Some(repo),
+            pool: self,
+        })
+    }
+
+    fn stamp(&self) -> Stamp {
+        let Some(git_dir) = crate::sync::lock(&self.git_dir).clone() else {
+            return [None, None];
+        };
+        ["HEAD", "index"].map(|file| {
+            let meta = std::fs::metadata(git_dir.join(file)).ok()?;
+            Some((meta.len(), meta.modified().ok()?))
+        })
+    }
+
+    fn put_back(&self, repo: git2::Repository) {
+        let stamp = self.stamp();
+        let mut idle = self.idle();
+        if idle.len() < MAX_IDLE {
+            idle.push((stamp, repo));
+        }
+    }
+
+    /// Handles a panicking caller was using may be mid-operation, so they
+    /// are dropped rather than handed out again.
+    fn idle(&self) -> std::sync::MutexGuard<'_, Vec<(Stamp, git2::Repository)>> {
+        crate::sync::lock_or_repair(&self.idle, Vec::clear)
+    }
+}
+
+/// Returns the handle to its pool on drop.
+pub struct PooledRepo<'a> {
+    repo: Option<git2::Repository>,
+    pool: &'a RepoPool,
+}
+
+impl Deref for PooledRepo<'_> {
+    type Target = git2::Repository;
+
+    fn deref(&self) -> &git2::Repository {
+        self.repo.as_ref().expect("pooled repository taken")
+    }
+}
+
+impl DerefMut for PooledRepo<'_> {
+    fn deref_mut(&mut self) -> &mut git2::Repository {
+        self.repo.as_mut().expect("pooled repository taken")
+    }
+}
+
+impl Drop for PooledRepo<'_> {
+    fn drop(&mut self) {
+        if let Some(repo) = self.repo.take() {
+            self.pool.put_back(repo);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn temp_repo(label: &str) -> crate::test_support::TempDir {
+        let (dir, repo) = crate::test_support::init_repo(&format!("pool-{label}"));
+        repo.index().expect("index").write().expect("write index");
+        dir
+    }
+
+    fn idle(pool: &RepoPool) -> usize {
+        pool.idle.lock().unwrap().len()
+    }
+
+    #[test]
+    fn handles_are_reused_until_head_or_index_changes() {
+        let dir = temp_repo("reuse");
+        let path = dir.as_str();
+        let pool = RepoPool::new(path);
+
+        let (a, b) = (pool.get().expect("first"), pool.get().expect("second"));
+        drop((a, b));
+        assert_eq!(idle(&pool), 2);
+        let reused = pool.get().expect("reuse");
+        assert_eq!(idle(&pool), 1);
+        drop(reused);
+
+        // Another process switches branches.
+        std::thread::sleep(Duration::from_millis(20));
+        std::fs::write(
+            std::path::Path::new(&path).join(".git/HEAD"),
+            "ref: refs/heads/other-branch\n",
+        )
+        .expect("rewrite HEAD");
+        let fresh = pool.get().expect("fresh");
+        assert_eq!(idle(&pool), 0, "stale handles are dropped");
+        assert_eq!(
+            fresh.find_reference("HEAD").unwrap().symbolic_target(),
+            Some("refs/heads/other-branch")
+        );
+    }
+}
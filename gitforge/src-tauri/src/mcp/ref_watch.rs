@@ -0,0 +1,196 @@
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Notification method sent when a watched ref moves.
+pub const REFS_CHANGED_METHOD: &str = "refs/changed";
+
+/// One watched ref that moved; `old` is `None` for a new ref and `new`
+/// for a deleted one.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RefMoved {
+    #[serde(rename = "ref")]
+    pub name: String,
+    pub old: Option<String>,
+    pub new: Option<String>,
+}
+
+/// The JSON-RPC notification announcing that `moved` moved.
+pub fn notification(moved: &RefMoved) -> serde_json::Value {
+    serde_json::json!({
+        "jsonrpc": "2.0",
+        "method": REFS_CHANGED_METHOD,
+        "params": moved,
+    })
+}
+
+/// The refs one WebSocket connection asked `refs_watch` for, and the ids
+/// they were last seen at. A pattern is a full ref name, `HEAD`, or a
+/// glob such as `refs/heads/*`.
+#[derive(Debug, Default)]
+pub struct RefWatch {
+    /// Per pattern, the refs it matched and their ids.
+    watched: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+impl RefWatch {
+    pub fn is_empty(&self) -> bool {
+        self.watched.is_empty()
+    }
+
+    pub fn patterns(&self) -> impl Iterator<Item = &str> {
+        self.watched.keys().map(String::as_str)
+    }
+
+    /// Adds `patterns` and returns what every ref now watched points at.
+    pub fn watch(
+        &mut self,
+        repo: &git2::Repository,
+        patterns: &[String],
+    ) -> Result<BTreeMap<String, String>, String> {
+        for pattern in patterns {
+            if pattern != "HEAD" && !pattern.starts_with("refs/") {
+                return Err(format!(
+                    "invalid ref '{pattern}': expected HEAD or a full name such as refs/heads/main"
+                ));
+            }
+        }
+        for pattern in patterns {
+            let refs = resolve(repo, pattern)?;
+            self.watched.insert(pattern.clone(), refs);
+        }
+        Ok(merged(self.watched.values()))
+    }
+
+    /// Stops watching `patterns`, or everything when empty.
+    pub fn unwatch(&mut self, patterns: &[String]) {
+        if patterns.is_empty() {
+            self.watched.clear();
+        }
+        for pattern in patterns {
+            self.watched.remove(pattern);
+        }
+    }
+
+    /// The watched refs that moved since the last call, by name.
+    pub fn changes(&mut self, repo: &git2::Repository) -> Result<Vec<RefMoved>, String> {
+        let mut now = BTreeMap::new();
+        for pattern in self.watched.keys() {
+            now.insert(pattern.clone(), resolve(repo, pattern)?);
+        }
+        let before = merged(self.watched.values());
+        let after = merged(now.values());
+        let names: BTreeSet<&String> = before.keys().chain(after.keys()).collect();
+        let moved = names
+            .into_iter()
+            .filter(|name| before.get(*name) != after.get(*name))
+            .map(|name| RefMoved {
+                name: name.clone(),
+                old: before.get(name).cloned(),
+                new: after.get(name).cloned(),
+            })
+            .collect();
+        self.watched = now;
+        Ok(moved)
+    }
+}
+
+/// The refs `pattern` names and the commits they point at; symbolic refs
+/// such as `HEAD` are followed.
+fn resolve(repo: &git2::Repository, pattern: &str) -> Result<BTreeMap<String, String>, String> {
+    let mut refs = BTreeMap::new();
+    if !pattern.contains(['*', '?', '[']) {
+        if let Some(oid) = repo
+            .find_reference(pattern)
+            .ok()
+            .and_then(|r| r.resolve().ok())
+            .and_then(|r| r.target())
+        {
+            refs.insert(pattern.to_string(), oid.to_string());
+        }
+        return Ok(refs);
+    }
+    let matches = repo
+        .references_glob(pattern)
+        .map_err(|e| format!("failed to list {pattern}: {e}"))?;
+    for reference in matches.flatten() {
+        if let (Some(name), Some(oid)) = (
+            reference.name().map(str::to_string),
+            reference.resolve().ok().and_then(|r| r.target()),
+        ) {
+            refs.insert(name, oid.to_string());
+        }
+    }
+    Ok(refs)
+}
+
+fn merged<'a>(
+    refs: impl Iterator<Item = &'a BTreeMap<String, String>>,
+) -> BTreeMap<String, String> {
+    refs.flat_map(|refs| refs.iter().map(|(k, v)| (k.clone(), v.clone())))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_watched_refs_that_move_appear_or_vanish() {
+        let (_dir, repo) = crate::test_support::init_repo("ref-watch");
+        let sig = git2::Signature::now("GitForge Test", "test@gitforge.dev").expect("sig");
+        let tree = repo
+            .find_tree(repo.index().unwrap().write_tree().unwrap())
+            .unwrap();
+        let first = repo
+            .commit(Some("refs/heads/main"), &sig, &sig, "one", &tree, &[])
+            .expect("commit");
+        let first = repo.find_commit(first).unwrap();
+        let second = repo
+            .commit(None, &sig, &sig, "two", &tree, &[&first])
+            .expect("commit");
+
+        let mut watch = RefWatch::default();
+        assert!(watch.watch(&repo, &["main".to_string()]).is_err());
+        let seen = watch
+            .watch(
+                &repo,
+                &["refs/heads/main".to_string(), "refs/tags/*".to_string()],
+            )
+            .expect("watch");
+        assert_eq!(seen.len(), 1);
+        assert!(watch.changes(&repo).expect("changes").is_empty());
+
+        repo.reference("refs/heads/main", second, true, "move")
+            .expect("move main");
+        repo.reference("refs/heads/other", second, true, "unwatched")
+            .expect("other");
+        repo.reference("refs/tags/v1", first.id(), false, "tag")
+            .expect("tag");
+        assert_eq!(
+            watch.changes(&repo).expect("changes"),
+            [
+                RefMoved {
+                    name: "refs/heads/main".to_string(),
+                    old: Some(first.id().to_string()),
+                    new: Some(second.to_string()),
+                },
+                RefMoved {
+                    name: "refs/tags/v1".to_string(),
+                    old: None,
+                    new: Some(first.id().to_string()),
+                },
+            ]
+        );
+
+        repo.find_reference("refs/tags/v1")
+            .unwrap()
+            .delete()
+            .expect("delete tag");
+        let gone = watch.changes(&repo).expect("changes");
+        assert_eq!((gone.len(), gone[0].new.clone()), (1, None));
+        watch.unwatch(&["refs/tags/*".to_string()]);
+        assert_eq!(watch.patterns().collect::<Vec<_>>(), ["refs/heads/main"]);
+        watch.unwatch(&[]);
+        assert!(watch.is_empty());
+    }
+}
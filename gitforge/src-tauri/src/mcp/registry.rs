@@ -0,0 +1,161 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use super::server::GitForgeMcp;
+
+/// One long-lived [`GitForgeMcp`] per open repository, so the store pool
+/// and schema setup are paid once instead of on every UI call. Windows are
+/// bound to a repository by label; windows on the same repository share its
+/// server and event bus.
+#[derive(Default)]
+pub struct ServerRegistry {
+    servers: Mutex<HashMap<PathBuf, Arc<GitForgeMcp>>>,
+    windows: Mutex<HashMap<String, PathBuf>>,
+}
+
+impl ServerRegistry {
+    /// Returns the server for `repo_path`, creating it on first use.
+    pub fn get_or_open(&self, repo_path: &str) -> Result<Arc<GitForgeMcp>, String> {
+        let key = registry_key(repo_path);
+        let mut servers = crate::sync::lock(&self.servers);
+        if let Some(server) = servers.get(&key) {
+            return Ok(Arc::clone(server));
+        }
+
+        let server = Arc::new(GitForgeMcp::new(repo_path.to_string())?);
+        servers.insert(key, Arc::clone(&server));
+        Ok(server)
+    }
+
+    /// Drops the registry's handle; in-flight calls keep theirs until done.
+    pub fn close(&self, repo_path: &str) -> Result<bool, String> {
+        let mut servers = crate::sync::lock(&self.servers);
+        Ok(servers.remove(&registry_key(repo_path)).is_some())
+    }
+
+    /// Shows `repo_path` in window `label`, replacing whatever it showed.
+    pub fn bind_window(&self, label: &str, repo_path: &str) -> Result<Arc<GitForgeMcp>, String> {
+        let server = self.get_or_open(repo_path)?;
+        let previous =
+            crate::sync::lock(&self.windows).insert(label.to_string(), registry_key(repo_path));
+        if let Some(previous) = previous.filter(|p| *p != registry_key(repo_path)) {
+            self.close_if_unused(&previous)?;
+        }
+        Ok(server)
+    }
+
+    pub fn window_repo(&self, label: &str) -> Result<Option<PathBuf>, String> {
+        let windows = crate::sync::lock(&self.windows);
+        Ok(windows.get(label).cloned())
+    }
+
+    /// The server behind window `label`.
+    pub fn for_window(&self, label: &str) -> Result<Arc<GitForgeMcp>, String> {
+        let repo = self
+            .window_repo(label)?
+            .ok_or_else(|| format!("window '{label}' has no repository open"))?;
+        self.get_or_open(&repo.to_string_lossy())
+    }
+
+    /// Labels of the windows showing `repo_path`, sorted.
+    pub fn windows_for(&self, repo_path: &str) -> Result<Vec<String>, String> {
+        let key = registry_key(repo_path);
+        let windows = crate::sync::lock(&self.windows);
+        let mut labels: Vec<String> = windows
+            .iter()
+            .filter(|(_, repo)| **repo == key)
+            .map(|(label, _)| label.clone())
+            .collect();
+        labels.sort();
+        Ok(labels)
+    }
+
+    /// Forgets window `label`, closing its repository's server once no other
+    /// window shows it. Returns the repository it showed.
+    pub fn unbind_window(&self, label: &str) -> Result<Option<PathBuf>, String> {
+        let repo = crate::sync::lock(&self.windows).remove(label);
+        if let Some(repo) = &repo {
+            self.close_if_unused(repo)?;
+        }
+        Ok(repo)
+    }
+
+    fn close_if_unused(&self, repo: &Path) -> Result<(), String> {
+        let repo = repo.to_string_lossy();
+        if self.windows_for(&repo)?.is_empty() {
+            self.close(&repo)?;
+        }
+        Ok(())
+    }
+
+    pub fn open_repos(&self) -> Result<Vec<PathBuf>, String> {
+        let servers = crate::sync::lock(&self.servers);
+        let mut paths: Vec<PathBuf> = servers.keys().cloned().collect();
+        paths.sort();
+        Ok(paths)
+    }
+}
+
+/// `repo/` and `repo/./` should share one server.
+pub(crate) fn registry_key(repo_path: &str) -> PathBuf {
+    std::fs::canonicalize(repo_path).unwrap_or_else(|_| PathBuf::from(repo_path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_repo(label: &str) -> crate::test_support::TempDir {
+        let dir = crate::test_support::temp_path(&format!("registry-{label}"));
+        git2::Repository::init(&dir).expect("init repo");
+        dir
+    }
+
+    #[test]
+    fn servers_are_reused_until_closed() {
+        let registry = ServerRegistry::default();
+        let dir = temp_repo("reuse");
+        let repo = dir.as_str();
+
+        let first = registry.get_or_open(repo).expect("open");
+        let again = registry
+            .get_or_open(&format!("{repo}/."))
+            .expect("open again");
+        assert!(Arc::ptr_eq(&first, &again));
+        assert_eq!(registry.open_repos().expect("list").len(), 1);
+
+        assert!(registry.close(repo).expect("close"));
+        assert!(!registry.close(repo).expect("close twice"));
+        let reopened = registry.get_or_open(repo).expect("reopen");
+        assert!(!Arc::ptr_eq(&first, &reopened));
+    }
+
+    #[test]
+    fn windows_share_a_repo_until_the_last_one_closes() {
+        let registry = ServerRegistry::default();
+        let dir = temp_repo("windows");
+        let repo = dir.as_str();
+        let other_dir = temp_repo("windows-other");
+        let other = other_dir.as_str();
+
+        let main = registry.bind_window("main", repo).expect("bind main");
+        let second = registry.bind_window("repo-1", repo).expect("bind second");
+        assert!(Arc::ptr_eq(&main, &second));
+        assert_eq!(
+            registry.windows_for(repo).expect("windows"),
+            ["main", "repo-1"]
+        );
+        assert!(registry.for_window("nope").is_err());
+
+        registry.bind_window("repo-1", other).expect("rebind");
+        assert_eq!(registry.open_repos().expect("open").len(), 2);
+
+        registry.unbind_window("main").expect("unbind main");
+        assert_eq!(registry.open_repos().expect("open"), [registry_key(other)]);
+        assert_eq!(
+            registry.window_repo("repo-1").expect("lookup"),
+            Some(registry_key(other))
+        );
+    }
+}
@@ -0,0 +1,154 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use tokio::sync::oneshot;
+
+use crate::sync::lock;
+
+type Waiters<T> = Arc<Mutex<HashMap<String, Vec<oneshot::Sender<T>>>>>;
+
+/// Runs at most one computation per key at a time; callers arriving while
+/// it is in flight wait for and share its result. Nothing is cached once
+/// it completes. Cloning shares the in-flight table.
+#[derive(Clone)]
+pub struct SingleFlight<T> {
+    calls: Waiters<T>,
+}
+
+impl<T> Default for SingleFlight<T> {
+    fn default() -> Self {
+        Self {
+            calls: Arc::default(),
+        }
+    }
+}
+
+impl<T: Clone> SingleFlight<T> {
+    pub async fn run<F, Fut>(&self, key: String, f: F) -> T
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = T>,
+    {
+        let waiting = {
+            let mut calls = lock(&self.calls);
+            match calls.get_mut(&key) {
+                Some(waiters) => {
+                    let (tx, rx) = oneshot::channel();
+                    waiters.push(tx);
+                    Some(rx)
+                }
+                None => {
+                    calls.insert(key.clone(), Vec::new());
+                    None
+                }
+            }
+        };
+        if let Some(rx) = waiting {
+            // An error means the leading caller was cancelled midway.
+            return match rx.await {
+                Ok(value) => value,
+                Err(_) => f().await,
+            };
+        }
+
+        let flight = Flight {
+            calls: &self.calls,
+            key: Some(key),
+        };
+        let value = f().await;
+        for waiter in flight.land() {
+            let _ = waiter.send(value.clone());
+        }
+        value
+    }
+
+    /// Calls currently in flight.
+    pub fn in_flight(&self) -> usize {
+        lock(&self.calls).len()
+    }
+}
+
+/// Removes its key when dropped, so a cancelled leader releases its
+/// waiters instead of leaving them hanging.
+struct Flight<'a, T> {
+    calls: &'a Waiters<T>,
+    key: Option<String>,
+}
+
+impl<T> Flight<'_, T> {
+    fn land(mut self) -> Vec<oneshot::Sender<T>> {
+        let key = self.key.take().expect("flight lands once");
+        lock(self.calls).remove(&key).unwrap_or_default()
+    }
+}
+
+impl<T> Drop for Flight<'_, T> {
+    fn drop(&mut self) {
+        if let Some(key) = self.key.take() {
+            lock(self.calls).remove(&key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn concurrent_calls_share_one_computation() {
+        let flights = SingleFlight::default();
+        let runs = AtomicUsize::new(0);
+        let compute = || async {
+            runs.fetch_add(1, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            42
+        };
+
+        let (a, b) = tokio::join!(
+            flights.run("status".into(), compute),
+            flights.run("status".into(), compute)
+        );
+        assert_eq!((a, b), (42, 42));
+        assert_eq!(runs.load(Ordering::SeqCst), 1);
+        assert_eq!(flights.in_flight(), 0);
+
+        let (_, _) = tokio::join!(
+            flights.run("status".into(), compute),
+            flights.run("prs".into(), compute)
+        );
+        assert_eq!(
+            runs.load(Ordering::SeqCst),
+            3,
+            "different keys run separately"
+        );
+    }
+
+    #[tokio::test]
+    async fn waiters_recompute_when_the_leader_is_cancelled() {
+        let flights = SingleFlight::default();
+        let leader = tokio::spawn({
+            let flights = flights.clone();
+            async move {
+                let slow = || async {
+                    tokio::time::sleep(Duration::from_secs(60)).await;
+                    1
+                };
+                flights.run("status".into(), slow).await
+            }
+        });
+        while flights.in_flight() == 0 {
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+        let follower = tokio::spawn({
+            let flights = flights.clone();
+            async move { flights.run("status".into(), || async { 2 }).await }
+        });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+
+        leader.abort();
+        assert_eq!(follower.await.expect("follower"), 2);
+        assert_eq!(flights.in_flight(), 0, "cancelled leader released its key");
+    }
+}
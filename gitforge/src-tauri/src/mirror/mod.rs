@@ -0,0 +1,502 @@
+use serde::Serialize;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Git config, may repeat: a remote name or URL that receives every ref
+/// with `git push --mirror`, so refs deleted here are deleted there too.
+pub const REMOTE_KEY: &str = "gitforge.mirror.remote";
+/// Git config, may repeat: a bundle file rewritten with every ref. Relative
+/// paths are relative to the working tree.
+pub const BUNDLE_KEY: &str = "gitforge.mirror.bundle";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Target {
+    Remote(String),
+    Bundle(PathBuf),
+}
+
+impl Target {
+    pub fn label(&self) -> String {
+        match self {
+            Target::Remote(remote) => remote.clone(),
+            Target::Bundle(path) => path.display().to_string(),
+        }
+    }
+
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Target::Remote(_) => "remote",
+            Target::Bundle(_) => "bundle",
+        }
+    }
+}
+
+/// The configured targets, remotes first, in config order.
+pub fn targets(repo: &git2::Repository) -> Result<Vec<Target>, String> {
+    let config = repo
+        .config()
+        .map_err(|e| format!("failed to read config: {e}"))?;
+    let read = |key: &str| -> Result<Vec<String>, String> {
+        let mut values = Vec::new();
+        let entries = match config.multivar(key, None) {
+            Ok(entries) => entries,
+            Err(e) if e.code() == git2::ErrorCode::NotFound => return Ok(values),
+            Err(e) => return Err(format!("invalid {key}: {e}")),
+        };
+        entries
+            .for_each(|entry| {
+                if let Some(value) = entry.value().map(str::trim).filter(|v| !v.is_empty()) {
+                    values.push(value.to_string());
+                }
+            })
+            .map_err(|e| format!("invalid {key}: {e}"))?;
+        Ok(values)
+    };
+
+    let mut targets = Vec::new();
+    for remote in read(REMOTE_KEY)? {
+        // Would be taken for an option by `git push`.
+        if remote.starts_with('-') {
+            return Err(format!("invalid {REMOTE_KEY} {remote:?}"));
+        }
+        targets.push(Target::Remote(remote));
+    }
+    let base = repo.workdir().unwrap_or_else(|| repo.path());
+    for bundle in read(BUNDLE_KEY)? {
+        targets.push(Target::Bundle(base.join(bundle)));
+    }
+    Ok(targets)
+}
+
+/// How the latest backup to one target went.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TargetReport {
+    pub target: String,
+    pub kind: &'static str,
+    pub ok: bool,
+    /// What changed, on success.
+    pub detail: Option<String>,
+    pub error: Option<String>,
+    /// Seconds since the unix epoch.
+    pub finished_at: u64,
+    /// When this target last succeeded, carried over from earlier reports.
+    pub last_success: Option<u64>,
+}
+
+/// Backs `repo` up to each target in turn; one failing doesn't stop the
+/// rest. `previous` reports supply each target's `last_success`.
+pub fn backup(
+    repo: &git2::Repository,
+    targets: &[Target],
+    previous: &[TargetReport],
+) -> Vec<TargetReport> {
+    targets
+        .iter()
+        .map(|target| {
+            let result = match target {
+                Target::Remote(remote) => push_mirror(repo.path(), remote),
+                Target::Bundle(path) => write_bundle(repo, path),
+            };
+            let finished_at = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|age| age.as_secs())
+                .unwrap_or_default();
+            let label = target.label();
+            let earlier = previous
+                .iter()
+                .find(|report| report.target == label)
+                .and_then(|report| report.last_success);
+            let ok = result.is_ok();
+            let (detail, error) = match result {
+                Ok(detail) => (Some(detail), None),
+                Err(error) => (None, Some(error)),
+            };
+            TargetReport {
+                target: label,
+                kind: target.kind(),
+                ok,
+                detail,
+                error,
+                finished_at,
+                last_success: if ok { Some(finished_at) } else { earlier },
+            }
+        })
+        .collect()
+}
+
+/// `git push --mirror`, without prompting for credentials.
+fn push_mirror(git_dir: &Path, remote: &str) -> Result<String, String> {
+    let output = git(git_dir)
+        .args(["push", "--mirror", "--porcelain", remote])
+        .output()
+        .map_err(|e| format!("failed to run git: {e}"))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("push to {remote} failed: {}", stderr.trim()));
+    }
+    // Porcelain lines are `<flag>\t<from>:<to>\t<summary>`; `=` is up to date.
+    let updated = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter(|line| line.as_bytes().get(1) == Some(&b'\t') && !line.starts_with('='))
+        .count();
+    Ok(match updated {
+        0 => "up to date".to_string(),
+        1 => "1 ref updated".to_string(),
+        n => format!("{n} refs updated"),
+    })
+}
+
+/// `git bundle create --all` for the backup task.
+fn write_bundle(repo: &git2::Repository, path: &Path) -> Result<String, String> {
+    let has_refs = repo
+        .references()
+        .map_err(|e| format!("failed to list refs: {e}"))?
+        .next()
+        .is_some();
+    if !has_refs {
+        return Err("the repository has no refs to bundle".to_string());
+    }
+    bundle_to(repo, path, &["--all".to_string()])?;
+    let bytes = std::fs::metadata(path).map(|m| m.len()).unwrap_or_default();
+    Ok(format!("{bytes} bytes"))
+}
+
+/// `git bundle create` with `revs`, written beside `path` and renamed over
+/// it so a failed run leaves any previous bundle intact.
+fn bundle_to(repo: &git2::Repository, path: &Path, revs: &[String]) -> Result<(), String> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)
+            .map_err(|e| format!("failed to create {}: {e}", dir.display()))?;
+    }
+    let mut partial = path.as_os_str().to_owned();
+    partial.push(".partial");
+    let partial = PathBuf::from(partial);
+    let output = git(repo.path())
+        .args(["bundle", "create", "--quiet"])
+        .arg(&partial)
+        .args(revs)
+        .output()
+        .map_err(|e| format!("failed to run git: {e}"))?;
+    if !output.status.success() {
+        let _ = std::fs::remove_file(&partial);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!(
+            "bundle {} failed: {}",
+            path.display(),
+            stderr.trim()
+        ));
+    }
+    std::fs::rename(&partial, path)
+        .map_err(|e| format!("failed to replace {}: {e}", path.display()))
+}
+
+/// A ref a bundle carries.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BundleRef {
+    pub name: String,
+    pub target: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BundleInfo {
+    pub path: String,
+    pub bytes: u64,
+    pub refs: Vec<BundleRef>,
+    /// Commits the receiving repository must already have; empty for a
+    /// full bundle.
+    pub prerequisites: Vec<String>,
+}
+
+/// Writes a bundle of `refs` (every ref when empty) to `path`. With
+/// `since`, the bundle is incremental: it leaves out what `since` already
+/// has, and the receiver needs that history first.
+pub fn create_bundle(
+    repo: &git2::Repository,
+    path: &Path,
+    refs: &[String],
+    since: Option<&str>,
+) -> Result<BundleInfo, String> {
+    let mut revs = Vec::new();
+    for name in refs {
+        if name.starts_with('-') {
+            return Err(format!("invalid ref {name:?}"));
+        }
+        let reference = repo
+            .resolve_reference_from_short_name(name)
+            .map_err(|e| format!("failed to resolve {name}: {e}"))?;
+        revs.push(reference.name().unwrap_or(name).to_string());
+    }
+    if revs.is_empty() {
+        revs.push("--all".to_string());
+    }
+    if let Some(since) = since {
+        let base = repo
+            .revparse_single(since)
+            .and_then(|object| object.peel_to_commit())
+            .map_err(|e| format!("failed to resolve {since}: {e}"))?;
+        revs.push(format!("^{}", base.id()));
+    }
+    bundle_to(repo, path, &revs)?;
+    read_bundle(path)
+}
+
+/// Reads the refs and prerequisites from a bundle's header.
+pub fn read_bundle(path: &Path) -> Result<BundleInfo, String> {
+    let failed = |e: std::io::Error| format!("failed to read {}: {e}", path.display());
+    let file = std::fs::File::open(path).map_err(failed)?;
+    let bytes = file.metadata().map_err(failed)?.len();
+    let mut lines = BufReader::new(file).split(b'\n');
+    let signature = lines
+        .next()
+        .transpose()
+        .map_err(failed)?
+        .unwrap_or_default();
+    if !matches!(
+        signature.as_slice(),
+        b"# v2 git bundle" | b"# v3 git bundle"
+    ) {
+        return Err(format!("{} is not a git bundle", path.display()));
+    }
+    let mut refs = Vec::new();
+    let mut prerequisites = Vec::new();
+    for line in lines {
+        let line = line.map_err(failed)?;
+        let line = String::from_utf8_lossy(&line);
+        if line.is_empty() {
+            break;
+        }
+        if let Some(prerequisite) = line.strip_prefix('-') {
+            let id = prerequisite.split(' ').next().unwrap_or_default();
+            prerequisites.push(id.to_string());
+        } else if let Some((target, name)) = line.split_once(' ') {
+            // v3 capability lines start with `@` and have no target.
+            if !line.starts_with('@') {
+                refs.push(BundleRef {
+                    name: name.to_string(),
+                    target: target.to_string(),
+                });
+            }
+        }
+    }
+    Ok(BundleInfo {
+        path: path.display().to_string(),
+        bytes,
+        refs,
+        prerequisites,
+    })
+}
+
+/// A ref written by [`import_bundle`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ImportedRef {
+    /// The ref in this repository.
+    pub name: String,
+    /// The ref as the bundle names it.
+    pub from: String,
+    pub old: Option<String>,
+    pub new: String,
+}
+
+/// Fetches a bundle's branches into `refs/remotes/<remote>/` and its tags
+/// into `refs/tags/`, so nothing checked out here moves. Returns the refs
+/// that changed.
+pub fn import_bundle(
+    repo: &git2::Repository,
+    path: &Path,
+    remote: &str,
+) -> Result<Vec<ImportedRef>, String> {
+    if remote.is_empty() || !git2::Reference::is_valid_name(&format!("refs/remotes/{remote}/x")) {
+        return Err(format!("invalid remote name {remote:?}"));
+    }
+    let info = read_bundle(path)?;
+    let missing: Vec<&str> = info
+        .prerequisites
+        .iter()
+        .filter(|id| {
+            git2::Oid::from_str(id)
+                .and_then(|oid| repo.find_commit(oid))
+                .is_err()
+        })
+        .map(|id| &id[..id.len().min(7)])
+        .collect();
+    if !missing.is_empty() {
+        return Err(format!(
+            "the bundle builds on commits this repository lacks ({}); import the bundle they came from first",
+            missing.join(", ")
+        ));
+    }
+
+    let local = |name: &str| {
+        if let Some(branch) = name.strip_prefix("refs/heads/") {
+            Some(format!("refs/remotes/{remote}/{branch}"))
+        } else {
+            name.starts_with("refs/tags/").then(|| name.to_string())
+        }
+    };
+    let wanted: Vec<(&BundleRef, String)> = info
+        .refs
+        .iter()
+        .filter_map(|bundled| local(&bundled.name).map(|name| (bundled, name)))
+        .collect();
+    if wanted.is_empty() {
+        return Err(format!("{} has no branches or tags", path.display()));
+    }
+    let target = |name: &str| repo.refname_to_id(name).ok().map(|id| id.to_string());
+    let before: Vec<Option<String>> = wanted.iter().map(|(_, name)| target(name)).collect();
+
+    let output = git(repo.path())
+        .arg("fetch")
+        .arg("--quiet")
+        .arg(path)
+        .arg(format!("+refs/heads/*:refs/remotes/{remote}/*"))
+        .arg("refs/tags/*:refs/tags/*")
+        .output()
+        .map_err(|e| format!("failed to run git: {e}"))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!(
+            "fetch from {} failed: {}",
+            path.display(),
+            stderr.trim()
+        ));
+    }
+
+    Ok(wanted
+        .into_iter()
+        .zip(before)
+        .filter_map(|((bundled, name), old)| {
+            let new = target(&name)?;
+            (old.as_deref() != Some(new.as_str())).then(|| ImportedRef {
+                name,
+                from: bundled.name.clone(),
+                old,
+                new,
+            })
+        })
+        .collect())
+}
+
+fn git(git_dir: &Path) -> Command {
+    let mut command = Command::new("git");
+    command
+        .arg("--git-dir")
+        .arg(git_dir)
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .stdin(Stdio::null());
+    command
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_repo(label: &str, bare: bool) -> (crate::test_support::TempDir, git2::Repository) {
+        let dir = crate::test_support::temp_path(&format!("mirror-{label}"));
+        let repo = if bare {
+            git2::Repository::init_bare(&dir)
+        } else {
+            git2::Repository::init(&dir)
+        }
+        .expect("init repo");
+        (dir, repo)
+    }
+
+    fn commit(repo: &git2::Repository, message: &str) -> git2::Oid {
+        let sig = git2::Signature::now("Test", "test@example.com").expect("signature");
+        let tree = repo
+            .find_tree(repo.index().unwrap().write_tree().expect("write tree"))
+            .expect("tree");
+        let parent = repo.head().ok().and_then(|head| head.peel_to_commit().ok());
+        let parents: Vec<&git2::Commit> = parent.iter().collect();
+        repo.commit(Some("HEAD"), &sig, &sig, message, &tree, &parents)
+            .expect("commit")
+    }
+
+    #[test]
+    fn mirrors_every_ref_and_prunes_deleted_ones() {
+        let (dir, repo) = temp_repo("source", false);
+        let (backup_dir, backup_repo) = temp_repo("backup", true);
+        let tip = commit(&repo, "init");
+        let head = repo.find_commit(tip).unwrap();
+        repo.branch("topic", &head, false).expect("branch");
+        repo.tag_lightweight("v1", head.as_object(), false)
+            .expect("tag");
+        let bundle = dir.join("backups/repo.bundle");
+        let mut config = repo.config().expect("config");
+        config
+            .set_multivar(REMOTE_KEY, "^$", &backup_dir.to_string_lossy())
+            .expect("remote");
+        config
+            .set_multivar(BUNDLE_KEY, "^$", "backups/repo.bundle")
+            .expect("bundle");
+
+        let targets = targets(&repo).expect("targets");
+        assert_eq!(
+            targets,
+            [
+                Target::Remote(backup_dir.to_string_lossy().to_string()),
+                Target::Bundle(bundle.clone()),
+            ]
+        );
+        let reports = backup(&repo, &targets, &[]);
+        assert!(reports.iter().all(|r| r.ok), "{reports:?}");
+        assert_eq!(backup_repo.refname_to_id("refs/tags/v1").ok(), Some(tip));
+        assert_eq!(
+            backup_repo.refname_to_id("refs/heads/topic").ok(),
+            Some(tip)
+        );
+        assert!(std::fs::metadata(&bundle).expect("bundle written").len() > 0);
+
+        repo.find_branch("topic", git2::BranchType::Local)
+            .unwrap()
+            .delete()
+            .expect("delete branch");
+        let again = backup(&repo, &targets[..1], &reports);
+        assert_eq!(again[0].detail.as_deref(), Some("1 ref updated"));
+        assert!(backup_repo.refname_to_id("refs/heads/topic").is_err());
+
+        let missing = [Target::Remote(
+            dir.join("nowhere").to_string_lossy().to_string(),
+        )];
+        let failed = backup(&repo, &missing, &[]);
+        assert!(!failed[0].ok);
+        assert!(failed[0].error.as_deref().unwrap().contains("push to"));
+        assert_eq!(failed[0].last_success, None);
+    }
+
+    #[test]
+    fn incremental_bundles_need_their_base() {
+        let (dir, repo) = temp_repo("bundle-source", false);
+        let (_other_dir, other) = temp_repo("bundle-target", true);
+        let (_fresh_dir, fresh) = temp_repo("bundle-fresh", true);
+        let first = commit(&repo, "init");
+        repo.tag_lightweight("v1", &repo.find_object(first, None).unwrap(), false)
+            .expect("tag");
+        let branch = repo.head().unwrap().shorthand().unwrap().to_string();
+
+        let full = dir.join("full.bundle");
+        let info = create_bundle(&repo, &full, &[], None).expect("full bundle");
+        assert!(info.prerequisites.is_empty());
+        assert!(info.refs.iter().any(|r| r.name == "refs/tags/v1"));
+        let imported = import_bundle(&other, &full, "laptop").expect("import full");
+        let names: Vec<&str> = imported.iter().map(|r| r.name.as_str()).collect();
+        assert!(names.contains(&format!("refs/remotes/laptop/{branch}").as_str()));
+        assert!(names.contains(&"refs/tags/v1"));
+
+        let second = commit(&repo, "more");
+        let delta = dir.join("delta.bundle");
+        let info =
+            create_bundle(&repo, &delta, std::slice::from_ref(&branch), Some("v1")).expect("delta");
+        assert_eq!(info.prerequisites, [first.to_string()]);
+        assert_eq!(info.refs.len(), 1);
+        let missing = import_bundle(&fresh, &delta, "laptop").expect_err("no base");
+        assert!(missing.contains("lacks"), "{missing}");
+        let imported = import_bundle(&other, &delta, "laptop").expect("import delta");
+        assert_eq!(imported.len(), 1);
+        assert_eq!(imported[0].old.as_deref(), Some(first.to_string().as_str()));
+        assert_eq!(imported[0].new, second.to_string());
+        assert!(import_bundle(&other, &delta, "laptop").unwrap().is_empty());
+    }
+}
@@ -0,0 +1,268 @@
+use ant_core::{AntEngine, GoalStatus, SystemEvent};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast;
+
+use crate::mcp::registry::registry_key;
+use crate::store::MetaStore;
+
+/// Turns every notification off when set to `false`.
+pub const ENABLED_KEY: &str = "notifications.enabled";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationKind {
+    GoalCompleted,
+    GoalFailed,
+    PrApproved,
+    MergeConflict,
+}
+
+impl NotificationKind {
+    /// Setting toggling this kind; unset means on.
+    pub fn setting_key(self) -> &'static str {
+        match self {
+            Self::GoalCompleted => "notifications.goal_completed",
+            Self::GoalFailed => "notifications.goal_failed",
+            Self::PrApproved => "notifications.pr_approved",
+            Self::MergeConflict => "notifications.merge_conflict",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Notification {
+    pub kind: NotificationKind,
+    pub title: String,
+    pub body: String,
+}
+
+/// Shows a notification, e.g. through the OS notification center.
+pub type Notifier = Arc<dyn Fn(&Notification) + Send + Sync>;
+
+/// The notification `event` deserves, if any.
+pub fn for_event(repo_name: &str, event: &SystemEvent) -> Option<Notification> {
+    let (kind, title, body) = match event {
+        SystemEvent::GoalStatusChanged {
+            goal_id,
+            status: GoalStatus::Completed,
+        } => (
+            NotificationKind::GoalCompleted,
+            "Goal completed".to_string(),
+            format!("{goal_id} finished in {repo_name}"),
+        ),
+        SystemEvent::GoalStatusChanged {
+            goal_id,
+            status: GoalStatus::Failed,
+        } => (
+            NotificationKind::GoalFailed,
+            "Goal failed".to_string(),
+            format!("{goal_id} failed in {repo_name}"),
+        ),
+        SystemEvent::PrStateChanged { pr_id, state } if state == "approved" => (
+            NotificationKind::PrApproved,
+            "Pull request approved".to_string(),
+            format!("PR #{pr_id} in {repo_name} was approved"),
+        ),
+        SystemEvent::MergeConflicted { branch, paths } => (
+            NotificationKind::MergeConflict,
+            "Merge conflict".to_string(),
+            match paths.as_slice() {
+                [only] => format!("{only} conflicts on {branch} in {repo_name}"),
+                paths => format!("{} files conflict on {branch} in {repo_name}", paths.len()),
+            },
+        ),
+        _ => return None,
+    };
+    Some(Notification { kind, title, body })
+}
+
+/// Whether `kind` is switched on. Unreadable settings count as on, so a
+/// bad value never silences failures.
+pub fn enabled(store: &Arc<dyn MetaStore>, kind: NotificationKind) -> bool {
+    [ENABLED_KEY, kind.setting_key()]
+        .iter()
+        .all(|key| store.get_bool(key).ok().flatten().unwrap_or(true))
+}
+
+/// Raises notifications for one repository's bus until dropped.
+pub struct NotificationService {
+    task: tokio::task::JoinHandle<()>,
+}
+
+impl Drop for NotificationService {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+impl NotificationService {
+    /// Must be called inside a tokio runtime. Settings are read per event so
+    /// toggles apply immediately.
+    pub fn start(
+        repo_path: &str,
+        engine: &AntEngine,
+        store: Arc<dyn MetaStore>,
+        notify: Notifier,
+    ) -> Self {
+        let repo_name = std::path::Path::new(repo_path)
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| repo_path.to_string());
+        let mut rx = engine.subscribe_events();
+        let task = tokio::spawn(async move {
+            loop {
+                match rx.recv().await {
+                    Ok(event) => {
+                        let Some(notification) = for_event(&repo_name, &event.event) else {
+                            continue;
+                        };
+                        let store = Arc::clone(&store);
+                        let kind = notification.kind;
+                        let on = tokio::task::spawn_blocking(move || enabled(&store, kind))
+                            .await
+                            .unwrap_or(true);
+                        if on {
+                            notify(&notification);
+                        }
+                    }
+                    Err(broadcast::error::RecvError::Lagged(missed)) => {
+                        eprintln!("notifications for {repo_name} lagged, {missed} events dropped");
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        });
+        Self { task }
+    }
+}
+
+/// Running services per repository, held in Tauri managed state.
+#[derive(Default)]
+pub struct Notifications {
+    services: Mutex<HashMap<PathBuf, NotificationService>>,
+}
+
+impl Notifications {
+    /// Starts notifying for `repo_path` unless it already is; returns
+    /// whether a new service was started.
+    pub fn watch(
+        &self,
+        repo_path: &str,
+        engine: &AntEngine,
+        store: Arc<dyn MetaStore>,
+        notify: Notifier,
+    ) -> Result<bool, String> {
+        let key = registry_key(repo_path);
+        let mut services = crate::sync::lock(&self.services);
+        if services.contains_key(&key) {
+            return Ok(false);
+        }
+        let service = NotificationService::start(repo_path, engine, store, notify);
+        services.insert(key, service);
+        Ok(true)
+    }
+
+    pub fn unwatch(&self, repo_path: &str) -> Result<bool, String> {
+        let mut services = crate::sync::lock(&self.services);
+        Ok(services.remove(&registry_key(repo_path)).is_some())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn temp_store(label: &str) -> (crate::test_support::TempDir, Arc<dyn MetaStore>) {
+        let dir = crate::test_support::temp_dir(&format!("notify-{label}"));
+        let store = crate::store::open(dir.as_str()).expect("open store");
+        (dir, store)
+    }
+
+    #[test]
+    fn only_interesting_events_notify() {
+        let completed = SystemEvent::GoalStatusChanged {
+            goal_id: "G-1".into(),
+            status: GoalStatus::Completed,
+        };
+        let running = SystemEvent::GoalStatusChanged {
+            goal_id: "G-1".into(),
+            status: GoalStatus::Running,
+        };
+        let commented = SystemEvent::PrStateChanged {
+            pr_id: 3,
+            state: "closed".into(),
+        };
+        let conflict = SystemEvent::MergeConflicted {
+            branch: "main".into(),
+            paths: vec!["a.rs".into(), "b.rs".into()],
+        };
+
+        assert_eq!(
+            for_event("demo", &completed).map(|n| n.kind),
+            Some(NotificationKind::GoalCompleted)
+        );
+        assert!(for_event("demo", &running).is_none());
+        assert!(for_event("demo", &commented).is_none());
+        assert_eq!(
+            for_event("demo", &conflict).expect("conflict").body,
+            "2 files conflict on main in demo"
+        );
+    }
+
+    #[test]
+    fn toggles_are_read_from_settings() {
+        let (_path, store) = temp_store("toggles");
+        assert!(enabled(&store, NotificationKind::PrApproved));
+
+        store
+            .set_bool(NotificationKind::PrApproved.setting_key(), false)
+            .expect("disable");
+        assert!(!enabled(&store, NotificationKind::PrApproved));
+        assert!(enabled(&store, NotificationKind::GoalFailed));
+
+        store.set_bool(ENABLED_KEY, false).expect("disable all");
+        assert!(!enabled(&store, NotificationKind::GoalFailed));
+    }
+
+    #[tokio::test]
+    async fn service_notifies_enabled_kinds_only() {
+        let (dir, store) = temp_store("service");
+        let path = dir.as_str();
+        store
+            .set_bool(NotificationKind::GoalFailed.setting_key(), false)
+            .expect("disable failures");
+        let engine = AntEngine::new();
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let notify: Notifier = Arc::new(move |n: &Notification| {
+            let _ = tx.send(n.clone());
+        });
+
+        let notifications = Notifications::default();
+        assert!(notifications
+            .watch(path, &engine, Arc::clone(&store), Arc::clone(&notify))
+            .expect("watch"));
+        assert!(!notifications
+            .watch(path, &engine, store, notify)
+            .expect("watch again"));
+
+        engine.create_goal("G-1", "fail").expect("goal");
+        engine.create_goal("G-2", "pass").expect("goal");
+        engine
+            .update_goal_status("G-1", GoalStatus::Failed)
+            .expect("fail");
+        engine
+            .update_goal_status("G-2", GoalStatus::Completed)
+            .expect("complete");
+
+        let shown = tokio::time::timeout(Duration::from_secs(5), rx.recv())
+            .await
+            .expect("notification in time")
+            .expect("notification");
+        assert_eq!(shown.kind, NotificationKind::GoalCompleted);
+        assert!(notifications.unwatch(path).expect("unwatch"));
+    }
+}
@@ -0,0 +1,1156 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use crate::auth::credentials::Credentials;
+use crate::conflicts::{self, MergeCommit};
+
+/// Frontend event carrying a [`Progress`]; listeners filter on `operation_id`.
+pub const PROGRESS_EVENT: &str = "operation_progress";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OperationKind {
+    Clone,
+    Fetch,
+    Push,
+    Pull,
+    Rebase,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Phase {
+    Receiving,
+    Indexing,
+    Checkout,
+    Packing,
+    Uploading,
+    Rebasing,
+    Done,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Progress {
+    pub operation_id: String,
+    pub kind: OperationKind,
+    pub phase: Phase,
+    /// Objects (or rebase steps) handled so far, out of `total`.
+    pub current: usize,
+    pub total: usize,
+    pub bytes: usize,
+    pub percent: u8,
+}
+
+pub type ProgressSink = Arc<dyn Fn(&Progress) + Send + Sync>;
+
+/// Cancellation flags of running operations, held in Tauri managed state.
+/// Cloning shares the registry.
+#[derive(Clone, Default)]
+pub struct Operations {
+    running: Arc<Mutex<HashMap<String, Arc<AtomicBool>>>>,
+}
+
+impl Operations {
+    /// Registers `id`; the returned [`Operation`] unregisters it on drop.
+    pub fn begin(
+        &self,
+        id: &str,
+        kind: OperationKind,
+        sink: ProgressSink,
+    ) -> Result<Operation, String> {
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let mut running = self.lock();
+        if running.contains_key(id) {
+            return Err(format!("operation {id} is already running"));
+        }
+        running.insert(id.to_string(), Arc::clone(&cancelled));
+        Ok(Operation {
+            id: id.to_string(),
+            kind,
+            cancelled,
+            registry: self.clone(),
+            sink,
+            last: Mutex::new(None),
+        })
+    }
+
+    /// Asks operation `id` to stop at its next progress callback; `false` if
+    /// it isn't running.
+    pub fn cancel(&self, id: &str) -> Result<bool, String> {
+        Ok(match self.lock().get(id) {
+            Some(flag) => {
+                flag.store(true, Ordering::SeqCst);
+                true
+            }
+            None => false,
+        })
+    }
+
+    pub fn running(&self) -> Result<Vec<String>, String> {
+        let mut ids: Vec<String> = self.lock().keys().cloned().collect();
+        ids.sort();
+        Ok(ids)
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, Arc<AtomicBool>>> {
+        crate::sync::lock(&self.running)
+    }
+}
+
+pub struct Operation {
+    id: String,
+    kind: OperationKind,
+    cancelled: Arc<AtomicBool>,
+    registry: Operations,
+    sink: ProgressSink,
+    /// Last `(phase, percent)` sent; callbacks fire per object, the UI only
+    /// needs whole percents.
+    last: Mutex<Option<(Phase, u8)>>,
+}
+
+impl Drop for Operation {
+    fn drop(&mut self) {
+        // Even after a panic elsewhere, or the id would stay taken.
+        self.registry.lock().remove(&self.id);
+    }
+}
+
+impl Operation {
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Sends progress unless it would repeat the last phase and percent.
+    /// Returns `false` once cancelled, the value git2 callbacks expect.
+    fn report(&self, phase: Phase, current: usize, total: usize, bytes: usize) -> bool {
+        let percent = match total {
+            0 => 0,
+            total => (current.min(total) * 100 / total) as u8,
+        };
+        let changed = {
+            let mut last = crate::sync::lock(&self.last);
+            let changed = *last != Some((phase, percent));
+            *last = Some((phase, percent));
+            changed
+        };
+        if changed {
+            (self.sink)(&Progress {
+                operation_id: self.id.clone(),
+                kind: self.kind,
+                phase,
+                current,
+                total,
+                bytes,
+                percent,
+            });
+        }
+        !self.is_cancelled()
+    }
+
+    fn check(&self) -> Result<(), String> {
+        if self.is_cancelled() {
+            Err(format!("operation {} cancelled", self.id))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Prefers the cancellation message over git2's "callback returned" one.
+    fn fail(&self, what: &str) -> impl Fn(git2::Error) -> String + '_ {
+        let what = what.to_string();
+        move |e| match self.check() {
+            Err(cancelled) => cancelled,
+            Ok(()) => format!("{what}: {e}"),
+        }
+    }
+
+    fn callbacks(&self, mut credentials: Credentials) -> git2::RemoteCallbacks<'_> {
+        let mut callbacks = git2::RemoteCallbacks::new();
+        callbacks.transfer_progress(|stats| {
+            let (phase, current) = if stats.received_objects() < stats.total_objects() {
+                (Phase::Receiving, stats.received_objects())
+            } else {
+                (Phase::Indexing, stats.indexed_deltas())
+            };
+            let total = match phase {
+                Phase::Indexing => stats.total_deltas(),
+                _ => stats.total_objects(),
+            };
+            self.report(phase, current, total, stats.received_bytes())
+        });
+        callbacks.pack_progress(|_, current, total| {
+            self.report(Phase::Packing, current, total, 0);
+        });
+        callbacks.push_transfer_progress(|current, total, bytes| {
+            self.report(Phase::Uploading, current, total, bytes);
+        });
+        callbacks
+            .credentials(move |url, username, allowed| credentials.next(url, username, allowed));
+        callbacks.push_negotiation(|_| {
+            self.check()
+                .map_err(|message| git2::Error::from_str(&message))
+        });
+        callbacks
+    }
+}
+
+/// Clones `url` into `path`, reporting transfer and checkout progress.
+pub fn clone(op: &Operation, url: &str, path: &Path) -> Result<(), String> {
+    op.check()?;
+    let mut fetch = git2::FetchOptions::new();
+    fetch.remote_callbacks(op.callbacks(Credentials::for_clone()?));
+    let mut checkout = git2::build::CheckoutBuilder::new();
+    checkout.progress(|_, current, total| {
+        op.report(Phase::Checkout, current, total, 0);
+    });
+    git2::build::RepoBuilder::new()
+        .fetch_options(fetch)
+        .with_checkout(checkout)
+        .clone(url, path)
+        .map_err(op.fail(&format!("failed to clone {url}")))?;
+    op.check()?;
+    op.report(Phase::Done, 1, 1, 0);
+    Ok(())
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RefUpdate {
+    pub name: String,
+    /// `None` when the fetch created the ref.
+    pub old: Option<String>,
+    /// `None` when the fetch pruned it.
+    pub new: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FetchSummary {
+    pub remote: String,
+    /// Local refs the fetch moved, created or pruned, in the order git
+    /// updated them.
+    pub refs: Vec<RefUpdate>,
+    pub received_objects: usize,
+    pub total_objects: usize,
+    /// Objects the remote sent that were already here.
+    pub local_objects: usize,
+    pub received_bytes: usize,
+}
+
+/// Fetches `remote`, with `refspecs` or else its configured ones. `prune`
+/// deletes remote-tracking refs the remote no longer has.
+pub fn fetch(
+    op: &Operation,
+    repo: &git2::Repository,
+    remote: &str,
+    refspecs: &[String],
+    prune: bool,
+) -> Result<FetchSummary, String> {
+    let summary = fetch_refs(op, repo, remote, refspecs, prune)?;
+    op.report(Phase::Done, 1, 1, 0);
+    Ok(summary)
+}
+
+/// [`fetch`] without reporting it done, for operations that go on.
+fn fetch_refs(
+    op: &Operation,
+    repo: &git2::Repository,
+    remote: &str,
+    refspecs: &[String],
+    prune: bool,
+) -> Result<FetchSummary, String> {
+    op.check()?;
+    let mut remote_handle = repo
+        .find_remote(remote)
+        .map_err(|e| format!("unknown remote '{remote}': {e}"))?;
+    let mut refs = Vec::new();
+    let mut callbacks = op.callbacks(Credentials::for_remote(repo, remote)?);
+    callbacks.update_tips(|name, old, new| {
+        let id = |oid: git2::Oid| (!oid.is_zero()).then(|| oid.to_string());
+        refs.push(RefUpdate {
+            name: name.to_string(),
+            old: id(old),
+            new: id(new),
+        });
+        true
+    });
+    let mut options = git2::FetchOptions::new();
+    options.remote_callbacks(callbacks);
+    if prune {
+        options.prune(git2::FetchPrune::On);
+    }
+    remote_handle
+        .fetch(refspecs, Some(&mut options), None)
+        .map_err(op.fail(&format!("failed to fetch {remote}")))?;
+    // Ends the callbacks' borrow of `refs`.
+    drop(options);
+    let stats = remote_handle.stats();
+    Ok(FetchSummary {
+        remote: remote.to_string(),
+        refs,
+        received_objects: stats.received_objects(),
+        total_objects: stats.total_objects(),
+        local_objects: stats.local_objects(),
+        received_bytes: stats.received_bytes(),
+    })
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PushedRef {
+    /// The ref on the remote.
+    pub name: String,
+    /// `None` when the push created the ref.
+    pub old: Option<String>,
+    /// `None` when the push deleted it.
+    pub new: Option<String>,
+    /// Why the remote refused this ref; the others may still have gone in.
+    pub rejected: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PushSummary {
+    pub remote: String,
+    pub refs: Vec<PushedRef>,
+}
+
+impl PushSummary {
+    pub fn rejected(&self) -> impl Iterator<Item = &PushedRef> {
+        self.refs.iter().filter(|pushed| pushed.rejected.is_some())
+    }
+}
+
+/// Pushes `refspecs` to `remote`. With `lease` every ref is forced, but
+/// only while the remote still has it where our remote-tracking ref says,
+/// or doesn't have it when we track none: `git push --force-with-lease`.
+/// Cancelling after negotiation lets the upload finish; a push can't be
+/// interrupted halfway.
+pub fn push(
+    op: &Operation,
+    repo: &git2::Repository,
+    remote: &str,
+    refspecs: &[String],
+    lease: bool,
+) -> Result<PushSummary, String> {
+    op.check()?;
+    let mut remote_handle = repo
+        .find_remote(remote)
+        .map_err(|e| format!("unknown remote '{remote}': {e}"))?;
+    let tracking = repo
+        .find_remote(remote)
+        .map_err(|e| format!("unknown remote '{remote}': {e}"))?;
+    let refspecs: Vec<String> = refspecs
+        .iter()
+        .map(|spec| match lease && !spec.starts_with('+') {
+            true => format!("+{spec}"),
+            false => spec.clone(),
+        })
+        .collect();
+    let refs = std::cell::RefCell::new(Vec::new());
+    // libgit2 may overwrite a callback's error message before returning.
+    let stale = std::cell::RefCell::new(None);
+    let mut callbacks = op.callbacks(Credentials::for_remote(repo, remote)?);
+    // Replaces the cancellation check `callbacks` installs, so repeats it.
+    callbacks.push_negotiation(|updates| {
+        op.check()
+            .map_err(|message| git2::Error::from_str(&message))?;
+        for update in updates {
+            let name = update.dst_refname().unwrap_or_default().to_string();
+            if lease {
+                let expected = tracked(repo, &tracking, &name);
+                let actual = Some(update.src()).filter(|oid| !oid.is_zero());
+                if actual != expected {
+                    let show = |oid: Option<git2::Oid>| oid.map_or("nothing".to_string(), |oid| oid.to_string());
+                    let message = format!(
+                        "stale info for {name}: {remote} has {}, not {} as last fetched; fetch and check before forcing",
+                        show(actual),
+                        show(expected)
+                    );
+                    let error = git2::Error::from_str(&message);
+                    *stale.borrow_mut() = Some(message);
+                    return Err(error);
+                }
+            }
+            let id = |oid: git2::Oid| (!oid.is_zero()).then(|| oid.to_string());
+            refs.borrow_mut().push(PushedRef {
+                name,
+                old: id(update.src()),
+                new: id(update.dst()),
+                rejected: None,
+            });
+        }
+        Ok(())
+    });
+    callbacks.push_update_reference(|name, status| {
+        if let Some(status) = status {
+            let mut refs = refs.borrow_mut();
+            match refs.iter_mut().find(|pushed| pushed.name == name) {
+                Some(pushed) => pushed.rejected = Some(status.to_string()),
+                None => refs.push(PushedRef {
+                    name: name.to_string(),
+                    old: None,
+                    new: None,
+                    rejected: Some(status.to_string()),
+                }),
+            }
+        }
+        Ok(())
+    });
+    let mut options = git2::PushOptions::new();
+    options.remote_callbacks(callbacks);
+    remote_handle
+        .push(&refspecs, Some(&mut options))
+        .map_err(|e| match (stale.borrow_mut().take(), e.code()) {
+            (Some(stale), _) => stale,
+            (None, git2::ErrorCode::NotFastForward) => format!(
+                "{remote} rejected the push: not a fast-forward; fetch and integrate its changes, or push with lease"
+            ),
+            (None, _) => op.fail(&format!("failed to push to {remote}"))(e),
+        })?;
+    // Ends the callbacks' borrow of `refs`.
+    drop(options);
+    op.report(Phase::Done, 1, 1, 0);
+    Ok(PushSummary {
+        remote: remote.to_string(),
+        refs: refs.into_inner(),
+    })
+}
+
+/// Where the remote-tracking ref for `remote`'s ref `name` points.
+fn tracked(repo: &git2::Repository, remote: &git2::Remote, name: &str) -> Option<git2::Oid> {
+    let tracking = remote
+        .refspecs()
+        .filter(|spec| spec.direction() == git2::Direction::Fetch)
+        .find(|spec| spec.src_matches(name))?
+        .transform(name)
+        .ok()?;
+    repo.refname_to_id(tracking.as_str()?).ok()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "outcome", rename_all = "snake_case")]
+pub enum PullOutcome {
+    UpToDate,
+    FastForwarded {
+        from: String,
+        to: String,
+    },
+    Merged {
+        commit: MergeCommit,
+    },
+    /// The merge stopped with these files conflicted and is left in
+    /// progress for the merge editor.
+    Conflicts {
+        files: Vec<String>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PullSummary {
+    pub branch: String,
+    /// The remote-tracking ref merged in.
+    pub upstream: String,
+    pub fetch: FetchSummary,
+    #[serde(flatten)]
+    pub outcome: PullOutcome,
+}
+
+/// Fetches, then fast-forwards the current branch to its upstream or
+/// merges the upstream in. `remote` defaults to the branch's configured
+/// one, else `origin`, and the upstream to `<remote>/<branch>` when none
+/// is configured for it. Local changes the update would overwrite refuse
+/// it before anything is touched.
+pub fn pull(
+    op: &Operation,
+    repo: &git2::Repository,
+    remote: Option<&str>,
+) -> Result<PullSummary, String> {
+    let head = repo
+        .head()
+        .ok()
+        .filter(|head| head.is_branch())
+        .ok_or_else(|| "HEAD is not on a branch; check one out to pull into".to_string())?;
+    let refname = head.name().unwrap_or_default().to_string();
+    let branch = refname.trim_start_matches("refs/heads/").to_string();
+    let configured = repo
+        .branch_upstream_remote(&refname)
+        .ok()
+        .and_then(|name| name.as_str().map(str::to_string));
+    let remote = remote
+        .map(str::to_string)
+        .or_else(|| configured.clone())
+        .unwrap_or_else(|| "origin".to_string());
+    let fetch = fetch_refs(op, repo, &remote, &[], false)?;
+    op.check()?;
+
+    let upstream = match configured.as_deref() == Some(remote.as_str()) {
+        true => repo
+            .branch_upstream_name(&refname)
+            .ok()
+            .and_then(|name| name.as_str().map(str::to_string)),
+        false => None,
+    }
+    .unwrap_or_else(|| format!("refs/remotes/{remote}/{branch}"));
+    let theirs = repo
+        .find_reference(&upstream)
+        .and_then(|reference| repo.reference_to_annotated_commit(&reference))
+        .map_err(|e| format!("no upstream {upstream} to pull from: {e}"))?;
+    let (analysis, _) = repo
+        .merge_analysis(&[&theirs])
+        .map_err(|e| format!("failed to compare {branch} with {upstream}: {e}"))?;
+    let short = upstream.trim_start_matches("refs/remotes/");
+    let outcome = if analysis.is_up_to_date() {
+        PullOutcome::UpToDate
+    } else if analysis.is_fast_forward() {
+        let from = head.target().map(|oid| oid.to_string()).unwrap_or_default();
+        let target = repo
+            .find_commit(theirs.id())
+            .map_err(|e| format!("failed to read {short}: {e}"))?;
+        repo.checkout_tree(
+            target.as_object(),
+            Some(git2::build::CheckoutBuilder::new().safe()),
+        )
+        .map_err(|e| format!("failed to fast-forward to {short}: {e}"))?;
+        repo.reference(
+            &refname,
+            theirs.id(),
+            true,
+            &format!("pull: fast-forward to {short}"),
+        )
+        .map_err(|e| format!("failed to update {refname}: {e}"))?;
+        PullOutcome::FastForwarded {
+            from,
+            to: theirs.id().to_string(),
+        }
+    } else {
+        repo.merge(
+            &[&theirs],
+            None,
+            Some(git2::build::CheckoutBuilder::new().safe()),
+        )
+        .map_err(|e| format!("failed to merge {short}: {e}"))?;
+        let conflicted = repo
+            .index()
+            .map_err(|e| format!("failed to open index: {e}"))?
+            .has_conflicts();
+        if conflicted {
+            let files = conflicts::conflicts_list(repo)?
+                .into_iter()
+                .map(|file| file.path)
+                .collect();
+            PullOutcome::Conflicts { files }
+        } else {
+            PullOutcome::Merged {
+                commit: conflicts::merge_finalize(repo, None)?,
+            }
+        }
+    };
+    op.report(Phase::Done, 1, 1, 0);
+    Ok(PullSummary {
+        branch,
+        upstream: short.to_string(),
+        fetch,
+        outcome,
+    })
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RebaseSummary {
+    pub applied: usize,
+    pub head: String,
+}
+
+/// One commit replayed by [`rebase_onto`] or [`rebase_continue`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RebaseStep {
+    /// The original commit.
+    pub commit: String,
+    /// Its replayed copy; `None` when the change was already upstream.
+    pub rebased: Option<String>,
+    pub current: usize,
+    pub total: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "outcome", rename_all = "snake_case")]
+pub enum RebaseOutcome {
+    Completed {
+        head: String,
+    },
+    /// Stopped at `commit`. The rebase is left in progress: resolve and
+    /// stage `files`, then [`rebase_continue`], or [`rebase_abort`].
+    Conflicts {
+        commit: String,
+        files: Vec<String>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RebaseRun {
+    /// The branch being rebased; `None` for a detached HEAD.
+    pub branch: Option<String>,
+    /// Commits replayed by this call, in order.
+    pub steps: Vec<RebaseStep>,
+    pub total: usize,
+    #[serde(flatten)]
+    pub outcome: RebaseOutcome,
+}
+
+/// Replays the current branch onto `upstream`, one progress step per
+/// commit. Conflicts and cancellation abort the rebase, leaving the branch
+/// where it was.
+pub fn rebase(
+    op: &Operation,
+    repo: &git2::Repository,
+    upstream: &str,
+) -> Result<RebaseSummary, String> {
+    let run = rebase_onto(op, repo, upstream, &mut |_| {})?;
+    match run.outcome {
+        RebaseOutcome::Completed { head } => Ok(RebaseSummary {
+            applied: run.steps.len(),
+            head,
+        }),
+        RebaseOutcome::Conflicts { commit, .. } => {
+            rebase_abort(repo)?;
+            Err(format!(
+                "commit {commit} conflicts with {upstream}; rebase aborted"
+            ))
+        }
+    }
+}
+
+/// Replays the current branch onto `onto`, handing each replayed commit
+/// to `on_step`. A conflict stops the rebase and leaves it in progress;
+/// cancellation and other failures abort it, leaving the branch where it
+/// was.
+pub fn rebase_onto(
+    op: &Operation,
+    repo: &git2::Repository,
+    onto: &str,
+    on_step: &mut dyn FnMut(&RebaseStep),
+) -> Result<RebaseRun, String> {
+    op.check()?;
+    if repo.state() != git2::RepositoryState::Clean {
+        return Err(format!(
+            "the repository is in the middle of a {:?}; finish or abort it first",
+            repo.state()
+        ));
+    }
+    let target = repo
+        .revparse_single(onto)
+        .and_then(|object| object.peel_to_commit())
+        .and_then(|commit| repo.find_annotated_commit(commit.id()))
+        .map_err(|e| format!("unknown upstream '{onto}': {e}"))?;
+    let mut rebase = repo
+        .rebase(None, Some(&target), None, None)
+        .map_err(|e| format!("failed to start rebase: {e}"))?;
+    replay(op, repo, &mut rebase, false, on_step)
+}
+
+/// Resumes a rebase [`rebase_onto`] stopped, once the conflicted files are
+/// resolved and staged: commits the stopped step and replays the rest.
+pub fn rebase_continue(
+    op: &Operation,
+    repo: &git2::Repository,
+    on_step: &mut dyn FnMut(&RebaseStep),
+) -> Result<RebaseRun, String> {
+    op.check()?;
+    let mut rebase = open_rebase(repo)?;
+    let resume = rebase.operation_current().is_some();
+    replay(op, repo, &mut rebase, resume, on_step)
+}
+
+/// Abandons the rebase in progress, putting the branch back where it was.
+/// Returns the restored head.
+pub fn rebase_abort(repo: &git2::Repository) -> Result<String, String> {
+    open_rebase(repo)?
+        .abort()
+        .map_err(|e| format!("failed to abort rebase: {e}"))?;
+    Ok(head_id(repo))
+}
+
+fn open_rebase(repo: &git2::Repository) -> Result<git2::Rebase<'_>, String> {
+    repo.open_rebase(None).map_err(|e| match e.code() {
+        git2::ErrorCode::NotFound => "no rebase in progress".to_string(),
+        _ => format!("failed to open the rebase in progress: {e}"),
+    })
+}
+
+fn head_id(repo: &git2::Repository) -> String {
+    repo.head()
+        .ok()
+        .and_then(|h| h.target())
+        .map(|oid| oid.to_string())
+        .unwrap_or_default()
+}
+
+/// Commits `rebase`'s steps from the current one, when `resume`, or the
+/// next. Stops at the first conflict.
+fn replay(
+    op: &Operation,
+    repo: &git2::Repository,
+    rebase: &mut git2::Rebase,
+    resume: bool,
+    on_step: &mut dyn FnMut(&RebaseStep),
+) -> Result<RebaseRun, String> {
+    let signature = repo
+        .signature()
+        .or_else(|_| git2::Signature::now("GitForge", "gitforge@localhost"))
+        .map_err(|e| format!("failed to create signature: {e}"))?;
+    let total = rebase.len();
+    let branch = rebase
+        .orig_head_name()
+        .map(|name| name.strip_prefix("refs/heads/").unwrap_or(name).to_string());
+    let mut steps = Vec::new();
+    let mut pending = resume;
+    loop {
+        if !pending {
+            match rebase.next() {
+                None => break,
+                Some(Ok(_)) => {}
+                Some(Err(e)) => {
+                    let _ = rebase.abort();
+                    return Err(format!("failed to apply commit: {e}"));
+                }
+            }
+        }
+        pending = false;
+        let step = commit_step(repo, rebase, &signature).and_then(|step| {
+            if let Ok(step) = &step {
+                op.report(Phase::Rebasing, step.current, total, 0);
+                on_step(step);
+                op.check()?;
+            }
+            Ok(step)
+        });
+        match step {
+            Ok(Ok(step)) => steps.push(step),
+            Ok(Err(outcome)) => {
+                return Ok(RebaseRun {
+                    branch,
+                    steps,
+                    total,
+                    outcome,
+                })
+            }
+            Err(message) => {
+                let _ = rebase.abort();
+                return Err(message);
+            }
+        }
+    }
+    rebase
+        .finish(Some(&signature))
+        .map_err(|e| format!("failed to finish rebase: {e}"))?;
+    op.report(Phase::Done, total, total, 0);
+    Ok(RebaseRun {
+        branch,
+        steps,
+        total,
+        outcome: RebaseOutcome::Completed {
+            head: head_id(repo),
+        },
+    })
+}
+
+/// Commits the current step from the index, or the conflicts keeping it
+/// from being committed.
+fn commit_step(
+    repo: &git2::Repository,
+    rebase: &mut git2::Rebase,
+    signature: &git2::Signature,
+) -> Result<Result<RebaseStep, RebaseOutcome>, String> {
+    let current = rebase
+        .operation_current()
+        .ok_or_else(|| "the rebase has no current step".to_string())?;
+    let commit = rebase
+        .nth(current)
+        .map(|operation| operation.id().to_string())
+        .ok_or_else(|| format!("rebase step {current} is missing"))?;
+    let index = repo
+        .index()
+        .map_err(|e| format!("failed to read index: {e}"))?;
+    if index.has_conflicts() {
+        return Ok(Err(RebaseOutcome::Conflicts {
+            commit,
+            files: crate::rebase_plan::conflicts(&index),
+        }));
+    }
+    let rebased = match rebase.commit(None, signature, None) {
+        Ok(oid) => Some(oid.to_string()),
+        // The change is already upstream.
+        Err(e) if e.code() == git2::ErrorCode::Applied => None,
+        Err(e) => return Err(format!("failed to commit rebased {commit}: {e}")),
+    };
+    Ok(Ok(RebaseStep {
+        commit,
+        rebased,
+        current: current + 1,
+        total: rebase.len(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::temp_path;
+    use std::fs;
+
+    fn commit_file(repo: &git2::Repository, name: &str, text: &str, message: &str) -> git2::Oid {
+        let dir = repo.workdir().expect("workdir");
+        fs::write(dir.join(name), text).expect("write file");
+        let mut index = repo.index().expect("index");
+        index.add_path(Path::new(name)).expect("stage");
+        index.write().expect("write index");
+        let tree = repo
+            .find_tree(index.write_tree().expect("write tree"))
+            .expect("find tree");
+        let sig = git2::Signature::now("GitForge Test", "test@gitforge.dev").expect("signature");
+        let parent = repo.head().ok().and_then(|h| h.peel_to_commit().ok());
+        let parents: Vec<&git2::Commit> = parent.iter().collect();
+        repo.commit(Some("HEAD"), &sig, &sig, message, &tree, &parents)
+            .expect("commit")
+    }
+
+    fn recorder() -> (ProgressSink, Arc<Mutex<Vec<Progress>>>) {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let sink_seen = Arc::clone(&seen);
+        let sink: ProgressSink = Arc::new(move |p: &Progress| {
+            sink_seen.lock().unwrap().push(p.clone());
+        });
+        (sink, seen)
+    }
+
+    #[test]
+    fn clone_reports_progress_and_unregisters() {
+        let origin = temp_path("ops-origin");
+        let repo = git2::Repository::init(&origin).expect("init origin");
+        for i in 0..3 {
+            commit_file(&repo, &format!("f{i}.txt"), &"x".repeat(i * 10), "add");
+        }
+
+        let operations = Operations::default();
+        let (sink, seen) = recorder();
+        let op = operations
+            .begin("clone-1", OperationKind::Clone, sink)
+            .expect("begin");
+        assert!(operations
+            .begin("clone-1", OperationKind::Clone, Arc::new(|_: &Progress| {}))
+            .is_err());
+        assert_eq!(operations.running().unwrap(), ["clone-1"]);
+
+        let url = format!("file://{}", origin.display());
+        let dest = temp_path("ops-clone");
+        clone(&op, &url, &dest).expect("clone");
+        drop(op);
+
+        assert!(dest.join("f2.txt").exists());
+        let seen = seen.lock().unwrap();
+        assert_eq!(seen.last().map(|p| p.phase), Some(Phase::Done));
+        assert!(seen.iter().all(|p| p.operation_id == "clone-1"));
+        assert!(operations.running().unwrap().is_empty());
+        assert!(!operations.cancel("clone-1").unwrap());
+    }
+
+    #[test]
+    fn fetch_reports_moved_created_and_pruned_refs() {
+        let origin = temp_path("ops-fetch-origin");
+        let upstream = git2::Repository::init(&origin).expect("init origin");
+        let first = commit_file(&upstream, "a.txt", "a", "first");
+        let dest = temp_path("ops-fetch");
+        let repo =
+            git2::Repository::clone(&format!("file://{}", origin.display()), &dest).expect("clone");
+        let branch = upstream.head().unwrap().shorthand().unwrap().to_string();
+
+        let second = commit_file(&upstream, "b.txt", "b", "second");
+        upstream
+            .branch("topic", &upstream.find_commit(second).unwrap(), false)
+            .expect("branch");
+        let operations = Operations::default();
+        let run = |refspecs: &[String], prune: bool| {
+            let op = operations
+                .begin("fetch-1", OperationKind::Fetch, Arc::new(|_: &Progress| {}))
+                .expect("begin");
+            fetch(&op, &repo, "origin", refspecs, prune).expect("fetch")
+        };
+        let summary = run(&[], false);
+        let tracking = format!("refs/remotes/origin/{branch}");
+        assert_eq!(
+            summary.refs,
+            [
+                RefUpdate {
+                    name: tracking.clone(),
+                    old: Some(first.to_string()),
+                    new: Some(second.to_string()),
+                },
+                RefUpdate {
+                    name: "refs/remotes/origin/topic".to_string(),
+                    old: None,
+                    new: Some(second.to_string()),
+                },
+            ]
+        );
+        assert!(summary.received_objects > 0);
+        assert!(run(&[], false).refs.is_empty());
+
+        upstream
+            .find_branch("topic", git2::BranchType::Local)
+            .unwrap()
+            .delete()
+            .expect("delete");
+        let pruned = run(&[], true);
+        assert_eq!(
+            pruned.refs,
+            [RefUpdate {
+                name: "refs/remotes/origin/topic".to_string(),
+                old: Some(second.to_string()),
+                new: None,
+            }]
+        );
+        assert!(fetch(
+            &operations
+                .begin("fetch-2", OperationKind::Fetch, Arc::new(|_: &Progress| {}))
+                .unwrap(),
+            &repo,
+            "nowhere",
+            &[],
+            false
+        )
+        .is_err());
+        let _ = (fs::remove_dir_all(origin), fs::remove_dir_all(dest));
+    }
+
+    #[test]
+    fn push_reports_refs_and_honours_the_lease() {
+        let origin = temp_path("ops-push-origin");
+        let bare = git2::Repository::init_bare(&origin).expect("init origin");
+        let dir = temp_path("ops-push");
+        let repo = git2::Repository::init(&dir).expect("init");
+        let url = format!("file://{}", origin.display());
+        repo.remote("origin", &url).expect("remote");
+        let first = commit_file(&repo, "a.txt", "a", "first");
+        let branch = repo.head().unwrap().name().unwrap().to_string();
+        let refspecs = [format!("{branch}:{branch}")];
+        let operations = Operations::default();
+        let run = |repo: &git2::Repository, lease: bool| {
+            let op = operations
+                .begin("push-1", OperationKind::Push, Arc::new(|_: &Progress| {}))
+                .expect("begin");
+            push(&op, repo, "origin", &refspecs, lease)
+        };
+
+        let summary = run(&repo, false).expect("push");
+        assert_eq!(
+            summary.refs,
+            [PushedRef {
+                name: branch.clone(),
+                old: None,
+                new: Some(first.to_string()),
+                rejected: None,
+            }]
+        );
+        assert_eq!(bare.refname_to_id(&branch).unwrap(), first);
+        let short = branch.trim_start_matches("refs/heads/");
+        let tracking = format!("refs/remotes/origin/{short}");
+        assert_eq!(repo.refname_to_id(&tracking).unwrap(), first);
+
+        // Someone else pushes on top; we amend ours without fetching.
+        let other_dir = temp_path("ops-push-other");
+        let other = git2::Repository::clone(&url, &other_dir).expect("clone");
+        let theirs = commit_file(&other, "b.txt", "b", "theirs");
+        run(&other, false).expect("push theirs");
+        repo.find_reference(&branch)
+            .unwrap()
+            .set_target(first, "reset")
+            .expect("reset");
+        let ours = commit_file(&repo, "c.txt", "c", "ours");
+        assert!(run(&repo, false).is_err_and(|e| e.contains("not a fast-forward")));
+        assert!(run(&repo, true).is_err_and(|e| e.contains("stale info")));
+        assert_eq!(bare.refname_to_id(&branch).unwrap(), theirs);
+
+        repo.find_remote("origin")
+            .unwrap()
+            .fetch::<&str>(&[], None, None)
+            .expect("fetch");
+        let forced = run(&repo, true).expect("push with lease");
+        assert_eq!(forced.refs[0].old, Some(theirs.to_string()));
+        assert_eq!(forced.rejected().count(), 0);
+        assert_eq!(bare.refname_to_id(&branch).unwrap(), ours);
+        let _ = (
+            fs::remove_dir_all(origin),
+            fs::remove_dir_all(dir),
+            fs::remove_dir_all(other_dir),
+        );
+    }
+
+    #[test]
+    fn pull_fast_forwards_merges_and_stops_on_conflicts() {
+        let origin = temp_path("ops-pull-origin");
+        let upstream = git2::Repository::init(&origin).expect("init origin");
+        commit_file(&upstream, "a.txt", "a\n", "first");
+        let dest = temp_path("ops-pull");
+        let repo =
+            git2::Repository::clone(&format!("file://{}", origin.display()), &dest).expect("clone");
+        let operations = Operations::default();
+        let run = |remote: Option<&str>| {
+            let op = operations
+                .begin("pull-1", OperationKind::Pull, Arc::new(|_: &Progress| {}))
+                .expect("begin");
+            pull(&op, &repo, remote).expect("pull")
+        };
+        assert_eq!(run(None).outcome, PullOutcome::UpToDate);
+
+        let second = commit_file(&upstream, "b.txt", "b\n", "second");
+        let forwarded = run(None);
+        assert!(matches!(
+            &forwarded.outcome,
+            PullOutcome::FastForwarded { to, .. } if *to == second.to_string()
+        ));
+        assert_eq!(forwarded.fetch.refs.len(), 1);
+        assert!(dest.join("b.txt").exists());
+
+        commit_file(&upstream, "b.txt", "b2\n", "theirs");
+        commit_file(&repo, "c.txt", "c\n", "ours");
+        let merged = run(Some("origin"));
+        let PullOutcome::Merged { commit } = &merged.outcome else {
+            panic!("expected a merge, got {:?}", merged.outcome);
+        };
+        assert_eq!(commit.parents.len(), 2);
+        assert_eq!(
+            repo.head().unwrap().target().unwrap().to_string(),
+            commit.id
+        );
+        assert_eq!(fs::read_to_string(dest.join("b.txt")).unwrap(), "b2\n");
+
+        commit_file(&upstream, "a.txt", "theirs\n", "edit a");
+        commit_file(&repo, "a.txt", "ours\n", "edit a too");
+        assert_eq!(
+            run(None).outcome,
+            PullOutcome::Conflicts {
+                files: vec!["a.txt".to_string()]
+            }
+        );
+        assert_eq!(repo.state(), git2::RepositoryState::Merge);
+        let _ = (fs::remove_dir_all(origin), fs::remove_dir_all(dest));
+    }
+
+    #[test]
+    fn cancelled_rebase_is_aborted() {
+        let dir = temp_path("ops-rebase");
+        let repo = git2::Repository::init(&dir).expect("init");
+        let base = commit_file(&repo, "base.txt", "base", "base");
+        let main_ref = repo.head().unwrap().name().unwrap().to_string();
+        commit_file(&repo, "main.txt", "main", "main work");
+
+        repo.branch("topic", &repo.find_commit(base).unwrap(), false)
+            .expect("branch");
+        repo.set_head("refs/heads/topic").expect("switch");
+        repo.checkout_head(Some(git2::build::CheckoutBuilder::new().force()))
+            .expect("checkout");
+        let topic_tip = commit_file(&repo, "a.txt", "a", "topic a");
+        commit_file(&repo, "b.txt", "b", "topic b");
+        let before = repo.head().unwrap().target().unwrap();
+
+        let operations = Operations::default();
+        let cancel_on_first = operations.clone();
+        let sink: ProgressSink = Arc::new(move |p: &Progress| {
+            if p.phase == Phase::Rebasing {
+                cancel_on_first.cancel(&p.operation_id).unwrap();
+            }
+        });
+        let op = operations
+            .begin("rebase-1", OperationKind::Rebase, sink)
+            .expect("begin");
+        let err = rebase(&op, &repo, &main_ref).expect_err("cancelled");
+        assert_eq!(err, "operation rebase-1 cancelled");
+        assert_eq!(repo.head().unwrap().target(), Some(before));
+        assert_eq!(repo.state(), git2::RepositoryState::Clean);
+        drop(op);
+
+        let (sink, seen) = recorder();
+        let op = operations
+            .begin("rebase-2", OperationKind::Rebase, sink)
+            .expect("begin");
+        let summary = rebase(&op, &repo, &main_ref).expect("rebase");
+        assert_eq!(summary.applied, 2);
+        let rebased = repo.head().unwrap().peel_to_commit().unwrap();
+        assert_ne!(rebased.id(), topic_tip);
+        assert!(dir.join("main.txt").exists());
+        let percents: Vec<u8> = seen
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|p| p.phase == Phase::Rebasing)
+            .map(|p| p.percent)
+            .collect();
+        assert_eq!(percents, [50, 100]);
+    }
+
+    #[test]
+    fn conflicted_rebase_stops_and_continues_or_aborts() {
+        let dir = temp_path("ops-rebase-stop");
+        let repo = git2::Repository::init(&dir).expect("init");
+        let base = commit_file(&repo, "a.txt", "base", "base");
+        let main_ref = repo.head().unwrap().name().unwrap().to_string();
+        commit_file(&repo, "a.txt", "main", "main edits a");
+
+        repo.branch("topic", &repo.find_commit(base).unwrap(), false)
+            .expect("branch");
+        repo.set_head("refs/heads/topic").expect("switch");
+        repo.checkout_head(Some(git2::build::CheckoutBuilder::new().force()))
+            .expect("checkout");
+        let clean = commit_file(&repo, "b.txt", "b", "topic adds b");
+        let conflicting = commit_file(&repo, "a.txt", "topic", "topic edits a");
+        commit_file(&repo, "c.txt", "c", "topic adds c");
+        let before = repo.head().unwrap().target().unwrap();
+
+        let operations = Operations::default();
+        let start = |id: &str| {
+            operations
+                .begin(id, OperationKind::Rebase, Arc::new(|_: &Progress| {}))
+                .expect("begin")
+        };
+        let mut seen = Vec::new();
+        let run = rebase_onto(&start("stop-1"), &repo, &main_ref, &mut |step| {
+            seen.push(step.commit.clone())
+        })
+        .expect("rebase");
+        assert_eq!(run.branch.as_deref(), Some("topic"));
+        assert_eq!((run.steps.len(), run.total), (1, 3));
+        assert_eq!(seen, [clean.to_string()]);
+        assert_eq!(
+            run.outcome,
+            RebaseOutcome::Conflicts {
+                commit: conflicting.to_string(),
+                files: vec!["a.txt".to_string()],
+            }
+        );
+        assert!(rebase_onto(&start("stop-2"), &repo, &main_ref, &mut |_| {}).is_err());
+        let again = rebase_continue(&start("stop-3"), &repo, &mut |_| {}).expect("unresolved");
+        assert!(matches!(again.outcome, RebaseOutcome::Conflicts { .. }));
+
+        assert_eq!(rebase_abort(&repo).expect("abort"), before.to_string());
+        assert_eq!(repo.state(), git2::RepositoryState::Clean);
+        assert!(rebase_abort(&repo).is_err_and(|e| e.contains("no rebase")));
+
+        rebase_onto(&start("stop-4"), &repo, &main_ref, &mut |_| {}).expect("rebase");
+        fs::write(dir.join("a.txt"), "resolved").expect("resolve");
+        let mut index = repo.index().expect("index");
+        index.add_path(Path::new("a.txt")).expect("stage");
+        index.write().expect("write index");
+        let run = rebase_continue(&start("stop-5"), &repo, &mut |_| {}).expect("continue");
+        let RebaseOutcome::Completed { head } = &run.outcome else {
+            panic!("expected completed, got {run:?}");
+        };
+        assert_eq!(run.steps.len(), 2);
+        assert_eq!(run.steps[0].commit, conflicting.to_string());
+        assert_eq!(*head, repo.head().unwrap().target().unwrap().to_string());
+        assert_eq!(fs::read_to_string(dir.join("a.txt")).unwrap(), "resolved");
+        assert!(dir.join("c.txt").exists());
+        assert_eq!(repo.state(), git2::RepositoryState::Clean);
+    }
+}
@@ -0,0 +1,223 @@
+use serde::Serialize;
+use std::path::Path;
+
+/// Where a CODEOWNERS file is looked for, in GitHub's order.
+pub const LOCATIONS: [&str; 3] = [".github/CODEOWNERS", "CODEOWNERS", "docs/CODEOWNERS"];
+
+/// Files larger than this are ignored rather than parsed.
+const MAX_BYTES: usize = 3 * 1024 * 1024;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Rule {
+    pub pattern: String,
+    /// `@user`, `@org/team`, or an email; empty un-owns the paths.
+    pub owners: Vec<String>,
+}
+
+/// A parsed CODEOWNERS file. As on GitHub, the last rule matching a path
+/// decides its owners.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Owners {
+    /// The file the rules came from, e.g. `.github/CODEOWNERS`.
+    pub file: String,
+    pub rules: Vec<Rule>,
+}
+
+impl Owners {
+    pub fn parse(file: &str, text: &str) -> Self {
+        let rules = text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .filter_map(|line| {
+                let line = line.split(" #").next().unwrap_or(line);
+                let mut words = line.split_whitespace();
+                let pattern = words.next()?.to_string();
+                Some(Rule {
+                    pattern,
+                    owners: words.map(str::to_string).collect(),
+                })
+            })
+            .collect();
+        Self {
+            file: file.to_string(),
+            rules,
+        }
+    }
+
+    /// The first CODEOWNERS file in `rev`'s tree, or `None` without one.
+    pub fn load(repo: &git2::Repository, rev: &str) -> Result<Option<Self>, String> {
+        let tree = repo
+            .revparse_single(rev)
+            .and_then(|object| object.peel_to_tree())
+            .map_err(|e| format!("failed to resolve {rev}: {e}"))?;
+        for location in LOCATIONS {
+            let Ok(entry) = tree.get_path(Path::new(location)) else {
+                continue;
+            };
+            let blob = entry
+                .to_object(repo)
+                .and_then(|object| object.peel_to_blob())
+                .map_err(|e| format!("failed to read {location} at {rev}: {e}"))?;
+            if blob.size() > MAX_BYTES {
+                continue;
+            }
+            let text = String::from_utf8_lossy(blob.content());
+            return Ok(Some(Self::parse(location, &text)));
+        }
+        Ok(None)
+    }
+
+    /// Owners of a repository-relative path; empty when none match.
+    pub fn owners_of(&self, path: &str) -> &[String] {
+        self.rules
+            .iter()
+            .rev()
+            .find(|rule| matches(&rule.pattern, path))
+            .map_or(&[], |rule| rule.owners.as_slice())
+    }
+
+    /// Everyone owning any of `paths`, in the order first seen.
+    pub fn reviewers<'a>(&self, paths: impl IntoIterator<Item = &'a str>) -> Vec<String> {
+        let mut reviewers: Vec<String> = Vec::new();
+        for path in paths {
+            for owner in self.owners_of(path) {
+                if !reviewers.contains(owner) {
+                    reviewers.push(owner.clone());
+                }
+            }
+        }
+        reviewers
+    }
+
+    /// The owned paths among `paths` that none of their owners approved.
+    pub fn unapproved<'a>(
+        &self,
+        paths: impl IntoIterator<Item = &'a str>,
+        approvers: &[&str],
+    ) -> Vec<String> {
+        paths
+            .into_iter()
+            .filter(|path| {
+                let owners = self.owners_of(path);
+                !owners.is_empty()
+                    && !owners
+                        .iter()
+                        .any(|owner| approvers.iter().any(|who| same_owner(owner, who)))
+            })
+            .map(str::to_string)
+            .collect()
+    }
+}
+
+/// `@ana` and `ana` name the same reviewer. Teams only match a reviewer
+/// recorded under the team's own name.
+fn same_owner(owner: &str, reviewer: &str) -> bool {
+    owner
+        .trim_start_matches('@')
+        .eq_ignore_ascii_case(reviewer.trim_start_matches('@'))
+}
+
+/// Paths a branch changes since it left `to`, like GitHub's PR diff.
+pub fn touched_paths(repo: &git2::Repository, from: &str, to: &str) -> Result<Vec<String>, String> {
+    let commit = |spec: &str| {
+        repo.revparse_single(spec)
+            .and_then(|object| object.peel_to_commit())
+            .map_err(|e| format!("failed to resolve {spec}: {e}"))
+    };
+    let (tip, target) = (commit(from)?, commit(to)?);
+    let base = repo
+        .merge_base(tip.id(), target.id())
+        .and_then(|oid| repo.find_commit(oid))
+        .map_err(|e| format!("failed to find the merge base of {from} and {to}: {e}"))?;
+    let (old, new) = base
+        .tree()
+        .and_then(|old| Ok((old, tip.tree()?)))
+        .map_err(|e| format!("failed to read trees of {to}...{from}: {e}"))?;
+    let diff = repo
+        .diff_tree_to_tree(Some(&old), Some(&new), None)
+        .map_err(|e| format!("failed to diff {to}...{from}: {e}"))?;
+    let mut paths = Vec::new();
+    for delta in diff.deltas() {
+        for file in [delta.old_file(), delta.new_file()] {
+            if let Some(path) = file.path().map(|p| p.to_string_lossy().into_owned()) {
+                if !paths.contains(&path) {
+                    paths.push(path);
+                }
+            }
+        }
+    }
+    Ok(paths)
+}
+
+/// gitignore-style matching: a leading or inner `/` anchors the pattern at
+/// the root, a trailing `/` only matches inside a directory, and a pattern
+/// that names a directory owns everything under it.
+fn matches(pattern: &str, path: &str) -> bool {
+    let directory_only = pattern.ends_with('/');
+    let trimmed = pattern.trim_end_matches('/');
+    let anchored = trimmed.starts_with('/') || trimmed.contains('/');
+    let trimmed = trimmed.trim_start_matches('/');
+    if trimmed.is_empty() {
+        return false;
+    }
+    let pattern = if anchored {
+        trimmed.to_string()
+    } else {
+        format!("**/{trimmed}")
+    };
+    (!directory_only && glob(pattern.as_bytes(), path.as_bytes()))
+        || glob(format!("{pattern}/**").as_bytes(), path.as_bytes())
+}
+
+/// `*` and `?` stay within a path segment; `**` crosses them.
+fn glob(pattern: &[u8], name: &[u8]) -> bool {
+    match pattern {
+        [] => name.is_empty(),
+        [b'*', b'*', b'/', rest @ ..] => {
+            glob(rest, name)
+                || name
+                    .iter()
+                    .enumerate()
+                    .any(|(i, &c)| c == b'/' && glob(rest, &name[i + 1..]))
+        }
+        [b'*', b'*', rest @ ..] => (0..=name.len()).any(|i| glob(rest, &name[i..])),
+        [b'*', rest @ ..] => {
+            let segment = name.iter().position(|&c| c == b'/').unwrap_or(name.len());
+            (0..=segment).any(|i| glob(rest, &name[i..]))
+        }
+        [b'?', rest @ ..] => name.first().is_some_and(|&c| c != b'/') && glob(rest, &name[1..]),
+        [c, rest @ ..] => name.first() == Some(c) && glob(rest, &name[1..]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn last_matching_rule_owns_a_path() {
+        let owners = Owners::parse(
+            "CODEOWNERS",
+            "# Default owners\n\
+             *       @core\n\
+             *.md    @docs # prose\n\
+             /apps/web/ @ana @web-team\n\
+             apps/web/vendor/\n\
+             src/**/test_*.rs @qa\n\
+             build/  @ops\n",
+        );
+        assert_eq!(owners.owners_of("Cargo.toml"), ["@core"]);
+        assert_eq!(owners.owners_of("guide/intro.md"), ["@docs"]);
+        assert_eq!(owners.owners_of("apps/web/index.ts"), ["@ana", "@web-team"]);
+        assert!(owners.owners_of("apps/web/vendor/lib.js").is_empty());
+        assert_eq!(owners.owners_of("src/a/b/test_x.rs"), ["@qa"]);
+        assert_eq!(owners.owners_of("tools/build/out.txt"), ["@ops"]);
+        assert_eq!(owners.owners_of("build"), ["@core"]);
+
+        let touched = ["apps/web/index.ts", "README.md", "apps/web/vendor/lib.js"];
+        assert_eq!(owners.reviewers(touched), ["@ana", "@web-team", "@docs"]);
+        assert_eq!(owners.unapproved(touched, &["ana"]), ["README.md"]);
+        assert!(owners.unapproved(touched, &["ana", "@DOCS"]).is_empty());
+    }
+}
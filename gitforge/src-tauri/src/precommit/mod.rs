@@ -0,0 +1,348 @@
+use serde::{Deserialize, Serialize};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+use crate::sandbox::{self, Sandbox};
+
+/// The pipeline definition, relative to the working tree root.
+pub const CONFIG_PATH: &str = ".gitforge/checks.toml";
+/// Lets `install_hooks` recognize a hook it wrote earlier.
+const HOOK_MARKER: &str = "# Managed by gitforge";
+/// Seconds a check may run when it doesn't set `timeout`.
+const DEFAULT_TIMEOUT: u64 = 300;
+/// Output kept per check; the end is where failures usually are.
+const MAX_OUTPUT: usize = 8 * 1024;
+
+/// One `[[check]]` table:
+///
+/// ```toml
+/// [[check]]
+/// name = "format"
+/// run = "cargo fmt --check"
+/// timeout = 120
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Check {
+    pub name: String,
+    /// Shell command, run from the working tree root.
+    pub run: String,
+    /// Seconds before the check is killed and fails.
+    #[serde(default)]
+    pub timeout: Option<u64>,
+    /// Limits to run under; the file's `[sandbox]` when left out.
+    #[serde(default)]
+    pub sandbox: Option<Sandbox>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Pipeline {
+    #[serde(default)]
+    sandbox: Option<Sandbox>,
+    #[serde(default)]
+    check: Vec<Check>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CheckResult {
+    pub name: String,
+    pub command: String,
+    pub passed: bool,
+    /// `None` when the check was killed or couldn't start.
+    pub exit_code: Option<i32>,
+    pub timed_out: bool,
+    pub duration_ms: u64,
+    /// The end of stdout followed by the end of stderr.
+    pub output: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Report {
+    pub passed: bool,
+    pub results: Vec<CheckResult>,
+}
+
+impl Report {
+    pub fn failed(&self) -> Vec<&CheckResult> {
+        self.results.iter().filter(|r| !r.passed).collect()
+    }
+
+    /// One line naming the failed checks, or `None` when all passed.
+    pub fn failure_summary(&self) -> Option<String> {
+        let failed = self.failed();
+        if failed.is_empty() {
+            return None;
+        }
+        let names: Vec<String> = failed
+            .iter()
+            .map(|r| match (r.timed_out, r.exit_code) {
+                (true, _) => format!("{} (timed out)", r.name),
+                (false, Some(code)) => format!("{} (exit {code})", r.name),
+                (false, None) => format!("{} (didn't run)", r.name),
+            })
+            .collect();
+        Some(format!("pre-commit checks failed: {}", names.join(", ")))
+    }
+}
+
+/// The checks in `workdir`'s `.gitforge/checks.toml`; none without one.
+pub fn load(workdir: &Path) -> Result<Vec<Check>, String> {
+    let path = workdir.join(CONFIG_PATH);
+    let text = match std::fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(format!("failed to read {CONFIG_PATH}: {e}")),
+    };
+    let mut pipeline: Pipeline =
+        toml::from_str(&text).map_err(|e| format!("invalid {CONFIG_PATH}: {e}"))?;
+    for (i, check) in pipeline.check.iter().enumerate() {
+        if check.name.trim().is_empty() || check.run.trim().is_empty() {
+            return Err(format!(
+                "invalid {CONFIG_PATH}: check {} needs a name and a run command",
+                i + 1
+            ));
+        }
+    }
+    for check in &mut pipeline.check {
+        if check.sandbox.is_none() {
+            check.sandbox.clone_from(&pipeline.sandbox);
+        }
+    }
+    Ok(pipeline.check)
+}
+
+/// Runs every check in order, even after one fails, so the report covers
+/// them all. The staged paths are passed in `GITFORGE_STAGED_FILES`, one
+/// per line.
+pub fn run(workdir: &Path, checks: &[Check], staged: &[String]) -> Report {
+    let staged = staged.join("\n");
+    let results: Vec<CheckResult> = checks
+        .iter()
+        .map(|check| run_check(workdir, check, &staged))
+        .collect();
+    Report {
+        passed: results.iter().all(|r| r.passed),
+        results,
+    }
+}
+
+fn run_check(workdir: &Path, check: &Check, staged: &str) -> CheckResult {
+    let started = Instant::now();
+    let timeout = Duration::from_secs(check.timeout.unwrap_or(DEFAULT_TIMEOUT));
+    let result = |passed, exit_code, timed_out, output: String| CheckResult {
+        name: check.name.clone(),
+        command: check.run.clone(),
+        passed,
+        exit_code,
+        timed_out,
+        duration_ms: started.elapsed().as_millis() as u64,
+        output,
+    };
+
+    let mut command = shell(&check.run);
+    if let Some(limits) = &check.sandbox {
+        if let Err(e) = sandbox::apply(&mut command, limits) {
+            return result(false, None, false, format!("failed to start: {e}"));
+        }
+    }
+    let mut child = match command
+        .current_dir(workdir)
+        .env("GITFORGE_STAGED_FILES", staged)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+    {
+        Ok(child) => child,
+        Err(e) => return result(false, None, false, format!("failed to start: {e}")),
+    };
+    // Drained on threads so a chatty check can't fill a pipe and stall.
+    let drain = |pipe: Option<Box<dyn Read + Send>>| {
+        std::thread::spawn(move || {
+            let mut bytes = Vec::new();
+            if let Some(mut pipe) = pipe {
+                let _ = pipe.read_to_end(&mut bytes);
+            }
+            bytes
+        })
+    };
+    let stdout = drain(
+        child
+            .stdout
+            .take()
+            .map(|p| Box::new(p) as Box<dyn Read + Send>),
+    );
+    let stderr = drain(
+        child
+            .stderr
+            .take()
+            .map(|p| Box::new(p) as Box<dyn Read + Send>),
+    );
+
+    let (status, timed_out) = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break (Some(status), false),
+            Ok(None) if started.elapsed() >= timeout => {
+                sandbox::kill(&mut child);
+                let _ = child.wait();
+                break (None, true);
+            }
+            Ok(None) => std::thread::sleep(Duration::from_millis(20)),
+            Err(e) => {
+                sandbox::kill(&mut child);
+                return result(false, None, false, format!("failed to wait: {e}"));
+            }
+        }
+    };
+    let mut output = tail(&stdout.join().unwrap_or_default());
+    let errors = tail(&stderr.join().unwrap_or_default());
+    if !errors.is_empty() {
+        if !output.is_empty() && !output.ends_with('\n') {
+            output.push('\n');
+        }
+        output.push_str(&errors);
+    }
+    let exit_code = status.and_then(|s| s.code());
+    let passed = status.is_some_and(|s| s.success());
+    result(passed, exit_code, timed_out, output)
+}
+
+pub(crate) fn shell(command: &str) -> Command {
+    if cfg!(windows) {
+        let mut shell = Command::new("cmd");
+        shell.arg("/C").arg(command);
+        shell
+    } else {
+        let mut shell = Command::new("sh");
+        shell.arg("-c").arg(command);
+        shell
+    }
+}
+
+/// The last [`MAX_OUTPUT`] bytes of `bytes` as text.
+pub(crate) fn tail(bytes: &[u8]) -> String {
+    let text = String::from_utf8_lossy(bytes);
+    if text.len() <= MAX_OUTPUT {
+        return text.into_owned();
+    }
+    let mut start = text.len() - MAX_OUTPUT;
+    while !text.is_char_boundary(start) {
+        start += 1;
+    }
+    format!("…{}", &text[start..])
+}
+
+/// Paths whose index entry differs from HEAD, i.e. what the next commit
+/// changes.
+pub fn staged_files(repo: &git2::Repository) -> Result<Vec<String>, String> {
+    let head = repo.head().ok().and_then(|head| head.peel_to_tree().ok());
+    let diff = repo
+        .diff_tree_to_index(head.as_ref(), None, None)
+        .map_err(|e| format!("failed to diff the index: {e}"))?;
+    Ok(diff
+        .deltas()
+        .filter_map(|delta| delta.new_file().path().or_else(|| delta.old_file().path()))
+        .map(|path| path.to_string_lossy().into_owned())
+        .collect())
+}
+
+/// The hooks gitforge manages and the command each one runs.
+const HOOKS: [(&str, &str); 2] = [
+    ("pre-commit", "exec gitforge checks run"),
+    ("commit-msg", "exec gitforge checks lint-msg \"$1\""),
+];
+
+/// Writes `pre-commit` and `commit-msg` hooks that run the checks and the
+/// commit message lint, honouring `core.hooksPath`. An existing hook
+/// gitforge didn't write is only replaced with `force`.
+pub fn install_hooks(repo: &git2::Repository, force: bool) -> Result<Vec<PathBuf>, String> {
+    let hooks = match repo.config().and_then(|c| c.get_path("core.hooksPath")) {
+        Ok(path) if path.is_absolute() => path,
+        Ok(path) => repo.workdir().unwrap_or_else(|| repo.path()).join(path),
+        Err(_) => repo.path().join("hooks"),
+    };
+    let paths: Vec<PathBuf> = HOOKS.iter().map(|(name, _)| hooks.join(name)).collect();
+    for path in &paths {
+        if let Ok(existing) = std::fs::read_to_string(path) {
+            if !existing.contains(HOOK_MARKER) && !force {
+                return Err(format!(
+                    "{} already exists; pass --force to replace it",
+                    path.display()
+                ));
+            }
+        }
+    }
+    std::fs::create_dir_all(&hooks)
+        .map_err(|e| format!("failed to create {}: {e}", hooks.display()))?;
+    for (path, (_, command)) in paths.iter().zip(HOOKS) {
+        let script = format!(
+            "#!/bin/sh\n{HOOK_MARKER}. Skip once with `git commit --no-verify`.\n{command}\n"
+        );
+        std::fs::write(path, script)
+            .map_err(|e| format!("failed to write {}: {e}", path.display()))?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o755))
+                .map_err(|e| format!("failed to make {} executable: {e}", path.display()))?;
+        }
+    }
+    Ok(paths)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir() -> crate::test_support::TempDir {
+        let dir = crate::test_support::temp_path("precommit");
+        std::fs::create_dir_all(dir.join(".gitforge")).expect("create dir");
+        dir
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn pipeline_runs_every_check_and_reports_each() {
+        let dir = temp_dir();
+        assert!(load(&dir).expect("no file").is_empty());
+        std::fs::write(
+            dir.join(CONFIG_PATH),
+            r#"
+[[check]]
+name = "staged"
+run = "echo \"$GITFORGE_STAGED_FILES\""
+
+[[check]]
+name = "lint"
+run = "echo bad >&2; exit 3"
+
+[[check]]
+name = "slow"
+run = "sleep 5"
+timeout = 0
+"#,
+        )
+        .expect("write checks");
+        let checks = load(&dir).expect("load");
+        let report = run(&dir, &checks, &["a.rs".to_string(), "b.rs".to_string()]);
+
+        assert!(!report.passed);
+        let [staged, lint, slow] = report.results.as_slice() else {
+            panic!("{report:?}");
+        };
+        assert!(staged.passed);
+        assert_eq!(staged.output, "a.rs\nb.rs\n");
+        assert_eq!((lint.exit_code, lint.output.as_str()), (Some(3), "bad\n"));
+        assert!(slow.timed_out && !slow.passed);
+        assert_eq!(
+            report.failure_summary().as_deref(),
+            Some("pre-commit checks failed: lint (exit 3), slow (timed out)")
+        );
+
+        std::fs::write(dir.join(CONFIG_PATH), "[[check]]\nname = \"x\"\n").expect("write");
+        assert!(load(&dir).unwrap_err().contains("invalid"));
+    }
+}
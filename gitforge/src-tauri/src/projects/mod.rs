@@ -0,0 +1,230 @@
+use serde::Serialize;
+use std::path::Path;
+
+/// Git config section; each project is a subsection whose repeated `path`
+/// entries are the prefixes it owns, e.g. `gitforge.project.web.path`.
+pub const SECTION: &str = "gitforge.project";
+
+/// A named slice of a monorepo: the files under any of its prefixes.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Project {
+    pub name: String,
+    /// Repository-relative, without leading `./` or trailing `/`.
+    pub paths: Vec<String>,
+}
+
+impl Project {
+    /// Whether a repository-relative path lies under one of the prefixes.
+    pub fn contains(&self, path: &str) -> bool {
+        let path = path.trim_end_matches('/');
+        self.paths.iter().any(|prefix| {
+            path == prefix
+                || path
+                    .strip_prefix(prefix.as_str())
+                    .is_some_and(|rest| rest.starts_with('/'))
+        })
+    }
+
+    /// Whether `commit` changes anything under the prefixes. Like
+    /// `git log -- <paths>`, a commit that matches one of its parents there
+    /// doesn't count, so merges only count for what they resolve.
+    pub fn touches(&self, commit: &git2::Commit) -> Result<bool, String> {
+        let ids = self.tree_ids(commit)?;
+        if commit.parent_count() == 0 {
+            return Ok(ids.iter().any(Option::is_some));
+        }
+        for parent in commit.parents() {
+            if self.tree_ids(&parent)? == ids {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    /// Whether `tip` differs from `base` under the prefixes.
+    pub fn differs(&self, base: &git2::Commit, tip: &git2::Commit) -> Result<bool, String> {
+        Ok(self.tree_ids(base)? != self.tree_ids(tip)?)
+    }
+
+    /// The object at each prefix, so comparing two commits doesn't need a
+    /// diff.
+    fn tree_ids(&self, commit: &git2::Commit) -> Result<Vec<Option<git2::Oid>>, String> {
+        let tree = commit
+            .tree()
+            .map_err(|e| format!("failed to read tree of {}: {e}", commit.id()))?;
+        Ok(self
+            .paths
+            .iter()
+            .map(|prefix| tree.get_path(Path::new(prefix)).ok().map(|e| e.id()))
+            .collect())
+    }
+
+    /// Diff options limited to the prefixes.
+    pub fn diff_options(&self) -> git2::DiffOptions {
+        let mut options = git2::DiffOptions::new();
+        for prefix in &self.paths {
+            options.pathspec(prefix);
+        }
+        options
+    }
+}
+
+fn path_key(name: &str) -> String {
+    format!("{SECTION}.{name}.path")
+}
+
+fn valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_' | b'.'))
+}
+
+/// Normalizes a prefix, refusing ones that leave the repository.
+fn normalize(path: &str) -> Result<String, String> {
+    let trimmed = path.trim().trim_start_matches("./").trim_end_matches('/');
+    let escapes = Path::new(trimmed).is_absolute()
+        || trimmed.split('/').any(|part| part == ".." || part == ".");
+    if trimmed.is_empty() || escapes {
+        return Err(format!("invalid project path {path:?}"));
+    }
+    Ok(trimmed.to_string())
+}
+
+/// Every registered project, sorted by name.
+pub fn list(config: &git2::Config) -> Result<Vec<Project>, String> {
+    let mut projects: Vec<Project> = Vec::new();
+    let pattern = format!(r"^{}\..*\.path$", SECTION.replace('.', r"\."));
+    let entries = config
+        .entries(Some(&pattern))
+        .map_err(|e| format!("failed to read {SECTION}.*: {e}"))?;
+    entries
+        .for_each(|entry| {
+            let (Some(key), Some(path)) = (entry.name(), entry.value()) else {
+                return;
+            };
+            let name = &key[SECTION.len() + 1..key.len() - ".path".len()];
+            let Ok(path) = normalize(path) else {
+                return;
+            };
+            match projects.iter_mut().find(|p| p.name == name) {
+                Some(project) if !project.paths.contains(&path) => project.paths.push(path),
+                Some(_) => {}
+                None => projects.push(Project {
+                    name: name.to_string(),
+                    paths: vec![path],
+                }),
+            }
+        })
+        .map_err(|e| format!("failed to read {SECTION}.*: {e}"))?;
+    projects.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(projects)
+}
+
+pub fn find(config: &git2::Config, name: &str) -> Result<Option<Project>, String> {
+    Ok(list(config)?
+        .into_iter()
+        .find(|project| project.name == name))
+}
+
+/// Registers `name` with exactly `paths`, replacing any earlier prefixes.
+pub fn save(config: &mut git2::Config, name: &str, paths: &[String]) -> Result<Project, String> {
+    if !valid_name(name) {
+        return Err(format!(
+            "invalid project name {name:?} (letters, digits, '.', '-', '_')"
+        ));
+    }
+    let mut normalized = Vec::new();
+    for path in paths {
+        let path = normalize(path)?;
+        if !normalized.contains(&path) {
+            normalized.push(path);
+        }
+    }
+    if normalized.is_empty() {
+        return Err("a project needs at least one path".to_string());
+    }
+    remove(config, name)?;
+    let key = path_key(name);
+    for path in &normalized {
+        config
+            .set_multivar(&key, "^$", path)
+            .map_err(|e| format!("failed to write {key}: {e}"))?;
+    }
+    Ok(Project {
+        name: name.to_string(),
+        paths: normalized,
+    })
+}
+
+/// `false` when no such project was registered.
+pub fn remove(config: &mut git2::Config, name: &str) -> Result<bool, String> {
+    let key = path_key(name);
+    match config.remove_multivar(&key, ".*") {
+        Ok(()) => Ok(true),
+        Err(e) if e.code() == git2::ErrorCode::NotFound => Ok(false),
+        Err(e) => Err(format!("failed to remove {key}: {e}")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_repo() -> (crate::test_support::TempDir, git2::Repository) {
+        let (dir, repo) = crate::test_support::init_repo("projects");
+        (dir, repo)
+    }
+
+    fn commit(repo: &git2::Repository, dir: &Path, path: &str, message: &str) -> git2::Oid {
+        let file = dir.join(path);
+        std::fs::create_dir_all(file.parent().unwrap()).unwrap();
+        std::fs::write(&file, message).unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_path(Path::new(path)).unwrap();
+        index.write().unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let sig = git2::Signature::now("Test", "test@example.com").unwrap();
+        let parent = repo.head().ok().and_then(|head| head.peel_to_commit().ok());
+        let parents: Vec<&git2::Commit> = parent.iter().collect();
+        repo.commit(Some("HEAD"), &sig, &sig, message, &tree, &parents)
+            .unwrap()
+    }
+
+    #[test]
+    fn projects_round_trip_through_config_and_scope_paths() {
+        let (dir, repo) = temp_repo();
+        let mut config = repo.config().unwrap();
+        assert!(list(&config).unwrap().is_empty());
+        let web = save(
+            &mut config,
+            "web",
+            &["./apps/web/".to_string(), "libs/ui".to_string()],
+        )
+        .expect("save");
+        assert_eq!(web.paths, ["apps/web", "libs/ui"]);
+        save(&mut config, "api", &["services/api".to_string()]).expect("save");
+        assert!(save(&mut config, "bad", &["../outside".to_string()]).is_err());
+        assert!(save(&mut config, "bad name", &["x".to_string()]).is_err());
+
+        let listed = list(&config).unwrap();
+        assert_eq!(
+            listed.iter().map(|p| p.name.as_str()).collect::<Vec<_>>(),
+            ["api", "web"]
+        );
+        let web = find(&config, "web").unwrap().expect("web");
+        assert!(web.contains("apps/web/index.ts"));
+        assert!(web.contains("libs/ui"));
+        assert!(!web.contains("apps/website/index.ts"));
+        save(&mut config, "web", &["apps/web".to_string()]).expect("replace");
+        assert_eq!(find(&config, "web").unwrap().unwrap().paths, ["apps/web"]);
+        assert!(remove(&mut config, "web").unwrap());
+        assert!(!remove(&mut config, "web").unwrap());
+
+        let api = find(&config, "api").unwrap().unwrap();
+        let first = commit(&repo, &dir, "services/api/main.rs", "api");
+        let second = commit(&repo, &dir, "apps/web/index.ts", "web");
+        assert!(api.touches(&repo.find_commit(first).unwrap()).unwrap());
+        assert!(!api.touches(&repo.find_commit(second).unwrap()).unwrap());
+    }
+}
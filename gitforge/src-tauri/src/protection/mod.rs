@@ -0,0 +1,397 @@
+use ant_core::{AntEngine, SystemEvent, VersionedSystemEvent, SYSTEM_EVENT_SCHEMA_VERSION};
+use serde::Serialize;
+
+use crate::owners::Owners;
+use crate::store::{CheckRecord, MetaStore, PrRecord};
+
+/// Protected branch names or `*` globs, e.g. `main` or `release/*`
+/// (multivar). Protected branches take no direct commits or pushes.
+pub const BRANCH_KEY: &str = "gitforge.protect.branch";
+/// Distinct reviewers that must approve a PR before it merges into a
+/// protected branch.
+pub const APPROVALS_KEY: &str = "gitforge.protect.approvals";
+/// Whether the newest check from each CI provider must have succeeded.
+pub const CHECKS_KEY: &str = "gitforge.protect.requireChecks";
+/// Whether every path a PR touches needs an approval from one of its
+/// CODEOWNERS.
+pub const OWNERS_KEY: &str = "gitforge.protect.requireOwners";
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Rules {
+    pub branches: Vec<String>,
+    pub approvals: usize,
+    pub require_checks: bool,
+    pub require_owners: bool,
+}
+
+/// Why an action on a protected branch was refused.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Violation {
+    pub branch: String,
+    /// `direct_commit`, `direct_push`, `deletion`, `history_rewrite`,
+    /// `approvals`, `checks`, `owners`, or `secrets` (see `crate::secrets`).
+    pub rule: &'static str,
+    pub message: String,
+}
+
+impl Rules {
+    pub fn load(repo: &git2::Repository) -> Result<Self, String> {
+        let config = repo
+            .config()
+            .map_err(|e| format!("failed to read config: {e}"))?;
+        let mut branches = Vec::new();
+        match config.multivar(BRANCH_KEY, None) {
+            Ok(entries) => entries
+                .for_each(|entry| {
+                    if let Some(value) = entry.value().map(str::trim).filter(|v| !v.is_empty()) {
+                        branches.push(value.trim_start_matches("refs/heads/").to_string());
+                    }
+                })
+                .map_err(|e| format!("invalid {BRANCH_KEY}: {e}"))?,
+            Err(e) if e.code() == git2::ErrorCode::NotFound => {}
+            Err(e) => return Err(format!("invalid {BRANCH_KEY}: {e}")),
+        }
+        let approvals = match config.get_i64(APPROVALS_KEY) {
+            Ok(n) if n >= 0 => n as usize,
+            Ok(n) => return Err(format!("{APPROVALS_KEY} must not be negative (got {n})")),
+            Err(e) if e.code() == git2::ErrorCode::NotFound => 0,
+            Err(e) => return Err(format!("invalid {APPROVALS_KEY}: {e}")),
+        };
+        let flag = |key: &str| match config.get_bool(key) {
+            Ok(required) => Ok(required),
+            Err(e) if e.code() == git2::ErrorCode::NotFound => Ok(false),
+            Err(e) => Err(format!("invalid {key}: {e}")),
+        };
+        Ok(Self {
+            branches,
+            approvals,
+            require_checks: flag(CHECKS_KEY)?,
+            require_owners: flag(OWNERS_KEY)?,
+        })
+    }
+
+    /// Whether `branch` (a name or `refs/heads/` ref) is protected.
+    pub fn protects(&self, branch: &str) -> bool {
+        let branch = branch.strip_prefix("refs/heads/").unwrap_or(branch);
+        self.branches.iter().any(|pattern| glob(pattern, branch))
+    }
+
+    /// Refuses committing onto a protected branch checked out in `repo`.
+    pub fn check_commit(&self, repo: &git2::Repository) -> Option<Violation> {
+        self.check_commit_on(&current_branch(repo)?)
+    }
+
+    /// Refuses committing onto `branch` if it's protected, checked out or
+    /// not.
+    pub fn check_commit_on(&self, branch: &str) -> Option<Violation> {
+        let branch = branch.strip_prefix("refs/heads/").unwrap_or(branch);
+        self.protects(branch).then(|| Violation {
+            message: format!(
+                "{branch} is protected: commit on another branch and open a PR (force overrides)"
+            ),
+            branch: branch.to_string(),
+            rule: "direct_commit",
+        })
+    }
+
+    /// Refuses deleting a protected branch.
+    pub fn check_delete(&self, branch: &str) -> Option<Violation> {
+        self.protects(branch).then(|| Violation {
+            message: format!("{branch} is protected and can't be deleted (force overrides)"),
+            branch: branch.to_string(),
+            rule: "deletion",
+        })
+    }
+
+    /// Refuses rewriting the history of any protected branch in `branches`.
+    pub fn check_rewrite(&self, branches: &[String]) -> Option<Violation> {
+        let branch = branches.iter().find(|branch| self.protects(branch))?;
+        Some(Violation {
+            message: format!(
+                "{branch} is protected and its history can't be rewritten (force overrides)"
+            ),
+            branch: branch.trim_start_matches("refs/heads/").to_string(),
+            rule: "history_rewrite",
+        })
+    }
+
+    /// Refuses pushing any refspec whose destination is a protected
+    /// branch, deletions (`:main`) included. A source-only refspec pushes
+    /// to the same name, and `HEAD` stands for the current branch.
+    pub fn check_push(&self, repo: &git2::Repository, refspecs: &[String]) -> Option<Violation> {
+        refspecs.iter().find_map(|spec| {
+            let spec = spec.trim_start_matches('+');
+            let destination = match spec.split_once(':') {
+                Some((_, "")) => return None,
+                Some((_, destination)) => destination.to_string(),
+                None if spec == "HEAD" => current_branch(repo)?,
+                None => spec.to_string(),
+            };
+            let bare = !destination.starts_with("refs/");
+            let branch = destination
+                .strip_prefix("refs/heads/")
+                .map(str::to_string)
+                .or(bare.then_some(destination))?;
+            self.protects(&branch).then(|| Violation {
+                message: format!(
+                    "{branch} is protected: push another branch and merge a PR (force overrides)"
+                ),
+                branch,
+                rule: "direct_push",
+            })
+        })
+    }
+
+    /// Refuses merging `pr` into a protected branch without enough
+    /// approvals or, when required, passing checks. `checks` are the PR's,
+    /// newest first.
+    pub fn check_merge(&self, pr: &PrRecord, checks: &[CheckRecord]) -> Option<Violation> {
+        let branch = pr.to.strip_prefix("refs/heads/").unwrap_or(&pr.to);
+        if !self.protects(branch) {
+            return None;
+        }
+        let branch = branch.to_string();
+        if pr.approvals.len() < self.approvals {
+            return Some(Violation {
+                message: format!(
+                    "{branch} is protected: PR {} has {} of {} required approvals",
+                    pr.id,
+                    pr.approvals.len(),
+                    self.approvals
+                ),
+                branch,
+                rule: "approvals",
+            });
+        }
+        if !self.require_checks {
+            return None;
+        }
+        let mut newest: Vec<&CheckRecord> = Vec::new();
+        for check in checks {
+            if !newest.iter().any(|seen| seen.provider == check.provider) {
+                newest.push(check);
+            }
+        }
+        let failing: Vec<String> = newest
+            .iter()
+            .filter(|check| check.conclusion.as_deref() != Some("success"))
+            .map(|check| {
+                let outcome = check.conclusion.as_deref().unwrap_or(&check.status);
+                format!("{} {outcome}", check.provider)
+            })
+            .collect();
+        let message = match (newest.is_empty(), failing.is_empty()) {
+            (true, _) => format!("{branch} is protected: PR {} has no checks", pr.id),
+            (false, false) => format!(
+                "{branch} is protected: PR {} checks haven't passed ({})",
+                pr.id,
+                failing.join(", ")
+            ),
+            (false, true) => return None,
+        };
+        Some(Violation {
+            branch,
+            rule: "checks",
+            message,
+        })
+    }
+
+    /// Refuses merging `pr` into a protected branch while some of the
+    /// `touched` paths lack an approval from one of their owners.
+    pub fn check_owners(
+        &self,
+        pr: &PrRecord,
+        owners: &Owners,
+        touched: &[String],
+    ) -> Option<Violation> {
+        let branch = pr.to.strip_prefix("refs/heads/").unwrap_or(&pr.to);
+        if !self.require_owners || !self.protects(branch) {
+            return None;
+        }
+        let approvers: Vec<&str> = pr.approvals.iter().map(|a| a.reviewer.as_str()).collect();
+        let unapproved = owners.unapproved(touched.iter().map(String::as_str), &approvers);
+        let first = unapproved.first()?;
+        Some(Violation {
+            message: format!(
+                "{branch} is protected: {} path(s) need a code owner's approval, e.g. {first} ({})",
+                unapproved.len(),
+                owners.owners_of(first).join(" ")
+            ),
+            branch: branch.to_string(),
+            rule: "owners",
+        })
+    }
+}
+
+/// Lets `action` go ahead when nothing was violated. A violation fails
+/// with its message unless `force` is set, in which case it's audited and
+/// returned so the caller can report the override.
+pub fn enforce(
+    store: &dyn MetaStore,
+    engine: &AntEngine,
+    violation: Option<Violation>,
+    action: &str,
+    force: bool,
+    reason: Option<&str>,
+) -> Result<Option<Violation>, String> {
+    let Some(violation) = violation else {
+        return Ok(None);
+    };
+    if !force {
+        return Err(violation.message);
+    }
+    audit(store, engine, &violation, action, reason)?;
+    Ok(Some(violation))
+}
+
+/// Records that `action` went ahead despite `violation`: stored as a
+/// `protection_overridden` event, so `events_since` doubles as the audit
+/// log, and announced on the bus.
+pub fn audit(
+    store: &dyn MetaStore,
+    engine: &AntEngine,
+    violation: &Violation,
+    action: &str,
+    reason: Option<&str>,
+) -> Result<i64, String> {
+    let reason = reason.map(str::to_string);
+    let seq = store.append_event(&VersionedSystemEvent {
+        schema_version: SYSTEM_EVENT_SCHEMA_VERSION,
+        event: SystemEvent::ProtectionOverridden {
+            branch: violation.branch.clone(),
+            action: action.to_string(),
+            rule: violation.rule.to_string(),
+            reason: reason.clone(),
+        },
+    })?;
+    engine.protection_overridden(&violation.branch, action, violation.rule, reason);
+    Ok(seq)
+}
+
+/// The branch HEAD points at. An unborn branch still names where the
+/// commit will go.
+pub fn current_branch(repo: &git2::Repository) -> Option<String> {
+    let head = repo.find_reference("HEAD").ok()?;
+    let target = head.symbolic_target()?;
+    target.strip_prefix("refs/heads/").map(str::to_string)
+}
+
+/// `*` matches any run of characters, including `/`.
+fn glob(pattern: &str, name: &str) -> bool {
+    let Some((first, rest)) = pattern.split_once('*') else {
+        return pattern == name;
+    };
+    let Some(mut remaining) = name.strip_prefix(first) else {
+        return false;
+    };
+    let mut parts: Vec<&str> = rest.split('*').collect();
+    let last = parts.pop().unwrap_or_default();
+    for part in parts {
+        match remaining.find(part) {
+            Some(at) => remaining = &remaining[at + part.len()..],
+            None => return false,
+        }
+    }
+    remaining.len() >= last.len() && remaining.ends_with(last)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pr(to: &str, approvals: &[&str]) -> PrRecord {
+        PrRecord {
+            id: 1,
+            title: "Change".to_string(),
+            from: "feature".to_string(),
+            to: to.to_string(),
+            state: "open".to_string(),
+            description: None,
+            created_at: String::new(),
+            links: Vec::new(),
+            reviewers: Vec::new(),
+            merged_at: None,
+            approvals: approvals
+                .iter()
+                .map(|reviewer| crate::store::Approval {
+                    reviewer: reviewer.to_string(),
+                    approved_at: String::new(),
+                })
+                .collect(),
+        }
+    }
+
+    fn check(provider: &str, conclusion: Option<&str>) -> CheckRecord {
+        CheckRecord {
+            id: 1,
+            pr_id: 1,
+            provider: provider.to_string(),
+            branch: "feature".to_string(),
+            status: if conclusion.is_some() {
+                "completed"
+            } else {
+                "queued"
+            }
+            .to_string(),
+            conclusion: conclusion.map(str::to_string),
+            run_id: None,
+            url: None,
+            created_at: String::new(),
+            updated_at: String::new(),
+        }
+    }
+
+    #[test]
+    fn rules_match_globs_and_gate_merges_on_approvals_and_checks() {
+        let rules = Rules {
+            branches: vec!["main".to_string(), "release/*".to_string()],
+            approvals: 2,
+            require_checks: true,
+            require_owners: false,
+        };
+        assert!(rules.protects("refs/heads/main"));
+        assert!(rules.protects("release/1.2"));
+        assert!(!rules.protects("mainline"));
+        assert!(!rules.protects("feature/release/x"));
+
+        assert!(rules.check_merge(&pr("dev", &[]), &[]).is_none());
+        let short = rules
+            .check_merge(&pr("main", &["ana"]), &[])
+            .expect("needs approvals");
+        assert_eq!(short.rule, "approvals");
+        let approved = pr("main", &["ana", "ben"]);
+        assert_eq!(
+            rules.check_merge(&approved, &[]).expect("no checks").rule,
+            "checks"
+        );
+        // Only the newest run per provider counts.
+        let rerun = [
+            check("github", Some("success")),
+            check("github", Some("failure")),
+        ];
+        assert!(rules.check_merge(&approved, &rerun).is_none());
+        let pending = [check("gitlab", None), check("github", Some("success"))];
+        let failing = rules.check_merge(&approved, &pending).expect("pending");
+        assert!(
+            failing.message.contains("gitlab queued"),
+            "{}",
+            failing.message
+        );
+
+        let (_dir, repo) = crate::test_support::init_repo("protection");
+        repo.set_head("refs/heads/main").expect("unborn main");
+        let push = |specs: &[&str]| {
+            let specs: Vec<String> = specs.iter().map(|s| s.to_string()).collect();
+            rules.check_push(&repo, &specs).map(|v| v.branch)
+        };
+        assert_eq!(push(&["HEAD"]).as_deref(), Some("main"));
+        assert_eq!(
+            push(&["+refs/heads/x:refs/heads/release/2"]).as_deref(),
+            Some("release/2")
+        );
+        assert_eq!(push(&[":main"]).as_deref(), Some("main"));
+        assert_eq!(push(&["feature", "refs/tags/v1"]), None);
+        assert_eq!(rules.check_delete("main").map(|v| v.rule), Some("deletion"));
+        assert!(rules.check_delete("feature").is_none());
+    }
+}
@@ -0,0 +1,411 @@
+//! Removing a file from the whole history, like `git filter-repo
+//! --invert-paths --path`, for secrets and files that should never have been
+//! committed. Every local branch and tag is rewritten; new commit ids ripple
+//! out from the oldest commit that contained the path.
+
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Target {
+    /// A repo-relative file or directory.
+    Path(String),
+    /// Every file with this content, wherever it lives.
+    Blob(git2::Oid),
+}
+
+impl std::fmt::Display for Target {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Path(path) => f.write_str(path),
+            Self::Blob(oid) => write!(f, "{oid}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RewrittenRef {
+    pub name: String,
+    pub old: String,
+    pub new: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Report {
+    pub target: String,
+    /// Commits that got a new id, whether they held the target or descend
+    /// from one that did.
+    pub commits: usize,
+    /// Refs whose tip changed; the others are left alone.
+    pub refs: Vec<RewrittenRef>,
+}
+
+/// Writes the rewritten commits without moving any ref, so the report
+/// doubles as a dry run. Unreferenced objects are left for `git gc`.
+pub fn rewrite(repo: &git2::Repository, target: &Target) -> Result<Report, String> {
+    if let Target::Blob(oid) = target {
+        repo.find_blob(*oid)
+            .map_err(|e| format!("unknown blob {oid}: {e}"))?;
+    }
+    let refs = refs(repo)?;
+    let mut walk = repo
+        .revwalk()
+        .map_err(|e| format!("failed to walk history: {e}"))?;
+    walk.set_sorting(git2::Sort::TOPOLOGICAL | git2::Sort::REVERSE)
+        .map_err(|e| format!("failed to walk history: {e}"))?;
+    for (_, tip) in &refs {
+        walk.push(*tip)
+            .map_err(|e| format!("failed to walk history: {e}"))?;
+    }
+
+    let mut trees = HashMap::new();
+    let mut commits: HashMap<git2::Oid, git2::Oid> = HashMap::new();
+    let mut rewritten = 0;
+    for oid in walk {
+        let commit = oid
+            .and_then(|oid| repo.find_commit(oid))
+            .map_err(|e| format!("failed to read commit: {e}"))?;
+        let tree = commit
+            .tree()
+            .map_err(|e| format!("failed to read tree of {}: {e}", commit.id()))?;
+        let new_tree = root(repo, &tree, target, &mut trees)
+            .map_err(|e| format!("failed to rewrite tree of {}: {e}", commit.id()))?;
+        let parents: Vec<git2::Oid> = commit
+            .parent_ids()
+            .map(|parent| commits.get(&parent).copied().unwrap_or(parent))
+            .collect();
+        if new_tree == tree.id() && commit.parent_ids().eq(parents.iter().copied()) {
+            commits.insert(commit.id(), commit.id());
+            continue;
+        }
+        let new_tree = repo
+            .find_tree(new_tree)
+            .map_err(|e| format!("failed to read tree {new_tree}: {e}"))?;
+        let parents = parents
+            .iter()
+            .map(|parent| repo.find_commit(*parent))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("failed to read parents of {}: {e}", commit.id()))?;
+        let parents: Vec<&git2::Commit> = parents.iter().collect();
+        let message = commit
+            .message_raw()
+            .ok_or_else(|| format!("commit {} has a non-UTF-8 message", commit.id()))?;
+        let id = repo
+            .commit(
+                None,
+                &commit.author(),
+                &commit.committer(),
+                message,
+                &new_tree,
+                &parents,
+            )
+            .map_err(|e| format!("failed to write commit: {e}"))?;
+        commits.insert(commit.id(), id);
+        rewritten += 1;
+    }
+
+    let mut moved = Vec::new();
+    for (name, tip) in refs {
+        let new = commits.get(&tip).copied().unwrap_or(tip);
+        if new == tip {
+            continue;
+        }
+        let old = repo
+            .refname_to_id(&name)
+            .map_err(|e| format!("failed to resolve {name}: {e}"))?;
+        let new = retag(repo, &name, old, new)?;
+        moved.push(RewrittenRef {
+            name,
+            old: old.to_string(),
+            new: new.to_string(),
+        });
+    }
+    Ok(Report {
+        target: target.to_string(),
+        commits: rewritten,
+        refs: moved,
+    })
+}
+
+/// Moves every ref in `report` and checks out the current branch's new
+/// tip, which deletes the target from the working tree too. A ref that
+/// changed since [`rewrite`] refuses the whole update, and so do
+/// uncommitted changes to tracked files.
+pub fn apply(repo: &git2::Repository, report: &Report) -> Result<(), String> {
+    let oid = |id: &str| git2::Oid::from_str(id).map_err(|e| format!("invalid id {id}: {e}"));
+    for rewritten in &report.refs {
+        let current = repo
+            .refname_to_id(&rewritten.name)
+            .map_err(|e| format!("failed to resolve {}: {e}", rewritten.name))?;
+        if current != oid(&rewritten.old)? {
+            return Err(format!(
+                "{} moved since the rewrite was planned; run it again",
+                rewritten.name
+            ));
+        }
+    }
+    let dirty = crate::branches::dirty_paths(repo)?;
+    if !dirty.is_empty() {
+        return Err(format!(
+            "working tree has uncommitted changes in {} files; commit or stash them first",
+            dirty.len()
+        ));
+    }
+
+    let head = repo
+        .head()
+        .ok()
+        .filter(|head| head.is_branch())
+        .and_then(|head| head.name().map(str::to_string));
+    if let Some(rewritten) = report.refs.iter().find(|r| Some(&r.name) == head.as_ref()) {
+        let tip = repo
+            .find_commit(oid(&rewritten.new)?)
+            .map_err(|e| format!("failed to read commit {}: {e}", rewritten.new))?;
+        let mut checkout = git2::build::CheckoutBuilder::new();
+        checkout.safe();
+        repo.checkout_tree(tip.as_object(), Some(&mut checkout))
+            .map_err(|e| format!("failed to check out the result: {e}"))?;
+    }
+    let reflog = format!("gitforge: purge {} from history", report.target);
+    for rewritten in &report.refs {
+        repo.reference(&rewritten.name, oid(&rewritten.new)?, true, &reflog)
+            .map_err(|e| format!("failed to update {}: {e}", rewritten.name))?;
+    }
+    Ok(())
+}
+
+/// Local branches and tags with the commit each one reaches.
+fn refs(repo: &git2::Repository) -> Result<Vec<(String, git2::Oid)>, String> {
+    let mut refs = Vec::new();
+    for glob in ["refs/heads/*", "refs/tags/*"] {
+        let references = repo
+            .references_glob(glob)
+            .map_err(|e| format!("failed to list {glob}: {e}"))?;
+        for reference in references {
+            let reference = reference.map_err(|e| format!("failed to read ref: {e}"))?;
+            // Tags of trees or blobs have no history to rewrite.
+            let (Some(name), Ok(commit)) = (reference.name(), reference.peel_to_commit()) else {
+                continue;
+            };
+            refs.push((name.to_string(), commit.id()));
+        }
+    }
+    Ok(refs)
+}
+
+/// The id `name` should point at once its commit is `commit`: the commit
+/// itself, or a copy of an annotated tag pointing at it.
+fn retag(
+    repo: &git2::Repository,
+    name: &str,
+    old: git2::Oid,
+    commit: git2::Oid,
+) -> Result<git2::Oid, String> {
+    let Ok(tag) = repo.find_tag(old) else {
+        return Ok(commit);
+    };
+    let target = repo
+        .find_object(commit, Some(git2::ObjectType::Commit))
+        .map_err(|e| format!("failed to read commit {commit}: {e}"))?;
+    let Some(tagger) = tag.tagger() else {
+        return Ok(commit);
+    };
+    repo.tag_annotation_create(
+        name.trim_start_matches("refs/tags/"),
+        &target,
+        &tagger,
+        tag.message().unwrap_or(""),
+    )
+    .map_err(|e| format!("failed to rewrite tag {name}: {e}"))
+}
+
+/// `tree` without the target. Commits share most of their trees, so each
+/// root is rewritten once.
+fn root(
+    repo: &git2::Repository,
+    tree: &git2::Tree,
+    target: &Target,
+    cache: &mut HashMap<git2::Oid, git2::Oid>,
+) -> Result<git2::Oid, git2::Error> {
+    if let Some(&done) = cache.get(&tree.id()) {
+        return Ok(done);
+    }
+    let new = match target {
+        Target::Path(path) => {
+            let components: Vec<&str> = path.split('/').filter(|c| !c.is_empty()).collect();
+            without_path(repo, tree, &components)?
+        }
+        Target::Blob(blob) => without_blob(repo, tree, *blob, cache)?,
+    };
+    let new = match new {
+        Some(new) => new,
+        None => repo.treebuilder(None)?.write()?,
+    };
+    cache.insert(tree.id(), new);
+    Ok(new)
+}
+
+/// `tree` without the entry at `components`, or `None` when nothing is
+/// left. Directories emptied along the way go too, as git can't keep them.
+fn without_path(
+    repo: &git2::Repository,
+    tree: &git2::Tree,
+    components: &[&str],
+) -> Result<Option<git2::Oid>, git2::Error> {
+    let Some((first, rest)) = components.split_first() else {
+        return Ok(Some(tree.id()));
+    };
+    let Some(entry) = tree.get_name(first) else {
+        return Ok(Some(tree.id()));
+    };
+    let mut builder = repo.treebuilder(Some(tree))?;
+    if rest.is_empty() {
+        builder.remove(first)?;
+    } else if entry.kind() == Some(git2::ObjectType::Tree) {
+        let subtree = repo.find_tree(entry.id())?;
+        match without_path(repo, &subtree, rest)? {
+            Some(new) if new == subtree.id() => return Ok(Some(tree.id())),
+            Some(new) => {
+                builder.insert(first, new, entry.filemode())?;
+            }
+            None => builder.remove(first)?,
+        }
+    } else {
+        return Ok(Some(tree.id()));
+    }
+    if builder.is_empty() {
+        return Ok(None);
+    }
+    builder.write().map(Some)
+}
+
+/// `tree` without any file whose content is `blob`.
+fn without_blob(
+    repo: &git2::Repository,
+    tree: &git2::Tree,
+    blob: git2::Oid,
+    cache: &mut HashMap<git2::Oid, git2::Oid>,
+) -> Result<Option<git2::Oid>, git2::Error> {
+    let mut builder = repo.treebuilder(Some(tree))?;
+    let mut changed = false;
+    for entry in tree.iter() {
+        let name = entry.name_bytes();
+        match entry.kind() {
+            Some(git2::ObjectType::Blob) if entry.id() == blob => {
+                builder.remove(name)?;
+                changed = true;
+            }
+            Some(git2::ObjectType::Tree) => {
+                let new = match cache.get(&entry.id()) {
+                    Some(&done) => Some(done),
+                    None => {
+                        let subtree = repo.find_tree(entry.id())?;
+                        let new = without_blob(repo, &subtree, blob, cache)?;
+                        if let Some(new) = new {
+                            cache.insert(entry.id(), new);
+                        }
+                        new
+                    }
+                };
+                match new {
+                    Some(new) if new == entry.id() => {}
+                    Some(new) => {
+                        builder.insert(name, new, entry.filemode())?;
+                        changed = true;
+                    }
+                    None => {
+                        builder.remove(name)?;
+                        changed = true;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    if !changed {
+        return Ok(Some(tree.id()));
+    }
+    if builder.is_empty() {
+        return Ok(None);
+    }
+    builder.write().map(Some)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::path::Path;
+
+    #[test]
+    fn purges_a_path_from_every_branch_and_tag() {
+        let (dir, repo) = crate::test_support::init_repo("purge");
+        let sig = git2::Signature::now("GitForge Test", "test@gitforge.dev").expect("sig");
+        let commit = |path: &str, text: &str, message: &str| {
+            fs::create_dir_all(dir.join(path).parent().unwrap()).expect("dir");
+            fs::write(dir.join(path), text).expect("write");
+            let mut index = repo.index().expect("index");
+            index.add_path(Path::new(path)).expect("add");
+            index.write().expect("write index");
+            let tree = repo
+                .find_tree(index.write_tree().expect("tree"))
+                .expect("tree");
+            let parent = repo.head().ok().and_then(|h| h.peel_to_commit().ok());
+            let parents: Vec<&git2::Commit> = parent.iter().collect();
+            repo.commit(Some("HEAD"), &sig, &sig, message, &tree, &parents)
+                .expect("commit")
+        };
+        let base = commit("README.md", "hi\n", "Init");
+        commit("config/.env", "TOKEN=hunter2\n", "Add env");
+        let tagged = commit("src/main.rs", "fn main() {}\n", "Add main");
+        repo.tag(
+            "v1",
+            &repo.find_object(tagged, None).unwrap(),
+            &sig,
+            "First",
+            false,
+        )
+        .expect("tag");
+        repo.branch("old", &repo.find_commit(base).unwrap(), false)
+            .expect("branch");
+
+        let report = rewrite(&repo, &Target::Path("config/.env".into())).expect("rewrite");
+        assert_eq!(report.commits, 2);
+        let mut names: Vec<&str> = report.refs.iter().map(|r| r.name.as_str()).collect();
+        names.sort();
+        let head = repo.head().unwrap().name().unwrap().to_string();
+        assert_eq!(names, [head.as_str(), "refs/tags/v1"]);
+        // Nothing moves until the report is applied.
+        assert_eq!(repo.head().unwrap().target(), Some(tagged));
+
+        apply(&repo, &report).expect("apply");
+        let tip = repo.head().unwrap().peel_to_commit().unwrap();
+        assert_ne!(tip.id(), tagged);
+        assert_eq!(tip.summary(), Some("Add main"));
+        assert!(tip.tree().unwrap().get_path(Path::new("config")).is_err());
+        assert_eq!(tip.parent(0).unwrap().parent_id(0).unwrap(), base);
+        assert!(!dir.join("config/.env").exists());
+        let tag = repo
+            .find_reference("refs/tags/v1")
+            .unwrap()
+            .peel_to_tag()
+            .expect("still annotated");
+        assert_eq!((tag.target_id(), tag.message()), (tip.id(), Some("First")));
+        assert_eq!(repo.refname_to_id("refs/heads/old").unwrap(), base);
+        assert!(apply(&repo, &report).is_err_and(|e| e.contains("moved since")));
+
+        // By content: the same text under another name goes too.
+        commit("a/b.txt", "secret\n", "Add b");
+        commit("c.txt", "secret\n", "Add c");
+        let blob = repo.blob(b"secret\n").unwrap();
+        let report = rewrite(&repo, &Target::Blob(blob)).expect("rewrite blob");
+        apply(&repo, &report).expect("apply blob");
+        let tree = repo.head().unwrap().peel_to_tree().unwrap();
+        let names: Vec<String> = tree
+            .iter()
+            .filter_map(|e| e.name().map(str::to_string))
+            .collect();
+        assert_eq!(names, ["README.md", "src"]);
+    }
+}
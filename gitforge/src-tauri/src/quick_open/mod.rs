@@ -0,0 +1,252 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+use crate::mcp::registry::registry_key;
+
+pub const DEFAULT_LIMIT: usize = 50;
+pub const MAX_LIMIT: usize = 500;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct QuickOpenMatch {
+    pub path: String,
+    pub score: i64,
+    /// Byte offsets of the matched characters, for highlighting.
+    pub positions: Vec<usize>,
+}
+
+/// Tracked paths of one repository as of one version of its git index.
+struct FileIndex {
+    /// `(size, mtime)` of `.git/index` when loaded.
+    stamp: Option<(u64, SystemTime)>,
+    paths: Vec<String>,
+    lowercase: Vec<String>,
+}
+
+/// Per-repository path indexes for the ctrl-P picker, reloaded when the git
+/// index file changes. Cloning shares the cache.
+#[derive(Clone, Default)]
+pub struct QuickOpen {
+    indexes: Arc<Mutex<HashMap<PathBuf, Arc<FileIndex>>>>,
+}
+
+impl QuickOpen {
+    /// Tracked files fuzzy-matching `query`, best first. An empty query
+    /// lists files in index order.
+    pub fn query(
+        &self,
+        repo_path: &str,
+        query: &str,
+        limit: usize,
+    ) -> Result<Vec<QuickOpenMatch>, String> {
+        let limit = match limit {
+            0 => DEFAULT_LIMIT,
+            n => n.min(MAX_LIMIT),
+        };
+        let index = self.index(repo_path)?;
+        let needle: Vec<char> = query
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .flat_map(char::to_lowercase)
+            .collect();
+
+        if needle.is_empty() {
+            return Ok(index
+                .paths
+                .iter()
+                .take(limit)
+                .map(|path| QuickOpenMatch {
+                    path: path.clone(),
+                    score: 0,
+                    positions: Vec::new(),
+                })
+                .collect());
+        }
+
+        let mut matches: Vec<(i64, usize, Vec<usize>)> = index
+            .lowercase
+            .iter()
+            .enumerate()
+            .filter_map(|(i, lower)| {
+                fuzzy_match(lower, &needle).map(|(score, positions)| (score, i, positions))
+            })
+            .collect();
+        matches.sort_by(|a, b| {
+            b.0.cmp(&a.0)
+                .then_with(|| index.paths[a.1].len().cmp(&index.paths[b.1].len()))
+                .then_with(|| a.1.cmp(&b.1))
+        });
+        Ok(matches
+            .into_iter()
+            .take(limit)
+            .map(|(score, i, positions)| QuickOpenMatch {
+                path: index.paths[i].clone(),
+                score,
+                positions,
+            })
+            .collect())
+    }
+
+    /// Drops the cached paths for `repo_path`, e.g. when its window closes.
+    pub fn forget(&self, repo_path: &str) -> Result<bool, String> {
+        Ok(self.lock().remove(&registry_key(repo_path)).is_some())
+    }
+
+    fn index(&self, repo_path: &str) -> Result<Arc<FileIndex>, String> {
+        let repo = git2::Repository::open(repo_path)
+            .map_err(|e| format!("failed to open repository: {e}"))?;
+        let stamp = index_stamp(&repo);
+        let key = registry_key(repo_path);
+        if let Some(index) = self.lock().get(&key) {
+            if index.stamp.is_some() && index.stamp == stamp {
+                return Ok(Arc::clone(index));
+            }
+        }
+
+        // Load outside the lock; a big index takes a moment to walk.
+        let git_index = repo
+            .index()
+            .map_err(|e| format!("failed to read index: {e}"))?;
+        let mut paths: Vec<String> = git_index
+            .iter()
+            .filter(|entry| (entry.flags >> 12) & 0x3 == 0)
+            .map(|entry| String::from_utf8_lossy(&entry.path).into_owned())
+            .collect();
+        paths.dedup();
+        let lowercase = paths.iter().map(|p| p.to_lowercase()).collect();
+        let index = Arc::new(FileIndex {
+            stamp,
+            paths,
+            lowercase,
+        });
+        self.lock().insert(key, Arc::clone(&index));
+        Ok(index)
+    }
+
+    /// Whole indexes go in and out, so a panic can't leave one half-built.
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<PathBuf, Arc<FileIndex>>> {
+        crate::sync::lock(&self.indexes)
+    }
+}
+
+fn index_stamp(repo: &git2::Repository) -> Option<(u64, SystemTime)> {
+    let meta = std::fs::metadata(repo.path().join("index")).ok()?;
+    Some((meta.len(), meta.modified().ok()?))
+}
+
+/// Greedy subsequence match of `needle` in `haystack` (both lowercase).
+/// Rewards matches at word starts and runs of adjacent characters, with
+/// extra weight on the file name, and charges a little per skipped byte.
+fn fuzzy_match(haystack: &str, needle: &[char]) -> Option<(i64, Vec<usize>)> {
+    let name_start = haystack.rfind('/').map_or(0, |i| i + 1);
+    let mut positions = Vec::with_capacity(needle.len());
+    let mut score = 0i64;
+    let mut wanted = needle.iter().peekable();
+    let mut previous: Option<(usize, char)> = None;
+    let mut last_match: Option<usize> = None;
+
+    for (offset, c) in haystack.char_indices() {
+        let Some(&&want) = wanted.peek() else {
+            break;
+        };
+        if c == want {
+            let word_start = match previous {
+                None => true,
+                Some((_, p)) => matches!(p, '/' | '_' | '-' | '.' | ' '),
+            };
+            let adjacent = last_match.is_some_and(|last| previous.is_some_and(|(o, _)| o == last));
+            score += 1;
+            if word_start {
+                score += 8;
+            }
+            if adjacent {
+                score += 5;
+            }
+            if offset >= name_start {
+                score += 2;
+            }
+            if let Some(last) = last_match {
+                score -= (offset - last - 1).min(10) as i64;
+            }
+            positions.push(offset);
+            last_match = Some(offset);
+            wanted.next();
+        }
+        previous = Some((offset, c));
+    }
+
+    if wanted.peek().is_some() {
+        return None;
+    }
+    Some((score, positions))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::path::Path;
+
+    fn repo_with_files(
+        label: &str,
+        files: &[&str],
+    ) -> (crate::test_support::TempDir, git2::Repository) {
+        let (dir, repo) = crate::test_support::init_repo(&format!("quickopen-{label}"));
+        stage(&dir, &repo, files);
+        (dir, repo)
+    }
+
+    fn stage(dir: &Path, repo: &git2::Repository, files: &[&str]) {
+        let mut index = repo.index().expect("index");
+        for file in files {
+            let full = dir.join(file);
+            fs::create_dir_all(full.parent().unwrap()).expect("mkdir");
+            fs::write(&full, "x").expect("write");
+            index.add_path(Path::new(file)).expect("stage");
+        }
+        index.write().expect("write index");
+    }
+
+    #[test]
+    fn file_name_and_word_start_matches_rank_first() {
+        let (dir, _repo) = repo_with_files(
+            "rank",
+            &[
+                "docs/server-notes.md",
+                "src/mcp/server.rs",
+                "src/store/sqlite_store.rs",
+                "assets/serpent.svg",
+            ],
+        );
+        let quick = QuickOpen::default();
+        let repo = dir.to_string_lossy();
+
+        let hits = quick.query(&repo, "server", 0).expect("query");
+        assert_eq!(hits[0].path, "src/mcp/server.rs");
+        assert_eq!(hits.len(), 2);
+
+        let hits = quick.query(&repo, "ss", 1).expect("query");
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].path, "src/store/sqlite_store.rs");
+        assert_eq!(hits[0].positions, [0, 4]);
+
+        assert!(quick.query(&repo, "zzz", 0).expect("no match").is_empty());
+        assert_eq!(quick.query(&repo, "", 0).expect("all").len(), 4);
+    }
+
+    #[test]
+    fn index_changes_are_picked_up() {
+        let (dir, repo) = repo_with_files("refresh", &["a.txt"]);
+        let quick = QuickOpen::default();
+        let path = dir.to_string_lossy();
+        assert!(quick.query(&path, "new", 0).expect("query").is_empty());
+
+        // mtime granularity can hide a same-size rewrite; the size changes here.
+        stage(&dir, &repo, &["lib/new_module.rs"]);
+        let hits = quick.query(&path, "new", 0).expect("query");
+        assert_eq!(hits[0].path, "lib/new_module.rs");
+        assert!(quick.forget(&path).expect("forget"));
+    }
+}
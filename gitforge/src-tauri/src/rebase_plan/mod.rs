@@ -0,0 +1,425 @@
+//! Interactive rebase as data: the todo list for a range of commits, which
+//! a client reorders and edits, and executing it in memory.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    Pick,
+    /// Pick with a new message.
+    Reword,
+    /// Folded into the commit before it, appending its message.
+    Squash,
+    /// Folded into the commit before it, keeping that one's message.
+    Fixup,
+    Drop,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Step {
+    pub action: Action,
+    pub commit: String,
+    /// Filled in by [`get`]; ignored when applying.
+    #[serde(default)]
+    pub summary: String,
+    /// The message of the commit this step leaves at the tip, replacing
+    /// the one `action` would give it. Required for `reword`.
+    #[serde(default)]
+    pub message: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Plan {
+    /// The commit the rewritten ones go on top of.
+    pub base: String,
+    /// Oldest first.
+    pub steps: Vec<Step>,
+}
+
+impl Plan {
+    /// Steps other than picks.
+    pub fn edits(&self) -> usize {
+        self.steps
+            .iter()
+            .filter(|step| step.action != Action::Pick || step.message.is_some())
+            .count()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Applied {
+    #[serde(flatten)]
+    pub plan: Plan,
+    pub previous_head: String,
+    pub head: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Failure {
+    /// A step's change doesn't apply where it moved to. Nothing was
+    /// changed.
+    Conflict {
+        step: Step,
+        paths: Vec<String>,
+    },
+    Other(String),
+}
+
+impl std::fmt::Display for Failure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Failure::Conflict { step, paths } => write!(
+                f,
+                "{} {} conflicts in {}; nothing was changed",
+                short(&step.commit),
+                step.summary,
+                paths.join(", ")
+            ),
+            Failure::Other(message) => f.write_str(message),
+        }
+    }
+}
+
+impl From<String> for Failure {
+    fn from(message: String) -> Self {
+        Failure::Other(message)
+    }
+}
+
+impl From<Failure> for String {
+    fn from(failure: Failure) -> Self {
+        failure.to_string()
+    }
+}
+
+/// Every commit after `onto` on HEAD's first-parent history, oldest first,
+/// each picked.
+pub fn get(repo: &git2::Repository, onto: &str) -> Result<Plan, String> {
+    let base = resolve(repo, onto)?;
+    let steps = range(repo, Some(base))?
+        .iter()
+        .map(|commit| Step {
+            action: Action::Pick,
+            commit: commit.id().to_string(),
+            summary: commit.summary().unwrap_or("").to_string(),
+            message: None,
+        })
+        .collect();
+    Ok(Plan {
+        base: base.to_string(),
+        steps,
+    })
+}
+
+/// HEAD's first-parent history after `onto`, oldest first. Without `onto`
+/// it goes back to the first merge or the root.
+pub(crate) fn range(
+    repo: &git2::Repository,
+    onto: Option<git2::Oid>,
+) -> Result<Vec<git2::Commit<'_>>, String> {
+    let mut commits = Vec::new();
+    let mut next = Some(head_commit(repo)?);
+    let mut reached = false;
+    while let Some(commit) = next.take() {
+        if Some(commit.id()) == onto {
+            reached = true;
+            break;
+        }
+        if commit.parent_count() > 1 {
+            if onto.is_some() {
+                return Err(format!(
+                    "merge commit {} is in the range; only linear history can be rewritten",
+                    commit.id()
+                ));
+            }
+            break;
+        }
+        next = commit.parent(0).ok();
+        commits.push(commit);
+    }
+    if onto.is_some() && !reached {
+        return Err("onto is not a first-parent ancestor of HEAD".to_string());
+    }
+    commits.reverse();
+    Ok(commits)
+}
+
+/// Checks that `plan` lists each commit after its base exactly once and
+/// that every fold has a commit to fold into.
+pub fn validate(repo: &git2::Repository, plan: &Plan) -> Result<(), String> {
+    let base = resolve(repo, &plan.base)?;
+    let wanted: HashSet<String> = range(repo, Some(base))?
+        .iter()
+        .map(|commit| commit.id().to_string())
+        .collect();
+    let mut seen = HashSet::new();
+    let mut kept = false;
+    for step in &plan.steps {
+        if !wanted.contains(&step.commit) {
+            return Err(format!(
+                "{} is not a commit between {} and HEAD",
+                step.commit, plan.base
+            ));
+        }
+        if !seen.insert(step.commit.as_str()) {
+            return Err(format!("{} is listed twice", step.commit));
+        }
+        match step.action {
+            Action::Squash | Action::Fixup if !kept => {
+                return Err(format!(
+                    "{} has no earlier commit to fold into",
+                    step.commit
+                ))
+            }
+            Action::Reword if step.message.as_deref().is_none_or(|m| m.trim().is_empty()) => {
+                return Err(format!("reword of {} needs a message", step.commit))
+            }
+            Action::Drop => {}
+            _ => kept = true,
+        }
+    }
+    if seen.len() < wanted.len() {
+        return Err(format!(
+            "{} commits between {} and HEAD are missing; list them, with drop to remove one",
+            wanted.len() - seen.len(),
+            plan.base
+        ));
+    }
+    Ok(())
+}
+
+/// Executes `plan` and moves the current branch to the result. Every step
+/// is applied in memory first, so a conflict leaves the branch and working
+/// tree as they were. Uncommitted changes to tracked files refuse it.
+pub fn apply(repo: &git2::Repository, plan: Plan) -> Result<Applied, Failure> {
+    validate(repo, &plan)?;
+    let dirty = crate::branches::dirty_paths(repo)?;
+    if !dirty.is_empty() {
+        return Err(Failure::Other(format!(
+            "working tree has uncommitted changes in {} files; commit or stash them first",
+            dirty.len()
+        )));
+    }
+    let previous = head_commit(repo)?;
+    let signature = repo
+        .signature()
+        .or_else(|_| git2::Signature::now("GitForge", "gitforge@localhost"))
+        .map_err(|e| format!("failed to create signature: {e}"))?;
+    let find = |id: &str| {
+        git2::Oid::from_str(id)
+            .and_then(|oid| repo.find_commit(oid))
+            .map_err(|e| format!("failed to read commit {id}: {e}"))
+    };
+
+    let mut tip = find(&resolve(repo, &plan.base)?.to_string())?;
+    // Untouched leading commits keep their ids.
+    let mut rewriting = false;
+    let mut steps = Vec::with_capacity(plan.steps.len());
+    for mut step in plan.steps {
+        let commit = find(&step.commit)?;
+        step.summary = commit.summary().unwrap_or("").to_string();
+        if step.action == Action::Drop {
+            rewriting = true;
+            steps.push(step);
+            continue;
+        }
+        if !rewriting
+            && step.action == Action::Pick
+            && step.message.is_none()
+            && commit.parent_id(0).ok() == Some(tip.id())
+        {
+            tip = commit;
+            steps.push(step);
+            continue;
+        }
+        rewriting = true;
+        let mut index = repo
+            .cherrypick_commit(&commit, &tip, 0, None)
+            .map_err(|e| format!("failed to apply {}: {e}", step.commit))?;
+        if index.has_conflicts() {
+            return Err(Failure::Conflict {
+                paths: conflicts(&index),
+                step,
+            });
+        }
+        let tree = index
+            .write_tree_to(repo)
+            .and_then(|id| repo.find_tree(id))
+            .map_err(|e| format!("failed to write tree: {e}"))?;
+        let id = match step.action {
+            Action::Pick | Action::Reword => repo.commit(
+                None,
+                &commit.author(),
+                &signature,
+                step.message
+                    .as_deref()
+                    .unwrap_or(commit.message().unwrap_or("")),
+                &tree,
+                &[&tip],
+            ),
+            Action::Squash | Action::Fixup => {
+                let message = match (&step.message, step.action) {
+                    (Some(message), _) => message.clone(),
+                    (None, Action::Squash) => {
+                        squash_message(tip.message().unwrap_or(""), commit.message().unwrap_or(""))
+                    }
+                    (None, _) => tip.message().unwrap_or("").to_string(),
+                };
+                let parents: Vec<git2::Commit> = tip.parents().collect();
+                let parents: Vec<&git2::Commit> = parents.iter().collect();
+                repo.commit(None, &tip.author(), &signature, &message, &tree, &parents)
+            }
+            Action::Drop => unreachable!("drops are skipped above"),
+        }
+        .map_err(|e| format!("failed to write commit: {e}"))?;
+        tip = repo
+            .find_commit(id)
+            .map_err(|e| format!("failed to read commit {id}: {e}"))?;
+        steps.push(step);
+    }
+
+    let mut checkout = git2::build::CheckoutBuilder::new();
+    checkout.safe();
+    repo.checkout_tree(tip.as_object(), Some(&mut checkout))
+        .map_err(|e| format!("failed to check out the result: {e}"))?;
+    let reflog = format!("gitforge: rebase onto {}", plan.base);
+    match repo.head().ok().filter(|head| head.is_branch()) {
+        Some(mut head) => head.set_target(tip.id(), &reflog).map(|_| ()),
+        None => repo.set_head_detached(tip.id()),
+    }
+    .map_err(|e| format!("failed to move HEAD: {e}"))?;
+
+    Ok(Applied {
+        plan: Plan {
+            base: plan.base,
+            steps,
+        },
+        previous_head: previous.id().to_string(),
+        head: tip.id().to_string(),
+    })
+}
+
+/// Paths a step conflicts on.
+pub fn conflicts(index: &git2::Index) -> Vec<String> {
+    let mut paths: Vec<String> = index
+        .conflicts()
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter_map(|conflict| conflict.our.or(conflict.their).or(conflict.ancestor))
+        .map(|entry| String::from_utf8_lossy(&entry.path).into_owned())
+        .collect();
+    paths.dedup();
+    paths
+}
+
+/// `target`'s message followed by `squash`'s. A `squash! <subject>` line
+/// only says where it goes, so it's left out.
+fn squash_message(target: &str, squash: &str) -> String {
+    let body = match squash.strip_prefix("squash! ") {
+        Some(_) => squash.split_once('\n').map_or("", |(_, body)| body),
+        None => squash,
+    }
+    .trim();
+    if body.is_empty() {
+        return target.to_string();
+    }
+    format!("{}\n\n{body}\n", target.trim_end())
+}
+
+fn resolve(repo: &git2::Repository, rev: &str) -> Result<git2::Oid, String> {
+    repo.revparse_single(rev)
+        .and_then(|object| object.peel_to_commit())
+        .map(|commit| commit.id())
+        .map_err(|e| format!("failed to resolve {rev}: {e}"))
+}
+
+pub(crate) fn head_commit(repo: &git2::Repository) -> Result<git2::Commit<'_>, String> {
+    repo.head()
+        .and_then(|head| head.peel_to_commit())
+        .map_err(|e| format!("failed to read HEAD: {e}"))
+}
+
+fn short(id: &str) -> &str {
+    &id[..id.len().min(7)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn edited_plans_reorder_reword_squash_and_drop() {
+        let (dir, repo) = crate::test_support::init_repo("rebase-plan");
+        let sig = git2::Signature::now("GitForge Test", "test@gitforge.dev").expect("sig");
+        let commit = |path: &str, text: &str, message: &str| {
+            fs::write(dir.join(path), text).expect("write");
+            let mut index = repo.index().expect("index");
+            index.add_path(std::path::Path::new(path)).expect("add");
+            index.write().expect("write index");
+            let tree = repo
+                .find_tree(index.write_tree().expect("tree"))
+                .expect("tree");
+            let parent = repo.head().ok().and_then(|h| h.peel_to_commit().ok());
+            let parents: Vec<&git2::Commit> = parent.iter().collect();
+            repo.commit(Some("HEAD"), &sig, &sig, message, &tree, &parents)
+                .expect("commit")
+        };
+        let base = commit("base.txt", "base\n", "Base");
+        commit("a.txt", "a\n", "Add a");
+        commit("b.txt", "b\n", "Add b");
+        commit("c.txt", "c\n", "Add c");
+        commit("b.txt", "b2\n", "More b");
+
+        let mut plan = get(&repo, &base.to_string()).expect("plan");
+        let summaries: Vec<&str> = plan.steps.iter().map(|s| s.summary.as_str()).collect();
+        assert_eq!(summaries, ["Add a", "Add b", "Add c", "More b"]);
+
+        // Drop a, reword c and move it first, squash "More b" into b.
+        plan.steps[0].action = Action::Drop;
+        plan.steps[2].action = Action::Reword;
+        plan.steps[2].message = Some("Add c first\n".to_string());
+        plan.steps[3].action = Action::Squash;
+        plan.steps.swap(1, 2);
+        let mut missing = plan.clone();
+        missing.steps.pop();
+        assert!(validate(&repo, &missing).unwrap_err().contains("missing"));
+        let mut orphan = plan.clone();
+        orphan.steps[1].action = Action::Fixup;
+        orphan.steps.swap(0, 1);
+        assert!(validate(&repo, &orphan).unwrap_err().contains("fold into"));
+
+        let applied = apply(&repo, plan).expect("apply");
+        assert_eq!(applied.plan.edits(), 3);
+        let head = repo.head().unwrap().peel_to_commit().unwrap();
+        assert_eq!(head.id().to_string(), applied.head);
+        assert_eq!(head.message(), Some("Add b\n\nMore b\n"));
+        let first = head.parent(0).unwrap();
+        assert_eq!(first.message(), Some("Add c first\n"));
+        assert_eq!(first.parent_id(0).unwrap(), base);
+        assert!(!dir.join("a.txt").exists());
+        assert_eq!(fs::read_to_string(dir.join("b.txt")).unwrap(), "b2\n");
+
+        // Moving b's follow-up before b itself can't apply.
+        let mut plan = get(&repo, &base.to_string()).expect("plan");
+        commit("b.txt", "b3\n", "Even more b");
+        plan = Plan {
+            steps: std::iter::once(get(&repo, "HEAD~1").unwrap().steps[0].clone())
+                .chain(plan.steps)
+                .collect(),
+            ..plan
+        };
+        let before = repo.head().unwrap().target().unwrap();
+        match apply(&repo, plan).expect_err("conflict") {
+            Failure::Conflict { step, paths } => {
+                assert_eq!(step.summary, "Even more b");
+                assert_eq!(paths, ["b.txt"]);
+            }
+            other => panic!("{other}"),
+        }
+        assert_eq!(repo.head().unwrap().target().unwrap(), before);
+    }
+}
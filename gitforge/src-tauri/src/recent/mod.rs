@@ -0,0 +1,137 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Overrides where the recent-repositories list is kept.
+pub const RECENT_ENV: &str = "GITFORGE_RECENT";
+pub const MAX_RECENT: usize = 20;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecentRepo {
+    /// Working-tree root (or git dir for bare repositories).
+    pub path: String,
+    pub name: String,
+    pub opened_at: String,
+}
+
+/// Repositories opened in the desktop app, most recent first. Kept in a
+/// JSON file because it spans repositories, unlike `gitforge.db`.
+pub struct RecentRepos {
+    file: PathBuf,
+}
+
+impl RecentRepos {
+    /// `$GITFORGE_RECENT`, else `<data home>/gitforge/recent.json`.
+    pub fn open_default() -> Self {
+        let file = std::env::var_os(RECENT_ENV)
+            .filter(|v| !v.is_empty())
+            .map(PathBuf::from)
+            .unwrap_or_else(|| {
+                crate::db::location::xdg_data_home()
+                    .join("gitforge")
+                    .join("recent.json")
+            });
+        Self::at(file)
+    }
+
+    pub fn at(file: PathBuf) -> Self {
+        Self { file }
+    }
+
+    /// A missing or unreadable file is an empty list, not an error.
+    pub fn list(&self) -> Vec<RecentRepo> {
+        std::fs::read_to_string(&self.file)
+            .ok()
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default()
+    }
+
+    /// Moves `repo_root` to the front of the list.
+    pub fn touch(&self, repo_root: &str) -> Result<Vec<RecentRepo>, String> {
+        let mut items = self.list();
+        items.retain(|item| item.path != repo_root);
+        let name = Path::new(repo_root)
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| repo_root.to_string());
+        items.insert(
+            0,
+            RecentRepo {
+                path: repo_root.to_string(),
+                name,
+                opened_at: crate::store::now_timestamp(),
+            },
+        );
+        items.truncate(MAX_RECENT);
+        self.save(&items)?;
+        Ok(items)
+    }
+
+    pub fn forget(&self, repo_root: &str) -> Result<bool, String> {
+        let mut items = self.list();
+        let before = items.len();
+        items.retain(|item| item.path != repo_root);
+        if items.len() == before {
+            return Ok(false);
+        }
+        self.save(&items)?;
+        Ok(true)
+    }
+
+    fn save(&self, items: &[RecentRepo]) -> Result<(), String> {
+        if let Some(parent) = self.file.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("failed to create {}: {e}", parent.display()))?;
+        }
+        let json = serde_json::to_string_pretty(items)
+            .map_err(|e| format!("failed to encode recent repositories: {e}"))?;
+        std::fs::write(&self.file, json)
+            .map_err(|e| format!("failed to write {}: {e}", self.file.display()))
+    }
+}
+
+/// Root of the repository containing `path`, for picking any folder inside it.
+pub fn repo_root(path: &str) -> Result<String, String> {
+    let repo = git2::Repository::discover(path)
+        .map_err(|_| format!("{path} is not inside a git repository"))?;
+    let root = repo.workdir().unwrap_or_else(|| repo.path());
+    let root = root.canonicalize().unwrap_or_else(|_| root.to_path_buf());
+    Ok(root.to_string_lossy().trim_end_matches('/').to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::temp_path;
+
+    #[test]
+    fn touch_moves_to_front_and_caps_the_list() {
+        let dir = temp_path("recent-list");
+        let recent = RecentRepos::at(dir.join("recent.json"));
+        assert!(recent.list().is_empty());
+
+        for i in 0..MAX_RECENT + 2 {
+            recent.touch(&format!("/repos/r{i}")).expect("touch");
+        }
+        recent.touch("/repos/r5").expect("touch again");
+
+        let items = recent.list();
+        assert_eq!(items.len(), MAX_RECENT);
+        assert_eq!(items[0].path, "/repos/r5");
+        assert_eq!(items[0].name, "r5");
+        assert_eq!(items.iter().filter(|i| i.path == "/repos/r5").count(), 1);
+
+        assert!(recent.forget("/repos/r5").expect("forget"));
+        assert!(!recent.forget("/repos/r5").expect("forget twice"));
+    }
+
+    #[test]
+    fn repo_root_is_found_from_a_subdirectory() {
+        let dir = temp_path("recent-root");
+        git2::Repository::init(&dir).expect("init repo");
+        std::fs::create_dir_all(dir.join("src/deep")).expect("mkdir");
+
+        let root = repo_root(&dir.join("src/deep").to_string_lossy()).expect("root");
+        assert_eq!(PathBuf::from(root), dir.canonicalize().expect("canonical"));
+        assert!(repo_root(&std::env::temp_dir().to_string_lossy()).is_err());
+    }
+}
@@ -0,0 +1,452 @@
+use serde::Serialize;
+use std::fmt;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::auth::credentials::Credentials;
+use crate::auth::{self, github, Forge};
+
+pub const DEFAULT_PREFIX: &str = "v";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Bump {
+    Patch,
+    Minor,
+    Major,
+}
+
+impl Bump {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "patch" => Some(Bump::Patch),
+            "minor" => Some(Bump::Minor),
+            "major" => Some(Bump::Major),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub struct Version {
+    pub major: u64,
+    pub minor: u64,
+    pub patch: u64,
+}
+
+impl Version {
+    /// `1.2.3`; pre-releases and build metadata are not release tags.
+    pub fn parse(text: &str) -> Option<Self> {
+        let mut parts = text.split('.').map(|part| {
+            let plain = !part.is_empty() && part.bytes().all(|b| b.is_ascii_digit());
+            plain.then(|| part.parse().ok()).flatten()
+        });
+        let version = Version {
+            major: parts.next()??,
+            minor: parts.next()??,
+            patch: parts.next()??,
+        };
+        parts.next().is_none().then_some(version)
+    }
+
+    /// Before 1.0 a breaking change only bumps the minor version, as semver
+    /// leaves 0.x unstable.
+    pub fn bump(self, bump: Bump) -> Self {
+        match bump {
+            Bump::Major if self.major == 0 => self.bump(Bump::Minor),
+            Bump::Major => Version {
+                major: self.major + 1,
+                minor: 0,
+                patch: 0,
+            },
+            Bump::Minor => Version {
+                minor: self.minor + 1,
+                patch: 0,
+                ..self
+            },
+            Bump::Patch => Version {
+                patch: self.patch + 1,
+                ..self
+            },
+        }
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+/// A commit message in Conventional Commits form: `type(scope)!: summary`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Change {
+    pub kind: String,
+    pub scope: Option<String>,
+    /// A `!` after the type or a `BREAKING CHANGE:` footer.
+    pub breaking: bool,
+    pub summary: String,
+    /// Short commit id.
+    pub commit: String,
+}
+
+impl Change {
+    pub fn parse(message: &str, commit: &str) -> Option<Self> {
+        let header = message.lines().next()?;
+        let (prefix, summary) = header.split_once(':')?;
+        let summary = summary.trim();
+        let (prefix, bang) = match prefix.strip_suffix('!') {
+            Some(prefix) => (prefix, true),
+            None => (prefix, false),
+        };
+        let (kind, scope) = match prefix.split_once('(') {
+            Some((kind, scope)) => (kind, Some(scope.strip_suffix(')')?)),
+            None => (prefix, None),
+        };
+        let word = |text: &str| !text.is_empty() && !text.contains(char::is_whitespace);
+        if !word(kind) || scope.is_some_and(|scope| !word(scope)) || summary.is_empty() {
+            return None;
+        }
+        let footer = message.lines().skip(1).any(|line| {
+            line.starts_with("BREAKING CHANGE:") || line.starts_with("BREAKING-CHANGE:")
+        });
+        Some(Change {
+            kind: kind.to_ascii_lowercase(),
+            scope: scope.map(str::to_string),
+            breaking: bang || footer,
+            summary: summary.to_string(),
+            commit: commit.to_string(),
+        })
+    }
+
+    /// `feat` is a minor bump and `fix`/`perf` a patch; other types (docs,
+    /// chore, refactor, ...) don't call for a release on their own.
+    pub fn bump(&self) -> Option<Bump> {
+        if self.breaking {
+            return Some(Bump::Major);
+        }
+        match self.kind.as_str() {
+            "feat" => Some(Bump::Minor),
+            "fix" | "perf" => Some(Bump::Patch),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Plan {
+    /// The newest release tag HEAD contains, if any.
+    pub previous: Option<String>,
+    pub tag: String,
+    pub version: String,
+    pub bump: Bump,
+    /// Commit the tag points at.
+    pub target: String,
+    pub changes: Vec<Change>,
+    /// Commits since `previous` that aren't Conventional Commits.
+    pub skipped: usize,
+    /// Markdown release notes, also used as the tag message.
+    pub notes: String,
+}
+
+/// The release HEAD would become: the newest `<prefix>X.Y.Z` tag it contains
+/// bumped by its Conventional Commits since then, or by `bump` when given.
+pub fn plan(repo: &git2::Repository, prefix: &str, bump: Option<Bump>) -> Result<Plan, String> {
+    let head = repo
+        .head()
+        .and_then(|head| head.peel_to_commit())
+        .map_err(|e| format!("failed to resolve HEAD: {e}"))?;
+    let previous = latest_release(repo, prefix, head.id())?;
+
+    let mut walk = repo
+        .revwalk()
+        .map_err(|e| format!("failed to walk history: {e}"))?;
+    walk.push(head.id())
+        .map_err(|e| format!("failed to walk history: {e}"))?;
+    if let Some((_, _, base)) = &previous {
+        walk.hide(*base)
+            .map_err(|e| format!("failed to walk history: {e}"))?;
+    }
+    let mut changes = Vec::new();
+    let mut skipped = 0;
+    for oid in walk {
+        let oid = oid.map_err(|e| format!("failed to walk history: {e}"))?;
+        let commit = repo
+            .find_commit(oid)
+            .map_err(|e| format!("failed to read commit {oid}: {e}"))?;
+        let short = oid.to_string()[..7].to_string();
+        match Change::parse(commit.message().unwrap_or_default(), &short) {
+            Some(change) => changes.push(change),
+            None => skipped += 1,
+        }
+    }
+    if changes.is_empty() && skipped == 0 {
+        return Err(match &previous {
+            Some((tag, _, _)) => format!("nothing to release: HEAD is {tag}"),
+            None => "nothing to release: no commits".to_string(),
+        });
+    }
+
+    let bump = bump
+        .or_else(|| changes.iter().filter_map(Change::bump).max())
+        .ok_or("no feat, fix, perf, or breaking commits since the last release; pass a bump")?;
+    let base = previous.as_ref().map(|(_, version, _)| *version);
+    let version = base.unwrap_or_default().bump(bump);
+    let tag = format!("{prefix}{version}");
+    let notes = release_notes(&tag, &changes, today());
+    Ok(Plan {
+        previous: previous.map(|(tag, _, _)| tag),
+        tag,
+        version: version.to_string(),
+        bump,
+        target: head.id().to_string(),
+        changes,
+        skipped,
+        notes,
+    })
+}
+
+/// The highest-versioned `<prefix>X.Y.Z` tag that `head` contains.
+fn latest_release(
+    repo: &git2::Repository,
+    prefix: &str,
+    head: git2::Oid,
+) -> Result<Option<(String, Version, git2::Oid)>, String> {
+    let names = repo
+        .tag_names(Some(&format!("{prefix}*")))
+        .map_err(|e| format!("failed to list tags: {e}"))?;
+    let mut best: Option<(String, Version, git2::Oid)> = None;
+    for name in names.iter().flatten() {
+        let Some(version) = name.strip_prefix(prefix).and_then(Version::parse) else {
+            continue;
+        };
+        if best
+            .as_ref()
+            .is_some_and(|(_, newest, _)| *newest >= version)
+        {
+            continue;
+        }
+        let Ok(commit) = repo
+            .revparse_single(&format!("refs/tags/{name}"))
+            .and_then(|object| object.peel_to_commit())
+        else {
+            continue;
+        };
+        let contained = commit.id() == head
+            || repo
+                .graph_descendant_of(head, commit.id())
+                .map_err(|e| format!("failed to compare {name} with HEAD: {e}"))?;
+        if contained {
+            best = Some((name.to_string(), version, commit.id()));
+        }
+    }
+    Ok(best)
+}
+
+const SECTIONS: [(&str, &str); 3] = [
+    ("feat", "Features"),
+    ("fix", "Fixes"),
+    ("perf", "Performance"),
+];
+
+fn release_notes(tag: &str, changes: &[Change], date: String) -> String {
+    let line = |change: &Change| match &change.scope {
+        Some(scope) => format!("- **{scope}:** {} ({})\n", change.summary, change.commit),
+        None => format!("- {} ({})\n", change.summary, change.commit),
+    };
+    let mut notes = format!("## {tag} ({date})\n");
+    let breaking: Vec<&Change> = changes.iter().filter(|c| c.breaking).collect();
+    if !breaking.is_empty() {
+        notes.push_str("\n### Breaking changes\n\n");
+        breaking
+            .iter()
+            .for_each(|change| notes.push_str(&line(change)));
+    }
+    for (kind, title) in SECTIONS {
+        let section: Vec<&Change> = changes.iter().filter(|c| c.kind == kind).collect();
+        if section.is_empty() {
+            continue;
+        }
+        notes.push_str(&format!("\n### {title}\n\n"));
+        section
+            .iter()
+            .for_each(|change| notes.push_str(&line(change)));
+    }
+    notes
+}
+
+/// Today's UTC date as `YYYY-MM-DD`.
+fn today() -> String {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    let (year, month, day) = civil_date((secs / 86_400) as i64);
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+/// Year, month, and day of a day count since 1970-01-01.
+pub(crate) fn civil_date(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+/// Creates `plan`'s annotated tag with its notes as the message.
+pub fn create_tag(repo: &git2::Repository, plan: &Plan) -> Result<(), String> {
+    let target = git2::Oid::from_str(&plan.target)
+        .and_then(|oid| repo.find_object(oid, None))
+        .map_err(|e| format!("failed to find {}: {e}", plan.target))?;
+    let tagger = repo
+        .signature()
+        .or_else(|_| git2::Signature::now("GitForge", "release@gitforge.dev"))
+        .map_err(|e| format!("failed to create signature: {e}"))?;
+    repo.tag(&plan.tag, &target, &tagger, &plan.notes, false)
+        .map(|_| ())
+        .map_err(|e| format!("failed to create tag {}: {e}", plan.tag))
+}
+
+/// Pushes `plan`'s tag to `remote` and publishes it as a GitHub release,
+/// with the `gitforge auth login` token. Returns the release page.
+pub fn publish(
+    repo: &git2::Repository,
+    remote: &str,
+    plan: &Plan,
+    http: &dyn github::Transport,
+) -> Result<String, String> {
+    let mut remote_handle = repo
+        .find_remote(remote)
+        .map_err(|e| format!("failed to find remote {remote}: {e}"))?;
+    let url = remote_handle
+        .url()
+        .map(str::to_string)
+        .ok_or_else(|| format!("remote {remote} has no URL"))?;
+    let slug = github::repo_slug(&url)
+        .ok_or_else(|| format!("remote {remote} ({url}) is not a GitHub repository"))?;
+    let token =
+        auth::token(Forge::GitHub)?.ok_or("no GitHub token; run `gitforge auth login github`")?;
+
+    let mut credentials = Credentials::for_remote(repo, remote)?;
+    let mut callbacks = git2::RemoteCallbacks::new();
+    callbacks.credentials(move |url, username, allowed| credentials.next(url, username, allowed));
+    let mut options = git2::PushOptions::new();
+    options.remote_callbacks(callbacks);
+    let refspec = format!("refs/tags/{0}:refs/tags/{0}", plan.tag);
+    remote_handle
+        .push(&[refspec], Some(&mut options))
+        .map_err(|e| format!("failed to push {} to {remote}: {e}", plan.tag))?;
+    github::create_release(
+        http,
+        &token.secret,
+        &slug,
+        &github::NewRelease {
+            tag_name: &plan.tag,
+            target_commitish: &plan.target,
+            name: &plan.tag,
+            body: &plan.notes,
+            draft: false,
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_repo(label: &str) -> (crate::test_support::TempDir, git2::Repository) {
+        crate::test_support::init_repo(&format!("release-{label}"))
+    }
+
+    fn commit(repo: &git2::Repository, message: &str) -> git2::Oid {
+        let sig = git2::Signature::now("GitForge", "test@gitforge.dev").expect("signature");
+        let tree_id = repo.index().expect("index").write_tree().expect("tree");
+        let tree = repo.find_tree(tree_id).expect("find tree");
+        let parent = repo.head().ok().and_then(|h| h.peel_to_commit().ok());
+        let parents: Vec<&git2::Commit> = parent.iter().collect();
+        repo.commit(Some("HEAD"), &sig, &sig, message, &tree, &parents)
+            .expect("commit")
+    }
+
+    #[test]
+    fn conventional_headers_parse_and_pick_a_bump() {
+        let feat = Change::parse("feat(cli): add release\n\nbody", "abc1234").expect("feat");
+        assert_eq!(feat.scope.as_deref(), Some("cli"));
+        assert_eq!(feat.bump(), Some(Bump::Minor));
+        let bang = Change::parse("fix!: drop v1 API", "abc1234").expect("bang");
+        assert_eq!(bang.bump(), Some(Bump::Major));
+        let footer =
+            Change::parse("perf: faster\n\nBREAKING CHANGE: new format", "a").expect("footer");
+        assert!(footer.breaking);
+        assert_eq!(
+            Change::parse("docs: readme", "a").and_then(|c| c.bump()),
+            None
+        );
+        for message in ["Merge branch 'x'", "feat add thing", "fix(): x", "feat: "] {
+            assert_eq!(Change::parse(message, "a"), None, "{message}");
+        }
+        assert_eq!(
+            Version::parse("1.2.3").map(|v| v.to_string()),
+            Some("1.2.3".into())
+        );
+        assert_eq!(Version::parse("1.2.3-rc.1"), None);
+        assert_eq!(
+            Version::parse("0.4.1").map(|v| v.bump(Bump::Major).to_string()),
+            Some("0.5.0".into())
+        );
+        assert_eq!(civil_date(0), (1970, 1, 1));
+        assert_eq!(civil_date(20_740), (2026, 10, 14));
+    }
+
+    #[test]
+    fn plans_from_the_last_release_tag() {
+        let (_dir, repo) = temp_repo("plan");
+        commit(&repo, "feat: first");
+        let first = plan(&repo, DEFAULT_PREFIX, None).expect("first plan");
+        assert_eq!(
+            (first.previous.as_deref(), first.tag.as_str()),
+            (None, "v0.1.0")
+        );
+        create_tag(&repo, &first).expect("tag");
+
+        commit(&repo, "fix(store): handle empty pages");
+        commit(&repo, "chore: tidy");
+        commit(
+            &repo,
+            "feat(mcp)!: rename tools\n\nBREAKING CHANGE: new names",
+        );
+        let next = plan(&repo, DEFAULT_PREFIX, None).expect("next plan");
+        assert_eq!(next.previous.as_deref(), Some("v0.1.0"));
+        assert_eq!((next.bump, next.tag.as_str()), (Bump::Major, "v0.2.0"));
+        assert_eq!(next.changes.len(), 3);
+        assert!(next
+            .notes
+            .contains("### Breaking changes\n\n- **mcp:** rename tools"));
+        assert!(next
+            .notes
+            .contains("### Fixes\n\n- **store:** handle empty pages"));
+        assert!(!next.notes.contains("tidy"));
+
+        let forced = plan(&repo, DEFAULT_PREFIX, Some(Bump::Patch)).expect("forced");
+        assert_eq!(forced.tag, "v0.1.1");
+        create_tag(&repo, &next).expect("tag");
+        let err = create_tag(&repo, &next).unwrap_err();
+        assert!(err.contains("v0.2.0"), "{err}");
+        let err = plan(&repo, DEFAULT_PREFIX, None).unwrap_err();
+        assert!(err.contains("nothing to release"), "{err}");
+
+        commit(&repo, "docs: explain");
+        let err = plan(&repo, DEFAULT_PREFIX, None).unwrap_err();
+        assert!(err.contains("pass a bump"), "{err}");
+        let tagged = repo
+            .revparse_single("refs/tags/v0.2.0")
+            .and_then(|tag| tag.peel_to_commit())
+            .expect("tagged commit");
+        assert_eq!(tagged.id().to_string(), next.target);
+    }
+}
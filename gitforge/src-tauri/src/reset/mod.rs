@@ -0,0 +1,174 @@
+//! `git reset`: moving the current branch to another commit, taking the
+//! index and working tree along as far as the mode says.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Mode {
+    /// Only HEAD moves; the undone changes stay staged.
+    Soft,
+    /// The index follows; the undone changes stay in the working tree.
+    #[default]
+    Mixed,
+    /// Everything follows; uncommitted changes to tracked files are lost.
+    Hard,
+}
+
+impl Mode {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        match text {
+            "soft" => Ok(Self::Soft),
+            "mixed" => Ok(Self::Mixed),
+            "hard" => Ok(Self::Hard),
+            other => Err(format!(
+                "unknown mode '{other}': expected soft, mixed or hard"
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Dropped {
+    pub oid: String,
+    pub summary: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Reset {
+    pub mode: Mode,
+    pub previous_head: String,
+    pub head: String,
+    /// Commits HEAD no longer reaches, newest first; still in the reflog.
+    pub dropped: Vec<Dropped>,
+    /// Tracked files whose uncommitted changes a hard reset threw away.
+    pub discarded: Vec<String>,
+}
+
+/// The commit `rev` names and the commits resetting HEAD to it would
+/// leave behind, so callers can check protection before [`reset`].
+pub fn plan<'r>(
+    repo: &'r git2::Repository,
+    rev: &str,
+) -> Result<(git2::Commit<'r>, Vec<Dropped>), String> {
+    let target = repo
+        .revparse_single(rev)
+        .and_then(|object| object.peel_to_commit())
+        .map_err(|e| format!("unknown commit '{rev}': {e}"))?;
+    let head = repo
+        .head()
+        .and_then(|head| head.peel_to_commit())
+        .map_err(|e| format!("HEAD has no commit to reset: {e}"))?;
+    let mut walk = repo
+        .revwalk()
+        .map_err(|e| format!("failed to walk history: {e}"))?;
+    walk.set_sorting(git2::Sort::TOPOLOGICAL | git2::Sort::TIME)
+        .and_then(|()| walk.push(head.id()))
+        .and_then(|()| walk.hide(target.id()))
+        .map_err(|e| format!("failed to walk history: {e}"))?;
+    let dropped = walk
+        .map(|oid| {
+            let commit = oid
+                .and_then(|oid| repo.find_commit(oid))
+                .map_err(|e| format!("failed to read commit: {e}"))?;
+            Ok(Dropped {
+                oid: commit.id().to_string(),
+                summary: commit.summary().unwrap_or("").to_string(),
+            })
+        })
+        .collect::<Result<_, String>>()?;
+    Ok((target, dropped))
+}
+
+/// What resetting HEAD to `rev` in `mode` would do, without doing it.
+pub fn preview(repo: &git2::Repository, rev: &str, mode: Mode) -> Result<Reset, String> {
+    planned(repo, rev, mode).map(|(_, reset)| reset)
+}
+
+/// Resets HEAD to `rev` in `mode`. The commits it leaves behind are
+/// reported, and stay reachable from the reflog.
+pub fn reset(repo: &git2::Repository, rev: &str, mode: Mode) -> Result<Reset, String> {
+    let (target, reset) = planned(repo, rev, mode)?;
+    let kind = match mode {
+        Mode::Soft => git2::ResetType::Soft,
+        Mode::Mixed => git2::ResetType::Mixed,
+        Mode::Hard => git2::ResetType::Hard,
+    };
+    repo.reset(target.as_object(), kind, None)
+        .map_err(|e| format!("failed to reset to {}: {e}", target.id()))?;
+    Ok(reset)
+}
+
+fn planned<'r>(
+    repo: &'r git2::Repository,
+    rev: &str,
+    mode: Mode,
+) -> Result<(git2::Commit<'r>, Reset), String> {
+    let previous = repo
+        .head()
+        .and_then(|head| head.peel_to_commit())
+        .map_err(|e| format!("HEAD has no commit to reset: {e}"))?;
+    let (target, dropped) = plan(repo, rev)?;
+    let discarded = match mode {
+        Mode::Hard => crate::branches::dirty_paths(repo)?,
+        Mode::Soft | Mode::Mixed => Vec::new(),
+    };
+    let reset = Reset {
+        mode,
+        previous_head: previous.id().to_string(),
+        head: target.id().to_string(),
+        dropped,
+        discarded,
+    };
+    Ok((target, reset))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::path::Path;
+
+    #[test]
+    fn resets_soft_mixed_and_hard() {
+        let (dir, repo) = crate::test_support::init_repo("reset");
+        let sig = git2::Signature::now("GitForge Test", "test@gitforge.dev").expect("sig");
+        let commit = |text: &str, message: &str| {
+            fs::write(dir.join("a.txt"), text).expect("write");
+            let mut index = repo.index().expect("index");
+            index.add_path(Path::new("a.txt")).expect("add");
+            index.write().expect("write index");
+            let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+            let parent = repo.head().ok().and_then(|h| h.peel_to_commit().ok());
+            let parents: Vec<&git2::Commit> = parent.iter().collect();
+            repo.commit(Some("HEAD"), &sig, &sig, message, &tree, &parents)
+                .expect("commit")
+                .to_string()
+        };
+        let base = commit("one\n", "base");
+        commit("two\n", "Botched");
+        let status =
+            |repo: &git2::Repository| repo.status_file(Path::new("a.txt")).expect("status");
+
+        let soft = reset(&repo, "HEAD~1", Mode::Soft).expect("soft");
+        assert_eq!(soft.head, base);
+        assert_eq!(soft.dropped.len(), 1);
+        assert_eq!(soft.dropped[0].summary, "Botched");
+        assert_eq!(status(&repo), git2::Status::INDEX_MODIFIED);
+
+        let botched = soft.dropped[0].oid.clone();
+        reset(&repo, &botched, Mode::Soft).expect("forward again");
+        let mixed = reset(&repo, "HEAD~1", Mode::Mixed).expect("mixed");
+        assert!(mixed.discarded.is_empty());
+        assert_eq!(status(&repo), git2::Status::WT_MODIFIED);
+
+        let planned = preview(&repo, "HEAD", Mode::Hard).expect("preview");
+        assert_eq!(planned.discarded, ["a.txt"]);
+        assert_eq!(status(&repo), git2::Status::WT_MODIFIED);
+        let hard = reset(&repo, "HEAD", Mode::Hard).expect("hard");
+        assert!(hard.dropped.is_empty());
+        assert_eq!(hard.discarded, ["a.txt"]);
+        assert_eq!(fs::read_to_string(dir.join("a.txt")).unwrap(), "one\n");
+        assert!(Mode::parse("keep").is_err());
+    }
+}
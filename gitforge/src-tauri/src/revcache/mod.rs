@@ -0,0 +1,284 @@
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::{Arc, Mutex};
+use std::time::UNIX_EPOCH;
+
+/// Answers kept before the oldest are evicted.
+pub const MAX_ENTRIES: usize = 4096;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Query {
+    AheadBehind(git2::Oid, git2::Oid),
+    MergeBase(git2::Oid, git2::Oid),
+    History(git2::Oid),
+}
+
+#[derive(Clone)]
+enum Answer {
+    AheadBehind(usize, usize),
+    MergeBase(Option<git2::Oid>),
+    /// Newest first; `complete` once the walk reached the root commits.
+    History {
+        oids: Arc<Vec<git2::Oid>>,
+        complete: bool,
+    },
+}
+
+#[derive(Default)]
+struct Entries {
+    answers: HashMap<(PathBuf, Query), Answer>,
+    order: VecDeque<(PathBuf, Query)>,
+}
+
+/// Memoized rev-walk results. Everything is keyed by commit ids, whose
+/// ancestry never changes, so entries stay valid until evicted; only the
+/// repository (shallow clones differ) is part of the key. Cloning shares
+/// the cache.
+#[derive(Clone, Default)]
+pub struct RevCache {
+    entries: Arc<Mutex<Entries>>,
+}
+
+impl RevCache {
+    /// Commits reachable from `local` but not `upstream`, and vice versa.
+    pub fn ahead_behind(
+        &self,
+        repo: &git2::Repository,
+        local: git2::Oid,
+        upstream: git2::Oid,
+    ) -> Result<(usize, usize), String> {
+        let query = Query::AheadBehind(local, upstream);
+        if let Some(Answer::AheadBehind(ahead, behind)) = self.get(repo, &query)? {
+            return Ok((ahead, behind));
+        }
+        let (ahead, behind) = repo
+            .graph_ahead_behind(local, upstream)
+            .map_err(|e| format!("failed to compare {local} with {upstream}: {e}"))?;
+        self.put(repo, query, Answer::AheadBehind(ahead, behind))?;
+        Ok((ahead, behind))
+    }
+
+    /// Best common ancestor, or `None` for unrelated histories.
+    pub fn merge_base(
+        &self,
+        repo: &git2::Repository,
+        a: git2::Oid,
+        b: git2::Oid,
+    ) -> Result<Option<git2::Oid>, String> {
+        let (a, b) = (a.min(b), a.max(b));
+        let query = Query::MergeBase(a, b);
+        if let Some(Answer::MergeBase(base)) = self.get(repo, &query)? {
+            return Ok(base);
+        }
+        let base = match repo.merge_base(a, b) {
+            Ok(base) => Some(base),
+            Err(e) if e.code() == git2::ErrorCode::NotFound => None,
+            Err(e) => return Err(format!("failed to find merge base of {a} and {b}: {e}")),
+        };
+        self.put(repo, query, Answer::MergeBase(base))?;
+        Ok(base)
+    }
+
+    /// The first `count` commits reachable from `tip`, newest first (by
+    /// commit time, parents after children). Shorter when history is.
+    pub fn history(
+        &self,
+        repo: &git2::Repository,
+        tip: git2::Oid,
+        count: usize,
+    ) -> Result<Vec<git2::Oid>, String> {
+        let query = Query::History(tip);
+        if let Some(Answer::History { oids, complete }) = self.get(repo, &query)? {
+            if complete || oids.len() >= count {
+                return Ok(oids.iter().take(count).copied().collect());
+            }
+        }
+
+        let mut walk = repo
+            .revwalk()
+            .map_err(|e| format!("failed to start history walk: {e}"))?;
+        walk.set_sorting(git2::Sort::TOPOLOGICAL | git2::Sort::TIME)
+            .and_then(|_| walk.push(tip))
+            .map_err(|e| format!("failed to walk from {tip}: {e}"))?;
+        let oids = walk
+            .take(count.saturating_add(1))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("failed to walk from {tip}: {e}"))?;
+        let complete = oids.len() <= count;
+        let oids: Vec<git2::Oid> = oids.into_iter().take(count).collect();
+        let answer = Answer::History {
+            oids: Arc::new(oids.clone()),
+            complete,
+        };
+        self.put(repo, query, answer)?;
+        Ok(oids)
+    }
+
+    fn get(&self, repo: &git2::Repository, query: &Query) -> Result<Option<Answer>, String> {
+        let key = (repo.path().to_path_buf(), query.clone());
+        Ok(self.lock().answers.get(&key).cloned())
+    }
+
+    fn put(&self, repo: &git2::Repository, query: Query, answer: Answer) -> Result<(), String> {
+        let key = (repo.path().to_path_buf(), query);
+        let mut entries = self.lock();
+        if entries.answers.insert(key.clone(), answer).is_none() {
+            entries.order.push_back(key);
+        }
+        while entries.order.len() > MAX_ENTRIES {
+            if let Some(oldest) = entries.order.pop_front() {
+                entries.answers.remove(&oldest);
+            }
+        }
+        Ok(())
+    }
+
+    /// A panic between updating `answers` and `order` leaves them out of
+    /// step, so the cache starts over.
+    fn lock(&self) -> std::sync::MutexGuard<'_, Entries> {
+        crate::sync::lock_or_repair(&self.entries, |entries| *entries = Entries::default())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CommitGraphFile {
+    pub present: bool,
+    /// Single file or the split chain's directory.
+    pub path: String,
+    pub bytes: u64,
+    /// Seconds since the unix epoch.
+    pub modified: Option<u64>,
+}
+
+/// git's commit-graph for `repo`. libgit2 picks it up automatically for
+/// walks and ancestry checks when present.
+pub fn commit_graph_file(repo: &git2::Repository) -> CommitGraphFile {
+    let info = repo.path().join("objects").join("info");
+    let single = info.join("commit-graph");
+    let chain = info.join("commit-graphs");
+    let path = if chain.is_dir() { chain } else { single };
+
+    let bytes = if path.is_dir() {
+        std::fs::read_dir(&path)
+            .map(|dir| {
+                dir.flatten()
+                    .filter_map(|entry| entry.metadata().ok())
+                    .map(|meta| meta.len())
+                    .sum()
+            })
+            .unwrap_or(0)
+    } else {
+        std::fs::metadata(&path).map(|meta| meta.len()).unwrap_or(0)
+    };
+    let modified = std::fs::metadata(&path)
+        .and_then(|meta| meta.modified())
+        .ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map(|age| age.as_secs());
+
+    CommitGraphFile {
+        present: path.exists(),
+        path: path.to_string_lossy().to_string(),
+        bytes,
+        modified,
+    }
+}
+
+/// (Re)writes the commit-graph for everything reachable from refs, with
+/// changed-path filters. libgit2 can read commit-graphs but not write
+/// them, so this runs the `git` CLI.
+pub fn write_commit_graph(repo: &git2::Repository) -> Result<CommitGraphFile, String> {
+    run_git(
+        repo.path(),
+        &["commit-graph", "write", "--reachable", "--changed-paths"],
+    )?;
+    Ok(commit_graph_file(repo))
+}
+
+fn run_git(git_dir: &Path, args: &[&str]) -> Result<(), String> {
+    let output = Command::new("git")
+        .arg("--git-dir")
+        .arg(git_dir)
+        .args(args)
+        .output()
+        .map_err(|e| format!("failed to run git: {e}"))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("git {} failed: {}", args.join(" "), stderr.trim()));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_repo() -> (crate::test_support::TempDir, git2::Repository) {
+        crate::test_support::init_repo("revcache")
+    }
+
+    fn commit(repo: &git2::Repository, parents: &[git2::Oid], message: &str) -> git2::Oid {
+        let sig = git2::Signature::now("Test", "test@example.com").expect("signature");
+        let tree_id = repo
+            .treebuilder(None)
+            .and_then(|builder| builder.write())
+            .expect("empty tree");
+        let tree = repo.find_tree(tree_id).expect("tree");
+        let parents: Vec<git2::Commit> = parents
+            .iter()
+            .map(|oid| repo.find_commit(*oid).expect("parent"))
+            .collect();
+        let parents: Vec<&git2::Commit> = parents.iter().collect();
+        repo.commit(None, &sig, &sig, message, &tree, &parents)
+            .expect("commit")
+    }
+
+    #[test]
+    fn answers_are_computed_once_and_reused() {
+        let (_dir, repo) = temp_repo();
+        let root = commit(&repo, &[], "root");
+        let main = commit(&repo, &[root], "main");
+        let feature = commit(&repo, &[root], "feature");
+        let feature2 = commit(&repo, &[feature], "feature 2");
+        let cache = RevCache::default();
+
+        assert_eq!(cache.ahead_behind(&repo, feature2, main), Ok((2, 1)));
+        assert_eq!(cache.merge_base(&repo, main, feature2), Ok(Some(root)));
+        assert_eq!(cache.merge_base(&repo, feature2, main), Ok(Some(root)));
+        assert_eq!(cache.lock().answers.len(), 2);
+
+        let orphan = commit(&repo, &[], "orphan");
+        assert_eq!(cache.merge_base(&repo, orphan, main), Ok(None));
+
+        assert_eq!(
+            cache.history(&repo, feature2, 2),
+            Ok(vec![feature2, feature])
+        );
+        assert_eq!(
+            cache.history(&repo, feature2, 10),
+            Ok(vec![feature2, feature, root])
+        );
+        // The complete walk now answers any length.
+        assert_eq!(cache.history(&repo, feature2, 1), Ok(vec![feature2]));
+    }
+
+    #[test]
+    fn commit_graph_is_written_with_the_git_cli() {
+        let (_dir, repo) = temp_repo();
+        let root = commit(&repo, &[], "root");
+        repo.reference("refs/heads/main", root, true, "test")
+            .expect("branch");
+        assert!(!commit_graph_file(&repo).present);
+
+        match write_commit_graph(&repo) {
+            Ok(file) => {
+                assert!(file.present);
+                assert!(file.bytes > 0);
+            }
+            // No git on PATH: nothing to assert about the file.
+            Err(e) => assert!(e.starts_with("failed to run git"), "{e}"),
+        }
+    }
+}
@@ -0,0 +1,180 @@
+//! `git revert`: undoing a commit with a new one, or with staged changes
+//! left for the caller to commit.
+
+use serde::Serialize;
+
+use crate::cherry_pick::advance_head;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Revert {
+    pub reverted: String,
+    pub summary: String,
+    /// The revert commit; `None` when the revert was only staged.
+    pub commit: Option<String>,
+    pub staged: bool,
+    /// Paths the revert changes.
+    pub files: Vec<String>,
+}
+
+/// Undoes `rev` on HEAD. The revert is computed in memory first, so one
+/// that conflicts with later changes is refused, naming the paths, and
+/// leaves nothing behind. `mainline` picks the parent a merge commit is
+/// reverted to. With `no_commit` the changes are staged instead, and HEAD
+/// stays put. Uncommitted changes to tracked files refuse it.
+pub fn revert(
+    repo: &git2::Repository,
+    rev: &str,
+    mainline: Option<u32>,
+    no_commit: bool,
+) -> Result<Revert, String> {
+    let commit = repo
+        .revparse_single(rev)
+        .and_then(|object| object.peel_to_commit())
+        .map_err(|e| format!("unknown commit '{rev}': {e}"))?;
+    let mainline = match (commit.parent_count(), mainline) {
+        (0, _) => {
+            return Err(format!(
+                "{} is a root commit; nothing to revert to",
+                commit.id()
+            ))
+        }
+        (1, _) => 0,
+        (_, Some(parent)) => parent,
+        (_, None) => {
+            return Err(format!(
+                "{} is a merge; pass mainline to pick the parent to revert to",
+                commit.id()
+            ))
+        }
+    };
+    let dirty = crate::branches::dirty_paths(repo)?;
+    if !dirty.is_empty() {
+        return Err(format!(
+            "working tree has uncommitted changes in {} files; commit or stash them first",
+            dirty.len()
+        ));
+    }
+    let head = repo
+        .head()
+        .and_then(|head| head.peel_to_commit())
+        .map_err(|e| format!("HEAD has no commit to revert on: {e}"))?;
+
+    let mut index = repo
+        .revert_commit(&commit, &head, mainline, None)
+        .map_err(|e| format!("failed to revert {}: {e}", commit.id()))?;
+    if index.has_conflicts() {
+        return Err(format!(
+            "reverting {} conflicts with later changes to {}",
+            commit.id(),
+            crate::rebase_plan::conflicts(&index).join(", ")
+        ));
+    }
+    let tree = index
+        .write_tree_to(repo)
+        .and_then(|id| repo.find_tree(id))
+        .map_err(|e| format!("failed to write tree: {e}"))?;
+    if tree.id() == head.tree_id() {
+        return Err(format!("{} is already undone on HEAD", commit.id()));
+    }
+    let head_tree = head
+        .tree()
+        .map_err(|e| format!("failed to read tree: {e}"))?;
+    let files = repo
+        .diff_tree_to_tree(Some(&head_tree), Some(&tree), None)
+        .map_err(|e| format!("failed to diff the revert: {e}"))?
+        .deltas()
+        .filter_map(|delta| delta.new_file().path().or_else(|| delta.old_file().path()))
+        .map(|path| path.to_string_lossy().into_owned())
+        .collect();
+    let summary = commit.summary().unwrap_or("").to_string();
+
+    let new_commit = if no_commit {
+        let mut checkout = git2::build::CheckoutBuilder::new();
+        checkout.safe();
+        repo.checkout_tree(tree.as_object(), Some(&mut checkout))
+            .map_err(|e| format!("failed to stage the revert: {e}"))?;
+        None
+    } else {
+        let signature = repo
+            .signature()
+            .or_else(|_| git2::Signature::now("GitForge", "gitforge@localhost"))
+            .map_err(|e| format!("failed to create signature: {e}"))?;
+        let message = format!(
+            "Revert \"{summary}\"\n\nThis reverts commit {}.\n",
+            commit.id()
+        );
+        let id = repo
+            .commit(None, &signature, &signature, &message, &tree, &[&head])
+            .map_err(|e| format!("failed to write commit: {e}"))?;
+        let reverted = repo
+            .find_commit(id)
+            .map_err(|e| format!("failed to read commit {id}: {e}"))?;
+        advance_head(
+            repo,
+            &reverted,
+            &format!("gitforge: revert {}", commit.id()),
+        )?;
+        Some(id.to_string())
+    };
+    Ok(Revert {
+        reverted: commit.id().to_string(),
+        summary,
+        commit: new_commit,
+        staged: no_commit,
+        files,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::path::Path;
+
+    #[test]
+    fn reverts_with_a_commit_or_staged_and_refuses_conflicts() {
+        let (dir, repo) = crate::test_support::init_repo("revert");
+        let sig = git2::Signature::now("GitForge Test", "test@gitforge.dev").expect("sig");
+        let commit = |path: &str, text: &str, message: &str| {
+            fs::write(dir.join(path), text).expect("write");
+            let mut index = repo.index().expect("index");
+            index.add_path(Path::new(path)).expect("add");
+            index.write().expect("write index");
+            let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+            let parent = repo.head().ok().and_then(|h| h.peel_to_commit().ok());
+            let parents: Vec<&git2::Commit> = parent.iter().collect();
+            repo.commit(Some("HEAD"), &sig, &sig, message, &tree, &parents)
+                .expect("commit")
+                .to_string()
+        };
+        commit("a.txt", "one\n", "base");
+        commit("b.txt", "b\n", "Add b");
+        let edit = commit("a.txt", "two\n", "Edit a");
+        commit("a.txt", "three\n", "Edit a again");
+
+        assert!(revert(&repo, &edit, None, false).is_err_and(|e| e.contains("a.txt")));
+        assert!(repo.statuses(None).unwrap().is_empty());
+
+        let staged = revert(&repo, "HEAD~2", None, true).expect("stage revert");
+        assert_eq!(
+            (staged.commit, staged.files),
+            (None, vec!["b.txt".to_string()])
+        );
+        assert!(!dir.join("b.txt").exists());
+        let index_has_b = repo
+            .index()
+            .unwrap()
+            .get_path(Path::new("b.txt"), 0)
+            .is_some();
+        assert!(!index_has_b);
+        repo.checkout_head(Some(git2::build::CheckoutBuilder::new().force()))
+            .expect("reset");
+
+        let undone = revert(&repo, "HEAD", None, false).expect("revert");
+        let head = repo.head().unwrap().peel_to_commit().unwrap();
+        assert_eq!(undone.commit, Some(head.id().to_string()));
+        assert_eq!(head.summary(), Some("Revert \"Edit a again\""));
+        assert_eq!(fs::read_to_string(dir.join("a.txt")).unwrap(), "two\n");
+        assert!(revert(&repo, "HEAD~1", None, false).is_err_and(|e| e.contains("already undone")));
+    }
+}
@@ -0,0 +1,246 @@
+//! Limits for project commands agents run through tasks and pre-commit
+//! checks: a working directory that can't leave the checkout, credentials
+//! scrubbed from the environment, CPU and memory caps, and on Linux no
+//! network.
+
+use serde::{Deserialize, Serialize};
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command};
+
+/// A `sandbox` table, at the top of `tasks.toml` or `checks.toml` for
+/// every entry, or on one entry in place of that:
+///
+/// ```toml
+/// [sandbox]
+/// cpu_seconds = 300
+/// memory_mb = 2048
+/// network = false
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Sandbox {
+    /// CPU time before the command is killed (Unix).
+    #[serde(default)]
+    pub cpu_seconds: Option<u64>,
+    /// Address space cap, in MiB (Unix).
+    #[serde(default)]
+    pub memory_mb: Option<u64>,
+    /// `false` runs the command in its own network namespace with no
+    /// interfaces up (Linux only; refused elsewhere rather than ignored).
+    #[serde(default = "yes")]
+    pub network: bool,
+    /// Drop inherited variables that look like credentials; ones the
+    /// entry sets itself are kept.
+    #[serde(default = "yes")]
+    pub scrub_env: bool,
+}
+
+impl Default for Sandbox {
+    fn default() -> Self {
+        Self {
+            cpu_seconds: None,
+            memory_mb: None,
+            network: true,
+            scrub_env: true,
+        }
+    }
+}
+
+fn yes() -> bool {
+    true
+}
+
+/// Name fragments of inherited variables [`Sandbox::scrub_env`] drops.
+const SECRET_NAMES: [&str; 9] = [
+    "TOKEN",
+    "SECRET",
+    "PASSWORD",
+    "PASSWD",
+    "CREDENTIAL",
+    "PRIVATE_KEY",
+    "API_KEY",
+    "ACCESS_KEY",
+    "SSH_AUTH_SOCK",
+];
+
+/// Whether `name` is one [`Sandbox::scrub_env`] drops.
+pub fn looks_secret(name: &str) -> bool {
+    let name = name.to_ascii_uppercase();
+    SECRET_NAMES.iter().any(|fragment| name.contains(fragment))
+}
+
+/// `dir` under `root`, refused if it resolves anywhere else, through `..`
+/// or a symlink.
+pub fn confine(root: &Path, dir: &str) -> Result<PathBuf, String> {
+    let root = root
+        .canonicalize()
+        .map_err(|e| format!("failed to resolve {}: {e}", root.display()))?;
+    let path = root
+        .join(dir)
+        .canonicalize()
+        .map_err(|e| format!("failed to resolve directory '{dir}': {e}"))?;
+    if !path.starts_with(&root) || !path.is_dir() {
+        return Err(format!(
+            "directory '{dir}' is outside the checkout or not a directory"
+        ));
+    }
+    Ok(path)
+}
+
+/// Applies `sandbox` to `command`, before it is given its own variables.
+/// The command also gets its own process group, so [`kill`] stops
+/// whatever it started.
+pub fn apply(command: &mut Command, sandbox: &Sandbox) -> Result<(), String> {
+    apply_with_env(command, sandbox, std::env::vars_os())
+}
+
+/// [`apply`] for a command that inherits `env` instead of GitForge's own
+/// environment.
+pub fn apply_with_env<K, V>(
+    command: &mut Command,
+    sandbox: &Sandbox,
+    env: impl IntoIterator<Item = (K, V)>,
+) -> Result<(), String>
+where
+    K: AsRef<OsStr>,
+    V: AsRef<OsStr>,
+{
+    command.env_clear();
+    for (name, value) in env {
+        if !(sandbox.scrub_env && looks_secret(&name.as_ref().to_string_lossy())) {
+            command.env(name, value);
+        }
+    }
+    if !sandbox.network && !cfg!(target_os = "linux") {
+        return Err("network = false is only supported on Linux".to_string());
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        let cpu = sandbox.cpu_seconds;
+        let memory = sandbox.memory_mb.map(|mb| mb.saturating_mul(1024 * 1024));
+        let isolate = !sandbox.network;
+        command.process_group(0);
+        // SAFETY: the hook only makes async-signal-safe system calls
+        // between fork and exec.
+        unsafe {
+            command.pre_exec(move || {
+                if let Some(seconds) = cpu {
+                    limit(libc::RLIMIT_CPU, seconds)?;
+                }
+                if let Some(bytes) = memory {
+                    limit(libc::RLIMIT_AS, bytes)?;
+                }
+                if isolate {
+                    isolate_network()?;
+                }
+                Ok(())
+            });
+        }
+    }
+    #[cfg(not(unix))]
+    if sandbox.cpu_seconds.is_some() || sandbox.memory_mb.is_some() {
+        return Err("cpu_seconds and memory_mb are only supported on Unix".to_string());
+    }
+    Ok(())
+}
+
+/// Kills `child` and, when [`apply`] gave it its own process group,
+/// everything it started.
+pub fn kill(child: &mut Child) {
+    #[cfg(unix)]
+    // SAFETY: signals the process group the child leads, if it leads one.
+    unsafe {
+        libc::kill(-(child.id() as libc::pid_t), libc::SIGKILL);
+    }
+    let _ = child.kill();
+}
+
+#[cfg(all(target_os = "linux", target_env = "gnu"))]
+type Resource = libc::__rlimit_resource_t;
+#[cfg(all(unix, not(all(target_os = "linux", target_env = "gnu"))))]
+type Resource = libc::c_int;
+
+#[cfg(unix)]
+fn limit(resource: Resource, value: u64) -> std::io::Result<()> {
+    let limit = libc::rlimit {
+        rlim_cur: value as libc::rlim_t,
+        rlim_max: value as libc::rlim_t,
+    };
+    // SAFETY: `limit` is a valid rlimit for the duration of the call.
+    if unsafe { libc::setrlimit(resource, &limit) } != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Moves the process into new user and network namespaces: it keeps its
+/// files but has no network, not even loopback.
+#[cfg(target_os = "linux")]
+fn isolate_network() -> std::io::Result<()> {
+    // SAFETY: unshare only changes the calling process's namespaces.
+    if unsafe { libc::unshare(libc::CLONE_NEWUSER | libc::CLONE_NEWNET) } != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(all(unix, not(target_os = "linux")))]
+fn isolate_network() -> std::io::Result<()> {
+    Err(std::io::Error::other("network isolation needs Linux"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn confines_directories_scrubs_secrets_and_limits_cpu() {
+        let dir = crate::test_support::temp_path("sandbox");
+        std::fs::create_dir_all(dir.join("web")).expect("create dir");
+        std::os::unix::fs::symlink("/", dir.join("escape")).expect("symlink");
+        assert_eq!(
+            confine(&dir, "web").expect("inside"),
+            dir.join("web").canonicalize().unwrap()
+        );
+        for outside in ["..", "escape", "missing"] {
+            assert!(confine(&dir, outside).is_err(), "{outside}");
+        }
+
+        assert!(looks_secret("GITHUB_TOKEN") && looks_secret("aws_secret_access_key"));
+        assert!(!looks_secret("PATH"));
+        let env = [
+            ("PATH", std::env::var("PATH").unwrap_or_default()),
+            ("GITFORGE_TEST_SANDBOX_TOKEN", "s3cret".to_string()),
+        ];
+        let run = |sandbox: &Sandbox, script: &str| {
+            let mut command = crate::precommit::shell(script);
+            apply_with_env(&mut command, sandbox, env.clone()).expect("apply");
+            command.output().expect("run")
+        };
+        let scrubbed = run(
+            &Sandbox::default(),
+            "echo ${GITFORGE_TEST_SANDBOX_TOKEN:-gone}",
+        );
+        assert_eq!(String::from_utf8_lossy(&scrubbed.stdout), "gone\n");
+        let kept = run(
+            &Sandbox {
+                scrub_env: false,
+                ..Sandbox::default()
+            },
+            "echo $GITFORGE_TEST_SANDBOX_TOKEN",
+        );
+        assert_eq!(String::from_utf8_lossy(&kept.stdout), "s3cret\n");
+
+        let spin = run(
+            &Sandbox {
+                cpu_seconds: Some(1),
+                ..Sandbox::default()
+            },
+            "while :; do :; done",
+        );
+        assert!(!spin.status.success());
+    }
+}
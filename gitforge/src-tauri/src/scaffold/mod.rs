@@ -0,0 +1,373 @@
+//! New repositories from templates. A template is a local directory or a
+//! git URL; its files are copied with `{{variable}}` placeholders filled
+//! in, in contents and paths alike, and the result gets a git repository,
+//! a metadata store and an initial commit.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::operations::{OperationKind, Operations};
+
+/// Declares a template's variables; it isn't copied.
+pub const MANIFEST: &str = "gitforge-template.toml";
+pub const DEFAULT_BRANCH: &str = "main";
+
+/// A new project to create.
+#[derive(Debug, Clone)]
+pub struct Request<'a> {
+    /// A local directory, or a URL git can clone (`https://…`, `ssh://…`,
+    /// `git@host:path`).
+    pub template: &'a str,
+    /// Must not exist yet, or be an empty directory.
+    pub dest: &'a Path,
+    pub variables: BTreeMap<String, String>,
+    /// Initial branch; defaults to [`DEFAULT_BRANCH`].
+    pub branch: Option<&'a str>,
+    /// Initial commit message; defaults to naming the template.
+    pub message: Option<&'a str>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Scaffolded {
+    pub path: String,
+    pub branch: String,
+    pub commit: String,
+    pub files: usize,
+    /// Every variable the files were rendered with.
+    pub variables: BTreeMap<String, String>,
+    /// Symlinks, which aren't copied, relative to the template.
+    pub skipped: Vec<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct Manifest {
+    #[serde(default)]
+    variables: BTreeMap<String, Variable>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct Variable {
+    default: Option<String>,
+}
+
+/// Whether `template` names a repository to clone rather than a directory.
+pub fn is_url(template: &str) -> bool {
+    template.contains("://") || template.starts_with("git@")
+}
+
+pub fn scaffold(request: &Request<'_>) -> Result<Scaffolded, String> {
+    let dest = request.dest;
+    let existed = dest.exists();
+    if existed
+        && std::fs::read_dir(dest)
+            .map_err(|e| format!("failed to read {}: {e}", dest.display()))?
+            .next()
+            .is_some()
+    {
+        return Err(format!("{} already exists and isn't empty", dest.display()));
+    }
+    let result = build(request);
+    if result.is_err() {
+        // Leave nothing half made behind.
+        if existed {
+            clear(dest);
+        } else {
+            let _ = std::fs::remove_dir_all(dest);
+        }
+    }
+    result
+}
+
+fn build(request: &Request<'_>) -> Result<Scaffolded, String> {
+    let dest = request.dest;
+    std::fs::create_dir_all(dest)
+        .map_err(|e| format!("failed to create {}: {e}", dest.display()))?;
+    let clone = if is_url(request.template) {
+        Some(clone_template(request.template)?)
+    } else {
+        None
+    };
+    let source = match &clone {
+        Some(clone) => clone.0.clone(),
+        None => PathBuf::from(request.template),
+    };
+    if !source.is_dir() {
+        return Err(format!("template {} is not a directory", request.template));
+    }
+
+    let variables = variables(&source, dest, &request.variables)?;
+    let mut skipped = Vec::new();
+    let files = copy_dir(&source, &source, dest, &variables, &mut skipped)?;
+    drop(clone);
+
+    let branch = request.branch.unwrap_or(DEFAULT_BRANCH);
+    let mut options = git2::RepositoryInitOptions::new();
+    options.initial_head(branch);
+    let repo = git2::Repository::init_opts(dest, &options)
+        .map_err(|e| format!("failed to initialize {}: {e}", dest.display()))?;
+    let dest_str = dest.to_string_lossy();
+    crate::store::open(&dest_str)?;
+
+    let mut index = repo
+        .index()
+        .map_err(|e| format!("failed to open the index: {e}"))?;
+    index
+        .add_all(["*"], git2::IndexAddOption::DEFAULT, None)
+        .and_then(|()| index.write())
+        .map_err(|e| format!("failed to stage the template: {e}"))?;
+    let tree = index
+        .write_tree()
+        .and_then(|id| repo.find_tree(id))
+        .map_err(|e| format!("failed to write the tree: {e}"))?;
+    let signature = repo
+        .signature()
+        .or_else(|_| git2::Signature::now("GitForge", "gitforge@localhost"))
+        .map_err(|e| format!("failed to create signature: {e}"))?;
+    let message = match request.message {
+        Some(message) => message.to_string(),
+        None => format!("Initial commit from {}", request.template),
+    };
+    let commit = repo
+        .commit(Some("HEAD"), &signature, &signature, &message, &tree, &[])
+        .map_err(|e| format!("failed to commit: {e}"))?;
+
+    Ok(Scaffolded {
+        path: dest_str.into_owned(),
+        branch: branch.to_string(),
+        commit: commit.to_string(),
+        files,
+        variables,
+        skipped,
+    })
+}
+
+/// A clone of the template in a temporary directory, removed on drop.
+struct TempClone(PathBuf);
+
+impl Drop for TempClone {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+fn clone_template(url: &str) -> Result<TempClone, String> {
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or_default();
+    let clone = TempClone(std::env::temp_dir().join(format!("gitforge-template-{nanos}")));
+    let operations = Operations::default();
+    let id = format!("scaffold-{nanos}");
+    let op = operations.begin(&id, OperationKind::Clone, Arc::new(|_| {}))?;
+    crate::operations::clone(&op, url, &clone.0)?;
+    Ok(clone)
+}
+
+/// `given` plus the manifest's defaults and `project_name`, the
+/// destination's directory name. Refuses if a declared variable without a
+/// default is missing.
+fn variables(
+    source: &Path,
+    dest: &Path,
+    given: &BTreeMap<String, String>,
+) -> Result<BTreeMap<String, String>, String> {
+    let manifest: Manifest = match std::fs::read_to_string(source.join(MANIFEST)) {
+        Ok(text) => toml::from_str(&text).map_err(|e| format!("invalid {MANIFEST}: {e}"))?,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Manifest::default(),
+        Err(e) => return Err(format!("failed to read {MANIFEST}: {e}")),
+    };
+    let mut variables = BTreeMap::new();
+    if let Some(name) = dest.file_name() {
+        variables.insert(
+            "project_name".to_string(),
+            name.to_string_lossy().into_owned(),
+        );
+    }
+    let mut missing = Vec::new();
+    for (name, variable) in manifest.variables {
+        match variable.default {
+            Some(default) => {
+                variables.insert(name, default);
+            }
+            None if given.contains_key(&name) || variables.contains_key(&name) => {}
+            None => missing.push(name),
+        }
+    }
+    if !missing.is_empty() {
+        return Err(format!(
+            "missing template variables: {} (declared in {MANIFEST})",
+            missing.join(", ")
+        ));
+    }
+    variables.extend(given.iter().map(|(k, v)| (k.clone(), v.clone())));
+    Ok(variables)
+}
+
+/// Copies `dir` (inside template `root`) to `dest`, rendering names and
+/// text files; returns how many files it wrote.
+fn copy_dir(
+    root: &Path,
+    dir: &Path,
+    dest: &Path,
+    variables: &BTreeMap<String, String>,
+    skipped: &mut Vec<String>,
+) -> Result<usize, String> {
+    let mut entries: Vec<_> = std::fs::read_dir(dir)
+        .map_err(|e| format!("failed to read {}: {e}", dir.display()))?
+        .collect::<Result<_, _>>()
+        .map_err(|e| format!("failed to read {}: {e}", dir.display()))?;
+    entries.sort_by_key(|entry| entry.file_name());
+
+    let mut files = 0;
+    for entry in entries {
+        let path = entry.path();
+        let name = entry.file_name();
+        if dir == root && (name == ".git" || name == MANIFEST) {
+            continue;
+        }
+        let kind = entry
+            .file_type()
+            .map_err(|e| format!("failed to read {}: {e}", path.display()))?;
+        let relative = path.strip_prefix(root).unwrap_or(&path);
+        if kind.is_symlink() {
+            skipped.push(relative.to_string_lossy().into_owned());
+            continue;
+        }
+        let rendered = render(&name.to_string_lossy(), variables);
+        if rendered.is_empty() || rendered.contains(['/', '\\']) || rendered == ".." {
+            return Err(format!(
+                "{} renders to an invalid file name {rendered:?}",
+                relative.display()
+            ));
+        }
+        let target = dest.join(rendered);
+        if kind.is_dir() {
+            std::fs::create_dir_all(&target)
+                .map_err(|e| format!("failed to create {}: {e}", target.display()))?;
+            files += copy_dir(root, &path, &target, variables, skipped)?;
+            continue;
+        }
+        let bytes =
+            std::fs::read(&path).map_err(|e| format!("failed to read {}: {e}", path.display()))?;
+        // Binary files are copied untouched.
+        let bytes = match String::from_utf8(bytes) {
+            Ok(text) if !text.contains('\0') => render(&text, variables).into_bytes(),
+            Ok(text) => text.into_bytes(),
+            Err(e) => e.into_bytes(),
+        };
+        std::fs::write(&target, bytes)
+            .map_err(|e| format!("failed to write {}: {e}", target.display()))?;
+        // Keeps scripts executable.
+        if let Ok(metadata) = entry.metadata() {
+            let _ = std::fs::set_permissions(&target, metadata.permissions());
+        }
+        files += 1;
+    }
+    Ok(files)
+}
+
+/// Replaces `{{name}}` (spaces inside the braces allowed) for each known
+/// variable; other placeholders are left for the project's own tooling.
+fn render(text: &str, variables: &BTreeMap<String, String>) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("{{") {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let value = after
+            .find("}}")
+            .and_then(|end| Some((variables.get(after[..end].trim())?, end)));
+        match value {
+            Some((value, end)) => {
+                out.push_str(value);
+                rest = &after[end + 2..];
+            }
+            None => {
+                out.push_str("{{");
+                rest = after;
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Empties `dir` but keeps it.
+fn clear(dir: &Path) {
+    if let Ok(entries) = std::fs::read_dir(dir) {
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let _ = if path.is_dir() {
+                std::fs::remove_dir_all(path)
+            } else {
+                std::fs::remove_file(path)
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn templates_render_into_a_committed_repository() {
+        let root = crate::test_support::temp_path("scaffold");
+        let template = root.join("template");
+        std::fs::create_dir_all(template.join("src/{{ module }}")).expect("template dirs");
+        std::fs::write(
+            template.join(MANIFEST),
+            "[variables]\nmodule = {}\nlicense = { default = \"MIT\" }\n",
+        )
+        .expect("manifest");
+        std::fs::write(
+            template.join("README.md"),
+            "# {{project_name}}\n\n{{license}}, {{ unknown }}\n",
+        )
+        .expect("readme");
+        std::fs::write(template.join("src/{{ module }}/mod.rs"), "// {{module}}\n").expect("mod");
+        std::fs::write(template.join("logo.bin"), [0xff, 0x00, b'{', b'{']).expect("binary");
+
+        let dest = root.join("demo");
+        let template = template.to_string_lossy();
+        let request = |variables: &[(&str, &str)]| Request {
+            template: &template,
+            dest: &dest,
+            variables: variables
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+            branch: None,
+            message: None,
+        };
+        let missing = scaffold(&request(&[])).expect_err("module has no default");
+        assert!(missing.contains("module"), "{missing}");
+        assert!(!dest.exists());
+
+        let done = scaffold(&request(&[("module", "parser")])).expect("scaffold");
+        assert_eq!((done.files, done.branch.as_str()), (3, "main"));
+        assert_eq!(
+            std::fs::read_to_string(dest.join("README.md")).expect("readme"),
+            "# demo\n\nMIT, {{ unknown }}\n"
+        );
+        assert_eq!(
+            std::fs::read_to_string(dest.join("src/parser/mod.rs")).expect("mod"),
+            "// parser\n"
+        );
+        assert!(!dest.join(MANIFEST).exists());
+
+        let repo = git2::Repository::open(&dest).expect("repo");
+        let head = repo.head().expect("head");
+        assert_eq!(head.shorthand(), Some("main"));
+        let tree = head.peel_to_tree().expect("tree");
+        assert!(tree.get_path(Path::new("src/parser/mod.rs")).is_ok());
+        assert!(repo.statuses(None).expect("status").is_empty());
+        assert!(crate::db::db_path(&dest.to_string_lossy()).exists());
+
+        let again = scaffold(&request(&[("module", "parser")]));
+        assert!(again.expect_err("not empty").contains("isn't empty"));
+        assert!(is_url("https://example.com/t.git") && is_url("git@host:t"));
+    }
+}
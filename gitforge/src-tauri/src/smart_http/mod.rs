@@ -0,0 +1,650 @@
+//! Serves a repository over git's smart HTTP protocol by driving
+//! `git http-backend` as a CGI program, one thread per connection.
+//!
+//! Clones and fetches are open to anyone who can reach the socket unless
+//! `require_auth` is set; pushes are refused unless `allow_push` is set and
+//! the client presents the token as an HTTP Basic password or a Bearer
+//! token.
+
+use crate::auth::constant_time_eq;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+pub const DEFAULT_BIND: &str = "127.0.0.1:8642";
+/// Environment variable holding the token clients authenticate with.
+pub const TOKEN_ENV: &str = "GITFORGE_HTTP_TOKEN";
+/// Connections served at once; further ones are turned away with a 503.
+pub const MAX_CONNECTIONS: usize = 32;
+
+const MAX_HEAD_BYTES: usize = 16 * 1024;
+const MAX_HEADERS: usize = 64;
+/// Idle time allowed on a socket read or write before the connection is
+/// dropped.
+const IO_TIMEOUT: Duration = Duration::from_secs(60);
+const REALM: &str = "gitforge";
+
+#[derive(Clone, Default)]
+pub struct Options {
+    /// Accept `git push` from authenticated clients.
+    pub allow_push: bool,
+    /// Authenticate clones and fetches too.
+    pub require_auth: bool,
+    /// What clients must present; needed for `allow_push` and
+    /// `require_auth`.
+    pub token: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Service {
+    UploadPack,
+    ReceivePack,
+}
+
+impl Service {
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "git-upload-pack" => Some(Service::UploadPack),
+            "git-receive-pack" => Some(Service::ReceivePack),
+            _ => None,
+        }
+    }
+}
+
+/// The part of a request `http-backend` needs.
+struct Request {
+    method: String,
+    path: String,
+    query: String,
+    headers: Vec<(String, String)>,
+}
+
+impl Request {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Whether the body is chunked: `chunked` is always the last coding
+    /// listed, as in `gzip, chunked`.
+    fn chunked(&self) -> bool {
+        self.header("Transfer-Encoding").is_some_and(|value| {
+            value
+                .rsplit(',')
+                .next()
+                .is_some_and(|coding| coding.trim().eq_ignore_ascii_case("chunked"))
+        })
+    }
+
+    /// Headers `http-backend` reads as CGI variables. A chunked body reaches
+    /// it decoded, so any Content-Length sent alongside is dropped and the
+    /// backend reads to end of input instead.
+    fn cgi_env(&self) -> Vec<(&'static str, &str)> {
+        [
+            ("CONTENT_TYPE", "Content-Type"),
+            ("CONTENT_LENGTH", "Content-Length"),
+            ("HTTP_CONTENT_ENCODING", "Content-Encoding"),
+            ("GIT_PROTOCOL", "Git-Protocol"),
+        ]
+        .into_iter()
+        .filter(|(var, _)| !(*var == "CONTENT_LENGTH" && self.chunked()))
+        .filter_map(|(var, header)| Some((var, self.header(header)?)))
+        .collect()
+    }
+
+    /// The service and the path `http-backend` is given. Anything before the
+    /// protocol endpoint (`/team/repo.git`) is ignored: one repository is
+    /// served under whatever name the client picked.
+    fn route(&self) -> Option<(Service, &'static str)> {
+        let path = self.path.trim_end_matches('/');
+        if path.ends_with("/info/refs") && self.method == "GET" {
+            let service = self
+                .query
+                .split('&')
+                .find_map(|pair| pair.strip_prefix("service="))
+                .and_then(Service::parse)?;
+            return Some((service, "/info/refs"));
+        }
+        if self.method != "POST" {
+            return None;
+        }
+        if path.ends_with("/git-upload-pack") {
+            Some((Service::UploadPack, "/git-upload-pack"))
+        } else if path.ends_with("/git-receive-pack") {
+            Some((Service::ReceivePack, "/git-receive-pack"))
+        } else {
+            None
+        }
+    }
+}
+
+pub struct SmartHttp {
+    git_dir: PathBuf,
+    options: Options,
+    active: Arc<AtomicUsize>,
+}
+
+impl SmartHttp {
+    pub fn new(repo_path: &str, options: Options) -> Result<Self, String> {
+        let needs_token = options.allow_push || options.require_auth;
+        if needs_token && options.token.as_deref().is_none_or(str::is_empty) {
+            return Err(format!(
+                "pushing and authenticated reads need a token; set {TOKEN_ENV}"
+            ));
+        }
+        let repo = git2::Repository::open(repo_path)
+            .map_err(|e| format!("failed to open repository: {e}"))?;
+        Ok(Self {
+            git_dir: repo.path().to_path_buf(),
+            options,
+            active: Arc::new(AtomicUsize::new(0)),
+        })
+    }
+
+    /// Accepts connections until the listener fails.
+    pub fn serve(self, listener: TcpListener) -> Result<(), String> {
+        let server = Arc::new(self);
+        for stream in listener.incoming() {
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(e) => {
+                    eprintln!("failed to accept a connection: {e}");
+                    continue;
+                }
+            };
+            if server.active.fetch_add(1, Ordering::SeqCst) >= MAX_CONNECTIONS {
+                server.active.fetch_sub(1, Ordering::SeqCst);
+                let mut stream = stream;
+                let _ = respond(&mut stream, 503, "Service Unavailable", &[], "busy\n");
+                continue;
+            }
+            let server = Arc::clone(&server);
+            std::thread::spawn(move || {
+                if let Err(e) = server.handle(stream) {
+                    eprintln!("smart-http connection failed: {e}");
+                }
+                server.active.fetch_sub(1, Ordering::SeqCst);
+            });
+        }
+        Ok(())
+    }
+
+    fn handle(&self, stream: TcpStream) -> io::Result<()> {
+        stream.set_read_timeout(Some(IO_TIMEOUT))?;
+        stream.set_write_timeout(Some(IO_TIMEOUT))?;
+        let peer = stream.peer_addr()?;
+        let mut out = stream.try_clone()?;
+        let mut reader = BufReader::new(stream);
+
+        let request = match read_request(&mut reader) {
+            Ok(Some(request)) => request,
+            Ok(None) => return Ok(()),
+            Err(e) if e.kind() == io::ErrorKind::InvalidData => {
+                return respond(&mut out, 400, "Bad Request", &[], &format!("{e}\n"));
+            }
+            Err(e) => return Err(e),
+        };
+        let Some((service, path_info)) = request.route() else {
+            return respond(&mut out, 404, "Not Found", &[], "not found\n");
+        };
+        if service == Service::ReceivePack && !self.options.allow_push {
+            return respond(&mut out, 403, "Forbidden", &[], "pushing is disabled\n");
+        }
+        let user = self.authenticate(&request);
+        let needs_auth = service == Service::ReceivePack || self.options.require_auth;
+        if needs_auth && user.is_none() {
+            let challenge = format!("Basic realm=\"{REALM}\"");
+            return respond(
+                &mut out,
+                401,
+                "Unauthorized",
+                &[("WWW-Authenticate", &challenge)],
+                "authentication required\n",
+            );
+        }
+
+        let mut body = body_reader(&request, &mut reader)?;
+        self.run_backend(
+            &request,
+            path_info,
+            peer,
+            user.as_deref(),
+            &mut body,
+            &mut out,
+        )
+    }
+
+    /// The user a request authenticated as, if it presented the token.
+    fn authenticate(&self, request: &Request) -> Option<String> {
+        let token = self.options.token.as_deref()?;
+        let value = request.header("Authorization")?;
+        let (scheme, credentials) = value.split_once(' ')?;
+        let (user, secret) = if scheme.eq_ignore_ascii_case("Basic") {
+            let decoded = String::from_utf8(decode_base64(credentials.trim())?).ok()?;
+            let (user, password) = decoded.split_once(':')?;
+            (user.to_string(), password.to_string())
+        } else if scheme.eq_ignore_ascii_case("Bearer") {
+            ("token".to_string(), credentials.trim().to_string())
+        } else {
+            return None;
+        };
+        constant_time_eq(secret.as_bytes(), token.as_bytes()).then_some(user)
+    }
+
+    fn run_backend(
+        &self,
+        request: &Request,
+        path_info: &str,
+        peer: SocketAddr,
+        user: Option<&str>,
+        body: &mut (dyn Read + Send),
+        out: &mut TcpStream,
+    ) -> io::Result<()> {
+        let mut command = Command::new("git");
+        command
+            .arg("-c")
+            .arg(format!("http.receivepack={}", self.options.allow_push))
+            .arg("http-backend")
+            .env("GIT_PROJECT_ROOT", &self.git_dir)
+            .env("GIT_HTTP_EXPORT_ALL", "1")
+            .env("GATEWAY_INTERFACE", "CGI/1.1")
+            .env("SERVER_PROTOCOL", "HTTP/1.1")
+            .env("REQUEST_METHOD", &request.method)
+            .env("PATH_INFO", path_info)
+            .env("QUERY_STRING", &request.query)
+            .env("REMOTE_ADDR", peer.ip().to_string())
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit());
+        for (var, value) in request.cgi_env() {
+            command.env(var, value);
+        }
+        if let Some(user) = user {
+            command.env("REMOTE_USER", user);
+        }
+        let mut child = command.spawn()?;
+        let mut stdin = child.stdin.take().expect("piped stdin");
+        let stdout = child.stdout.take().expect("piped stdout");
+
+        // The body is fed on its own thread so a backend that starts
+        // answering before it has read everything cannot deadlock us.
+        let relayed = std::thread::scope(|scope| {
+            let feeder = scope.spawn(move || {
+                let copied = io::copy(body, &mut stdin);
+                drop(stdin);
+                copied
+            });
+            let relayed = relay_cgi(BufReader::new(stdout), out);
+            let fed = feeder.join().expect("request body thread panicked");
+            relayed.and(fed.map(|_| ()))
+        });
+        let status = child.wait()?;
+        if !status.success() {
+            eprintln!("git http-backend exited with {status}");
+        }
+        relayed
+    }
+}
+
+/// Reads the request head; `None` when the client closed the connection
+/// without sending one.
+fn read_request(reader: &mut impl BufRead) -> io::Result<Option<Request>> {
+    let mut head = Vec::new();
+    loop {
+        let before = head.len();
+        let read = reader
+            .by_ref()
+            .take((MAX_HEAD_BYTES + 1 - head.len()) as u64)
+            .read_until(b'\n', &mut head)?;
+        if read == 0 {
+            if head.is_empty() {
+                return Ok(None);
+            }
+            return Err(invalid("request head ended early"));
+        }
+        if head.len() > MAX_HEAD_BYTES {
+            return Err(invalid("request head too large"));
+        }
+        if matches!(&head[before..], b"\r\n" | b"\n") {
+            break;
+        }
+    }
+
+    let mut headers = [httparse::EMPTY_HEADER; MAX_HEADERS];
+    let mut parsed = httparse::Request::new(&mut headers);
+    match parsed.parse(&head) {
+        Ok(httparse::Status::Complete(_)) => {}
+        Ok(httparse::Status::Partial) => return Err(invalid("incomplete request head")),
+        Err(e) => return Err(invalid(&format!("malformed request: {e}"))),
+    }
+    let target = parsed.path.unwrap_or("/");
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    Ok(Some(Request {
+        method: parsed.method.unwrap_or_default().to_string(),
+        path: path.to_string(),
+        query: query.to_string(),
+        headers: parsed
+            .headers
+            .iter()
+            .map(|header| {
+                let value = String::from_utf8_lossy(header.value).into_owned();
+                (header.name.to_string(), value)
+            })
+            .collect(),
+    }))
+}
+
+fn body_reader<'a>(
+    request: &Request,
+    reader: &'a mut (dyn BufRead + Send),
+) -> io::Result<Box<dyn Read + Send + 'a>> {
+    if request.chunked() {
+        return Ok(Box::new(Chunked::new(reader)));
+    }
+    match request.header("Content-Length") {
+        Some(length) => {
+            let length: u64 = length
+                .trim()
+                .parse()
+                .map_err(|_| invalid("invalid Content-Length"))?;
+            Ok(Box::new(reader.take(length)))
+        }
+        None => Ok(Box::new(io::empty())),
+    }
+}
+
+/// Decodes a `Transfer-Encoding: chunked` body, which git sends for pushes
+/// and large fetch negotiations.
+struct Chunked<R> {
+    inner: R,
+    /// Bytes left in the current chunk.
+    remaining: u64,
+    done: bool,
+}
+
+impl<R: BufRead> Chunked<R> {
+    fn new(inner: R) -> Self {
+        Self {
+            inner,
+            remaining: 0,
+            done: false,
+        }
+    }
+
+    fn line(&mut self) -> io::Result<String> {
+        let mut line = String::new();
+        if self.inner.read_line(&mut line)? == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "chunked body ended early",
+            ));
+        }
+        Ok(line.trim_end().to_string())
+    }
+}
+
+impl<R: BufRead> Read for Chunked<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.done || buf.is_empty() {
+            return Ok(0);
+        }
+        if self.remaining == 0 {
+            let line = self.line()?;
+            let size = line.split(';').next().unwrap_or_default().trim();
+            self.remaining =
+                u64::from_str_radix(size, 16).map_err(|_| invalid("invalid chunk size"))?;
+            if self.remaining == 0 {
+                // Skip any trailers up to the blank line that ends the body.
+                while !self.line()?.is_empty() {}
+                self.done = true;
+                return Ok(0);
+            }
+        }
+        let want = buf.len().min(self.remaining as usize);
+        let read = self.inner.read(&mut buf[..want])?;
+        if read == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "chunked body ended early",
+            ));
+        }
+        self.remaining -= read as u64;
+        if self.remaining == 0 && !self.line()?.is_empty() {
+            return Err(invalid("chunk not followed by CRLF"));
+        }
+        Ok(read)
+    }
+}
+
+/// Turns the backend's CGI reply into an HTTP response: its `Status:`
+/// header becomes the status line and the rest is passed through.
+fn relay_cgi(mut cgi: impl BufRead, out: &mut impl Write) -> io::Result<()> {
+    let mut status = "200 OK".to_string();
+    let mut headers = String::new();
+    loop {
+        let mut line = String::new();
+        if cgi.read_line(&mut line)? == 0 {
+            break;
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        match line.split_once(':') {
+            Some((name, value)) if name.eq_ignore_ascii_case("Status") => {
+                status = value.trim().to_string();
+            }
+            Some(_) => {
+                headers.push_str(line);
+                headers.push_str("\r\n");
+            }
+            None => return Err(invalid("malformed header from git http-backend")),
+        }
+    }
+    write!(
+        out,
+        "HTTP/1.1 {status}\r\n{headers}Connection: close\r\n\r\n"
+    )?;
+    io::copy(&mut cgi, out)?;
+    out.flush()
+}
+
+fn respond(
+    out: &mut impl Write,
+    code: u16,
+    reason: &str,
+    headers: &[(&str, &str)],
+    body: &str,
+) -> io::Result<()> {
+    let mut head = format!("HTTP/1.1 {code} {reason}\r\n");
+    for (name, value) in headers {
+        head.push_str(&format!("{name}: {value}\r\n"));
+    }
+    head.push_str(&format!(
+        "Content-Type: text/plain\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        body.len()
+    ));
+    out.write_all(head.as_bytes())?;
+    out.write_all(body.as_bytes())?;
+    out.flush()
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+pub(crate) fn decode_base64(text: &str) -> Option<Vec<u8>> {
+    let text = text.trim_end_matches('=');
+    let mut bytes = Vec::with_capacity(text.len() * 3 / 4);
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+    for c in text.bytes() {
+        let value = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            _ => return None,
+        };
+        buffer = (buffer << 6) | u32::from(value);
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((buffer >> bits) as u8);
+            buffer &= (1 << bits) - 1;
+        }
+    }
+    Some(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mcp::chunks::base64;
+    use crate::test_support::{init_repo_with_file, temp_dir};
+    use std::path::Path;
+
+    /// Starts a server on an ephemeral port and returns its base URL.
+    fn start(repo: &Path, options: Options) -> String {
+        let server = SmartHttp::new(&repo.to_string_lossy(), options).expect("server");
+        let listener = TcpListener::bind("127.0.0.1:0").expect("bind");
+        let addr = listener.local_addr().expect("addr");
+        std::thread::spawn(move || server.serve(listener));
+        format!("http://{addr}")
+    }
+
+    /// Runs git without prompts or the user's credential helpers.
+    fn git(cwd: &Path, args: &[&str]) -> std::process::Output {
+        Command::new("git")
+            .args(["-c", "credential.helper=", "-c", "user.name=GitForge"])
+            .args(["-c", "user.email=gitforge@example.com"])
+            .args(args)
+            .current_dir(cwd)
+            .env("GIT_TERMINAL_PROMPT", "0")
+            .output()
+            .expect("run git")
+    }
+
+    #[test]
+    fn clones_over_http_and_gates_pushes_on_the_token() {
+        let (origin, _) = init_repo_with_file("smart-http-origin");
+        let dir = temp_dir("smart-http-clone");
+
+        let url = start(&origin, Options::default());
+        let clone = git(&dir, &["clone", &format!("{url}/team/repo.git"), "clone"]);
+        assert!(clone.status.success(), "{clone:?}");
+        assert!(dir.join("clone/README.md").is_file());
+        let push = git(&dir.join("clone"), &["push", "origin", "HEAD:refs/heads/x"]);
+        assert!(!push.status.success(), "read-only server accepted a push");
+
+        let options = Options {
+            allow_push: true,
+            token: Some("s3cret".to_string()),
+            ..Options::default()
+        };
+        let url = start(&origin, options);
+        let authority = url.trim_start_matches("http://");
+        let wrong = format!("http://me:nope@{authority}/repo.git");
+        let push = git(&dir.join("clone"), &["push", &wrong, "HEAD:refs/heads/x"]);
+        assert!(!push.status.success(), "push with a wrong token succeeded");
+        let right = format!("http://me:s3cret@{authority}/repo.git");
+        let push = git(&dir.join("clone"), &["push", &right, "HEAD:refs/heads/x"]);
+        assert!(push.status.success(), "{push:?}");
+
+        let head = git2::Repository::open(&origin)
+            .expect("origin")
+            .find_branch("x", git2::BranchType::Local)
+            .is_ok();
+        assert!(head, "pushed branch missing from origin");
+    }
+
+    #[test]
+    fn tokens_are_checked_from_basic_and_bearer_headers() {
+        let (dir, _) = init_repo_with_file("smart-http-auth");
+        let server = SmartHttp::new(
+            &dir.to_string_lossy(),
+            Options {
+                require_auth: true,
+                token: Some("s3cret".to_string()),
+                ..Options::default()
+            },
+        )
+        .expect("server");
+        let request = |authorization: &str| Request {
+            method: "GET".to_string(),
+            path: "/info/refs".to_string(),
+            query: "service=git-upload-pack".to_string(),
+            headers: vec![("authorization".to_string(), authorization.to_string())],
+        };
+
+        let basic = format!("Basic {}", base64(b"alice:s3cret"));
+        assert_eq!(
+            server.authenticate(&request(&basic)).as_deref(),
+            Some("alice")
+        );
+        assert!(server.authenticate(&request("Bearer s3cret")).is_some());
+        let wrong = format!("Basic {}", base64(b"alice:guess"));
+        assert_eq!(server.authenticate(&request(&wrong)), None);
+        assert_eq!(
+            request("").route(),
+            Some((Service::UploadPack, "/info/refs"))
+        );
+
+        let err = SmartHttp::new(
+            &dir.to_string_lossy(),
+            Options {
+                allow_push: true,
+                ..Options::default()
+            },
+        )
+        .err()
+        .expect("push without a token");
+        assert!(err.contains(TOKEN_ENV), "{err}");
+    }
+
+    #[test]
+    fn chunked_bodies_are_decoded() {
+        let wire: &[u8] = b"5\r\nhello\r\n7;ext=1\r\n, world\r\n0\r\nX-Trailer: 1\r\n\r\nrest";
+        let mut reader = BufReader::new(wire);
+        let mut body = String::new();
+        Chunked::new(&mut reader)
+            .read_to_string(&mut body)
+            .expect("decode");
+        assert_eq!(body, "hello, world");
+        let mut rest = String::new();
+        reader.read_to_string(&mut rest).expect("rest");
+        assert_eq!(rest, "rest", "decoder read past the body");
+
+        let mut truncated = Chunked::new(&b"5\r\nhel"[..]);
+        assert!(truncated.read_to_string(&mut String::new()).is_err());
+    }
+
+    #[test]
+    fn chunked_requests_pass_no_content_length() {
+        let request = |headers: &[(&str, &str)]| Request {
+            method: "POST".into(),
+            path: "/repo.git/git-receive-pack".into(),
+            query: String::new(),
+            headers: headers
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+        };
+        let sized = request(&[("Content-Type", "x"), ("Content-Length", "12")]);
+        assert_eq!(
+            sized.cgi_env(),
+            [("CONTENT_TYPE", "x"), ("CONTENT_LENGTH", "12")]
+        );
+        let chunked = request(&[("Content-Length", "12"), ("transfer-encoding", "Chunked")]);
+        assert!(chunked.cgi_env().is_empty());
+        assert!(request(&[("Transfer-Encoding", "gzip, chunked")]).chunked());
+        assert!(!request(&[("Transfer-Encoding", "chunked, gzip")]).chunked());
+    }
+}
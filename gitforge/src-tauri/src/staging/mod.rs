@@ -0,0 +1,442 @@
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+use crate::content::{self, DiffHunk, INDEX, WORKDIR};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Discarded {
+    pub path: String,
+    /// Copy of the discarded working-tree file, if there was one.
+    pub backup: Option<String>,
+}
+
+/// Stages the working-tree state of `path`, including deletion.
+pub fn stage_file(repo: &git2::Repository, path: &str) -> Result<(), String> {
+    content::check_path(path)?;
+    let mut index = index(repo)?;
+    if workdir(repo)?.join(path).exists() {
+        index.add_path(Path::new(path))
+    } else {
+        index.remove_path(Path::new(path))
+    }
+    .map_err(|e| format!("failed to stage {path}: {e}"))?;
+    index
+        .write()
+        .map_err(|e| format!("failed to write index: {e}"))
+}
+
+/// Resets the index entry for `path` to `HEAD`, leaving the working tree alone.
+pub fn unstage_file(repo: &git2::Repository, path: &str) -> Result<(), String> {
+    content::check_path(path)?;
+    match head_commit(repo)? {
+        Some(head) => repo
+            .reset_default(Some(head.as_object()), [path])
+            .map_err(|e| format!("failed to unstage {path}: {e}")),
+        None => {
+            let mut index = index(repo)?;
+            index
+                .remove_path(Path::new(path))
+                .map_err(|e| format!("failed to unstage {path}: {e}"))?;
+            index
+                .write()
+                .map_err(|e| format!("failed to write index: {e}"))
+        }
+    }
+}
+
+/// Stages every file `pathspecs` match, like `git add -A -- <pathspecs>`:
+/// new, modified and deleted files, leaving ignored ones alone. Each
+/// pathspec is a path, a directory or a glob such as `src/*.rs`, and must
+/// match something. Returns the paths whose index entries changed.
+pub fn add(repo: &git2::Repository, pathspecs: &[String]) -> Result<Vec<String>, String> {
+    let pathspecs = check_pathspecs(repo, pathspecs)?;
+    let pending = matching_status(repo, &pathspecs, |status| {
+        status.intersects(
+            git2::Status::WT_NEW
+                | git2::Status::WT_MODIFIED
+                | git2::Status::WT_DELETED
+                | git2::Status::WT_TYPECHANGE
+                | git2::Status::WT_RENAMED,
+        )
+    })?;
+    let mut index = index(repo)?;
+    index
+        .add_all(&pathspecs, git2::IndexAddOption::DEFAULT, None)
+        .and_then(|()| index.update_all(&pathspecs, None))
+        .map_err(|e| format!("failed to stage {}: {e}", pathspecs.join(" ")))?;
+    index
+        .write()
+        .map_err(|e| format!("failed to write index: {e}"))?;
+    Ok(pending)
+}
+
+/// Resets the index entries `pathspecs` match to `HEAD`, like `git
+/// restore --staged -- <pathspecs>`, leaving the working tree alone.
+/// Returns the paths that were staged.
+pub fn unstage(repo: &git2::Repository, pathspecs: &[String]) -> Result<Vec<String>, String> {
+    let pathspecs = check_pathspecs(repo, pathspecs)?;
+    let staged = matching_status(repo, &pathspecs, |status| {
+        status.intersects(
+            git2::Status::INDEX_NEW
+                | git2::Status::INDEX_MODIFIED
+                | git2::Status::INDEX_DELETED
+                | git2::Status::INDEX_TYPECHANGE
+                | git2::Status::INDEX_RENAMED,
+        )
+    })?;
+    match head_commit(repo)? {
+        Some(head) => repo
+            .reset_default(Some(head.as_object()), &pathspecs)
+            .map_err(|e| format!("failed to unstage {}: {e}", pathspecs.join(" ")))?,
+        None => {
+            let mut index = index(repo)?;
+            index
+                .remove_all(&pathspecs, None)
+                .map_err(|e| format!("failed to unstage {}: {e}", pathspecs.join(" ")))?;
+            index
+                .write()
+                .map_err(|e| format!("failed to write index: {e}"))?;
+        }
+    }
+    Ok(staged)
+}
+
+/// Refuses an empty list, paths leaving the repository, and pathspecs
+/// that match no file in the working tree, the index or `HEAD`. A bare
+/// `.` stands for the whole tree.
+fn check_pathspecs(repo: &git2::Repository, pathspecs: &[String]) -> Result<Vec<String>, String> {
+    if pathspecs.is_empty() {
+        return Err("no paths given".to_string());
+    }
+    let mut checked = Vec::with_capacity(pathspecs.len());
+    for pathspec in pathspecs {
+        content::check_path(pathspec)?;
+        let pathspec = pathspec.trim_start_matches("./").trim_end_matches('/');
+        checked.push(match pathspec {
+            "" | "." => "*".to_string(),
+            pathspec => pathspec.to_string(),
+        });
+    }
+    let head = head_commit(repo)?
+        .map(|commit| commit.tree())
+        .transpose()
+        .map_err(|e| format!("failed to read HEAD: {e}"))?;
+    let index = index(repo)?;
+    for pathspec in &checked {
+        let spec = git2::Pathspec::new([pathspec.as_str()])
+            .map_err(|e| format!("invalid pathspec '{pathspec}': {e}"))?;
+        let flags = git2::PathspecFlags::NO_MATCH_ERROR;
+        let found = spec.match_workdir(repo, flags).is_ok()
+            || spec.match_index(&index, flags).is_ok()
+            || head
+                .as_ref()
+                .is_some_and(|tree| spec.match_tree(tree, flags).is_ok());
+        if !found {
+            return Err(format!("pathspec '{pathspec}' did not match any files"));
+        }
+    }
+    Ok(checked)
+}
+
+/// Paths under `pathspecs`, untracked ones included, whose status passes
+/// `wanted`.
+fn matching_status(
+    repo: &git2::Repository,
+    pathspecs: &[String],
+    wanted: impl Fn(git2::Status) -> bool,
+) -> Result<Vec<String>, String> {
+    let mut options = git2::StatusOptions::new();
+    options
+        .include_untracked(true)
+        .recurse_untracked_dirs(true)
+        .include_ignored(false);
+    for pathspec in pathspecs {
+        options.pathspec(pathspec);
+    }
+    let statuses = repo
+        .statuses(Some(&mut options))
+        .map_err(|e| format!("failed to read status: {e}"))?;
+    Ok(statuses
+        .iter()
+        .filter(|entry| wanted(entry.status()))
+        .filter_map(|entry| entry.path().map(str::to_string))
+        .collect())
+}
+
+/// Restores `path` from the index (or deletes it if untracked), first
+/// copying the working file to `.git/gitforge/trash/<time>/<path>`.
+pub fn discard_file(repo: &git2::Repository, path: &str) -> Result<Discarded, String> {
+    content::check_path(path)?;
+    let full = workdir(repo)?.join(path);
+    let backup = if full.is_file() {
+        Some(backup(repo, path, &full)?)
+    } else {
+        None
+    };
+
+    let mut index = index(repo)?;
+    if index.get_path(Path::new(path), 0).is_some() {
+        let mut checkout = git2::build::CheckoutBuilder::new();
+        checkout.force().path(path);
+        repo.checkout_index(Some(&mut index), Some(&mut checkout))
+            .map_err(|e| format!("failed to discard {path}: {e}"))?;
+    } else if backup.is_some() {
+        std::fs::remove_file(&full).map_err(|e| format!("failed to discard {path}: {e}"))?;
+    }
+
+    Ok(Discarded {
+        path: path.to_string(),
+        backup: backup.map(|p| p.to_string_lossy().to_string()),
+    })
+}
+
+/// Stages hunk `hunk` of the index → working-tree diff of `path`, as
+/// numbered by [`content::diff_file`] with base `INDEX`.
+pub fn stage_hunk(repo: &git2::Repository, path: &str, hunk: usize) -> Result<(), String> {
+    let staged = text(content::load(repo, path, INDEX)?, path)?;
+    let working = text(content::load(repo, path, WORKDIR)?, path)?;
+    let hunks = content::hunks(path, staged.as_bytes(), working.as_bytes())?;
+    let selected = pick(&hunks, hunk, path)?;
+
+    write_index_blob(repo, path, &apply(&staged, selected))
+}
+
+/// Reverts hunk `hunk` of the `HEAD` → index diff of `path` in the index only.
+pub fn unstage_hunk(repo: &git2::Repository, path: &str, hunk: usize) -> Result<(), String> {
+    let committed = match head_commit(repo)? {
+        Some(_) => text(content::load(repo, path, "HEAD")?, path)?,
+        None => String::new(),
+    };
+    let staged = text(content::load(repo, path, INDEX)?, path)?;
+    let hunks = content::hunks(path, committed.as_bytes(), staged.as_bytes())?;
+    let selected = pick(&hunks, hunk, path)?;
+
+    write_index_blob(repo, path, &revert(&staged, selected))
+}
+
+fn index(repo: &git2::Repository) -> Result<git2::Index, String> {
+    repo.index()
+        .map_err(|e| format!("failed to open index: {e}"))
+}
+
+fn workdir(repo: &git2::Repository) -> Result<&Path, String> {
+    repo.workdir()
+        .ok_or_else(|| "bare repositories have no working tree".to_string())
+}
+
+/// `None` on an unborn branch.
+fn head_commit(repo: &git2::Repository) -> Result<Option<git2::Commit<'_>>, String> {
+    match repo.head() {
+        Ok(head) => head
+            .peel_to_commit()
+            .map(Some)
+            .map_err(|e| format!("failed to resolve HEAD: {e}")),
+        Err(e) if e.code() == git2::ErrorCode::UnbornBranch => Ok(None),
+        Err(e) => Err(format!("failed to resolve HEAD: {e}")),
+    }
+}
+
+/// Missing files diff as empty; binary ones can't be split into hunks.
+fn text(bytes: Option<Vec<u8>>, path: &str) -> Result<String, String> {
+    String::from_utf8(bytes.unwrap_or_default())
+        .ok()
+        .filter(|text| !text.contains('\0'))
+        .ok_or_else(|| format!("{path} is binary; stage the whole file instead"))
+}
+
+fn pick<'a>(hunks: &'a [DiffHunk], hunk: usize, path: &str) -> Result<&'a DiffHunk, String> {
+    hunks.get(hunk).ok_or_else(|| {
+        format!(
+            "{path} has {} hunks, no hunk {hunk}; refresh the diff",
+            hunks.len()
+        )
+    })
+}
+
+/// Replaces the hunk's old-side lines in `old` with its new side.
+fn apply(old: &str, hunk: &DiffHunk) -> String {
+    splice(old, hunk.old_start, hunk.old_lines, hunk, '+')
+}
+
+/// Replaces the hunk's new-side lines in `new` with its old side.
+fn revert(new: &str, hunk: &DiffHunk) -> String {
+    splice(new, hunk.new_start, hunk.new_lines, hunk, '-')
+}
+
+fn splice(base: &str, start: u32, count: u32, hunk: &DiffHunk, keep: char) -> String {
+    let lines: Vec<&str> = base.split_inclusive('\n').collect();
+    // An empty side's start is the line *after which* the hunk applies.
+    let start = if count == 0 { start } else { start - 1 } as usize;
+    let end = (start + count as usize).min(lines.len());
+
+    let mut out = String::with_capacity(base.len());
+    out.extend(lines[..start.min(lines.len())].iter().copied());
+    out.extend(
+        hunk.lines
+            .iter()
+            .filter(|line| line.origin == ' ' || line.origin == keep)
+            .map(|line| line.content.as_str()),
+    );
+    out.extend(lines[end..].iter().copied());
+    out
+}
+
+fn write_index_blob(repo: &git2::Repository, path: &str, contents: &str) -> Result<(), String> {
+    let mut index = index(repo)?;
+    let entry = match index.get_path(Path::new(path), 0) {
+        Some(entry) => entry,
+        None => git2::IndexEntry {
+            ctime: git2::IndexTime::new(0, 0),
+            mtime: git2::IndexTime::new(0, 0),
+            dev: 0,
+            ino: 0,
+            mode: 0o100644,
+            uid: 0,
+            gid: 0,
+            file_size: 0,
+            id: git2::Oid::zero(),
+            flags: 0,
+            flags_extended: 0,
+            path: path.as_bytes().to_vec(),
+        },
+    };
+    index
+        .add_frombuffer(&entry, contents.as_bytes())
+        .map_err(|e| format!("failed to stage hunk of {path}: {e}"))?;
+    index
+        .write()
+        .map_err(|e| format!("failed to write index: {e}"))
+}
+
+fn backup(repo: &git2::Repository, path: &str, full: &Path) -> Result<PathBuf, String> {
+    let stamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or_default();
+    let dest = repo
+        .path()
+        .join("gitforge")
+        .join("trash")
+        .join(stamp.to_string())
+        .join(path);
+    if let Some(parent) = dest.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| format!("failed to create {}: {e}", parent.display()))?;
+    }
+    std::fs::copy(full, &dest).map_err(|e| format!("failed to back up {path}: {e}"))?;
+    Ok(dest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    const ORIGINAL: &str = "a\nb\nc\nd\ne\nf\ng\nh\ni\nj\n";
+
+    /// Repo with `list.txt` committed, far enough apart for two hunks.
+    fn committed_repo(label: &str) -> (crate::test_support::TempDir, git2::Repository) {
+        let (dir, repo) = crate::test_support::init_repo(&format!("staging-{label}"));
+        fs::write(dir.join("list.txt"), ORIGINAL).expect("write file");
+
+        let mut index = repo.index().expect("index");
+        index.add_path(Path::new("list.txt")).expect("stage");
+        index.write().expect("write index");
+        let tree = repo
+            .find_tree(index.write_tree().expect("write tree"))
+            .expect("find tree");
+        let sig = git2::Signature::now("GitForge Test", "test@gitforge.dev").expect("signature");
+        repo.commit(Some("HEAD"), &sig, &sig, "init", &tree, &[])
+            .expect("commit");
+        drop(tree);
+        (dir, repo)
+    }
+
+    fn staged(repo: &git2::Repository) -> String {
+        String::from_utf8(content::load(repo, "list.txt", INDEX).unwrap().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn pathspecs_stage_and_unstage_matching_files() {
+        let (dir, repo) = committed_repo("pathspec");
+        fs::create_dir_all(dir.join("src")).expect("mkdir");
+        fs::write(dir.join("src/a.rs"), "a").expect("write a");
+        fs::write(dir.join("src/b.rs"), "b").expect("write b");
+        fs::write(dir.join("notes.md"), "notes").expect("write notes");
+        fs::remove_file(dir.join("list.txt")).expect("delete list");
+        let specs = |specs: &[&str]| specs.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+
+        assert_eq!(
+            add(&repo, &specs(&["src/*.rs", "list.txt"])).expect("add"),
+            ["list.txt", "src/a.rs", "src/b.rs"]
+        );
+        let index = repo.index().expect("index");
+        assert!(index.get_path(Path::new("list.txt"), 0).is_none());
+        assert!(index.get_path(Path::new("notes.md"), 0).is_none());
+        assert!(add(&repo, &specs(&["missing/*"])).is_err_and(|e| e.contains("did not match")));
+        assert!(add(&repo, &specs(&["../x"])).is_err());
+
+        assert_eq!(
+            unstage(&repo, &specs(&["src"])).expect("unstage"),
+            ["src/a.rs", "src/b.rs"]
+        );
+        assert_eq!(add(&repo, &specs(&["."])).expect("add all").len(), 3);
+    }
+
+    #[test]
+    fn one_hunk_can_be_staged_and_unstaged() {
+        let (dir, repo) = committed_repo("hunk");
+        fs::write(dir.join("list.txt"), "A\nb\nc\nd\ne\nf\ng\nh\ni\nJ\n").expect("edit");
+
+        stage_hunk(&repo, "list.txt", 1).expect("stage second hunk");
+        assert_eq!(staged(&repo), "a\nb\nc\nd\ne\nf\ng\nh\ni\nJ\n");
+
+        stage_hunk(&repo, "list.txt", 0).expect("stage remaining hunk");
+        assert_eq!(staged(&repo), "A\nb\nc\nd\ne\nf\ng\nh\ni\nJ\n");
+
+        unstage_hunk(&repo, "list.txt", 1).expect("unstage second hunk");
+        assert_eq!(staged(&repo), "A\nb\nc\nd\ne\nf\ng\nh\ni\nj\n");
+        assert!(stage_hunk(&repo, "list.txt", 5).is_err());
+    }
+
+    #[test]
+    fn files_stage_unstage_and_discard_with_backup() {
+        let (dir, repo) = committed_repo("file");
+        fs::write(dir.join("list.txt"), "edited\n").expect("edit");
+
+        stage_file(&repo, "list.txt").expect("stage");
+        assert_eq!(staged(&repo), "edited\n");
+        unstage_file(&repo, "list.txt").expect("unstage");
+        assert_eq!(staged(&repo), ORIGINAL);
+
+        let discarded = discard_file(&repo, "list.txt").expect("discard");
+        assert_eq!(
+            fs::read_to_string(dir.join("list.txt")).expect("read"),
+            ORIGINAL
+        );
+        let backup = discarded.backup.expect("backup path");
+        assert_eq!(fs::read_to_string(backup).expect("read backup"), "edited\n");
+
+        fs::write(dir.join("scratch.txt"), "tmp").expect("untracked");
+        let discarded = discard_file(&repo, "scratch.txt").expect("discard untracked");
+        assert!(!dir.join("scratch.txt").exists());
+        assert!(discarded.backup.is_some());
+    }
+
+    #[test]
+    fn deletions_and_new_files_stage() {
+        let (dir, repo) = committed_repo("delete");
+        fs::remove_file(dir.join("list.txt")).expect("delete");
+        stage_file(&repo, "list.txt").expect("stage deletion");
+        assert!(content::load(&repo, "list.txt", INDEX)
+            .expect("load")
+            .is_none());
+
+        fs::write(dir.join("new.txt"), "x\ny\n").expect("new file");
+        stage_hunk(&repo, "new.txt", 0).expect("stage new file hunk");
+        assert_eq!(
+            content::load(&repo, "new.txt", INDEX).expect("load"),
+            Some(b"x\ny\n".to_vec())
+        );
+    }
+}
@@ -0,0 +1,327 @@
+//! Stale work: local branches with no commits for a while and open PRs
+//! nobody has touched, with optional cleanup goals for the agent.
+
+use ant_core::{AntEngine, GoalStatus, SystemEvent, VersionedSystemEvent};
+use serde::Serialize;
+
+use crate::store::{self, MetaStore, PrQuery, MAX_PAGE_SIZE};
+
+pub const DEFAULT_DAYS: u32 = 30;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Query {
+    /// Days without a commit before a branch is stale.
+    pub branch_days: u32,
+    /// Days without activity before an open PR is stale.
+    pub pr_days: u32,
+}
+
+impl Default for Query {
+    fn default() -> Self {
+        Self {
+            branch_days: DEFAULT_DAYS,
+            pr_days: DEFAULT_DAYS,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Report {
+    /// Longest idle first.
+    pub branches: Vec<StaleBranch>,
+    /// Longest idle first.
+    pub prs: Vec<StalePr>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct StaleBranch {
+    pub name: String,
+    pub commit: String,
+    pub last_commit: String,
+    pub idle_days: i64,
+    /// Whether HEAD already contains the branch, so deleting it loses
+    /// nothing.
+    pub merged: bool,
+    /// Open PRs from the branch.
+    pub open_prs: Vec<i64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct StalePr {
+    pub id: i64,
+    pub title: String,
+    pub from: String,
+    pub to: String,
+    /// The newest of its opening, approvals, check updates and commits on
+    /// its source branch.
+    pub last_activity: String,
+    pub idle_days: i64,
+}
+
+/// A cleanup goal created for a stale branch or PR.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CleanupGoal {
+    pub goal_id: String,
+    pub task: String,
+}
+
+pub fn report(
+    repo: &git2::Repository,
+    store: &dyn MetaStore,
+    query: Query,
+) -> Result<Report, String> {
+    let now = now();
+    let open = open_prs(store)?;
+    let current = crate::protection::current_branch(repo);
+    let head = repo.head().ok().and_then(|head| head.target());
+
+    let mut branches = Vec::new();
+    let listing = repo
+        .branches(Some(git2::BranchType::Local))
+        .map_err(|e| format!("failed to list branches: {e}"))?;
+    for entry in listing {
+        let (branch, _) = entry.map_err(|e| format!("failed to list branches: {e}"))?;
+        let Some(name) = branch.name().ok().flatten().map(str::to_string) else {
+            continue;
+        };
+        // The checked-out branch is being worked on by definition.
+        if current.as_deref() == Some(name.as_str()) {
+            continue;
+        }
+        let commit = branch
+            .get()
+            .peel_to_commit()
+            .map_err(|e| format!("failed to read branch {name}: {e}"))?;
+        let idle_days = (now - commit.time().seconds()) / 86_400;
+        if idle_days < i64::from(query.branch_days) {
+            continue;
+        }
+        let merged = head.is_some_and(|head| {
+            head == commit.id() || repo.graph_descendant_of(head, commit.id()).unwrap_or(false)
+        });
+        branches.push(StaleBranch {
+            open_prs: open
+                .iter()
+                .filter(|pr| pr.from == name)
+                .map(|pr| pr.id)
+                .collect(),
+            name,
+            commit: commit.id().to_string(),
+            last_commit: store::timestamp(commit.time().seconds()),
+            idle_days,
+            merged,
+        });
+    }
+    branches.sort_by(|a, b| b.idle_days.cmp(&a.idle_days).then(a.name.cmp(&b.name)));
+
+    let mut prs = Vec::new();
+    for pr in open {
+        let mut last = store::parse_timestamp(&pr.created_at).unwrap_or_default();
+        for approval in &pr.approvals {
+            last = last.max(store::parse_timestamp(&approval.approved_at).unwrap_or_default());
+        }
+        for check in store.list_checks(pr.id)? {
+            last = last.max(store::parse_timestamp(&check.updated_at).unwrap_or_default());
+        }
+        if let Ok(commit) = repo
+            .find_branch(&pr.from, git2::BranchType::Local)
+            .and_then(|branch| branch.get().peel_to_commit())
+        {
+            last = last.max(commit.time().seconds());
+        }
+        let idle_days = (now - last) / 86_400;
+        if idle_days < i64::from(query.pr_days) {
+            continue;
+        }
+        prs.push(StalePr {
+            id: pr.id,
+            title: pr.title,
+            from: pr.from,
+            to: pr.to,
+            last_activity: store::timestamp(last),
+            idle_days,
+        });
+    }
+    prs.sort_by(|a, b| b.idle_days.cmp(&a.idle_days).then(a.id.cmp(&b.id)));
+
+    Ok(Report { branches, prs })
+}
+
+/// Creates a goal for each stale branch and PR that doesn't have one yet,
+/// so running the report again doesn't queue the same cleanup twice.
+pub fn create_cleanup_goals(
+    engine: &AntEngine,
+    store: &dyn MetaStore,
+    report: &Report,
+) -> Result<Vec<CleanupGoal>, String> {
+    let existing: Vec<String> = store
+        .list_goals()?
+        .into_iter()
+        .map(|goal| goal.goal_id)
+        .collect();
+    let wanted = report
+        .branches
+        .iter()
+        .map(|branch| CleanupGoal {
+            goal_id: format!("stale-branch/{}", branch.name),
+            task: if branch.merged {
+                format!(
+                    "Delete branch {}: merged and without commits for {} days",
+                    branch.name, branch.idle_days
+                )
+            } else {
+                format!(
+                    "Rebase or delete branch {}: unmerged and without commits for {} days",
+                    branch.name, branch.idle_days
+                )
+            },
+        })
+        .chain(report.prs.iter().map(|pr| CleanupGoal {
+            goal_id: format!("stale-pr/{}", pr.id),
+            task: format!(
+                "Revive or close PR #{} ({}): no activity for {} days",
+                pr.id, pr.title, pr.idle_days
+            ),
+        }));
+
+    let mut created = Vec::new();
+    for goal in wanted {
+        if existing.contains(&goal.goal_id) {
+            continue;
+        }
+        // The engine only knows this process's goals; the store has them
+        // all, so it already filtered out the rest.
+        if engine.create_goal(&goal.goal_id, &goal.task).is_err() {
+            continue;
+        }
+        // The bus isn't persisted, so record the goal for `list_goals`.
+        for event in [
+            SystemEvent::GoalCreated {
+                goal_id: goal.goal_id.clone(),
+                task: goal.task.clone(),
+            },
+            SystemEvent::GoalStatusChanged {
+                goal_id: goal.goal_id.clone(),
+                status: GoalStatus::Pending,
+            },
+        ] {
+            store.append_event(&VersionedSystemEvent {
+                schema_version: ant_core::SYSTEM_EVENT_SCHEMA_VERSION,
+                event,
+            })?;
+        }
+        created.push(goal);
+    }
+    Ok(created)
+}
+
+/// Open PRs, newest first.
+fn open_prs(store: &dyn MetaStore) -> Result<Vec<store::PrRecord>, String> {
+    let mut prs = Vec::new();
+    let mut before = None;
+    loop {
+        let page = store.list_prs(PrQuery {
+            state: Some("open"),
+            before,
+            limit: MAX_PAGE_SIZE,
+        })?;
+        prs.extend(page.items);
+        match page.next_before {
+            Some(next) => before = Some(next),
+            None => return Ok(prs),
+        }
+    }
+}
+
+fn now() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn old_branches_and_open_prs_are_reported_once_as_goals() {
+        let (dir, repo) = crate::test_support::init_repo("stale");
+        let tree = repo
+            .index()
+            .and_then(|mut index| index.write_tree())
+            .and_then(|id| repo.find_tree(id))
+            .expect("tree");
+        let at = |secs| {
+            git2::Signature::new(
+                "GitForge Test",
+                "test@gitforge.dev",
+                &git2::Time::new(secs, 0),
+            )
+            .expect("sig")
+        };
+        let old = at(now() - 60 * 86_400);
+        let base = repo
+            .commit(Some("HEAD"), &old, &old, "base", &tree, &[])
+            .expect("commit");
+        let base = repo.find_commit(base).expect("base");
+        repo.branch("merged", &base, false).expect("merged");
+        let abandoned = repo
+            .commit(None, &old, &old, "abandoned", &tree, &[&base])
+            .expect("commit");
+        let abandoned = repo.find_commit(abandoned).expect("abandoned");
+        repo.branch("abandoned", &abandoned, false).expect("branch");
+        let fresh = at(now());
+        let fresh = repo
+            .commit(None, &fresh, &fresh, "fresh", &tree, &[&base])
+            .expect("commit");
+        repo.branch("fresh", &repo.find_commit(fresh).expect("fresh"), false)
+            .expect("branch");
+
+        let store = crate::store::open(&dir.to_string_lossy()).expect("store");
+        for from in ["abandoned", "fresh"] {
+            store
+                .create_pr(store::NewPr {
+                    title: from,
+                    from,
+                    to: "main",
+                    description: None,
+                })
+                .expect("pr");
+        }
+
+        let report = report(&repo, store.as_ref(), Query::default()).expect("report");
+        let names: Vec<(&str, bool)> = report
+            .branches
+            .iter()
+            .map(|branch| (branch.name.as_str(), branch.merged))
+            .collect();
+        assert_eq!(names, [("abandoned", false), ("merged", true)]);
+        assert_eq!(report.branches[0].open_prs, [1]);
+        assert!(report.branches[0].idle_days >= 59);
+        // Just opened, so neither PR is stale yet.
+        assert!(report.prs.is_empty());
+        let strict = super::report(
+            &repo,
+            store.as_ref(),
+            Query {
+                branch_days: 90,
+                pr_days: 0,
+            },
+        )
+        .expect("report");
+        assert!(strict.branches.is_empty());
+        assert_eq!(strict.prs.len(), 2);
+
+        let engine = AntEngine::new();
+        let goals = create_cleanup_goals(&engine, store.as_ref(), &report).expect("goals");
+        let ids: Vec<&str> = goals.iter().map(|goal| goal.goal_id.as_str()).collect();
+        assert_eq!(ids, ["stale-branch/abandoned", "stale-branch/merged"]);
+        assert!(goals[1].task.starts_with("Delete branch merged"));
+        assert_eq!(store.list_goals().expect("goals").len(), 2);
+        // A later run, even from a fresh process, doesn't queue them again.
+        let again =
+            create_cleanup_goals(&AntEngine::new(), store.as_ref(), &report).expect("again");
+        assert!(again.is_empty());
+    }
+}
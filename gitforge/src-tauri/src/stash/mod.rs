@@ -0,0 +1,227 @@
+//! `git stash`: parking uncommitted work before switching branches or
+//! worktrees, and bringing it back.
+
+use serde::Serialize;
+
+use crate::store;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct StashEntry {
+    /// `stash@{index}`; 0 is the newest.
+    pub index: usize,
+    pub oid: String,
+    pub message: String,
+    /// The branch it was saved on, from the message.
+    pub branch: Option<String>,
+    /// `YYYY-MM-DD HH:MM:SS` UTC.
+    pub date: String,
+}
+
+/// Why a stash couldn't be applied.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Failure {
+    /// The stash touches files that changed since it was saved; nothing was
+    /// applied and the stash is kept.
+    Conflict(String),
+    Other(String),
+}
+
+impl std::fmt::Display for Failure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Failure::Conflict(message) | Failure::Other(message) => f.write_str(message),
+        }
+    }
+}
+
+impl From<String> for Failure {
+    fn from(message: String) -> Self {
+        Failure::Other(message)
+    }
+}
+
+impl From<Failure> for String {
+    fn from(failure: Failure) -> Self {
+        failure.to_string()
+    }
+}
+
+/// Stashes tracked changes, and untracked files too with `untracked`.
+/// `keep_index` leaves staged changes in place as well as stashing them.
+pub fn save(
+    repo: &mut git2::Repository,
+    message: Option<&str>,
+    untracked: bool,
+    keep_index: bool,
+) -> Result<StashEntry, String> {
+    let signature = repo
+        .signature()
+        .or_else(|_| git2::Signature::now("GitForge", "gitforge@localhost"))
+        .map_err(|e| format!("failed to create signature: {e}"))?;
+    let mut flags = git2::StashFlags::DEFAULT;
+    if untracked {
+        flags |= git2::StashFlags::INCLUDE_UNTRACKED;
+    }
+    if keep_index {
+        flags |= git2::StashFlags::KEEP_INDEX;
+    }
+    let oid = repo
+        .stash_save2(&signature, message, Some(flags))
+        .map_err(|e| match e.code() {
+            git2::ErrorCode::NotFound => "no local changes to stash".to_string(),
+            _ => format!("failed to stash changes: {e}"),
+        })?;
+    list(repo)?
+        .into_iter()
+        .find(|entry| entry.oid == oid.to_string())
+        .ok_or_else(|| format!("stash {oid} is missing from the stash list"))
+}
+
+/// Every stash, newest first.
+pub fn list(repo: &mut git2::Repository) -> Result<Vec<StashEntry>, String> {
+    let mut found = Vec::new();
+    repo.stash_foreach(|index, message, oid| {
+        found.push((index, message.to_string(), *oid));
+        true
+    })
+    .map_err(|e| format!("failed to list stashes: {e}"))?;
+    found
+        .into_iter()
+        .map(|(index, message, oid)| {
+            let commit = repo
+                .find_commit(oid)
+                .map_err(|e| format!("failed to read stash@{{{index}}}: {e}"))?;
+            Ok(StashEntry {
+                index,
+                oid: oid.to_string(),
+                branch: branch(&message),
+                message,
+                date: store::timestamp(commit.time().seconds()),
+            })
+        })
+        .collect()
+}
+
+/// Applies `stash@{index}` to the working tree, restoring its staged
+/// changes as staged, and with `pop` drops it once it applied cleanly.
+/// `oid`, from [`list`], guards against the stack shifting in between.
+pub fn apply(
+    repo: &mut git2::Repository,
+    index: usize,
+    oid: Option<&str>,
+    pop: bool,
+) -> Result<StashEntry, Failure> {
+    let entry = find(repo, index, oid)?;
+    let mut opts = git2::StashApplyOptions::new();
+    opts.reinstantiate_index();
+    let result = if pop {
+        repo.stash_pop(index, Some(&mut opts))
+    } else {
+        repo.stash_apply(index, Some(&mut opts))
+    };
+    result.map_err(|e| match e.code() {
+        git2::ErrorCode::Conflict | git2::ErrorCode::MergeConflict => Failure::Conflict(format!(
+            "stash@{{{index}}} conflicts with changes in the working tree; commit or discard them first"
+        )),
+        _ => Failure::Other(format!("failed to apply stash@{{{index}}}: {e}")),
+    })?;
+    Ok(entry)
+}
+
+/// Deletes `stash@{index}` without applying it.
+pub fn drop(
+    repo: &mut git2::Repository,
+    index: usize,
+    oid: Option<&str>,
+) -> Result<StashEntry, String> {
+    let entry = find(repo, index, oid)?;
+    repo.stash_drop(index)
+        .map_err(|e| format!("failed to drop stash@{{{index}}}: {e}"))?;
+    Ok(entry)
+}
+
+fn find(
+    repo: &mut git2::Repository,
+    index: usize,
+    oid: Option<&str>,
+) -> Result<StashEntry, String> {
+    let entry = list(repo)?
+        .into_iter()
+        .find(|entry| entry.index == index)
+        .ok_or_else(|| format!("no stash@{{{index}}}"))?;
+    match oid {
+        Some(oid) if oid != entry.oid => Err(format!(
+            "stash@{{{index}}} is now {}, not {oid}; list the stashes again",
+            entry.oid
+        )),
+        _ => Ok(entry),
+    }
+}
+
+/// `main` from `WIP on main: 1a2b3c4 subject` or `On main: message`.
+fn branch(message: &str) -> Option<String> {
+    let rest = message
+        .strip_prefix("WIP on ")
+        .or_else(|| message.strip_prefix("On "))?;
+    let (branch, _) = rest.split_once(':')?;
+    (branch != "(no branch)").then(|| branch.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::path::Path;
+
+    #[test]
+    fn saves_lists_applies_pops_and_drops() {
+        let (dir, mut repo) = crate::test_support::init_repo("stash");
+        let sig = git2::Signature::now("GitForge Test", "test@gitforge.dev").expect("sig");
+        fs::write(dir.join("a.txt"), "one\n").expect("write");
+        {
+            let mut index = repo.index().expect("index");
+            index.add_path(Path::new("a.txt")).expect("add");
+            index.write().expect("write index");
+            let tree = repo
+                .find_tree(index.write_tree().expect("tree"))
+                .expect("tree");
+            repo.commit(Some("HEAD"), &sig, &sig, "init", &tree, &[])
+                .expect("commit");
+        }
+        let head = repo.head().unwrap().shorthand().unwrap().to_string();
+
+        assert!(save(&mut repo, None, false, false).is_err_and(|e| e.contains("no local")));
+        fs::write(dir.join("a.txt"), "two\n").expect("edit");
+        fs::write(dir.join("new.txt"), "new\n").expect("untracked");
+        let first = save(&mut repo, Some("park a"), false, false).expect("save");
+        assert_eq!(
+            (first.index, first.branch.as_deref()),
+            (0, Some(head.as_str()))
+        );
+        assert_eq!(fs::read_to_string(dir.join("a.txt")).unwrap(), "one\n");
+        assert!(dir.join("new.txt").exists());
+        let second = save(&mut repo, None, true, false).expect("save untracked");
+        assert!(!dir.join("new.txt").exists());
+        assert!(second.message.starts_with(&format!("WIP on {head}: ")));
+
+        let stashes = list(&mut repo).expect("list");
+        assert_eq!(stashes.len(), 2);
+        assert_eq!(stashes[1].message, format!("On {head}: park a"));
+        assert!(apply(&mut repo, 1, Some(&second.oid), false)
+            .is_err_and(|e| matches!(e, Failure::Other(m) if m.contains("list the stashes"))));
+
+        apply(&mut repo, 0, Some(&second.oid), true).expect("pop");
+        assert!(dir.join("new.txt").exists());
+        assert_eq!(list(&mut repo).unwrap().len(), 1);
+        fs::write(dir.join("a.txt"), "three\n").expect("edit again");
+        assert!(matches!(
+            apply(&mut repo, 0, None, true),
+            Err(Failure::Conflict(_))
+        ));
+        assert_eq!(fs::read_to_string(dir.join("a.txt")).unwrap(), "three\n");
+        assert_eq!(list(&mut repo).unwrap().len(), 1);
+        let dropped = drop(&mut repo, 0, Some(&first.oid)).expect("drop");
+        assert_eq!(dropped.message, format!("On {head}: park a"));
+        assert!(list(&mut repo).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,481 @@
+//! Repository statistics: commit frequency, contributors, hot files and
+//! lines changed per week on HEAD, and PR cycle time from the prs table.
+//! Merge commits are left out, like `git log --no-merges`. Per-commit line
+//! counts are cached in the sqlite store's `commit_stats` table, so a
+//! refresh only diffs the commits it hasn't seen before.
+
+use rusqlite::OptionalExtension;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::store::{self, MetaStore, PrQuery, MAX_PAGE_SIZE};
+
+pub const DEFAULT_DAYS: u32 = 90;
+pub const DEFAULT_TOP: usize = 10;
+
+const WEEK: i64 = 7 * 86_400;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Query {
+    /// How far back to look; `0` is all history.
+    pub days: u32,
+    /// How many contributors and hot files to list.
+    pub top: usize,
+}
+
+impl Default for Query {
+    fn default() -> Self {
+        Self {
+            days: DEFAULT_DAYS,
+            top: DEFAULT_TOP,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Stats {
+    /// Start of the range, or `None` for all history.
+    pub since: Option<String>,
+    pub commits: usize,
+    pub added: usize,
+    pub deleted: usize,
+    /// Every week of the range, oldest first, including quiet ones.
+    pub weeks: Vec<Week>,
+    /// Most commits first.
+    pub contributors: Vec<Contributor>,
+    /// Files touched by the most commits first.
+    pub hot_files: Vec<HotFile>,
+    pub pr_cycle: PrCycle,
+    /// Commits diffed for this call rather than read from the cache.
+    pub diffed: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Week {
+    /// The week's Monday, `YYYY-MM-DD`.
+    pub week: String,
+    pub commits: usize,
+    pub added: usize,
+    pub deleted: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Contributor {
+    /// The name on their newest commit.
+    pub name: String,
+    pub email: String,
+    pub commits: usize,
+    pub added: usize,
+    pub deleted: usize,
+    pub last_commit: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct HotFile {
+    pub path: String,
+    pub commits: usize,
+    pub added: usize,
+    pub deleted: usize,
+}
+
+/// PRs merged in the range, from opening to merge.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PrCycle {
+    pub merged: usize,
+    pub average_hours: Option<f64>,
+    pub median_hours: Option<f64>,
+}
+
+/// One commit's changes against its parent.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct CommitStats {
+    name: String,
+    email: String,
+    time: i64,
+    files: Vec<FileStats>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct FileStats {
+    path: String,
+    added: usize,
+    deleted: usize,
+}
+
+/// Statistics for HEAD; `cache` is the sqlite store's connection, when
+/// there is one.
+pub fn compute(
+    repo: &git2::Repository,
+    store: &dyn MetaStore,
+    cache: Option<&rusqlite::Connection>,
+    query: Query,
+) -> Result<Stats, String> {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or_default();
+    let since = (query.days > 0).then(|| now - i64::from(query.days) * 86_400);
+    let (commits, diffed) = commits(repo, cache, since)?;
+
+    let mut weeks: HashMap<i64, Week> = HashMap::new();
+    let mut contributors: HashMap<String, Contributor> = HashMap::new();
+    let mut files: HashMap<&str, HotFile> = HashMap::new();
+    let (mut added, mut deleted) = (0, 0);
+    // Newest first, so each contributor's first entry names them.
+    for commit in &commits {
+        let commit_added: usize = commit.files.iter().map(|file| file.added).sum();
+        let commit_deleted: usize = commit.files.iter().map(|file| file.deleted).sum();
+        added += commit_added;
+        deleted += commit_deleted;
+
+        let week = weeks.entry(monday(commit.time)).or_insert_with(|| Week {
+            week: String::new(),
+            commits: 0,
+            added: 0,
+            deleted: 0,
+        });
+        week.commits += 1;
+        week.added += commit_added;
+        week.deleted += commit_deleted;
+
+        let contributor = contributors
+            .entry(commit.email.to_lowercase())
+            .or_insert_with(|| Contributor {
+                name: commit.name.clone(),
+                email: commit.email.clone(),
+                commits: 0,
+                added: 0,
+                deleted: 0,
+                last_commit: store::timestamp(commit.time),
+            });
+        contributor.commits += 1;
+        contributor.added += commit_added;
+        contributor.deleted += commit_deleted;
+
+        for file in &commit.files {
+            let hot = files.entry(&file.path).or_insert_with(|| HotFile {
+                path: file.path.clone(),
+                commits: 0,
+                added: 0,
+                deleted: 0,
+            });
+            hot.commits += 1;
+            hot.added += file.added;
+            hot.deleted += file.deleted;
+        }
+    }
+
+    let first_week = since
+        .or_else(|| commits.last().map(|commit| commit.time))
+        .map(monday);
+    let mut week_list = Vec::new();
+    if let Some(mut start) = first_week {
+        while start <= monday(now) {
+            let mut week = weeks.remove(&start).unwrap_or(Week {
+                week: String::new(),
+                commits: 0,
+                added: 0,
+                deleted: 0,
+            });
+            week.week = store::timestamp(start)[..10].to_string();
+            week_list.push(week);
+            start += WEEK;
+        }
+    }
+
+    let mut contributors: Vec<Contributor> = contributors.into_values().collect();
+    contributors.sort_by(|a, b| b.commits.cmp(&a.commits).then(a.email.cmp(&b.email)));
+    contributors.truncate(query.top);
+    let mut hot_files: Vec<HotFile> = files.into_values().collect();
+    hot_files.sort_by(|a, b| {
+        b.commits
+            .cmp(&a.commits)
+            .then((b.added + b.deleted).cmp(&(a.added + a.deleted)))
+            .then(a.path.cmp(&b.path))
+    });
+    hot_files.truncate(query.top);
+
+    Ok(Stats {
+        since: since.map(store::timestamp),
+        commits: commits.len(),
+        added,
+        deleted,
+        weeks: week_list,
+        contributors,
+        hot_files,
+        pr_cycle: pr_cycle(store, since)?,
+        diffed,
+    })
+}
+
+/// The non-merge commits on HEAD since `since`, newest first, and how
+/// many of them had to be diffed.
+fn commits(
+    repo: &git2::Repository,
+    cache: Option<&rusqlite::Connection>,
+    since: Option<i64>,
+) -> Result<(Vec<CommitStats>, usize), String> {
+    let mut walk = repo
+        .revwalk()
+        .map_err(|e| format!("failed to walk history: {e}"))?;
+    walk.set_sorting(git2::Sort::TIME)
+        .map_err(|e| format!("failed to walk history: {e}"))?;
+    if walk.push_head().is_err() {
+        // An unborn HEAD has no history yet.
+        return Ok((Vec::new(), 0));
+    }
+    // One transaction for the whole refresh instead of one per commit.
+    let tx = cache
+        .map(|db| db.unchecked_transaction())
+        .transpose()
+        .map_err(|e| format!("failed to open the stats cache: {e}"))?;
+
+    let (mut commits, mut diffed) = (Vec::new(), 0);
+    for oid in walk {
+        let oid = oid.map_err(|e| format!("failed to walk history: {e}"))?;
+        let commit = repo
+            .find_commit(oid)
+            .map_err(|e| format!("failed to read commit {oid}: {e}"))?;
+        if since.is_some_and(|since| commit.time().seconds() < since) {
+            break;
+        }
+        if commit.parent_count() > 1 {
+            continue;
+        }
+        let cached = match &tx {
+            Some(db) => cached(db, oid)?,
+            None => None,
+        };
+        let stats = match cached {
+            Some(stats) => stats,
+            None => {
+                let stats = diff(repo, &commit)?;
+                if let Some(db) = &tx {
+                    remember(db, oid, &stats)?;
+                }
+                diffed += 1;
+                stats
+            }
+        };
+        commits.push(stats);
+    }
+    if let Some(tx) = tx {
+        tx.commit()
+            .map_err(|e| format!("failed to save the stats cache: {e}"))?;
+    }
+    Ok((commits, diffed))
+}
+
+fn diff(repo: &git2::Repository, commit: &git2::Commit<'_>) -> Result<CommitStats, String> {
+    let oid = commit.id();
+    let failed = |e: git2::Error| format!("failed to diff commit {oid}: {e}");
+    let tree = commit.tree().map_err(failed)?;
+    let parent = match commit.parent(0) {
+        Ok(parent) => Some(parent.tree().map_err(failed)?),
+        Err(_) => None,
+    };
+    let diff = repo
+        .diff_tree_to_tree(parent.as_ref(), Some(&tree), None)
+        .map_err(failed)?;
+
+    let mut files = Vec::new();
+    for (index, delta) in diff.deltas().enumerate() {
+        let Some(path) = delta.new_file().path().or_else(|| delta.old_file().path()) else {
+            continue;
+        };
+        // Binary files have no patch and count as touched without lines.
+        let (added, deleted) = match git2::Patch::from_diff(&diff, index).map_err(failed)? {
+            Some(patch) => {
+                let (_, added, deleted) = patch.line_stats().map_err(failed)?;
+                (added, deleted)
+            }
+            None => (0, 0),
+        };
+        files.push(FileStats {
+            path: path.to_string_lossy().into_owned(),
+            added,
+            deleted,
+        });
+    }
+    let author = commit.author();
+    Ok(CommitStats {
+        name: author.name().unwrap_or_default().to_string(),
+        email: author.email().unwrap_or_default().to_string(),
+        time: commit.time().seconds(),
+        files,
+    })
+}
+
+fn cached(db: &rusqlite::Connection, oid: git2::Oid) -> Result<Option<CommitStats>, String> {
+    let row = db
+        .prepare_cached("SELECT author, email, time, files FROM commit_stats WHERE oid = ?1")
+        .and_then(|mut stmt| {
+            stmt.query_row([oid.to_string()], |row| {
+                Ok((
+                    row.get(0)?,
+                    row.get(1)?,
+                    row.get(2)?,
+                    row.get::<_, String>(3)?,
+                ))
+            })
+            .optional()
+        })
+        .map_err(|e| format!("failed to read the stats cache: {e}"))?;
+    let Some((name, email, time, files)) = row else {
+        return Ok(None);
+    };
+    // An entry that doesn't parse is diffed again and overwritten.
+    Ok(serde_json::from_str(&files).ok().map(|files| CommitStats {
+        name,
+        email,
+        time,
+        files,
+    }))
+}
+
+fn remember(db: &rusqlite::Connection, oid: git2::Oid, stats: &CommitStats) -> Result<(), String> {
+    let files = serde_json::to_string(&stats.files)
+        .map_err(|e| format!("failed to encode commit stats: {e}"))?;
+    db.prepare_cached(
+        "INSERT OR REPLACE INTO commit_stats (oid, author, email, time, files)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+    )
+    .and_then(|mut stmt| {
+        stmt.execute(rusqlite::params![
+            oid.to_string(),
+            stats.name,
+            stats.email,
+            stats.time,
+            files
+        ])
+    })
+    .map(|_| ())
+    .map_err(|e| format!("failed to save the stats cache: {e}"))
+}
+
+fn pr_cycle(store: &dyn MetaStore, since: Option<i64>) -> Result<PrCycle, String> {
+    let mut hours = Vec::new();
+    let mut before = None;
+    loop {
+        let page = store.list_prs(PrQuery {
+            state: Some("merged"),
+            before,
+            limit: MAX_PAGE_SIZE,
+        })?;
+        for pr in &page.items {
+            let opened = store::parse_timestamp(&pr.created_at);
+            let merged = pr.merged_at.as_deref().and_then(store::parse_timestamp);
+            if let (Some(opened), Some(merged)) = (opened, merged) {
+                if since.is_none_or(|since| merged >= since) {
+                    hours.push((merged - opened).max(0) as f64 / 3600.0);
+                }
+            }
+        }
+        match page.next_before {
+            Some(next) => before = Some(next),
+            None => break,
+        }
+    }
+    hours.sort_by(f64::total_cmp);
+    let average = (!hours.is_empty()).then(|| hours.iter().sum::<f64>() / hours.len() as f64);
+    let median = match hours.len() {
+        0 => None,
+        n if n % 2 == 1 => Some(hours[n / 2]),
+        n => Some((hours[n / 2 - 1] + hours[n / 2]) / 2.0),
+    };
+    Ok(PrCycle {
+        merged: hours.len(),
+        average_hours: average,
+        median_hours: median,
+    })
+}
+
+/// Midnight UTC on the Monday of the week holding `secs`.
+fn monday(secs: i64) -> i64 {
+    let days = secs.div_euclid(86_400);
+    // 1970-01-01 was a Thursday, three days after a Monday.
+    (days - (days + 3).rem_euclid(7)) * 86_400
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stats_count_commits_files_and_reuse_the_cache() {
+        let (dir, repo) = crate::test_support::init_repo("stats");
+        let recent = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .expect("clock")
+            .as_secs() as i64
+            - 86_400;
+        let commit = |author: &str, path: &str, contents: &str| {
+            std::fs::write(dir.join(path), contents).expect("write");
+            let mut index = repo.index().expect("index");
+            index.add_path(std::path::Path::new(path)).expect("add");
+            index.write().expect("write index");
+            let tree = repo
+                .find_tree(index.write_tree().expect("tree"))
+                .expect("tree");
+            let signature = git2::Signature::new(
+                author,
+                &format!("{}@example.com", author.to_lowercase()),
+                &git2::Time::new(recent, 0),
+            )
+            .expect("sig");
+            let parents: Vec<git2::Commit<'_>> = repo
+                .head()
+                .ok()
+                .and_then(|head| head.peel_to_commit().ok())
+                .into_iter()
+                .collect();
+            let parents: Vec<&git2::Commit<'_>> = parents.iter().collect();
+            repo.commit(
+                Some("HEAD"),
+                &signature,
+                &signature,
+                "change",
+                &tree,
+                &parents,
+            )
+            .expect("commit");
+        };
+        commit("Ana", "a.txt", "one\ntwo\n");
+        commit("Ben", "a.txt", "one\n2\nthree\n");
+        commit("Ana", "b.txt", "b\n");
+
+        let repo_path = dir.to_string_lossy().to_string();
+        let store = store::open(&repo_path).expect("store");
+        let db = crate::db::open(&repo_path).expect("db");
+        let pr = store
+            .create_pr(store::NewPr {
+                title: "Stats",
+                from: "feature",
+                to: "main",
+                description: None,
+            })
+            .expect("pr");
+        store.set_pr_state(pr, "merged").expect("merged");
+
+        let stats = compute(&repo, store.as_ref(), Some(&db), Query::default()).expect("stats");
+        assert_eq!((stats.commits, stats.added, stats.deleted), (3, 5, 1));
+        assert_eq!(stats.diffed, 3);
+        assert_eq!(stats.weeks.len(), 13 + 1, "{:?}", stats.weeks);
+        assert_eq!(stats.weeks.iter().map(|w| w.commits).sum::<usize>(), 3);
+        assert_eq!(stats.contributors[0].name, "Ana");
+        assert_eq!(stats.contributors[0].commits, 2);
+        assert_eq!(stats.hot_files[0].path, "a.txt");
+        assert_eq!(stats.hot_files[0].commits, 2);
+        assert_eq!(stats.pr_cycle.merged, 1);
+        assert!(stats.pr_cycle.median_hours.is_some_and(|h| h < 1.0));
+
+        commit("Ben", "c.txt", "c\n");
+        let again = compute(&repo, store.as_ref(), Some(&db), Query::default()).expect("again");
+        assert_eq!((again.commits, again.diffed), (4, 1));
+        let uncached =
+            compute(&repo, store.as_ref(), None, Query { days: 0, top: 1 }).expect("all");
+        assert_eq!((uncached.commits, uncached.diffed), (4, 4));
+        assert_eq!(uncached.hot_files.len(), 1);
+        assert_eq!(monday(0), -3 * 86_400);
+    }
+}
@@ -0,0 +1,486 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::Path;
+use std::sync::{Arc, Mutex, Weak};
+use std::time::SystemTime;
+use tokio::sync::broadcast;
+
+use crate::watcher::service::{RepoChanged, WatchService};
+
+/// Changed paths beyond which one full rescan beats pathspec-limited ones.
+pub const MAX_INCREMENTAL_PATHS: usize = 256;
+
+/// How untracked files are reported, as in `git status --untracked-files`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Untracked {
+    /// Untracked directories collapse to one `dir/` entry.
+    Normal,
+    /// Every untracked file, recursing into untracked directories.
+    #[default]
+    All,
+    /// Untracked files are left out.
+    No,
+}
+
+impl Untracked {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "normal" => Some(Self::Normal),
+            "all" => Some(Self::All),
+            "no" => Some(Self::No),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn options(self) -> git2::StatusOptions {
+        let mut opts = git2::StatusOptions::new();
+        opts.include_untracked(self != Self::No)
+            .recurse_untracked_dirs(self == Self::All);
+        opts
+    }
+}
+
+/// Status per path, sorted by path.
+pub type Entries = Arc<BTreeMap<String, git2::Status>>;
+
+pub struct StatusView {
+    pub entries: Entries,
+    /// Served without touching the working tree.
+    pub cached: bool,
+}
+
+/// Entry counts per category. An entry staged and then edited again
+/// counts as both `staged` and `modified`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct StatusSummary {
+    pub total: usize,
+    pub staged: usize,
+    pub modified: usize,
+    pub deleted: usize,
+    pub renamed: usize,
+    pub untracked: usize,
+    pub conflicted: usize,
+}
+
+impl StatusSummary {
+    pub fn add(&mut self, status: git2::Status) {
+        use git2::Status as S;
+        let staged = S::INDEX_NEW
+            | S::INDEX_MODIFIED
+            | S::INDEX_DELETED
+            | S::INDEX_RENAMED
+            | S::INDEX_TYPECHANGE;
+        self.total += 1;
+        self.staged += status.intersects(staged) as usize;
+        self.modified += status.intersects(S::WT_MODIFIED | S::WT_TYPECHANGE) as usize;
+        self.deleted += status.intersects(S::INDEX_DELETED | S::WT_DELETED) as usize;
+        self.renamed += status.intersects(S::INDEX_RENAMED | S::WT_RENAMED) as usize;
+        self.untracked += status.contains(S::WT_NEW) as usize;
+        self.conflicted += status.contains(S::CONFLICTED) as usize;
+    }
+}
+
+/// What a snapshot was computed against; any difference forces a rescan.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Key {
+    /// `(size, mtime)` of `.git/index`.
+    index: Option<(u64, SystemTime)>,
+    head: Option<git2::Oid>,
+}
+
+struct Snapshot {
+    /// `None` until the first scan lands, and while one is running.
+    key: Option<Key>,
+    entries: Entries,
+    /// Paths the watcher reported since `entries` was computed.
+    dirty: BTreeSet<String>,
+    /// Too much (or an unknown amount) changed to patch `entries`.
+    overflow: bool,
+    /// The latest scan started; older ones finishing late are discarded.
+    taken: u64,
+}
+
+#[derive(Default)]
+enum Watch {
+    #[default]
+    Idle,
+    Running {
+        _service: WatchService,
+    },
+    Unavailable,
+}
+
+#[derive(Default)]
+struct Inner {
+    watch: Watch,
+    scans: u64,
+    snapshots: HashMap<Untracked, Snapshot>,
+}
+
+/// Last `git status` result per [`Untracked`] mode for one repository. A
+/// [`WatchService`] started on first use reports edited paths, so a repeat
+/// call rescans only those, or nothing at all. Without a watcher every call
+/// is a full scan. Cloning shares the cache.
+#[derive(Clone)]
+pub struct StatusCache {
+    repo_path: Arc<String>,
+    inner: Arc<Mutex<Inner>>,
+}
+
+impl StatusCache {
+    pub fn new(repo_path: &str) -> Self {
+        Self {
+            repo_path: Arc::new(repo_path.to_string()),
+            inner: Arc::default(),
+        }
+    }
+
+    /// Status of `repo`. Edits reach the cache after the watcher's debounce,
+    /// so callers that just wrote files should pass `refresh` to force a
+    /// full scan.
+    pub fn status(
+        &self,
+        repo: &git2::Repository,
+        untracked: Untracked,
+        refresh: bool,
+    ) -> Result<StatusView, String> {
+        let watching = self.ensure_watch()?;
+        if !watching {
+            return Ok(StatusView {
+                entries: scan(repo, untracked, None)?,
+                cached: false,
+            });
+        }
+
+        let key = key(repo);
+        let (scan_id, base) = {
+            let mut inner = self.lock();
+            inner.scans += 1;
+            let scan_id = inner.scans;
+            let snapshot = inner
+                .snapshots
+                .entry(untracked)
+                .or_insert_with(|| Snapshot {
+                    key: None,
+                    entries: Arc::default(),
+                    dirty: BTreeSet::new(),
+                    overflow: false,
+                    taken: 0,
+                });
+            let reusable = !refresh && !snapshot.overflow && snapshot.key.as_ref() == Some(&key);
+            if reusable && snapshot.dirty.is_empty() {
+                return Ok(StatusView {
+                    entries: Arc::clone(&snapshot.entries),
+                    cached: true,
+                });
+            }
+
+            // Anything the watcher reports from here on lands in a fresh
+            // dirty set and applies on top of this scan.
+            let dirty = std::mem::take(&mut snapshot.dirty);
+            let base = reusable.then(|| (Arc::clone(&snapshot.entries), dirty));
+            snapshot.key = None;
+            snapshot.overflow = false;
+            snapshot.taken = scan_id;
+            (scan_id, base)
+        };
+
+        let entries = scan(repo, untracked, base)?;
+        let mut inner = self.lock();
+        if let Some(snapshot) = inner.snapshots.get_mut(&untracked) {
+            if snapshot.taken == scan_id {
+                snapshot.key = Some(key);
+                snapshot.entries = Arc::clone(&entries);
+            }
+        }
+        Ok(StatusView {
+            entries,
+            cached: false,
+        })
+    }
+
+    fn ensure_watch(&self) -> Result<bool, String> {
+        let mut inner = self.lock();
+        match inner.watch {
+            Watch::Running { .. } => return Ok(true),
+            Watch::Unavailable => return Ok(false),
+            Watch::Idle => {}
+        }
+        match WatchService::start(&self.repo_path) {
+            Ok(service) => {
+                let rx = service.subscribe();
+                let weak = Arc::downgrade(&self.inner);
+                std::thread::spawn(move || follow(weak, rx));
+                inner.watch = Watch::Running { _service: service };
+                Ok(true)
+            }
+            Err(e) => {
+                eprintln!("status cache for {} disabled: {e}", self.repo_path);
+                inner.watch = Watch::Unavailable;
+                Ok(false)
+            }
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Inner> {
+        lock(&self.inner)
+    }
+}
+
+/// A panic mid-scan may leave a snapshot half-updated, so every snapshot
+/// is rescanned in full on its next use.
+fn lock(inner: &Mutex<Inner>) -> std::sync::MutexGuard<'_, Inner> {
+    crate::sync::lock_or_repair(inner, |inner| {
+        for snapshot in inner.snapshots.values_mut() {
+            snapshot.overflow = true;
+        }
+    })
+}
+
+/// Feeds watcher batches into the snapshots until the cache is dropped
+/// (which stops the watcher and closes the channel).
+fn follow(weak: Weak<Mutex<Inner>>, mut rx: broadcast::Receiver<RepoChanged>) {
+    loop {
+        let changed = match rx.blocking_recv() {
+            Ok(changed) => Some(changed),
+            Err(broadcast::error::RecvError::Lagged(_)) => None,
+            Err(broadcast::error::RecvError::Closed) => return,
+        };
+        let Some(inner) = weak.upgrade() else {
+            return;
+        };
+        let mut inner = lock(&inner);
+        for snapshot in inner.snapshots.values_mut() {
+            match &changed {
+                Some(changed) if !changed.index => {
+                    snapshot.dirty.extend(changed.paths.iter().cloned());
+                    if snapshot.dirty.len() > MAX_INCREMENTAL_PATHS {
+                        snapshot.overflow = true;
+                    }
+                }
+                // Staging can rewrite the index without changing its size
+                // within one mtime tick, so don't rely on the key alone.
+                _ => snapshot.overflow = true,
+            }
+        }
+    }
+}
+
+fn key(repo: &git2::Repository) -> Key {
+    let index = std::fs::metadata(repo.path().join("index"))
+        .ok()
+        .and_then(|meta| Some((meta.len(), meta.modified().ok()?)));
+    let head = repo.head().ok().and_then(|head| head.target());
+    Key { index, head }
+}
+
+/// Full scan, or with `base` a rescan of just the dirty paths patched into
+/// the previous entries.
+fn scan(
+    repo: &git2::Repository,
+    untracked: Untracked,
+    base: Option<(Entries, BTreeSet<String>)>,
+) -> Result<Entries, String> {
+    let mut opts = untracked.options();
+    let mut entries = match base {
+        Some((mut entries, dirty)) => match pathspecs(repo, untracked, &dirty)? {
+            Some(specs) => {
+                let map = Arc::make_mut(&mut entries);
+                for spec in &specs {
+                    map.remove(spec);
+                    // `{spec}/` up to `{spec}0` ('/' + 1) is everything below.
+                    let below: Vec<String> = map
+                        .range(format!("{spec}/")..format!("{spec}0"))
+                        .map(|(path, _)| path.clone())
+                        .collect();
+                    for path in below {
+                        map.remove(&path);
+                    }
+                    opts.pathspec(spec);
+                }
+                entries
+            }
+            None => Arc::default(),
+        },
+        None => Arc::default(),
+    };
+
+    let statuses = repo
+        .statuses(Some(&mut opts))
+        .map_err(|e| format!("failed to read status: {e}"))?;
+    let map = Arc::make_mut(&mut entries);
+    for entry in statuses.iter() {
+        if let Some(path) = entry.path() {
+            map.insert(path.to_string(), entry.status());
+        }
+    }
+    Ok(entries)
+}
+
+/// Pathspecs covering every status entry `dirty` can affect, or `None`
+/// when only a full scan is safe.
+fn pathspecs(
+    repo: &git2::Repository,
+    untracked: Untracked,
+    dirty: &BTreeSet<String>,
+) -> Result<Option<Vec<String>>, String> {
+    if dirty.len() > MAX_INCREMENTAL_PATHS {
+        return Ok(None);
+    }
+    // Ignore rules change what every other path reports; glob characters
+    // would widen the pathspec.
+    let unsafe_path = |path: &String| {
+        Path::new(path)
+            .file_name()
+            .is_some_and(|n| n == ".gitignore")
+            || path.contains(['*', '?', '[', '\\'])
+    };
+    if dirty.iter().any(unsafe_path) {
+        return Ok(None);
+    }
+    if untracked != Untracked::Normal {
+        return Ok(Some(dirty.iter().cloned().collect()));
+    }
+
+    // A file inside an untracked directory shows up as that directory, so
+    // rescan from its outermost prefix with nothing tracked below.
+    let index = repo
+        .index()
+        .map_err(|e| format!("failed to read index: {e}"))?;
+    let mut specs = BTreeSet::new();
+    for path in dirty {
+        let mut prefix = String::new();
+        for component in path.split('/') {
+            if !prefix.is_empty() {
+                prefix.push('/');
+            }
+            prefix.push_str(component);
+            if index.find_prefix(format!("{prefix}/")).is_err() {
+                break;
+            }
+        }
+        specs.insert(prefix);
+    }
+    Ok(Some(specs.into_iter().collect()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    use std::time::{Duration, Instant};
+
+    fn repo_with_files(
+        label: &str,
+        files: &[&str],
+    ) -> (crate::test_support::TempDir, git2::Repository) {
+        let (dir, repo) = crate::test_support::init_repo(&format!("status-{label}"));
+        {
+            let mut index = repo.index().expect("index");
+            for file in files {
+                write(&dir, file, "tracked");
+                index.add_path(Path::new(file)).expect("stage");
+            }
+            index.write().expect("write index");
+            let tree = repo
+                .find_tree(index.write_tree().expect("write tree"))
+                .expect("tree");
+            let sig = git2::Signature::now("Test", "test@example.com").expect("signature");
+            repo.commit(Some("HEAD"), &sig, &sig, "init", &tree, &[])
+                .expect("commit");
+        }
+        (dir, repo)
+    }
+
+    fn write(dir: &Path, file: &str, contents: &str) {
+        let full = dir.join(file);
+        fs::create_dir_all(full.parent().unwrap()).expect("mkdir");
+        fs::write(full, contents).expect("write");
+    }
+
+    fn full(repo: &git2::Repository, untracked: Untracked) -> Entries {
+        scan(repo, untracked, None).expect("full scan")
+    }
+
+    #[test]
+    fn rescanning_dirty_paths_matches_a_full_scan() {
+        let (dir, repo) = repo_with_files("incremental", &["src/lib.rs", "README.md"]);
+        write(&dir, "notes/old.txt", "untracked");
+
+        for mode in [Untracked::Normal, Untracked::All, Untracked::No] {
+            let before = full(&repo, mode);
+            write(&dir, "src/lib.rs", "edited");
+            write(&dir, "notes/new.txt", "untracked");
+            write(&dir, "scratch/deep/a.txt", "untracked");
+            fs::remove_file(dir.join("README.md")).expect("delete");
+
+            let dirty: BTreeSet<String> = [
+                "src/lib.rs",
+                "notes/new.txt",
+                "scratch/deep/a.txt",
+                "README.md",
+            ]
+            .map(String::from)
+            .into();
+            let patched = scan(&repo, mode, Some((before, dirty))).expect("incremental");
+            assert_eq!(patched, full(&repo, mode), "{mode:?}");
+
+            // Undo for the next mode.
+            write(&dir, "src/lib.rs", "tracked");
+            write(&dir, "README.md", "tracked");
+            fs::remove_file(dir.join("notes/new.txt")).expect("cleanup");
+            fs::remove_dir_all(dir.join("scratch")).expect("cleanup");
+        }
+
+        let normal = full(&repo, Untracked::Normal);
+        assert!(normal.contains_key("notes/"));
+        assert!(full(&repo, Untracked::All).contains_key("notes/old.txt"));
+        assert!(full(&repo, Untracked::No).is_empty());
+
+        let mut summary = StatusSummary::default();
+        for status in full(&repo, Untracked::All).values() {
+            summary.add(*status);
+        }
+        assert_eq!((summary.total, summary.untracked), (1, 1));
+
+        let dirty = BTreeSet::from([".gitignore".to_string()]);
+        assert_eq!(
+            pathspecs(&repo, Untracked::All, &dirty).expect("specs"),
+            None
+        );
+    }
+
+    #[test]
+    fn repeat_calls_are_served_from_cache_until_files_change() {
+        let (dir, repo) = repo_with_files("cache", &["a.txt"]);
+        let cache = StatusCache::new(&dir.to_string_lossy());
+
+        let first = cache.status(&repo, Untracked::All, false).expect("status");
+        assert!(!first.cached);
+        assert!(first.entries.is_empty());
+        let second = cache.status(&repo, Untracked::All, false).expect("status");
+        assert!(second.cached);
+
+        write(&dir, "a.txt", "changed");
+        let deadline = Instant::now() + Duration::from_secs(10);
+        let changed = loop {
+            let view = cache.status(&repo, Untracked::All, false).expect("status");
+            if !view.cached {
+                break view;
+            }
+            assert!(Instant::now() < deadline, "watcher never reported a.txt");
+            std::thread::sleep(Duration::from_millis(20));
+        };
+        assert_eq!(
+            changed.entries.get("a.txt"),
+            Some(&git2::Status::WT_MODIFIED)
+        );
+
+        write(&dir, "b.txt", "new");
+        let forced = cache.status(&repo, Untracked::All, true).expect("refresh");
+        assert!(!forced.cached);
+        assert_eq!(forced.entries.get("b.txt"), Some(&git2::Status::WT_NEW));
+    }
+}
@@ -0,0 +1,156 @@
+use serde::Serialize;
+
+use super::{timestamp, unix_now, MetaStore};
+
+/// Git config keys for the retention policy; `0` keeps events forever.
+pub const EVENT_DAYS_KEY: &str = "gitforge.retention.eventDays";
+pub const MAX_EVENTS_KEY: &str = "gitforge.retention.maxEvents";
+
+pub const DEFAULT_EVENT_DAYS: u32 = 90;
+pub const DEFAULT_MAX_EVENTS: usize = 100_000;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RetentionPolicy {
+    /// Drop events older than this many days.
+    pub event_days: Option<u32>,
+    /// Keep at most this many of the newest events.
+    pub max_events: Option<usize>,
+}
+
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        Self {
+            event_days: Some(DEFAULT_EVENT_DAYS),
+            max_events: Some(DEFAULT_MAX_EVENTS),
+        }
+    }
+}
+
+impl RetentionPolicy {
+    /// Defaults overridden by the repo's `gitforge.retention.*` git config.
+    pub fn from_config(repo_path: &str) -> Result<Self, String> {
+        let mut policy = Self::default();
+        let Ok(config) = git2::Repository::open(repo_path).and_then(|repo| repo.config()) else {
+            return Ok(policy);
+        };
+
+        if let Some(days) = read_number(&config, EVENT_DAYS_KEY)? {
+            policy.event_days = (days > 0).then_some(days as u32);
+        }
+        if let Some(max) = read_number(&config, MAX_EVENTS_KEY)? {
+            policy.max_events = (max > 0).then_some(max as usize);
+        }
+        Ok(policy)
+    }
+}
+
+fn read_number(config: &git2::Config, key: &str) -> Result<Option<i64>, String> {
+    match config.get_i64(key) {
+        Ok(value) if value >= 0 => Ok(Some(value)),
+        Ok(value) => Err(format!("{key} must not be negative (got {value})")),
+        Err(e) if e.code() == git2::ErrorCode::NotFound => Ok(None),
+        Err(e) => Err(format!("invalid {key}: {e}")),
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MaintenanceReport {
+    pub dry_run: bool,
+    pub policy: RetentionPolicy,
+    pub events_pruned: usize,
+    pub bytes_before: u64,
+    pub bytes_after: u64,
+    pub reclaimed_bytes: u64,
+}
+
+/// Applies `policy` and compacts the store. A dry run only counts what
+/// would be pruned and skips compaction.
+pub fn maintain(
+    store: &dyn MetaStore,
+    policy: &RetentionPolicy,
+    dry_run: bool,
+) -> Result<MaintenanceReport, String> {
+    let bytes_before = size_on_disk(store);
+    let cutoff = policy
+        .event_days
+        .map(|days| timestamp(unix_now() - i64::from(days) * 86_400));
+    let events_pruned = store.prune_events(cutoff.as_deref(), policy.max_events, dry_run)?;
+
+    if !dry_run {
+        store.compact()?;
+    }
+    let bytes_after = size_on_disk(store);
+
+    Ok(MaintenanceReport {
+        dry_run,
+        policy: policy.clone(),
+        events_pruned,
+        bytes_before,
+        bytes_after,
+        reclaimed_bytes: bytes_before.saturating_sub(bytes_after),
+    })
+}
+
+fn size_on_disk(store: &dyn MetaStore) -> u64 {
+    store
+        .files()
+        .iter()
+        .filter_map(|path| std::fs::metadata(path).ok())
+        .map(|meta| meta.len())
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn policy_reads_git_config_and_zero_disables() {
+        let (dir, repo) = crate::test_support::init_repo("maintain-config");
+        let path = dir.as_str();
+        assert_eq!(
+            RetentionPolicy::from_config(path).expect("defaults"),
+            RetentionPolicy::default()
+        );
+
+        let mut config = repo.config().expect("config");
+        config.set_i64(EVENT_DAYS_KEY, 7).expect("set days");
+        config.set_i64(MAX_EVENTS_KEY, 0).expect("set max");
+        let policy = RetentionPolicy::from_config(path).expect("policy");
+
+        assert_eq!(policy.event_days, Some(7));
+        assert_eq!(policy.max_events, None);
+    }
+
+    #[test]
+    fn maintain_prunes_and_reports_reclaimed_space() {
+        let (dir, _repo) = crate::test_support::init_repo("maintain-run");
+        let path = dir.as_str();
+        let store = super::super::open(path).expect("open store");
+        let padding = "x".repeat(4096);
+        for i in 0..200 {
+            store
+                .append_event(&ant_core::VersionedSystemEvent {
+                    schema_version: ant_core::SYSTEM_EVENT_SCHEMA_VERSION,
+                    event: ant_core::SystemEvent::GoalCreated {
+                        goal_id: format!("G-{i}"),
+                        task: padding.clone(),
+                    },
+                })
+                .expect("append");
+        }
+        let policy = RetentionPolicy {
+            event_days: None,
+            max_events: Some(10),
+        };
+
+        let preview = maintain(store.as_ref(), &policy, true).expect("dry run");
+        assert_eq!(preview.events_pruned, 190);
+        assert_eq!(store.events_since(0, 1000).expect("events").len(), 200);
+
+        let report = maintain(store.as_ref(), &policy, false).expect("maintain");
+        assert_eq!(report.events_pruned, 190);
+        assert!(report.reclaimed_bytes > 0, "{report:?}");
+        assert_eq!(store.events_since(0, 1000).expect("events").len(), 10);
+    }
+}
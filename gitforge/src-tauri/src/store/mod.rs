@@ -0,0 +1,1043 @@
+pub mod maintenance;
+mod redb_store;
+pub mod settings;
+mod sqlite_store;
+
+pub use maintenance::{MaintenanceReport, RetentionPolicy};
+pub use redb_store::RedbStore;
+pub use sqlite_store::SqliteStore;
+
+use ant_core::VersionedSystemEvent;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+pub use crate::db::events::StoredEvent;
+
+/// Backend override, taking precedence over git config.
+pub const STORE_ENV: &str = "GITFORGE_STORE";
+/// Per-repo git config key: `sqlite` (default) or `redb`.
+pub const STORE_CONFIG_KEY: &str = "gitforge.store";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PrRecord {
+    pub id: i64,
+    pub title: String,
+    pub from: String,
+    pub to: String,
+    pub state: String,
+    pub description: Option<String>,
+    pub created_at: String,
+    /// Jira/Linear issues the PR refers to.
+    #[serde(default)]
+    pub links: Vec<LinkedIssue>,
+    /// One per reviewer, oldest first.
+    #[serde(default)]
+    pub approvals: Vec<Approval>,
+    /// Reviews requested when the PR was opened, e.g. from CODEOWNERS.
+    #[serde(default)]
+    pub reviewers: Vec<String>,
+    /// When the PR was last set to `merged`.
+    #[serde(default)]
+    pub merged_at: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Approval {
+    pub reviewer: String,
+    pub approved_at: String,
+}
+
+/// An issue in an external tracker, as last fetched. `title` and `status`
+/// are unset when the tracker couldn't be reached.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LinkedIssue {
+    /// `PROJ-123`.
+    pub key: String,
+    /// `jira` or `linear`.
+    pub tracker: String,
+    pub title: Option<String>,
+    pub status: Option<String>,
+    pub url: Option<String>,
+}
+
+/// Default and maximum page size for list queries.
+pub const DEFAULT_PAGE_SIZE: usize = 50;
+pub const MAX_PAGE_SIZE: usize = 500;
+
+/// Keyset page of PRs, newest first. `before` is the `next_before` cursor
+/// from the previous page, so pages stay stable while new PRs arrive.
+#[derive(Debug, Clone, Default)]
+pub struct PrQuery<'a> {
+    pub state: Option<&'a str>,
+    pub before: Option<i64>,
+    pub limit: usize,
+}
+
+impl PrQuery<'_> {
+    fn page_size(&self) -> usize {
+        match self.limit {
+            0 => DEFAULT_PAGE_SIZE,
+            n => n.min(MAX_PAGE_SIZE),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    /// Cursor for the next page; `None` once the listing is exhausted.
+    pub next_before: Option<i64>,
+}
+
+impl<T> Page<T> {
+    fn of(items: Vec<T>, page_size: usize, id: fn(&T) -> i64) -> Self {
+        let next_before = (items.len() == page_size)
+            .then(|| items.last().map(id))
+            .flatten();
+        Self { items, next_before }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct NewPr<'a> {
+    pub title: &'a str,
+    pub from: &'a str,
+    pub to: &'a str,
+    pub description: Option<&'a str>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IssueRecord {
+    pub id: i64,
+    pub title: String,
+    pub body: Option<String>,
+    /// `open` or `closed`.
+    pub state: String,
+    /// Goal the issue is being worked under.
+    pub goal_id: Option<String>,
+    /// PR that addresses the issue.
+    pub pr_id: Option<i64>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+/// Keyset page of issues, newest first; see [`PrQuery`].
+#[derive(Debug, Clone, Default)]
+pub struct IssueQuery<'a> {
+    pub state: Option<&'a str>,
+    pub goal_id: Option<&'a str>,
+    pub pr_id: Option<i64>,
+    pub before: Option<i64>,
+    pub limit: usize,
+}
+
+impl IssueQuery<'_> {
+    fn page_size(&self) -> usize {
+        match self.limit {
+            0 => DEFAULT_PAGE_SIZE,
+            n => n.min(MAX_PAGE_SIZE),
+        }
+    }
+
+    fn matches(&self, issue: &IssueRecord) -> bool {
+        self.state.is_none_or(|state| issue.state == state)
+            && self
+                .goal_id
+                .is_none_or(|goal| issue.goal_id.as_deref() == Some(goal))
+            && self.pr_id.is_none_or(|pr| issue.pr_id == Some(pr))
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct NewIssue<'a> {
+    pub title: &'a str,
+    pub body: Option<&'a str>,
+    pub goal_id: Option<&'a str>,
+    pub pr_id: Option<i64>,
+}
+
+/// Fields to change on an issue; `None` leaves a field as it is.
+#[derive(Debug, Clone, Default)]
+pub struct IssueUpdate<'a> {
+    pub title: Option<&'a str>,
+    pub body: Option<&'a str>,
+    pub state: Option<&'a str>,
+    pub goal_id: Option<&'a str>,
+    pub pr_id: Option<i64>,
+}
+
+impl IssueUpdate<'_> {
+    fn apply(&self, issue: &mut IssueRecord) {
+        if let Some(title) = self.title {
+            issue.title = title.to_string();
+        }
+        if let Some(body) = self.body {
+            issue.body = Some(body.to_string());
+        }
+        if let Some(state) = self.state {
+            issue.state = state.to_string();
+        }
+        if let Some(goal_id) = self.goal_id {
+            issue.goal_id = Some(goal_id.to_string());
+        }
+        if let Some(pr_id) = self.pr_id {
+            issue.pr_id = Some(pr_id);
+        }
+    }
+}
+
+/// A CI run started for a PR, as last polled.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CheckRecord {
+    pub id: i64,
+    pub pr_id: i64,
+    /// `github`, `gitlab`, or `webhook`.
+    pub provider: String,
+    pub branch: String,
+    /// `queued`, `in_progress`, or `completed`.
+    pub status: String,
+    /// Set once `completed`: `success`, `failure`, `cancelled`, ...
+    pub conclusion: Option<String>,
+    /// The provider's id for the run, once known.
+    pub run_id: Option<String>,
+    pub url: Option<String>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct NewCheck<'a> {
+    pub pr_id: i64,
+    pub provider: &'a str,
+    pub branch: &'a str,
+    pub status: &'a str,
+    pub conclusion: Option<&'a str>,
+    pub run_id: Option<&'a str>,
+    pub url: Option<&'a str>,
+}
+
+/// Fields to change on a check; `None` leaves a field as it is.
+#[derive(Debug, Clone, Default)]
+pub struct CheckUpdate<'a> {
+    pub status: Option<&'a str>,
+    pub conclusion: Option<&'a str>,
+    pub run_id: Option<&'a str>,
+    pub url: Option<&'a str>,
+}
+
+impl CheckUpdate<'_> {
+    fn apply(&self, check: &mut CheckRecord) {
+        if let Some(status) = self.status {
+            check.status = status.to_string();
+        }
+        if let Some(conclusion) = self.conclusion {
+            check.conclusion = Some(conclusion.to_string());
+        }
+        if let Some(run_id) = self.run_id {
+            check.run_id = Some(run_id.to_string());
+        }
+        if let Some(url) = self.url {
+            check.url = Some(url.to_string());
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WorktreeRecord {
+    pub name: String,
+    pub path: String,
+    pub branch: String,
+    pub created_at: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GoalRecord {
+    pub goal_id: String,
+    pub task: String,
+    pub status: Option<String>,
+    pub updated_at: String,
+}
+
+/// One line of a goal's execution log.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GoalLogLine {
+    /// Increasing across every goal's log.
+    pub id: i64,
+    pub goal_id: String,
+    pub line: String,
+    pub recorded_at: String,
+}
+
+/// Largest page [`MetaStore::goal_logs`] returns, however many are
+/// requested.
+pub const MAX_GOAL_LOG_PAGE: usize = 1000;
+
+/// A file attached to a goal or PR. Its bytes are in the artifact store
+/// under `hash`, so re-attaching the same output adds no copy.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ArtifactRecord {
+    pub id: i64,
+    pub hash: String,
+    pub name: String,
+    pub media_type: String,
+    pub size: u64,
+    pub goal_id: Option<String>,
+    pub pr_id: Option<i64>,
+    pub created_at: String,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct NewArtifact<'a> {
+    pub hash: &'a str,
+    pub name: &'a str,
+    pub media_type: &'a str,
+    pub size: u64,
+    pub goal_id: Option<&'a str>,
+    pub pr_id: Option<i64>,
+}
+
+/// A persisted setting; `value` is any JSON value.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SettingRecord {
+    pub key: String,
+    pub value: serde_json::Value,
+    pub updated_at: String,
+}
+
+/// Metadata persistence used by the MCP server, CLI, and agent. Lists are
+/// newest first except events, which page oldest first by sequence.
+pub trait MetaStore: Send + Sync {
+    fn backend(&self) -> Backend;
+
+    fn create_pr(&self, pr: NewPr<'_>) -> Result<i64, String>;
+    fn get_pr(&self, id: i64) -> Result<Option<PrRecord>, String>;
+    fn list_prs(&self, query: PrQuery<'_>) -> Result<Page<PrRecord>, String>;
+    /// Replaces the external issues linked to PR `id`; `false` if there is
+    /// no such PR.
+    fn set_pr_links(&self, id: i64, links: &[LinkedIssue]) -> Result<bool, String>;
+    /// Records `reviewer`'s approval of PR `id`, once per reviewer; `false`
+    /// if there is no such PR.
+    fn approve_pr(&self, id: i64, reviewer: &str) -> Result<bool, String>;
+    /// Replaces the reviewers requested on PR `id`; `false` if there is no
+    /// such PR.
+    fn set_pr_reviewers(&self, id: i64, reviewers: &[String]) -> Result<bool, String>;
+    /// Sets the state of PR `id` (e.g. `approved`, `merged`), stamping
+    /// `merged_at` on a merge; `false` if there is no such PR.
+    fn set_pr_state(&self, id: i64, state: &str) -> Result<bool, String>;
+
+    fn create_issue(&self, issue: NewIssue<'_>) -> Result<i64, String>;
+    fn get_issue(&self, id: i64) -> Result<Option<IssueRecord>, String>;
+    fn list_issues(&self, query: IssueQuery<'_>) -> Result<Page<IssueRecord>, String>;
+    /// Applies `update` to issue `id` and returns the result; `None` if
+    /// there is no such issue.
+    fn update_issue(&self, id: i64, update: IssueUpdate<'_>)
+        -> Result<Option<IssueRecord>, String>;
+
+    fn create_check(&self, check: NewCheck<'_>) -> Result<i64, String>;
+    /// Checks of PR `pr_id`, newest first.
+    fn list_checks(&self, pr_id: i64) -> Result<Vec<CheckRecord>, String>;
+    /// Applies `update` to check `id` and returns the result; `None` if
+    /// there is no such check.
+    fn update_check(&self, id: i64, update: CheckUpdate<'_>)
+        -> Result<Option<CheckRecord>, String>;
+
+    /// Inserts or replaces the worktree registered under `name`.
+    fn upsert_worktree(&self, name: &str, path: &str, branch: &str) -> Result<(), String>;
+    fn list_worktrees(&self) -> Result<Vec<WorktreeRecord>, String>;
+    fn remove_worktree(&self, name: &str) -> Result<bool, String>;
+
+    fn list_goals(&self) -> Result<Vec<GoalRecord>, String>;
+    /// Appends `lines` to goal `goal_id`'s log, in order, and returns them
+    /// as stored.
+    fn append_goal_logs(&self, goal_id: &str, lines: &[String])
+        -> Result<Vec<GoalLogLine>, String>;
+    /// Goal `goal_id`'s log lines with an id above `after`, oldest first.
+    fn goal_logs(
+        &self,
+        goal_id: &str,
+        after: i64,
+        limit: usize,
+    ) -> Result<Vec<GoalLogLine>, String>;
+
+    fn add_artifact(&self, artifact: NewArtifact<'_>) -> Result<i64, String>;
+    fn get_artifact(&self, id: i64) -> Result<Option<ArtifactRecord>, String>;
+    /// Artifacts attached to goal `goal_id` and PR `pr_id`, newest first;
+    /// every artifact when both are `None`.
+    fn list_artifacts(
+        &self,
+        goal_id: Option<&str>,
+        pr_id: Option<i64>,
+    ) -> Result<Vec<ArtifactRecord>, String>;
+
+    fn get_setting(&self, key: &str) -> Result<Option<serde_json::Value>, String>;
+    /// Inserts or replaces the setting stored under `key`.
+    fn set_setting(&self, key: &str, value: &serde_json::Value) -> Result<(), String>;
+    /// All settings, ordered by key.
+    fn list_settings(&self) -> Result<Vec<SettingRecord>, String>;
+
+    /// Stores `event`, updates the goal it refers to, and returns its
+    /// sequence number. Sequences are never reused.
+    fn append_event(&self, event: &VersionedSystemEvent) -> Result<i64, String>;
+    fn events_since(&self, after: i64, limit: usize) -> Result<Vec<StoredEvent>, String>;
+    /// Events with a sequence below `before` (all when `None`), newest
+    /// first.
+    fn events_before(&self, before: Option<i64>, limit: usize) -> Result<Vec<StoredEvent>, String>;
+
+    /// Deletes events recorded before `cutoff` (a `YYYY-MM-DD HH:MM:SS`
+    /// timestamp) and all but the newest `keep_latest`, returning how many
+    /// rows matched. With `dry_run` nothing is deleted.
+    fn prune_events(
+        &self,
+        cutoff: Option<&str>,
+        keep_latest: Option<usize>,
+        dry_run: bool,
+    ) -> Result<usize, String>;
+
+    /// Reclaims space after bulk deletes.
+    fn compact(&self) -> Result<(), String>;
+
+    /// Files backing the store, for size reporting.
+    fn files(&self) -> Vec<PathBuf>;
+
+    /// The underlying pool for sqlite-only features (search, review
+    /// comments, export); `None` for other backends.
+    fn sqlite_pool(&self) -> Option<&crate::db::Pool> {
+        None
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Backend {
+    Sqlite,
+    Redb,
+}
+
+impl Backend {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Backend::Sqlite => "sqlite",
+            Backend::Redb => "redb",
+        }
+    }
+
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "" | "sqlite" => Ok(Backend::Sqlite),
+            "redb" => Ok(Backend::Redb),
+            other => Err(format!(
+                "unknown store backend '{other}' (expected sqlite or redb)"
+            )),
+        }
+    }
+}
+
+/// The backend selected by `$GITFORGE_STORE` or the repo's `gitforge.store`
+/// git config; sqlite when neither is set.
+pub fn configured_backend(repo_path: &str) -> Result<Backend, String> {
+    if let Some(value) = std::env::var(STORE_ENV).ok().filter(|v| !v.is_empty()) {
+        return Backend::parse(&value);
+    }
+
+    let configured = git2::Repository::open(repo_path)
+        .ok()
+        .and_then(|repo| repo.config().ok())
+        .and_then(|config| config.get_string(STORE_CONFIG_KEY).ok());
+    configured.map_or(Ok(Backend::Sqlite), |value| Backend::parse(&value))
+}
+
+/// Opens the configured store for `repo_path`.
+pub fn open(repo_path: &str) -> Result<Arc<dyn MetaStore>, String> {
+    open_backend(repo_path, configured_backend(repo_path)?)
+}
+
+pub fn open_backend(repo_path: &str, backend: Backend) -> Result<Arc<dyn MetaStore>, String> {
+    Ok(match backend {
+        Backend::Sqlite => Arc::new(SqliteStore::open(repo_path)?),
+        Backend::Redb => Arc::new(RedbStore::open(repo_path)?),
+    })
+}
+
+pub(crate) fn now_timestamp() -> String {
+    timestamp(unix_now())
+}
+
+fn unix_now() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or_default()
+}
+
+/// Seconds since the epoch of a [`timestamp`], or `None` if `text` isn't
+/// one.
+pub(crate) fn parse_timestamp(text: &str) -> Option<i64> {
+    let number = |range: std::ops::Range<usize>| text.get(range)?.parse::<i64>().ok();
+    if text.len() != "2024-01-01 00:00:00".len() {
+        return None;
+    }
+    let (year, month, day) = (number(0..4)?, number(5..7)?, number(8..10)?);
+    let (hour, minute, second) = (number(11..13)?, number(14..16)?, number(17..19)?);
+
+    // Days-from-civil, the inverse of the conversion in `timestamp`.
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let doy = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146_097 + doe - 719_468;
+    Some(days * 86_400 + hour * 3600 + minute * 60 + second)
+}
+
+/// `YYYY-MM-DD HH:MM:SS` in UTC, matching sqlite's `CURRENT_TIMESTAMP`, so
+/// timestamps from both backends compare correctly as strings.
+pub(crate) fn timestamp(secs: i64) -> String {
+    let (days, rem) = (secs.div_euclid(86_400), secs.rem_euclid(86_400));
+
+    // Civil-from-days (Howard Hinnant), valid for the proleptic Gregorian calendar.
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{year:04}-{month:02}-{day:02} {:02}:{:02}:{:02}",
+        rem / 3600,
+        rem % 3600 / 60,
+        rem % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{temp_dir, TempDir};
+    use ant_core::{GoalStatus, SystemEvent, SYSTEM_EVENT_SCHEMA_VERSION};
+
+    /// Every test runs against each backend, each in its own directory.
+    fn stores(label: &str) -> Vec<(TempDir, Arc<dyn MetaStore>)> {
+        [Backend::Sqlite, Backend::Redb]
+            .into_iter()
+            .map(|backend| {
+                let dir = temp_dir(&format!("store-{label}-{}", backend.as_str()));
+                let store = open_backend(dir.as_str(), backend).expect("open store");
+                (dir, store)
+            })
+            .collect()
+    }
+
+    fn event(event: SystemEvent) -> VersionedSystemEvent {
+        VersionedSystemEvent {
+            schema_version: SYSTEM_EVENT_SCHEMA_VERSION,
+            event,
+        }
+    }
+
+    #[test]
+    fn prs_are_listed_newest_first() {
+        for (_dir, store) in stores("prs") {
+            let first = store
+                .create_pr(NewPr {
+                    title: "First",
+                    from: "feature/a",
+                    to: "main",
+                    description: Some("details"),
+                })
+                .expect("create first");
+            let second = store
+                .create_pr(NewPr {
+                    title: "Second",
+                    from: "feature/b",
+                    to: "main",
+                    description: None,
+                })
+                .expect("create second");
+            assert!(second > first);
+
+            let prs = store.list_prs(PrQuery::default()).expect("list").items;
+            let titles: Vec<&str> = prs.iter().map(|p| p.title.as_str()).collect();
+            assert_eq!(titles, ["Second", "First"], "{:?}", store.backend());
+            assert_eq!(prs[0].state, "open");
+            assert_eq!(prs[1].description.as_deref(), Some("details"));
+            assert_eq!(prs[1].created_at.len(), "2024-01-01 00:00:00".len());
+        }
+    }
+
+    #[test]
+    fn pr_pages_follow_the_cursor_and_filter_by_state() {
+        for (_dir, store) in stores("pr-pages") {
+            for i in 0..5 {
+                store
+                    .create_pr(NewPr {
+                        title: &format!("PR {i}"),
+                        from: "feature",
+                        to: "main",
+                        description: None,
+                    })
+                    .expect("create");
+            }
+
+            let first = store
+                .list_prs(PrQuery {
+                    limit: 2,
+                    ..PrQuery::default()
+                })
+                .expect("first page");
+            let titles: Vec<&str> = first.items.iter().map(|p| p.title.as_str()).collect();
+            assert_eq!(titles, ["PR 4", "PR 3"], "{:?}", store.backend());
+
+            let second = store
+                .list_prs(PrQuery {
+                    before: first.next_before,
+                    limit: 2,
+                    ..PrQuery::default()
+                })
+                .expect("second page");
+            assert_eq!(second.items[0].title, "PR 2");
+
+            let last = store
+                .list_prs(PrQuery {
+                    before: Some(second.items[1].id),
+                    limit: 2,
+                    ..PrQuery::default()
+                })
+                .expect("last page");
+            assert_eq!(last.items.len(), 1);
+            assert_eq!(last.next_before, None);
+
+            let merged = store
+                .list_prs(PrQuery {
+                    state: Some("merged"),
+                    ..PrQuery::default()
+                })
+                .expect("merged");
+            assert!(merged.items.is_empty());
+
+            assert!(store
+                .set_pr_state(last.items[0].id, "merged")
+                .expect("update"));
+            assert!(!store.set_pr_state(999, "merged").expect("missing PR"));
+            let link = LinkedIssue {
+                key: "PROJ-1".to_string(),
+                tracker: "jira".to_string(),
+                title: Some("Login".to_string()),
+                status: None,
+                url: None,
+            };
+            assert!(store
+                .set_pr_links(last.items[0].id, std::slice::from_ref(&link))
+                .expect("link"));
+            assert!(!store.set_pr_links(999, &[]).expect("missing PR"));
+            for reviewer in ["ana", "ben", "ana"] {
+                assert!(store
+                    .approve_pr(last.items[0].id, reviewer)
+                    .expect("approve"));
+            }
+            assert!(!store.approve_pr(999, "ana").expect("missing PR"));
+            let requested = ["@ana".to_string(), "@web".to_string()];
+            assert!(store
+                .set_pr_reviewers(last.items[0].id, &requested)
+                .expect("request reviews"));
+            assert!(!store.set_pr_reviewers(999, &[]).expect("missing PR"));
+            let pr = store
+                .get_pr(last.items[0].id)
+                .expect("get")
+                .expect("exists");
+            assert_eq!(pr.state, "merged");
+            assert_eq!(
+                pr.merged_at.as_ref().map(String::len),
+                Some("2024-01-01 00:00:00".len()),
+                "{:?}",
+                store.backend()
+            );
+            assert_eq!(pr.links, [link], "{:?}", store.backend());
+            let reviewers: Vec<&str> = pr.approvals.iter().map(|a| a.reviewer.as_str()).collect();
+            assert_eq!(reviewers, ["ana", "ben"], "{:?}", store.backend());
+            assert_eq!(pr.reviewers, requested, "{:?}", store.backend());
+            assert!(store.get_pr(999).expect("missing PR").is_none());
+            let merged = store
+                .list_prs(PrQuery {
+                    state: Some("merged"),
+                    ..PrQuery::default()
+                })
+                .expect("merged");
+            let titles: Vec<&str> = merged.items.iter().map(|p| p.title.as_str()).collect();
+            assert_eq!(titles, ["PR 0"], "{:?}", store.backend());
+        }
+    }
+
+    #[test]
+    fn issues_filter_update_and_page() {
+        for (_dir, store) in stores("issues") {
+            let first = store
+                .create_issue(NewIssue {
+                    title: "Crash on empty repo",
+                    body: Some("steps"),
+                    goal_id: Some("G-1"),
+                    ..NewIssue::default()
+                })
+                .expect("create first");
+            let second = store
+                .create_issue(NewIssue {
+                    title: "Slow status",
+                    ..NewIssue::default()
+                })
+                .expect("create second");
+            assert!(second > first);
+
+            let issue = store.get_issue(first).expect("get").expect("exists");
+            assert_eq!(issue.state, "open", "{:?}", store.backend());
+            assert_eq!(issue.goal_id.as_deref(), Some("G-1"));
+
+            let closed = store
+                .update_issue(
+                    first,
+                    IssueUpdate {
+                        state: Some("closed"),
+                        pr_id: Some(7),
+                        ..IssueUpdate::default()
+                    },
+                )
+                .expect("update")
+                .expect("exists");
+            assert_eq!(closed.state, "closed");
+            assert_eq!(closed.pr_id, Some(7));
+            assert_eq!(
+                closed.body.as_deref(),
+                Some("steps"),
+                "untouched fields stay"
+            );
+            assert!(store
+                .update_issue(999, IssueUpdate::default())
+                .expect("missing issue")
+                .is_none());
+
+            let open = store
+                .list_issues(IssueQuery {
+                    state: Some("open"),
+                    ..IssueQuery::default()
+                })
+                .expect("open");
+            let titles: Vec<&str> = open.items.iter().map(|i| i.title.as_str()).collect();
+            assert_eq!(titles, ["Slow status"], "{:?}", store.backend());
+
+            let by_pr = store
+                .list_issues(IssueQuery {
+                    pr_id: Some(7),
+                    ..IssueQuery::default()
+                })
+                .expect("by PR");
+            assert_eq!(by_pr.items.len(), 1);
+
+            let page = store
+                .list_issues(IssueQuery {
+                    limit: 1,
+                    ..IssueQuery::default()
+                })
+                .expect("page");
+            assert_eq!(page.next_before, Some(second));
+            let rest = store
+                .list_issues(IssueQuery {
+                    before: page.next_before,
+                    ..IssueQuery::default()
+                })
+                .expect("rest");
+            assert_eq!(rest.items[0].id, first);
+            assert_eq!(rest.next_before, None);
+        }
+    }
+
+    #[test]
+    fn checks_are_listed_per_pr_and_updated() {
+        for (_dir, store) in stores("checks") {
+            let pr = |title| {
+                store
+                    .create_pr(NewPr {
+                        title,
+                        from: "feature",
+                        to: "main",
+                        description: None,
+                    })
+                    .expect("create PR")
+            };
+            let (pr1, pr2) = (pr("Checked"), pr("Other"));
+            let queued = |pr_id| NewCheck {
+                pr_id,
+                provider: "github",
+                branch: "feature",
+                status: "queued",
+                ..NewCheck::default()
+            };
+            let first = store.create_check(queued(pr1)).expect("first");
+            let second = store.create_check(queued(pr1)).expect("second");
+            store.create_check(queued(pr2)).expect("other PR");
+
+            let done = store
+                .update_check(
+                    first,
+                    CheckUpdate {
+                        status: Some("completed"),
+                        conclusion: Some("success"),
+                        run_id: Some("42"),
+                        ..CheckUpdate::default()
+                    },
+                )
+                .expect("update")
+                .expect("exists");
+            assert_eq!(done.conclusion.as_deref(), Some("success"));
+            assert_eq!(done.branch, "feature", "untouched fields stay");
+            assert!(store
+                .update_check(999, CheckUpdate::default())
+                .expect("missing check")
+                .is_none());
+
+            let checks = store.list_checks(pr1).expect("list");
+            let ids: Vec<i64> = checks.iter().map(|c| c.id).collect();
+            assert_eq!(ids, [second, first], "{:?}", store.backend());
+            assert_eq!(checks[1].run_id.as_deref(), Some("42"));
+            assert!(store.list_checks(999).expect("none").is_empty());
+        }
+    }
+
+    #[test]
+    fn worktrees_upsert_and_remove() {
+        for (_dir, store) in stores("worktrees") {
+            store
+                .upsert_worktree("wt", "/tmp/wt", "feature/a")
+                .expect("insert");
+            store
+                .upsert_worktree("wt", "/tmp/wt2", "feature/b")
+                .expect("replace");
+
+            let worktrees = store.list_worktrees().expect("list");
+            assert_eq!(worktrees.len(), 1, "{:?}", store.backend());
+            assert_eq!(worktrees[0].branch, "feature/b");
+
+            assert!(store.remove_worktree("wt").expect("remove"));
+            assert!(!store.remove_worktree("wt").expect("remove again"));
+            store.compact().expect("compact");
+            assert!(store.list_worktrees().expect("list").is_empty());
+        }
+    }
+
+    #[test]
+    fn events_page_by_sequence_and_project_goals() {
+        for (_dir, store) in stores("events") {
+            let created = store
+                .append_event(&event(SystemEvent::GoalCreated {
+                    goal_id: "G-1".into(),
+                    task: "Nightly triage".into(),
+                }))
+                .expect("append created");
+            store
+                .append_event(&event(SystemEvent::GoalStatusChanged {
+                    goal_id: "G-1".into(),
+                    status: GoalStatus::Completed,
+                }))
+                .expect("append status");
+
+            let page = store.events_since(created, 10).expect("events");
+            assert_eq!(page.len(), 1, "{:?}", store.backend());
+            assert_eq!(page[0].kind, "goal_status_changed");
+            assert_eq!(page[0].event["status"], "completed");
+
+            let newest = store.events_before(None, 10).expect("newest");
+            let kinds: Vec<&str> = newest.iter().map(|e| e.kind.as_str()).collect();
+            assert_eq!(kinds, ["goal_status_changed", "goal_created"]);
+            let older = store.events_before(Some(newest[0].seq), 10).expect("older");
+            assert_eq!(older.len(), 1);
+            assert_eq!(older[0].seq, created);
+            assert!(store
+                .events_before(Some(created), 10)
+                .expect("none")
+                .is_empty());
+
+            let goals = store.list_goals().expect("goals");
+            assert_eq!(goals.len(), 1);
+            assert_eq!(goals[0].task, "Nightly triage");
+            assert_eq!(goals[0].status.as_deref(), Some("completed"));
+        }
+    }
+
+    #[test]
+    fn goal_logs_page_per_goal_in_order() {
+        for (_dir, store) in stores("goal-logs") {
+            let lines = |texts: &[&str]| texts.iter().map(|t| t.to_string()).collect::<Vec<_>>();
+            let first = store
+                .append_goal_logs("G-1", &lines(&["cloning", "building"]))
+                .expect("append");
+            store
+                .append_goal_logs("G-2", &lines(&["other goal"]))
+                .expect("append other");
+            let last = store
+                .append_goal_logs("G-1", &lines(&["tests passed"]))
+                .expect("append again");
+            assert!(first[0].id < first[1].id && first[1].id < last[0].id);
+            assert_eq!(first[0].recorded_at.len(), "2024-01-01 00:00:00".len());
+
+            let read = |after: i64, limit: usize| -> Vec<String> {
+                store
+                    .goal_logs("G-1", after, limit)
+                    .expect("read")
+                    .into_iter()
+                    .map(|line| line.line)
+                    .collect()
+            };
+            assert_eq!(
+                read(0, 10),
+                ["cloning", "building", "tests passed"],
+                "{:?}",
+                store.backend()
+            );
+            assert_eq!(read(0, 1), ["cloning"]);
+            assert_eq!(read(first[1].id, 10), ["tests passed"]);
+            assert!(read(last[0].id, 10).is_empty());
+            assert!(store.goal_logs("G-3", 0, 10).expect("none").is_empty());
+        }
+    }
+
+    #[test]
+    fn artifacts_list_by_goal_and_pr() {
+        for (_dir, store) in stores("artifacts") {
+            let pr = store
+                .create_pr(NewPr {
+                    title: "Build",
+                    from: "feature",
+                    to: "main",
+                    description: None,
+                })
+                .expect("pr");
+            let artifact = |name, goal_id, pr_id| NewArtifact {
+                hash: "da39a3ee5e6b4b0d3255bfef95601890afd80709",
+                name,
+                media_type: "text/plain",
+                size: 0,
+                goal_id,
+                pr_id,
+            };
+            let log = store
+                .add_artifact(artifact("build.log", Some("G-1"), None))
+                .expect("add");
+            let report = store
+                .add_artifact(artifact("report.xml", Some("G-1"), Some(pr)))
+                .expect("add");
+            store
+                .add_artifact(artifact("shot.png", None, Some(pr)))
+                .expect("add");
+
+            let names = |goal_id, pr_id| -> Vec<String> {
+                store
+                    .list_artifacts(goal_id, pr_id)
+                    .expect("list")
+                    .into_iter()
+                    .map(|artifact| artifact.name)
+                    .collect()
+            };
+            assert_eq!(
+                names(Some("G-1"), None),
+                ["report.xml", "build.log"],
+                "{:?}",
+                store.backend()
+            );
+            assert_eq!(names(None, Some(pr)), ["shot.png", "report.xml"]);
+            assert_eq!(names(Some("G-1"), Some(pr)), ["report.xml"]);
+            assert_eq!(names(None, None).len(), 3);
+            let found = store.get_artifact(report).expect("get").expect("exists");
+            assert_eq!((found.pr_id, found.size), (Some(pr), 0));
+            assert!(found.created_at.len() == "2024-01-01 00:00:00".len() && log < report);
+            assert!(store.get_artifact(report + 10).expect("get").is_none());
+        }
+    }
+
+    #[test]
+    fn event_retention_prunes_by_count_and_age() {
+        for (_dir, store) in stores("retention") {
+            for i in 0..4 {
+                store
+                    .append_event(&event(SystemEvent::GoalCancelled {
+                        goal_id: format!("G-{i}"),
+                    }))
+                    .expect("append");
+            }
+
+            assert_eq!(store.prune_events(None, Some(1), true).expect("dry run"), 3);
+            assert_eq!(store.events_since(0, 10).expect("events").len(), 4);
+
+            assert_eq!(store.prune_events(None, Some(1), false).expect("prune"), 3);
+            let left = store.events_since(0, 10).expect("events");
+            assert_eq!(left.len(), 1, "{:?}", store.backend());
+            assert_eq!(left[0].event["goal_id"], "G-3");
+
+            let future = timestamp(unix_now() + 60);
+            assert_eq!(
+                store.prune_events(Some(&future), None, false).expect("age"),
+                1
+            );
+            assert!(store.events_since(0, 10).expect("events").is_empty());
+        }
+    }
+
+    #[test]
+    fn settings_are_typed_and_overwritten() {
+        for (_dir, store) in stores("settings") {
+            assert_eq!(store.get_bool("ui.wrap").expect("unset"), None);
+
+            store.set_bool("ui.wrap", true).expect("set bool");
+            store.set_int("editor.tabSize", 4).expect("set int");
+            store.set_string("ui.theme", "dark").expect("set string");
+            store
+                .set_json("panels", &vec!["files", "terminal"])
+                .expect("set json");
+            store.set_string("ui.theme", "light").expect("overwrite");
+
+            assert_eq!(store.get_bool("ui.wrap").expect("bool"), Some(true));
+            assert_eq!(store.get_int("editor.tabSize").expect("int"), Some(4));
+            assert_eq!(
+                store.get_string("ui.theme").expect("string").as_deref(),
+                Some("light")
+            );
+            assert_eq!(
+                store.get_json::<Vec<String>>("panels").expect("json"),
+                Some(vec!["files".to_string(), "terminal".to_string()])
+            );
+            let err = store.get_int("ui.theme").expect_err("wrong type");
+            assert!(err.contains("is not an integer"), "{err}");
+
+            let keys: Vec<String> = store
+                .list_settings()
+                .expect("list")
+                .into_iter()
+                .map(|setting| setting.key)
+                .collect();
+            assert_eq!(
+                keys,
+                ["editor.tabSize", "panels", "ui.theme", "ui.wrap"],
+                "{:?}",
+                store.backend()
+            );
+        }
+    }
+
+    #[test]
+    fn timestamps_match_sqlite_format() {
+        assert_eq!(timestamp(0), "1970-01-01 00:00:00");
+        assert_eq!(timestamp(1_709_211_845), "2024-02-29 13:04:05");
+        for secs in [0, 951_782_400, 1_709_211_845, 4_102_444_799] {
+            assert_eq!(parse_timestamp(&timestamp(secs)), Some(secs));
+        }
+        assert_eq!(parse_timestamp("2024-02-29"), None);
+    }
+
+    #[test]
+    fn backend_names_parse() {
+        assert_eq!(Backend::parse("REDB"), Ok(Backend::Redb));
+        assert_eq!(Backend::parse(""), Ok(Backend::Sqlite));
+        assert!(Backend::parse("mongo").is_err());
+    }
+}
@@ -0,0 +1,808 @@
+use ant_core::{SystemEvent, VersionedSystemEvent};
+use redb::{ReadableTable, TableDefinition};
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+use std::path::PathBuf;
+use std::sync::RwLock;
+
+use super::{
+    now_timestamp, Approval, ArtifactRecord, Backend, CheckRecord, CheckUpdate, GoalLogLine,
+    GoalRecord, IssueQuery, IssueRecord, IssueUpdate, LinkedIssue, MetaStore, NewArtifact,
+    NewCheck, NewIssue, NewPr, Page, PrQuery, PrRecord, SettingRecord, StoredEvent, WorktreeRecord,
+    MAX_GOAL_LOG_PAGE,
+};
+use crate::db::events::MAX_EVENTS_PAGE;
+
+/// Records are stored as JSON so adding a field doesn't need a migration.
+const PRS: TableDefinition<u64, &str> = TableDefinition::new("prs");
+const ISSUES: TableDefinition<u64, &str> = TableDefinition::new("issues");
+const CHECKS: TableDefinition<u64, &str> = TableDefinition::new("pr_checks");
+const WORKTREES: TableDefinition<&str, &str> = TableDefinition::new("worktrees");
+const GOALS: TableDefinition<&str, &str> = TableDefinition::new("goals");
+const GOAL_LOGS: TableDefinition<u64, &str> = TableDefinition::new("goal_logs");
+const ARTIFACTS: TableDefinition<u64, &str> = TableDefinition::new("artifacts");
+const EVENTS: TableDefinition<u64, &str> = TableDefinition::new("events");
+const SETTINGS: TableDefinition<&str, &str> = TableDefinition::new("settings");
+/// Last issued id per sequence, so ids are never reused after deletes.
+const SEQUENCES: TableDefinition<&str, u64> = TableDefinition::new("sequences");
+
+/// Worktrees and goals are keyed by name; `order` preserves the
+/// newest-first listing the sqlite backend gets from its rowids.
+#[derive(Serialize, Deserialize)]
+struct Ordered<T> {
+    order: u64,
+    #[serde(flatten)]
+    record: T,
+}
+
+pub struct RedbStore {
+    /// `compact` needs exclusive access; everything else shares the lock.
+    db: RwLock<redb::Database>,
+    path: PathBuf,
+}
+
+impl RedbStore {
+    pub fn open(repo_path: &str) -> Result<Self, String> {
+        Self::open_path(redb_path(repo_path))
+    }
+
+    pub fn open_path(path: PathBuf) -> Result<Self, String> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("failed to create {}: {e}", parent.display()))?;
+        }
+        let db = redb::Database::create(&path).map_err(fail("failed to open redb store"))?;
+
+        // Read transactions fail on tables that were never created.
+        let tx = db
+            .begin_write()
+            .map_err(fail("failed to initialize redb"))?;
+        tx.open_table(PRS)
+            .map_err(fail("failed to initialize redb"))?;
+        tx.open_table(ISSUES)
+            .map_err(fail("failed to initialize redb"))?;
+        tx.open_table(CHECKS)
+            .map_err(fail("failed to initialize redb"))?;
+        tx.open_table(WORKTREES)
+            .map_err(fail("failed to initialize redb"))?;
+        tx.open_table(GOALS)
+            .map_err(fail("failed to initialize redb"))?;
+        tx.open_table(GOAL_LOGS)
+            .map_err(fail("failed to initialize redb"))?;
+        tx.open_table(ARTIFACTS)
+            .map_err(fail("failed to initialize redb"))?;
+        tx.open_table(EVENTS)
+            .map_err(fail("failed to initialize redb"))?;
+        tx.open_table(SETTINGS)
+            .map_err(fail("failed to initialize redb"))?;
+        tx.open_table(SEQUENCES)
+            .map_err(fail("failed to initialize redb"))?;
+        tx.commit().map_err(fail("failed to initialize redb"))?;
+
+        Ok(Self {
+            db: RwLock::new(db),
+            path,
+        })
+    }
+
+    fn write<T>(
+        &self,
+        what: &'static str,
+        f: impl FnOnce(&redb::WriteTransaction) -> Result<T, String>,
+    ) -> Result<T, String> {
+        let db = crate::sync::read(&self.db);
+        let tx = db.begin_write().map_err(fail(what))?;
+        let value = f(&tx)?;
+        tx.commit().map_err(fail(what))?;
+        Ok(value)
+    }
+
+    /// Applies `update` to PR `id`; `false` if there is no such PR.
+    fn update_pr(&self, id: i64, update: impl FnOnce(&mut PrRecord)) -> Result<bool, String> {
+        if id < 1 {
+            return Ok(false);
+        }
+        self.write("failed to update PR", |tx| {
+            let mut table = tx.open_table(PRS).map_err(fail("failed to open PRs"))?;
+            let existing = table
+                .get(id as u64)
+                .map_err(fail("failed to read PR"))?
+                .map(|value| decode::<PrRecord>(value.value()))
+                .transpose()?;
+            let Some(mut pr) = existing else {
+                return Ok(false);
+            };
+            update(&mut pr);
+            table
+                .insert(id as u64, encode(&pr)?.as_str())
+                .map_err(fail("failed to update PR"))?;
+            Ok(true)
+        })
+    }
+
+    fn read<T>(
+        &self,
+        what: &'static str,
+        f: impl FnOnce(&redb::ReadTransaction) -> Result<T, String>,
+    ) -> Result<T, String> {
+        let db = crate::sync::read(&self.db);
+        let tx = db.begin_read().map_err(fail(what))?;
+        f(&tx)
+    }
+}
+
+/// `gitforge.redb` next to where `gitforge.db` would live.
+pub fn redb_path(repo_path: &str) -> PathBuf {
+    crate::db::db_path(repo_path).with_extension("redb")
+}
+
+fn fail<E: Display>(what: &'static str) -> impl Fn(E) -> String {
+    move |e| format!("{what}: {e}")
+}
+
+fn next_id(tx: &redb::WriteTransaction, sequence: &str) -> Result<u64, String> {
+    let mut table = tx
+        .open_table(SEQUENCES)
+        .map_err(fail("failed to open sequences"))?;
+    let next = table
+        .get(sequence)
+        .map_err(fail("failed to read sequence"))?
+        .map_or(0, |v| v.value())
+        + 1;
+    table
+        .insert(sequence, next)
+        .map_err(fail("failed to advance sequence"))?;
+    Ok(next)
+}
+
+fn encode<T: Serialize>(value: &T) -> Result<String, String> {
+    serde_json::to_string(value).map_err(fail("failed to encode record"))
+}
+
+fn decode<T: for<'de> Deserialize<'de>>(json: &str) -> Result<T, String> {
+    serde_json::from_str(json).map_err(fail("failed to decode record"))
+}
+
+fn upsert_goal(
+    tx: &redb::WriteTransaction,
+    goal_id: &str,
+    update: impl FnOnce(&mut GoalRecord),
+) -> Result<(), String> {
+    let existing = {
+        let table = tx.open_table(GOALS).map_err(fail("failed to open goals"))?;
+        let row = table.get(goal_id).map_err(fail("failed to read goal"))?;
+        row.map(|v| decode::<Ordered<GoalRecord>>(v.value()))
+            .transpose()?
+    };
+    let mut goal = match existing {
+        Some(goal) => goal,
+        None => Ordered {
+            order: next_id(tx, "goals")?,
+            record: GoalRecord {
+                goal_id: goal_id.to_string(),
+                task: String::new(),
+                status: None,
+                updated_at: String::new(),
+            },
+        },
+    };
+    update(&mut goal.record);
+    goal.record.updated_at = now_timestamp();
+
+    tx.open_table(GOALS)
+        .map_err(fail("failed to open goals"))?
+        .insert(goal_id, encode(&goal)?.as_str())
+        .map_err(fail("failed to update goal"))?;
+    Ok(())
+}
+
+impl MetaStore for RedbStore {
+    fn backend(&self) -> Backend {
+        Backend::Redb
+    }
+
+    fn create_pr(&self, pr: NewPr<'_>) -> Result<i64, String> {
+        self.write("failed to save PR", |tx| {
+            let id = next_id(tx, "prs")?;
+            let record = PrRecord {
+                id: id as i64,
+                title: pr.title.to_string(),
+                from: pr.from.to_string(),
+                to: pr.to.to_string(),
+                state: "open".to_string(),
+                description: pr.description.map(str::to_string),
+                created_at: now_timestamp(),
+                links: Vec::new(),
+                approvals: Vec::new(),
+                reviewers: Vec::new(),
+                merged_at: None,
+            };
+            tx.open_table(PRS)
+                .map_err(fail("failed to open PRs"))?
+                .insert(id, encode(&record)?.as_str())
+                .map_err(fail("failed to save PR"))?;
+            Ok(record.id)
+        })
+    }
+
+    fn set_pr_state(&self, id: i64, state: &str) -> Result<bool, String> {
+        self.update_pr(id, |pr| {
+            pr.state = state.to_string();
+            if state == "merged" {
+                pr.merged_at = Some(now_timestamp());
+            }
+        })
+    }
+
+    fn set_pr_links(&self, id: i64, links: &[LinkedIssue]) -> Result<bool, String> {
+        self.update_pr(id, |pr| pr.links = links.to_vec())
+    }
+
+    fn set_pr_reviewers(&self, id: i64, reviewers: &[String]) -> Result<bool, String> {
+        self.update_pr(id, |pr| pr.reviewers = reviewers.to_vec())
+    }
+
+    fn approve_pr(&self, id: i64, reviewer: &str) -> Result<bool, String> {
+        self.update_pr(id, |pr| {
+            if !pr.approvals.iter().any(|a| a.reviewer == reviewer) {
+                pr.approvals.push(Approval {
+                    reviewer: reviewer.to_string(),
+                    approved_at: now_timestamp(),
+                });
+            }
+        })
+    }
+
+    fn get_pr(&self, id: i64) -> Result<Option<PrRecord>, String> {
+        if id < 1 {
+            return Ok(None);
+        }
+        self.read("failed to read PR", |tx| {
+            let table = tx.open_table(PRS).map_err(fail("failed to open PRs"))?;
+            let row = table.get(id as u64).map_err(fail("failed to read PR"))?;
+            row.map(|v| decode(v.value())).transpose()
+        })
+    }
+
+    fn list_prs(&self, query: PrQuery<'_>) -> Result<Page<PrRecord>, String> {
+        let page_size = query.page_size();
+        let before = query.before.map_or(u64::MAX, |id| id.max(0) as u64);
+        let items = self.read("failed to list PRs", |tx| {
+            let table = tx.open_table(PRS).map_err(fail("failed to open PRs"))?;
+            let rows = table.range(..before).map_err(fail("failed to list PRs"))?;
+            let mut items = Vec::new();
+            for row in rows.rev() {
+                let (_, value) = row.map_err(fail("failed to parse PR row"))?;
+                let pr: PrRecord = decode(value.value())?;
+                if query.state.is_none_or(|state| pr.state == state) {
+                    items.push(pr);
+                    if items.len() == page_size {
+                        break;
+                    }
+                }
+            }
+            Ok(items)
+        })?;
+        Ok(Page::of(items, page_size, |pr| pr.id))
+    }
+
+    fn create_issue(&self, issue: NewIssue<'_>) -> Result<i64, String> {
+        self.write("failed to save issue", |tx| {
+            let id = next_id(tx, "issues")?;
+            let now = now_timestamp();
+            let record = IssueRecord {
+                id: id as i64,
+                title: issue.title.to_string(),
+                body: issue.body.map(str::to_string),
+                state: "open".to_string(),
+                goal_id: issue.goal_id.map(str::to_string),
+                pr_id: issue.pr_id,
+                created_at: now.clone(),
+                updated_at: now,
+            };
+            tx.open_table(ISSUES)
+                .map_err(fail("failed to open issues"))?
+                .insert(id, encode(&record)?.as_str())
+                .map_err(fail("failed to save issue"))?;
+            Ok(record.id)
+        })
+    }
+
+    fn get_issue(&self, id: i64) -> Result<Option<IssueRecord>, String> {
+        if id < 1 {
+            return Ok(None);
+        }
+        self.read("failed to read issue", |tx| {
+            let table = tx
+                .open_table(ISSUES)
+                .map_err(fail("failed to open issues"))?;
+            let row = table.get(id as u64).map_err(fail("failed to read issue"))?;
+            row.map(|v| decode(v.value())).transpose()
+        })
+    }
+
+    fn list_issues(&self, query: IssueQuery<'_>) -> Result<Page<IssueRecord>, String> {
+        let page_size = query.page_size();
+        let before = query.before.map_or(u64::MAX, |id| id.max(0) as u64);
+        let items = self.read("failed to list issues", |tx| {
+            let table = tx
+                .open_table(ISSUES)
+                .map_err(fail("failed to open issues"))?;
+            let rows = table
+                .range(..before)
+                .map_err(fail("failed to list issues"))?;
+            let mut items = Vec::new();
+            for row in rows.rev() {
+                let (_, value) = row.map_err(fail("failed to parse issue row"))?;
+                let issue: IssueRecord = decode(value.value())?;
+                if query.matches(&issue) {
+                    items.push(issue);
+                    if items.len() == page_size {
+                        break;
+                    }
+                }
+            }
+            Ok(items)
+        })?;
+        Ok(Page::of(items, page_size, |issue| issue.id))
+    }
+
+    fn update_issue(
+        &self,
+        id: i64,
+        update: IssueUpdate<'_>,
+    ) -> Result<Option<IssueRecord>, String> {
+        if id < 1 {
+            return Ok(None);
+        }
+        self.write("failed to update issue", |tx| {
+            let mut table = tx
+                .open_table(ISSUES)
+                .map_err(fail("failed to open issues"))?;
+            let existing = table
+                .get(id as u64)
+                .map_err(fail("failed to read issue"))?
+                .map(|value| decode::<IssueRecord>(value.value()))
+                .transpose()?;
+            let Some(mut issue) = existing else {
+                return Ok(None);
+            };
+            update.apply(&mut issue);
+            issue.updated_at = now_timestamp();
+            table
+                .insert(id as u64, encode(&issue)?.as_str())
+                .map_err(fail("failed to update issue"))?;
+            Ok(Some(issue))
+        })
+    }
+
+    fn create_check(&self, check: NewCheck<'_>) -> Result<i64, String> {
+        self.write("failed to save check", |tx| {
+            let id = next_id(tx, "pr_checks")?;
+            let now = now_timestamp();
+            let record = CheckRecord {
+                id: id as i64,
+                pr_id: check.pr_id,
+                provider: check.provider.to_string(),
+                branch: check.branch.to_string(),
+                status: check.status.to_string(),
+                conclusion: check.conclusion.map(str::to_string),
+                run_id: check.run_id.map(str::to_string),
+                url: check.url.map(str::to_string),
+                created_at: now.clone(),
+                updated_at: now,
+            };
+            tx.open_table(CHECKS)
+                .map_err(fail("failed to open checks"))?
+                .insert(id, encode(&record)?.as_str())
+                .map_err(fail("failed to save check"))?;
+            Ok(record.id)
+        })
+    }
+
+    fn list_checks(&self, pr_id: i64) -> Result<Vec<CheckRecord>, String> {
+        self.read("failed to list checks", |tx| {
+            let table = tx
+                .open_table(CHECKS)
+                .map_err(fail("failed to open checks"))?;
+            let rows = table.iter().map_err(fail("failed to list checks"))?;
+            let mut items = Vec::new();
+            for row in rows.rev() {
+                let (_, value) = row.map_err(fail("failed to parse check row"))?;
+                let check: CheckRecord = decode(value.value())?;
+                if check.pr_id == pr_id {
+                    items.push(check);
+                }
+            }
+            Ok(items)
+        })
+    }
+
+    fn update_check(
+        &self,
+        id: i64,
+        update: CheckUpdate<'_>,
+    ) -> Result<Option<CheckRecord>, String> {
+        if id < 1 {
+            return Ok(None);
+        }
+        self.write("failed to update check", |tx| {
+            let mut table = tx
+                .open_table(CHECKS)
+                .map_err(fail("failed to open checks"))?;
+            let existing = table
+                .get(id as u64)
+                .map_err(fail("failed to read check"))?
+                .map(|value| decode::<CheckRecord>(value.value()))
+                .transpose()?;
+            let Some(mut check) = existing else {
+                return Ok(None);
+            };
+            update.apply(&mut check);
+            check.updated_at = now_timestamp();
+            table
+                .insert(id as u64, encode(&check)?.as_str())
+                .map_err(fail("failed to update check"))?;
+            Ok(Some(check))
+        })
+    }
+
+    fn upsert_worktree(&self, name: &str, path: &str, branch: &str) -> Result<(), String> {
+        self.write("failed to register worktree", |tx| {
+            let record = Ordered {
+                order: next_id(tx, "worktrees")?,
+                record: WorktreeRecord {
+                    name: name.to_string(),
+                    path: path.to_string(),
+                    branch: branch.to_string(),
+                    created_at: now_timestamp(),
+                },
+            };
+            tx.open_table(WORKTREES)
+                .map_err(fail("failed to open worktrees"))?
+                .insert(name, encode(&record)?.as_str())
+                .map_err(fail("failed to register worktree"))?;
+            Ok(())
+        })
+    }
+
+    fn list_worktrees(&self) -> Result<Vec<WorktreeRecord>, String> {
+        let mut rows: Vec<Ordered<WorktreeRecord>> =
+            self.read("failed to list worktrees", |tx| {
+                let table = tx
+                    .open_table(WORKTREES)
+                    .map_err(fail("failed to open worktrees"))?;
+                let rows = table.iter().map_err(fail("failed to list worktrees"))?;
+                rows.map(|row| {
+                    let (_, value) = row.map_err(fail("failed to parse worktree row"))?;
+                    decode(value.value())
+                })
+                .collect()
+            })?;
+        rows.sort_by_key(|row| std::cmp::Reverse(row.order));
+        Ok(rows.into_iter().map(|row| row.record).collect())
+    }
+
+    fn remove_worktree(&self, name: &str) -> Result<bool, String> {
+        self.write("failed to unregister worktree", |tx| {
+            let removed = tx
+                .open_table(WORKTREES)
+                .map_err(fail("failed to open worktrees"))?
+                .remove(name)
+                .map_err(fail("failed to unregister worktree"))?
+                .is_some();
+            Ok(removed)
+        })
+    }
+
+    fn list_goals(&self) -> Result<Vec<GoalRecord>, String> {
+        let mut rows: Vec<Ordered<GoalRecord>> = self.read("failed to list goals", |tx| {
+            let table = tx.open_table(GOALS).map_err(fail("failed to open goals"))?;
+            let rows = table.iter().map_err(fail("failed to list goals"))?;
+            rows.map(|row| {
+                let (_, value) = row.map_err(fail("failed to parse goal row"))?;
+                decode(value.value())
+            })
+            .collect()
+        })?;
+        rows.sort_by_key(|row| std::cmp::Reverse(row.order));
+        Ok(rows.into_iter().map(|row| row.record).collect())
+    }
+
+    fn append_goal_logs(
+        &self,
+        goal_id: &str,
+        lines: &[String],
+    ) -> Result<Vec<GoalLogLine>, String> {
+        self.write("failed to append to goal log", |tx| {
+            let mut table = tx
+                .open_table(GOAL_LOGS)
+                .map_err(fail("failed to open goal logs"))?;
+            let recorded_at = now_timestamp();
+            let mut stored = Vec::with_capacity(lines.len());
+            for line in lines {
+                let id = next_id(tx, "goal_logs")?;
+                let record = GoalLogLine {
+                    id: id as i64,
+                    goal_id: goal_id.to_string(),
+                    line: line.clone(),
+                    recorded_at: recorded_at.clone(),
+                };
+                table
+                    .insert(id, encode(&record)?.as_str())
+                    .map_err(fail("failed to append to goal log"))?;
+                stored.push(record);
+            }
+            Ok(stored)
+        })
+    }
+
+    fn goal_logs(
+        &self,
+        goal_id: &str,
+        after: i64,
+        limit: usize,
+    ) -> Result<Vec<GoalLogLine>, String> {
+        let limit = limit.clamp(1, MAX_GOAL_LOG_PAGE);
+        let start = u64::try_from(after).unwrap_or(0) + 1;
+        self.read("failed to read goal log", |tx| {
+            let table = tx
+                .open_table(GOAL_LOGS)
+                .map_err(fail("failed to open goal logs"))?;
+            let rows = table
+                .range(start..)
+                .map_err(fail("failed to read goal log"))?;
+            let mut items = Vec::new();
+            for row in rows {
+                let (_, value) = row.map_err(fail("failed to parse goal log row"))?;
+                let line: GoalLogLine = decode(value.value())?;
+                if line.goal_id == goal_id {
+                    items.push(line);
+                    if items.len() == limit {
+                        break;
+                    }
+                }
+            }
+            Ok(items)
+        })
+    }
+
+    fn add_artifact(&self, artifact: NewArtifact<'_>) -> Result<i64, String> {
+        self.write("failed to save artifact", |tx| {
+            let id = next_id(tx, "artifacts")?;
+            let record = ArtifactRecord {
+                id: id as i64,
+                hash: artifact.hash.to_string(),
+                name: artifact.name.to_string(),
+                media_type: artifact.media_type.to_string(),
+                size: artifact.size,
+                goal_id: artifact.goal_id.map(str::to_string),
+                pr_id: artifact.pr_id,
+                created_at: now_timestamp(),
+            };
+            tx.open_table(ARTIFACTS)
+                .map_err(fail("failed to open artifacts"))?
+                .insert(id, encode(&record)?.as_str())
+                .map_err(fail("failed to save artifact"))?;
+            Ok(record.id)
+        })
+    }
+
+    fn get_artifact(&self, id: i64) -> Result<Option<ArtifactRecord>, String> {
+        if id < 1 {
+            return Ok(None);
+        }
+        self.read("failed to read artifact", |tx| {
+            tx.open_table(ARTIFACTS)
+                .map_err(fail("failed to open artifacts"))?
+                .get(id as u64)
+                .map_err(fail("failed to read artifact"))?
+                .map(|value| decode(value.value()))
+                .transpose()
+        })
+    }
+
+    fn list_artifacts(
+        &self,
+        goal_id: Option<&str>,
+        pr_id: Option<i64>,
+    ) -> Result<Vec<ArtifactRecord>, String> {
+        self.read("failed to list artifacts", |tx| {
+            let table = tx
+                .open_table(ARTIFACTS)
+                .map_err(fail("failed to open artifacts"))?;
+            let rows = table.iter().map_err(fail("failed to list artifacts"))?;
+            let mut items = Vec::new();
+            for row in rows.rev() {
+                let (_, value) = row.map_err(fail("failed to parse artifact row"))?;
+                let artifact: ArtifactRecord = decode(value.value())?;
+                if goal_id.is_none_or(|goal| artifact.goal_id.as_deref() == Some(goal))
+                    && pr_id.is_none_or(|pr| artifact.pr_id == Some(pr))
+                {
+                    items.push(artifact);
+                }
+            }
+            Ok(items)
+        })
+    }
+
+    fn get_setting(&self, key: &str) -> Result<Option<serde_json::Value>, String> {
+        let record = self.read("failed to read setting", |tx| {
+            let table = tx
+                .open_table(SETTINGS)
+                .map_err(fail("failed to open settings"))?;
+            let row = table.get(key).map_err(fail("failed to read setting"))?;
+            row.map(|v| decode::<SettingRecord>(v.value())).transpose()
+        })?;
+        Ok(record.map(|record| record.value))
+    }
+
+    fn set_setting(&self, key: &str, value: &serde_json::Value) -> Result<(), String> {
+        self.write("failed to save setting", |tx| {
+            let record = SettingRecord {
+                key: key.to_string(),
+                value: value.clone(),
+                updated_at: now_timestamp(),
+            };
+            tx.open_table(SETTINGS)
+                .map_err(fail("failed to open settings"))?
+                .insert(key, encode(&record)?.as_str())
+                .map_err(fail("failed to save setting"))?;
+            Ok(())
+        })
+    }
+
+    fn list_settings(&self) -> Result<Vec<SettingRecord>, String> {
+        // Keys iterate in byte order, matching sqlite's `ORDER BY key`.
+        self.read("failed to list settings", |tx| {
+            let table = tx
+                .open_table(SETTINGS)
+                .map_err(fail("failed to open settings"))?;
+            let rows = table.iter().map_err(fail("failed to list settings"))?;
+            rows.map(|row| {
+                let (_, value) = row.map_err(fail("failed to parse setting row"))?;
+                decode(value.value())
+            })
+            .collect()
+        })
+    }
+
+    fn append_event(&self, event: &VersionedSystemEvent) -> Result<i64, String> {
+        let payload = serde_json::to_value(&event.event).map_err(fail("failed to encode event"))?;
+        let kind = payload
+            .get("type")
+            .and_then(|v| v.as_str())
+            .unwrap_or("unknown")
+            .to_string();
+
+        self.write("failed to store event", |tx| {
+            let seq = next_id(tx, "events")? as i64;
+            let stored = StoredEvent {
+                seq,
+                schema_version: event.schema_version,
+                kind,
+                event: payload,
+                recorded_at: now_timestamp(),
+            };
+            tx.open_table(EVENTS)
+                .map_err(fail("failed to open events"))?
+                .insert(seq as u64, encode(&stored)?.as_str())
+                .map_err(fail("failed to store event"))?;
+
+            match &event.event {
+                SystemEvent::GoalCreated { goal_id, task } => {
+                    upsert_goal(tx, goal_id, |goal| goal.task = task.clone())?
+                }
+                SystemEvent::GoalStatusChanged { goal_id, .. } => {
+                    let status = stored.event["status"].as_str().map(str::to_string);
+                    upsert_goal(tx, goal_id, |goal| goal.status = status)?
+                }
+                SystemEvent::GoalCancelled { .. }
+                | SystemEvent::SettingChanged { .. }
+                | SystemEvent::PrStateChanged { .. }
+                | SystemEvent::MergeConflicted { .. }
+                | SystemEvent::ProtectionOverridden { .. }
+                | SystemEvent::GoalLogAppended { .. }
+                | SystemEvent::PotentialConflict { .. }
+                | SystemEvent::RebaseProgress { .. } => {}
+            }
+            Ok(seq)
+        })
+    }
+
+    fn events_since(&self, after: i64, limit: usize) -> Result<Vec<StoredEvent>, String> {
+        let limit = limit.clamp(1, MAX_EVENTS_PAGE);
+        let start = u64::try_from(after).unwrap_or(0) + 1;
+        self.read("failed to read events", |tx| {
+            let table = tx
+                .open_table(EVENTS)
+                .map_err(fail("failed to open events"))?;
+            let rows = table
+                .range(start..)
+                .map_err(fail("failed to read events"))?;
+            rows.take(limit)
+                .map(|row| {
+                    let (_, value) = row.map_err(fail("failed to read events"))?;
+                    decode(value.value())
+                })
+                .collect()
+        })
+    }
+
+    fn events_before(&self, before: Option<i64>, limit: usize) -> Result<Vec<StoredEvent>, String> {
+        let limit = limit.clamp(1, MAX_EVENTS_PAGE);
+        let end = before.map_or(u64::MAX, |seq| u64::try_from(seq).unwrap_or(0));
+        self.read("failed to read events", |tx| {
+            let table = tx
+                .open_table(EVENTS)
+                .map_err(fail("failed to open events"))?;
+            let rows = table.range(..end).map_err(fail("failed to read events"))?;
+            rows.rev()
+                .take(limit)
+                .map(|row| {
+                    let (_, value) = row.map_err(fail("failed to read events"))?;
+                    decode(value.value())
+                })
+                .collect()
+        })
+    }
+
+    fn prune_events(
+        &self,
+        cutoff: Option<&str>,
+        keep_latest: Option<usize>,
+        dry_run: bool,
+    ) -> Result<usize, String> {
+        let doomed = self.read("failed to inspect events", |tx| {
+            let table = tx
+                .open_table(EVENTS)
+                .map_err(fail("failed to open events"))?;
+            let total = table.len().map_err(fail("failed to count events"))? as usize;
+            let over_limit = keep_latest.map_or(0, |keep| total.saturating_sub(keep));
+
+            let mut doomed = Vec::new();
+            for (index, row) in table
+                .iter()
+                .map_err(fail("failed to read events"))?
+                .enumerate()
+            {
+                let (seq, value) = row.map_err(fail("failed to read events"))?;
+                let expired = match cutoff {
+                    Some(cutoff) => {
+                        decode::<StoredEvent>(value.value())?.recorded_at.as_str() < cutoff
+                    }
+                    None => false,
+                };
+                if index < over_limit || expired {
+                    doomed.push(seq.value());
+                }
+            }
+            Ok(doomed)
+        })?;
+
+        if !dry_run && !doomed.is_empty() {
+            self.write("failed to prune events", |tx| {
+                let mut table = tx
+                    .open_table(EVENTS)
+                    .map_err(fail("failed to open events"))?;
+                for seq in &doomed {
+                    table.remove(*seq).map_err(fail("failed to prune events"))?;
+                }
+                Ok(())
+            })?;
+        }
+        Ok(doomed.len())
+    }
+
+    /// Only needed after large deletes; redb reuses freed pages otherwise.
+    fn compact(&self) -> Result<(), String> {
+        let mut db = crate::sync::write(&self.db);
+        db.compact()
+            .map(|_| ())
+            .map_err(fail("failed to compact redb store"))
+    }
+
+    fn files(&self) -> Vec<PathBuf> {
+        vec![self.path.clone()]
+    }
+}
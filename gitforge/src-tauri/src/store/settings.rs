@@ -0,0 +1,60 @@
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use super::MetaStore;
+
+/// Typed access to the `settings` key/value table. Values are stored as
+/// JSON, so reading a key with the wrong type is an error rather than a
+/// silent default.
+impl dyn MetaStore {
+    pub fn get_bool(&self, key: &str) -> Result<Option<bool>, String> {
+        self.get_typed(key, "a bool", serde_json::Value::as_bool)
+    }
+
+    pub fn get_int(&self, key: &str) -> Result<Option<i64>, String> {
+        self.get_typed(key, "an integer", serde_json::Value::as_i64)
+    }
+
+    pub fn get_string(&self, key: &str) -> Result<Option<String>, String> {
+        self.get_typed(key, "a string", |v| v.as_str().map(str::to_string))
+    }
+
+    pub fn get_json<T: DeserializeOwned>(&self, key: &str) -> Result<Option<T>, String> {
+        self.get_setting(key)?
+            .map(serde_json::from_value)
+            .transpose()
+            .map_err(|e| format!("setting '{key}' has an unexpected shape: {e}"))
+    }
+
+    pub fn set_bool(&self, key: &str, value: bool) -> Result<(), String> {
+        self.set_setting(key, &serde_json::Value::Bool(value))
+    }
+
+    pub fn set_int(&self, key: &str, value: i64) -> Result<(), String> {
+        self.set_setting(key, &serde_json::Value::from(value))
+    }
+
+    pub fn set_string(&self, key: &str, value: &str) -> Result<(), String> {
+        self.set_setting(key, &serde_json::Value::from(value))
+    }
+
+    pub fn set_json<T: Serialize>(&self, key: &str, value: &T) -> Result<(), String> {
+        let value = serde_json::to_value(value)
+            .map_err(|e| format!("failed to encode setting '{key}': {e}"))?;
+        self.set_setting(key, &value)
+    }
+
+    fn get_typed<T>(
+        &self,
+        key: &str,
+        expected: &str,
+        convert: impl FnOnce(&serde_json::Value) -> Option<T>,
+    ) -> Result<Option<T>, String> {
+        match self.get_setting(key)? {
+            None => Ok(None),
+            Some(value) => convert(&value)
+                .map(Some)
+                .ok_or_else(|| format!("setting '{key}' is not {expected} (got {value})")),
+        }
+    }
+}
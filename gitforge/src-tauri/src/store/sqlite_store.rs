@@ -0,0 +1,758 @@
+use ant_core::VersionedSystemEvent;
+use rusqlite::types::Value;
+use rusqlite::OptionalExtension;
+use std::path::PathBuf;
+
+use super::{
+    ArtifactRecord, Backend, CheckRecord, CheckUpdate, GoalLogLine, GoalRecord, IssueQuery,
+    IssueRecord, IssueUpdate, LinkedIssue, MetaStore, NewArtifact, NewCheck, NewIssue, NewPr, Page,
+    PrQuery, PrRecord, SettingRecord, StoredEvent, WorktreeRecord, MAX_GOAL_LOG_PAGE,
+};
+use crate::db::{self, Pool, PooledConnection};
+
+pub struct SqliteStore {
+    pool: Pool,
+    path: PathBuf,
+}
+
+impl SqliteStore {
+    pub fn open(repo_path: &str) -> Result<Self, String> {
+        Ok(Self {
+            pool: db::open_pool(repo_path)?,
+            path: db::db_path(repo_path),
+        })
+    }
+
+    fn conn(&self) -> Result<PooledConnection, String> {
+        self.pool
+            .get()
+            .map_err(|e| format!("db connection unavailable: {e}"))
+    }
+}
+
+fn pr_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<PrRecord> {
+    Ok(PrRecord {
+        id: row.get(0)?,
+        title: row.get(1)?,
+        from: row.get::<_, Option<String>>(2)?.unwrap_or_default(),
+        to: row.get::<_, Option<String>>(3)?.unwrap_or_default(),
+        state: row
+            .get::<_, Option<String>>(4)?
+            .unwrap_or_else(|| "open".to_string()),
+        description: row.get(5)?,
+        created_at: row.get::<_, Option<String>>(6)?.unwrap_or_default(),
+        links: json_column(row, 7)?,
+        approvals: json_column(row, 8)?,
+        reviewers: json_column(row, 9)?,
+        merged_at: row.get(10)?,
+    })
+}
+
+/// A JSON array column; NULL in rows written before it existed.
+fn json_column<T: serde::de::DeserializeOwned>(
+    row: &rusqlite::Row<'_>,
+    index: usize,
+) -> rusqlite::Result<Vec<T>> {
+    match row.get::<_, Option<String>>(index)? {
+        Some(json) => serde_json::from_str(&json).map_err(|e| {
+            rusqlite::Error::FromSqlConversionFailure(index, rusqlite::types::Type::Text, e.into())
+        }),
+        None => Ok(Vec::new()),
+    }
+}
+
+const ARTIFACT_COLUMNS: &str = "id, hash, name, media_type, size, goal_id, pr_id, created_at";
+
+fn artifact_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<ArtifactRecord> {
+    Ok(ArtifactRecord {
+        id: row.get(0)?,
+        hash: row.get(1)?,
+        name: row.get(2)?,
+        media_type: row.get(3)?,
+        size: row.get(4)?,
+        goal_id: row.get(5)?,
+        pr_id: row.get(6)?,
+        created_at: row.get::<_, Option<String>>(7)?.unwrap_or_default(),
+    })
+}
+
+const CHECK_COLUMNS: &str =
+    "id, pr_id, provider, branch, status, conclusion, run_id, url, created_at, updated_at";
+
+fn check_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<CheckRecord> {
+    Ok(CheckRecord {
+        id: row.get(0)?,
+        pr_id: row.get(1)?,
+        provider: row.get(2)?,
+        branch: row.get(3)?,
+        status: row.get(4)?,
+        conclusion: row.get(5)?,
+        run_id: row.get(6)?,
+        url: row.get(7)?,
+        created_at: row.get::<_, Option<String>>(8)?.unwrap_or_default(),
+        updated_at: row.get::<_, Option<String>>(9)?.unwrap_or_default(),
+    })
+}
+
+const ISSUE_COLUMNS: &str = "id, title, body, state, goal_id, pr_id, created_at, updated_at";
+
+fn issue_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<IssueRecord> {
+    Ok(IssueRecord {
+        id: row.get(0)?,
+        title: row.get(1)?,
+        body: row.get(2)?,
+        state: row.get(3)?,
+        goal_id: row.get(4)?,
+        pr_id: row.get(5)?,
+        created_at: row.get::<_, Option<String>>(6)?.unwrap_or_default(),
+        updated_at: row.get::<_, Option<String>>(7)?.unwrap_or_default(),
+    })
+}
+
+impl MetaStore for SqliteStore {
+    fn backend(&self) -> Backend {
+        Backend::Sqlite
+    }
+
+    fn create_pr(&self, pr: NewPr<'_>) -> Result<i64, String> {
+        let db = self.conn()?;
+        db.execute(
+            "INSERT INTO prs (title, from_branch, to_branch, description) VALUES (?1, ?2, ?3, ?4)",
+            rusqlite::params![pr.title, pr.from, pr.to, pr.description],
+        )
+        .map_err(|e| format!("failed to save PR: {e}"))?;
+        Ok(db.last_insert_rowid())
+    }
+
+    fn set_pr_state(&self, id: i64, state: &str) -> Result<bool, String> {
+        let updated = self
+            .conn()?
+            .execute(
+                "UPDATE prs SET state = ?1,
+                     merged_at = CASE WHEN ?1 = 'merged' THEN CURRENT_TIMESTAMP ELSE merged_at END
+                 WHERE id = ?2",
+                rusqlite::params![state, id],
+            )
+            .map_err(|e| format!("failed to update PR: {e}"))?;
+        Ok(updated > 0)
+    }
+
+    fn get_pr(&self, id: i64) -> Result<Option<PrRecord>, String> {
+        self.conn()?
+            .query_row(
+                "SELECT id, title, from_branch, to_branch, state, description, created_at, links,
+                        approvals, reviewers, merged_at
+                 FROM prs WHERE id = ?1",
+                [id],
+                pr_row,
+            )
+            .optional()
+            .map_err(|e| format!("failed to read PR: {e}"))
+    }
+
+    fn set_pr_links(&self, id: i64, links: &[LinkedIssue]) -> Result<bool, String> {
+        let json =
+            serde_json::to_string(links).map_err(|e| format!("failed to encode links: {e}"))?;
+        let updated = self
+            .conn()?
+            .execute(
+                "UPDATE prs SET links = ?1 WHERE id = ?2",
+                rusqlite::params![json, id],
+            )
+            .map_err(|e| format!("failed to link issues: {e}"))?;
+        Ok(updated > 0)
+    }
+
+    fn set_pr_reviewers(&self, id: i64, reviewers: &[String]) -> Result<bool, String> {
+        let json = serde_json::to_string(reviewers)
+            .map_err(|e| format!("failed to encode reviewers: {e}"))?;
+        let updated = self
+            .conn()?
+            .execute(
+                "UPDATE prs SET reviewers = ?1 WHERE id = ?2",
+                rusqlite::params![json, id],
+            )
+            .map_err(|e| format!("failed to request reviews: {e}"))?;
+        Ok(updated > 0)
+    }
+
+    fn approve_pr(&self, id: i64, reviewer: &str) -> Result<bool, String> {
+        // One statement, so concurrent reviewers can't drop each other's
+        // approvals.
+        let updated = self
+            .conn()?
+            .execute(
+                "UPDATE prs SET approvals = json_insert(
+                     coalesce(approvals, '[]'), '$[#]',
+                     json_object('reviewer', ?1, 'approved_at', datetime('now'))
+                 )
+                 WHERE id = ?2 AND NOT EXISTS (
+                     SELECT 1 FROM json_each(coalesce(approvals, '[]'))
+                     WHERE json_extract(value, '$.reviewer') = ?1
+                 )",
+                rusqlite::params![reviewer, id],
+            )
+            .map_err(|e| format!("failed to approve PR: {e}"))?;
+        if updated > 0 {
+            return Ok(true);
+        }
+        let exists: bool = self
+            .conn()?
+            .query_row(
+                "SELECT EXISTS(SELECT 1 FROM prs WHERE id = ?1)",
+                [id],
+                |row| row.get(0),
+            )
+            .map_err(|e| format!("failed to read PR: {e}"))?;
+        Ok(exists)
+    }
+
+    fn list_prs(&self, query: PrQuery<'_>) -> Result<Page<PrRecord>, String> {
+        let db = self.conn()?;
+        let page_size = query.page_size();
+        let before = query.before.unwrap_or(i64::MAX);
+
+        // With a state filter, walk prs_state_created from the cursor's
+        // (created_at, id); otherwise the rowid alone orders the listing.
+        let (sql, params) = match query.state {
+            Some(state) => (
+                "SELECT id, title, from_branch, to_branch, state, description, created_at, links,
+                        approvals, reviewers, merged_at
+                 FROM prs
+                 WHERE state = ?1
+                   AND (created_at, id) < (
+                       SELECT coalesce(max(created_at), '9999'), ?2 FROM prs WHERE id = ?2
+                   )
+                 ORDER BY created_at DESC, id DESC LIMIT ?3",
+                vec![
+                    Value::from(state.to_string()),
+                    Value::from(before),
+                    Value::from(page_size as i64),
+                ],
+            ),
+            None => (
+                "SELECT id, title, from_branch, to_branch, state, description, created_at, links,
+                        approvals, reviewers, merged_at
+                 FROM prs WHERE id < ?1 ORDER BY id DESC LIMIT ?2",
+                vec![Value::from(before), Value::from(page_size as i64)],
+            ),
+        };
+
+        let mut stmt = db
+            .prepare_cached(sql)
+            .map_err(|e| format!("failed to prepare query: {e}"))?;
+        let rows = stmt
+            .query_map(rusqlite::params_from_iter(params), pr_row)
+            .map_err(|e| format!("failed to list PRs: {e}"))?;
+
+        let items = rows
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("failed to parse PR row: {e}"))?;
+        Ok(Page::of(items, page_size, |pr| pr.id))
+    }
+
+    fn create_issue(&self, issue: NewIssue<'_>) -> Result<i64, String> {
+        let db = self.conn()?;
+        db.execute(
+            "INSERT INTO issues (title, body, goal_id, pr_id) VALUES (?1, ?2, ?3, ?4)",
+            rusqlite::params![issue.title, issue.body, issue.goal_id, issue.pr_id],
+        )
+        .map_err(|e| format!("failed to save issue: {e}"))?;
+        Ok(db.last_insert_rowid())
+    }
+
+    fn get_issue(&self, id: i64) -> Result<Option<IssueRecord>, String> {
+        self.conn()?
+            .query_row(
+                &format!("SELECT {ISSUE_COLUMNS} FROM issues WHERE id = ?1"),
+                [id],
+                issue_row,
+            )
+            .optional()
+            .map_err(|e| format!("failed to read issue: {e}"))
+    }
+
+    fn list_issues(&self, query: IssueQuery<'_>) -> Result<Page<IssueRecord>, String> {
+        let db = self.conn()?;
+        let page_size = query.page_size();
+
+        // Rowid tables carry the id in every index, so `issues_state` serves
+        // both the filter and the newest-first order.
+        let mut filter = String::from("id < ?1");
+        let mut params = vec![Value::from(query.before.unwrap_or(i64::MAX))];
+        let mut and = |clause: &str, value: Value| {
+            params.push(value);
+            filter.push_str(&format!(" AND {clause} = ?{}", params.len()));
+        };
+        if let Some(state) = query.state {
+            and("state", Value::from(state.to_string()));
+        }
+        if let Some(goal_id) = query.goal_id {
+            and("goal_id", Value::from(goal_id.to_string()));
+        }
+        if let Some(pr_id) = query.pr_id {
+            and("pr_id", Value::from(pr_id));
+        }
+        params.push(Value::from(page_size as i64));
+        let sql = format!(
+            "SELECT {ISSUE_COLUMNS} FROM issues WHERE {filter} ORDER BY id DESC LIMIT ?{}",
+            params.len()
+        );
+
+        let mut stmt = db
+            .prepare_cached(&sql)
+            .map_err(|e| format!("failed to prepare query: {e}"))?;
+        let rows = stmt
+            .query_map(rusqlite::params_from_iter(params), issue_row)
+            .map_err(|e| format!("failed to list issues: {e}"))?;
+        let items = rows
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("failed to parse issue row: {e}"))?;
+        Ok(Page::of(items, page_size, |issue| issue.id))
+    }
+
+    fn update_issue(
+        &self,
+        id: i64,
+        update: IssueUpdate<'_>,
+    ) -> Result<Option<IssueRecord>, String> {
+        let updated = self
+            .conn()?
+            .execute(
+                "UPDATE issues SET title = coalesce(?1, title), body = coalesce(?2, body),
+                     state = coalesce(?3, state), goal_id = coalesce(?4, goal_id),
+                     pr_id = coalesce(?5, pr_id), updated_at = CURRENT_TIMESTAMP
+                 WHERE id = ?6",
+                rusqlite::params![
+                    update.title,
+                    update.body,
+                    update.state,
+                    update.goal_id,
+                    update.pr_id,
+                    id
+                ],
+            )
+            .map_err(|e| format!("failed to update issue: {e}"))?;
+        if updated == 0 {
+            return Ok(None);
+        }
+        self.get_issue(id)
+    }
+
+    fn create_check(&self, check: NewCheck<'_>) -> Result<i64, String> {
+        let db = self.conn()?;
+        db.execute(
+            "INSERT INTO pr_checks (pr_id, provider, branch, status, conclusion, run_id, url)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            rusqlite::params![
+                check.pr_id,
+                check.provider,
+                check.branch,
+                check.status,
+                check.conclusion,
+                check.run_id,
+                check.url
+            ],
+        )
+        .map_err(|e| format!("failed to save check: {e}"))?;
+        Ok(db.last_insert_rowid())
+    }
+
+    fn list_checks(&self, pr_id: i64) -> Result<Vec<CheckRecord>, String> {
+        let db = self.conn()?;
+        let mut stmt = db
+            .prepare_cached(&format!(
+                "SELECT {CHECK_COLUMNS} FROM pr_checks WHERE pr_id = ?1 ORDER BY id DESC"
+            ))
+            .map_err(|e| format!("failed to prepare query: {e}"))?;
+        let rows = stmt
+            .query_map([pr_id], check_row)
+            .map_err(|e| format!("failed to list checks: {e}"))?;
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("failed to parse check row: {e}"))
+    }
+
+    fn update_check(
+        &self,
+        id: i64,
+        update: CheckUpdate<'_>,
+    ) -> Result<Option<CheckRecord>, String> {
+        let db = self.conn()?;
+        let updated = db
+            .execute(
+                "UPDATE pr_checks SET status = coalesce(?1, status),
+                     conclusion = coalesce(?2, conclusion), run_id = coalesce(?3, run_id),
+                     url = coalesce(?4, url), updated_at = CURRENT_TIMESTAMP
+                 WHERE id = ?5",
+                rusqlite::params![
+                    update.status,
+                    update.conclusion,
+                    update.run_id,
+                    update.url,
+                    id
+                ],
+            )
+            .map_err(|e| format!("failed to update check: {e}"))?;
+        if updated == 0 {
+            return Ok(None);
+        }
+        db.query_row(
+            &format!("SELECT {CHECK_COLUMNS} FROM pr_checks WHERE id = ?1"),
+            [id],
+            check_row,
+        )
+        .optional()
+        .map_err(|e| format!("failed to read check: {e}"))
+    }
+
+    fn upsert_worktree(&self, name: &str, path: &str, branch: &str) -> Result<(), String> {
+        self.conn()?
+            .execute(
+                "INSERT OR REPLACE INTO worktrees (name, path, branch) VALUES (?1, ?2, ?3)",
+                rusqlite::params![name, path, branch],
+            )
+            .map(|_| ())
+            .map_err(|e| format!("failed to register worktree: {e}"))
+    }
+
+    fn list_worktrees(&self) -> Result<Vec<WorktreeRecord>, String> {
+        let db = self.conn()?;
+        let mut stmt = db
+            .prepare("SELECT name, path, branch, created_at FROM worktrees ORDER BY id DESC")
+            .map_err(|e| format!("failed to prepare query: {e}"))?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok(WorktreeRecord {
+                    name: row.get(0)?,
+                    path: row.get::<_, Option<String>>(1)?.unwrap_or_default(),
+                    branch: row.get::<_, Option<String>>(2)?.unwrap_or_default(),
+                    created_at: row.get::<_, Option<String>>(3)?.unwrap_or_default(),
+                })
+            })
+            .map_err(|e| format!("failed to list worktrees: {e}"))?;
+
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("failed to parse worktree row: {e}"))
+    }
+
+    fn remove_worktree(&self, name: &str) -> Result<bool, String> {
+        self.conn()?
+            .execute("DELETE FROM worktrees WHERE name = ?1", [name])
+            .map(|removed| removed > 0)
+            .map_err(|e| format!("failed to unregister worktree '{name}': {e}"))
+    }
+
+    fn list_goals(&self) -> Result<Vec<GoalRecord>, String> {
+        let db = self.conn()?;
+        let mut stmt = db
+            .prepare("SELECT goal_id, task, status, updated_at FROM goals ORDER BY id DESC")
+            .map_err(|e| format!("failed to prepare query: {e}"))?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok(GoalRecord {
+                    goal_id: row.get(0)?,
+                    task: row.get(1)?,
+                    status: row.get(2)?,
+                    updated_at: row.get::<_, Option<String>>(3)?.unwrap_or_default(),
+                })
+            })
+            .map_err(|e| format!("failed to list goals: {e}"))?;
+
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("failed to parse goal row: {e}"))
+    }
+
+    fn append_goal_logs(
+        &self,
+        goal_id: &str,
+        lines: &[String],
+    ) -> Result<Vec<GoalLogLine>, String> {
+        let mut db = self.conn()?;
+        let fail = |e: rusqlite::Error| format!("failed to append to goal log: {e}");
+        let tx = db.transaction().map_err(fail)?;
+        let mut stored = Vec::with_capacity(lines.len());
+        {
+            let mut stmt = tx
+                .prepare_cached(
+                    "INSERT INTO goal_logs (goal_id, line) VALUES (?1, ?2)
+                     RETURNING id, recorded_at",
+                )
+                .map_err(fail)?;
+            for line in lines {
+                let (id, recorded_at) = stmt
+                    .query_row(rusqlite::params![goal_id, line], |row| {
+                        Ok((row.get(0)?, row.get(1)?))
+                    })
+                    .map_err(fail)?;
+                stored.push(GoalLogLine {
+                    id,
+                    goal_id: goal_id.to_string(),
+                    line: line.clone(),
+                    recorded_at,
+                });
+            }
+        }
+        tx.commit().map_err(fail)?;
+        Ok(stored)
+    }
+
+    fn goal_logs(
+        &self,
+        goal_id: &str,
+        after: i64,
+        limit: usize,
+    ) -> Result<Vec<GoalLogLine>, String> {
+        let limit = limit.clamp(1, MAX_GOAL_LOG_PAGE) as i64;
+        let db = self.conn()?;
+        let mut stmt = db
+            .prepare_cached(
+                "SELECT id, goal_id, line, recorded_at FROM goal_logs
+                 WHERE goal_id = ?1 AND id > ?2 ORDER BY id LIMIT ?3",
+            )
+            .map_err(|e| format!("failed to prepare query: {e}"))?;
+        let rows = stmt
+            .query_map(rusqlite::params![goal_id, after, limit], |row| {
+                Ok(GoalLogLine {
+                    id: row.get(0)?,
+                    goal_id: row.get(1)?,
+                    line: row.get(2)?,
+                    recorded_at: row.get::<_, Option<String>>(3)?.unwrap_or_default(),
+                })
+            })
+            .map_err(|e| format!("failed to read goal log: {e}"))?;
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("failed to parse goal log row: {e}"))
+    }
+
+    fn add_artifact(&self, artifact: NewArtifact<'_>) -> Result<i64, String> {
+        let db = self.conn()?;
+        db.execute(
+            "INSERT INTO artifacts (hash, name, media_type, size, goal_id, pr_id)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            rusqlite::params![
+                artifact.hash,
+                artifact.name,
+                artifact.media_type,
+                artifact.size,
+                artifact.goal_id,
+                artifact.pr_id
+            ],
+        )
+        .map_err(|e| format!("failed to save artifact: {e}"))?;
+        Ok(db.last_insert_rowid())
+    }
+
+    fn get_artifact(&self, id: i64) -> Result<Option<ArtifactRecord>, String> {
+        let db = self.conn()?;
+        db.query_row(
+            &format!("SELECT {ARTIFACT_COLUMNS} FROM artifacts WHERE id = ?1"),
+            [id],
+            artifact_row,
+        )
+        .optional()
+        .map_err(|e| format!("failed to read artifact: {e}"))
+    }
+
+    fn list_artifacts(
+        &self,
+        goal_id: Option<&str>,
+        pr_id: Option<i64>,
+    ) -> Result<Vec<ArtifactRecord>, String> {
+        let db = self.conn()?;
+        let mut stmt = db
+            .prepare_cached(&format!(
+                "SELECT {ARTIFACT_COLUMNS} FROM artifacts
+                 WHERE (?1 IS NULL OR goal_id = ?1) AND (?2 IS NULL OR pr_id = ?2)
+                 ORDER BY id DESC"
+            ))
+            .map_err(|e| format!("failed to prepare query: {e}"))?;
+        let rows = stmt
+            .query_map(rusqlite::params![goal_id, pr_id], artifact_row)
+            .map_err(|e| format!("failed to list artifacts: {e}"))?;
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("failed to parse artifact row: {e}"))
+    }
+
+    fn get_setting(&self, key: &str) -> Result<Option<serde_json::Value>, String> {
+        let db = self.conn()?;
+        let value: Option<String> = db
+            .query_row("SELECT value FROM settings WHERE key = ?1", [key], |row| {
+                row.get(0)
+            })
+            .optional()
+            .map_err(|e| format!("failed to read setting: {e}"))?;
+        value
+            .map(|json| serde_json::from_str(&json))
+            .transpose()
+            .map_err(|e| format!("failed to decode setting '{key}': {e}"))
+    }
+
+    fn set_setting(&self, key: &str, value: &serde_json::Value) -> Result<(), String> {
+        self.conn()?
+            .execute(
+                "INSERT INTO settings (key, value) VALUES (?1, ?2)
+                 ON CONFLICT(key) DO UPDATE SET value = excluded.value,
+                     updated_at = CURRENT_TIMESTAMP",
+                rusqlite::params![key, value.to_string()],
+            )
+            .map_err(|e| format!("failed to save setting: {e}"))?;
+        Ok(())
+    }
+
+    fn list_settings(&self) -> Result<Vec<SettingRecord>, String> {
+        let db = self.conn()?;
+        let mut stmt = db
+            .prepare("SELECT key, value, updated_at FROM settings ORDER BY key")
+            .map_err(|e| format!("failed to prepare query: {e}"))?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, Option<String>>(2)?.unwrap_or_default(),
+                ))
+            })
+            .map_err(|e| format!("failed to list settings: {e}"))?;
+
+        rows.map(|row| {
+            let (key, value, updated_at) =
+                row.map_err(|e| format!("failed to parse setting row: {e}"))?;
+            let value = serde_json::from_str(&value)
+                .map_err(|e| format!("failed to decode setting '{key}': {e}"))?;
+            Ok(SettingRecord {
+                key,
+                value,
+                updated_at,
+            })
+        })
+        .collect()
+    }
+
+    fn append_event(&self, event: &VersionedSystemEvent) -> Result<i64, String> {
+        db::events::append(&*self.conn()?, event)
+    }
+
+    fn events_since(&self, after: i64, limit: usize) -> Result<Vec<StoredEvent>, String> {
+        db::events::events_since(&*self.conn()?, after, limit)
+    }
+
+    fn events_before(&self, before: Option<i64>, limit: usize) -> Result<Vec<StoredEvent>, String> {
+        db::events::events_before(&*self.conn()?, before, limit)
+    }
+
+    fn prune_events(
+        &self,
+        cutoff: Option<&str>,
+        keep_latest: Option<usize>,
+        dry_run: bool,
+    ) -> Result<usize, String> {
+        let db = self.conn()?;
+        // Everything at or below this seq is outside the newest `keep_latest`.
+        let oldest_kept: Option<i64> = match keep_latest {
+            Some(keep) => db
+                .query_row(
+                    "SELECT seq FROM events ORDER BY seq DESC LIMIT 1 OFFSET ?1",
+                    [keep as i64],
+                    |row| row.get(0),
+                )
+                .map(Some)
+                .or_else(|e| match e {
+                    rusqlite::Error::QueryReturnedNoRows => Ok(None),
+                    e => Err(e),
+                })
+                .map_err(|e| format!("failed to inspect events: {e}"))?,
+            None => None,
+        };
+
+        let filter = "WHERE recorded_at < ?1 OR seq <= ?2";
+        let params = rusqlite::params![cutoff, oldest_kept];
+        if dry_run {
+            return db
+                .query_row(
+                    &format!("SELECT COUNT(*) FROM events {filter}"),
+                    params,
+                    |row| row.get::<_, i64>(0),
+                )
+                .map(|count| count as usize)
+                .map_err(|e| format!("failed to count events: {e}"));
+        }
+        db.execute(&format!("DELETE FROM events {filter}"), params)
+            .map_err(|e| format!("failed to prune events: {e}"))
+    }
+
+    /// `optimize` refreshes planner statistics; the checkpoint folds the
+    /// vacuumed pages back into the main file so it actually shrinks.
+    fn compact(&self) -> Result<(), String> {
+        self.conn()?
+            .execute_batch("PRAGMA optimize; VACUUM; PRAGMA wal_checkpoint(TRUNCATE);")
+            .map_err(|e| format!("failed to compact database: {e}"))
+    }
+
+    fn files(&self) -> Vec<PathBuf> {
+        ["", "-wal", "-shm"]
+            .iter()
+            .map(|suffix| {
+                let mut name = self.path.clone().into_os_string();
+                name.push(suffix);
+                PathBuf::from(name)
+            })
+            .collect()
+    }
+
+    fn sqlite_pool(&self) -> Option<&Pool> {
+        Some(&self.pool)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn plan(db: &rusqlite::Connection, sql: &str) -> String {
+        let mut stmt = db
+            .prepare(&format!("EXPLAIN QUERY PLAN {sql}"))
+            .expect("explain");
+        let rows = stmt
+            .query_map([], |row| row.get::<_, String>(3))
+            .expect("plan rows");
+        rows.collect::<Result<Vec<_>, _>>()
+            .expect("plan")
+            .join("\n")
+    }
+
+    #[test]
+    fn list_queries_use_indices_without_sorting() {
+        let db = rusqlite::Connection::open_in_memory().expect("open memory db");
+        db.execute_batch(db::SCHEMA).expect("schema");
+        db.execute_batch(db::INDEXES).expect("indices");
+
+        let by_state = plan(
+            &db,
+            "SELECT id FROM prs WHERE state = 'open'
+               AND (created_at, id) < ('9999', 10)
+             ORDER BY created_at DESC, id DESC LIMIT 5",
+        );
+        assert!(by_state.contains("prs_state_created"), "{by_state}");
+        assert!(!by_state.contains("TEMP B-TREE"), "{by_state}");
+
+        let all = plan(&db, "SELECT id FROM prs WHERE id < 10 ORDER BY id DESC");
+        assert!(!all.contains("TEMP B-TREE"), "{all}");
+
+        let comments = plan(&db, "SELECT id FROM pr_comments WHERE pr_id = 1");
+        assert!(comments.contains("pr_comments_pr"), "{comments}");
+
+        let issues = plan(
+            &db,
+            "SELECT id FROM issues WHERE id < 10 AND state = 'open' ORDER BY id DESC LIMIT 5",
+        );
+        assert!(issues.contains("issues_state"), "{issues}");
+        assert!(!issues.contains("TEMP B-TREE"), "{issues}");
+
+        let logs = plan(
+            &db,
+            "SELECT id FROM goal_logs WHERE goal_id = 'G-1' AND id > 0 ORDER BY id LIMIT 5",
+        );
+        assert!(logs.contains("goal_logs_goal"), "{logs}");
+        assert!(!logs.contains("TEMP B-TREE"), "{logs}");
+    }
+}
@@ -0,0 +1,236 @@
+use ant_core::AntEngine;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast;
+
+use crate::mcp::registry::registry_key;
+use crate::watcher::service::WatchService;
+use crate::watcher::StatusTracker;
+
+/// Frontend event names; payloads are `{"repo": <path>, "event": <event>}`.
+pub const SYSTEM_EVENT: &str = "system_event";
+/// A debounced [`RepoChanged`](crate::watcher::service::RepoChanged) batch.
+pub const REPO_CHANGED: &str = "repo_changed";
+/// Per-file status differences, following the `repo_changed` that caused them.
+pub const REPO_EVENT: &str = "repo_event";
+
+/// Delivers `(event name, payload)` to the UI, e.g. `Emitter::emit` on the app handle.
+pub type Sink = Arc<dyn Fn(&str, serde_json::Value) + Send + Sync>;
+
+/// Forwards one repository's bus and watcher events to a [`Sink`] until
+/// dropped.
+pub struct EventStream {
+    bus: tokio::task::JoinHandle<()>,
+    _watch: WatchService,
+}
+
+impl Drop for EventStream {
+    fn drop(&mut self) {
+        self.bus.abort();
+    }
+}
+
+/// Starts forwarding; must be called inside a tokio runtime.
+pub fn start(repo_path: &str, engine: &AntEngine, sink: Sink) -> Result<EventStream, String> {
+    let watch = WatchService::start(repo_path)?;
+    let status_repo =
+        git2::Repository::open(repo_path).map_err(|e| format!("failed to open repository: {e}"))?;
+
+    let bus = {
+        let mut rx = engine.subscribe_events();
+        let repo = repo_path.to_string();
+        let sink = Arc::clone(&sink);
+        tokio::spawn(async move {
+            loop {
+                match rx.recv().await {
+                    Ok(event) => sink(
+                        SYSTEM_EVENT,
+                        serde_json::json!({ "repo": repo, "event": event }),
+                    ),
+                    Err(broadcast::error::RecvError::Lagged(missed)) => {
+                        eprintln!("event stream for {repo} lagged, {missed} events dropped");
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        })
+    };
+
+    {
+        let repo = repo_path.to_string();
+        let rx = watch.subscribe();
+        let engine = engine.clone();
+        std::thread::spawn(move || forward_repo_events(&repo, status_repo, rx, &engine, &sink));
+    }
+
+    Ok(EventStream { bus, _watch: watch })
+}
+
+/// Ends when the [`WatchService`] is dropped and its channel closes. Also
+/// publishes `merge_conflicted` on the bus when the index starts holding
+/// conflicts, whoever ran the merge.
+fn forward_repo_events(
+    repo: &str,
+    status_repo: git2::Repository,
+    mut rx: broadcast::Receiver<crate::watcher::service::RepoChanged>,
+    engine: &AntEngine,
+    sink: &Sink,
+) {
+    let mut tracker = StatusTracker::default();
+    if let Err(e) = tracker.refresh(&status_repo) {
+        eprintln!("event stream for {repo}: {e}");
+    }
+    let mut conflicted = has_conflicts(&status_repo);
+
+    loop {
+        let changed = match rx.blocking_recv() {
+            Ok(changed) => changed,
+            Err(broadcast::error::RecvError::Lagged(missed)) => {
+                eprintln!("event stream for {repo} lagged, {missed} batches dropped");
+                continue;
+            }
+            Err(broadcast::error::RecvError::Closed) => return,
+        };
+        sink(
+            REPO_CHANGED,
+            serde_json::json!({ "repo": repo, "event": changed }),
+        );
+        if changed.index {
+            let now = has_conflicts(&status_repo);
+            if now && !conflicted {
+                announce_conflicts(&status_repo, engine);
+            }
+            conflicted = now;
+        }
+        if !changed.touches_status() {
+            continue;
+        }
+        match tracker.refresh(&status_repo) {
+            Ok(changes) => {
+                for event in changes {
+                    sink(
+                        REPO_EVENT,
+                        serde_json::json!({ "repo": repo, "event": event }),
+                    );
+                }
+            }
+            Err(e) => eprintln!("event stream for {repo}: {e}"),
+        }
+    }
+}
+
+/// Rereads the index first; the repository handle caches it.
+fn has_conflicts(repo: &git2::Repository) -> bool {
+    repo.index()
+        .is_ok_and(|mut index| index.read(false).is_ok() && index.has_conflicts())
+}
+
+fn announce_conflicts(repo: &git2::Repository, engine: &AntEngine) {
+    let Ok(files) = crate::conflicts::conflicts_list(repo) else {
+        return;
+    };
+    let branch = repo
+        .head()
+        .ok()
+        .and_then(|h| h.shorthand().map(str::to_string))
+        .unwrap_or_else(|| "HEAD".to_string());
+    engine.merge_conflicted(branch, files.into_iter().map(|f| f.path).collect());
+}
+
+/// Active streams per repository, held in Tauri managed state.
+#[derive(Default)]
+pub struct EventStreams {
+    streams: Mutex<HashMap<PathBuf, EventStream>>,
+}
+
+impl EventStreams {
+    /// Starts streaming `repo_path` unless it already is; returns whether a
+    /// new stream was started.
+    pub fn subscribe(
+        &self,
+        repo_path: &str,
+        engine: &AntEngine,
+        sink: Sink,
+    ) -> Result<bool, String> {
+        let mut streams = crate::sync::lock(&self.streams);
+        let key = registry_key(repo_path);
+        if streams.contains_key(&key) {
+            return Ok(false);
+        }
+        streams.insert(key, start(repo_path, engine, sink)?);
+        Ok(true)
+    }
+
+    pub fn unsubscribe(&self, repo_path: &str) -> Result<bool, String> {
+        let mut streams = crate::sync::lock(&self.streams);
+        Ok(streams.remove(&registry_key(repo_path)).is_some())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+    use std::time::{Duration, Instant};
+
+    fn temp_repo(label: &str) -> crate::test_support::TempDir {
+        let dir = crate::test_support::temp_path(&format!("stream-{label}"));
+        git2::Repository::init(&dir).expect("init repo");
+        dir
+    }
+
+    fn channel_sink() -> (Sink, mpsc::Receiver<(String, serde_json::Value)>) {
+        let (tx, rx) = mpsc::channel();
+        let tx = Mutex::new(tx);
+        let sink: Sink = Arc::new(move |name, payload| {
+            let _ = tx
+                .lock()
+                .expect("sink lock")
+                .send((name.to_string(), payload));
+        });
+        (sink, rx)
+    }
+
+    fn wait_for(rx: &mpsc::Receiver<(String, serde_json::Value)>, name: &str) -> serde_json::Value {
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while let Ok((got, payload)) =
+            rx.recv_timeout(deadline.saturating_duration_since(Instant::now()))
+        {
+            if got == name {
+                return payload;
+            }
+        }
+        panic!("no {name} event within 10s");
+    }
+
+    #[tokio::test]
+    async fn bus_and_watcher_events_reach_the_sink() {
+        let dir = temp_repo("forward");
+        let repo = dir.to_string_lossy().to_string();
+        let engine = AntEngine::new();
+        let streams = EventStreams::default();
+        let (sink, rx) = channel_sink();
+
+        assert!(streams
+            .subscribe(&repo, &engine, Arc::clone(&sink))
+            .expect("subscribe"));
+        assert!(!streams
+            .subscribe(&repo, &engine, sink)
+            .expect("resubscribe"));
+
+        engine.setting_changed("ui.theme", serde_json::json!("dark"));
+        let payload = tokio::task::spawn_blocking(move || {
+            let system = wait_for(&rx, SYSTEM_EVENT);
+            std::fs::write(dir.join("notes.txt"), "hello").expect("write file");
+            (system, wait_for(&rx, REPO_CHANGED))
+        })
+        .await
+        .expect("receiver thread");
+
+        assert_eq!(payload.0["event"]["event"]["type"], "setting_changed");
+        assert_eq!(payload.1["repo"], repo);
+        assert!(payload.1["event"]["generation"].as_u64() >= Some(1));
+        assert!(streams.unsubscribe(&repo).expect("unsubscribe"));
+    }
+}
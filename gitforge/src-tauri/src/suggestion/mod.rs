@@ -0,0 +1,218 @@
+//! Patches suggested in review comments, applied to the PR branch as a
+//! commit by the reviewer who suggested them.
+
+use std::path::Path;
+
+/// Turns a comment's `suggestion` into a patch git can apply. A bare hunk
+/// (starting at `@@`) is for the comment's `path`; anything else must be a
+/// whole unified diff. Either way it has to parse and touch at least one
+/// file.
+pub fn normalize(path: Option<&str>, suggestion: &str) -> Result<String, String> {
+    let patch = if suggestion.trim_start().starts_with("@@") {
+        let path = path.ok_or("a suggested hunk needs the comment's path")?;
+        let mut patch =
+            format!("diff --git a/{path} b/{path}\n--- a/{path}\n+++ b/{path}\n{suggestion}");
+        if !patch.ends_with('\n') {
+            patch.push('\n');
+        }
+        patch
+    } else {
+        suggestion.to_string()
+    };
+    let diff = git2::Diff::from_buffer(patch.as_bytes())
+        .map_err(|e| format!("invalid suggestion: {e}"))?;
+    if diff.deltas().len() == 0 {
+        return Err("invalid suggestion: the patch changes no files".to_string());
+    }
+    Ok(patch)
+}
+
+/// `Name <email>` as a signature; a bare name gets GitForge's address.
+pub fn suggester(author: Option<&str>) -> Result<git2::Signature<'static>, String> {
+    let author = author.map(str::trim).filter(|a| !a.is_empty());
+    let (name, email) = match author.and_then(|a| a.strip_suffix('>')?.split_once('<')) {
+        Some((name, email)) => (name.trim(), email.trim()),
+        None => (author.unwrap_or("reviewer"), "gitforge@localhost"),
+    };
+    git2::Signature::now(name, email).map_err(|e| format!("invalid author '{name}': {e}"))
+}
+
+/// A suggestion applied in memory to the tip of its branch, for checking
+/// before [`commit`] records it.
+pub struct Prepared<'r> {
+    branch: String,
+    refname: String,
+    pub tip: git2::Commit<'r>,
+    pub tree: git2::Tree<'r>,
+}
+
+/// Applies `patch` to the tip of `branch` in the main repository `repo`
+/// and commits it there, authored by `author`, returning the new commit.
+pub fn apply(
+    repo: &git2::Repository,
+    branch: &str,
+    patch: &str,
+    author: &git2::Signature,
+    message: &str,
+) -> Result<git2::Oid, String> {
+    commit(repo, &prepare(repo, branch, patch)?, author, message)
+}
+
+/// Applies `patch` to the tip of `branch` without writing anything but
+/// the new tree's objects.
+pub fn prepare<'r>(
+    repo: &'r git2::Repository,
+    branch: &str,
+    patch: &str,
+) -> Result<Prepared<'r>, String> {
+    let refname = if branch.starts_with("refs/") {
+        branch.to_string()
+    } else {
+        format!("refs/heads/{branch}")
+    };
+    let tip = repo
+        .find_reference(&refname)
+        .and_then(|r| r.peel_to_commit())
+        .map_err(|e| format!("branch '{branch}' not found: {e}"))?;
+    let diff = git2::Diff::from_buffer(patch.as_bytes())
+        .map_err(|e| format!("invalid suggestion: {e}"))?;
+    let tree = tip
+        .tree()
+        .map_err(|e| format!("failed to read tree: {e}"))?;
+    let mut index = repo
+        .apply_to_tree(&tree, &diff, None)
+        .map_err(|e| format!("the suggestion no longer applies to {branch}: {e}"))?;
+    let tree = index
+        .write_tree_to(repo)
+        .and_then(|id| repo.find_tree(id))
+        .map_err(|e| format!("failed to write tree: {e}"))?;
+    if tree.id() == tip.tree_id() {
+        return Err(format!("the suggestion is already applied on {branch}"));
+    }
+    Ok(Prepared {
+        branch: branch.to_string(),
+        refname,
+        tip,
+        tree,
+    })
+}
+
+/// Commits a [`prepare`]d suggestion onto its branch. If the branch is
+/// checked out, in the main checkout or a worktree, that checkout is
+/// updated first; local edits to the files it touches refuse the
+/// suggestion.
+pub fn commit(
+    repo: &git2::Repository,
+    prepared: &Prepared,
+    author: &git2::Signature,
+    message: &str,
+) -> Result<git2::Oid, String> {
+    let Prepared {
+        branch,
+        refname,
+        tip,
+        tree,
+    } = prepared;
+    let committer = repo
+        .signature()
+        .or_else(|_| git2::Signature::now("GitForge", "gitforge@localhost"))
+        .map_err(|e| format!("failed to create signature: {e}"))?;
+
+    if let Some(checkout) = checked_out(repo, refname)? {
+        let mut options = git2::build::CheckoutBuilder::new();
+        options.safe();
+        checkout
+            .find_tree(tree.id())
+            .and_then(|tree| checkout.checkout_tree(tree.as_object(), Some(&mut options)))
+            .map_err(|e| {
+                let at = checkout.workdir().unwrap_or(Path::new("")).display();
+                format!("failed to update {branch} checked out at {at}: {e}")
+            })?;
+    }
+    repo.commit(Some(refname), author, &committer, message, tree, &[tip])
+        .map_err(|e| format!("failed to commit the suggestion: {e}"))
+}
+
+/// The checkout of main repository `repo`, or of one of its worktrees,
+/// whose HEAD is `refname`.
+fn checked_out(repo: &git2::Repository, refname: &str) -> Result<Option<git2::Repository>, String> {
+    let on = |checkout: &git2::Repository| {
+        !checkout.is_bare()
+            && checkout
+                .head()
+                .ok()
+                .is_some_and(|head| head.name() == Some(refname))
+    };
+    let main = git2::Repository::open(repo.path())
+        .map_err(|e| format!("failed to open repository: {e}"))?;
+    if on(&main) {
+        return Ok(Some(main));
+    }
+    let names = main
+        .worktrees()
+        .map_err(|e| format!("failed to list worktrees: {e}"))?;
+    for name in names.iter().flatten() {
+        let Ok(worktree) = main.find_worktree(name) else {
+            continue;
+        };
+        if let Ok(checkout) = git2::Repository::open_from_worktree(&worktree) {
+            if on(&checkout) {
+                return Ok(Some(checkout));
+            }
+        }
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn applies_suggested_hunks_as_the_suggester() {
+        let (dir, repo) = crate::test_support::init_repo("suggestion");
+        let sig = git2::Signature::now("GitForge Test", "test@gitforge.dev").expect("sig");
+        fs::write(dir.join("lib.rs"), "fn a() {}\nfn b() {}\n").expect("write");
+        let mut index = repo.index().expect("index");
+        index.add_path(Path::new("lib.rs")).expect("add");
+        index.write().expect("write index");
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        repo.commit(Some("HEAD"), &sig, &sig, "base", &tree, &[])
+            .expect("commit");
+        let base = repo.head().unwrap().peel_to_commit().unwrap();
+        repo.branch("feature", &base, false).expect("branch");
+
+        let hunk = "@@ -1,2 +1,2 @@\n fn a() {}\n-fn b() {}\n+fn b() -> u8 { 1 }\n";
+        assert!(normalize(None, hunk).is_err());
+        assert!(normalize(Some("lib.rs"), "not a patch").is_err());
+        let patch = normalize(Some("lib.rs"), hunk).expect("normalize");
+
+        let author = suggester(Some("Rita Reviewer <rita@example.com>")).expect("author");
+        assert_eq!(suggester(None).unwrap().name(), Some("reviewer"));
+        let id = apply(&repo, "feature", &patch, &author, "Apply suggestion").expect("apply");
+        let commit = repo.find_commit(id).expect("commit");
+        assert_eq!(commit.author().email(), Some("rita@example.com"));
+        assert_eq!(
+            repo.find_branch("feature", git2::BranchType::Local)
+                .unwrap()
+                .get()
+                .target(),
+            Some(id)
+        );
+        // feature isn't checked out, so the main checkout is untouched.
+        assert_eq!(
+            fs::read_to_string(dir.join("lib.rs")).unwrap(),
+            "fn a() {}\nfn b() {}\n"
+        );
+        assert!(apply(&repo, "feature", &patch, &author, "again").is_err());
+
+        let head = repo.head().unwrap().name().unwrap().to_string();
+        apply(&repo, &head, &patch, &author, "On the checkout").expect("apply to HEAD");
+        assert_eq!(
+            fs::read_to_string(dir.join("lib.rs")).unwrap(),
+            "fn a() {}\nfn b() -> u8 { 1 }\n"
+        );
+        assert!(repo.statuses(None).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,73 @@
+//! Locks that outlive a panicking holder. A panic while a `Mutex` is held
+//! poisons it, and treating that as fatal would leave a long-running
+//! server refusing every later call. Instead the guard is recovered, and
+//! state a half-finished update may have left inconsistent is repaired
+//! once before anyone else sees it.
+
+use std::sync::{Mutex, MutexGuard, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+/// Locks `mutex` whose state stays valid through any panic, e.g. a map
+/// that's only ever inserted into or removed from whole.
+pub fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    lock_or_repair(mutex, |_| {})
+}
+
+/// Locks `mutex`, first running `repair` on the state if its last holder
+/// panicked. Caches usually repair by clearing themselves.
+pub fn lock_or_repair<T>(mutex: &Mutex<T>, repair: impl FnOnce(&mut T)) -> MutexGuard<'_, T> {
+    match mutex.lock() {
+        Ok(guard) => guard,
+        Err(poisoned) => {
+            let mut guard = poisoned.into_inner();
+            repair(&mut guard);
+            mutex.clear_poison();
+            guard
+        }
+    }
+}
+
+/// `lock` for a `RwLock` reader.
+pub fn read<T>(lock: &RwLock<T>) -> RwLockReadGuard<'_, T> {
+    lock.read().unwrap_or_else(|poisoned| {
+        lock.clear_poison();
+        poisoned.into_inner()
+    })
+}
+
+/// `lock` for a `RwLock` writer.
+pub fn write<T>(lock: &RwLock<T>) -> RwLockWriteGuard<'_, T> {
+    lock.write().unwrap_or_else(|poisoned| {
+        lock.clear_poison();
+        poisoned.into_inner()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[test]
+    fn a_panicking_holder_is_repaired_once() {
+        let cache = Arc::new(Mutex::new(vec![1, 2]));
+        let holder = Arc::clone(&cache);
+        let panicked = std::thread::spawn(move || {
+            let mut entries = holder.lock().expect("lock");
+            entries.push(3);
+            panic!("half-way through an update");
+        })
+        .join();
+        assert!(panicked.is_err());
+        assert!(cache.is_poisoned());
+
+        let mut repairs = 0;
+        lock_or_repair(&cache, |entries| {
+            repairs += 1;
+            entries.clear();
+        })
+        .push(4);
+        assert!(!cache.is_poisoned());
+        assert_eq!(*lock_or_repair(&cache, |_| repairs += 1), [4]);
+        assert_eq!(repairs, 1);
+    }
+}
@@ -0,0 +1,332 @@
+//! Named build and task commands declared per repository, so agents run
+//! builds through a reviewed list instead of arbitrary shell.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::Read;
+use std::path::Path;
+use std::process::Stdio;
+use std::time::{Duration, Instant};
+
+use crate::precommit::{shell, tail};
+use crate::sandbox::{self, Sandbox};
+
+/// The task registry, relative to the repository root.
+pub const CONFIG_PATH: &str = ".gitforge/tasks.toml";
+/// Seconds a task may run when it doesn't set `timeout`.
+const DEFAULT_TIMEOUT: u64 = 600;
+/// Variables a task started with `clear_env` still gets from GitForge's
+/// environment, so the shell and common toolchains can start.
+const BASE_ENV: [&str; 6] = [
+    "PATH",
+    "HOME",
+    "USERPROFILE",
+    "SystemRoot",
+    "TMPDIR",
+    "TEMP",
+];
+
+/// One `[[task]]` table:
+///
+/// ```toml
+/// [[task]]
+/// name = "build"
+/// run = "cargo build --release"
+/// description = "Release build"
+/// timeout = 900
+/// env = { RUSTFLAGS = "-D warnings" }
+/// clear_env = true
+/// keep_env = ["CARGO_HOME"]
+/// allow_env = ["FEATURES"]
+/// dir = "crates/app"
+/// sandbox = { cpu_seconds = 600, network = false }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Task {
+    pub name: String,
+    /// Shell command, run from the working tree root.
+    pub run: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Seconds before the task is killed and fails.
+    #[serde(default)]
+    pub timeout: Option<u64>,
+    /// Variables set for the task.
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+    /// Start from an empty environment rather than GitForge's, keeping only
+    /// the basics and `keep_env`.
+    #[serde(default)]
+    pub clear_env: bool,
+    #[serde(default)]
+    pub keep_env: Vec<String>,
+    /// Variables a caller may set when running the task; any others are
+    /// refused.
+    #[serde(default)]
+    pub allow_env: Vec<String>,
+    /// Directory to run in, relative to the working tree root; it can't
+    /// lead outside the checkout.
+    #[serde(default)]
+    pub dir: Option<String>,
+    /// Limits to run under; the file's `[sandbox]` when left out.
+    #[serde(default)]
+    pub sandbox: Option<Sandbox>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Registry {
+    #[serde(default)]
+    sandbox: Option<Sandbox>,
+    #[serde(default)]
+    task: Vec<Task>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TaskRun {
+    pub task: String,
+    pub command: String,
+    pub success: bool,
+    /// `None` when the task was killed or couldn't start.
+    pub exit_code: Option<i32>,
+    pub timed_out: bool,
+    pub duration_ms: u64,
+    /// The end of each stream.
+    pub stdout: String,
+    pub stderr: String,
+}
+
+/// The tasks in `.gitforge/tasks.toml` as committed at the HEAD of the
+/// checkout at `workdir`; none without one. Uncommitted edits don't count,
+/// so only a reviewed registry can name what runs.
+pub fn load(workdir: &Path) -> Result<Vec<Task>, String> {
+    let repo = git2::Repository::open(workdir)
+        .map_err(|e| format!("failed to open {}: {e}", workdir.display()))?;
+    let Ok(tree) = repo.head().and_then(|head| head.peel_to_tree()) else {
+        return Ok(Vec::new());
+    };
+    let entry = match tree.get_path(Path::new(CONFIG_PATH)) {
+        Ok(entry) => entry,
+        Err(e) if e.code() == git2::ErrorCode::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(format!("failed to read {CONFIG_PATH}: {e}")),
+    };
+    let blob = entry
+        .to_object(&repo)
+        .and_then(|object| object.peel_to_blob())
+        .map_err(|e| format!("failed to read {CONFIG_PATH}: {e}"))?;
+    let text =
+        std::str::from_utf8(blob.content()).map_err(|e| format!("invalid {CONFIG_PATH}: {e}"))?;
+    let mut registry: Registry =
+        toml::from_str(text).map_err(|e| format!("invalid {CONFIG_PATH}: {e}"))?;
+    for (i, task) in registry.task.iter().enumerate() {
+        if task.name.trim().is_empty() || task.run.trim().is_empty() {
+            return Err(format!(
+                "invalid {CONFIG_PATH}: task {} needs a name and a run command",
+                i + 1
+            ));
+        }
+        if registry.task[..i].iter().any(|seen| seen.name == task.name) {
+            return Err(format!(
+                "invalid {CONFIG_PATH}: task '{}' is declared twice",
+                task.name
+            ));
+        }
+    }
+    for task in &mut registry.task {
+        if task.sandbox.is_none() {
+            task.sandbox.clone_from(&registry.sandbox);
+        }
+    }
+    Ok(registry.task)
+}
+
+/// Runs `task` from `workdir`, or the `dir` under it, in its sandbox and
+/// with the caller's `env`, every key of which must be in the task's
+/// `allow_env`.
+pub fn run(workdir: &Path, task: &Task, env: &BTreeMap<String, String>) -> Result<TaskRun, String> {
+    if let Some(key) = env.keys().find(|key| !task.allow_env.contains(key)) {
+        return Err(format!(
+            "task '{}' doesn't allow setting {key}; allowed: {}",
+            task.name,
+            if task.allow_env.is_empty() {
+                "none".to_string()
+            } else {
+                task.allow_env.join(", ")
+            }
+        ));
+    }
+    let started = Instant::now();
+    let timeout = Duration::from_secs(task.timeout.unwrap_or(DEFAULT_TIMEOUT));
+    let result = |exit_code: Option<i32>, timed_out: bool, stdout: &[u8], stderr: &[u8]| TaskRun {
+        task: task.name.clone(),
+        command: task.run.clone(),
+        success: exit_code == Some(0) && !timed_out,
+        exit_code,
+        timed_out,
+        duration_ms: started.elapsed().as_millis() as u64,
+        stdout: tail(stdout),
+        stderr: tail(stderr),
+    };
+
+    let not_started = |message: String| Ok(result(None, false, b"", message.as_bytes()));
+    let dir = match &task.dir {
+        Some(dir) => match sandbox::confine(workdir, dir) {
+            Ok(dir) => dir,
+            Err(e) => return not_started(format!("failed to start: {e}")),
+        },
+        None => workdir.to_path_buf(),
+    };
+    let mut command = shell(&task.run);
+    if let Some(limits) = &task.sandbox {
+        if let Err(e) = sandbox::apply(&mut command, limits) {
+            return not_started(format!("failed to start: {e}"));
+        }
+    }
+    if task.clear_env {
+        command.env_clear();
+        for key in BASE_ENV
+            .iter()
+            .copied()
+            .chain(task.keep_env.iter().map(String::as_str))
+        {
+            if let Some(value) = std::env::var_os(key) {
+                command.env(key, value);
+            }
+        }
+    }
+    let mut child = match command
+        .envs(&task.env)
+        .envs(env)
+        .current_dir(&dir)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+    {
+        Ok(child) => child,
+        Err(e) => return not_started(format!("failed to start: {e}")),
+    };
+    // Drained on threads so a chatty task can't fill a pipe and stall.
+    let drain = |pipe: Option<Box<dyn Read + Send>>| {
+        std::thread::spawn(move || {
+            let mut bytes = Vec::new();
+            if let Some(mut pipe) = pipe {
+                let _ = pipe.read_to_end(&mut bytes);
+            }
+            bytes
+        })
+    };
+    let stdout = drain(
+        child
+            .stdout
+            .take()
+            .map(|p| Box::new(p) as Box<dyn Read + Send>),
+    );
+    let stderr = drain(
+        child
+            .stderr
+            .take()
+            .map(|p| Box::new(p) as Box<dyn Read + Send>),
+    );
+
+    let (status, timed_out) = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break (Some(status), false),
+            Ok(None) if started.elapsed() >= timeout => {
+                sandbox::kill(&mut child);
+                let _ = child.wait();
+                break (None, true);
+            }
+            Ok(None) => std::thread::sleep(Duration::from_millis(20)),
+            Err(e) => {
+                sandbox::kill(&mut child);
+                return not_started(format!("failed to wait: {e}"));
+            }
+        }
+    };
+    let stdout = stdout.join().unwrap_or_default();
+    let stderr = stderr.join().unwrap_or_default();
+    Ok(result(
+        status.and_then(|s| s.code()),
+        timed_out,
+        &stdout,
+        &stderr,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn runs_declared_tasks_with_controlled_environment() {
+        let (dir, repo) = crate::test_support::init_repo("tasks");
+        assert!(load(&dir).expect("no commits").is_empty());
+        crate::test_support::commit_file(&repo, "README.md", "tasks\n", "initial");
+        assert!(load(&dir).expect("no file").is_empty());
+        crate::test_support::commit_file(
+            &repo,
+            CONFIG_PATH,
+            r#"
+[sandbox]
+cpu_seconds = 60
+
+[[task]]
+name = "env"
+run = "echo \"$MODE-${FEATURES:-none}-${GITFORGE_TEST_TASK_LEAK:-unset}\"; echo warn >&2"
+env = { MODE = "release" }
+clear_env = true
+allow_env = ["FEATURES"]
+
+[[task]]
+name = "fail"
+run = "exit 3"
+
+[[task]]
+name = "hang"
+run = "sleep 5"
+timeout = 0
+sandbox = { scrub_env = false }
+
+[[task]]
+name = "escape"
+run = "pwd"
+dir = ".."
+"#,
+            "tasks",
+        );
+        let tasks = load(&dir).expect("load");
+        assert_eq!(tasks.len(), 4);
+        assert_eq!(
+            tasks[0].sandbox.as_ref().and_then(|s| s.cpu_seconds),
+            Some(60)
+        );
+        assert_eq!(tasks[2].sandbox.as_ref().map(|s| s.cpu_seconds), Some(None));
+
+        std::env::set_var("GITFORGE_TEST_TASK_LEAK", "leaked");
+        let mut env = BTreeMap::new();
+        env.insert("FEATURES".to_string(), "simd".to_string());
+        let run_env = run(&dir, &tasks[0], &env).expect("run env");
+        assert!(run_env.success);
+        assert_eq!(run_env.stdout, "release-simd-unset\n");
+        assert_eq!(run_env.stderr, "warn\n");
+        env.insert("PATH".to_string(), "/tmp".to_string());
+        assert!(run(&dir, &tasks[0], &env).is_err_and(|e| e.contains("doesn't allow setting PATH")));
+
+        let failed = run(&dir, &tasks[1], &BTreeMap::new()).expect("run fail");
+        assert_eq!((failed.success, failed.exit_code), (false, Some(3)));
+        let hung = run(&dir, &tasks[2], &BTreeMap::new()).expect("run hang");
+        assert!(hung.timed_out && !hung.success);
+        let escaped = run(&dir, &tasks[3], &BTreeMap::new()).expect("run escape");
+        assert!(!escaped.success && escaped.stderr.contains("outside the checkout"));
+
+        let duplicate =
+            "[[task]]\nname = \"a\"\nrun = \"true\"\n[[task]]\nname = \"a\"\nrun = \"false\"\n";
+        std::fs::write(dir.join(CONFIG_PATH), duplicate).expect("write duplicate");
+        assert_eq!(load(&dir).expect("committed").len(), 4);
+        crate::test_support::commit_file(&repo, CONFIG_PATH, duplicate, "duplicate");
+        assert!(load(&dir).is_err_and(|e| e.contains("declared twice")));
+    }
+}
@@ -0,0 +1,417 @@
+//! The project's test suites, run in a checkout with their output read
+//! line by line into per-test results for `cargo test`, jest and pytest.
+
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, BufReader, Read};
+use std::path::Path;
+use std::process::Stdio;
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+use crate::precommit::{shell, tail};
+
+/// The suite definitions, relative to the working tree root.
+pub const CONFIG_PATH: &str = ".gitforge/tests.toml";
+/// Seconds a suite may run when it doesn't set `timeout`.
+const DEFAULT_TIMEOUT: u64 = 1800;
+
+/// One `[[suite]]` table:
+///
+/// ```toml
+/// [[suite]]
+/// name = "unit"
+/// run = "cargo test"
+/// format = "cargo"
+/// timeout = 600
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Suite {
+    pub name: String,
+    /// Shell command, run from the working tree root.
+    pub run: String,
+    /// How to read the output; guessed from `run` when left out.
+    #[serde(default)]
+    pub format: Option<Format>,
+    /// Seconds before the suite is killed and fails.
+    #[serde(default)]
+    pub timeout: Option<u64>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Config {
+    #[serde(default)]
+    suite: Vec<Suite>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Format {
+    /// `test name ... ok`
+    Cargo,
+    /// Verbose jest: `✓ name (3 ms)`
+    Jest,
+    /// `pytest -v`: `path::name PASSED`, or the `-r` summary lines.
+    Pytest,
+}
+
+impl Format {
+    fn guess(command: &str) -> Option<Self> {
+        if command.contains("cargo") {
+            Some(Self::Cargo)
+        } else if command.contains("pytest") {
+            Some(Self::Pytest)
+        } else if command.contains("jest") {
+            Some(Self::Jest)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+    Passed,
+    Failed,
+    Skipped,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TestCase {
+    pub name: String,
+    pub outcome: Outcome,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct Counts {
+    pub passed: usize,
+    pub failed: usize,
+    pub skipped: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TestRun {
+    pub suite: String,
+    pub command: String,
+    pub format: Option<Format>,
+    /// The command exited successfully in time; failing tests it didn't
+    /// report through its exit code don't count.
+    pub passed: bool,
+    /// `None` when the suite was killed or couldn't start.
+    pub exit_code: Option<i32>,
+    pub timed_out: bool,
+    pub duration_ms: u64,
+    pub counts: Counts,
+    /// In the order the output reported them.
+    pub tests: Vec<TestCase>,
+    /// The end of the combined output.
+    pub output: String,
+}
+
+/// The suites in `workdir`'s `.gitforge/tests.toml`; none without one.
+pub fn load(workdir: &Path) -> Result<Vec<Suite>, String> {
+    let path = workdir.join(CONFIG_PATH);
+    let text = match std::fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(format!("failed to read {CONFIG_PATH}: {e}")),
+    };
+    let config: Config =
+        toml::from_str(&text).map_err(|e| format!("invalid {CONFIG_PATH}: {e}"))?;
+    for (i, suite) in config.suite.iter().enumerate() {
+        if suite.name.trim().is_empty() || suite.run.trim().is_empty() {
+            return Err(format!(
+                "invalid {CONFIG_PATH}: suite {} needs a name and a run command",
+                i + 1
+            ));
+        }
+    }
+    Ok(config.suite)
+}
+
+/// Runs `suite` from `workdir`, handing each line of stdout and stderr to
+/// `on_line` as it arrives.
+pub fn run(workdir: &Path, suite: &Suite, on_line: &mut dyn FnMut(&str)) -> TestRun {
+    let started = Instant::now();
+    let timeout = Duration::from_secs(suite.timeout.unwrap_or(DEFAULT_TIMEOUT));
+    let format = suite.format.or_else(|| Format::guess(&suite.run));
+    let mut output = Vec::new();
+    let mut tests: Vec<TestCase> = Vec::new();
+    let finish = |output: &[u8], tests: Vec<TestCase>, exit_code, timed_out, passed| {
+        let mut counts = Counts::default();
+        for test in &tests {
+            match test.outcome {
+                Outcome::Passed => counts.passed += 1,
+                Outcome::Failed => counts.failed += 1,
+                Outcome::Skipped => counts.skipped += 1,
+            }
+        }
+        TestRun {
+            suite: suite.name.clone(),
+            command: suite.run.clone(),
+            format,
+            passed,
+            exit_code,
+            timed_out,
+            duration_ms: started.elapsed().as_millis() as u64,
+            counts,
+            tests,
+            output: tail(output),
+        }
+    };
+
+    let mut child = match shell(&suite.run)
+        .current_dir(workdir)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+    {
+        Ok(child) => child,
+        Err(e) => {
+            return finish(
+                format!("failed to start: {e}").as_bytes(),
+                tests,
+                None,
+                false,
+                false,
+            )
+        }
+    };
+    let (tx, rx) = mpsc::channel::<String>();
+    // One reader per pipe, so a chatty stream can't fill its pipe and stall.
+    let read = |pipe: Option<Box<dyn Read + Send>>, tx: mpsc::Sender<String>| {
+        std::thread::spawn(move || {
+            let Some(pipe) = pipe else { return };
+            for line in BufReader::new(pipe).split(b'\n') {
+                let Ok(line) = line else { return };
+                if tx
+                    .send(String::from_utf8_lossy(&line).into_owned())
+                    .is_err()
+                {
+                    return;
+                }
+            }
+        })
+    };
+    read(
+        child
+            .stdout
+            .take()
+            .map(|p| Box::new(p) as Box<dyn Read + Send>),
+        tx.clone(),
+    );
+    read(
+        child
+            .stderr
+            .take()
+            .map(|p| Box::new(p) as Box<dyn Read + Send>),
+        tx,
+    );
+
+    let mut timed_out = false;
+    loop {
+        let left = timeout.saturating_sub(started.elapsed());
+        match rx.recv_timeout(left.min(Duration::from_millis(200))) {
+            Ok(line) => {
+                let line = line.trim_end_matches('\r');
+                on_line(line);
+                output.extend_from_slice(line.as_bytes());
+                output.push(b'\n');
+                if let Some(test) = format.and_then(|format| parse(format, line)) {
+                    record(&mut tests, format == Some(Format::Pytest), test);
+                }
+            }
+            // Both pipes closed: the suite is done or has detached them.
+            Err(mpsc::RecvTimeoutError::Disconnected) => break,
+            Err(mpsc::RecvTimeoutError::Timeout) if left.is_zero() => {
+                timed_out = true;
+                break;
+            }
+            Err(mpsc::RecvTimeoutError::Timeout) => {}
+        }
+    }
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break Some(status),
+            Ok(None) if timed_out || started.elapsed() >= timeout => {
+                let _ = child.kill();
+                let _ = child.wait();
+                timed_out = true;
+                break None;
+            }
+            Ok(None) => std::thread::sleep(Duration::from_millis(20)),
+            Err(e) => {
+                let _ = child.kill();
+                output.extend_from_slice(format!("failed to wait: {e}\n").as_bytes());
+                break None;
+            }
+        }
+    };
+    let exit_code = status.and_then(|s| s.code());
+    let passed = status.is_some_and(|s| s.success());
+    finish(&output, tests, exit_code, timed_out, passed)
+}
+
+/// pytest reports a test again in its summary, so a later line for the
+/// same test replaces the earlier one there.
+fn record(tests: &mut Vec<TestCase>, replace: bool, test: TestCase) {
+    if replace {
+        if let Some(seen) = tests.iter_mut().find(|seen| seen.name == test.name) {
+            *seen = test;
+            return;
+        }
+    }
+    tests.push(test);
+}
+
+/// The test result `line` reports, if it reports one.
+pub fn parse(format: Format, line: &str) -> Option<TestCase> {
+    let case = |name: &str, outcome| {
+        Some(TestCase {
+            name: name.trim().to_string(),
+            outcome,
+        })
+    };
+    match format {
+        Format::Cargo => {
+            let (name, result) = line.strip_prefix("test ")?.split_once(" ... ")?;
+            let outcome = match result.split([' ', ',']).next()? {
+                "ok" => Outcome::Passed,
+                "FAILED" => Outcome::Failed,
+                "ignored" => Outcome::Skipped,
+                _ => return None,
+            };
+            case(name, outcome)
+        }
+        Format::Jest => {
+            let line = line.trim_start();
+            let mut chars = line.chars();
+            let outcome = match chars.next()? {
+                '✓' | '√' => Outcome::Passed,
+                '✕' | '×' => Outcome::Failed,
+                '○' => Outcome::Skipped,
+                _ => return None,
+            };
+            let rest = chars.as_str().trim();
+            let name = rest
+                .strip_prefix("skipped ")
+                .or_else(|| rest.strip_prefix("todo "))
+                .unwrap_or(rest);
+            // `adds numbers (3 ms)`
+            let name = match name.rsplit_once(" (") {
+                Some((name, time)) if time.ends_with("ms)") || time.ends_with(" s)") => name,
+                _ => name,
+            };
+            case(name, outcome)
+        }
+        Format::Pytest => {
+            let mut words = line.split_whitespace();
+            let (first, second) = (words.next()?, words.next()?);
+            let outcome = |word: &str| match word {
+                "PASSED" | "XPASS" => Some(Outcome::Passed),
+                "FAILED" | "ERROR" => Some(Outcome::Failed),
+                "SKIPPED" | "XFAIL" => Some(Outcome::Skipped),
+                _ => None,
+            };
+            if first.contains("::") {
+                case(first, outcome(second)?)
+            } else if second.contains("::") {
+                case(second, outcome(first)?)
+            } else {
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_each_format() {
+        let passed = |name: &str| TestCase {
+            name: name.to_string(),
+            outcome: Outcome::Passed,
+        };
+        assert_eq!(
+            parse(Format::Cargo, "test store::tests::pages ... ok"),
+            Some(passed("store::tests::pages"))
+        );
+        assert_eq!(
+            parse(Format::Cargo, "test slow ... ignored, needs network").map(|t| t.outcome),
+            Some(Outcome::Skipped)
+        );
+        assert_eq!(parse(Format::Cargo, "test result: ok. 3 passed"), None);
+        assert_eq!(
+            parse(Format::Jest, "    ✓ adds numbers (3 ms)"),
+            Some(passed("adds numbers"))
+        );
+        assert_eq!(
+            parse(Format::Jest, "    ○ skipped later").map(|t| (t.name, t.outcome)),
+            Some(("later".to_string(), Outcome::Skipped))
+        );
+        assert_eq!(
+            parse(Format::Pytest, "tests/test_a.py::test_x PASSED    [ 50%]"),
+            Some(passed("tests/test_a.py::test_x"))
+        );
+        assert_eq!(
+            parse(
+                Format::Pytest,
+                "FAILED tests/test_a.py::test_y - assert 1 == 2"
+            )
+            .map(|t| t.outcome),
+            Some(Outcome::Failed)
+        );
+        assert_eq!(parse(Format::Pytest, "collected 2 items"), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn runs_a_suite_streaming_lines_into_results() {
+        let dir = crate::test_support::temp_path("tests");
+        std::fs::create_dir_all(dir.join(".gitforge")).expect("create dir");
+        assert!(load(&dir).expect("no file").is_empty());
+        std::fs::write(
+            dir.join(CONFIG_PATH),
+            r#"
+[[suite]]
+name = "unit"
+run = "echo 'test a ... ok'; echo 'test b ... FAILED' >&2; echo 'test c ... ignored'; exit 101"
+format = "cargo"
+
+[[suite]]
+name = "slow"
+run = "sleep 5"
+timeout = 0
+"#,
+        )
+        .expect("write suites");
+        let suites = load(&dir).expect("load");
+
+        let mut lines = Vec::new();
+        let unit = run(&dir, &suites[0], &mut |line| lines.push(line.to_string()));
+        assert!(!unit.passed);
+        assert_eq!(unit.exit_code, Some(101));
+        assert_eq!(
+            unit.counts,
+            Counts {
+                passed: 1,
+                failed: 1,
+                skipped: 1
+            }
+        );
+        assert_eq!(lines.len(), 3);
+        assert!(unit.output.contains("test b ... FAILED"));
+
+        let slow = run(&dir, &suites[1], &mut |_| {});
+        assert!(slow.timed_out && !slow.passed);
+        assert_eq!(slow.format, None);
+
+        std::fs::write(dir.join(CONFIG_PATH), "[[suite]]\nname = \"x\"\n").expect("write");
+        assert!(load(&dir).is_err());
+    }
+}
@@ -0,0 +1,152 @@
+//! Fixtures shared by the unit tests: scratch directories that clean up
+//! after themselves, and repositories initialized in them.
+
+use std::ffi::OsStr;
+use std::fmt;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A path under the system temp dir, unique to one call. Whatever ends up
+/// there is removed when the guard drops, so keep it bound for as long as
+/// the test uses the path.
+#[derive(Debug)]
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+
+    /// The path as UTF-8, for the APIs that take repository paths as text.
+    pub fn as_str(&self) -> &str {
+        self.0.to_str().expect("utf8 temp path")
+    }
+}
+
+impl Deref for TempDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl AsRef<Path> for TempDir {
+    fn as_ref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl AsRef<OsStr> for TempDir {
+    fn as_ref(&self) -> &OsStr {
+        self.0.as_os_str()
+    }
+}
+
+impl fmt::Display for TempDir {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.display().fmt(f)
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+/// A path named after `label` that doesn't exist yet, for tests that
+/// create it themselves or check that nothing was created.
+pub fn temp_path(label: &str) -> TempDir {
+    static NEXT: AtomicU64 = AtomicU64::new(0);
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("clock before unix epoch")
+        .as_nanos();
+    let n = NEXT.fetch_add(1, Ordering::Relaxed);
+    let name = format!("gitforge-{label}-{}-{nanos}-{n}", std::process::id());
+    TempDir(std::env::temp_dir().join(name))
+}
+
+/// An empty directory named after `label`.
+pub fn temp_dir(label: &str) -> TempDir {
+    let dir = temp_path(label);
+    std::fs::create_dir_all(&dir.0).expect("create temp dir");
+    dir
+}
+
+/// A repository with no commits at a fresh [`temp_path`].
+pub fn init_repo(label: &str) -> (TempDir, git2::Repository) {
+    let dir = temp_path(label);
+    let repo = git2::Repository::init(&dir.0).expect("init repo");
+    (dir, repo)
+}
+
+/// Like [`init_repo`], with one commit adding `README.md`.
+pub fn init_repo_with_file(label: &str) -> (TempDir, git2::Repository) {
+    let (dir, repo) = init_repo(label);
+    commit_file(&repo, "README.md", "hello gitforge\n", "initial");
+    (dir, repo)
+}
+
+/// Writes `contents` to `path` in `repo`'s working tree and commits it
+/// on HEAD with `message`.
+pub fn commit_file(
+    repo: &git2::Repository,
+    path: &str,
+    contents: &str,
+    message: &str,
+) -> git2::Oid {
+    let workdir = repo.workdir().expect("working tree");
+    if let Some(parent) = workdir.join(path).parent() {
+        std::fs::create_dir_all(parent).expect("create dirs");
+    }
+    std::fs::write(workdir.join(path), contents).expect("write file");
+    let mut index = repo.index().expect("index");
+    index.add_path(Path::new(path)).expect("stage file");
+    index.write().expect("write index");
+    let tree = repo
+        .find_tree(index.write_tree().expect("write tree"))
+        .expect("find tree");
+    let sig = git2::Signature::now("GitForge Test", "test@gitforge.dev").expect("signature");
+    let parent = repo.head().ok().and_then(|head| head.peel_to_commit().ok());
+    repo.commit(
+        Some("HEAD"),
+        &sig,
+        &sig,
+        message,
+        &tree,
+        &parent.iter().collect::<Vec<_>>(),
+    )
+    .expect("commit")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn temp_dirs_are_unique_and_removed_on_drop() {
+        let (dir, repo) = init_repo("support");
+        let other = temp_dir("support");
+        assert_ne!(dir.path(), other.path());
+        assert!(repo.is_empty().expect("empty"));
+        let (_committed, repo) = init_repo_with_file("support");
+        assert_eq!(
+            repo.head()
+                .expect("head")
+                .peel_to_commit()
+                .unwrap()
+                .message(),
+            Some("initial")
+        );
+        std::fs::write(other.join("a.txt"), "a").expect("write");
+        let (kept, removed) = (dir.to_path_buf(), other.to_path_buf());
+        assert!(!temp_path("support").exists());
+        drop(other);
+        assert!(!removed.exists());
+        assert!(kept.exists());
+    }
+}
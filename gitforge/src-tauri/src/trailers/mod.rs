@@ -0,0 +1,134 @@
+use serde::Serialize;
+
+/// The goal a commit was made for, e.g. `GitForge-Goal: G-12`.
+pub const GOAL: &str = "GitForge-Goal";
+/// The PR a commit belongs to, e.g. `GitForge-PR: 7`.
+pub const PR: &str = "GitForge-PR";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Trailer {
+    pub key: String,
+    pub value: String,
+}
+
+/// The trailers in a message's last paragraph, as `git interpret-trailers`
+/// reads them.
+pub fn read(message: &str) -> Vec<Trailer> {
+    let Ok(trailers) = git2::message_trailers_strs(message) else {
+        return Vec::new();
+    };
+    trailers
+        .iter()
+        .map(|(key, value)| Trailer {
+            key: key.to_string(),
+            value: value.trim().to_string(),
+        })
+        .collect()
+}
+
+/// `message` with each of `trailers` added to its trailer block, leaving
+/// out ones it already has. Without a trailer block, one is started after
+/// a blank line.
+pub fn append(message: &str, trailers: &[(&str, String)]) -> String {
+    let existing = read(message);
+    let missing: Vec<&(&str, String)> = trailers
+        .iter()
+        .filter(|(key, value)| {
+            !existing
+                .iter()
+                .any(|t| t.key.eq_ignore_ascii_case(key) && t.value == *value)
+        })
+        .collect();
+    if missing.is_empty() {
+        return message.to_string();
+    }
+    let mut out = message.trim_end().to_string();
+    out.push_str(if existing.is_empty() { "\n\n" } else { "\n" });
+    for (key, value) in missing {
+        out.push_str(&format!("{key}: {value}\n"));
+    }
+    out
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TrailerMatch {
+    pub commit: String,
+    pub summary: String,
+    pub author: String,
+    pub time: i64,
+    /// All of the commit's trailers, not just the one matched.
+    pub trailers: Vec<Trailer>,
+}
+
+/// Commits reachable from HEAD or a local branch with a `key` trailer
+/// (compared case-insensitively), whose value is `value` when given,
+/// newest first and at most `limit` of them.
+pub fn query(
+    repo: &git2::Repository,
+    key: &str,
+    value: Option<&str>,
+    limit: usize,
+) -> Result<Vec<TrailerMatch>, String> {
+    let mut walk = repo
+        .revwalk()
+        .map_err(|e| format!("failed to walk history: {e}"))?;
+    walk.set_sorting(git2::Sort::TOPOLOGICAL | git2::Sort::TIME)
+        .map_err(|e| format!("failed to walk history: {e}"))?;
+    if repo.head().is_ok() {
+        walk.push_head()
+            .map_err(|e| format!("failed to walk HEAD: {e}"))?;
+    }
+    walk.push_glob("refs/heads")
+        .map_err(|e| format!("failed to walk branches: {e}"))?;
+
+    let mut matches = Vec::new();
+    for oid in walk {
+        if matches.len() == limit {
+            break;
+        }
+        let oid = oid.map_err(|e| format!("failed to walk history: {e}"))?;
+        let commit = repo
+            .find_commit(oid)
+            .map_err(|e| format!("failed to read commit {oid}: {e}"))?;
+        let trailers = read(&String::from_utf8_lossy(commit.message_bytes()));
+        let hit = trailers
+            .iter()
+            .any(|t| t.key.eq_ignore_ascii_case(key) && value.is_none_or(|value| t.value == value));
+        if hit {
+            matches.push(TrailerMatch {
+                commit: oid.to_string(),
+                summary: commit.summary().unwrap_or_default().to_string(),
+                author: commit.author().name().unwrap_or_default().to_string(),
+                time: commit.time().seconds(),
+                trailers,
+            });
+        }
+    }
+    Ok(matches)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn append_joins_an_existing_trailer_block_and_skips_duplicates() {
+        let plain = append("Fix login\n", &[(PR, "7".to_string())]);
+        assert_eq!(plain, "Fix login\n\nGitForge-PR: 7\n");
+        assert_eq!(
+            read(&plain),
+            [Trailer {
+                key: PR.into(),
+                value: "7".into()
+            }]
+        );
+
+        let signed = "Fix login\n\nBody.\n\nSigned-off-by: A <a@example.com>\n";
+        let both = append(signed, &[(GOAL, "G-1".to_string()), (PR, "7".to_string())]);
+        assert_eq!(
+            both,
+            "Fix login\n\nBody.\n\nSigned-off-by: A <a@example.com>\nGitForge-Goal: G-1\nGitForge-PR: 7\n"
+        );
+        assert_eq!(append(&both, &[(PR, "7".to_string())]), both);
+    }
+}
@@ -0,0 +1,312 @@
+pub mod service;
+
+use notify::{RecursiveMode, Watcher};
+use serde::Serialize;
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::time::Duration;
+
+/// Repository change observed by [`RepoWatcher`], shared by the CLI and UI.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RepoEvent {
+    WorktreeChanged { paths: Vec<String> },
+    IndexChanged,
+    RefUpdated { name: String },
+    StatusChanged { path: String, status: String },
+}
+
+impl RepoEvent {
+    /// Whether per-file status may have changed, i.e. a [`StatusTracker`]
+    /// refresh is worth running.
+    pub fn touches_status(&self) -> bool {
+        matches!(
+            self,
+            RepoEvent::WorktreeChanged { .. } | RepoEvent::IndexChanged
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Change {
+    Worktree(String),
+    Index,
+    Ref(String),
+}
+
+pub struct RepoWatcher {
+    repo: git2::Repository,
+    git_dir: PathBuf,
+    workdir: Option<PathBuf>,
+    rx: mpsc::Receiver<notify::Result<notify::Event>>,
+    _watcher: notify::RecommendedWatcher,
+}
+
+impl RepoWatcher {
+    pub fn new(repo_path: &str) -> Result<Self, String> {
+        let repo = git2::Repository::open(repo_path)
+            .map_err(|e| format!("failed to open repository: {e}"))?;
+        let git_dir = canonical(repo.path());
+        let workdir = repo.workdir().map(canonical);
+
+        let (tx, rx) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(move |res| {
+            let _ = tx.send(res);
+        })
+        .map_err(|e| format!("failed to start watcher: {e}"))?;
+
+        let mut roots = Vec::new();
+        match &workdir {
+            Some(workdir) => {
+                roots.push(workdir.clone());
+                if !git_dir.starts_with(workdir) {
+                    roots.push(git_dir.clone());
+                }
+            }
+            None => roots.push(git_dir.clone()),
+        }
+        for root in &roots {
+            watcher
+                .watch(root, RecursiveMode::Recursive)
+                .map_err(|e| format!("failed to watch {}: {e}", root.display()))?;
+        }
+
+        Ok(Self {
+            repo,
+            git_dir,
+            workdir,
+            rx,
+            _watcher: watcher,
+        })
+    }
+
+    pub fn repo(&self) -> &git2::Repository {
+        &self.repo
+    }
+
+    /// Blocks until something changes, then keeps collecting until the
+    /// repository has been quiet for `debounce` and returns the merged batch.
+    pub fn next_batch(&self, debounce: Duration) -> Result<Vec<RepoEvent>, String> {
+        let first = self.rx.recv().map_err(|_| "watcher stopped".to_string())?;
+        self.collect(first, debounce)
+    }
+
+    /// Like [`next_batch`](Self::next_batch), but returns `None` after `wait`
+    /// without activity so long-running callers can check for shutdown.
+    pub fn poll_batch(
+        &self,
+        wait: Duration,
+        debounce: Duration,
+    ) -> Result<Option<Vec<RepoEvent>>, String> {
+        match self.rx.recv_timeout(wait) {
+            Ok(first) => self.collect(first, debounce).map(Some),
+            Err(mpsc::RecvTimeoutError::Timeout) => Ok(None),
+            Err(mpsc::RecvTimeoutError::Disconnected) => Err("watcher stopped".to_string()),
+        }
+    }
+
+    fn collect(
+        &self,
+        first: notify::Result<notify::Event>,
+        debounce: Duration,
+    ) -> Result<Vec<RepoEvent>, String> {
+        let mut raw = vec![first];
+        while let Ok(event) = self.rx.recv_timeout(debounce) {
+            raw.push(event);
+        }
+
+        let mut worktree = BTreeSet::new();
+        let mut index = false;
+        let mut refs = BTreeSet::new();
+
+        for event in raw {
+            let event = event.map_err(|e| format!("watch error: {e}"))?;
+            if event.kind.is_access() {
+                continue;
+            }
+            for path in &event.paths {
+                match classify(&self.git_dir, self.workdir.as_deref(), path) {
+                    Some(Change::Worktree(rel)) if !self.is_ignored(&rel) => {
+                        worktree.insert(rel);
+                    }
+                    Some(Change::Index) => index = true,
+                    Some(Change::Ref(name)) => {
+                        refs.insert(name);
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        let mut events = Vec::new();
+        if !worktree.is_empty() {
+            events.push(RepoEvent::WorktreeChanged {
+                paths: worktree.into_iter().collect(),
+            });
+        }
+        if index {
+            events.push(RepoEvent::IndexChanged);
+        }
+        events.extend(refs.into_iter().map(|name| RepoEvent::RefUpdated { name }));
+
+        Ok(events)
+    }
+
+    fn is_ignored(&self, rel: &str) -> bool {
+        self.repo
+            .status_should_ignore(Path::new(rel))
+            .unwrap_or(false)
+    }
+}
+
+/// Remembers the last observed status per path so only differences are reported.
+#[derive(Default)]
+pub struct StatusTracker {
+    last: HashMap<String, git2::Status>,
+}
+
+impl StatusTracker {
+    pub fn refresh(&mut self, repo: &git2::Repository) -> Result<Vec<RepoEvent>, String> {
+        let mut opts = git2::StatusOptions::new();
+        opts.include_untracked(true).recurse_untracked_dirs(true);
+
+        let statuses = repo
+            .statuses(Some(&mut opts))
+            .map_err(|e| format!("failed to read status: {e}"))?;
+
+        let current: HashMap<String, git2::Status> = statuses
+            .iter()
+            .filter_map(|entry| entry.path().map(|p| (p.to_string(), entry.status())))
+            .collect();
+
+        let mut changes: Vec<RepoEvent> = current
+            .iter()
+            .filter(|(path, status)| self.last.get(*path) != Some(status))
+            .map(|(path, status)| RepoEvent::StatusChanged {
+                path: path.clone(),
+                status: format!("{status:?}"),
+            })
+            .collect();
+        changes.extend(
+            self.last
+                .keys()
+                .filter(|path| !current.contains_key(*path))
+                .map(|path| RepoEvent::StatusChanged {
+                    path: path.clone(),
+                    status: format!("{:?}", git2::Status::CURRENT),
+                }),
+        );
+        changes.sort_by(|a, b| status_path(a).cmp(status_path(b)));
+
+        self.last = current;
+        Ok(changes)
+    }
+}
+
+fn status_path(event: &RepoEvent) -> &str {
+    match event {
+        RepoEvent::StatusChanged { path, .. } => path,
+        _ => "",
+    }
+}
+
+fn classify(git_dir: &Path, workdir: Option<&Path>, path: &Path) -> Option<Change> {
+    if let Ok(rel) = path.strip_prefix(git_dir) {
+        let rel = rel.to_string_lossy().replace('\\', "/");
+        if rel.ends_with(".lock") {
+            return None;
+        }
+        return match rel.as_str() {
+            "index" => Some(Change::Index),
+            "HEAD" | "ORIG_HEAD" | "FETCH_HEAD" | "MERGE_HEAD" | "packed-refs" => {
+                Some(Change::Ref(rel))
+            }
+            _ if rel.starts_with("refs/") => Some(Change::Ref(rel)),
+            _ => None,
+        };
+    }
+
+    let rel = path.strip_prefix(workdir?).ok()?;
+    if rel.as_os_str().is_empty() || rel.starts_with(".git") {
+        return None;
+    }
+    Some(Change::Worktree(rel.to_string_lossy().replace('\\', "/")))
+}
+
+fn canonical(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn temp_repo(label: &str) -> (crate::test_support::TempDir, git2::Repository) {
+        let dir = crate::test_support::temp_path(&format!("watch-{label}"));
+        fs::create_dir_all(&dir).expect("create repo dir");
+        let repo = git2::Repository::init(&dir).expect("init repo");
+        (dir, repo)
+    }
+
+    #[test]
+    fn classify_separates_index_refs_and_worktree() {
+        let workdir = Path::new("/repo");
+        let git_dir = Path::new("/repo/.git");
+
+        assert_eq!(
+            classify(git_dir, Some(workdir), Path::new("/repo/.git/index")),
+            Some(Change::Index)
+        );
+        assert_eq!(
+            classify(
+                git_dir,
+                Some(workdir),
+                Path::new("/repo/.git/refs/heads/main")
+            ),
+            Some(Change::Ref("refs/heads/main".into()))
+        );
+        assert_eq!(
+            classify(git_dir, Some(workdir), Path::new("/repo/src/lib.rs")),
+            Some(Change::Worktree("src/lib.rs".into()))
+        );
+    }
+
+    #[test]
+    fn classify_ignores_git_internals_and_locks() {
+        let workdir = Path::new("/repo");
+        let git_dir = Path::new("/repo/.git");
+
+        assert_eq!(
+            classify(git_dir, Some(workdir), Path::new("/repo/.git/index.lock")),
+            None
+        );
+        assert_eq!(
+            classify(
+                git_dir,
+                Some(workdir),
+                Path::new("/repo/.git/objects/ab/cdef")
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn status_tracker_reports_only_differences() {
+        let (dir, repo) = temp_repo("tracker");
+        let mut tracker = StatusTracker::default();
+        assert!(tracker.refresh(&repo).expect("refresh").is_empty());
+
+        fs::write(dir.join("new.txt"), "content").expect("write file");
+        let changes = tracker.refresh(&repo).expect("refresh");
+        assert_eq!(changes.len(), 1);
+        assert!(matches!(&changes[0], RepoEvent::StatusChanged { path, .. } if path == "new.txt"));
+
+        assert!(tracker.refresh(&repo).expect("refresh").is_empty());
+
+        fs::remove_file(dir.join("new.txt")).expect("remove file");
+        let changes = tracker.refresh(&repo).expect("refresh");
+        assert_eq!(changes.len(), 1);
+    }
+}
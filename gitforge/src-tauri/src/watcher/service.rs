@@ -0,0 +1,157 @@
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast;
+
+use super::{RepoEvent, RepoWatcher};
+
+const DEBOUNCE: Duration = Duration::from_millis(200);
+/// How long the watch thread blocks before re-checking for shutdown.
+const STOP_POLL: Duration = Duration::from_millis(500);
+
+/// One debounced batch of repository changes.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RepoChanged {
+    /// Bumped once per batch; caches stamped with an older generation are stale.
+    pub generation: u64,
+    /// Working-tree paths, relative to the workdir, not ignored by git.
+    pub paths: Vec<String>,
+    pub index: bool,
+    pub refs: Vec<String>,
+}
+
+impl RepoChanged {
+    fn from_batch(generation: u64, events: Vec<RepoEvent>) -> Self {
+        let mut changed = Self {
+            generation,
+            paths: Vec::new(),
+            index: false,
+            refs: Vec::new(),
+        };
+        for event in events {
+            match event {
+                RepoEvent::WorktreeChanged { paths } => changed.paths.extend(paths),
+                RepoEvent::IndexChanged => changed.index = true,
+                RepoEvent::RefUpdated { name } => changed.refs.push(name),
+                RepoEvent::StatusChanged { .. } => {}
+            }
+        }
+        changed
+    }
+
+    /// Whether per-file status may differ from before this batch.
+    pub fn touches_status(&self) -> bool {
+        !self.paths.is_empty() || self.index
+    }
+}
+
+/// Runs a [`RepoWatcher`] on its own thread and broadcasts [`RepoChanged`]
+/// to every subscriber (UI stream, status cache). Stops when dropped.
+pub struct WatchService {
+    tx: broadcast::Sender<RepoChanged>,
+    generation: Arc<AtomicU64>,
+    stop: Arc<AtomicBool>,
+}
+
+impl WatchService {
+    pub fn start(repo_path: &str) -> Result<Self, String> {
+        let watcher = RepoWatcher::new(repo_path)?;
+        let (tx, _) = broadcast::channel(256);
+        let generation = Arc::new(AtomicU64::new(0));
+        let stop = Arc::new(AtomicBool::new(false));
+
+        {
+            let repo = repo_path.to_string();
+            let tx = tx.clone();
+            let generation = Arc::clone(&generation);
+            let stop = Arc::clone(&stop);
+            std::thread::spawn(move || {
+                while !stop.load(Ordering::Relaxed) {
+                    let events = match watcher.poll_batch(STOP_POLL, DEBOUNCE) {
+                        Ok(Some(events)) if !events.is_empty() => events,
+                        Ok(_) => continue,
+                        Err(e) => {
+                            eprintln!("watcher for {repo} stopped: {e}");
+                            return;
+                        }
+                    };
+                    let generation = generation.fetch_add(1, Ordering::Relaxed) + 1;
+                    let _ = tx.send(RepoChanged::from_batch(generation, events));
+                }
+            });
+        }
+
+        Ok(Self {
+            tx,
+            generation,
+            stop,
+        })
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<RepoChanged> {
+        self.tx.subscribe()
+    }
+
+    /// Generation of the latest batch; `0` until something changes.
+    pub fn generation(&self) -> u64 {
+        self.generation.load(Ordering::Relaxed)
+    }
+}
+
+impl Drop for WatchService {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn batches_are_summarized() {
+        let changed = RepoChanged::from_batch(
+            3,
+            vec![
+                RepoEvent::WorktreeChanged {
+                    paths: vec!["src/lib.rs".into()],
+                },
+                RepoEvent::RefUpdated {
+                    name: "refs/heads/main".into(),
+                },
+            ],
+        );
+
+        assert_eq!(changed.generation, 3);
+        assert_eq!(changed.paths, ["src/lib.rs"]);
+        assert_eq!(changed.refs, ["refs/heads/main"]);
+        assert!(!changed.index);
+        assert!(changed.touches_status());
+    }
+
+    #[test]
+    fn edits_are_broadcast_with_a_new_generation() {
+        let dir = crate::test_support::temp_path("watch-service");
+        git2::Repository::init(&dir).expect("init repo");
+
+        let service = WatchService::start(&dir.to_string_lossy()).expect("start service");
+        let mut rx = service.subscribe();
+        assert_eq!(service.generation(), 0);
+        std::fs::write(dir.join("notes.txt"), "hello").expect("write file");
+
+        let deadline = std::time::Instant::now() + Duration::from_secs(10);
+        let changed = loop {
+            match rx.try_recv() {
+                Ok(changed) if changed.paths.iter().any(|p| p == "notes.txt") => break changed,
+                Ok(_) | Err(broadcast::error::TryRecvError::Empty) => {
+                    assert!(std::time::Instant::now() < deadline, "no repo_changed");
+                    std::thread::sleep(Duration::from_millis(20));
+                }
+                Err(e) => panic!("watch service stopped: {e}"),
+            }
+        };
+        assert!(changed.generation >= 1);
+        assert!(service.generation() >= changed.generation);
+    }
+}
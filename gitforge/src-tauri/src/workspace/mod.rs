@@ -0,0 +1,367 @@
+//! Workspaces: named sets of repositories, for status, sync and PR lists
+//! across a fleet of services at once. Kept in a JSON file like the
+//! recent-repositories list, since they span repositories.
+
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+use crate::revcache::RevCache;
+use crate::status::Untracked;
+use crate::store::{PrQuery, PrRecord, MAX_PAGE_SIZE};
+use crate::worktree::{self, WorktreeStatus, WorktreeSync};
+
+/// Overrides where workspaces are kept.
+pub const WORKSPACES_ENV: &str = "GITFORGE_WORKSPACES";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Workspace {
+    pub name: String,
+    /// Repository roots, in the order they were given.
+    pub repos: Vec<String>,
+    pub updated_at: String,
+}
+
+pub struct Workspaces {
+    file: PathBuf,
+}
+
+impl Workspaces {
+    /// `$GITFORGE_WORKSPACES`, else `<data home>/gitforge/workspaces.json`.
+    pub fn open_default() -> Self {
+        let file = std::env::var_os(WORKSPACES_ENV)
+            .filter(|v| !v.is_empty())
+            .map(PathBuf::from)
+            .unwrap_or_else(|| {
+                crate::db::location::xdg_data_home()
+                    .join("gitforge")
+                    .join("workspaces.json")
+            });
+        Self::at(file)
+    }
+
+    pub fn at(file: PathBuf) -> Self {
+        Self { file }
+    }
+
+    /// Sorted by name. A missing file is no workspaces.
+    pub fn list(&self) -> Result<Vec<Workspace>, String> {
+        let json = match std::fs::read_to_string(&self.file) {
+            Ok(json) => json,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(format!("failed to read {}: {e}", self.file.display())),
+        };
+        let mut items: Vec<Workspace> = serde_json::from_str(&json)
+            .map_err(|e| format!("invalid {}: {e}", self.file.display()))?;
+        items.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(items)
+    }
+
+    pub fn get(&self, name: &str) -> Result<Workspace, String> {
+        self.list()?
+            .into_iter()
+            .find(|workspace| workspace.name == name)
+            .ok_or_else(|| format!("workspace '{name}' not found"))
+    }
+
+    /// Creates or replaces workspace `name`. Each path may be anywhere in
+    /// its repository; the root is stored.
+    pub fn save(&self, name: &str, paths: &[String]) -> Result<Workspace, String> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err("workspace name is empty".to_string());
+        }
+        if paths.is_empty() {
+            return Err(format!("workspace '{name}' needs at least one repository"));
+        }
+        let mut repos: Vec<String> = Vec::new();
+        for path in paths {
+            let root = crate::recent::repo_root(path)?;
+            if !repos.contains(&root) {
+                repos.push(root);
+            }
+        }
+        let workspace = Workspace {
+            name: name.to_string(),
+            repos,
+            updated_at: crate::store::now_timestamp(),
+        };
+        let mut items = self.list()?;
+        items.retain(|item| item.name != name);
+        items.push(workspace.clone());
+        self.write(&mut items)?;
+        Ok(workspace)
+    }
+
+    pub fn remove(&self, name: &str) -> Result<bool, String> {
+        let mut items = self.list()?;
+        let before = items.len();
+        items.retain(|item| item.name != name);
+        if items.len() == before {
+            return Ok(false);
+        }
+        self.write(&mut items)?;
+        Ok(true)
+    }
+
+    /// Through a temporary file, so a crash mid-write keeps the old list.
+    fn write(&self, items: &mut [Workspace]) -> Result<(), String> {
+        items.sort_by(|a, b| a.name.cmp(&b.name));
+        if let Some(parent) = self.file.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("failed to create {}: {e}", parent.display()))?;
+        }
+        let json = serde_json::to_string_pretty(items)
+            .map_err(|e| format!("failed to encode workspaces: {e}"))?;
+        let temp = self.file.with_extension("tmp");
+        std::fs::write(&temp, json)
+            .and_then(|()| std::fs::rename(&temp, &self.file))
+            .map_err(|e| format!("failed to write {}: {e}", self.file.display()))
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RepoStatus {
+    pub repo: String,
+    /// The main checkout first, then linked worktrees.
+    pub worktrees: Vec<WorktreeStatus>,
+    /// Set when the repository couldn't be read at all.
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct StatusTotals {
+    pub repos: usize,
+    /// Repositories with a checkout that has changes.
+    pub dirty: usize,
+    /// Commits not pushed and not pulled, over every checkout.
+    pub ahead: usize,
+    pub behind: usize,
+    /// Repositories or checkouts that couldn't be read.
+    pub failed: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StatusReport {
+    pub workspace: String,
+    pub items: Vec<RepoStatus>,
+    pub totals: StatusTotals,
+}
+
+/// Status of every checkout of every repository, `parallelism`
+/// repositories at a time.
+pub fn status(
+    workspace: &Workspace,
+    untracked: Untracked,
+    parallelism: usize,
+    revs: &RevCache,
+) -> StatusReport {
+    let items = worktree::parallel_map(&workspace.repos, parallelism, |repo| {
+        let read = worktree::status_all(repo, untracked, 1, revs);
+        let (worktrees, error) = match read {
+            Ok(worktrees) => (worktrees, None),
+            Err(error) => (Vec::new(), Some(error)),
+        };
+        RepoStatus {
+            repo: repo.clone(),
+            worktrees,
+            error,
+        }
+    });
+    let mut totals = StatusTotals {
+        repos: items.len(),
+        ..StatusTotals::default()
+    };
+    for item in &items {
+        let failed = item.error.is_some() || item.worktrees.iter().any(|wt| wt.error.is_some());
+        totals.failed += failed as usize;
+        totals.dirty += item.worktrees.iter().any(|wt| wt.summary.total > 0) as usize;
+        totals.ahead += item.worktrees.iter().map(|wt| wt.ahead).sum::<usize>();
+        totals.behind += item.worktrees.iter().map(|wt| wt.behind).sum::<usize>();
+    }
+    StatusReport {
+        workspace: workspace.name.clone(),
+        items,
+        totals,
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RepoSync {
+    pub repo: String,
+    pub worktrees: Vec<WorktreeSync>,
+    /// Set when the fetch, or the repository as a whole, failed.
+    pub error: Option<String>,
+}
+
+/// Fast-forwards every clean checkout of every repository, fetching
+/// `fetch` in each first; see [`worktree::sync_all`]. Each repository's
+/// metadata write lock is held while it syncs.
+pub fn sync(workspace: &Workspace, fetch: Option<&str>, parallelism: usize) -> Vec<RepoSync> {
+    worktree::parallel_map(&workspace.repos, parallelism, |repo| {
+        let synced = crate::db::lock::acquire(repo, "workspace sync", crate::db::BUSY_TIMEOUT)
+            .map_err(String::from)
+            .and_then(|_lock| worktree::sync_all(repo, fetch, 1));
+        match synced {
+            Ok(worktrees) => RepoSync {
+                repo: repo.clone(),
+                worktrees,
+                error: None,
+            },
+            Err(error) => RepoSync {
+                repo: repo.clone(),
+                worktrees: Vec::new(),
+                error: Some(error),
+            },
+        }
+    })
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RepoPr {
+    pub repo: String,
+    #[serde(flatten)]
+    pub pr: PrRecord,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RepoError {
+    pub repo: String,
+    pub error: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PrReport {
+    pub workspace: String,
+    /// Newest first across all repositories.
+    pub items: Vec<RepoPr>,
+    /// Repositories whose PRs couldn't be read.
+    pub errors: Vec<RepoError>,
+}
+
+/// Every PR in `state` (all states for `None`) from each repository's
+/// metadata store.
+pub fn prs(workspace: &Workspace, state: Option<&str>, parallelism: usize) -> PrReport {
+    let results = worktree::parallel_map(&workspace.repos, parallelism, |repo| {
+        repo_prs(repo, state).map_err(|error| RepoError {
+            repo: repo.clone(),
+            error,
+        })
+    });
+    let mut items = Vec::new();
+    let mut errors = Vec::new();
+    for result in results {
+        match result {
+            Ok(prs) => items.extend(prs),
+            Err(error) => errors.push(error),
+        }
+    }
+    items.sort_by(|a, b| {
+        b.pr.created_at
+            .cmp(&a.pr.created_at)
+            .then(a.repo.cmp(&b.repo))
+            .then(b.pr.id.cmp(&a.pr.id))
+    });
+    PrReport {
+        workspace: workspace.name.clone(),
+        items,
+        errors,
+    }
+}
+
+fn repo_prs(repo: &str, state: Option<&str>) -> Result<Vec<RepoPr>, String> {
+    let store = crate::store::open(repo)?;
+    let mut items = Vec::new();
+    let mut before = None;
+    loop {
+        let page = store.list_prs(PrQuery {
+            state,
+            before,
+            limit: MAX_PAGE_SIZE,
+        })?;
+        items.extend(page.items.into_iter().map(|pr| RepoPr {
+            repo: repo.to_string(),
+            pr,
+        }));
+        match page.next_before {
+            Some(next) => before = Some(next),
+            None => return Ok(items),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn workspaces_persist_and_aggregate_their_repositories() {
+        let dir = crate::test_support::temp_path("workspace");
+        let signature = git2::Signature::now("GitForge Test", "test@gitforge.dev").expect("sig");
+        let mut paths = Vec::new();
+        for name in ["api", "web"] {
+            let path = dir.join(name);
+            let repo = git2::Repository::init(&path).expect("init");
+            let tree = repo
+                .index()
+                .and_then(|mut index| index.write_tree())
+                .and_then(|id| repo.find_tree(id))
+                .expect("tree");
+            repo.commit(Some("HEAD"), &signature, &signature, "init", &tree, &[])
+                .expect("commit");
+            std::fs::write(path.join("notes.txt"), "wip\n").expect("untracked");
+            let store = crate::store::open(&path.to_string_lossy()).expect("store");
+            store
+                .create_pr(crate::store::NewPr {
+                    title: name,
+                    from: "feature",
+                    to: "main",
+                    description: None,
+                })
+                .expect("pr");
+            paths.push(path.to_string_lossy().into_owned());
+        }
+        std::fs::create_dir_all(dir.join("api/src")).expect("subdir");
+        let workspaces = Workspaces::at(dir.join("workspaces.json"));
+        assert!(workspaces.list().expect("empty").is_empty());
+
+        // A subdirectory names the same repository.
+        let given = [
+            paths[0].clone(),
+            paths[1].clone(),
+            format!("{}/src", paths[0]),
+        ];
+        let saved = workspaces.save("fleet", &given).expect("save");
+        assert_eq!(saved.repos.len(), 2);
+        assert!(workspaces.save("empty", &[]).is_err());
+        let fleet = workspaces.get("fleet").expect("get");
+        assert_eq!(fleet, saved);
+
+        let report = status(&fleet, Untracked::default(), 2, &RevCache::default());
+        assert_eq!(
+            report.totals,
+            StatusTotals {
+                repos: 2,
+                dirty: 2,
+                ..StatusTotals::default()
+            }
+        );
+        assert!(report.items.iter().all(|item| item.worktrees[0].main));
+
+        let prs = prs(&fleet, Some("open"), 2);
+        let titles: Vec<&str> = prs
+            .items
+            .iter()
+            .map(|item| item.pr.title.as_str())
+            .collect();
+        assert_eq!(titles.len(), 2);
+        assert!(titles.contains(&"api") && titles.contains(&"web"));
+        assert!(prs.errors.is_empty());
+
+        let synced = sync(&fleet, None, 2);
+        assert!(synced.iter().all(|repo| repo.error.is_none()));
+
+        assert!(workspaces.remove("fleet").expect("remove"));
+        assert!(!workspaces.remove("fleet").expect("remove twice"));
+        assert!(workspaces.get("fleet").is_err());
+    }
+}
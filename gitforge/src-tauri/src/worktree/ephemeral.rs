@@ -0,0 +1,268 @@
+//! Throwaway worktrees for agent experiments: a temp-dir checkout on its
+//! own branch that [`expire`] deletes, branch and all, once its time is up,
+//! unless a PR was opened from the branch by then.
+
+use serde::Serialize;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::store::{self, MetaStore, PrQuery, MAX_PAGE_SIZE};
+
+/// Git config section holding `<name>.branch`, `<name>.path` and
+/// `<name>.expires` (unix seconds) per ephemeral worktree.
+pub const SECTION: &str = "gitforge.ephemeral";
+pub const DEFAULT_TTL: Duration = Duration::from_secs(60 * 60);
+pub const MAX_TTL: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Ephemeral {
+    pub name: String,
+    pub branch: String,
+    pub path: String,
+    /// `YYYY-MM-DD HH:MM:SS` UTC.
+    pub expires_at: String,
+    #[serde(skip)]
+    pub expires: i64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Expired {
+    /// Deleted with their branch.
+    pub removed: Vec<Ephemeral>,
+    /// Kept as ordinary worktrees because a PR was opened from them.
+    pub promoted: Vec<Ephemeral>,
+}
+
+/// A fresh worktree name, its branch and its directory under the system
+/// temp dir.
+pub fn allocate() -> (String, String, PathBuf) {
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or_default();
+    let name = format!("sandbox-{:08x}", nanos as u32);
+    let branch = format!("sandbox/{name}");
+    let path = std::env::temp_dir().join(format!("gitforge-{name}"));
+    (name, branch, path)
+}
+
+/// Records worktree `name` as expiring at `expires`.
+pub fn mark(
+    repo: &git2::Repository,
+    name: &str,
+    branch: &str,
+    path: &str,
+    expires: i64,
+) -> Result<Ephemeral, String> {
+    let mut config = repo
+        .config()
+        .map_err(|e| format!("failed to open git config: {e}"))?;
+    let key = |field: &str| format!("{SECTION}.{name}.{field}");
+    config
+        .set_str(&key("branch"), branch)
+        .and_then(|()| config.set_str(&key("path"), path))
+        .and_then(|()| config.set_i64(&key("expires"), expires))
+        .map_err(|e| format!("failed to record ephemeral worktree {name}: {e}"))?;
+    Ok(Ephemeral {
+        name: name.to_string(),
+        branch: branch.to_string(),
+        path: path.to_string(),
+        expires_at: store::timestamp(expires),
+        expires,
+    })
+}
+
+/// Every ephemeral worktree, soonest to expire first.
+pub fn list(repo: &git2::Repository) -> Result<Vec<Ephemeral>, String> {
+    let config = repo
+        .config()
+        .and_then(|mut config| config.snapshot())
+        .map_err(|e| format!("failed to open git config: {e}"))?;
+    let pattern = format!("^{}\\..*\\.expires$", SECTION.replace('.', "\\."));
+    let mut found = Vec::new();
+    let entries = config
+        .entries(Some(&pattern))
+        .map_err(|e| format!("failed to read {SECTION}: {e}"))?;
+    entries
+        .for_each(|entry| {
+            if let Some(name) = entry
+                .name()
+                .and_then(|key| key.strip_prefix(&format!("{SECTION}.")))
+                .and_then(|key| key.strip_suffix(".expires"))
+            {
+                found.push(name.to_string());
+            }
+        })
+        .map_err(|e| format!("failed to read {SECTION}: {e}"))?;
+
+    let mut worktrees = Vec::new();
+    for name in found {
+        let key = |field: &str| format!("{SECTION}.{name}.{field}");
+        let expires = config
+            .get_i64(&key("expires"))
+            .map_err(|e| format!("invalid {}: {e}", key("expires")))?;
+        worktrees.push(Ephemeral {
+            branch: config.get_string(&key("branch")).unwrap_or_default(),
+            path: config.get_string(&key("path")).unwrap_or_default(),
+            expires_at: store::timestamp(expires),
+            expires,
+            name,
+        });
+    }
+    worktrees.sort_by_key(|worktree| worktree.expires);
+    Ok(worktrees)
+}
+
+/// Deletes the worktrees that expired by `now`, with their branch and
+/// registry row, whatever they hold. One with a PR from its branch is
+/// promoted instead: it stops expiring and is left alone.
+pub fn expire(repo: &git2::Repository, store: &dyn MetaStore, now: i64) -> Result<Expired, String> {
+    let due: Vec<Ephemeral> = list(repo)?
+        .into_iter()
+        .filter(|worktree| worktree.expires <= now)
+        .collect();
+    let mut expired = Expired::default();
+    if due.is_empty() {
+        return Ok(expired);
+    }
+    let prs = all_prs(store)?;
+    for worktree in due {
+        if prs.iter().any(|pr| pr.from == worktree.branch) {
+            unmark(repo, &worktree.name)?;
+            expired.promoted.push(worktree);
+            continue;
+        }
+        remove(repo, store, &worktree)?;
+        unmark(repo, &worktree.name)?;
+        expired.removed.push(worktree);
+    }
+    Ok(expired)
+}
+
+fn remove(
+    repo: &git2::Repository,
+    store: &dyn MetaStore,
+    worktree: &Ephemeral,
+) -> Result<(), String> {
+    let name = &worktree.name;
+    if let Ok(found) = repo.find_worktree(name) {
+        let mut opts = git2::WorktreePruneOptions::new();
+        opts.valid(true).locked(true).working_tree(true);
+        found
+            .prune(Some(&mut opts))
+            .map_err(|e| format!("failed to remove worktree {name}: {e}"))?;
+    }
+    let path = std::path::Path::new(&worktree.path);
+    if path.exists() {
+        std::fs::remove_dir_all(path)
+            .map_err(|e| format!("failed to remove {}: {e}", worktree.path))?;
+    }
+    if let Ok(mut branch) = repo.find_branch(&worktree.branch, git2::BranchType::Local) {
+        branch
+            .delete()
+            .map_err(|e| format!("failed to delete branch {}: {e}", worktree.branch))?;
+    }
+    store.remove_worktree(name)?;
+    Ok(())
+}
+
+fn unmark(repo: &git2::Repository, name: &str) -> Result<(), String> {
+    let mut config = repo
+        .config()
+        .map_err(|e| format!("failed to open git config: {e}"))?;
+    for field in ["branch", "path", "expires"] {
+        match config.remove(&format!("{SECTION}.{name}.{field}")) {
+            Ok(()) => {}
+            Err(e) if e.code() == git2::ErrorCode::NotFound => {}
+            Err(e) => return Err(format!("failed to forget ephemeral worktree {name}: {e}")),
+        }
+    }
+    Ok(())
+}
+
+fn all_prs(store: &dyn MetaStore) -> Result<Vec<store::PrRecord>, String> {
+    let mut prs = Vec::new();
+    let mut before = None;
+    loop {
+        let page = store.list_prs(PrQuery {
+            state: None,
+            before,
+            limit: MAX_PAGE_SIZE,
+        })?;
+        prs.extend(page.items);
+        match page.next_before {
+            Some(next) => before = Some(next),
+            None => return Ok(prs),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expired_worktrees_go_unless_promoted_to_a_pr() {
+        let (dir, repo) = crate::test_support::init_repo("ephemeral");
+        let sig = git2::Signature::now("GitForge Test", "test@gitforge.dev").expect("sig");
+        let tree = repo
+            .find_tree(repo.index().unwrap().write_tree().unwrap())
+            .unwrap();
+        let head = repo
+            .commit(Some("HEAD"), &sig, &sig, "init", &tree, &[])
+            .expect("commit");
+        let store = store::open(&dir.to_string_lossy()).expect("store");
+
+        let add = |name: &str, expires: i64| {
+            let branch = format!("sandbox/{name}");
+            let path = dir.with_file_name(format!("{}-{name}", dir.display()));
+            let commit = repo.find_commit(head).unwrap();
+            let reference = repo.branch(&branch, &commit, false).expect("branch");
+            let mut opts = git2::WorktreeAddOptions::new();
+            opts.reference(Some(reference.get()));
+            repo.worktree(name, &path, Some(&opts)).expect("worktree");
+            store
+                .upsert_worktree(name, &path.to_string_lossy(), &branch)
+                .expect("register");
+            mark(&repo, name, &branch, &path.to_string_lossy(), expires).expect("mark")
+        };
+        let old = add("old", 100);
+        let kept = add("kept", 200);
+        let fresh = add("fresh", 10_000);
+        store
+            .create_pr(store::NewPr {
+                title: "Keep this",
+                from: &kept.branch,
+                to: "main",
+                description: None,
+            })
+            .expect("pr");
+        assert_eq!(
+            list(&repo).unwrap(),
+            [old.clone(), kept.clone(), fresh.clone()]
+        );
+
+        let expired = expire(&repo, store.as_ref(), 1_000).expect("expire");
+        assert_eq!(
+            (expired.removed, expired.promoted),
+            (vec![old.clone()], vec![kept.clone()])
+        );
+        assert!(!std::path::Path::new(&old.path).exists());
+        assert!(repo
+            .find_branch(&old.branch, git2::BranchType::Local)
+            .is_err());
+        assert!(repo.find_worktree("old").is_err());
+        assert!(std::path::Path::new(&kept.path).exists());
+        assert_eq!(list(&repo).unwrap(), vec![fresh.clone()]);
+        let names: Vec<String> = store
+            .list_worktrees()
+            .unwrap()
+            .into_iter()
+            .map(|worktree| worktree.name)
+            .collect();
+        assert!(!names.contains(&"old".to_string()) && names.contains(&"kept".to_string()));
+        for path in [&kept.path, &fresh.path] {
+            let _ = std::fs::remove_dir_all(path);
+        }
+    }
+}
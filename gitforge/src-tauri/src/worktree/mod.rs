@@ -0,0 +1,763 @@
+pub mod ephemeral;
+pub mod overlap;
+
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+use crate::auth::credentials::Credentials;
+use crate::revcache::RevCache;
+use crate::status::{StatusSummary, Untracked};
+
+/// Worktrees processed at once by [`status_all`] and [`sync_all`].
+pub const DEFAULT_PARALLELISM: usize = 4;
+pub const MAX_PARALLELISM: usize = 16;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "reason", rename_all = "snake_case")]
+pub enum PruneReason {
+    /// The worktree directory no longer exists on disk.
+    Missing,
+    /// The checked-out branch is fully contained in `into`.
+    Merged { branch: String, into: String },
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PruneEntry {
+    pub name: String,
+    pub path: String,
+    #[serde(flatten)]
+    pub reason: PruneReason,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SkippedWorktree {
+    pub name: String,
+    pub why: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PruneReport {
+    pub dry_run: bool,
+    pub pruned: Vec<PruneEntry>,
+    pub skipped: Vec<SkippedWorktree>,
+    /// Registry rows whose worktree no longer exists in git.
+    pub registry_rows: Vec<String>,
+}
+
+/// Removes worktrees whose branch is merged into `merged_into` (defaults to
+/// the main checkout's branch) or whose directory is gone, then drops stale
+/// rows from the sqlite registry. With `dry_run` nothing is touched.
+pub fn prune(
+    repo_path: &str,
+    merged_into: Option<&str>,
+    dry_run: bool,
+) -> Result<PruneReport, String> {
+    let repo =
+        git2::Repository::open(repo_path).map_err(|e| format!("failed to open repository: {e}"))?;
+
+    let target = match merged_into {
+        Some(branch) => Some(resolve_branch(&repo, branch)?),
+        None => repo
+            .head()
+            .ok()
+            .filter(|head| head.is_branch())
+            .and_then(|head| Some((head.shorthand()?.to_string(), head.target()?))),
+    };
+
+    let names = repo
+        .worktrees()
+        .map_err(|e| format!("failed to list worktrees: {e}"))?;
+
+    let mut report = PruneReport {
+        dry_run,
+        pruned: Vec::new(),
+        skipped: Vec::new(),
+        registry_rows: Vec::new(),
+    };
+
+    for name in names.iter().flatten() {
+        let worktree = repo
+            .find_worktree(name)
+            .map_err(|e| format!("failed to open worktree '{name}': {e}"))?;
+        let path = worktree.path().to_string_lossy().to_string();
+
+        if let Ok(git2::WorktreeLockStatus::Locked(reason)) = worktree.is_locked() {
+            report.skipped.push(SkippedWorktree {
+                name: name.to_string(),
+                why: format!("locked ({})", reason.unwrap_or_default()),
+            });
+            continue;
+        }
+
+        let reason = if !worktree.path().exists() {
+            PruneReason::Missing
+        } else {
+            match merged_reason(&worktree, target.as_ref())? {
+                Classification::Merged(reason) => reason,
+                Classification::Dirty => {
+                    report.skipped.push(SkippedWorktree {
+                        name: name.to_string(),
+                        why: "uncommitted changes".to_string(),
+                    });
+                    continue;
+                }
+                Classification::Keep => continue,
+            }
+        };
+
+        if !dry_run {
+            let mut opts = git2::WorktreePruneOptions::new();
+            opts.valid(true).working_tree(true);
+            worktree
+                .prune(Some(&mut opts))
+                .map_err(|e| format!("failed to prune worktree '{name}': {e}"))?;
+        }
+
+        report.pruned.push(PruneEntry {
+            name: name.to_string(),
+            path,
+            reason,
+        });
+    }
+
+    let pruned: Vec<&str> = report.pruned.iter().map(|e| e.name.as_str()).collect();
+    let live: Vec<&str> = names
+        .iter()
+        .flatten()
+        .filter(|name| !pruned.contains(name))
+        .collect();
+
+    let store = crate::store::open(repo_path)?;
+    let _lock = crate::db::lock::acquire(repo_path, "worktree prune", crate::db::BUSY_TIMEOUT)?;
+    report.registry_rows = store
+        .list_worktrees()?
+        .into_iter()
+        .map(|worktree| worktree.name)
+        .filter(|name| !live.contains(&name.as_str()))
+        .collect();
+
+    if !dry_run && !report.registry_rows.is_empty() {
+        for name in &report.registry_rows {
+            store.remove_worktree(name)?;
+        }
+        store
+            .compact()
+            .map_err(|e| format!("failed to compact registry: {e}"))?;
+    }
+
+    Ok(report)
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WorktreeStatus {
+    /// Directory name for the main checkout.
+    pub name: String,
+    pub path: String,
+    pub main: bool,
+    pub branch: Option<String>,
+    pub head: Option<String>,
+    pub upstream: Option<String>,
+    pub ahead: usize,
+    pub behind: usize,
+    pub summary: StatusSummary,
+    /// Set when this worktree couldn't be read; the other fields are empty.
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "outcome", rename_all = "snake_case")]
+pub enum SyncOutcome {
+    UpToDate,
+    FastForwarded { from: String, to: String },
+    Skipped { why: String },
+    Failed { error: String },
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WorktreeSync {
+    pub name: String,
+    pub path: String,
+    pub branch: Option<String>,
+    #[serde(flatten)]
+    pub outcome: SyncOutcome,
+}
+
+struct Checkout {
+    name: String,
+    path: PathBuf,
+    main: bool,
+}
+
+/// Status of the main checkout and every linked worktree, read
+/// `parallelism` at a time with one repository handle each.
+pub fn status_all(
+    repo_path: &str,
+    untracked: Untracked,
+    parallelism: usize,
+    revs: &RevCache,
+) -> Result<Vec<WorktreeStatus>, String> {
+    let checkouts = checkouts(repo_path)?;
+    Ok(parallel_map(&checkouts, parallelism, |checkout| {
+        let mut status = WorktreeStatus {
+            name: checkout.name.clone(),
+            path: checkout.path.to_string_lossy().to_string(),
+            main: checkout.main,
+            branch: None,
+            head: None,
+            upstream: None,
+            ahead: 0,
+            behind: 0,
+            summary: StatusSummary::default(),
+            error: None,
+        };
+        if let Err(e) = read_status(&checkout.path, untracked, revs, &mut status) {
+            status.error = Some(e);
+        }
+        status
+    }))
+}
+
+fn read_status(
+    path: &Path,
+    untracked: Untracked,
+    revs: &RevCache,
+    status: &mut WorktreeStatus,
+) -> Result<(), String> {
+    let repo = git2::Repository::open(path).map_err(|e| format!("failed to open worktree: {e}"))?;
+    if let Ok(head) = repo.head() {
+        status.head = head.target().map(|oid| oid.to_string());
+        if head.is_branch() {
+            status.branch = head.shorthand().map(str::to_string);
+        }
+    }
+    if let Some((upstream, tip, upstream_tip)) = upstream(&repo) {
+        let (ahead, behind) = revs.ahead_behind(&repo, tip, upstream_tip)?;
+        status.upstream = Some(upstream);
+        status.ahead = ahead;
+        status.behind = behind;
+    }
+    let mut opts = untracked.options();
+    let statuses = repo
+        .statuses(Some(&mut opts))
+        .map_err(|e| format!("failed to read worktree status: {e}"))?;
+    for entry in statuses.iter() {
+        status.summary.add(entry.status());
+    }
+    Ok(())
+}
+
+/// Fast-forwards every clean checkout whose branch is behind its upstream,
+/// `parallelism` at a time. With `fetch`, that remote is fetched once
+/// first; all worktrees share its refs.
+pub fn sync_all(
+    repo_path: &str,
+    fetch: Option<&str>,
+    parallelism: usize,
+) -> Result<Vec<WorktreeSync>, String> {
+    if let Some(remote) = fetch {
+        let repo = git2::Repository::open(repo_path)
+            .map_err(|e| format!("failed to open repository: {e}"))?;
+        let mut credentials = Credentials::for_remote(&repo, remote)?;
+        let mut callbacks = git2::RemoteCallbacks::new();
+        callbacks
+            .credentials(move |url, username, allowed| credentials.next(url, username, allowed));
+        let mut options = git2::FetchOptions::new();
+        options.remote_callbacks(callbacks);
+        repo.find_remote(remote)
+            .and_then(|mut handle| handle.fetch::<&str>(&[], Some(&mut options), None))
+            .map_err(|e| format!("failed to fetch {remote}: {e}"))?;
+    }
+
+    let checkouts = checkouts(repo_path)?;
+    Ok(parallel_map(&checkouts, parallelism, |checkout| {
+        let (branch, outcome) = match fast_forward(&checkout.path) {
+            Ok(result) => result,
+            Err(error) => (None, SyncOutcome::Failed { error }),
+        };
+        WorktreeSync {
+            name: checkout.name.clone(),
+            path: checkout.path.to_string_lossy().to_string(),
+            branch,
+            outcome,
+        }
+    }))
+}
+
+fn fast_forward(path: &Path) -> Result<(Option<String>, SyncOutcome), String> {
+    let repo = git2::Repository::open(path).map_err(|e| format!("failed to open worktree: {e}"))?;
+    let head = repo
+        .head()
+        .map_err(|e| format!("failed to read HEAD: {e}"))?;
+    if !head.is_branch() {
+        let why = "detached HEAD".to_string();
+        return Ok((None, SyncOutcome::Skipped { why }));
+    }
+    let branch = head.shorthand().map(str::to_string);
+    let Some((upstream, tip, upstream_tip)) = upstream(&repo) else {
+        let why = "no upstream".to_string();
+        return Ok((branch, SyncOutcome::Skipped { why }));
+    };
+
+    if tip == upstream_tip
+        || repo
+            .graph_descendant_of(tip, upstream_tip)
+            .map_err(|e| format!("failed to compare with {upstream}: {e}"))?
+    {
+        return Ok((branch, SyncOutcome::UpToDate));
+    }
+    if !repo
+        .graph_descendant_of(upstream_tip, tip)
+        .map_err(|e| format!("failed to compare with {upstream}: {e}"))?
+    {
+        let why = format!("diverged from {upstream}");
+        return Ok((branch, SyncOutcome::Skipped { why }));
+    }
+    if is_dirty(&repo)? {
+        let why = "uncommitted changes".to_string();
+        return Ok((branch, SyncOutcome::Skipped { why }));
+    }
+
+    let target = repo
+        .find_commit(upstream_tip)
+        .map_err(|e| format!("failed to read {upstream}: {e}"))?;
+    repo.checkout_tree(
+        target.as_object(),
+        Some(git2::build::CheckoutBuilder::new().safe()),
+    )
+    .map_err(|e| format!("failed to check out {upstream}: {e}"))?;
+    let refname = head.name().unwrap_or("HEAD").to_string();
+    repo.reference(
+        &refname,
+        upstream_tip,
+        true,
+        &format!("sync: fast-forward to {upstream}"),
+    )
+    .map_err(|e| format!("failed to update {refname}: {e}"))?;
+
+    Ok((
+        branch,
+        SyncOutcome::FastForwarded {
+            from: tip.to_string(),
+            to: upstream_tip.to_string(),
+        },
+    ))
+}
+
+/// Where the checkout [`status_all`] names `name` is: the main checkout
+/// or a linked worktree.
+pub fn locate(repo_path: &str, name: &str) -> Result<PathBuf, String> {
+    checkouts(repo_path)?
+        .into_iter()
+        .find(|checkout| checkout.name == name)
+        .map(|checkout| checkout.path)
+        .ok_or_else(|| format!("unknown worktree '{name}'"))
+}
+
+/// Diffs the files in two checkouts, named as [`status_all`] names them,
+/// uncommitted changes and untracked files included: each side is what
+/// committing everything in it would record. Neither index is written.
+pub fn diff(
+    repo_path: &str,
+    base: &str,
+    target: &str,
+    paths: &[String],
+    context: u32,
+    budget: usize,
+) -> Result<crate::diff::Diff, String> {
+    let checkouts = checkouts(repo_path)?;
+    let snapshot = |name: &str| {
+        let checkout = checkouts
+            .iter()
+            .find(|checkout| checkout.name == name)
+            .ok_or_else(|| format!("unknown worktree '{name}'"))?;
+        snapshot(&checkout.path).map(|tree| tree.to_string())
+    };
+    let (base_tree, target_tree) = (snapshot(base)?, snapshot(target)?);
+    let repo =
+        git2::Repository::open(repo_path).map_err(|e| format!("failed to open repository: {e}"))?;
+    let query = crate::diff::Query {
+        base: &base_tree,
+        target: &target_tree,
+        paths,
+        context,
+        untracked: false,
+    };
+    let mut diff = crate::diff::diff(&repo, &query, budget)?;
+    diff.base = base.to_string();
+    diff.target = target.to_string();
+    Ok(diff)
+}
+
+/// The tree of everything in the checkout at `path` but ignored files.
+/// Worktrees share one object database, so the other checkouts can read
+/// it; nothing refers to it, so `git gc` drops it later.
+fn snapshot(path: &Path) -> Result<git2::Oid, String> {
+    let repo = git2::Repository::open(path)
+        .map_err(|e| format!("failed to open {}: {e}", path.display()))?;
+    let mut index = repo
+        .index()
+        .map_err(|e| format!("failed to read the index of {}: {e}", path.display()))?;
+    if index.has_conflicts() {
+        return Err(format!("{} has unresolved conflicts", path.display()));
+    }
+    // Linked worktrees checked out inside this one are repositories of
+    // their own, not content.
+    let mut skip_nested =
+        |entry: &Path, _: &[u8]| -> i32 { i32::from(path.join(entry).join(".git").exists()) };
+    index
+        .add_all(["*"], git2::IndexAddOption::DEFAULT, Some(&mut skip_nested))
+        .and_then(|()| index.update_all(["*"], None))
+        .and_then(|()| index.write_tree())
+        .map_err(|e| format!("failed to snapshot {}: {e}", path.display()))
+}
+
+/// `(upstream shorthand, HEAD tip, upstream tip)` when HEAD is a branch
+/// tracking something.
+fn upstream(repo: &git2::Repository) -> Option<(String, git2::Oid, git2::Oid)> {
+    let head = repo.head().ok().filter(|head| head.is_branch())?;
+    let tip = head.target()?;
+    let branch = git2::Branch::wrap(head);
+    let upstream = branch.upstream().ok()?;
+    let name = upstream.name().ok().flatten()?.to_string();
+    Some((name, tip, upstream.get().target()?))
+}
+
+/// The main checkout (unless bare) followed by linked worktrees.
+fn checkouts(repo_path: &str) -> Result<Vec<Checkout>, String> {
+    let repo =
+        git2::Repository::open(repo_path).map_err(|e| format!("failed to open repository: {e}"))?;
+    let mut checkouts = Vec::new();
+    if let Some(workdir) = repo.workdir() {
+        let name = workdir
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+        checkouts.push(Checkout {
+            name,
+            path: workdir.to_path_buf(),
+            main: true,
+        });
+    }
+    let names = repo
+        .worktrees()
+        .map_err(|e| format!("failed to list worktrees: {e}"))?;
+    for name in names.iter().flatten() {
+        let worktree = repo
+            .find_worktree(name)
+            .map_err(|e| format!("failed to open worktree '{name}': {e}"))?;
+        checkouts.push(Checkout {
+            name: name.to_string(),
+            path: worktree.path().to_path_buf(),
+            main: false,
+        });
+    }
+    Ok(checkouts)
+}
+
+/// `f` over `items` on at most `parallelism` (up to [`MAX_PARALLELISM`])
+/// threads, results in input order.
+pub(crate) fn parallel_map<T, R, F>(items: &[T], parallelism: usize, f: F) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> R + Sync,
+{
+    let next = AtomicUsize::new(0);
+    let results: Mutex<Vec<Option<R>>> = Mutex::new(items.iter().map(|_| None).collect());
+    std::thread::scope(|scope| {
+        let threads = parallelism.clamp(1, MAX_PARALLELISM).min(items.len());
+        for _ in 0..threads {
+            scope.spawn(|| loop {
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                let Some(item) = items.get(i) else {
+                    break;
+                };
+                let result = f(item);
+                results.lock().unwrap_or_else(|e| e.into_inner())[i] = Some(result);
+            });
+        }
+    });
+    results
+        .into_inner()
+        .unwrap_or_else(|e| e.into_inner())
+        .into_iter()
+        .map(|result| result.expect("every item is processed"))
+        .collect()
+}
+
+enum Classification {
+    Merged(PruneReason),
+    Dirty,
+    Keep,
+}
+
+fn merged_reason(
+    worktree: &git2::Worktree,
+    target: Option<&(String, git2::Oid)>,
+) -> Result<Classification, String> {
+    let Some((into, target_oid)) = target else {
+        return Ok(Classification::Keep);
+    };
+
+    let wt_repo = git2::Repository::open_from_worktree(worktree)
+        .map_err(|e| format!("failed to open worktree repository: {e}"))?;
+    let Ok(head) = wt_repo.head() else {
+        return Ok(Classification::Keep);
+    };
+    let (Some(branch), Some(tip)) = (head.shorthand(), head.target()) else {
+        return Ok(Classification::Keep);
+    };
+    if !head.is_branch() || branch == into {
+        return Ok(Classification::Keep);
+    }
+
+    // A branch still pointing at the target is indistinguishable from a
+    // freshly created one, so only strict ancestors count as merged.
+    let merged = tip != *target_oid
+        && wt_repo
+            .graph_descendant_of(*target_oid, tip)
+            .map_err(|e| format!("failed to compare '{branch}' with '{into}': {e}"))?;
+    if !merged {
+        return Ok(Classification::Keep);
+    }
+
+    if is_dirty(&wt_repo)? {
+        return Ok(Classification::Dirty);
+    }
+
+    Ok(Classification::Merged(PruneReason::Merged {
+        branch: branch.to_string(),
+        into: into.clone(),
+    }))
+}
+
+fn is_dirty(repo: &git2::Repository) -> Result<bool, String> {
+    let mut opts = git2::StatusOptions::new();
+    opts.include_untracked(true).include_ignored(false);
+    let statuses = repo
+        .statuses(Some(&mut opts))
+        .map_err(|e| format!("failed to read worktree status: {e}"))?;
+    Ok(!statuses.is_empty())
+}
+
+fn resolve_branch(repo: &git2::Repository, branch: &str) -> Result<(String, git2::Oid), String> {
+    let reference = repo
+        .find_branch(branch, git2::BranchType::Local)
+        .map_err(|e| format!("unknown branch '{branch}': {e}"))?;
+    let oid = reference
+        .get()
+        .target()
+        .ok_or_else(|| format!("branch '{branch}' has no target"))?;
+    Ok((branch.to_string(), oid))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{init_repo, TempDir};
+    use std::fs;
+
+    fn commit_file(repo: &git2::Repository, name: &str, content: &str) -> git2::Oid {
+        let workdir = repo.workdir().expect("workdir");
+        fs::write(workdir.join(name), content).expect("write file");
+        let mut index = repo.index().expect("index");
+        index.add_path(Path::new(name)).expect("stage file");
+        index.write().expect("write index");
+        let tree = repo
+            .find_tree(index.write_tree().expect("write tree"))
+            .expect("find tree");
+        let sig = git2::Signature::now("GitForge Test", "test@gitforge.dev").expect("signature");
+        let parents: Vec<git2::Commit> = repo
+            .head()
+            .ok()
+            .and_then(|h| h.peel_to_commit().ok())
+            .into_iter()
+            .collect();
+        let parents: Vec<&git2::Commit> = parents.iter().collect();
+        repo.commit(Some("HEAD"), &sig, &sig, name, &tree, &parents)
+            .expect("commit")
+    }
+
+    fn repo_with_worktree(label: &str, branch: &str) -> (TempDir, PathBuf, git2::Repository) {
+        let (dir, repo) = init_repo(&format!("prune-{label}"));
+        commit_file(&repo, "README.md", "hello\n");
+
+        let wt_path = dir.join(".worktrees").join("wt");
+        {
+            let head = repo.head().expect("head").peel_to_commit().expect("commit");
+            let branch_ref = repo.branch(branch, &head, false).expect("branch");
+            fs::create_dir_all(wt_path.parent().expect("parent")).expect("create parent");
+            let mut opts = git2::WorktreeAddOptions::new();
+            opts.reference(Some(branch_ref.get()));
+            repo.worktree("wt", &wt_path, Some(&opts))
+                .expect("add worktree");
+        }
+
+        let db = crate::db::open(dir.to_str().expect("utf8 path")).expect("open db");
+        db.execute(
+            "INSERT INTO worktrees (name, path, branch) VALUES ('wt', ?1, ?2)",
+            rusqlite::params![wt_path.to_string_lossy(), branch],
+        )
+        .expect("register worktree");
+
+        (dir, wt_path, repo)
+    }
+
+    #[test]
+    fn status_all_reads_every_checkout() {
+        let (dir, wt_path, _repo) = repo_with_worktree("status-all", "feature/status");
+        fs::write(wt_path.join("scratch.txt"), "new").expect("write");
+        fs::write(wt_path.join("README.md"), "edited\n").expect("edit");
+
+        let statuses = status_all(
+            dir.to_str().expect("utf8 path"),
+            Untracked::All,
+            2,
+            &RevCache::default(),
+        )
+        .expect("status all");
+        assert_eq!(statuses.len(), 2);
+        assert!(statuses[0].main && statuses[0].error.is_none());
+        let wt = &statuses[1];
+        assert_eq!(wt.name, "wt");
+        assert_eq!(wt.branch.as_deref(), Some("feature/status"));
+        assert_eq!((wt.summary.untracked, wt.summary.modified), (1, 1));
+        assert!(wt.error.is_none());
+    }
+
+    #[test]
+    fn diff_compares_checkouts_with_their_uncommitted_files() {
+        let (dir, wt_path, _repo) = repo_with_worktree("diff", "feature/diff");
+        fs::write(dir.join("main.txt"), "only in main\n").expect("write");
+        fs::write(wt_path.join("README.md"), "hello\nagain\n").expect("edit");
+        fs::write(wt_path.join("scratch.txt"), "new\n").expect("write");
+
+        let main = dir.file_name().unwrap().to_string_lossy().to_string();
+        let repo_path = dir.to_str().expect("utf8 path");
+        let diff = diff(repo_path, &main, "wt", &[], 3, usize::MAX).expect("diff");
+        let files: Vec<(&str, &str)> = diff
+            .files
+            .iter()
+            .map(|file| (file.path.as_str(), file.status))
+            .collect();
+        assert_eq!(
+            files,
+            [
+                ("README.md", "modified"),
+                ("main.txt", "deleted"),
+                ("scratch.txt", "added")
+            ]
+        );
+        assert_eq!(
+            (diff.base.as_str(), diff.target.as_str()),
+            (main.as_str(), "wt")
+        );
+        assert!(diff.patch.contains("+again\n"));
+        // Nothing was staged along the way.
+        let wt = git2::Repository::open(&wt_path).expect("open worktree");
+        assert!(wt
+            .index()
+            .unwrap()
+            .get_path(Path::new("scratch.txt"), 0)
+            .is_none());
+        assert!(super::diff(repo_path, "nope", "wt", &[], 3, usize::MAX).is_err());
+    }
+
+    #[test]
+    fn sync_all_fast_forwards_clean_worktrees_behind_upstream() {
+        let (dir, wt_path, repo) = repo_with_worktree("sync-all", "feature/sync");
+        let main = repo
+            .head()
+            .expect("head")
+            .shorthand()
+            .expect("name")
+            .to_string();
+        repo.find_branch("feature/sync", git2::BranchType::Local)
+            .expect("branch")
+            .set_upstream(Some(&main))
+            .expect("track main");
+        let tip = commit_file(&repo, "next.txt", "main moves on\n");
+        let repo_path = dir.to_str().expect("utf8 path");
+
+        fs::write(wt_path.join("README.md"), "dirty\n").expect("dirty");
+        let synced = sync_all(repo_path, None, 2).expect("sync");
+        assert!(matches!(
+            &synced[0].outcome,
+            SyncOutcome::Skipped { why } if why == "no upstream"
+        ));
+        assert!(matches!(
+            &synced[1].outcome,
+            SyncOutcome::Skipped { why } if why == "uncommitted changes"
+        ));
+
+        fs::write(wt_path.join("README.md"), "hello\n").expect("clean");
+        let synced = sync_all(repo_path, None, 2).expect("sync");
+        assert_eq!(
+            synced[1].outcome,
+            SyncOutcome::FastForwarded {
+                from: repo
+                    .find_commit(tip)
+                    .expect("tip")
+                    .parent_id(0)
+                    .expect("parent")
+                    .to_string(),
+                to: tip.to_string(),
+            }
+        );
+        assert!(wt_path.join("next.txt").exists());
+        assert_eq!(
+            sync_all(repo_path, None, 1).expect("sync")[1].outcome,
+            SyncOutcome::UpToDate
+        );
+    }
+
+    #[test]
+    fn missing_worktree_is_reported_in_dry_run_and_pruned_for_real() {
+        let (dir, wt_path, repo) = repo_with_worktree("missing", "feature/gone");
+        fs::remove_dir_all(&wt_path).expect("remove worktree dir");
+        let repo_path = dir.to_str().expect("utf8 path");
+
+        let report = prune(repo_path, None, true).expect("dry run");
+        assert_eq!(report.pruned.len(), 1);
+        assert_eq!(report.pruned[0].reason, PruneReason::Missing);
+        assert_eq!(report.registry_rows, vec!["wt".to_string()]);
+        assert!(repo.find_worktree("wt").is_ok());
+
+        prune(repo_path, None, false).expect("prune");
+        assert!(repo.find_worktree("wt").is_err());
+
+        let db = crate::db::open(repo_path).expect("open db");
+        let count: i64 = db
+            .query_row("SELECT COUNT(*) FROM worktrees", [], |row| row.get(0))
+            .expect("count rows");
+        assert_eq!(count, 0);
+    }
+
+    #[test]
+    fn merged_worktree_is_removed_and_fresh_one_is_kept() {
+        let (dir, wt_path, repo) = repo_with_worktree("merged", "feature/done");
+        let repo_path = dir.to_str().expect("utf8 path");
+
+        let report = prune(repo_path, None, true).expect("dry run");
+        assert!(report.pruned.is_empty(), "fresh branch must be kept");
+
+        let wt_repo = git2::Repository::open(&wt_path).expect("open worktree");
+        let tip = commit_file(&wt_repo, "feature.txt", "work\n");
+        let main = repo.head().expect("head").name().expect("name").to_string();
+        repo.reference(&main, tip, true, "fast-forward")
+            .expect("fast-forward main");
+        repo.checkout_head(Some(git2::build::CheckoutBuilder::new().force()))
+            .expect("checkout main");
+        commit_file(&repo, "next.txt", "main moves on\n");
+
+        let report = prune(repo_path, None, false).expect("prune");
+        assert_eq!(report.pruned.len(), 1);
+        assert!(matches!(
+            &report.pruned[0].reason,
+            PruneReason::Merged { branch, .. } if branch == "feature/done"
+        ));
+        assert!(!wt_path.exists());
+    }
+}
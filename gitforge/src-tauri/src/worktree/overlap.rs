@@ -0,0 +1,203 @@
+//! Early warning for conflicts between work in progress: two checkouts
+//! whose changes since their branches forked touch the same files, found
+//! before either branch has a PR.
+
+use ant_core::SystemEvent;
+use serde::Serialize;
+use std::collections::BTreeSet;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Overlap {
+    /// Checkout names as [`super::status_all`] names them.
+    pub worktrees: [String; 2],
+    pub branches: [String; 2],
+    /// Files both sides changed, sorted.
+    pub paths: Vec<String>,
+}
+
+impl Overlap {
+    pub fn event(&self) -> SystemEvent {
+        SystemEvent::PotentialConflict {
+            worktrees: self.worktrees.to_vec(),
+            branches: self.branches.to_vec(),
+            paths: self.paths.clone(),
+        }
+    }
+}
+
+struct Work {
+    name: String,
+    branch: String,
+    head: git2::Oid,
+    /// Everything in the checkout, uncommitted changes included.
+    tree: git2::Oid,
+}
+
+/// Every pair of checkouts whose files, uncommitted and untracked ones
+/// included, differ from their branches' merge base in the same paths.
+/// Detached and unborn checkouts are left out, and so are ones with
+/// unresolved conflicts, which already know.
+pub fn scan(repo_path: &str) -> Result<Vec<Overlap>, String> {
+    let mut checkouts = super::checkouts(repo_path)?;
+    // The main checkout first, then linked ones by name.
+    checkouts.sort_by(|a, b| (!a.main, &a.name).cmp(&(!b.main, &b.name)));
+    let mut work = Vec::new();
+    for checkout in checkouts {
+        let Ok(repo) = git2::Repository::open(&checkout.path) else {
+            // Deleted from disk; worktree prune reports those.
+            continue;
+        };
+        let Some((branch, head)) = repo
+            .head()
+            .ok()
+            .filter(|head| head.is_branch())
+            .and_then(|head| Some((head.shorthand()?.to_string(), head.target()?)))
+        else {
+            continue;
+        };
+        let Ok(tree) = super::snapshot(&checkout.path) else {
+            continue;
+        };
+        work.push(Work {
+            name: checkout.name,
+            branch,
+            head,
+            tree,
+        });
+    }
+
+    let repo =
+        git2::Repository::open(repo_path).map_err(|e| format!("failed to open repository: {e}"))?;
+    let mut overlaps = Vec::new();
+    for (i, a) in work.iter().enumerate() {
+        for b in &work[i + 1..] {
+            let base = match repo.merge_base(a.head, b.head) {
+                Ok(base) => base,
+                Err(e) if e.code() == git2::ErrorCode::NotFound => continue,
+                Err(e) => {
+                    return Err(format!(
+                        "failed to find where {} and {} forked: {e}",
+                        a.branch, b.branch
+                    ))
+                }
+            };
+            let base = repo
+                .find_commit(base)
+                .map(|commit| commit.tree_id())
+                .map_err(|e| format!("failed to read {base}: {e}"))?;
+            let ours = changed(&repo, base, a.tree)?;
+            let paths: Vec<String> = changed(&repo, base, b.tree)?
+                .intersection(&ours)
+                .cloned()
+                .collect();
+            if paths.is_empty() {
+                continue;
+            }
+            overlaps.push(Overlap {
+                worktrees: [a.name.clone(), b.name.clone()],
+                branches: [a.branch.clone(), b.branch.clone()],
+                paths,
+            });
+        }
+    }
+    Ok(overlaps)
+}
+
+/// Paths that differ between two trees; both sides of a rename count.
+fn changed(
+    repo: &git2::Repository,
+    from: git2::Oid,
+    to: git2::Oid,
+) -> Result<BTreeSet<String>, String> {
+    let tree = |oid| {
+        repo.find_tree(oid)
+            .map_err(|e| format!("failed to read tree {oid}: {e}"))
+    };
+    let diff = repo
+        .diff_tree_to_tree(Some(&tree(from)?), Some(&tree(to)?), None)
+        .map_err(|e| format!("failed to diff {from}..{to}: {e}"))?;
+    Ok(diff
+        .deltas()
+        .flat_map(|delta| [delta.old_file().path(), delta.new_file().path()])
+        .flatten()
+        .map(|path| path.to_string_lossy().to_string())
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::path::Path;
+
+    #[test]
+    fn finds_files_changed_in_two_checkouts_committed_or_not() {
+        let (dir, repo) = crate::test_support::init_repo("overlap");
+        let sig = git2::Signature::now("GitForge Test", "test@gitforge.dev").expect("sig");
+        let commit = |repo: &git2::Repository, path: &str, text: &str| {
+            let workdir = repo.workdir().unwrap();
+            fs::write(workdir.join(path), text).expect("write");
+            let mut index = repo.index().expect("index");
+            index.add_path(Path::new(path)).expect("add");
+            index.write().expect("write index");
+            let tree = repo
+                .find_tree(index.write_tree().expect("tree"))
+                .expect("tree");
+            let parent = repo.head().ok().and_then(|head| head.peel_to_commit().ok());
+            let parents: Vec<&git2::Commit> = parent.iter().collect();
+            repo.commit(Some("HEAD"), &sig, &sig, path, &tree, &parents)
+                .expect("commit");
+        };
+        commit(&repo, "README.md", "hello\n");
+        commit(&repo, "lib.rs", "fn main() {}\n");
+        let add = |name: &str| {
+            let head = repo.head().unwrap().peel_to_commit().unwrap();
+            let branch = repo
+                .branch(&format!("feature/{name}"), &head, false)
+                .expect("branch");
+            let path = dir.with_file_name(format!("{}-{name}", dir.display()));
+            let mut opts = git2::WorktreeAddOptions::new();
+            opts.reference(Some(branch.get()));
+            repo.worktree(name, &path, Some(&opts)).expect("worktree");
+            git2::Repository::open(&path).expect("open worktree")
+        };
+        let (a, b, c) = (add("a"), add("b"), add("c"));
+
+        commit(&a, "README.md", "hello from a\n");
+        fs::write(a.workdir().unwrap().join("notes.txt"), "a\n").expect("write");
+        fs::write(b.workdir().unwrap().join("README.md"), "hello from b\n").expect("edit");
+        fs::write(b.workdir().unwrap().join("notes.txt"), "b\n").expect("write");
+        fs::write(b.workdir().unwrap().join("only-b.txt"), "b\n").expect("write");
+        fs::write(c.workdir().unwrap().join("lib.rs"), "fn main() { c }\n").expect("edit");
+        // Commits on the main checkout's branch since c forked count too.
+        commit(&repo, "lib.rs", "fn main() { main }\n");
+
+        let overlaps = scan(dir.to_str().expect("utf8 path")).expect("scan");
+        let main = dir.file_name().unwrap().to_string_lossy().to_string();
+        assert_eq!(
+            overlaps,
+            [
+                Overlap {
+                    worktrees: [main.clone(), "c".to_string()],
+                    branches: [
+                        repo.head().unwrap().shorthand().unwrap().to_string(),
+                        "feature/c".to_string()
+                    ],
+                    paths: vec!["lib.rs".to_string()],
+                },
+                Overlap {
+                    worktrees: ["a".to_string(), "b".to_string()],
+                    branches: ["feature/a".to_string(), "feature/b".to_string()],
+                    paths: vec!["README.md".to_string(), "notes.txt".to_string()],
+                },
+            ]
+        );
+        assert!(matches!(
+            overlaps[1].event(),
+            SystemEvent::PotentialConflict { paths, .. } if paths.len() == 2
+        ));
+        for path in ["-a", "-b", "-c"] {
+            let _ = fs::remove_dir_all(format!("{dir}{path}"));
+        }
+    }
+}
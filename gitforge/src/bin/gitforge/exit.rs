@@ -0,0 +1,149 @@
+use std::fmt;
+
+/// How errors are written to stderr.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum ErrorFormat {
+    #[default]
+    Human,
+    Json,
+}
+
+/// Failure categories with stable exit codes. Scripts may rely on these
+/// values, so existing codes must never be renumbered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    General,
+    Usage,
+    Git,
+    Db,
+    Network,
+    Io,
+    InvalidData,
+}
+
+impl ErrorKind {
+    pub const fn code(self) -> u8 {
+        match self {
+            ErrorKind::General => 1,
+            ErrorKind::Usage => 2,
+            ErrorKind::Git => 3,
+            ErrorKind::Db => 4,
+            ErrorKind::Network => 5,
+            ErrorKind::Io => 6,
+            ErrorKind::InvalidData => 7,
+        }
+    }
+
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            ErrorKind::General => "general",
+            ErrorKind::Usage => "usage",
+            ErrorKind::Git => "git",
+            ErrorKind::Db => "db",
+            ErrorKind::Network => "network",
+            ErrorKind::Io => "io",
+            ErrorKind::InvalidData => "invalid_data",
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct CliError {
+    pub kind: ErrorKind,
+    pub message: String,
+}
+
+impl CliError {
+    pub fn new(kind: ErrorKind, message: impl fmt::Display) -> Self {
+        Self {
+            kind,
+            message: message.to_string(),
+        }
+    }
+
+    pub fn usage(message: impl fmt::Display) -> Self {
+        Self::new(ErrorKind::Usage, message)
+    }
+
+    pub fn git(message: impl fmt::Display) -> Self {
+        Self::new(ErrorKind::Git, message)
+    }
+
+    pub fn db(message: impl fmt::Display) -> Self {
+        Self::new(ErrorKind::Db, message)
+    }
+
+    pub fn network(message: impl fmt::Display) -> Self {
+        Self::new(ErrorKind::Network, message)
+    }
+
+    pub fn io(message: impl fmt::Display) -> Self {
+        Self::new(ErrorKind::Io, message)
+    }
+
+    pub fn data(message: impl fmt::Display) -> Self {
+        Self::new(ErrorKind::InvalidData, message)
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "error": {
+                "kind": self.kind.as_str(),
+                "code": self.kind.code(),
+                "message": self.message
+            }
+        })
+    }
+
+    pub fn report(&self, format: ErrorFormat) {
+        match format {
+            ErrorFormat::Human => eprintln!("gitforge: {} error: {}", self.kind.as_str(), self),
+            ErrorFormat::Json => eprintln!("{}", self.to_json()),
+        }
+    }
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl From<serde_json::Error> for CliError {
+    fn from(err: serde_json::Error) -> Self {
+        Self::data(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exit_codes_are_stable() {
+        let codes: Vec<u8> = [
+            ErrorKind::General,
+            ErrorKind::Usage,
+            ErrorKind::Git,
+            ErrorKind::Db,
+            ErrorKind::Network,
+            ErrorKind::Io,
+            ErrorKind::InvalidData,
+        ]
+        .iter()
+        .map(|kind| kind.code())
+        .collect();
+
+        assert_eq!(codes, vec![1, 2, 3, 4, 5, 6, 7]);
+    }
+
+    #[test]
+    fn json_report_carries_kind_and_code() {
+        let err = CliError::db("database is locked");
+        let json = err.to_json();
+
+        assert_eq!(json["error"]["kind"], "db");
+        assert_eq!(json["error"]["code"], 4);
+        assert_eq!(json["error"]["message"], "database is locked");
+    }
+}
@@ -0,0 +1,909 @@
+mod exit;
+
+use clap::{Parser, Subcommand};
+use exit::{CliError, ErrorFormat, ErrorKind};
+use gitforge::agent::voice::{AudioClip, CommandStt, SpeechToText};
+use gitforge::agent::BpgtAgent;
+use gitforge::auth::{self, github, Forge, TokenSource};
+use gitforge::commit_lint;
+use gitforge::db;
+use gitforge::mcp::access::{self, Options, Tokens};
+use gitforge::mcp::server::GitForgeMcp;
+use gitforge::precommit;
+use gitforge::release::{self, Bump};
+use gitforge::scaffold;
+use gitforge::smart_http::{self, SmartHttp};
+use gitforge::store;
+use gitforge::watcher::{RepoEvent, RepoWatcher, StatusTracker};
+use gitforge::worktree::{self, PruneReason};
+use std::process::ExitCode;
+use std::time::Duration;
+
+#[derive(Parser)]
+#[command(name = "gitforge", about = "🔨 Forge your Git workflow")]
+struct Cli {
+    /// Format used for errors written to stderr
+    #[arg(long, global = true, value_enum, default_value_t = ErrorFormat::Human)]
+    error_format: ErrorFormat,
+
+    #[command(subcommand)]
+    command: Option<Commands>,
+}
+
+#[derive(Subcommand)]
+enum Commands {
+    /// 🎨 Launch the desktop UI (Monaco + 5 columns)
+    Ui,
+
+    /// 🤖 MCP server for Claude/Cursor/GPT
+    #[command(name = "mcp-serve")]
+    McpServe {
+        /// Repository path
+        #[arg(default_value = ".")]
+        repo: String,
+        /// Address to listen on
+        #[arg(long, default_value = "127.0.0.1:6767")]
+        bind: String,
+        /// Refuse every tool that can change the repository
+        #[arg(long)]
+        read_only: bool,
+        /// Environment variable holding the client tokens
+        #[arg(long, default_value = access::TOKENS_ENV)]
+        token_env: String,
+    },
+
+    /// 🧠 Local BPGT agent
+    Agent {
+        #[arg(default_value = ".")]
+        repo: String,
+        #[command(subcommand)]
+        action: Option<AgentAction>,
+    },
+
+    /// 🌳 Git worktree helper CLI
+    Worktree {
+        #[arg(value_enum)]
+        action: WorktreeAction,
+        name: Option<String>,
+        #[arg(long, default_value = ".")]
+        repo: String,
+        /// prune: only report what would be removed
+        #[arg(long)]
+        dry_run: bool,
+        /// prune: branch that merged worktrees are compared against (default: HEAD)
+        #[arg(long)]
+        merged_into: Option<String>,
+    },
+
+    /// 📱 Embedded browser
+    Browser { url: String },
+
+    /// 📦 Export gitforge.db metadata to a portable JSON file
+    Export {
+        /// Destination file
+        output: String,
+        #[arg(long, default_value = ".")]
+        repo: String,
+    },
+
+    /// 📥 Import metadata from a JSON file produced by `gitforge export`
+    Import {
+        /// Source file
+        input: String,
+        #[arg(long, default_value = ".")]
+        repo: String,
+    },
+
+    /// 🗄️ Metadata database housekeeping
+    Db {
+        #[command(subcommand)]
+        action: DbAction,
+    },
+
+    /// 🔑 Sign in to a forge; the token is kept in the OS keyring
+    Auth {
+        #[command(subcommand)]
+        action: AuthAction,
+    },
+
+    /// 👀 Watch the repository and print changes as they happen
+    Watch {
+        #[arg(default_value = ".")]
+        repo: String,
+        /// Emit one JSON object per line instead of human-readable output
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// 🏷️ Tag the next release from Conventional Commits and write its notes
+    Release {
+        #[arg(default_value = ".")]
+        repo: String,
+        /// Override the bump computed from the commits
+        #[arg(long, value_enum)]
+        bump: Option<BumpArg>,
+        /// Tag prefix
+        #[arg(long, default_value = release::DEFAULT_PREFIX)]
+        prefix: String,
+        /// Print the version and notes without tagging
+        #[arg(long)]
+        dry_run: bool,
+        /// Also create a GitHub release for the remote
+        #[arg(long)]
+        publish: bool,
+        #[arg(long, default_value = "origin")]
+        remote: String,
+    },
+
+    /// 🌐 Serve the repository over git's smart HTTP protocol
+    #[command(name = "serve-http")]
+    ServeHttp {
+        #[arg(default_value = ".")]
+        repo: String,
+        /// Address to listen on
+        #[arg(long, default_value = smart_http::DEFAULT_BIND)]
+        bind: String,
+        /// Accept pushes from clients presenting the token
+        #[arg(long)]
+        allow_push: bool,
+        /// Require the token for clones and fetches too
+        #[arg(long)]
+        require_auth: bool,
+        /// Environment variable holding the token
+        #[arg(long, default_value = smart_http::TOKEN_ENV)]
+        token_env: String,
+    },
+
+    /// ✅ Pre-commit checks and commit message lint from .gitforge/
+    Checks {
+        #[command(subcommand)]
+        action: ChecksAction,
+    },
+
+    /// 🧱 Create a project from a template directory or git URL
+    New {
+        /// Template directory or git URL
+        template: String,
+        /// Directory to create; must not exist or be empty
+        path: String,
+        /// Template variable, e.g. --var license=MIT (repeatable)
+        #[arg(long = "var", value_name = "NAME=VALUE", value_parser = parse_var)]
+        vars: Vec<(String, String)>,
+        /// Initial branch
+        #[arg(long, default_value = scaffold::DEFAULT_BRANCH)]
+        branch: String,
+        /// Initial commit message
+        #[arg(long)]
+        message: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum AgentAction {
+    /// 🎙️ Talk to the agent through the default microphone
+    Voice {
+        #[arg(long, value_enum, default_value_t = VoiceMode::PushToTalk)]
+        mode: VoiceMode,
+        /// Local speech-to-text command; `{wav}` is replaced with the recording
+        /// (falls back to $GITFORGE_STT_COMMAND)
+        #[arg(long)]
+        stt_command: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum DbAction {
+    /// 🧽 Prune old events per the retention policy and compact the store
+    Maintain {
+        #[arg(long, default_value = ".")]
+        repo: String,
+        /// Only report what would be pruned
+        #[arg(long)]
+        dry_run: bool,
+        /// Drop events older than this many days (0 keeps all; default from
+        /// gitforge.retention.eventDays or 90)
+        #[arg(long)]
+        event_days: Option<u32>,
+        /// Keep at most this many events (0 keeps all; default from
+        /// gitforge.retention.maxEvents or 100000)
+        #[arg(long)]
+        max_events: Option<usize>,
+        /// Print the report as JSON
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum ChecksAction {
+    /// ▶️ Run every check against the staged changes
+    Run {
+        #[arg(long, default_value = ".")]
+        repo: String,
+        /// Print the report as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// 📝 Lint a commit message file against .gitforge/commit-lint.toml
+    LintMsg {
+        /// The message file, as git passes it to commit-msg hooks
+        file: String,
+        #[arg(long, default_value = ".")]
+        repo: String,
+    },
+    /// 🪝 Install pre-commit and commit-msg hooks that run the checks and lint
+    InstallHooks {
+        #[arg(long, default_value = ".")]
+        repo: String,
+        /// Replace hooks gitforge didn't write
+        #[arg(long)]
+        force: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum AuthAction {
+    /// 🔑 Authorize this machine with a device code
+    Login {
+        #[arg(value_enum)]
+        forge: ForgeArg,
+        /// OAuth app client id (falls back to $GITFORGE_GITHUB_CLIENT_ID, then
+        /// git config gitforge.github.clientId)
+        #[arg(long)]
+        client_id: Option<String>,
+        #[arg(long, default_value = github::DEFAULT_SCOPE)]
+        scope: String,
+        /// Repository whose git config is consulted for the client id
+        #[arg(long, default_value = ".")]
+        repo: String,
+    },
+    /// 🚪 Remove the stored token
+    Logout {
+        #[arg(value_enum)]
+        forge: ForgeArg,
+    },
+    /// 🪪 Show where the token comes from and whose it is
+    Status {
+        #[arg(value_enum)]
+        forge: ForgeArg,
+    },
+}
+
+#[derive(clap::ValueEnum, Clone, Copy)]
+enum ForgeArg {
+    Github,
+}
+
+impl From<ForgeArg> for Forge {
+    fn from(forge: ForgeArg) -> Self {
+        match forge {
+            ForgeArg::Github => Forge::GitHub,
+        }
+    }
+}
+
+#[derive(clap::ValueEnum, Clone, Copy)]
+enum BumpArg {
+    Major,
+    Minor,
+    Patch,
+}
+
+impl From<BumpArg> for Bump {
+    fn from(bump: BumpArg) -> Self {
+        match bump {
+            BumpArg::Major => Bump::Major,
+            BumpArg::Minor => Bump::Minor,
+            BumpArg::Patch => Bump::Patch,
+        }
+    }
+}
+
+#[derive(clap::ValueEnum, Clone, Copy)]
+enum VoiceMode {
+    /// Press Enter to start and stop each recording
+    PushToTalk,
+    /// Listen constantly and cut utterances on silence
+    Continuous,
+}
+
+#[derive(clap::ValueEnum, Clone)]
+enum WorktreeAction {
+    Create,
+    List,
+    Switch,
+    Prune,
+}
+
+fn main() -> ExitCode {
+    let cli = match Cli::try_parse() {
+        Ok(cli) => cli,
+        Err(err) if !err.use_stderr() => err.exit(),
+        Err(err) if requested_json_errors() => {
+            let rendered = err.to_string();
+            let summary = rendered.lines().next().unwrap_or_default();
+            CliError::usage(summary.trim_start_matches("error: ")).report(ErrorFormat::Json);
+            return ExitCode::from(exit::ErrorKind::Usage.code());
+        }
+        Err(err) => err.exit(),
+    };
+
+    match run(cli.command) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            err.report(cli.error_format);
+            ExitCode::from(err.kind.code())
+        }
+    }
+}
+
+fn parse_var(text: &str) -> Result<(String, String), String> {
+    match text.split_once('=') {
+        Some((name, value)) if !name.trim().is_empty() => {
+            Ok((name.trim().to_string(), value.to_string()))
+        }
+        _ => Err(format!("expected NAME=VALUE, got {text:?}")),
+    }
+}
+
+/// Parse failures happen before `--error-format` is known, so look for it
+/// in the raw arguments.
+fn requested_json_errors() -> bool {
+    let args: Vec<String> = std::env::args().collect();
+    args.iter().any(|a| a == "--error-format=json")
+        || args
+            .windows(2)
+            .any(|pair| pair[0] == "--error-format" && pair[1] == "json")
+}
+
+fn run(command: Option<Commands>) -> Result<(), CliError> {
+    match command {
+        Some(Commands::Ui) => {
+            println!("🚀 GitForge UI + MCP + Voice starting...");
+        }
+        Some(Commands::McpServe {
+            repo,
+            bind,
+            read_only,
+            token_env,
+        }) => mcp_serve(repo, bind, read_only, &token_env)?,
+        Some(Commands::Agent { repo, action }) => match action {
+            None => {
+                let backend = store::configured_backend(&repo).map_err(CliError::usage)?;
+                println!("🧠 BPGT Agent + {} starting for {}", backend.as_str(), repo)
+            }
+            Some(AgentAction::Voice { mode, stt_command }) => {
+                agent_voice(&repo, mode, stt_command)?
+            }
+        },
+        Some(Commands::Worktree {
+            action,
+            name,
+            repo,
+            dry_run,
+            merged_into,
+        }) => match action {
+            WorktreeAction::Create => {
+                println!(
+                    "🌳 Worktree '{}' created",
+                    name.unwrap_or_else(|| "new".to_string())
+                )
+            }
+            WorktreeAction::List => println!("📋 Worktree list"),
+            WorktreeAction::Switch => println!(
+                "🔀 Switched to worktree '{}'",
+                name.unwrap_or_else(|| "default".to_string())
+            ),
+            WorktreeAction::Prune => worktree_prune(&repo, merged_into.as_deref(), dry_run)?,
+        },
+        Some(Commands::Browser { url }) => {
+            println!("🌐 Opening {} in GitForge Browser", url);
+        }
+        Some(Commands::Export { output, repo }) => {
+            let conn = db::open(&repo).map_err(CliError::db)?;
+            let export = db::portable::export(&conn).map_err(CliError::db)?;
+            let json = serde_json::to_string_pretty(&export)?;
+            std::fs::write(&output, json)
+                .map_err(|e| CliError::io(format!("failed to write {output}: {e}")))?;
+            println!("📦 Exported {} tables to {}", export.tables.len(), output);
+        }
+        Some(Commands::Import { input, repo }) => {
+            let json = std::fs::read_to_string(&input)
+                .map_err(|e| CliError::io(format!("failed to read {input}: {e}")))?;
+            let export: db::portable::Export = serde_json::from_str(&json)
+                .map_err(|e| CliError::data(format!("invalid export file {input}: {e}")))?;
+            let mut conn = db::open(&repo).map_err(CliError::db)?;
+            let _lock = db::lock::acquire(&repo, "gitforge import", db::BUSY_TIMEOUT)
+                .map_err(CliError::db)?;
+            let summary = db::portable::import(&mut conn, &export).map_err(CliError::db)?;
+            println!(
+                "📥 Imported {} rows across {} tables into {}",
+                summary.rows,
+                summary.tables,
+                db::db_path(&repo).display()
+            );
+        }
+        Some(Commands::Db {
+            action:
+                DbAction::Maintain {
+                    repo,
+                    dry_run,
+                    event_days,
+                    max_events,
+                    json,
+                },
+        }) => db_maintain(&repo, dry_run, event_days, max_events, json)?,
+        Some(Commands::Auth { action }) => auth_command(action)?,
+        Some(Commands::Watch { repo, json }) => watch(&repo, json)?,
+        Some(Commands::Release {
+            repo,
+            bump,
+            prefix,
+            dry_run,
+            publish,
+            remote,
+        }) => release_command(
+            &repo,
+            bump.map(Bump::from),
+            &prefix,
+            dry_run,
+            publish,
+            &remote,
+        )?,
+        Some(Commands::ServeHttp {
+            repo,
+            bind,
+            allow_push,
+            require_auth,
+            token_env,
+        }) => serve_http(&repo, &bind, allow_push, require_auth, &token_env)?,
+        Some(Commands::Checks { action }) => checks_command(action)?,
+        Some(Commands::New {
+            template,
+            path,
+            vars,
+            branch,
+            message,
+        }) => {
+            let done = scaffold::scaffold(&scaffold::Request {
+                template: &template,
+                dest: std::path::Path::new(&path),
+                variables: vars.into_iter().collect(),
+                branch: Some(&branch),
+                message: message.as_deref(),
+            })
+            .map_err(|e| CliError::new(ErrorKind::General, e))?;
+            println!(
+                "🧱 Created {} from {template}: {} files, initial commit {} on {}",
+                done.path,
+                done.files,
+                &done.commit[..7],
+                done.branch
+            );
+            for skipped in &done.skipped {
+                println!("⚠️  Skipped symlink {skipped}");
+            }
+        }
+        None => {
+            println!("🔨 GitForge v2.0 — Forge your Git workflow");
+            println!(
+                "Usage: gitforge ui | mcp-serve | agent | worktree | export | import | db | auth | watch | release | serve-http | checks | new"
+            );
+        }
+    }
+
+    Ok(())
+}
+
+fn agent_voice(repo: &str, mode: VoiceMode, stt_command: Option<String>) -> Result<(), CliError> {
+    let command = stt_command
+        .or_else(|| std::env::var("GITFORGE_STT_COMMAND").ok())
+        .ok_or_else(|| {
+            CliError::usage(
+                "no STT command configured (pass --stt-command or set GITFORGE_STT_COMMAND)",
+            )
+        })?;
+    let stt = CommandStt::parse(&command).map_err(CliError::usage)?;
+    let agent = BpgtAgent::new(&db::db_path(repo).to_string_lossy());
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .map_err(CliError::io)?;
+
+    let mut handle = |clip: AudioClip| -> Result<bool, CliError> {
+        let transcript = stt.transcribe(&clip).map_err(CliError::io)?;
+        if transcript.is_empty() {
+            return Ok(true);
+        }
+        println!("🗣️  {transcript}");
+        if matches!(
+            transcript.to_lowercase().trim_end_matches('.'),
+            "stop" | "exit" | "quit"
+        ) {
+            return Ok(false);
+        }
+        let reply = runtime
+            .block_on(agent.process_voice(&transcript))
+            .map_err(|e| CliError::new(ErrorKind::General, e))?;
+        println!("🧠 {reply}");
+        Ok(true)
+    };
+
+    voice_loop(mode, &mut handle)
+}
+
+#[cfg(feature = "voice")]
+fn voice_loop(
+    mode: VoiceMode,
+    handle: &mut dyn FnMut(AudioClip) -> Result<bool, CliError>,
+) -> Result<(), CliError> {
+    use gitforge::agent::mic::Microphone;
+    use gitforge::agent::voice::Segmenter;
+
+    let mic = Microphone::open_default().map_err(CliError::io)?;
+    match mode {
+        VoiceMode::PushToTalk => {
+            let stdin = std::io::stdin();
+            loop {
+                println!("🎙️  Press Enter to talk (q + Enter to quit)");
+                let mut line = String::new();
+                if stdin.read_line(&mut line).map_err(CliError::io)? == 0 || line.trim() == "q" {
+                    return Ok(());
+                }
+                mic.start().map_err(CliError::io)?;
+                println!("🔴 Recording... press Enter to stop");
+                stdin.read_line(&mut line).map_err(CliError::io)?;
+                let clip = mic.stop().map_err(CliError::io)?;
+                if !handle(clip)? {
+                    return Ok(());
+                }
+            }
+        }
+        VoiceMode::Continuous => {
+            let mut segmenter = Segmenter::new(mic.sample_rate());
+            mic.start().map_err(CliError::io)?;
+            println!("🎧 Listening (say \"stop\" or Ctrl-C to quit)");
+            loop {
+                let Some(chunk) = mic.next_chunk(Duration::from_millis(100)) else {
+                    continue;
+                };
+                for clip in segmenter.push(&chunk) {
+                    if !handle(clip)? {
+                        return Ok(());
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(not(feature = "voice"))]
+fn voice_loop(
+    _mode: VoiceMode,
+    _handle: &mut dyn FnMut(AudioClip) -> Result<bool, CliError>,
+) -> Result<(), CliError> {
+    Err(CliError::new(
+        ErrorKind::General,
+        "gitforge was built without microphone support; rebuild with `--features voice`",
+    ))
+}
+
+fn worktree_prune(repo: &str, merged_into: Option<&str>, dry_run: bool) -> Result<(), CliError> {
+    let report = worktree::prune(repo, merged_into, dry_run).map_err(CliError::git)?;
+    let verb = if dry_run { "would prune" } else { "pruned" };
+
+    for entry in &report.pruned {
+        let why = match &entry.reason {
+            PruneReason::Missing => "directory missing".to_string(),
+            PruneReason::Merged { branch, into } => format!("{branch} merged into {into}"),
+        };
+        println!("🧹 {verb} '{}' at {} ({why})", entry.name, entry.path);
+    }
+    for skipped in &report.skipped {
+        println!("⏭️  kept '{}': {}", skipped.name, skipped.why);
+    }
+    for name in &report.registry_rows {
+        let verb = if dry_run { "would drop" } else { "dropped" };
+        println!("🗃️  {verb} registry entry '{name}'");
+    }
+    if report.pruned.is_empty() && report.registry_rows.is_empty() {
+        println!("✨ Nothing to prune");
+    }
+
+    Ok(())
+}
+
+fn db_maintain(
+    repo: &str,
+    dry_run: bool,
+    event_days: Option<u32>,
+    max_events: Option<usize>,
+    json: bool,
+) -> Result<(), CliError> {
+    let mut policy = store::RetentionPolicy::from_config(repo).map_err(CliError::usage)?;
+    if let Some(days) = event_days {
+        policy.event_days = (days > 0).then_some(days);
+    }
+    if let Some(max) = max_events {
+        policy.max_events = (max > 0).then_some(max);
+    }
+
+    let store = store::open(repo).map_err(CliError::db)?;
+    let _lock = (!dry_run)
+        .then(|| db::lock::acquire(repo, "gitforge db maintain", db::BUSY_TIMEOUT))
+        .transpose()
+        .map_err(CliError::db)?;
+    let report =
+        store::maintenance::maintain(store.as_ref(), &policy, dry_run).map_err(CliError::db)?;
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        return Ok(());
+    }
+    let verb = if dry_run { "would prune" } else { "pruned" };
+    println!("🧽 {verb} {} events", report.events_pruned);
+    if !dry_run {
+        println!(
+            "💾 {} → {} bytes ({} reclaimed)",
+            report.bytes_before, report.bytes_after, report.reclaimed_bytes
+        );
+    }
+    Ok(())
+}
+
+fn checks_command(action: ChecksAction) -> Result<(), CliError> {
+    match action {
+        ChecksAction::Run { repo, json } => {
+            let repo = git2::Repository::discover(&repo).map_err(CliError::git)?;
+            let workdir = repo
+                .workdir()
+                .ok_or_else(|| CliError::git("checks need a working tree"))?;
+            let checks = precommit::load(workdir).map_err(CliError::data)?;
+            let staged = precommit::staged_files(&repo).map_err(CliError::git)?;
+            let report = precommit::run(workdir, &checks, &staged);
+            if json {
+                println!("{}", serde_json::to_string_pretty(&report)?);
+            } else {
+                for result in &report.results {
+                    let mark = if result.passed { "✅" } else { "❌" };
+                    println!("{mark} {} ({} ms)", result.name, result.duration_ms);
+                    if !result.passed && !result.output.is_empty() {
+                        println!("{}", result.output.trim_end());
+                    }
+                }
+                if checks.is_empty() {
+                    println!("✨ No checks in {}", precommit::CONFIG_PATH);
+                }
+            }
+            match report.failure_summary() {
+                Some(summary) => Err(CliError::new(ErrorKind::General, summary)),
+                None => Ok(()),
+            }
+        }
+        ChecksAction::LintMsg { file, repo } => {
+            let repo = git2::Repository::discover(&repo).map_err(CliError::git)?;
+            let workdir = repo
+                .workdir()
+                .ok_or_else(|| CliError::git("commit lint needs a working tree"))?;
+            let rules = commit_lint::Rules::load(workdir).map_err(CliError::data)?;
+            let text = std::fs::read_to_string(&file)
+                .map_err(|e| CliError::io(format!("failed to read {file}: {e}")))?;
+            let message = git2::message_prettify(&text, Some(b'#')).map_err(CliError::git)?;
+            let problems = rules.lint(&message);
+            for problem in &problems {
+                let mark = match problem.level {
+                    commit_lint::Level::Error => "❌",
+                    _ => "⚠️ ",
+                };
+                println!("{mark} {}: {}", problem.rule, problem.message);
+            }
+            match commit_lint::refusal(&problems) {
+                Some(refusal) => Err(CliError::new(ErrorKind::General, refusal)),
+                None => Ok(()),
+            }
+        }
+        ChecksAction::InstallHooks { repo, force } => {
+            let repo = git2::Repository::discover(&repo).map_err(CliError::git)?;
+            for path in precommit::install_hooks(&repo, force).map_err(CliError::io)? {
+                println!("🪝 Installed {}", path.display());
+            }
+            Ok(())
+        }
+    }
+}
+
+fn auth_command(action: AuthAction) -> Result<(), CliError> {
+    match action {
+        AuthAction::Login {
+            forge,
+            client_id,
+            scope,
+            repo,
+        } => auth_login(forge.into(), client_id.as_deref(), &scope, &repo),
+        AuthAction::Logout { forge } => {
+            let forge = Forge::from(forge);
+            if auth::delete_token(forge).map_err(CliError::io)? {
+                println!("🚪 Removed the {} token from the keyring", forge.as_str());
+            } else {
+                println!("No {} token was stored", forge.as_str());
+            }
+            Ok(())
+        }
+        AuthAction::Status { forge } => {
+            let forge = Forge::from(forge);
+            let Some(token) = auth::token(forge).map_err(CliError::io)? else {
+                return Err(CliError::usage(format!(
+                    "not signed in to {}; run `gitforge auth login {}`",
+                    forge.as_str(),
+                    forge.as_str()
+                )));
+            };
+            let login =
+                github::user_login(&github::Http, &token.secret).map_err(CliError::network)?;
+            match token.var {
+                Some(var) => println!("🪪 {login} (token from ${var})"),
+                None => println!("🪪 {login} (token from the OS keyring)"),
+            }
+            Ok(())
+        }
+    }
+}
+
+fn auth_login(
+    forge: Forge,
+    client_id: Option<&str>,
+    scope: &str,
+    repo: &str,
+) -> Result<(), CliError> {
+    let client_id = github::client_id(client_id, repo).ok_or_else(|| {
+        CliError::usage(format!(
+            "no OAuth client id: pass --client-id, set ${}, or git config {} (the app needs device flow enabled)",
+            github::CLIENT_ID_ENV,
+            github::CLIENT_ID_KEY
+        ))
+    })?;
+
+    let http = github::Http;
+    let code = github::request_code(&http, &client_id, scope).map_err(CliError::network)?;
+    println!(
+        "🔑 Open {} and enter the code {}",
+        code.verification_uri, code.user_code
+    );
+    println!("⏳ Waiting for authorization...");
+    let token = github::poll_token(&http, &client_id, &code, &mut std::thread::sleep)
+        .map_err(CliError::network)?;
+    let login = github::user_login(&http, &token).map_err(CliError::network)?;
+    auth::store_token(forge, &token).map_err(CliError::io)?;
+
+    println!("✅ Signed in to GitHub as {login}; the token is in the OS keyring");
+    if let Ok(Some(active)) = auth::token(forge) {
+        if active.source == TokenSource::Env {
+            println!(
+                "⚠️  ${} is set and takes precedence over the keyring",
+                active.var.unwrap_or_default()
+            );
+        }
+    }
+    Ok(())
+}
+
+fn release_command(
+    repo_path: &str,
+    bump: Option<Bump>,
+    prefix: &str,
+    dry_run: bool,
+    publish: bool,
+    remote: &str,
+) -> Result<(), CliError> {
+    let repo = git2::Repository::open(repo_path)
+        .map_err(|e| CliError::git(format!("failed to open repository: {e}")))?;
+    let plan = release::plan(&repo, prefix, bump).map_err(CliError::git)?;
+    let from = plan.previous.as_deref().unwrap_or("the first commit");
+    println!(
+        "🏷️  {} ({:?} bump, {} changes since {from})\n",
+        plan.tag,
+        plan.bump,
+        plan.changes.len()
+    );
+    println!("{}", plan.notes);
+    if dry_run {
+        return Ok(());
+    }
+    release::create_tag(&repo, &plan).map_err(CliError::git)?;
+    println!("✅ Tagged {} at {}", plan.tag, &plan.target[..7]);
+    if publish {
+        let page = release::publish(&repo, remote, &plan, &github::Http).map_err(|e| {
+            CliError::network(format!("{} was tagged but not published: {e}", plan.tag))
+        })?;
+        println!("🚀 Published {page}");
+    }
+    Ok(())
+}
+
+fn serve_http(
+    repo: &str,
+    bind: &str,
+    allow_push: bool,
+    require_auth: bool,
+    token_env: &str,
+) -> Result<(), CliError> {
+    let token = std::env::var(token_env)
+        .ok()
+        .map(|token| token.trim().to_string())
+        .filter(|token| !token.is_empty());
+    if (allow_push || require_auth) && token.is_none() {
+        return Err(CliError::usage(format!(
+            "--allow-push and --require-auth need a token in {token_env}"
+        )));
+    }
+    let options = smart_http::Options {
+        allow_push,
+        require_auth,
+        token,
+    };
+    let server = SmartHttp::new(repo, options).map_err(CliError::git)?;
+    let listener = std::net::TcpListener::bind(bind)
+        .map_err(|e| CliError::network(format!("failed to listen on {bind}: {e}")))?;
+    let access = match (allow_push, require_auth) {
+        (true, true) => "clone and push with the token",
+        (true, false) => "anyone can clone; push with the token",
+        (false, true) => "read-only; clone with the token",
+        (false, false) => "read-only",
+    };
+    println!("🌐 Serving {repo} at http://{bind}/ ({access})");
+    server.serve(listener).map_err(CliError::network)
+}
+
+fn mcp_serve(repo: String, bind: String, read_only: bool, token_env: &str) -> Result<(), CliError> {
+    let tokens = match std::env::var(token_env) {
+        Ok(text) => Tokens::parse(&text)
+            .map_err(|e| CliError::usage(format!("invalid {token_env}: {e}")))?,
+        Err(_) => Tokens::default(),
+    };
+    let access = match (read_only, tokens.is_empty()) {
+        (true, true) => "read-only",
+        (true, false) => "read-only; connect with a token",
+        (false, true) => "read-write",
+        (false, false) => "connect with a token; read-only tokens can't write",
+    };
+    let options = Options { read_only, tokens };
+    let server = GitForgeMcp::with_options(repo.clone(), options).map_err(CliError::git)?;
+    let runtime = tokio::runtime::Runtime::new().map_err(CliError::io)?;
+    println!("🤖 MCP Server for {repo} ({access})");
+    runtime
+        .block_on(std::sync::Arc::new(server).serve(bind))
+        .map(|_| ())
+        .map_err(CliError::network)
+}
+
+fn watch(repo: &str, json: bool) -> Result<(), CliError> {
+    let watcher = RepoWatcher::new(repo).map_err(CliError::git)?;
+    let mut tracker = StatusTracker::default();
+    tracker.refresh(watcher.repo()).map_err(CliError::git)?;
+
+    if !json {
+        println!("👀 Watching {repo} (Ctrl-C to stop)");
+    }
+
+    loop {
+        let mut events = watcher
+            .next_batch(Duration::from_millis(200))
+            .map_err(CliError::io)?;
+
+        if events.iter().any(RepoEvent::touches_status) {
+            events.extend(tracker.refresh(watcher.repo()).map_err(CliError::git)?);
+        }
+
+        for event in events {
+            if json {
+                println!("{}", serde_json::to_string(&event)?);
+                continue;
+            }
+            match event {
+                RepoEvent::WorktreeChanged { paths } => println!("✏️  {}", paths.join(", ")),
+                RepoEvent::IndexChanged => println!("📇 index updated"),
+                RepoEvent::RefUpdated { name } => println!("🔖 {name} moved"),
+                RepoEvent::StatusChanged { path, status } => println!("   {path}: {status}"),
+            }
+        }
+    }
+}